@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AgentError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("System call failed: {0}")]
+    Sys(#[from] nix::Error),
+
+    #[error("No virtio-serial probe port found")]
+    NoProbePort,
+}
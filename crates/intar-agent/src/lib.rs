@@ -0,0 +1,40 @@
+//! The intar guest agent as a library, for custom images and supervisors that embed it
+//! instead of running the `intar-agent` binary.
+//!
+//! [`run_daemon`] is what the binary runs at boot. [`serve_on_port`] answers probe requests on
+//! one port, [`serve_on_socket`] on a unix socket for guests reached over an ssh tunnel, and
+//! [`handle_request`] answers a single one, for agents on other transports.
+//! [`record`] runs a shell session in one of the [`RecordMode`]s and streams it to the actions
+//! sink at [`ACTIONS_SOCK_PATH`].
+
+#[cfg(unix)]
+mod error;
+#[cfg(unix)]
+mod record;
+#[cfg(unix)]
+mod serve;
+
+#[cfg(unix)]
+pub use error::*;
+#[cfg(unix)]
+pub use record::*;
+#[cfg(unix)]
+pub use serve::*;
+
+/// Socket recorded sessions send their events to; [`run_daemon`] forwards them to the host.
+pub const ACTIONS_SOCK_PATH: &str = "/run/intar/actions.sock";
+
+/// Group that alone may write to [`ACTIONS_SOCK_PATH`] when the guest has it, as
+/// `intar start --paranoid` sets up; every user may otherwise.
+pub const ACTIONS_GROUP: &str = "intar-actions";
+
+#[cfg(unix)]
+fn unix_ms() -> u64 {
+    u64::try_from(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis(),
+    )
+    .unwrap_or(u64::MAX)
+}
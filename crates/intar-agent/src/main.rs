@@ -1,9 +1,37 @@
-#[cfg(unix)]
-mod unix;
-
 #[cfg(unix)]
 fn main() {
-    unix::main();
+    use intar_agent::{RecordMode, answer_encoded, record, run_daemon};
+
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("record-ssh") => {
+            let real_shell = args.next().unwrap_or_else(|| "/bin/bash".into());
+            let exit_code = record(&real_shell, &RecordMode::Interactive).unwrap_or(1);
+            std::process::exit(exit_code);
+        }
+        Some("record-command") => {
+            let real_shell = args.next().unwrap_or_else(|| "/bin/bash".into());
+            let command = args.next().unwrap_or_default();
+            let exit_code = record(&real_shell, &RecordMode::Command(command)).unwrap_or(1);
+            std::process::exit(exit_code);
+        }
+        Some("request") => {
+            let response = answer_encoded(&args.next().unwrap_or_default());
+            match serde_json::to_string(&response) {
+                Ok(json) => println!("{json}"),
+                Err(e) => {
+                    eprintln!("Failed to encode response: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            if let Err(e) = run_daemon() {
+                eprintln!("{e}, exiting");
+                std::process::exit(1);
+            }
+        }
+    }
 }
 
 #[cfg(not(unix))]
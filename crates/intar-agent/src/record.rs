@@ -1,212 +1,48 @@
+use crate::{ACTIONS_SOCK_PATH, AgentError, unix_ms};
 use base64::Engine as _;
-use intar_probes::{ActionEvent, ProbeResult, Request, Response, SshSessionKind, evaluate_probe};
+use intar_probes::{
+    ActionEvent, OUTPUT_BYTES_PER_SEC, OutputLimiter, ShellContext, SshSessionKind,
+};
 use nix::errno::Errno;
 use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
 use nix::pty::openpty;
 use nix::sys::termios::{SetArg, Termios, cfmakeraw, tcgetattr, tcsetattr};
-use nix::unistd::{close, dup2_stderr, dup2_stdin, dup2_stdout, read, setsid, write};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::os::unix::fs::PermissionsExt;
+use nix::unistd::{close, dup2_stderr, dup2_stdin, dup2_stdout, read, setsid, tcgetpgrp, write};
+use std::io::Write;
 use std::os::unix::io::{AsRawFd, BorrowedFd, IntoRawFd, RawFd};
-use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::net::UnixStream;
 use std::os::unix::process::CommandExt;
 use std::process::Command;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-
-const VIRTIO_AGENT_PORT: &str = "/dev/virtio-ports/intar.agent";
-const FALLBACK_AGENT_PORT: &str = "/dev/vport0p1";
-const VIRTIO_ACTIONS_PORT: &str = "/dev/virtio-ports/intar.actions";
-const ACTIONS_SOCK_PATH: &str = "/run/intar/actions.sock";
-
-pub fn main() {
-    let mut args = std::env::args().skip(1);
-    match args.next().as_deref() {
-        Some("record-ssh") => {
-            let real_shell = args.next().unwrap_or_else(|| "/bin/bash".into());
-            let exit_code = record_ssh(&real_shell).unwrap_or(1);
-            std::process::exit(exit_code);
-        }
-        Some("record-command") => {
-            let real_shell = args.next().unwrap_or_else(|| "/bin/bash".into());
-            let command = args.next().unwrap_or_default();
-            let exit_code = record_command(&real_shell, &command).unwrap_or(1);
-            std::process::exit(exit_code);
-        }
-        _ => daemon(),
-    }
-}
-
-fn daemon() {
-    let start_time = Instant::now();
-
-    eprintln!("intar-agent starting...");
-
-    std::thread::spawn(|| {
-        loop {
-            if let Err(e) = run_actions_sink() {
-                eprintln!("actions sink error: {e}; retrying in 1s...");
-                std::thread::sleep(Duration::from_secs(1));
-            }
-        }
-    });
-
-    let port_path = if std::path::Path::new(VIRTIO_AGENT_PORT).exists() {
-        VIRTIO_AGENT_PORT
-    } else if std::path::Path::new(FALLBACK_AGENT_PORT).exists() {
-        FALLBACK_AGENT_PORT
-    } else {
-        eprintln!("No virtio-serial probe port found, exiting");
-        std::process::exit(1);
-    };
-
-    eprintln!("Using virtio-serial probe port: {port_path}");
-
-    loop {
-        match run_probe_agent(port_path, &start_time) {
-            Ok(()) => {
-                eprintln!("Probe agent loop ended, restarting...");
-            }
-            Err(e) => {
-                eprintln!("Probe agent error: {e}, retrying in 1s...");
-                std::thread::sleep(Duration::from_secs(1));
-            }
-        }
-    }
-}
-
-fn run_actions_sink() -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(dir) = std::path::Path::new(ACTIONS_SOCK_PATH).parent() {
-        std::fs::create_dir_all(dir)?;
-    }
-    if std::path::Path::new(ACTIONS_SOCK_PATH).exists() {
-        let _ = std::fs::remove_file(ACTIONS_SOCK_PATH);
-    }
-
-    let listener = UnixListener::bind(ACTIONS_SOCK_PATH)?;
-    std::fs::set_permissions(ACTIONS_SOCK_PATH, std::fs::Permissions::from_mode(0o666))?;
-
-    let (tx, rx) = std::sync::mpsc::channel::<String>();
-    std::thread::spawn(move || actions_writer_loop(rx));
-
-    for conn in listener.incoming() {
-        match conn {
-            Ok(stream) => {
-                let tx = tx.clone();
-                std::thread::spawn(move || {
-                    let mut reader = BufReader::new(stream);
-                    loop {
-                        let mut line = String::new();
-                        match reader.read_line(&mut line) {
-                            Ok(0) | Err(_) => break,
-                            Ok(_) => {
-                                let trimmed = line.trim_end();
-                                if trimmed.is_empty() {
-                                    continue;
-                                }
-                                let _ = tx.send(trimmed.to_string());
-                            }
-                        }
-                    }
-                });
-            }
-            Err(e) => return Err(e.into()),
-        }
-    }
-
-    Ok(())
+use std::time::{Duration, Instant};
+
+/// Largest raw output event the writer builds by joining queued chunks.
+const MAX_COALESCED_OUTPUT: usize = 16 * 1024;
+
+/// How [`record`] runs and records a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordMode {
+    /// A login shell on a pty, recorded keystroke by keystroke, as for `ssh host`. Needs a
+    /// terminal on stdin and stdout.
+    Interactive,
+    /// One command run with `-c`, recorded with its output once it exits, as for
+    /// `ssh host <command>`.
+    Command(String),
 }
 
-fn actions_writer_loop(rx: std::sync::mpsc::Receiver<String>) {
-    let mut port: Option<File> = None;
-
-    for line in rx {
-        loop {
-            if port.is_none() {
-                if let Ok(f) = File::options().write(true).open(VIRTIO_ACTIONS_PORT) {
-                    port = Some(f);
-                } else {
-                    std::thread::sleep(Duration::from_millis(200));
-                    continue;
-                }
-            }
-
-            let Some(f) = port.as_mut() else {
-                continue;
-            };
-
-            if writeln!(f, "{line}").is_ok() && f.flush().is_ok() {
-                break;
-            }
-
-            port = None;
-        }
+/// Run `real_shell` in `mode` on this process's stdio and stream the session to
+/// [`ACTIONS_SOCK_PATH`], returning the shell's exit code. Sessions are still run when the
+/// sink is not listening, just not recorded.
+///
+/// # Errors
+/// Returns an error if the shell or its pty cannot be set up, or the session breaks off.
+pub fn record(real_shell: &str, mode: &RecordMode) -> Result<i32, AgentError> {
+    match mode {
+        RecordMode::Interactive => record_ssh(real_shell),
+        RecordMode::Command(command) => record_command(real_shell, command),
     }
 }
 
-fn run_probe_agent(
-    port_path: &str,
-    start_time: &Instant,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let port = File::options().read(true).write(true).open(port_path)?;
-
-    let mut writer = port.try_clone()?;
-    let mut reader = BufReader::new(port);
-
-    eprintln!("Connected to virtio-serial probe port");
-
-    loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
-            Ok(0) => {
-                std::thread::sleep(Duration::from_millis(100));
-            }
-            Ok(_) => {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
-
-                let response = match serde_json::from_str::<Request>(line) {
-                    Ok(request) => handle_probe_request(request, start_time),
-                    Err(e) => Response::Error {
-                        message: format!("Failed to parse request: {e}"),
-                    },
-                };
-
-                let response_json = serde_json::to_string(&response)?;
-                writeln!(writer, "{response_json}")?;
-                writer.flush()?;
-            }
-            Err(e) => return Err(e.into()),
-        }
-    }
-}
-
-fn handle_probe_request(request: Request, start_time: &Instant) -> Response {
-    match request {
-        Request::Ping => Response::Pong {
-            uptime_secs: start_time.elapsed().as_secs(),
-        },
-        Request::CheckProbe { id, spec } => {
-            let result = evaluate_probe(&id, &spec);
-            Response::ProbeResult {
-                id: result.id,
-                passed: result.passed,
-                message: result.message,
-            }
-        }
-        Request::CheckAll { probes } => {
-            let results: Vec<ProbeResult> = probes
-                .into_iter()
-                .map(|(id, spec)| evaluate_probe(&id, &spec))
-                .collect();
-            Response::AllResults { results }
-        }
-    }
-}
-
-fn record_command(real_shell: &str, command: &str) -> Result<i32, Box<dyn std::error::Error>> {
+fn record_command(real_shell: &str, command: &str) -> Result<i32, AgentError> {
     let user = std::env::var("USER").unwrap_or_else(|_| "user".into());
     let mut sink = connect_actions_sink();
     if let Some(s) = sink.as_mut() {
@@ -237,8 +73,13 @@ fn record_command(real_shell: &str, command: &str) -> Result<i32, Box<dyn std::e
     let code = output.status.code().unwrap_or(1);
 
     if let Some(s) = sink.as_mut() {
-        if !output.stdout.is_empty() {
-            let b64 = base64::engine::general_purpose::STANDARD.encode(&output.stdout);
+        let mut limiter = OutputLimiter::new(OUTPUT_BYTES_PER_SEC);
+        let now = Instant::now();
+        for stream in [&output.stdout, &output.stderr] {
+            if stream.is_empty() || !limiter.admit(stream, now) {
+                continue;
+            }
+            let b64 = base64::engine::general_purpose::STANDARD.encode(stream);
             send_event(
                 s,
                 &ActionEvent::SshRawOutput {
@@ -247,15 +88,8 @@ fn record_command(real_shell: &str, command: &str) -> Result<i32, Box<dyn std::e
                 },
             );
         }
-        if !output.stderr.is_empty() {
-            let b64 = base64::engine::general_purpose::STANDARD.encode(&output.stderr);
-            send_event(
-                s,
-                &ActionEvent::SshRawOutput {
-                    ts_unix_ms: unix_ms(),
-                    data_b64: b64,
-                },
-            );
+        if let Some(marker) = limiter.take_suppressed(unix_ms()) {
+            send_event(s, &marker);
         }
         send_event(
             s,
@@ -269,7 +103,7 @@ fn record_command(real_shell: &str, command: &str) -> Result<i32, Box<dyn std::e
     Ok(code)
 }
 
-fn record_ssh(real_shell: &str) -> Result<i32, Box<dyn std::error::Error>> {
+fn record_ssh(real_shell: &str) -> Result<i32, AgentError> {
     let stdin = std::io::stdin();
     let stdout = std::io::stdout();
     if !is_tty(stdin.as_raw_fd()) || !is_tty(stdout.as_raw_fd()) {
@@ -328,7 +162,7 @@ fn enable_raw_mode(stdin: &std::io::Stdin) -> Option<RawModeGuard<'_>> {
     Some(RawModeGuard { stdin, orig })
 }
 
-fn open_pty() -> Result<(RawFd, RawFd), Box<dyn std::error::Error>> {
+fn open_pty() -> Result<(RawFd, RawFd), AgentError> {
     let pty = openpty(None, None)?;
     Ok((pty.master.into_raw_fd(), pty.slave.into_raw_fd()))
 }
@@ -337,7 +171,7 @@ fn spawn_shell_in_pty(
     real_shell: &str,
     slave_fd: RawFd,
     master_fd: RawFd,
-) -> Result<std::process::Child, Box<dyn std::error::Error>> {
+) -> Result<std::process::Child, AgentError> {
     let mut cmd = Command::new(real_shell);
     cmd.arg("-l");
     unsafe {
@@ -366,13 +200,15 @@ fn spawn_shell_in_pty(
 fn proxy_pty_session(
     master_fd: RawFd,
     tx: &std::sync::mpsc::Sender<ActionEvent>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), AgentError> {
     let mut stdout = std::io::stdout();
     let stdin_fd = std::io::stdin().as_raw_fd();
     let stdin_borrowed = unsafe { BorrowedFd::borrow_raw(stdin_fd) };
     let master_borrowed = unsafe { BorrowedFd::borrow_raw(master_fd) };
 
     let mut buf = [0u8; 4096];
+    let mut typed = TypedLine::default();
+    let mut limiter = OutputLimiter::new(OUTPUT_BYTES_PER_SEC);
     loop {
         let mut fds = [
             PollFd::new(stdin_borrowed, PollFlags::POLLIN),
@@ -391,11 +227,17 @@ fn proxy_pty_session(
                 Ok(n) => {
                     stdout.write_all(&buf[..n])?;
                     stdout.flush()?;
-                    let b64 = base64::engine::general_purpose::STANDARD.encode(&buf[..n]);
-                    let _ = tx.send(ActionEvent::SshRawOutput {
-                        ts_unix_ms: unix_ms(),
-                        data_b64: b64,
-                    });
+                    // The learner sees everything; the host only what fits the budget.
+                    if limiter.admit(&buf[..n], Instant::now()) {
+                        if let Some(marker) = limiter.take_suppressed(unix_ms()) {
+                            let _ = tx.send(marker);
+                        }
+                        let b64 = base64::engine::general_purpose::STANDARD.encode(&buf[..n]);
+                        let _ = tx.send(ActionEvent::SshRawOutput {
+                            ts_unix_ms: unix_ms(),
+                            data_b64: b64,
+                        });
+                    }
                 }
                 Err(Errno::EINTR) => {}
                 Err(e) => return Err(e.into()),
@@ -407,6 +249,14 @@ fn proxy_pty_session(
                 Ok(0) => break,
                 Ok(n) => {
                     let chunk = &buf[..n];
+                    // Before the shell sees Enter and starts the command.
+                    for line in typed.feed(chunk) {
+                        let _ = tx.send(ActionEvent::SshLine {
+                            ts_unix_ms: unix_ms(),
+                            line,
+                            context: shell_context(master_borrowed),
+                        });
+                    }
                     write_all_fd(master_borrowed, chunk)?;
 
                     let b64 = base64::engine::general_purpose::STANDARD.encode(chunk);
@@ -421,9 +271,117 @@ fn proxy_pty_session(
         }
     }
 
+    if let Some(marker) = limiter.take_suppressed(unix_ms()) {
+        let _ = tx.send(marker);
+    }
     Ok(())
 }
 
+/// The line being typed into a session, rebuilt from keystrokes. Cursor movement and other
+/// escape sequences are skipped, so edited lines can differ from what the shell runs; the host
+/// derives the exact line from the session output.
+#[derive(Default)]
+struct TypedLine {
+    line: Vec<u8>,
+    /// How far into a keystroke escape sequence the input is; sequences may span reads.
+    escape: Escape,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    #[default]
+    None,
+    /// After ESC: `[` starts a CSI sequence, `O` an SS3 one, and anything else is an Alt+key.
+    Start,
+    /// Inside `ESC [`, up to the final byte in `0x40..=0x7e` (arrow and function keys).
+    Csi,
+    /// After `ESC O`, whose next byte ends it (arrow and function keys in application mode).
+    Ss3,
+}
+
+impl TypedLine {
+    /// Feed keystrokes, returning the lines ended by Enter.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &b in chunk {
+            match self.escape {
+                Escape::None => {}
+                Escape::Start => {
+                    self.escape = match b {
+                        b'[' => Escape::Csi,
+                        b'O' => Escape::Ss3,
+                        _ => Escape::None,
+                    };
+                    continue;
+                }
+                Escape::Csi => {
+                    if (0x40..=0x7e).contains(&b) {
+                        self.escape = Escape::None;
+                    }
+                    continue;
+                }
+                Escape::Ss3 => {
+                    self.escape = Escape::None;
+                    continue;
+                }
+            }
+            match b {
+                b'\r' | b'\n' => {
+                    let line = String::from_utf8_lossy(&self.line).trim().to_string();
+                    self.line.clear();
+                    if !line.is_empty() {
+                        lines.push(line);
+                    }
+                }
+                0x7f | 0x08 => {
+                    // Drop a whole UTF-8 character.
+                    while let Some(last) = self.line.pop() {
+                        if last & 0xc0 != 0x80 {
+                            break;
+                        }
+                    }
+                }
+                // Ctrl-C and Ctrl-U discard the line.
+                0x03 | 0x15 => self.line.clear(),
+                // Arrow keys, function keys, and Alt+key are skipped.
+                0x1b => self.escape = Escape::Start,
+                b'\t' => self.line.push(b' '),
+                b if b >= 0x20 => self.line.push(b),
+                _ => {}
+            }
+        }
+        lines
+    }
+}
+
+/// Working directory and effective user of the process in the session's foreground: the
+/// shell while it reads a command, or a root shell after `sudo -i`.
+fn shell_context(master: BorrowedFd<'_>) -> Option<ShellContext> {
+    let pgid = tcgetpgrp(master).ok()?;
+    let proc_dir = std::path::PathBuf::from(format!("/proc/{pgid}"));
+    let cwd = std::fs::read_link(proc_dir.join("cwd")).ok()?;
+    let status = std::fs::read_to_string(proc_dir.join("status")).ok()?;
+    let euid = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .nth(1)?;
+    Some(ShellContext {
+        cwd: cwd.display().to_string(),
+        user: user_name(euid).unwrap_or_else(|| euid.to_string()),
+    })
+}
+
+/// Name of the user with numeric id `uid`, from /etc/passwd.
+fn user_name(uid: &str) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|entry| {
+        let mut fields = entry.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)? == uid).then(|| name.to_string())
+    })
+}
+
 fn is_fd_readable(fd: &PollFd<'_>) -> bool {
     let revents = fd.revents().unwrap_or(PollFlags::empty());
     revents.contains(PollFlags::POLLIN)
@@ -512,9 +470,46 @@ fn actions_event_writer(rx: std::sync::mpsc::Receiver<ActionEvent>) {
         return;
     };
 
-    for ev in rx {
-        send_event(s, &ev);
+    while let Ok(first) = rx.recv() {
+        let mut batch = vec![first];
+        batch.extend(rx.try_iter());
+        for ev in coalesce_output(batch) {
+            send_event(s, &ev);
+        }
+    }
+}
+
+/// Join runs of raw output chunks that queued up while the port was busy into fewer, larger
+/// events, each stamped with the time of its first chunk.
+fn coalesce_output(batch: Vec<ActionEvent>) -> Vec<ActionEvent> {
+    let engine = &base64::engine::general_purpose::STANDARD;
+    let raw_output = |(ts_unix_ms, data): (u64, Vec<u8>)| ActionEvent::SshRawOutput {
+        ts_unix_ms,
+        data_b64: engine.encode(data),
+    };
+    let mut events = Vec::with_capacity(batch.len());
+    let mut pending: Option<(u64, Vec<u8>)> = None;
+    for ev in batch {
+        if let ActionEvent::SshRawOutput {
+            ts_unix_ms,
+            data_b64,
+        } = &ev
+            && let Ok(bytes) = engine.decode(data_b64)
+        {
+            if let Some((_, data)) = pending.as_mut()
+                && data.len() + bytes.len() <= MAX_COALESCED_OUTPUT
+            {
+                data.extend_from_slice(&bytes);
+            } else if let Some(done) = pending.replace((*ts_unix_ms, bytes)) {
+                events.push(raw_output(done));
+            }
+            continue;
+        }
+        events.extend(pending.take().map(raw_output));
+        events.push(ev);
     }
+    events.extend(pending.map(raw_output));
+    events
 }
 
 fn connect_actions_sink() -> Option<UnixStream> {
@@ -534,16 +529,6 @@ fn send_event(stream: &mut UnixStream, ev: &ActionEvent) {
     }
 }
 
-fn unix_ms() -> u64 {
-    u64::try_from(
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis(),
-    )
-    .unwrap_or(u64::MAX)
-}
-
 fn is_tty(fd: RawFd) -> bool {
     unsafe { nix::libc::isatty(fd) == 1 }
 }
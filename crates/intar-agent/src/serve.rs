@@ -0,0 +1,484 @@
+use crate::{ACTIONS_GROUP, ACTIONS_SOCK_PATH, AgentError, unix_ms};
+use base64::Engine as _;
+use intar_probes::{
+    CHECK_ALL_PLUGIN_BUDGET, CachingEvaluator, FileManifest, LiveEvaluator, ProbeEvaluator,
+    ProbeResult, ProbeSpec, Request, Response, evaluate_probe, sudo_invocations,
+};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt as _;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Probe ports the host attaches, in the order they are tried.
+const AGENT_PORTS: [&str; 2] = ["/dev/virtio-ports/intar.agent", "/dev/vport0p1"];
+const VIRTIO_ACTIONS_PORT: &str = "/dev/virtio-ports/intar.actions";
+/// Sockets standing in for the ports on guests without them (cloud instances), which the host
+/// reaches through an ssh tunnel logged in as [`TUNNEL_USER`].
+const PROBE_SOCKET: &str = "/run/intar/agent.sock";
+const ACTIONS_PORT_SOCKET: &str = "/run/intar/actions-port.sock";
+const TUNNEL_USER: &str = "user";
+const EXEC_DIR: &str = "/run/intar/exec";
+/// Kill an `exec` script's process group after this long, short of the host's 600-second
+/// wait so the host still hears why.
+const EXEC_TIMEOUT: Duration = Duration::from_secs(570);
+const EXEC_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static EXEC_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// The probe port the host attached to this guest, if any.
+#[must_use]
+pub fn probe_port() -> Option<&'static Path> {
+    AGENT_PORTS
+        .into_iter()
+        .map(Path::new)
+        .find(|path| path.exists())
+}
+
+/// Run the agent as the `intar-agent` binary does: forward recorded sessions from
+/// [`ACTIONS_SOCK_PATH`] to the host and answer probe requests on [`probe_port`], or on a
+/// socket for an ssh tunnel when the host attached no ports, restarting either after errors.
+///
+/// # Errors
+/// Returns an error if there is no probe port and the socket standing in for it fails;
+/// otherwise runs until the process exits.
+pub fn run_daemon() -> Result<(), AgentError> {
+    let start_time = Instant::now();
+
+    eprintln!("intar-agent starting...");
+
+    std::thread::spawn(|| {
+        loop {
+            if let Err(e) = serve_actions_sink() {
+                eprintln!("actions sink error: {e}; retrying in 1s...");
+                std::thread::sleep(Duration::from_secs(1));
+            }
+        }
+    });
+
+    let Some(port_path) = probe_port() else {
+        eprintln!("No virtio-serial probe port, answering on {PROBE_SOCKET}");
+        // systemd restarts the agent if the socket fails.
+        loop {
+            serve_on_socket(start_time)?;
+        }
+    };
+
+    eprintln!("Using virtio-serial probe port: {}", port_path.display());
+
+    loop {
+        match serve_on_port(port_path, start_time) {
+            Ok(()) => {
+                eprintln!("Probe agent loop ended, restarting...");
+            }
+            Err(e) => {
+                eprintln!("Probe agent error: {e}, retrying in 1s...");
+                std::thread::sleep(Duration::from_secs(1));
+            }
+        }
+    }
+}
+
+/// Listen on [`ACTIONS_SOCK_PATH`] and forward every line recorded sessions send to the
+/// host's actions port. Only members of [`ACTIONS_GROUP`] may connect if the guest has it.
+///
+/// # Errors
+/// Returns an error if the socket cannot be created or stops accepting connections.
+pub fn serve_actions_sink() -> Result<(), AgentError> {
+    if let Some(dir) = Path::new(ACTIONS_SOCK_PATH).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if Path::new(ACTIONS_SOCK_PATH).exists() {
+        let _ = std::fs::remove_file(ACTIONS_SOCK_PATH);
+    }
+
+    let listener = UnixListener::bind(ACTIONS_SOCK_PATH)?;
+    let mode = match group_id(ACTIONS_GROUP) {
+        Some(gid) => {
+            std::os::unix::fs::chown(ACTIONS_SOCK_PATH, None, Some(gid))?;
+            0o660
+        }
+        None => 0o666,
+    };
+    std::fs::set_permissions(ACTIONS_SOCK_PATH, std::fs::Permissions::from_mode(mode))?;
+
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+    std::thread::spawn(move || actions_writer_loop(rx));
+
+    for conn in listener.incoming() {
+        match conn {
+            Ok(stream) => {
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream);
+                    loop {
+                        let mut line = String::new();
+                        match reader.read_line(&mut line) {
+                            Ok(0) | Err(_) => break,
+                            Ok(_) => {
+                                let trimmed = line.trim_end();
+                                if trimmed.is_empty() {
+                                    continue;
+                                }
+                                let _ = tx.send(trimmed.to_string());
+                            }
+                        }
+                    }
+                });
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
+
+fn actions_writer_loop(rx: std::sync::mpsc::Receiver<String>) {
+    let listener = if Path::new(VIRTIO_ACTIONS_PORT).exists() {
+        None
+    } else {
+        match bind_tunnel_socket(ACTIONS_PORT_SOCKET) {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                eprintln!("actions port socket error: {e}");
+                None
+            }
+        }
+    };
+    let mut port: Option<Box<dyn Write>> = None;
+
+    for line in rx {
+        loop {
+            if port.is_none() {
+                if let Some(f) = open_actions_port(listener.as_ref()) {
+                    port = Some(f);
+                } else {
+                    std::thread::sleep(Duration::from_millis(200));
+                    continue;
+                }
+            }
+
+            let Some(f) = port.as_mut() else {
+                continue;
+            };
+
+            if writeln!(f, "{line}").is_ok() && f.flush().is_ok() {
+                break;
+            }
+
+            port = None;
+        }
+    }
+}
+
+/// Where recorded sessions go: the actions port, or the next host connection to `listener`.
+fn open_actions_port(listener: Option<&UnixListener>) -> Option<Box<dyn Write>> {
+    match listener {
+        Some(listener) => {
+            let (stream, _) = listener.accept().ok()?;
+            Some(Box::new(stream))
+        }
+        None => {
+            let port = File::options().write(true).open(VIRTIO_ACTIONS_PORT).ok()?;
+            Some(Box::new(port))
+        }
+    }
+}
+
+/// Bind a socket at `path` that only root and [`TUNNEL_USER`] may connect to.
+fn bind_tunnel_socket(path: &str) -> Result<UnixListener, AgentError> {
+    if let Some(dir) = Path::new(path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    if let Some((uid, gid)) = user_ids(TUNNEL_USER) {
+        std::os::unix::fs::chown(path, Some(uid), Some(gid))?;
+    }
+    Ok(listener)
+}
+
+/// GID of group `name`, from `/etc/group`.
+fn group_id(name: &str) -> Option<u32> {
+    let groups = std::fs::read_to_string("/etc/group").ok()?;
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// UID and GID of user `name`, from `/etc/passwd`.
+fn user_ids(name: &str) -> Option<(u32, u32)> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        let uid = fields.nth(1)?.parse().ok()?;
+        let gid = fields.next()?.parse().ok()?;
+        Some((uid, gid))
+    })
+}
+
+/// Answer the JSON requests the host writes to `port_path`, one per line, until the port
+/// fails. `Ping` reports the time since `start_time` as uptime; repeated `CheckAll` polls reuse
+/// the results of probes whose watched inputs are unchanged. `Exec` scripts run on their own
+/// thread, so requests that arrive meanwhile are still answered.
+///
+/// # Errors
+/// Returns an error if the port cannot be opened, read, or written.
+pub fn serve_on_port(port_path: &Path, start_time: Instant) -> Result<(), AgentError> {
+    let port = File::options().read(true).write(true).open(port_path)?;
+
+    let writer = Arc::new(Mutex::new(port.try_clone()?));
+    let mut reader = BufReader::new(port);
+
+    eprintln!("Connected to virtio-serial probe port");
+
+    let mut evaluator = CachingEvaluator::new(LiveEvaluator);
+
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Ok(_) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                let response = match serde_json::from_str::<Request>(line) {
+                    Ok(Request::Exec { script }) => {
+                        let writer = Arc::clone(&writer);
+                        std::thread::spawn(move || {
+                            if let Err(e) = write_response(&writer, &exec_response(&script)) {
+                                eprintln!("Failed to send exec result: {e}");
+                            }
+                        });
+                        continue;
+                    }
+                    Ok(request) => handle_request(request, start_time, &mut evaluator),
+                    Err(e) => parse_failure(&e),
+                };
+                write_response(&writer, &response)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Write `response` as one line; exec threads share the port with the request loop.
+fn write_response(writer: &Mutex<File>, response: &Response) -> Result<(), AgentError> {
+    let response_json = serde_json::to_string(response)?;
+    let mut writer = writer
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    writeln!(writer, "{response_json}")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Answer probe requests on every connection to a socket the host reaches through an ssh
+/// tunnel, for guests without a probe port. Connections are served concurrently.
+///
+/// # Errors
+/// Returns an error if the socket cannot be created or stops accepting connections.
+pub fn serve_on_socket(start_time: Instant) -> Result<(), AgentError> {
+    let listener = bind_tunnel_socket(PROBE_SOCKET)?;
+    for conn in listener.incoming() {
+        let stream = conn?;
+        std::thread::spawn(move || {
+            if let Err(e) = serve_connection(stream, start_time) {
+                eprintln!("Probe connection error: {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn serve_connection(stream: UnixStream, start_time: Instant) -> Result<(), AgentError> {
+    let mut writer = stream.try_clone()?;
+    let mut evaluator = CachingEvaluator::new(LiveEvaluator);
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let response = answer_line(line, start_time, &mut evaluator);
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn answer_line(line: &str, start_time: Instant, evaluator: &mut impl ProbeEvaluator) -> Response {
+    match serde_json::from_str::<Request>(line) {
+        Ok(request) => handle_request(request, start_time, evaluator),
+        Err(e) => parse_failure(&e),
+    }
+}
+
+fn parse_failure(error: &serde_json::Error) -> Response {
+    Response::Error {
+        message: format!("Failed to parse request: {error}"),
+    }
+}
+
+/// Answer the base64-encoded request `encoded`. Lets `intar probe run` evaluate probes on
+/// hosts that run no agent.
+#[must_use]
+pub fn answer_encoded(encoded: &str) -> Response {
+    let request = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_slice::<Request>(&json).map_err(|e| e.to_string()));
+    match request {
+        Ok(request) => handle_request(request, Instant::now(), &mut LiveEvaluator),
+        Err(e) => Response::Error {
+            message: format!("Failed to parse request: {e}"),
+        },
+    }
+}
+
+/// Answer one host request. `CheckAll` evaluates through `evaluator` and stops starting
+/// plugins once they have used [`CHECK_ALL_PLUGIN_BUDGET`]; single `CheckProbe` requests
+/// always evaluate live.
+pub fn handle_request(
+    request: Request,
+    start_time: Instant,
+    evaluator: &mut impl ProbeEvaluator,
+) -> Response {
+    match request {
+        Request::Ping => Response::Pong {
+            uptime_secs: start_time.elapsed().as_secs(),
+        },
+        Request::CheckProbe { id, spec } => {
+            let result = evaluate_probe(&id, &spec);
+            Response::ProbeResult {
+                id: result.id,
+                passed: result.passed,
+                message: result.message,
+                details: result.details,
+            }
+        }
+        Request::CheckAll { probes } => {
+            let mut plugin_time = Duration::ZERO;
+            let results: Vec<ProbeResult> = probes
+                .into_iter()
+                .map(|(id, spec)| {
+                    if !matches!(spec, ProbeSpec::Plugin { .. }) {
+                        return evaluator.evaluate(&id, &spec);
+                    }
+                    if plugin_time >= CHECK_ALL_PLUGIN_BUDGET {
+                        return ProbeResult::fail(
+                            id,
+                            format!(
+                                "Not run: plugins used this check's {}s budget",
+                                CHECK_ALL_PLUGIN_BUDGET.as_secs()
+                            ),
+                        );
+                    }
+                    let started = Instant::now();
+                    let result = evaluator.evaluate(&id, &spec);
+                    plugin_time += started.elapsed();
+                    result
+                })
+                .collect();
+            Response::AllResults { results }
+        }
+        Request::Exec { script } => exec_response(&script),
+        Request::SudoLog => match sudo_invocations() {
+            Ok(invocations) => Response::SudoLog { invocations },
+            Err(message) => Response::Error { message },
+        },
+        Request::FileManifest { paths } => Response::FileManifest {
+            manifest: FileManifest::scan(&paths),
+        },
+    }
+}
+
+fn exec_response(script: &str) -> Response {
+    match exec_script(script) {
+        Ok(response) => response,
+        Err(e) => Response::Error {
+            message: format!("exec failed: {e}"),
+        },
+    }
+}
+
+/// Run `script` with bash as root in its own process group, which is killed after
+/// [`EXEC_TIMEOUT`].
+fn exec_script(script: &str) -> Result<Response, AgentError> {
+    std::fs::create_dir_all(EXEC_DIR)?;
+    // Scripts can overlap, so the counter keeps their names apart.
+    let count = EXEC_COUNT.fetch_add(1, Ordering::Relaxed);
+    let path = Path::new(EXEC_DIR).join(format!("{}-{}-{count}.sh", std::process::id(), unix_ms()));
+    std::fs::write(&path, script)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))?;
+
+    let child = Command::new("bash")
+        .arg(&path)
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let result = child.map_err(AgentError::from).and_then(wait_for_script);
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn wait_for_script(mut child: std::process::Child) -> Result<Response, AgentError> {
+    let stdout = capture(child.stdout.take());
+    let stderr = capture(child.stderr.take());
+
+    let deadline = Instant::now() + EXEC_TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            if let Ok(pid) = i32::try_from(child.id()) {
+                unsafe {
+                    nix::libc::kill(-pid, nix::libc::SIGKILL);
+                }
+            }
+            let _ = child.wait();
+            return Err(AgentError::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("script timed out after {}s", EXEC_TIMEOUT.as_secs()),
+            )));
+        }
+        std::thread::sleep(EXEC_POLL_INTERVAL);
+    };
+
+    Ok(Response::ExecResult {
+        exit_code: status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&stdout.join().unwrap_or_default()).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr.join().unwrap_or_default()).into_owned(),
+    })
+}
+
+/// Read all of `pipe` on a thread so the script never blocks on a full pipe.
+fn capture<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut output);
+        }
+        output
+    })
+}
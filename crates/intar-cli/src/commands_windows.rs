@@ -1,13 +1,102 @@
 use crate::agent::{AGENT_AARCH64, AGENT_X86_64, is_placeholder};
+use crate::{StartArgs, VmHostArgs};
 use anyhow::{Context, Result, bail};
-use intar_core::Scenario;
+use intar_core::{CoreError, DEFAULT_GUEST_USER, Diagnostics, LintOptions, Scenario};
 use intar_ui::App;
-use intar_vm::IntarDirs;
+use intar_vm::{
+    ACTION_LOG_FILE, BackendKind, IntarConfig, IntarDirs, LearnerProfile, Multiplexer, ProbeTarget,
+    QuotaAction,
+};
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
-pub async fn start(scenario_path: PathBuf) -> Result<()> {
+pub async fn start(scenario_path: PathBuf, args: StartArgs, host: &VmHostArgs) -> Result<()> {
+    if args.remote.is_some() {
+        bail!("intar start --remote tunnels unix sockets over ssh, which Windows does not support");
+    }
+    if matches!(host.backend, BackendKind::Libvirt | BackendKind::Cloud) {
+        bail!("--backend {} is not supported on Windows", host.backend);
+    }
+    // Simulated VMs run no agent.
+    if host.backend == BackendKind::Qemu {
+        require_agent_binaries()?;
+    }
+    if let Some(name) = &args.name {
+        intar_vm::validate_run_name(name)?;
+    }
+
+    let scenario = Scenario::from_file(&scenario_path).context("Failed to parse scenario")?;
+
+    scenario.validate().context("Scenario validation failed")?;
+    let (config, config_path) = load_config()?;
+    admit_run(&scenario, &config, &config_path)?;
+
+    let mut app = App::new(scenario, AGENT_X86_64.to_vec(), AGENT_AARCH64.to_vec())
+        .with_run_name(args.name)
+        .with_seed(args.seed)
+        .with_keep_on_exit(args.keep_on_exit)
+        .with_auto_balloon(args.auto_balloon)
+        .with_idle_suspend(config.idle_suspend_after())
+        .with_ttl(args.ttl)
+        .with_qemu_priority(host.priority())
+        .with_boot_concurrency(host.boot_concurrency.or(config.boot_concurrency))
+        .with_backend(host.backend)
+        .with_watch(args.watch.then_some(scenario_path))
+        .with_author(args.author)
+        .with_ephemeral(args.ephemeral)
+        .with_paranoid(args.paranoid)
+        .with_allow_host_hooks(args.allow_host_hooks);
+    if args.ephemeral {
+        eprintln!(
+            "Warning: --ephemeral keeps the VMs' disks in RAM; the run is lost if the host \
+             reboots"
+        );
+    }
+    app.run().await?;
+    print_exit_summary(&app);
+
+    Ok(())
+}
+
+/// Load `config.yaml` from the intar config directory, returning it with its path.
+fn load_config() -> Result<(IntarConfig, PathBuf)> {
+    let path = IntarConfig::path(&IntarDirs::new()?);
+    let config = IntarConfig::load_from(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok((config, path))
+}
+
+/// Check the scenario against the run quota in the config file, asking on the terminal whether
+/// to start anyway when the quota is set to prompt.
+fn admit_run(scenario: &Scenario, config: &IntarConfig, path: &Path) -> Result<()> {
+    let quota = config.quota;
+    let violations = quota.violations(scenario);
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let summary = format!(
+        "Scenario '{}' exceeds the run quota in {}:\n  {}",
+        scenario.name,
+        path.display(),
+        violations.join("\n  ")
+    );
+    if quota.on_exceed == QuotaAction::Reject || !io::stdin().is_terminal() {
+        bail!("{summary}");
+    }
+    eprint!("{summary}\nStart anyway? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        bail!("Not starting scenario '{}'", scenario.name);
+    }
+    Ok(())
+}
+
+fn require_agent_binaries() -> Result<()> {
     if is_placeholder(AGENT_X86_64) || is_placeholder(AGENT_AARCH64) {
         bail!(
             "Agent binaries not built. Please install cargo-zigbuild and zig:\n\
@@ -19,49 +108,87 @@ pub async fn start(scenario_path: PathBuf) -> Result<()> {
             or simply re-run: cargo run --bin intar -- start <scenario.hcl>"
         );
     }
+    Ok(())
+}
 
-    let scenario = Scenario::from_file(&scenario_path).context("Failed to parse scenario")?;
+pub async fn attach(run_name: &str, keep_on_exit: bool, auto_balloon: bool) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    let scenario =
+        intar_vm::load_run_scenario(&run_dir).context("Failed to load the run's scenario")?;
 
-    scenario.validate().context("Scenario validation failed")?;
+    let (config, _) = load_config()?;
 
-    let mut app = App::new(scenario, AGENT_X86_64.to_vec(), AGENT_AARCH64.to_vec());
+    let mut app = App::new(scenario, AGENT_X86_64.to_vec(), AGENT_AARCH64.to_vec())
+        .with_resume(run_dir)
+        .with_keep_on_exit(keep_on_exit)
+        .with_auto_balloon(auto_balloon)
+        .with_idle_suspend(config.idle_suspend_after());
     app.run().await?;
+    print_exit_summary(&app);
 
     Ok(())
 }
 
-pub fn ssh(vm_name: &str, run_name: Option<&str>, command: Option<&str>) -> Result<()> {
-    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
-    let runs_root = dirs.runs_dir();
+pub async fn migrate_run(_run_name: &str, _to: &str) -> Result<()> {
+    bail!("intar migrate-run needs rsync, which does not run on Windows")
+}
 
-    let run_dir = if let Some(name) = run_name {
-        let dir = runs_root.join(name);
-        if !dir.exists() {
-            bail!("Run '{}' not found in {}", name, runs_root.display());
-        }
-        if !dir.join("state.json").exists() {
-            bail!("Run '{name}' has no state file");
-        }
-        dir
-    } else {
-        let mut entries: Vec<_> = std::fs::read_dir(&runs_root)?
-            .filter_map(Result::ok)
-            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
-            .filter(|e| e.path().join("state.json").exists())
-            .collect();
+pub async fn migrate_receive(_run_name: &str, _prepare: bool) -> Result<()> {
+    bail!("intar migrate-run needs rsync, which does not run on Windows")
+}
 
-        if entries.is_empty() {
-            bail!("No running scenario found. Start one with: intar start <scenario.hcl>");
-        }
+pub async fn remote_serve() -> Result<()> {
+    bail!("intar start --remote tunnels unix sockets over ssh, which Windows does not support")
+}
 
-        entries.sort_by_key(|e| {
-            e.metadata()
-                .and_then(|m| m.modified())
-                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-        });
+pub async fn vm_scale(run_name: &str, vm_name: &str, memory_mb: u32) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    intar_vm::scale_vm_memory(&run_dir, vm_name, memory_mb)
+        .await
+        .with_context(|| format!("Failed to scale VM '{vm_name}'"))?;
+    println!("Memory of {vm_name} set to {memory_mb} MB");
+    Ok(())
+}
 
-        entries.pop().unwrap().path()
-    };
+pub async fn kubeconfig(run_name: &str, vm_name: Option<&str>) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    let kubeconfig = intar_vm::host_kubeconfig(&run_dir, vm_name)
+        .await
+        .context("Failed to fetch kubeconfig")?;
+
+    let path = std::env::temp_dir().join(format!("intar-{run_name}-{}.kubeconfig", kubeconfig.vm));
+    std::fs::write(&path, &kubeconfig.content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    eprintln!(
+        "Kubeconfig for {} written to {}",
+        kubeconfig.vm,
+        path.display()
+    );
+    println!("$env:KUBECONFIG = \"{}\"", path.display());
+    Ok(())
+}
+
+fn print_exit_summary(app: &App) {
+    if let Some(report) = app.final_report() {
+        print!("{report}");
+    }
+    if let Some(run) = app.kept_run() {
+        println!("Run kept on disk. Attach again with: intar attach {run}");
+        if app.is_ephemeral() {
+            println!("Its VM disks are in RAM and do not survive a host reboot.");
+        }
+    }
+    if let Some(archive) = app.archived_run() {
+        println!(
+            "Run stopped when its TTL ran out. Records archived to {}",
+            archive.display()
+        );
+    }
+}
+
+pub fn ssh(vm_name: &str, run_name: Option<&str>, user: &str, command: Option<&str>) -> Result<()> {
+    let run_dir = running_run_dir(run_name)?;
     let state = intar_vm::RunState::load(&run_dir).context("Failed to load run state")?;
 
     let vm_info = state
@@ -81,11 +208,23 @@ pub fn ssh(vm_name: &str, run_name: Option<&str>, command: Option<&str>) -> Resu
             )
         })?;
 
+    if user != DEFAULT_GUEST_USER {
+        let scenario =
+            intar_vm::load_run_scenario(&run_dir).context("Failed to load the run's scenario")?;
+        if let Some(vm) = scenario.vms.iter().find(|vm| vm.name == vm_name) {
+            vm.check_login_user(user).map_err(anyhow::Error::msg)?;
+        }
+    }
+
     let ssh_key = run_dir.join("id_ed25519");
     if !ssh_key.exists() {
         bail!("SSH key not found at {}", ssh_key.display());
     }
 
+    let host_key =
+        intar_vm::host_key_options(&run_dir, vm_name, vm_info.host_key_fingerprint.as_deref())
+            .context("Refusing to connect")?;
+
     let mut cmd = std::process::Command::new("ssh");
     cmd.args([
         "-i",
@@ -98,14 +237,9 @@ pub fn ssh(vm_name: &str, run_name: Option<&str>, command: Option<&str>) -> Resu
         "ConnectTimeout=5",
         "-o",
         "ConnectionAttempts=1",
-        "-o",
-        "StrictHostKeyChecking=no",
-        "-o",
-        "UserKnownHostsFile=/dev/null",
-        "-o",
-        "LogLevel=ERROR",
-        "user@localhost",
-    ]);
+    ])
+    .args(host_key)
+    .args(["-o", "LogLevel=ERROR", &format!("{user}@localhost")]);
 
     if let Some(command) = command {
         cmd.arg(command);
@@ -120,6 +254,108 @@ pub fn ssh(vm_name: &str, run_name: Option<&str>, command: Option<&str>) -> Resu
     Ok(())
 }
 
+pub async fn console(vm_name: &str, run_name: Option<&str>) -> Result<()> {
+    let run_dir = running_run_dir(run_name)?;
+    let stream = intar_vm::connect_console(&run_dir, vm_name)
+        .await
+        .context("Failed to connect to the console")?;
+    eprintln!("Connected to the serial console of {vm_name}. Press Ctrl+] to leave.");
+    intar_ui::attach_console(stream).await?;
+    eprintln!();
+    Ok(())
+}
+
+pub fn inventory(run_name: &str, format: &str) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    let inventory =
+        intar_vm::AnsibleInventory::load(&run_dir).context("Failed to load run state")?;
+    if format == "ansible-ini" {
+        print!("{}", inventory.to_ini());
+    } else {
+        println!("{}", serde_json::to_string_pretty(&inventory.to_json())?);
+    }
+    Ok(())
+}
+
+pub fn workspace(_run_name: &str, multiplexer: Option<Multiplexer>) -> Result<()> {
+    let multiplexer = multiplexer.unwrap_or_default();
+    bail!(
+        "intar workspace needs {multiplexer}, which does not run on Windows; use intar ssh in separate windows"
+    )
+}
+
+pub fn net_stats(run_name: &str) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    let stats = intar_vm::LanStats::load(&run_dir).context("Failed to load LAN statistics")?;
+    println!("{stats}");
+    Ok(())
+}
+
+pub fn net_capture(
+    run_name: &str,
+    output: Option<&Path>,
+    max_size_mb: u64,
+    files: u32,
+) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    // Fails early for runs without a shared LAN.
+    intar_vm::LanStats::load(&run_dir).context("Failed to load LAN statistics")?;
+
+    let path = match output {
+        Some(path) => std::env::current_dir()?.join(path),
+        None => run_dir.join(intar_vm::DEFAULT_CAPTURE_FILE),
+    };
+    let config = intar_vm::CaptureConfig::new(path)
+        .with_max_file_mb(max_size_mb)
+        .with_max_files(files);
+    intar_vm::queue_lan_capture(&run_dir, Some(&config))
+        .context("Failed to request LAN capture")?;
+
+    println!(
+        "Requested a capture of run '{run_name}' to {} (files of up to {max_size_mb} MB, {files} kept)",
+        config.path.display()
+    );
+    Ok(())
+}
+
+pub fn net_capture_stop(run_name: &str) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    let stats = intar_vm::LanStats::load(&run_dir).context("Failed to load LAN statistics")?;
+    let Some(path) = stats.capture else {
+        bail!("Run '{run_name}' is not capturing its LAN");
+    };
+    intar_vm::queue_lan_capture(&run_dir, None).context("Failed to request LAN capture stop")?;
+
+    println!(
+        "Requested run '{run_name}' to stop capturing to {}",
+        path.display()
+    );
+    Ok(())
+}
+
+pub fn trigger(run_name: &str, step: &str) -> Result<()> {
+    let run_dir = running_run_dir(Some(run_name))?;
+    let state = intar_vm::RunState::load(&run_dir).context("Failed to load run state")?;
+    state
+        .request_trigger(&run_dir, step)
+        .context("Failed to request trigger")?;
+
+    println!("Requested step '{step}' on run '{run_name}'");
+    Ok(())
+}
+
+pub fn submit(flag: &str, run_name: Option<&str>) -> Result<()> {
+    let run_dir = running_run_dir(run_name)?;
+    intar_vm::queue_flag_submission(&run_dir, flag).context("Failed to submit flag")?;
+
+    let run = run_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    println!("Submitted flag to run '{run}'; the result appears in the TUI within a few seconds");
+    Ok(())
+}
+
 pub fn list(dir: &Path) -> Result<()> {
     println!("Searching for scenarios in: {}", dir.display());
 
@@ -149,6 +385,412 @@ pub fn list(dir: &Path) -> Result<()> {
     Ok(())
 }
 
+pub fn validate(path: &Path) -> Result<()> {
+    let scenario = load_checked(path)?;
+    println!(
+        "{}: scenario '{}' is valid ({} VMs, {} probes)",
+        path.display(),
+        scenario.name,
+        scenario.vms.len(),
+        scenario.total_probe_count()
+    );
+    Ok(())
+}
+
+pub fn migrate(path: &Path, dry_run: bool) -> Result<()> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file = path.display().to_string();
+    let migration = match intar_core::migrate_scenario(&source) {
+        Ok(migration) => migration,
+        Err(CoreError::Diagnostics(diagnostics)) => bail!("{}", diagnostics.in_file(&file)),
+        Err(err) => return Err(err).context("Failed to parse scenario"),
+    };
+    for change in &Diagnostics(migration.changes.clone()).in_file(&file).0 {
+        println!("{change}");
+    }
+
+    if migration.changes.is_empty() {
+        println!("{}: already up to date", path.display());
+    } else if !dry_run {
+        std::fs::write(path, &migration.source)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!(
+            "{}: migrated {} construct(s)",
+            path.display(),
+            migration.changes.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn lint(path: &Path, options: &LintOptions, deny_warnings: bool) -> Result<()> {
+    let scenario = load_checked(path)?;
+    let warnings = scenario.lint(options);
+    for warning in &warnings {
+        eprintln!("{}: {warning}", path.display());
+    }
+
+    if warnings.is_empty() {
+        println!("{}: no lint warnings", path.display());
+    } else if deny_warnings {
+        bail!("{} warning(s) in {}", warnings.len(), path.display());
+    }
+    Ok(())
+}
+
+/// Parse and validate a scenario, printing every parse diagnostic.
+fn load_checked(path: &Path) -> Result<Scenario> {
+    let scenario = match Scenario::from_file(path) {
+        Ok(scenario) => scenario,
+        Err(CoreError::Diagnostics(diagnostics)) => {
+            for diagnostic in &diagnostics.0 {
+                eprintln!("{diagnostic}");
+            }
+            bail!(
+                "{} problem(s) found in {}",
+                diagnostics.0.len(),
+                path.display()
+            );
+        }
+        Err(err) => return Err(err).context("Failed to parse scenario"),
+    };
+    scenario.validate().context("Scenario validation failed")?;
+    Ok(scenario)
+}
+
+pub async fn plan(path: &Path) -> Result<()> {
+    let mut scenario = load_checked(path)?;
+    let (config, config_path) = load_config()?;
+    config
+        .network
+        .apply(&mut scenario)
+        .with_context(|| format!("Invalid network settings in {}", config_path.display()))?;
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let image_cache = intar_vm::ImageCache::new(dirs.images_dir());
+    let plan = intar_vm::plan_run(&scenario, &image_cache)
+        .await
+        .context("Failed to plan scenario")?;
+    print!("{plan}");
+    if !plan.is_startable() {
+        bail!("Some images have no source for {}", plan.arch);
+    }
+    Ok(())
+}
+
+pub async fn bake(
+    scenario_path: &Path,
+    vm: Option<&str>,
+    output: Option<PathBuf>,
+    name: Option<String>,
+    url: Option<String>,
+) -> Result<()> {
+    require_agent_binaries()?;
+    let scenario = load_checked(scenario_path)?;
+    let vm_name = match (vm, scenario.vms.as_slice()) {
+        (Some(vm), _) => vm.to_string(),
+        (None, [only]) => only.name.clone(),
+        (None, vms) => bail!(
+            "Scenario has {} VMs; choose the one to bake with --vm",
+            vms.len()
+        ),
+    };
+    let name = name.unwrap_or_else(|| format!("{vm_name}-baked"));
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("{name}.qcow2")));
+
+    let baked = intar_vm::bake_image(
+        &scenario,
+        &vm_name,
+        &output,
+        AGENT_X86_64.to_vec(),
+        AGENT_AARCH64.to_vec(),
+        |stage| eprintln!("{stage}..."),
+    )
+    .await
+    .with_context(|| format!("Failed to bake VM '{vm_name}'"))?;
+
+    let url = match url {
+        Some(url) => url,
+        None => intar_vm::file_url(&std::fs::canonicalize(&baked.path)?),
+    };
+    eprintln!(
+        "Wrote {} ({} MB). Add this image block to your scenarios:\n",
+        baked.path.display(),
+        baked.size / (1024 * 1024)
+    );
+    print!("{}", baked.image_block(&name, &url));
+    Ok(())
+}
+
+pub async fn images_serve(listen: SocketAddr) -> Result<()> {
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let images_dir = dirs.images_dir();
+    let port = listen.port();
+    println!("Sharing {} on {listen}", images_dir.display());
+    println!("Add it to downloads.peers in config.yaml on other hosts, e.g.:");
+    println!("  downloads:\n    peers: [\"http://<this-host>:{port}\"]");
+    println!("Press Ctrl+C to stop.");
+    intar_vm::serve_image_cache(images_dir, listen)
+        .await
+        .with_context(|| format!("Failed to serve the image cache on {listen}"))
+}
+
+pub fn images_export(output: &Path, scenario_path: Option<&Path>) -> Result<()> {
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let image_cache = intar_vm::ImageCache::new(dirs.images_dir());
+    let images = match scenario_path {
+        Some(path) => {
+            let scenario = load_checked(path)?;
+            let mut images = Vec::new();
+            for (name, image) in &scenario.images {
+                let cached: Vec<_> = image
+                    .sources
+                    .iter()
+                    .filter_map(|source| image_cache.get_cached_path(source))
+                    .collect();
+                if cached.is_empty() {
+                    bail!("Image '{name}' is not cached; start the scenario once while online");
+                }
+                images.extend(cached);
+            }
+            images.sort();
+            images
+        }
+        None => image_cache.list_cached_images()?,
+    };
+    if images.is_empty() {
+        bail!("The image cache is empty");
+    }
+
+    let bundled = intar_vm::export_image_bundle(&images, output)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    let total: u64 = bundled.iter().map(|image| image.size).sum();
+    for image in &bundled {
+        println!("{}  {}", image.sha256, image.file);
+    }
+    println!(
+        "Wrote {} image(s), {} MB, to {}",
+        bundled.len(),
+        total / (1024 * 1024),
+        output.display()
+    );
+    Ok(())
+}
+
+pub fn images_import(bundle: &Path) -> Result<()> {
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let import = intar_vm::import_image_bundle(bundle, &dirs.images_dir())
+        .with_context(|| format!("Failed to import {}", bundle.display()))?;
+    for image in &import.imported {
+        println!("Imported {}", image.file);
+    }
+    for file in &import.skipped {
+        println!("Already cached {file}");
+    }
+    println!(
+        "Imported {} image(s) into {}",
+        import.imported.len(),
+        dirs.images_dir().display()
+    );
+    Ok(())
+}
+
+pub fn login(profile: LearnerProfile) -> Result<()> {
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let path = LearnerProfile::path(&dirs);
+    let mut current = LearnerProfile::load(&dirs)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .unwrap_or_default();
+    if profile.is_empty() {
+        if current.is_empty() {
+            println!(
+                "Not logged in. Set a profile with: intar login --name <name> --email <email>"
+            );
+        } else {
+            println!("Logged in as {current}");
+        }
+        return Ok(());
+    }
+
+    current.update(profile);
+    current
+        .save_to(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Logged in as {current}");
+    Ok(())
+}
+
+pub fn logout() -> Result<()> {
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let path = LearnerProfile::path(&dirs);
+    match std::fs::remove_file(&path) {
+        Ok(()) => println!("Logged out"),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => println!("Not logged in"),
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+    Ok(())
+}
+
+pub fn archive(run_name: &str, output: Option<&Path>, overlays: bool, redact: bool) -> Result<()> {
+    let run_dir = finished_run_dir(run_name)?;
+    let output = output.map_or_else(
+        || PathBuf::from(format!("{run_name}.tar.zst")),
+        Path::to_path_buf,
+    );
+    let options = intar_vm::ArchiveOptions { overlays, redact };
+    let entries = intar_vm::export_run_archive(&run_dir, &output, options)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    for entry in &entries {
+        println!("{entry}");
+    }
+    println!("Wrote {} file(s) to {}", entries.len(), output.display());
+    Ok(())
+}
+
+pub fn certificate(
+    run_name: &str,
+    key_path: &Path,
+    learner: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let key = read_certificate_key(key_path)?;
+    let run_dir = finished_run_dir(run_name)?;
+    let claims = intar_vm::run_certificate_claims(&run_dir, learner)
+        .with_context(|| format!("Cannot certify run '{run_name}'"))?;
+    let certificate = intar_vm::Certificate::sign(claims, &key)?;
+    let output = output.map_or_else(
+        || PathBuf::from(format!("{run_name}.certificate.json")),
+        Path::to_path_buf,
+    );
+    std::fs::write(&output, serde_json::to_string_pretty(&certificate)?)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    println!(
+        "Certified {} for {}: {}/{} objectives in {}s",
+        certificate.claims.scenario,
+        certificate.claims.learner,
+        certificate.claims.objectives_passed,
+        certificate.claims.objectives_total,
+        certificate.claims.duration_secs
+    );
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+pub fn certificate_verify(path: &Path, key_path: &Path) -> Result<()> {
+    let key = read_certificate_key(key_path)?;
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let certificate: intar_vm::Certificate = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not an intar certificate", path.display()))?;
+    if !certificate.verify(&key)? {
+        bail!(
+            "{} is not valid: it was altered or signed with another key",
+            path.display()
+        );
+    }
+    let claims = &certificate.claims;
+    println!(
+        "Valid: {} completed {} (run {}) with {}/{} objectives in {}s",
+        claims.learner,
+        claims.scenario,
+        claims.run,
+        claims.objectives_passed,
+        claims.objectives_total,
+        claims.duration_secs
+    );
+    Ok(())
+}
+
+/// The instructor's key, without the trailing newline an editor or `echo` leaves.
+fn read_certificate_key(path: &Path) -> Result<Vec<u8>> {
+    let mut key =
+        std::fs::read(path).with_context(|| format!("Failed to read key {}", path.display()))?;
+    while key.last().is_some_and(u8::is_ascii_whitespace) {
+        key.pop();
+    }
+    if key.is_empty() {
+        bail!("Key file {} is empty", path.display());
+    }
+    Ok(key)
+}
+
+/// A run that is still kept, or whose records were archived when it ended.
+fn finished_run_dir(run_name: &str) -> Result<PathBuf> {
+    intar_vm::validate_run_name(run_name)?;
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    [dirs.runs_dir(), dirs.archive_dir()]
+        .into_iter()
+        .map(|root| root.join(run_name))
+        .find(|dir| dir.join("progress.json").exists())
+        .with_context(|| {
+            format!(
+                "Run '{run_name}' not found in {} or {}",
+                dirs.runs_dir().display(),
+                dirs.archive_dir().display()
+            )
+        })
+}
+
+pub fn schema() -> Result<()> {
+    let schema = intar_core::scenario_json_schema(&intar_probes::probe_json_schema());
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+pub fn probes() -> Result<()> {
+    for preset in intar_core::builtin_probes()? {
+        let reference = format!("{}{}", intar_core::BUILTIN_PROBE_PREFIX, preset.name);
+        println!(
+            "{reference:<28} {:<16} {}",
+            preset.probe_type,
+            preset.description.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+/// Print what intar exposes on this host, flagging what `intar start --paranoid` would close.
+pub fn doctor() -> Result<()> {
+    let checks = intar_vm::security_summary(&IntarDirs::new()?);
+    println!("Security summary:");
+    for check in &checks {
+        let status = if check.ok { "ok" } else { "open" };
+        println!("  {status:<5} {}: {}", check.subject, check.detail);
+    }
+    if checks.iter().any(|check| !check.ok) {
+        println!("Runs started with --paranoid keep their SSH forwards and sockets to this host.");
+    }
+    Ok(())
+}
+
+/// Evaluate probe `probe` of the scenario at `path` on this machine, or over ssh on `target`
+/// (`ssh://user@host[:port]`), without starting a run.
+pub fn probe_run(path: &Path, probe: &str, target: Option<&str>) -> Result<()> {
+    let scenario = load_checked(path)?;
+    let spec = intar_vm::scenario_probe_spec(&scenario, probe)?;
+    let target = target.map_or(Ok(ProbeTarget::Local), str::parse)?;
+    if target != ProbeTarget::Local {
+        require_agent_binaries()?;
+    }
+    let result = intar_vm::run_probe_on_target(&target, probe, &spec, AGENT_X86_64, AGENT_AARCH64)?;
+
+    let status = if result.passed { "PASS" } else { "FAIL" };
+    println!("{status} {probe}: {}", result.message);
+    if let Some(observed) = &result.details.observed {
+        println!("  observed: {observed}");
+    }
+    if let Some(expected) = &result.details.expected {
+        println!("  expected: {expected}");
+    }
+    if let Some(diff) = &result.details.diff {
+        println!("{diff}");
+    }
+    if !result.passed {
+        bail!("Probe '{probe}' failed");
+    }
+    Ok(())
+}
+
 pub fn logs(run_name: Option<&str>, vm_name: Option<&str>, log_type: &str) -> Result<()> {
     let dirs = IntarDirs::new().context("Failed to initialize directories")?;
     let runs_root = dirs.runs_dir();
@@ -182,6 +824,9 @@ pub fn logs(run_name: Option<&str>, vm_name: Option<&str>, log_type: &str) -> Re
     if !logs_dir.exists() {
         bail!("No logs directory found in {}", run_dir.display());
     }
+    if log_type == "actions" {
+        return print_action_log(&logs_dir, vm_name);
+    }
 
     let vm_dir = if let Some(name) = vm_name {
         let dir = logs_dir.join(name);
@@ -201,10 +846,13 @@ pub fn logs(run_name: Option<&str>, vm_name: Option<&str>, log_type: &str) -> Re
     };
 
     let log_path = match log_type {
+        "qemu" => vm_dir.join("qemu.log"),
         "console" => vm_dir.join("console.log"),
-        "ssh" => vm_dir.join("ssh.log"),
-        "system" => vm_dir.join("system.log"),
-        other => bail!("Unknown log type '{other}'. Use console|ssh|system."),
+        "user-data" => vm_dir.join("user-data.yaml"),
+        "meta-data" => vm_dir.join("meta-data.yaml"),
+        other => {
+            bail!("Unknown log type '{other}'. Use: qemu, console, user-data, meta-data, actions")
+        }
     };
 
     if !log_path.exists() {
@@ -224,3 +872,65 @@ pub fn logs(run_name: Option<&str>, vm_name: Option<&str>, log_type: &str) -> Re
 
     Ok(())
 }
+
+/// Print the run's action log, or only the records of `vm_name`.
+fn print_action_log(logs_dir: &Path, vm_name: Option<&str>) -> Result<()> {
+    let log_path = logs_dir.join(ACTION_LOG_FILE);
+    if !log_path.exists() {
+        bail!("Log '{}' not found", log_path.display());
+    }
+
+    println!("=== {} ===", log_path.display());
+    let content = std::fs::read_to_string(&log_path)?;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    for line in content.lines() {
+        let record: serde_json::Value = serde_json::from_str(line).unwrap_or_default();
+        if vm_name.is_none_or(|vm| record["vm"] == vm)
+            && let Err(e) = writeln!(handle, "{line}")
+        {
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                break;
+            }
+            return Err(e.into());
+        }
+    }
+    handle.flush().ok();
+    Ok(())
+}
+
+/// Resolve a run with a state file: `run_name` if given, otherwise the most recent one.
+fn running_run_dir(run_name: Option<&str>) -> Result<PathBuf> {
+    let dirs = IntarDirs::new().context("Failed to initialize directories")?;
+    let runs_root = dirs.runs_dir();
+
+    let run_dir = if let Some(name) = run_name {
+        let dir = runs_root.join(name);
+        if !dir.exists() {
+            bail!("Run '{}' not found in {}", name, runs_root.display());
+        }
+        if !dir.join("state.json").exists() {
+            bail!("Run '{name}' has no state file");
+        }
+        dir
+    } else {
+        let mut entries: Vec<_> = std::fs::read_dir(&runs_root)?
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .filter(|e| e.path().join("state.json").exists())
+            .collect();
+
+        if entries.is_empty() {
+            bail!("No running scenario found. Start one with: intar start <scenario.hcl>");
+        }
+
+        entries.sort_by_key(|e| {
+            e.metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+        });
+
+        entries.pop().unwrap().path()
+    };
+    Ok(run_dir)
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use clap_complete::Shell;
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use intar_vm::IntarDirs;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Environment variable the registration script sets when asking intar for completions.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Print the script that registers intar's completions with `shell`. The script calls back into
+/// intar on every completion, so run and VM names are always current.
+pub fn print_script(shell: Shell) -> Result<()> {
+    let name = shell.to_string();
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&name)
+        .with_context(|| format!("No completion support for {name}"))?;
+    completer
+        .write_registration(
+            COMPLETE_VAR,
+            "intar",
+            "intar",
+            "intar",
+            &mut std::io::stdout(),
+        )
+        .context("Failed to write the completion script")
+}
+
+/// Names of the runs in the state directory, most recent first.
+pub fn run_names() -> Vec<CompletionCandidate> {
+    let mut runs: Vec<_> = run_dirs()
+        .into_iter()
+        .filter_map(|dir| {
+            let modified = dir.metadata().and_then(|meta| meta.modified()).ok();
+            let name = dir.file_name()?.to_str()?.to_string();
+            let scenario = intar_vm::load_run_scenario(&dir).ok().map(|s| s.name);
+            Some((modified, name, scenario))
+        })
+        .collect();
+    runs.sort_by_key(|(modified, ..)| Reverse(*modified));
+    runs.into_iter()
+        .map(|(_, name, scenario)| CompletionCandidate::new(name).help(scenario.map(Into::into)))
+        .collect()
+}
+
+/// Names of the VMs of every run; completion cannot see which run the command line names.
+pub fn vm_names() -> Vec<CompletionCandidate> {
+    run_dirs()
+        .iter()
+        .filter_map(|dir| intar_vm::load_run_scenario(dir).ok())
+        .flat_map(|scenario| scenario.vms.into_iter().map(|vm| vm.name))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+fn run_dirs() -> Vec<PathBuf> {
+    let Ok(dirs) = IntarDirs::new() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dirs.runs_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect()
+}
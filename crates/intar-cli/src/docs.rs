@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+// Shown by `--help` and in the man pages, but not by `-h`.
+
+pub const ENVIRONMENT: &str = "\
+Environment:
+  RUST_LOG          Log filter for intar.log in the intar state directory (default: info)
+  NO_COLOR          Render the TUI without colors
+  COLORTERM, TERM   Detect how many colors the terminal supports
+  COLORFGBG         Detect a light terminal background for the TUI theme
+  INTAR_LANG        Interface and scenario language, e.g. de (overrides `language` in
+                    config.yaml; --lang overrides it)
+  LC_ALL, LC_MESSAGES, LANG
+                    Interface language when neither INTAR_LANG nor config.yaml sets one
+  XDG_CACHE_HOME    Image cache location on Linux (default: ~/.cache/intar)
+  XDG_STATE_HOME    Run directory location on Linux (default: ~/.local/state/intar)
+  XDG_CONFIG_HOME   config.yaml location on Linux (default: ~/.config/intar)
+  HTTPS_PROXY, HTTP_PROXY, ALL_PROXY
+                    Proxy for image downloads (peer caches are always fetched directly)
+  NO_PROXY          Hosts to download from without the proxy
+  INTAR_FAKE_FIXTURE
+                    Probe fixture for `intar start --backend fake` to replay
+  COMPLETE          Set by the script from `intar completions` to request completions";
+
+pub const START_EXAMPLES: &str = "\
+Examples:
+  intar start scenarios/broken-nginx.hcl
+  intar start lab.hcl --name my-lab --keep-on-exit
+  intar start lab.hcl --ttl 4h --nice 10 --cpus 2-5
+  intar start lab.hcl --watch --author
+  intar start lab.hcl --watch --ephemeral
+  intar start lab.hcl --paranoid
+  intar start lab.hcl --allow-host-hooks
+  intar start lab.hcl --boot-concurrency 2
+  intar start lab.hcl --remote ssh://lab@lab-server
+  INTAR_FAKE_FIXTURE=solved.json intar start lab.hcl --backend fake";
+
+pub const ATTACH_EXAMPLES: &str = "\
+Examples:
+  intar attach my-lab
+  intar resume calm-otter-1234 --keep-on-exit";
+
+pub const MIGRATE_RUN_EXAMPLES: &str = "\
+Examples:
+  intar migrate-run my-lab --to ssh://lab@lab-server
+  intar migrate-run calm-otter-1234 --to ssh://me@laptop.local:2222";
+
+pub const VM_SCALE_EXAMPLES: &str = "\
+Examples:
+  intar vm scale my-lab web --memory 1024";
+
+pub const NET_STATS_EXAMPLES: &str = "\
+Examples:
+  intar net stats my-lab";
+
+pub const NET_CAPTURE_EXAMPLES: &str = "\
+Examples:
+  intar net capture my-lab
+  intar net capture my-lab -o /tmp/lan.pcap --max-size 20 --files 3
+  intar net capture my-lab --stop";
+
+pub const SSH_EXAMPLES: &str = "\
+Examples:
+  intar ssh web
+  intar ssh db --run my-lab --command 'systemctl status postgresql'
+  intar ssh web --user app";
+
+pub const CONSOLE_EXAMPLES: &str = "\
+Examples:
+  intar console web
+  intar console web --run my-lab
+
+Ctrl+] leaves the console. The VM's `console_autologin` user is logged in
+already; otherwise log in as a user with a password.";
+
+pub const KUBECONFIG_EXAMPLES: &str = "\
+Examples:
+  eval \"$(intar kubeconfig my-lab)\" && kubectl get nodes";
+
+pub const INVENTORY_EXAMPLES: &str = "\
+Examples:
+  intar inventory my-lab > inventory.json && ansible-playbook -i inventory.json site.yml
+  intar inventory my-lab --format ansible-ini > hosts.ini";
+
+pub const WORKSPACE_EXAMPLES: &str = "\
+Examples:
+  intar workspace my-lab
+  intar workspace my-lab --multiplexer zellij
+
+Set the default and a custom layout in config.yaml:
+  workspace:
+    multiplexer: zellij
+    template: |
+      layout {
+          {{attach}}
+          pane split_direction=\"vertical\" {
+      {{panes}}
+          }
+      }";
+
+pub const TRIGGER_EXAMPLES: &str = "\
+Examples:
+  intar trigger my-lab web/break-nginx
+  intar trigger my-lab break-nginx";
+
+pub const SUBMIT_EXAMPLES: &str = "\
+Examples:
+  intar submit 'FLAG{found-it}'
+  intar submit 'FLAG{found-it}' --run my-lab";
+
+pub const LIST_EXAMPLES: &str = "\
+Examples:
+  intar list --dir scenarios";
+
+pub const VALIDATE_EXAMPLES: &str = "\
+Examples:
+  intar validate scenarios/broken-nginx.hcl";
+
+pub const MIGRATE_EXAMPLES: &str = "\
+Examples:
+  intar migrate scenarios/k8s-lab.hcl --dry-run
+  intar migrate scenarios/k8s-lab.hcl";
+
+pub const LINT_EXAMPLES: &str = "\
+Examples:
+  intar lint lab.hcl
+  intar lint lab.hcl --arch amd64 --max-memory 8192 --deny warnings";
+
+pub const PLAN_EXAMPLES: &str = "\
+Examples:
+  intar plan lab.hcl";
+
+pub const BAKE_EXAMPLES: &str = "\
+Examples:
+  intar bake lab.hcl --vm web -o web.qcow2
+  intar bake lab.hcl --name web-golden --url https://images.example.com/web.qcow2";
+
+pub const IMAGES_SERVE_EXAMPLES: &str = "\
+Examples:
+  intar images serve
+  intar images serve --listen 192.168.1.20:9000";
+
+pub const IMAGES_EXPORT_EXAMPLES: &str = "\
+Examples:
+  intar images export images.tar
+  intar images export lab-images.tar --scenario lab.hcl";
+
+pub const IMAGES_IMPORT_EXAMPLES: &str = "\
+Examples:
+  intar images import /media/usb/lab-images.tar";
+
+pub const LOGIN_EXAMPLES: &str = "\
+Examples:
+  intar login --name \"Ada Lovelace\" --email ada@example.com --class-id linux-101
+  intar login";
+
+pub const CERTIFICATE_EXAMPLES: &str = "\
+Examples:
+  intar certificate fluffy-tiger-1234 --key instructor.key --learner ada@example.com
+  intar certificate verify fluffy-tiger-1234.certificate.json --key instructor.key";
+
+pub const ARCHIVE_EXAMPLES: &str = "\
+Examples:
+  intar archive fluffy-tiger-1234
+  intar archive fluffy-tiger-1234 --redact -o hand-in.tar.zst
+  intar archive fluffy-tiger-1234 --overlays";
+
+pub const DOCTOR_EXAMPLES: &str = "\
+Examples:
+  intar doctor";
+
+pub const SCHEMA_EXAMPLES: &str = "\
+Examples:
+  intar schema > intar-scenario.schema.json";
+
+pub const PROBES_EXAMPLES: &str = "\
+Examples:
+  intar probes";
+
+pub const PROBE_RUN_EXAMPLES: &str = "\
+Examples:
+  intar probe run lab.hcl --probe nginx-running --local
+  intar probe run lab.hcl --probe nginx-running --target ssh://admin@web1.lab
+  intar probe run lab.hcl --probe db-port --target ssh://admin@10.0.0.5:2222";
+
+pub const COMPLETIONS_EXAMPLES: &str = "\
+Examples:
+  echo 'source <(intar completions bash)' >> ~/.bashrc
+  echo 'source <(intar completions zsh)' >> ~/.zshrc
+  intar completions fish > ~/.config/fish/completions/intar.fish";
+
+pub const LOGS_EXAMPLES: &str = "\
+Examples:
+  intar logs
+  intar logs --run my-lab --vm web --log-type qemu
+  intar logs -t actions -m web";
+
+/// Write a man page for intar and one per visible subcommand (`intar-start.1`, ...) to
+/// `out_dir`.
+pub fn write_man_pages(cmd: clap::Command, out_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    clap_mangen::generate_to(cmd, out_dir)
+        .with_context(|| format!("Failed to write man pages to {}", out_dir.display()))
+}
@@ -5,10 +5,28 @@ mod commands_unix;
 mod commands_unix;
 #[cfg(windows)]
 mod commands_windows;
+mod completions;
+mod docs;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::engine::ArgValueCandidates;
+use clap_complete::{CompleteEnv, Shell};
+use intar_core::{
+    DEFAULT_GUEST_USER, DEFAULT_LINT_ARCHES, DEFAULT_MAX_TOTAL_MEMORY_MB, LintOptions,
+    parse_duration_secs,
+};
+use intar_vm::{
+    BackendKind, IntarDirs, LearnerProfile, Multiplexer, PEER_CACHE_PORT, QemuPriority,
+    set_language_override,
+};
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::{Directive, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
 
 #[cfg(unix)]
 use commands_unix as commands;
@@ -21,69 +39,717 @@ use commands_windows as commands;
 #[command(name = "intar")]
 #[command(about = "QEMU-based DevOps lab environment")]
 #[command(version)]
+#[command(after_long_help = docs::ENVIRONMENT)]
 struct Cli {
+    #[command(flatten)]
+    log: LogArgs,
+    /// Language for the interface and scenario text, e.g. "de" (default: INTAR_LANG,
+    /// config.yaml, then the system locale)
+    #[arg(long, global = true, value_name = "LANG")]
+    lang: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Logging flags, accepted before or after the subcommand.
+#[derive(Args)]
+struct LogArgs {
+    /// Log more to intar.log: -v for debug, -vv for trace
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Only log warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Per-module levels in RUST_LOG syntax, e.g. "intar_vm=debug,intar_ui=warn"
+    #[arg(long, global = true, value_name = "FILTER", value_parser = parse_log_filter)]
+    log_filter: Option<String>,
+    /// Also write logs to stderr; redirect it (e.g. `2>debug.log`) while the TUI runs
+    #[arg(long, global = true)]
+    log_stderr: bool,
+}
+
+/// How `intar start` runs the VMs.
+#[derive(Args)]
+struct VmHostArgs {
+    /// Run QEMU with this nice increment (1-19) and idle-class I/O priority
+    #[arg(long)]
+    nice: Option<i32>,
+    /// Pin QEMU to these host CPUs (e.g. "2-5" or "0,2,4"; Linux only)
+    #[arg(long)]
+    cpus: Option<String>,
+    /// Boot at most this many VMs at once; the rest start in waves as earlier ones come up
+    /// (overrides `boot_concurrency` in config.yaml)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    boot_concurrency: Option<u32>,
+    /// "qemu", "libvirt" to run QEMU as libvirt domains, "cloud" to launch the VMs on AWS or
+    /// GCP (experimental), or "fake" to simulate the VMs without booting anything (for CI)
+    #[arg(long, default_value_t = BackendKind::Qemu)]
+    backend: BackendKind,
+}
+
+impl VmHostArgs {
+    fn priority(&self) -> QemuPriority {
+        QemuPriority {
+            nice: self.nice,
+            cpus: self.cpus.clone(),
+        }
+    }
+}
+
+/// How `intar start` names and runs its run.
+#[derive(Args)]
+struct StartArgs {
+    /// Name of the run (lowercase letters, digits, and dashes; a petname when unset)
+    #[arg(long)]
+    name: Option<String>,
+    /// Seed for the run name and `random_one_of` step groups, to reproduce a run (recorded in
+    /// state.json; random when unset)
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Leave the VMs running on quit, without asking, so the run can be attached later
+    #[arg(long, alias = "keep")]
+    keep_on_exit: bool,
+    /// Reclaim memory from idle VMs while the host is short on RAM
+    #[arg(long)]
+    auto_balloon: bool,
+    /// Stop and archive the run after this long (e.g. "4h", "90m"; overrides the scenario)
+    #[arg(long, value_parser = parse_ttl)]
+    ttl: Option<Duration>,
+    /// Reload the scenario's probes whenever the file changes, for authoring
+    #[arg(long)]
+    watch: bool,
+    /// Show every probe evaluation with its timing and the agent's reply in a probes tab, where
+    /// E re-evaluates the selected probe
+    #[arg(long)]
+    author: bool,
+    /// Keep the VMs' disks in RAM (a tmpfs at /dev/shm; Linux only) for fast resets while
+    /// authoring; they are lost when the host reboots
+    #[arg(long)]
+    ephemeral: bool,
+    /// Keep the run to this host: SSH forwards on 127.0.0.1, a private run directory for the
+    /// VMs' sockets, and only the intar-actions group writing recorded sessions in the guests
+    #[arg(long)]
+    paranoid: bool,
+    /// Let the scenario's on_complete host commands and webhooks and its external actions run
+    /// on this host; see host_execution in config.yaml to allow them for every run
+    #[arg(long, conflicts_with = "paranoid")]
+    allow_host_hooks: bool,
+    /// Run the VMs on this host, as ssh://user@host[:port], with the TUI here; the host needs
+    /// intar on the PATH of its ssh sessions
+    #[arg(
+        long,
+        value_name = "URL",
+        conflicts_with_all = [
+            "ephemeral",
+            "paranoid",
+            "auto_balloon",
+            "watch",
+            "nice",
+            "cpus",
+            "boot_concurrency"
+        ]
+    )]
+    remote: Option<String>,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Start a scenario from an HCL file
+    #[command(after_long_help = docs::START_EXAMPLES)]
     Start {
         /// Path to the scenario HCL file
         scenario: PathBuf,
+        #[command(flatten)]
+        args: StartArgs,
+        #[command(flatten)]
+        host: VmHostArgs,
+    },
+    /// Re-attach to a run that was kept on quit or whose TUI crashed
+    #[command(visible_alias = "resume")]
+    #[command(after_long_help = docs::ATTACH_EXAMPLES)]
+    Attach {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// Leave the VMs running again on quit, without asking
+        #[arg(long, alias = "keep")]
+        keep_on_exit: bool,
+        /// Reclaim memory from idle VMs while the host is short on RAM
+        #[arg(long)]
+        auto_balloon: bool,
+    },
+    /// Move a kept run and its VMs to another host over ssh
+    #[command(after_long_help = docs::MIGRATE_RUN_EXAMPLES)]
+    MigrateRun {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// Host to move it to, as ssh://user@host[:port]
+        #[arg(long)]
+        to: String,
+    },
+    /// Take over a run sent by `intar migrate-run`; run by it on the destination over ssh
+    #[command(hide = true)]
+    MigrateReceive {
+        /// Name of the run
+        run: String,
+        /// Only create the run directory and print its path
+        #[arg(long)]
+        prepare: bool,
+    },
+    /// Host a run for `intar start --remote`; run by it on the remote host over ssh
+    #[command(hide = true)]
+    RemoteServe,
+    /// Manage the VMs of a running scenario
+    Vm {
+        #[command(subcommand)]
+        command: VmCommands,
+    },
+    /// Inspect the shared LAN of a running scenario
+    Net {
+        #[command(subcommand)]
+        command: NetCommands,
     },
     /// Open an SSH session to a VM
+    #[command(after_long_help = docs::SSH_EXAMPLES)]
     Ssh {
         /// Name of the VM
+        #[arg(add = ArgValueCandidates::new(completions::vm_names))]
         vm_name: String,
         /// Name of the run (defaults to most recent)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(completions::run_names))]
         run: Option<String>,
+        /// Guest user to log in as: `user` or one of the VM's `user` blocks
+        #[arg(short, long, default_value = DEFAULT_GUEST_USER)]
+        user: String,
         /// Run a command on the VM and exit
         #[arg(short, long)]
         command: Option<String>,
     },
+    /// Attach to a VM's serial console, e.g. while SSH is broken
+    #[command(after_long_help = docs::CONSOLE_EXAMPLES)]
+    Console {
+        /// Name of the VM
+        #[arg(add = ArgValueCandidates::new(completions::vm_names))]
+        vm_name: String,
+        /// Name of the run (defaults to most recent)
+        #[arg(short, long, add = ArgValueCandidates::new(completions::run_names))]
+        run: Option<String>,
+    },
+    /// Write a host kubeconfig for a VM's Kubernetes API and print the export line
+    #[command(after_long_help = docs::KUBECONFIG_EXAMPLES)]
+    Kubeconfig {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// VM to fetch it from (defaults to the first k8s_cluster server)
+        #[arg(long, add = ArgValueCandidates::new(completions::vm_names))]
+        vm: Option<String>,
+    },
+    /// Print an Ansible inventory of a running scenario's VMs
+    #[command(after_long_help = docs::INVENTORY_EXAMPLES)]
+    Inventory {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// `ansible` for dynamic-inventory JSON, `ansible-ini` for a static INI file
+        #[arg(long, default_value = "ansible", value_parser = ["ansible", "ansible-ini"])]
+        format: String,
+    },
+    /// Open a tmux or zellij session with a pane per VM and one running `intar attach`
+    #[command(after_long_help = docs::WORKSPACE_EXAMPLES)]
+    Workspace {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// `tmux` or `zellij` (defaults to `workspace.multiplexer` in the config file, else tmux)
+        #[arg(long)]
+        multiplexer: Option<Multiplexer>,
+    },
+    /// Fire a manual or delayed step in a running scenario
+    #[command(after_long_help = docs::TRIGGER_EXAMPLES)]
+    Trigger {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// Step to fire, as `<vm>/<step>` or a unique step name
+        step: String,
+    },
+    /// Submit a flag for a `flag` probe in a running scenario
+    #[command(after_long_help = docs::SUBMIT_EXAMPLES)]
+    Submit {
+        /// The flag value
+        flag: String,
+        /// Name of the run (defaults to most recent)
+        #[arg(short, long, add = ArgValueCandidates::new(completions::run_names))]
+        run: Option<String>,
+    },
     /// List available scenarios
+    #[command(after_long_help = docs::LIST_EXAMPLES)]
     List {
         /// Directory to search for scenarios
         #[arg(short, long, default_value = ".")]
         dir: PathBuf,
     },
+    /// Check a scenario file and report every problem with its line
+    #[command(after_long_help = docs::VALIDATE_EXAMPLES)]
+    Validate {
+        /// Path to the scenario HCL file
+        scenario: PathBuf,
+    },
+    /// Rewrite deprecated constructs in a scenario file to the current format
+    #[command(after_long_help = docs::MIGRATE_EXAMPLES)]
+    Migrate {
+        /// Path to the scenario HCL file, rewritten in place
+        scenario: PathBuf,
+        /// Only list what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check a scenario for best-practice problems that validation allows
+    #[command(after_long_help = docs::LINT_EXAMPLES)]
+    Lint {
+        /// Path to the scenario HCL file
+        scenario: PathBuf,
+        /// Image architectures every image should provide
+        #[arg(long = "arch", default_values_t = DEFAULT_LINT_ARCHES.iter().map(ToString::to_string))]
+        arches: Vec<String>,
+        /// Warn when the VMs need more memory than this in total (MB)
+        #[arg(long, default_value_t = DEFAULT_MAX_TOTAL_MEMORY_MB)]
+        max_memory: u32,
+        /// Exit with an error when there are warnings (`--deny warnings`)
+        #[arg(long, value_parser = ["warnings"])]
+        deny: Option<String>,
+    },
+    /// Show the VMs, networks, images, and probes a scenario would create, without starting it
+    #[command(after_long_help = docs::PLAN_EXAMPLES)]
+    Plan {
+        /// Path to the scenario HCL file
+        scenario: PathBuf,
+    },
+    /// Boot a scenario VM, apply its provisioning, and save the disk as a golden image
+    #[command(after_long_help = docs::BAKE_EXAMPLES)]
+    Bake {
+        /// Path to the scenario HCL file
+        scenario: PathBuf,
+        /// VM to bake (defaults to the scenario's only VM)
+        #[arg(long)]
+        vm: Option<String>,
+        /// Where to write the compressed qcow2 (defaults to `<name>.qcow2`)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Name of the emitted image block (defaults to `<vm>-baked`)
+        #[arg(long)]
+        name: Option<String>,
+        /// URL the image will be published at (defaults to a file:// URL of the output)
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Manage the local image cache
+    Images {
+        #[command(subcommand)]
+        command: ImagesCommands,
+    },
+    /// Save who is doing the labs, stamped into run reports, certificates, and webhooks
+    #[command(after_long_help = docs::LOGIN_EXAMPLES)]
+    Login {
+        /// Full name
+        #[arg(long)]
+        name: Option<String>,
+        /// Email address
+        #[arg(long)]
+        email: Option<String>,
+        /// Course or class identifier, e.g. "linux-101-fall"
+        #[arg(long)]
+        class_id: Option<String>,
+    },
+    /// Forget the profile saved by `intar login`
+    Logout,
+    /// Sign a completion certificate for a completed run, or check one
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    #[command(after_long_help = docs::CERTIFICATE_EXAMPLES)]
+    Certificate {
+        #[command(subcommand)]
+        command: Option<CertificateCommands>,
+        /// Name of the completed run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(required = true, add = ArgValueCandidates::new(completions::run_names))]
+        run: Option<String>,
+        /// File holding the instructor's signing key
+        #[arg(long, required = true)]
+        key: Option<PathBuf>,
+        /// Who completed the run (defaults to the `intar login` profile, then the user name)
+        #[arg(long)]
+        learner: Option<String>,
+        /// Certificate file to write (defaults to <run>.certificate.json)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Pack a finished run's reports, logs, and cloud-init assets into a tar.zst
+    #[command(after_long_help = docs::ARCHIVE_EXAMPLES)]
+    Archive {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// Archive to write (defaults to <run>.tar.zst)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also include the VMs' disk overlays
+        #[arg(long)]
+        overlays: bool,
+        /// Leave out SSH keys and the keystrokes recorded in session casts
+        #[arg(long)]
+        redact: bool,
+    },
+    /// Summarize what intar exposes on this host: SSH forwards, socket directories, and who
+    /// may write to the agents
+    #[command(after_long_help = docs::DOCTOR_EXAMPLES)]
+    Doctor,
+    /// Print a JSON Schema of the scenario format for editors and CI
+    #[command(after_long_help = docs::SCHEMA_EXAMPLES)]
+    Schema,
+    /// List the built-in probe presets available as `use = "builtin/<name>"`
+    #[command(after_long_help = docs::PROBES_EXAMPLES)]
+    Probes,
+    /// Develop a scenario's probes outside a run
+    Probe {
+        #[command(subcommand)]
+        command: ProbeCommands,
+    },
+    /// Print a shell completion script, e.g. `source <(intar completions bash)`
+    #[command(after_long_help = docs::COMPLETIONS_EXAMPLES)]
+    Completions {
+        /// Shell to complete in
+        shell: Shell,
+    },
+    /// Write man pages for intar and its subcommands (used when packaging releases)
+    #[command(hide = true)]
+    Docs {
+        /// Directory to write the pages to
+        #[arg(long, default_value = "man")]
+        out_dir: PathBuf,
+    },
     /// View logs for a scenario run
+    #[command(after_long_help = docs::LOGS_EXAMPLES)]
     Logs {
         /// Name of the run (petname, e.g., "fluffy-tiger-1234")
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(completions::run_names))]
         run: Option<String>,
         /// Name of the VM
-        #[arg(short, long)]
+        #[arg(short = 'm', long, add = ArgValueCandidates::new(completions::vm_names))]
         vm: Option<String>,
-        /// Which log file to view (qemu, console, user-data, meta-data)
+        /// Which log file to view (qemu, console, user-data, meta-data, or actions for the
+        /// run's SSH action log)
         #[arg(short = 't', long, default_value = "console")]
         log_type: String,
     },
 }
 
+#[derive(Subcommand)]
+enum VmCommands {
+    /// Change a VM's memory through its virtio balloon
+    #[command(after_long_help = docs::VM_SCALE_EXAMPLES)]
+    Scale {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// Name of the VM
+        #[arg(add = ArgValueCandidates::new(completions::vm_names))]
+        vm: String,
+        /// New memory size in MB (at most the VM's configured memory)
+        #[arg(long)]
+        memory: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProbeCommands {
+    /// Evaluate one of a scenario's probes on this machine or an existing host over ssh
+    #[command(after_long_help = docs::PROBE_RUN_EXAMPLES)]
+    Run {
+        /// Path to the scenario HCL file
+        scenario: PathBuf,
+        /// Name of the probe
+        #[arg(long)]
+        probe: String,
+        /// Host to evaluate it on, as ssh://user@host[:port]
+        #[arg(long, required_unless_present = "local")]
+        target: Option<String>,
+        /// Evaluate it on this machine
+        #[arg(long, conflicts_with = "target")]
+        local: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImagesCommands {
+    /// Share the image cache over HTTP with hosts that list this one in `downloads.peers`
+    #[command(after_long_help = docs::IMAGES_SERVE_EXAMPLES)]
+    Serve {
+        /// Address and port to listen on
+        #[arg(long, default_value_t = SocketAddr::from(([0, 0, 0, 0], PEER_CACHE_PORT)))]
+        listen: SocketAddr,
+    },
+    /// Pack cached images and their checksums into a tar file for machines without network
+    #[command(after_long_help = docs::IMAGES_EXPORT_EXAMPLES)]
+    Export {
+        /// Tar file to write
+        output: PathBuf,
+        /// Only include the cached images this scenario uses
+        #[arg(long)]
+        scenario: Option<PathBuf>,
+    },
+    /// Add the images of a tar file from `intar images export` to the image cache
+    #[command(after_long_help = docs::IMAGES_IMPORT_EXAMPLES)]
+    Import {
+        /// Tar file to read
+        bundle: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CertificateCommands {
+    /// Check that a certificate is unaltered and was signed with the given key
+    Verify {
+        /// Certificate file from `intar certificate`
+        certificate: PathBuf,
+        /// File holding the instructor's signing key
+        #[arg(long)]
+        key: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum NetCommands {
+    /// Show packet and byte counters per VM on the shared LAN switch
+    #[command(after_long_help = docs::NET_STATS_EXAMPLES)]
+    Stats {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+    },
+    /// Start or stop writing the shared LAN's traffic to pcap files
+    #[command(after_long_help = docs::NET_CAPTURE_EXAMPLES)]
+    Capture {
+        /// Name of the run (petname, e.g., "fluffy-tiger-1234")
+        #[arg(add = ArgValueCandidates::new(completions::run_names))]
+        run: String,
+        /// File to write (defaults to capture.pcap in the run directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Start a new file once the current one reaches this many MB
+        #[arg(long, default_value_t = 100)]
+        max_size: u64,
+        /// Rotated files to keep, including the current one
+        #[arg(long, default_value_t = 5)]
+        files: u32,
+        /// Stop the running capture instead
+        #[arg(long, conflicts_with_all = ["output", "max_size", "files"])]
+        stop: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let _log_guard = init_logging();
-
+    // Answers the completion script's callbacks before anything is written to stdout.
+    CompleteEnv::with_factory(Cli::command)
+        .var(completions::COMPLETE_VAR)
+        .complete();
     let cli = Cli::parse();
+    let _log_guard = init_logging(&cli.log);
+    if let Some(language) = &cli.lang {
+        set_language_override(language);
+    }
 
     match cli.command {
-        Commands::Start { scenario } => {
-            commands::start(scenario).await?;
+        Commands::Start {
+            scenario,
+            args,
+            host,
+        } => {
+            commands::start(scenario, args, &host).await?;
+        }
+        Commands::Attach {
+            run,
+            keep_on_exit,
+            auto_balloon,
+        } => {
+            commands::attach(&run, keep_on_exit, auto_balloon).await?;
+        }
+        Commands::MigrateRun { run, to } => {
+            commands::migrate_run(&run, &to).await?;
+        }
+        Commands::MigrateReceive { run, prepare } => {
+            commands::migrate_receive(&run, prepare).await?;
+        }
+        Commands::RemoteServe => {
+            commands::remote_serve().await?;
+        }
+        Commands::Vm {
+            command: VmCommands::Scale { run, vm, memory },
+        } => {
+            commands::vm_scale(&run, &vm, memory).await?;
+        }
+        Commands::Net {
+            command: NetCommands::Stats { run },
+        } => {
+            commands::net_stats(&run)?;
+        }
+        Commands::Net {
+            command:
+                NetCommands::Capture {
+                    run,
+                    output,
+                    max_size,
+                    files,
+                    stop,
+                },
+        } => {
+            if stop {
+                commands::net_capture_stop(&run)?;
+            } else {
+                commands::net_capture(&run, output.as_deref(), max_size, files)?;
+            }
         }
         Commands::Ssh {
             vm_name,
             run,
+            user,
             command,
         } => {
-            commands::ssh(&vm_name, run.as_deref(), command.as_deref())?;
+            commands::ssh(&vm_name, run.as_deref(), &user, command.as_deref())?;
+        }
+        Commands::Console { vm_name, run } => {
+            commands::console(&vm_name, run.as_deref()).await?;
+        }
+        Commands::Kubeconfig { run, vm } => {
+            commands::kubeconfig(&run, vm.as_deref()).await?;
+        }
+        Commands::Inventory { run, format } => {
+            commands::inventory(&run, &format)?;
+        }
+        Commands::Workspace { run, multiplexer } => {
+            commands::workspace(&run, multiplexer)?;
+        }
+        Commands::Trigger { run, step } => {
+            commands::trigger(&run, &step)?;
+        }
+        Commands::Submit { flag, run } => {
+            commands::submit(&flag, run.as_deref())?;
         }
         Commands::List { dir } => {
             commands::list(&dir)?;
         }
+        Commands::Validate { scenario } => {
+            commands::validate(&scenario)?;
+        }
+        Commands::Migrate { scenario, dry_run } => {
+            commands::migrate(&scenario, dry_run)?;
+        }
+        Commands::Lint {
+            scenario,
+            arches,
+            max_memory,
+            deny,
+        } => {
+            let options = LintOptions {
+                arches,
+                max_total_memory_mb: max_memory,
+            };
+            commands::lint(&scenario, &options, deny.is_some())?;
+        }
+        Commands::Plan { scenario } => {
+            commands::plan(&scenario).await?;
+        }
+        Commands::Bake {
+            scenario,
+            vm,
+            output,
+            name,
+            url,
+        } => {
+            commands::bake(&scenario, vm.as_deref(), output, name, url).await?;
+        }
+        Commands::Images {
+            command: ImagesCommands::Serve { listen },
+        } => {
+            commands::images_serve(listen).await?;
+        }
+        Commands::Images {
+            command: ImagesCommands::Export { output, scenario },
+        } => {
+            commands::images_export(&output, scenario.as_deref())?;
+        }
+        Commands::Images {
+            command: ImagesCommands::Import { bundle },
+        } => {
+            commands::images_import(&bundle)?;
+        }
+        Commands::Login {
+            name,
+            email,
+            class_id,
+        } => {
+            commands::login(LearnerProfile {
+                name,
+                email,
+                class_id,
+            })?;
+        }
+        Commands::Logout => {
+            commands::logout()?;
+        }
+        Commands::Certificate {
+            command: Some(CertificateCommands::Verify { certificate, key }),
+            ..
+        } => {
+            commands::certificate_verify(&certificate, &key)?;
+        }
+        Commands::Certificate {
+            command: None,
+            run,
+            key,
+            learner,
+            output,
+        } => {
+            let (Some(run), Some(key)) = (run, key) else {
+                anyhow::bail!("intar certificate needs a run and --key");
+            };
+            commands::certificate(&run, &key, learner.as_deref(), output.as_deref())?;
+        }
+        Commands::Archive {
+            run,
+            output,
+            overlays,
+            redact,
+        } => {
+            commands::archive(&run, output.as_deref(), overlays, redact)?;
+        }
+        Commands::Doctor => {
+            commands::doctor()?;
+        }
+        Commands::Schema => {
+            commands::schema()?;
+        }
+        Commands::Probes => {
+            commands::probes()?;
+        }
+        Commands::Probe {
+            command:
+                ProbeCommands::Run {
+                    scenario,
+                    probe,
+                    target,
+                    ..
+                },
+        } => {
+            commands::probe_run(&scenario, &probe, target.as_deref())?;
+        }
+        Commands::Docs { out_dir } => {
+            docs::write_man_pages(Cli::command(), &out_dir)?;
+        }
+        Commands::Completions { shell } => {
+            completions::print_script(shell)?;
+        }
         Commands::Logs { run, vm, log_type } => {
             commands::logs(run.as_deref(), vm.as_deref(), &log_type)?;
         }
@@ -92,16 +758,50 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn parse_ttl(value: &str) -> Result<Duration, String> {
+    parse_duration_secs(value)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("invalid duration '{value}' (expected e.g. 90m, 4h)"))
+}
+
 fn intar_log_dir() -> anyhow::Result<std::path::PathBuf> {
-    let state_dir = dirs::state_dir()
-        .or_else(dirs::data_local_dir)
-        .ok_or_else(|| anyhow::anyhow!("state directory not found"))?;
-    Ok(state_dir.join("intar").join("logs"))
+    Ok(IntarDirs::new()?.logs_dir())
+}
+
+fn parse_log_filter(value: &str) -> Result<String, String> {
+    for directive in value.split(',').filter(|d| !d.trim().is_empty()) {
+        directive
+            .trim()
+            .parse::<Directive>()
+            .map_err(|e| format!("invalid directive '{directive}': {e}"))?;
+    }
+    Ok(value.to_string())
+}
+
+/// RUST_LOG, with `-v`/`-q` overriding its default level and `--log-filter` on top.
+fn log_env_filter(args: &LogArgs) -> EnvFilter {
+    let level = match (args.quiet, args.verbose) {
+        (true, _) => Some(LevelFilter::WARN),
+        (false, 0) => None,
+        (false, 1) => Some(LevelFilter::DEBUG),
+        (false, _) => Some(LevelFilter::TRACE),
+    };
+    let mut filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy();
+    if let Some(level) = level {
+        filter = filter.add_directive(level.into());
+    }
+    let directives = args.log_filter.as_deref().unwrap_or_default().split(',');
+    for directive in directives.filter_map(|d| d.trim().parse::<Directive>().ok()) {
+        filter = filter.add_directive(directive);
+    }
+    filter
 }
 
-fn init_logging() -> Option<WorkerGuard> {
-    let env_filter = tracing_subscriber::EnvFilter::from_default_env()
-        .add_directive(tracing::Level::INFO.into());
+fn init_logging(args: &LogArgs) -> Option<WorkerGuard> {
+    let env_filter = log_env_filter(args);
 
     if let Ok(log_dir) = intar_log_dir()
         && std::fs::create_dir_all(&log_dir).is_ok()
@@ -115,10 +815,19 @@ fn init_logging() -> Option<WorkerGuard> {
         {
             let file_appender = rolling::never(&log_dir, "intar.log");
             let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
-            tracing_subscriber::fmt()
-                .with_env_filter(env_filter)
-                .with_ansi(false)
-                .with_writer(non_blocking)
+            let mirror = args.log_stderr.then(|| {
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(std::io::stderr)
+            });
+            tracing_subscriber::registry()
+                .with(env_filter)
+                .with(
+                    tracing_subscriber::fmt::layer()
+                        .with_ansi(false)
+                        .with_writer(non_blocking),
+                )
+                .with(mirror)
                 .init();
             return Some(guard);
         }
@@ -0,0 +1,69 @@
+# Probe presets shipped with intar, used as `use = "builtin/<name>"` in a probe block.
+# Attributes set next to `use` override the preset's.
+
+probe "nginx-running" {
+  type        = "service"
+  description = "nginx is running"
+  service     = "nginx"
+  state       = "running"
+}
+
+probe "nginx-enabled" {
+  type        = "service"
+  description = "nginx starts at boot"
+  service     = "nginx"
+  state       = "enabled"
+}
+
+probe "service-running" {
+  type        = "service"
+  description = "A service is running (set `service`)"
+  state       = "running"
+}
+
+probe "sshd-running" {
+  type        = "service"
+  description = "The SSH daemon is running"
+  service     = "ssh"
+  state       = "running"
+}
+
+probe "ssh-hardened" {
+  type        = "command"
+  description = "SSH refuses root and password logins"
+  cmd         = "sshd -T | grep -qx 'permitrootlogin no' && sshd -T | grep -qx 'passwordauthentication no'"
+  exit_code   = 0
+}
+
+probe "port-listening" {
+  type        = "port"
+  description = "A TCP port is listening (set `port`)"
+  state       = "listening"
+}
+
+probe "http-ok" {
+  type        = "http"
+  description = "The local web server answers 200"
+  url         = "http://localhost/"
+  status      = 200
+}
+
+probe "docker-running" {
+  type        = "service"
+  description = "Docker is running"
+  service     = "docker"
+  state       = "running"
+}
+
+probe "disk-space-ok" {
+  type        = "command"
+  description = "The root filesystem is less than 90% full"
+  cmd         = "[ \"$(df --output=pcent / | tail -n 1 | tr -dc 0-9)\" -lt 90 ]"
+  exit_code   = 0
+}
+
+probe "k8s-node-ready" {
+  type           = "k8s_nodes_ready"
+  description    = "The Kubernetes node is Ready"
+  expected_ready = 1
+}
@@ -0,0 +1,147 @@
+use crate::CoreError;
+use std::fmt;
+use std::ops::Range;
+
+/// A scenario problem, located in the HCL source where possible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Scenario file, set when parsing from a path.
+    pub file: Option<String>,
+    /// 1-based line and column.
+    pub location: Option<(usize, usize)>,
+    /// Enclosing block, e.g. `vm 'web'` or `vm 'web' > cloud_init`.
+    pub context: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            file: None,
+            location: None,
+            context: None,
+            message: message.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_location(mut self, location: Option<(usize, usize)>) -> Self {
+        self.location = location;
+        self
+    }
+
+    #[must_use]
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+}
+
+impl From<CoreError> for Diagnostic {
+    fn from(err: CoreError) -> Self {
+        match err {
+            CoreError::InvalidScenario(message) | CoreError::HclParse(message) => {
+                Self::new(message)
+            }
+            other => Self::new(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+        if let Some((line, column)) = self.location {
+            write!(f, "{line}:{column}:")?;
+        }
+        if self.file.is_some() || self.location.is_some() {
+            f.write_str(" ")?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, "{context}: ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Every problem found while parsing a scenario, in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// Attribute every diagnostic not already tied to a file (e.g. an extended one) to `file`.
+    #[must_use]
+    pub fn in_file(mut self, file: &str) -> Self {
+        for diagnostic in &mut self.0 {
+            diagnostic.file.get_or_insert_with(|| file.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps byte offsets in a source file to 1-based line and column numbers.
+pub(crate) struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    pub(crate) fn locate(
+        &self,
+        source: &str,
+        span: Option<Range<usize>>,
+    ) -> Option<(usize, usize)> {
+        let offset = span?.start;
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = source.get(line_start..offset)?.chars().count() + 1;
+        Some((line, column))
+    }
+}
+
+/// The candidate closest to `name`, if it is a plausible typo.
+pub(crate) fn did_you_mean<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(2);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
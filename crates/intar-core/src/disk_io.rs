@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+
+/// A VM's `disk_io` block: how QEMU backs the guest's main disk. Unset settings get defaults
+/// that suit the host, see [`DiskIo::drive_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskIo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<DiskCache>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aio: Option<DiskAio>,
+    /// Pass the guest's TRIM through and store zeroed blocks sparsely, so the overlay shrinks
+    /// again when files are deleted.
+    pub discard: bool,
+    /// Limit of read and write operations per second, together.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iops: Option<u32>,
+    /// Limit of bytes read and written per second, together.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bps: Option<u64>,
+}
+
+/// QEMU's host page cache mode for a disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskCache {
+    /// Writes complete once in the host page cache; guest flushes reach the host disk.
+    Writeback,
+    /// Bypasses the host page cache (`O_DIRECT`); guest flushes reach the host disk.
+    None,
+    /// Every write reaches the host disk before completing.
+    Writethrough,
+    /// Bypasses the host page cache and every write reaches the host disk.
+    Directsync,
+    /// Ignores guest flushes. Fastest, and fine for throwaway lab disks.
+    Unsafe,
+}
+
+/// QEMU's asynchronous I/O backend for a disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskAio {
+    /// A pool of I/O threads; works everywhere.
+    Threads,
+    /// Linux native AIO; needs a cache mode that bypasses the page cache.
+    Native,
+    /// Linux io_uring; needs a QEMU built with it.
+    IoUring,
+}
+
+impl DiskCache {
+    /// The value as scenarios and QEMU spell it.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Writeback => "writeback",
+            Self::None => "none",
+            Self::Writethrough => "writethrough",
+            Self::Directsync => "directsync",
+            Self::Unsafe => "unsafe",
+        }
+    }
+
+    /// Parse a `cache` attribute.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            Self::Writeback,
+            Self::None,
+            Self::Writethrough,
+            Self::Directsync,
+            Self::Unsafe,
+        ]
+        .into_iter()
+        .find(|cache| cache.as_str() == value)
+    }
+
+    /// Whether I/O bypasses the host page cache.
+    #[must_use]
+    pub fn is_direct(self) -> bool {
+        matches!(self, Self::None | Self::Directsync)
+    }
+}
+
+impl DiskAio {
+    /// The value as scenarios and QEMU spell it.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Threads => "threads",
+            Self::Native => "native",
+            Self::IoUring => "io_uring",
+        }
+    }
+
+    /// Parse an `aio` attribute.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Threads, Self::Native, Self::IoUring]
+            .into_iter()
+            .find(|aio| aio.as_str() == value)
+    }
+}
+
+impl Default for DiskIo {
+    /// Settings every VM gets without a `disk_io` block.
+    fn default() -> Self {
+        Self {
+            cache: None,
+            aio: None,
+            discard: true,
+            iops: None,
+            bps: None,
+        }
+    }
+}
+
+impl DiskIo {
+    /// Check the settings fit together.
+    ///
+    /// # Errors
+    /// Returns a message naming the offending setting.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.aio == Some(DiskAio::Native) && !self.cache.is_some_and(DiskCache::is_direct) {
+            return Err("aio 'native' needs cache 'none' or 'directsync'".into());
+        }
+        if self.iops == Some(0) || self.bps == Some(0) {
+            return Err("iops and bps limits must be > 0".into());
+        }
+        Ok(())
+    }
+
+    /// The cache mode the disk gets. Under emulation the guest is slow enough that flushes
+    /// dominate, so they are skipped; otherwise writeback, which lets the host page cache
+    /// absorb writes on every host while still honouring guest flushes.
+    #[must_use]
+    pub fn effective_cache(&self, emulated: bool) -> DiskCache {
+        self.cache.unwrap_or(if emulated {
+            DiskCache::Unsafe
+        } else {
+            DiskCache::Writeback
+        })
+    }
+
+    /// The AIO backend the disk gets: threads unless set, since native AIO only helps without
+    /// the page cache and io_uring is missing from many distribution builds of QEMU.
+    #[must_use]
+    pub fn effective_aio(&self) -> DiskAio {
+        self.aio.unwrap_or(DiskAio::Threads)
+    }
+
+    /// The options to append to the main disk's `-drive`, starting with a comma.
+    #[must_use]
+    pub fn drive_options(&self, emulated: bool) -> String {
+        let mut options = format!(
+            ",cache={},aio={}",
+            self.effective_cache(emulated).as_str(),
+            self.effective_aio().as_str()
+        );
+        if self.discard {
+            options.push_str(",discard=unmap,detect-zeroes=unmap");
+        }
+        if let Some(iops) = self.iops {
+            options.push_str(&format!(",throttling.iops-total={iops}"));
+        }
+        if let Some(bps) = self.bps {
+            options.push_str(&format!(",throttling.bps-total={bps}"));
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drive_options() {
+        assert_eq!(
+            DiskIo::default().drive_options(false),
+            ",cache=writeback,aio=threads,discard=unmap,detect-zeroes=unmap"
+        );
+        assert_eq!(
+            DiskIo::default().drive_options(true),
+            ",cache=unsafe,aio=threads,discard=unmap,detect-zeroes=unmap"
+        );
+
+        let throttled = DiskIo {
+            cache: Some(DiskCache::Writethrough),
+            aio: None,
+            discard: false,
+            iops: Some(500),
+            bps: Some(50_000_000),
+        };
+        assert_eq!(
+            throttled.drive_options(true),
+            ",cache=writethrough,aio=threads,throttling.iops-total=500,\
+             throttling.bps-total=50000000"
+        );
+    }
+
+    #[test]
+    fn test_validate_disk_io() {
+        let native = DiskIo {
+            aio: Some(DiskAio::Native),
+            ..DiskIo::default()
+        };
+        assert!(native.validate().unwrap_err().contains("cache 'none'"));
+        let direct = DiskIo {
+            cache: Some(DiskCache::None),
+            ..native
+        };
+        assert!(direct.validate().is_ok());
+
+        let unlimited = DiskIo {
+            iops: Some(0),
+            ..DiskIo::default()
+        };
+        assert!(unlimited.validate().is_err());
+        assert!(DiskIo::default().validate().is_ok());
+    }
+}
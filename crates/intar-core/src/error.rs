@@ -1,3 +1,4 @@
+use crate::Diagnostics;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,6 +9,9 @@ pub enum CoreError {
     #[error("Invalid scenario: {0}")]
     InvalidScenario(String),
 
+    #[error("{0}")]
+    Diagnostics(Diagnostics),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
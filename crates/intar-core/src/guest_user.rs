@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+/// Name of the account every VM gets, which intar logs in as and records sessions of.
+pub const DEFAULT_GUEST_USER: &str = "user";
+
+/// A VM's `user` block: a guest account cloud-init sets up next to [`DEFAULT_GUEST_USER`],
+/// e.g. an unprivileged `app` user or a locked `root`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuestUser {
+    pub name: String,
+    /// Login password in plain text; without one, password logins are locked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Authorized SSH keys besides the run's key, which `intar ssh --user` logs in with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_keys: Vec<String>,
+    #[serde(default)]
+    pub sudo: SudoPolicy,
+    /// Login shell; when unset, the shell that records sessions for `user`, running bash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Supplementary groups, created if missing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// No password and no SSH keys, not even the run's, so nobody logs in as the user.
+    #[serde(default)]
+    pub locked: bool,
+}
+
+/// What a guest user may run with `sudo`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoPolicy {
+    /// No sudo rule.
+    #[default]
+    None,
+    /// Every command, after entering the user's password.
+    Password,
+    /// Every command, without a password.
+    Nopasswd,
+}
+
+impl SudoPolicy {
+    /// The value as scenarios spell it.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Password => "password",
+            Self::Nopasswd => "nopasswd",
+        }
+    }
+
+    /// Parse a `sudo` attribute.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::None, Self::Password, Self::Nopasswd]
+            .into_iter()
+            .find(|policy| policy.as_str() == value)
+    }
+
+    /// The sudoers rule for the policy, if any.
+    #[must_use]
+    pub fn sudoers_rule(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Password => Some("ALL=(ALL) ALL"),
+            Self::Nopasswd => Some("ALL=(ALL) NOPASSWD:ALL"),
+        }
+    }
+}
+
+impl GuestUser {
+    /// A user named `name` with nothing else set.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            password: None,
+            ssh_keys: Vec::new(),
+            sudo: SudoPolicy::None,
+            shell: None,
+            groups: Vec::new(),
+            locked: false,
+        }
+    }
+
+    /// Whether `intar ssh --user` can log in as the user with the run's key.
+    #[must_use]
+    pub fn accepts_run_key(&self) -> bool {
+        !self.locked
+    }
+
+    /// Check the settings fit together.
+    ///
+    /// # Errors
+    /// Returns a message naming the offending setting.
+    pub fn validate(&self) -> Result<(), String> {
+        let name_ok = self
+            .name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+            && self.name.len() <= 32
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !name_ok {
+            return Err(
+                "name must start with a lowercase letter or '_' and use only lowercase letters, \
+                 digits, '-', and '_' (at most 32)"
+                    .into(),
+            );
+        }
+        if self.name == DEFAULT_GUEST_USER {
+            return Err(format!(
+                "'{DEFAULT_GUEST_USER}' is the account intar logs in as and cannot be redefined"
+            ));
+        }
+        if self.locked && (self.password.is_some() || !self.ssh_keys.is_empty()) {
+            return Err("a locked user cannot have a password or ssh_keys".into());
+        }
+        if self.sudo == SudoPolicy::Password && self.password.is_none() {
+            return Err("sudo 'password' needs a password".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_guest_user() {
+        let app = GuestUser {
+            password: Some("hunter2".into()),
+            sudo: SudoPolicy::Password,
+            ..GuestUser::new("app")
+        };
+        assert!(app.validate().is_ok());
+        assert!(app.accepts_run_key());
+
+        let root = GuestUser {
+            locked: true,
+            ..GuestUser::new("root")
+        };
+        assert!(root.validate().is_ok());
+        assert!(!root.accepts_run_key());
+
+        let locked_with_password = GuestUser {
+            locked: true,
+            ..app.clone()
+        };
+        assert!(
+            locked_with_password
+                .validate()
+                .unwrap_err()
+                .contains("locked")
+        );
+        let sudo_without_password = GuestUser {
+            sudo: SudoPolicy::Password,
+            ..GuestUser::new("app")
+        };
+        assert!(sudo_without_password.validate().is_err());
+        assert!(GuestUser::new("user").validate().is_err());
+        assert!(GuestUser::new("App").validate().is_err());
+        assert!(GuestUser::new("9lives").validate().is_err());
+    }
+
+    #[test]
+    fn test_sudo_policy() {
+        assert_eq!(SudoPolicy::parse("nopasswd"), Some(SudoPolicy::Nopasswd));
+        assert_eq!(SudoPolicy::parse("always"), None);
+        assert_eq!(SudoPolicy::None.sudoers_rule(), None);
+        assert_eq!(SudoPolicy::Password.sudoers_rule(), Some("ALL=(ALL) ALL"));
+    }
+}
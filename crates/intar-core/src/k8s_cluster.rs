@@ -0,0 +1,304 @@
+use crate::{ProbeDefinition, ProbePhase, StepTrigger, VmAction, VmDefinition, VmStep};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Admin kubeconfig k3s writes on every server.
+pub const K3S_KUBECONFIG: &str = "/etc/rancher/k3s/k3s.yaml";
+
+/// Port of the Kubernetes API on each server.
+pub const K3S_API_PORT: u16 = 6443;
+
+/// A `k8s_cluster` block: k3s servers and agents on the shared LAN. The parser expands it into
+/// `<name>-server-<n>` and `<name>-agent-<n>` VMs that bootstrap and join the cluster at boot,
+/// plus boot probes that wait for every node to be Ready.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct K8sCluster {
+    pub name: String,
+    pub servers: u32,
+    #[serde(default)]
+    pub agents: u32,
+    /// k3s release to install (e.g. `v1.31.4+k3s1`); the stable channel when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl K8sCluster {
+    /// VM name of the `index`th server, counting from 1. Server 1 initializes the cluster.
+    #[must_use]
+    pub fn server_name(&self, index: u32) -> String {
+        format!("{}-server-{index}", self.name)
+    }
+
+    /// VM name of the `index`th agent, counting from 1.
+    #[must_use]
+    pub fn agent_name(&self, index: u32) -> String {
+        format!("{}-agent-{index}", self.name)
+    }
+
+    /// Host name of the cluster API, resolving to server 1 on every VM.
+    #[must_use]
+    pub fn api_host(&self) -> String {
+        format!("{}-api", self.name)
+    }
+
+    /// Template variable holding the join token generated for each run.
+    #[must_use]
+    pub fn token_var(&self) -> String {
+        format!("clusters.{}.token", self.name)
+    }
+
+    /// Every node's VM name, servers first.
+    #[must_use]
+    pub fn nodes(&self) -> Vec<String> {
+        (1..=self.servers)
+            .map(|i| self.server_name(i))
+            .chain((1..=self.agents).map(|i| self.agent_name(i)))
+            .collect()
+    }
+
+    /// Node VMs built from `template` (image and sizing), each with a `k3s-bootstrap` boot step
+    /// and the cluster's default probes.
+    pub(crate) fn node_vms(&self, template: &VmDefinition) -> Vec<VmDefinition> {
+        let server = |index: u32| {
+            let role = if index == 1 {
+                "server --cluster-init".to_string()
+            } else {
+                format!("server --server https://{}:{K3S_API_PORT}", self.api_host())
+            };
+            let mut probes = vec![self.probe_name("k3s-server"), self.probe_name("api")];
+            if index == 1 {
+                probes.push(self.probe_name("nodes-ready"));
+            }
+            self.node_vm(template, self.server_name(index), &role, probes)
+        };
+        let agent = |index: u32| {
+            let role = format!("agent --server https://{}:{K3S_API_PORT}", self.api_host());
+            let probes = vec![self.probe_name("k3s-agent")];
+            self.node_vm(template, self.agent_name(index), &role, probes)
+        };
+
+        (1..=self.servers)
+            .map(server)
+            .chain((1..=self.agents).map(agent))
+            .collect()
+    }
+
+    /// Boot probes referenced by the node VMs.
+    pub(crate) fn boot_probes(&self) -> Vec<ProbeDefinition> {
+        let probe = |suffix: &str,
+                     probe_type: &str,
+                     description: String,
+                     config: Vec<(&str, serde_json::Value)>| ProbeDefinition {
+            name: self.probe_name(suffix),
+            probe_type: probe_type.into(),
+            description: Some(description),
+            description_translations: BTreeMap::new(),
+            phase: ProbePhase::Boot,
+            config: config
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect::<HashMap<_, _>>(),
+        };
+        let service = |unit: &str| {
+            vec![
+                ("service", serde_json::Value::from(unit)),
+                ("state", "running".into()),
+            ]
+        };
+
+        let mut probes = vec![
+            probe(
+                "k3s-server",
+                "service",
+                format!("k3s should be running on each '{}' server", self.name),
+                service("k3s"),
+            ),
+            probe(
+                "api",
+                "port",
+                format!(
+                    "The Kubernetes API should be listening on {K3S_API_PORT} on each '{}' server",
+                    self.name
+                ),
+                vec![("port", K3S_API_PORT.into()), ("state", "listening".into())],
+            ),
+            probe(
+                "nodes-ready",
+                "k8s_nodes_ready",
+                format!(
+                    "All {} nodes of '{}' should report Ready",
+                    self.servers + self.agents,
+                    self.name
+                ),
+                vec![
+                    ("kubeconfig", K3S_KUBECONFIG.into()),
+                    ("expected_ready", (self.servers + self.agents).into()),
+                ],
+            ),
+        ];
+        if self.agents > 0 {
+            probes.push(probe(
+                "k3s-agent",
+                "service",
+                format!("k3s-agent should be running on each '{}' agent", self.name),
+                service("k3s-agent"),
+            ));
+        }
+        probes
+    }
+
+    fn probe_name(&self, suffix: &str) -> String {
+        format!("{}-{suffix}", self.name)
+    }
+
+    fn node_vm(
+        &self,
+        template: &VmDefinition,
+        name: String,
+        role: &str,
+        probes: Vec<String>,
+    ) -> VmDefinition {
+        let script = format!(
+            "{}\nK3S_ROLE=\"{role}\"\n{}",
+            self.bootstrap_prelude(),
+            BOOTSTRAP_INSTALL
+        );
+        VmDefinition {
+            name,
+            steps: vec![VmStep {
+                name: "k3s-bootstrap".into(),
+                actions: vec![
+                    VmAction::FileWrite {
+                        path: BOOTSTRAP_PATH.into(),
+                        content: script,
+                        permissions: Some("0755".into()),
+                    },
+                    VmAction::Command {
+                        cmd: BOOTSTRAP_PATH.into(),
+                    },
+                ],
+                trigger: StepTrigger::Boot,
+            }],
+            random_steps: Vec::new(),
+            probes,
+            ..template.clone()
+        }
+    }
+
+    fn bootstrap_prelude(&self) -> String {
+        let api = self.api_host();
+        let version = self
+            .version
+            .as_deref()
+            .map(|version| format!("export INSTALL_K3S_VERSION=\"{version}\"\n"))
+            .unwrap_or_default();
+        format!(
+            "#!/usr/bin/env bash\nset -euo pipefail\nexec > /var/log/k3s-bootstrap.log 2>&1\n\n\
+             export K3S_TOKEN=\"{{{{{token}}}}}\"\n{version}\
+             API_SANS=\"--tls-san {api} --tls-san {api}.intar\"",
+            token = self.token_var()
+        )
+    }
+}
+
+const BOOTSTRAP_PATH: &str = "/usr/local/bin/k3s-bootstrap.sh";
+
+/// Shared tail of every node's bootstrap script. Expects `K3S_TOKEN`, `API_SANS`, and
+/// `K3S_ROLE` (`server ...` or `agent ...`).
+const BOOTSTRAP_INSTALL: &str = r#"
+# Nodes talk over the shared LAN; a single-VM cluster only has the management NIC.
+CLUSTER_IF="enp0s2"
+[ -d "/sys/class/net/$CLUSTER_IF" ] || CLUSTER_IF="enp0s1"
+ip link set "$CLUSTER_IF" up || true
+
+mkdir -p /etc/rancher/k3s
+cat > /etc/rancher/k3s/registries.yaml <<'REGISTRY_EOF'
+mirrors:
+  docker.io:
+  registry.k8s.io:
+REGISTRY_EOF
+
+# Most Ubuntu cloud images ship curl; install only if missing to avoid slow apt runs.
+if ! command -v curl >/dev/null 2>&1; then
+  export DEBIAN_FRONTEND=noninteractive
+  apt-get update -qq
+  apt-get install -y curl
+fi
+
+# Wait briefly for the cluster NIC to get its static address.
+NODE_IP=""
+for _ in $(seq 1 30); do
+  NODE_IP=$(ip -o -4 addr show dev "$CLUSTER_IF" 2>/dev/null | awk '{print $4}' | cut -d/ -f1 | head -n1 || true)
+  [ -n "$NODE_IP" ] && break
+  sleep 1
+done
+[ -n "$NODE_IP" ] || { echo "No IPv4 on $CLUSTER_IF"; exit 1; }
+
+ARGS="$K3S_ROLE --flannel-iface $CLUSTER_IF --node-ip $NODE_IP"
+case "$K3S_ROLE" in
+  server*) ARGS="$ARGS --embedded-registry --advertise-address $NODE_IP --tls-san $HOSTNAME --tls-san $HOSTNAME.intar $API_SANS --write-kubeconfig-mode 0644" ;;
+esac
+
+curl -sfL https://get.k3s.io | INSTALL_K3S_EXEC="$ARGS" sh -
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiskIo;
+
+    #[test]
+    fn test_node_vms_and_probes() {
+        let cluster = K8sCluster {
+            name: "lab".into(),
+            servers: 2,
+            agents: 1,
+            version: Some("v1.31.4+k3s1".into()),
+        };
+        let template = VmDefinition {
+            name: String::new(),
+            cpu: 2,
+            memory: 2048,
+            disk: 10,
+            image: "ubuntu".into(),
+            cloud_init: None,
+            steps: Vec::new(),
+            random_steps: Vec::new(),
+            probes: Vec::new(),
+            ipv6: false,
+            track_changes: Vec::new(),
+            disk_io: DiskIo::default(),
+            users: Vec::new(),
+            ssh_password_auth: false,
+            console_autologin: None,
+            wait_for: Vec::new(),
+        };
+
+        let vms = cluster.node_vms(&template);
+        let names: Vec<&str> = vms.iter().map(|vm| vm.name.as_str()).collect();
+        assert_eq!(names, vec!["lab-server-1", "lab-server-2", "lab-agent-1"]);
+        assert_eq!(cluster.nodes(), names);
+        assert!(vms.iter().all(|vm| vm.image == "ubuntu" && vm.cpu == 2));
+        assert_eq!(
+            vms[0].probes,
+            vec!["lab-k3s-server", "lab-api", "lab-nodes-ready"]
+        );
+        assert_eq!(vms[2].probes, vec!["lab-k3s-agent"]);
+
+        let script = |vm: &VmDefinition| match &vm.steps[0].actions[0] {
+            VmAction::FileWrite { content, .. } => content.clone(),
+            other => panic!("unexpected action {other:?}"),
+        };
+        let first = script(&vms[0]);
+        assert!(first.contains("K3S_TOKEN=\"{{clusters.lab.token}}\""));
+        assert!(first.contains("INSTALL_K3S_VERSION=\"v1.31.4+k3s1\""));
+        assert!(first.contains("K3S_ROLE=\"server --cluster-init\""));
+        assert!(script(&vms[1]).contains("K3S_ROLE=\"server --server https://lab-api:6443\""));
+        assert!(script(&vms[2]).contains("K3S_ROLE=\"agent --server https://lab-api:6443\""));
+
+        let probes = cluster.boot_probes();
+        assert!(probes.iter().all(|p| p.phase == ProbePhase::Boot));
+        let ready = probes.iter().find(|p| p.name == "lab-nodes-ready").unwrap();
+        assert_eq!(ready.config["expected_ready"], serde_json::json!(3));
+    }
+}
@@ -1,5 +1,27 @@
+mod diagnostics;
+mod disk_io;
 mod error;
+mod guest_user;
+mod k8s_cluster;
+mod lint;
+mod messages;
+mod migrate;
+mod mock_service;
+mod probe_library;
 mod scenario;
+mod schema;
+mod subnet;
 
+pub use diagnostics::*;
+pub use disk_io::*;
 pub use error::*;
+pub use guest_user::*;
+pub use k8s_cluster::*;
+pub use lint::*;
+pub use messages::*;
+pub use migrate::*;
+pub use mock_service::*;
+pub use probe_library::*;
 pub use scenario::*;
+pub use schema::scenario_json_schema;
+pub use subnet::*;
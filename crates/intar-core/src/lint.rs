@@ -0,0 +1,275 @@
+use crate::{Scenario, SystemctlAction, VmAction, VmStep};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Architectures checked for image sources unless the caller narrows them.
+pub const DEFAULT_LINT_ARCHES: &[&str] = &["amd64", "arm64"];
+
+/// Total VM memory above which a scenario is unlikely to run on a laptop.
+pub const DEFAULT_MAX_TOTAL_MEMORY_MB: u32 = 16 * 1024;
+
+/// Settings for [`Scenario::lint`].
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// Every image should have a source for each of these.
+    pub arches: Vec<String>,
+    pub max_total_memory_mb: u32,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            arches: DEFAULT_LINT_ARCHES
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            max_total_memory_mb: DEFAULT_MAX_TOTAL_MEMORY_MB,
+        }
+    }
+}
+
+/// A best-practice problem that does not stop the scenario from running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Stable code, e.g. `W001`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.code, self.message)
+    }
+}
+
+pub const UNUSED_PROBE: &str = "W001";
+pub const VM_WITHOUT_PROBES: &str = "W002";
+pub const VISIBLE_BREAK_STEP: &str = "W003";
+pub const MISSING_IMAGE_ARCH: &str = "W004";
+pub const LARGE_MEMORY_TOTAL: &str = "W005";
+pub const UNUSED_IMAGE: &str = "W006";
+pub const UNKNOWN_PROBE_PLUGIN: &str = "W007";
+
+impl Scenario {
+    /// Best-practice checks beyond [`Scenario::validate`], in a stable order.
+    #[must_use]
+    pub fn lint(&self, options: &LintOptions) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        let mut warn = |code, message: String| warnings.push(LintWarning { code, message });
+
+        let referenced: HashSet<&str> = self
+            .vms
+            .iter()
+            .flat_map(|vm| vm.probes.iter())
+            .chain(self.questions.iter().flat_map(|q| q.after.iter()))
+            .map(String::as_str)
+            .collect();
+        let mut probes: Vec<&str> = self.probes.keys().map(String::as_str).collect();
+        probes.sort_unstable();
+        for probe in probes.into_iter().filter(|p| !referenced.contains(p)) {
+            warn(
+                UNUSED_PROBE,
+                format!("probe '{probe}' is not referenced by any VM or question"),
+            );
+        }
+
+        for vm in self.vms.iter().filter(|vm| vm.probes.is_empty()) {
+            warn(
+                VM_WITHOUT_PROBES,
+                format!(
+                    "VM '{}' has no probes, so nothing checks its state",
+                    vm.name
+                ),
+            );
+        }
+
+        for vm in &self.vms {
+            let grouped = vm.random_steps.iter().flat_map(|g| g.steps.iter());
+            for step in vm.steps.iter().chain(grouped) {
+                if !step.is_hidden() && breaks_something(step) {
+                    warn(
+                        VISIBLE_BREAK_STEP,
+                        format!(
+                            "step '{}' on VM '{}' breaks the system but its script stays \
+                             readable in the guest; name it 'break-...' to hide it",
+                            step.name, vm.name
+                        ),
+                    );
+                }
+            }
+        }
+
+        let mut images: Vec<_> = self.images.values().collect();
+        images.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        for image in &images {
+            for arch in &options.arches {
+                if image.source_for_arch(arch).is_none() {
+                    warn(
+                        MISSING_IMAGE_ARCH,
+                        format!("image '{}' has no source for arch '{arch}'", image.name),
+                    );
+                }
+            }
+        }
+
+        let total_memory: u32 = self.vms.iter().map(|vm| vm.memory).sum();
+        if total_memory > options.max_total_memory_mb {
+            warn(
+                LARGE_MEMORY_TOTAL,
+                format!(
+                    "VMs need {total_memory} MB of memory in total (more than {} MB)",
+                    options.max_total_memory_mb
+                ),
+            );
+        }
+
+        for image in images {
+            if !self.vms.iter().any(|vm| vm.image == image.name) {
+                warn(
+                    UNUSED_IMAGE,
+                    format!("image '{}' is not used by any VM", image.name),
+                );
+            }
+        }
+
+        let mut plugin_probes: Vec<_> = self
+            .probes
+            .values()
+            .filter(|probe| probe.probe_type == "plugin")
+            .filter_map(|probe| Some((&probe.name, probe.config.get("plugin")?.as_str()?)))
+            .collect();
+        plugin_probes.sort_unstable();
+        for (probe, plugin) in plugin_probes {
+            if !self.probe_plugins.iter().any(|p| p.name == plugin) {
+                warn(
+                    UNKNOWN_PROBE_PLUGIN,
+                    format!(
+                        "probe '{probe}' runs plugin '{plugin}', which no probe_plugin block \
+                         installs; the image must already have it"
+                    ),
+                );
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Whether a step stops, removes, or rewrites something the learner has to repair.
+fn breaks_something(step: &VmStep) -> bool {
+    step.actions.iter().any(|action| {
+        matches!(
+            action,
+            VmAction::FileDelete { .. }
+                | VmAction::FileReplace { .. }
+                | VmAction::UserDelete { .. }
+                | VmAction::Systemctl {
+                    action: SystemctlAction::Stop | SystemctlAction::Disable,
+                    ..
+                }
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HCL: &str = r#"
+scenario "lint" {
+  description = "Lint checks"
+
+  image "ubuntu" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  image "debian" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/debian-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx" {
+    type    = "service"
+    service = "nginx"
+    state   = "running"
+  }
+
+  probe "unused" {
+    type    = "service"
+    service = "sshd"
+    state   = "running"
+  }
+
+  probe "replicas" {
+    type   = "plugin"
+    plugin = "db-replicas"
+  }
+
+  vm "web" {
+    image  = "ubuntu"
+    memory = 12288
+    probes = ["nginx", "replicas"]
+
+    step "stop-nginx" {
+      systemctl {
+        unit   = "nginx"
+        action = "stop"
+      }
+    }
+
+    step "break-config" {
+      file_delete {
+        path = "/etc/nginx/nginx.conf"
+      }
+    }
+  }
+
+  vm "db" {
+    image  = "ubuntu"
+    memory = 8192
+  }
+}
+"#;
+
+    #[test]
+    fn test_lint_reports_best_practice_warnings() {
+        let scenario = Scenario::parse(HCL).unwrap();
+        let codes: Vec<&str> = scenario
+            .lint(&LintOptions::default())
+            .iter()
+            .map(|w| w.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                UNUSED_PROBE,
+                VM_WITHOUT_PROBES,
+                VISIBLE_BREAK_STEP,
+                MISSING_IMAGE_ARCH,
+                MISSING_IMAGE_ARCH,
+                LARGE_MEMORY_TOTAL,
+                UNUSED_IMAGE,
+                UNKNOWN_PROBE_PLUGIN,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_respects_options() {
+        let scenario = Scenario::parse(HCL).unwrap();
+        let options = LintOptions {
+            arches: vec!["amd64".into()],
+            max_total_memory_mb: 32 * 1024,
+        };
+        let warnings = scenario.lint(&options);
+        assert!(warnings.iter().all(|w| w.code != MISSING_IMAGE_ARCH));
+        assert!(warnings.iter().all(|w| w.code != LARGE_MEMORY_TOTAL));
+    }
+}
@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Translations of user-facing English text into one language, keyed by the English text.
+///
+/// A key may contain `{name}` placeholders, e.g. `"Port {port} is not listening"`, to
+/// translate messages built at runtime such as probe results; the translation repeats the
+/// placeholders where the values belong. Text without a translation is shown in English.
+#[derive(Debug, Clone, Default)]
+pub struct MessageCatalog {
+    language: String,
+    exact: HashMap<String, String>,
+    templates: Vec<(Template, String)>,
+}
+
+/// A catalog key split at its placeholders: `literals` has one more entry than `names`.
+#[derive(Debug, Clone)]
+struct Template {
+    literals: Vec<String>,
+    names: Vec<String>,
+}
+
+impl MessageCatalog {
+    /// The built-in English interface, which translates nothing.
+    #[must_use]
+    pub fn english() -> Self {
+        Self {
+            language: "en".into(),
+            ..Self::default()
+        }
+    }
+
+    /// A catalog for `language` from English keys to translations.
+    #[must_use]
+    pub fn new(language: impl Into<String>, messages: HashMap<String, String>) -> Self {
+        let mut exact = HashMap::new();
+        let mut templates = Vec::new();
+        for (key, translation) in messages {
+            match Template::parse(&key) {
+                Some(template) => templates.push((template, translation)),
+                None => {
+                    exact.insert(key, translation);
+                }
+            }
+        }
+        // Most specific first, so "Port {port} is not listening" wins over "{what} failed".
+        templates.sort_by_key(|(template, _)| {
+            std::cmp::Reverse(template.literals.iter().map(String::len).sum::<usize>())
+        });
+        Self {
+            language: language.into(),
+            exact,
+            templates,
+        }
+    }
+
+    #[must_use]
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.exact.len() + self.templates.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The translation of fixed text, or `text` itself.
+    #[must_use]
+    pub fn get<'a>(&'a self, text: &'a str) -> &'a str {
+        self.exact.get(text).map_or(text, String::as_str)
+    }
+
+    /// The translation of a message that may have been built from a template key.
+    #[must_use]
+    pub fn translate<'a>(&self, message: &'a str) -> Cow<'a, str> {
+        if let Some(translation) = self.exact.get(message) {
+            return Cow::Owned(translation.clone());
+        }
+        for (template, translation) in &self.templates {
+            if let Some(values) = template.captures(message) {
+                let mut text = translation.clone();
+                for (name, value) in template.names.iter().zip(values) {
+                    text = text.replace(&format!("{{{name}}}"), value);
+                }
+                return Cow::Owned(text);
+            }
+        }
+        Cow::Borrowed(message)
+    }
+}
+
+impl Template {
+    /// `None` for keys without placeholders.
+    fn parse(key: &str) -> Option<Self> {
+        let mut literals = Vec::new();
+        let mut names = Vec::new();
+        let mut rest = key;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+                break;
+            };
+            let name = &rest[open + 1..close];
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            literals.push(rest[..open].to_string());
+            names.push(name.to_string());
+            rest = &rest[close + 1..];
+        }
+        if names.is_empty() {
+            return None;
+        }
+        literals.push(rest.to_string());
+        Some(Self { literals, names })
+    }
+
+    /// The placeholder values if `message` has the template's shape. Each value ends at the
+    /// first occurrence of the literal after it; the last runs to the final literal.
+    fn captures<'a>(&self, message: &'a str) -> Option<Vec<&'a str>> {
+        let (last, middle) = self.literals.split_last()?;
+        let mut rest = message.strip_prefix(middle.first()?.as_str())?;
+        rest = rest.strip_suffix(last.as_str())?;
+        let mut values = Vec::new();
+        for literal in &middle[1..] {
+            if literal.is_empty() {
+                return None;
+            }
+            let end = rest.find(literal.as_str())?;
+            values.push(&rest[..end]);
+            rest = &rest[end + literal.len()..];
+        }
+        values.push(rest);
+        Some(values)
+    }
+}
+
+/// The language part of a locale such as `de_DE.UTF-8` (giving `de_DE`). `C` and `POSIX`
+/// mean no particular language.
+#[must_use]
+pub fn locale_language(locale: &str) -> Option<&str> {
+    let language = locale.split(['.', '@']).next().unwrap_or_default().trim();
+    if language.is_empty() || language == "C" || language == "POSIX" {
+        None
+    } else {
+        Some(language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog(entries: &[(&str, &str)]) -> MessageCatalog {
+        MessageCatalog::new(
+            "de",
+            entries
+                .iter()
+                .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_exact_translation() {
+        let catalog = catalog(&[("Probes", "Prüfungen")]);
+        assert_eq!(catalog.get("Probes"), "Prüfungen");
+        assert_eq!(catalog.get("Logs"), "Logs");
+        assert_eq!(catalog.translate("Probes"), "Prüfungen");
+        assert_eq!(MessageCatalog::english().get("Probes"), "Probes");
+    }
+
+    #[test]
+    fn test_template_translation() {
+        let catalog = catalog(&[
+            (
+                "Service '{service}' is not running ({status})",
+                "Dienst '{service}' läuft nicht ({status})",
+            ),
+            ("Port {target} is listening", "Port {target} ist offen"),
+            ("{what} failed", "{what} fehlgeschlagen"),
+        ]);
+        assert_eq!(
+            catalog.translate("Service 'nginx' is not running (failed)"),
+            "Dienst 'nginx' läuft nicht (failed)"
+        );
+        assert_eq!(
+            catalog.translate("Port 80 on 10.0.0.1 is listening"),
+            "Port 80 on 10.0.0.1 ist offen"
+        );
+        assert_eq!(
+            catalog.translate("Download failed"),
+            "Download fehlgeschlagen"
+        );
+        assert_eq!(catalog.translate("Port 80 is closed"), "Port 80 is closed");
+    }
+
+    #[test]
+    fn test_template_needs_separators() {
+        assert!(Template::parse("no placeholders").is_none());
+        assert!(Template::parse("{not a name}").is_none());
+        let template = Template::parse("{a}{b}").unwrap();
+        assert_eq!(template.captures("xy"), None);
+    }
+
+    #[test]
+    fn test_locale_language() {
+        assert_eq!(locale_language("de_DE.UTF-8"), Some("de_DE"));
+        assert_eq!(locale_language("fr"), Some("fr"));
+        assert_eq!(locale_language("sr_RS@latin"), Some("sr_RS"));
+        assert_eq!(locale_language("C.UTF-8"), None);
+        assert_eq!(locale_language("POSIX"), None);
+        assert_eq!(locale_language(""), None);
+    }
+}
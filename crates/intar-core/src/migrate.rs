@@ -0,0 +1,151 @@
+use crate::diagnostics::LineIndex;
+use crate::schema::block_context;
+use crate::{CoreError, Diagnostic, Diagnostics};
+use hcl::edit::Span as _;
+use hcl::edit::structure::Body;
+use std::ops::Range;
+
+/// A string value the scenario format renamed: `from` as the `attribute` of `block` blocks is
+/// now spelled `to`.
+struct ValueRename {
+    block: &'static str,
+    attribute: &'static str,
+    from: &'static str,
+    to: &'static str,
+}
+
+/// Every rename [`migrate_scenario`] knows, oldest first. The parser keeps accepting the old
+/// spellings for a while; add an entry here whenever one is renamed.
+const VALUE_RENAMES: &[ValueRename] = &[ValueRename {
+    block: "probe",
+    attribute: "type",
+    from: "k8s_endpoints_nonempty",
+    to: "k8s_endpoints_non_empty",
+}];
+
+/// A scenario rewritten to the current format, with one note per change in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub source: String,
+    pub changes: Vec<Diagnostic>,
+}
+
+/// Rewrite the deprecated constructs in scenario `source` to the current format. Only the
+/// renamed text changes; comments, layout, and heredocs stay byte for byte as they were.
+///
+/// # Errors
+/// Returns `CoreError::Diagnostics` if `source` is not valid HCL.
+pub fn migrate_scenario(source: &str) -> Result<Migration, CoreError> {
+    let body = hcl::edit::parser::parse_body(source).map_err(|e| {
+        let location = e.location();
+        CoreError::Diagnostics(Diagnostics(vec![
+            Diagnostic::new(e.message()).with_location(Some((location.line(), location.column()))),
+        ]))
+    })?;
+    let lines = LineIndex::new(source);
+    let mut edits = Vec::new();
+    collect_edits(source, &lines, &body, None, &mut edits);
+    edits.sort_by_key(|(range, ..)| range.start);
+
+    let mut migrated = String::with_capacity(source.len());
+    let mut changes = Vec::with_capacity(edits.len());
+    let mut copied = 0;
+    for (range, replacement, change) in edits {
+        migrated.push_str(&source[copied..range.start]);
+        migrated.push_str(&replacement);
+        copied = range.end;
+        changes.push(change);
+    }
+    migrated.push_str(&source[copied..]);
+    Ok(Migration {
+        source: migrated,
+        changes,
+    })
+}
+
+fn collect_edits(
+    source: &str,
+    lines: &LineIndex,
+    body: &Body,
+    context: Option<&str>,
+    edits: &mut Vec<(Range<usize>, String, Diagnostic)>,
+) {
+    for block in body.blocks() {
+        let ident = block.ident.as_str();
+        let inner_context = match context {
+            _ if ident == "scenario" => None,
+            Some(context) => Some(format!("{context} > {}", block_context(block))),
+            None => Some(block_context(block)),
+        };
+        for rename in VALUE_RENAMES.iter().filter(|rename| rename.block == ident) {
+            let old = format!("\"{}\"", rename.from);
+            let Some(span) = block
+                .body
+                .get_attribute(rename.attribute)
+                .and_then(|attr| attr.span())
+            else {
+                continue;
+            };
+            let Some(start) = source
+                .get(span.clone())
+                .and_then(|attr| attr.split_once('='))
+                .filter(|(_, value)| value.trim_start().starts_with(&old))
+                .map(|(key, value)| {
+                    span.start + key.len() + 1 + value.len() - value.trim_start().len()
+                })
+            else {
+                continue;
+            };
+            let range = start..start + old.len();
+            let mut change = Diagnostic::new(format!(
+                "{} '{}' is now '{}'",
+                rename.attribute, rename.from, rename.to
+            ))
+            .with_location(lines.locate(source, Some(range.clone())));
+            if let Some(context) = &inner_context {
+                change = change.with_context(context);
+            }
+            edits.push((range, format!("\"{}\"", rename.to), change));
+        }
+        collect_edits(source, lines, &block.body, inner_context.as_deref(), edits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_scenario_renames_probe_type() {
+        let source = r#"
+scenario "k8s" {
+  # Endpoints appear once the deployment is ready.
+  probe "echo-svc" {
+    type      = "k8s_endpoints_nonempty" # old spelling
+    namespace = "default"
+    name      = "echo"
+  }
+}
+"#;
+        let migration = migrate_scenario(source).unwrap();
+        assert_eq!(
+            migration.source,
+            source.replace("\"k8s_endpoints_nonempty\"", "\"k8s_endpoints_non_empty\"")
+        );
+        assert_eq!(
+            migration
+                .changes
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "5:17: probe 'echo-svc': type 'k8s_endpoints_nonempty' is now \
+                 'k8s_endpoints_non_empty'"
+            ]
+        );
+
+        let again = migrate_scenario(&migration.source).unwrap();
+        assert_eq!(again.source, migration.source);
+        assert!(again.changes.is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// A `mock_service` block: a stub server intar runs on the host in place of an external
+/// service, so guests can call it without internet access. Guests reach it by name on `port`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockService {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: MockServiceKind,
+    /// Port guests connect to; the protocol's usual port unless set.
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MockServiceKind {
+    /// HTTP server answering each `route` with a canned response, and 404 otherwise.
+    Http { routes: Vec<MockRoute> },
+    /// SMTP server that accepts and keeps every message.
+    Smtp,
+    /// NTP server reporting the host's clock.
+    Ntp,
+}
+
+impl MockServiceKind {
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Http { .. } => "http",
+            Self::Smtp => "smtp",
+            Self::Ntp => "ntp",
+        }
+    }
+
+    #[must_use]
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::Http { .. } => 80,
+            Self::Smtp => 25,
+            Self::Ntp => 123,
+        }
+    }
+
+    /// Whether guests talk to the service over UDP rather than TCP.
+    #[must_use]
+    pub fn is_udp(&self) -> bool {
+        matches!(self, Self::Ntp)
+    }
+}
+
+/// A `route` of an HTTP mock service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockRoute {
+    /// Request path without the query string, e.g. `/v1/charge`.
+    pub path: String,
+    /// Only answer this method (e.g. `POST`); any method when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl MockRoute {
+    /// Whether this route answers `method` requests for `path` (query string ignored).
+    #[must_use]
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        let path = path.split('?').next().unwrap_or(path);
+        self.path == path
+            && self
+                .method
+                .as_deref()
+                .is_none_or(|m| m.eq_ignore_ascii_case(method))
+    }
+}
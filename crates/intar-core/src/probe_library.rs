@@ -0,0 +1,107 @@
+use crate::diagnostics::did_you_mean;
+use crate::scenario::parse_probe;
+use crate::{CoreError, ProbeDefinition};
+use std::sync::OnceLock;
+
+/// Prefix that addresses a preset from the built-in probe library, e.g. `builtin/nginx-running`.
+pub const BUILTIN_PROBE_PREFIX: &str = "builtin/";
+
+/// Presets ship inside the binary, so a scenario always gets the library of the intar running it.
+const BUILTIN_PROBES: &str = include_str!("../probes/builtin.hcl");
+
+/// Probe presets bundled with this build, in definition order. A probe block picks one with
+/// `use = "builtin/<name>"`; its own attributes override the preset's.
+///
+/// # Errors
+///
+/// Returns an error if the bundled library does not parse.
+pub fn builtin_probes() -> Result<&'static [ProbeDefinition], CoreError> {
+    static LIBRARY: OnceLock<Result<Vec<ProbeDefinition>, String>> = OnceLock::new();
+
+    let library = LIBRARY.get_or_init(|| {
+        let body = hcl::parse(BUILTIN_PROBES).map_err(|e| e.to_string())?;
+        body.blocks()
+            .map(|block| {
+                if block
+                    .body
+                    .attributes()
+                    .any(|attr| attr.key.as_str() == "use")
+                {
+                    return Err("built-in probe presets cannot `use` another preset".to_string());
+                }
+                parse_probe(block).map_err(|e| e.to_string())
+            })
+            .collect()
+    });
+
+    library
+        .as_deref()
+        .map_err(|e| CoreError::InvalidScenario(format!("Built-in probe library: {e}")))
+}
+
+/// Look up a preset by its `builtin/<name>` reference.
+///
+/// # Errors
+///
+/// Returns an error naming the closest preset if `reference` does not match one.
+pub fn builtin_probe(reference: &str) -> Result<&'static ProbeDefinition, CoreError> {
+    let presets = builtin_probes()?;
+    let Some(name) = reference.strip_prefix(BUILTIN_PROBE_PREFIX) else {
+        return Err(CoreError::InvalidScenario(format!(
+            "Unknown probe preset '{reference}'; presets are addressed as '{BUILTIN_PROBE_PREFIX}<name>'"
+        )));
+    };
+    if let Some(preset) = presets.iter().find(|preset| preset.name == name) {
+        return Ok(preset);
+    }
+
+    let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+    Err(CoreError::InvalidScenario(
+        match did_you_mean(name, &names) {
+            Some(suggestion) => format!(
+                "Unknown probe preset '{reference}', did you mean '{BUILTIN_PROBE_PREFIX}{suggestion}'?"
+            ),
+            None => format!("Unknown probe preset '{reference}'"),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intar_probes::ProbeSpec;
+
+    #[test]
+    fn test_builtin_probes_parse() {
+        let presets = builtin_probes().unwrap();
+        assert!(presets.iter().any(|p| p.name == "nginx-running"));
+        assert!(presets.iter().all(|p| p.description.is_some()));
+
+        // Presets that need a parameter (`service`, `port`) are only complete once it is set.
+        for preset in presets {
+            if matches!(preset.name.as_str(), "service-running" | "port-listening") {
+                continue;
+            }
+            let config = preset.config.clone().into_iter().collect();
+            ProbeSpec::from_definition(&preset.probe_type, &config)
+                .unwrap_or_else(|e| panic!("{}: {e}", preset.name));
+        }
+    }
+
+    #[test]
+    fn test_builtin_probe_lookup() {
+        assert_eq!(
+            builtin_probe("builtin/nginx-running").unwrap().probe_type,
+            "service"
+        );
+
+        let err = builtin_probe("builtin/nginx-runing")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("did you mean 'builtin/nginx-running'"),
+            "{err}"
+        );
+        assert!(builtin_probe("nginx-running").is_err());
+    }
+}
@@ -1,15 +1,160 @@
-use crate::CoreError;
+use crate::diagnostics::LineIndex;
+use crate::probe_library::builtin_probe;
+use crate::schema::{block_context, check_schema};
+use crate::{
+    CoreError, DEFAULT_GUEST_USER, Diagnostic, Diagnostics, DiskAio, DiskCache, DiskIo, GuestUser,
+    Ipv4Subnet, K8sCluster, MockRoute, MockService, MockServiceKind, SudoPolicy,
+};
+use base64::Engine as _;
+use hcl::edit::Span as _;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scenario {
     pub name: String,
     pub description: String,
+    /// `description` per language when the scenario gives it as `{ en = "...", de = "..." }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub description_translations: BTreeMap<String, String>,
+    #[serde(default)]
+    pub mode: ScenarioMode,
+    /// Lowercase hex SHA-256 of the code that reveals exam results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlock_sha256: Option<String>,
     pub images: HashMap<String, ImageSpec>,
     pub probes: HashMap<String, ProbeDefinition>,
     pub vms: Vec<VmDefinition>,
+    /// `k8s_cluster` blocks; their nodes and boot probes are already in `vms` and `probes`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clusters: Vec<K8sCluster>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mock_services: Vec<MockService>,
+    /// `probe_plugin` blocks, installed into every guest for `type = "plugin"` probes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub probe_plugins: Vec<ProbePlugin>,
+    /// `action "external"` blocks, run in order once every VM has booted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_actions: Vec<ExternalAction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_complete: Vec<CompletionAction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub questions: Vec<Question>,
+    #[serde(default)]
+    pub timeouts: ScenarioTimeouts,
+    #[serde(default)]
+    pub network: ScenarioNetwork,
+}
+
+/// Boot waits and run lifetime set by the scenario's `timeouts` block. Unset boot waits are
+/// calibrated from the host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioTimeouts {
+    /// Seconds to wait for the guest agents to come up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_secs: Option<u64>,
+    /// Seconds to wait for boot probes to pass once the agents are up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_probes_secs: Option<u64>,
+    /// Seconds after which a run is stopped and archived unless `intar start --ttl` overrides it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+/// Guest addressing and the services intar runs on the shared LAN, set by the scenario's
+/// `network` block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioNetwork {
+    /// Lease each VM its LAN address over DHCP instead of configuring it statically.
+    #[serde(default)]
+    pub dhcp: bool,
+    /// Resolve VM names with a DNS server on the LAN instead of `/etc/hosts` entries.
+    #[serde(default)]
+    pub dns: bool,
+    /// Domain VM names resolve under, e.g. `web.intar`.
+    pub domain: String,
+    /// Keep IPv6 enabled in every guest and give each a ULA address on the shared LAN.
+    #[serde(default)]
+    pub ipv6: bool,
+    /// User-mode management network; the user config's, or 10.0.2.0/24, when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub management_subnet: Option<Ipv4Subnet>,
+    /// Shared LAN; the user config's, or 10.11.0.0/24, when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lan_subnet: Option<Ipv4Subnet>,
+}
+
+impl Default for ScenarioNetwork {
+    fn default() -> Self {
+        Self {
+            dhcp: false,
+            dns: false,
+            domain: "intar".into(),
+            ipv6: false,
+            management_subnet: None,
+            lan_subnet: None,
+        }
+    }
+}
+
+impl ScenarioNetwork {
+    /// Whether any LAN service runs, i.e. the switch needs a services port.
+    #[must_use]
+    pub fn has_services(&self) -> bool {
+        self.dhcp || self.dns
+    }
+
+    #[must_use]
+    pub fn management(&self) -> Ipv4Subnet {
+        self.management_subnet
+            .unwrap_or(Ipv4Subnet::DEFAULT_MANAGEMENT)
+    }
+
+    #[must_use]
+    pub fn lan(&self) -> Ipv4Subnet {
+        self.lan_subnet.unwrap_or(Ipv4Subnet::DEFAULT_LAN)
+    }
+}
+
+/// A quiz `question` shown as a TUI overlay. The scenario only completes once every question
+/// has been answered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    pub name: String,
+    pub prompt: String,
+    /// Multiple-choice options; free-text when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    /// sha256 of the trimmed correct answer (or choice text). Without it, answers are only
+    /// recorded for grading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer_sha256: Option<String>,
+    /// Probes that must pass before the question is asked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+/// An `on_complete` hook, run once each time every scenario probe passes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CompletionAction {
+    /// Step actions executed inside `vm` through the agent.
+    Vm { vm: String, step: VmStep },
+    /// Shell command run on the host.
+    HostCommand { cmd: String },
+    /// JSON `POST` announcing completion.
+    Webhook { url: String },
+}
+
+/// How much the runner reveals to the learner while a scenario is running.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioMode {
+    #[default]
+    Practice,
+    /// Resets are disabled and probe details stay hidden until the unlock code is entered.
+    Exam,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,10 +166,21 @@ pub struct ImageSpec {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageSource {
     pub arch: String,
+    /// Primary download location; it also names the image in the cache.
     pub url: String,
+    /// Further locations of the same image, tried in order when `url` fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
     pub checksum: String,
 }
 
+impl ImageSource {
+    /// `url` followed by the mirrors.
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(String::as_str))
+    }
+}
+
 impl ImageSpec {
     #[must_use]
     pub fn source_for_arch(&self, arch: &str) -> Option<&ImageSource> {
@@ -44,18 +200,46 @@ pub struct ProbeDefinition {
     pub probe_type: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// `description` per language, as for [`Scenario::description_translations`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub description_translations: BTreeMap<String, String>,
     #[serde(default)]
     pub phase: ProbePhase,
     #[serde(flatten)]
     pub config: HashMap<String, serde_json::Value>,
 }
 
+/// An executable a `type = "plugin"` probe runs in the guest, from a `probe_plugin` block.
+/// Its attributes other than `plugin` are passed through to it untouched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbePlugin {
+    pub name: String,
+    /// The executable, base64-encoded.
+    pub content: String,
+}
+
+/// A host-side provisioner from an `action "external"` block, e.g.
+/// `{ provider = "ansible" playbook = "site.yml" }`. It runs after boot and before the initial
+/// checkpoint, so existing provisioning code can set the scenario up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalAction {
+    /// `ansible`, or the name of an `intar-provider-<provider>` executable on the host.
+    pub provider: String,
+    /// The block's other attributes, passed through to the provider.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub settings: BTreeMap<String, serde_json::Value>,
+    /// Directory of the scenario file; the provider runs there so relative paths resolve.
+    pub dir: PathBuf,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ProbePhase {
     Boot,
     #[default]
     Scenario,
+    /// Checked once when the learner quits; reported but never blocks completion.
+    Teardown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,13 +252,102 @@ pub struct VmDefinition {
     pub cloud_init: Option<CloudInitConfig>,
     #[serde(default)]
     pub steps: Vec<VmStep>,
+    #[serde(default)]
+    pub random_steps: Vec<RandomStepGroup>,
     pub probes: Vec<String>,
+    /// Keep IPv6 enabled in the guest and give it a ULA address on the shared LAN; every VM
+    /// gets it with the `network` block's `ipv6`.
+    #[serde(default)]
+    pub ipv6: bool,
+    /// Guest directories whose changed files the final report lists, e.g. `/etc`.
+    #[serde(default)]
+    pub track_changes: Vec<String>,
+    #[serde(default)]
+    pub disk_io: DiskIo,
+    /// Guest accounts from `user` blocks, besides the default `user`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<GuestUser>,
+    /// Let sshd accept passwords, for users that have one.
+    #[serde(default)]
+    pub ssh_password_auth: bool,
+    /// User the serial console logs in without a password, so `intar console` reaches the VM
+    /// when SSH does not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub console_autologin: Option<String>,
+    /// VMs whose boot probes must pass before this VM starts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wait_for: Vec<String>,
+}
+
+impl VmDefinition {
+    /// Check that the run's key logs in to the VM as `user`: the default user, or one of its
+    /// `user` blocks that is not locked.
+    ///
+    /// # Errors
+    /// Returns a message saying why not, listing the users that can log in.
+    pub fn check_login_user(&self, user: &str) -> Result<(), String> {
+        if user == DEFAULT_GUEST_USER {
+            return Ok(());
+        }
+        let logins: Vec<&str> = std::iter::once(DEFAULT_GUEST_USER)
+            .chain(
+                self.users
+                    .iter()
+                    .filter(|u| u.accepts_run_key())
+                    .map(|u| u.name.as_str()),
+            )
+            .collect();
+        match self.users.iter().find(|u| u.name == user) {
+            Some(u) if u.accepts_run_key() => Ok(()),
+            Some(_) => Err(format!(
+                "user '{user}' on VM '{}' is locked. Users that can log in: {}",
+                self.name,
+                logins.join(", ")
+            )),
+            None => Err(format!(
+                "VM '{}' has no user '{user}'. Users that can log in: {}",
+                self.name,
+                logins.join(", ")
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VmStep {
     pub name: String,
     pub actions: Vec<VmAction>,
+    #[serde(default)]
+    pub trigger: StepTrigger,
+}
+
+impl VmStep {
+    /// Steps named `break*` are scenario sabotage: their scripts run from `/run` and are removed
+    /// afterwards so the learner cannot read the solution from them.
+    #[must_use]
+    pub fn is_hidden(&self) -> bool {
+        let name = self.name.to_lowercase();
+        name.starts_with("break") || name.contains("break-") || name.contains("break_")
+    }
+}
+
+/// When a step runs: baked into cloud-init at boot, or injected later through the agent.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StepTrigger {
+    #[default]
+    Boot,
+    Manual,
+    After {
+        secs: u64,
+    },
+}
+
+/// A `random_one_of` block: exactly one of `steps` is applied per run, chosen from the run seed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomStepGroup {
+    pub name: String,
+    pub steps: Vec<VmStep>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +375,36 @@ pub enum VmAction {
     Command {
         cmd: String,
     },
+    UserCreate {
+        name: String,
+        shell: Option<String>,
+        #[serde(default)]
+        groups: Vec<String>,
+        #[serde(default)]
+        system: bool,
+    },
+    UserDelete {
+        name: String,
+        #[serde(default)]
+        remove_home: bool,
+    },
+    GroupAdd {
+        name: String,
+        #[serde(default)]
+        users: Vec<String>,
+    },
+    CronEntry {
+        name: String,
+        schedule: String,
+        command: String,
+        user: String,
+    },
+    Download {
+        url: String,
+        path: String,
+        sha256: String,
+        permissions: Option<String>,
+    },
     K8sApply {
         manifest: String,
         kubeconfig: Option<String>,
@@ -154,80 +457,367 @@ pub struct CloudInitConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WriteFile {
     pub path: String,
+    /// File content; base64-encoded when `binary` is set.
     pub content: String,
     pub permissions: Option<String>,
+    #[serde(default)]
+    pub binary: bool,
 }
 
 impl Scenario {
-    /// Parse a scenario from an HCL file path.
+    /// Parse a scenario from an HCL file path. `write_file` sources and `extends` are resolved
+    /// relative to the file's directory.
     ///
     /// # Errors
-    /// Returns `CoreError` if the file cannot be read or the contents cannot be parsed.
+    /// Returns `CoreError` if the file cannot be read or the contents cannot be parsed; parse
+    /// diagnostics are prefixed with `path`.
     pub fn from_file(path: &Path) -> Result<Self, CoreError> {
+        Self::load(path, &[])
+    }
+
+    /// Load `path` on behalf of the scenarios in `chain`, which extend it (outermost first).
+    fn load(path: &Path, chain: &[PathBuf]) -> Result<Self, CoreError> {
         let content = std::fs::read_to_string(path)?;
-        Self::parse(&content)
+        let canonical = path.canonicalize()?;
+        if chain.contains(&canonical) {
+            return Err(CoreError::InvalidScenario(format!(
+                "'{}' extends itself through a cycle",
+                path.display()
+            )));
+        }
+        let mut chain = chain.to_vec();
+        chain.push(canonical);
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        Self::parse_extending(&content, base_dir, &chain).map_err(|err| match err {
+            CoreError::Diagnostics(diagnostics) => {
+                CoreError::Diagnostics(diagnostics.in_file(&path.display().to_string()))
+            }
+            other => other,
+        })
     }
 
-    /// Parse a scenario from an HCL string.
+    /// Parse a scenario from an HCL string. `write_file` sources are resolved relative to the
+    /// current directory.
     ///
     /// # Errors
     /// Returns `CoreError` if the HCL is invalid or required fields are missing.
     pub fn parse(content: &str) -> Result<Self, CoreError> {
-        let body: hcl::Body =
-            hcl::from_str(content).map_err(|e| CoreError::HclParse(e.to_string()))?;
+        Self::parse_in(content, Path::new("."))
+    }
+
+    /// Parse a scenario from an HCL string, resolving `write_file` sources and `extends` against
+    /// `base_dir`.
+    ///
+    /// A scenario with `extends = "<path>"` starts from that scenario and then applies its own
+    /// blocks: `description`, `mode`, `unlock_sha256`, and each `timeouts` value replace the base's
+    /// when set; images, probes, and questions replace the base's entry of the same name or are
+    /// added; a `vm` named like a base VM overrides the attributes it sets, replaces `cloud_init`
+    /// if given, and replaces steps and `random_one_of` groups by name, appending new ones;
+    /// `on_complete` actions are appended. Nothing from the base can be removed.
+    ///
+    /// A `k8s_cluster` block adds its node VMs and boot probes; a later `vm` block named like a
+    /// node overrides it the same way, except that its `probes` are added to the node's.
+    ///
+    /// # Errors
+    /// Returns `CoreError::Diagnostics` listing every problem found, each with its line and
+    /// enclosing block: invalid HCL, unknown attributes or blocks, missing or invalid fields,
+    /// unreadable `write_file` sources, and problems in extended scenarios.
+    pub fn parse_in(content: &str, base_dir: &Path) -> Result<Self, CoreError> {
+        Self::parse_extending(content, base_dir, &[])
+    }
 
+    fn parse_extending(
+        content: &str,
+        base_dir: &Path,
+        chain: &[PathBuf],
+    ) -> Result<Self, CoreError> {
+        let lines = LineIndex::new(content);
+        let edit_body = hcl::edit::parser::parse_body(content).map_err(|e| {
+            let location = e.location();
+            CoreError::Diagnostics(Diagnostics(vec![
+                Diagnostic::new(e.message())
+                    .with_location(Some((location.line(), location.column()))),
+            ]))
+        })?;
+        let mut diagnostics = check_schema(content, &lines, &edit_body);
+        let body = hcl::Body::from(edit_body.clone());
+
+        let mut found_scenario = false;
+        let mut scenario_location = None;
         let mut scenario_name = String::new();
         let mut description = String::new();
+        let mut description_translations = BTreeMap::new();
+        let mut mode = ScenarioMode::Practice;
+        let mut unlock_sha256: Option<String> = None;
         let mut images = HashMap::new();
         let mut probes = HashMap::new();
-        let mut vms = Vec::new();
-
-        for block in body.blocks() {
-            if block.identifier.as_str() == "scenario" {
-                scenario_name = block
-                    .labels
-                    .first()
-                    .map(|l| l.as_str().to_string())
-                    .ok_or_else(|| CoreError::InvalidScenario("Missing scenario name".into()))?;
+        let mut vms: Vec<VmDefinition> = Vec::new();
+        let mut clusters: Vec<K8sCluster> = Vec::new();
+        // VMs a `vm` block overrides instead of defining: extended ones and cluster nodes.
+        let mut base_vms: HashSet<String> = HashSet::new();
+        let mut cluster_nodes: HashSet<String> = HashSet::new();
+        let mut mock_services: Vec<MockService> = Vec::new();
+        let mut probe_plugins: Vec<ProbePlugin> = Vec::new();
+        let mut external_actions: Vec<ExternalAction> = Vec::new();
+        let mut on_complete = Vec::new();
+        let mut questions: Vec<Question> = Vec::new();
+        let mut timeouts = ScenarioTimeouts::default();
+        let mut network = ScenarioNetwork::default();
+        // Diagnostics from extended files, reported after this file's own.
+        let mut base_diagnostics = Vec::new();
+
+        for (block, edit_block) in body.blocks().zip(edit_body.blocks()) {
+            if block.identifier.as_str() != "scenario" {
+                continue;
+            }
+            found_scenario = true;
+            scenario_location = lines.locate(content, edit_block.span());
+
+            match extract_optional_attr_string(block, "extends") {
+                Ok(None) => {}
+                Ok(Some(extends)) => match Self::load(&base_dir.join(&extends), chain) {
+                    Ok(scenario) => {
+                        description.clone_from(&scenario.description);
+                        description_translations = scenario.description_translations;
+                        mode = scenario.mode;
+                        unlock_sha256.clone_from(&scenario.unlock_sha256);
+                        images.clone_from(&scenario.images);
+                        probes.clone_from(&scenario.probes);
+                        base_vms = scenario.vms.iter().map(|vm| vm.name.clone()).collect();
+                        cluster_nodes = scenario
+                            .clusters
+                            .iter()
+                            .flat_map(K8sCluster::nodes)
+                            .collect();
+                        vms = scenario.vms;
+                        clusters = scenario.clusters;
+                        mock_services = scenario.mock_services;
+                        probe_plugins = scenario.probe_plugins;
+                        external_actions = scenario.external_actions;
+                        on_complete = scenario.on_complete;
+                        questions = scenario.questions;
+                        timeouts = scenario.timeouts;
+                        network = scenario.network;
+                    }
+                    Err(CoreError::Diagnostics(nested)) => base_diagnostics.extend(nested.0),
+                    Err(err) => {
+                        let location = edit_block
+                            .body
+                            .get_attribute("extends")
+                            .and_then(|attr| lines.locate(content, attr.span()));
+                        diagnostics.push(
+                            Diagnostic::new(format!("cannot extend '{extends}': {err}"))
+                                .with_location(location),
+                        );
+                    }
+                },
+                Err(err) => {
+                    diagnostics.push(Diagnostic::from(err).with_location(scenario_location));
+                }
+            }
 
-                if let Some(desc) = block
-                    .body
-                    .attributes()
-                    .find(|a| a.key.as_str() == "description")
-                {
-                    description = extract_string(&desc.expr)?;
+            match parse_scenario_header(block) {
+                Ok((name, own_description, own_mode, own_unlock_sha256)) => {
+                    scenario_name = name;
+                    if let Some((own_description, translations)) = own_description {
+                        description = own_description;
+                        description_translations = translations;
+                    }
+                    mode = own_mode.unwrap_or(mode);
+                    if own_unlock_sha256.is_some() {
+                        unlock_sha256 = own_unlock_sha256;
+                    }
+                }
+                Err(err) => {
+                    diagnostics.push(Diagnostic::from(err).with_location(scenario_location));
                 }
+            }
 
-                for inner_block in block.body.blocks() {
-                    match inner_block.identifier.as_str() {
-                        "image" => {
-                            let image = parse_image(inner_block)?;
-                            images.insert(image.name.clone(), image);
+            let mut own_questions = HashSet::new();
+            let mut own_services = HashSet::new();
+
+            for (inner_block, edit_inner) in block.body.blocks().zip(edit_block.body.blocks()) {
+                let result = match inner_block.identifier.as_str() {
+                    "image" => parse_image(inner_block).map(|image| {
+                        images.insert(image.name.clone(), image);
+                    }),
+                    "probe" => parse_probe(inner_block).map(|probe| {
+                        probes.insert(probe.name.clone(), probe);
+                    }),
+                    "vm" => {
+                        let name = inner_block.labels.first().map(|l| l.as_str().to_string());
+                        let base_vm = name
+                            .filter(|name| base_vms.contains(name) || cluster_nodes.contains(name))
+                            .and_then(|name| vms.iter().find(|vm| vm.name == name))
+                            .cloned();
+                        parse_vm(inner_block, base_dir, base_vm.as_ref()).map(
+                            |mut vm| match base_vm {
+                                Some(base_vm) => {
+                                    if cluster_nodes.contains(&vm.name) {
+                                        let own = std::mem::take(&mut vm.probes);
+                                        vm.probes = base_vm.probes;
+                                        for probe in own {
+                                            if !vm.probes.contains(&probe) {
+                                                vm.probes.push(probe);
+                                            }
+                                        }
+                                    }
+                                    replace_or_push(&mut vms, vm, |vm| &vm.name);
+                                }
+                                None => vms.push(vm),
+                            },
+                        )
+                    }
+                    "k8s_cluster" => parse_k8s_cluster(inner_block).and_then(|(cluster, nodes)| {
+                        if clusters.iter().any(|c| c.name == cluster.name) {
+                            return Err(CoreError::InvalidScenario(format!(
+                                "Duplicate k8s_cluster '{}'",
+                                cluster.name
+                            )));
                         }
-                        "probe" => {
-                            let probe = parse_probe(inner_block)?;
+                        if let Some(node) = nodes
+                            .iter()
+                            .find(|n| vms.iter().any(|vm| vm.name == n.name))
+                        {
+                            return Err(CoreError::InvalidScenario(format!(
+                                "k8s_cluster '{}' node '{}' clashes with an existing VM",
+                                cluster.name, node.name
+                            )));
+                        }
+                        for probe in cluster.boot_probes() {
                             probes.insert(probe.name.clone(), probe);
                         }
-                        "vm" => {
-                            let vm = parse_vm(inner_block)?;
-                            vms.push(vm);
+                        cluster_nodes.extend(nodes.iter().map(|vm| vm.name.clone()));
+                        vms.extend(nodes);
+                        clusters.push(cluster);
+                        Ok(())
+                    }),
+                    "mock_service" => parse_mock_service(inner_block).and_then(|service| {
+                        if !own_services.insert(service.name.clone()) {
+                            return Err(CoreError::InvalidScenario(format!(
+                                "Duplicate mock_service '{}'",
+                                service.name
+                            )));
                         }
-                        _ => {}
+                        replace_or_push(&mut mock_services, service, |s| &s.name);
+                        Ok(())
+                    }),
+                    "probe_plugin" => parse_probe_plugin(inner_block, base_dir).map(|plugin| {
+                        replace_or_push(&mut probe_plugins, plugin, |p| &p.name);
+                    }),
+                    "action" => parse_external_action(inner_block, base_dir)
+                        .map(|action| external_actions.push(action)),
+                    "on_complete" => {
+                        parse_on_complete(inner_block).map(|actions| on_complete.extend(actions))
                     }
+                    "question" => parse_question(inner_block).and_then(|question| {
+                        if !own_questions.insert(question.name.clone()) {
+                            return Err(CoreError::InvalidScenario(format!(
+                                "Duplicate question '{}'",
+                                question.name
+                            )));
+                        }
+                        replace_or_push(&mut questions, question, |q| &q.name);
+                        Ok(())
+                    }),
+                    "timeouts" => parse_timeouts(inner_block).map(|parsed| {
+                        timeouts = ScenarioTimeouts {
+                            agent_secs: parsed.agent_secs.or(timeouts.agent_secs),
+                            boot_probes_secs: parsed.boot_probes_secs.or(timeouts.boot_probes_secs),
+                            ttl_secs: parsed.ttl_secs.or(timeouts.ttl_secs),
+                        };
+                    }),
+                    "network" => parse_network(inner_block, &network).map(|parsed| {
+                        network = parsed;
+                    }),
+                    _ => Ok(()),
+                };
+                if let Err(err) = result {
+                    diagnostics.push(
+                        Diagnostic::from(err)
+                            .with_context(block_context(edit_inner))
+                            .with_location(lines.locate(content, edit_inner.span())),
+                    );
                 }
             }
         }
 
-        if scenario_name.is_empty() {
-            return Err(CoreError::InvalidScenario("No scenario block found".into()));
+        if !found_scenario {
+            diagnostics.push(Diagnostic::new("No scenario block found"));
+        }
+
+        // Both are host names in the guests' /etc/hosts.
+        for service in &mock_services {
+            if vms.iter().any(|vm| vm.name == service.name) {
+                diagnostics.push(
+                    Diagnostic::new(format!(
+                        "mock_service '{}' has the same name as a VM",
+                        service.name
+                    ))
+                    .with_location(scenario_location),
+                );
+            }
+        }
+
+        for vm in &vms {
+            if let Some(missing) = vm
+                .wait_for
+                .iter()
+                .find(|name| !vms.iter().any(|other| other.name == **name))
+            {
+                diagnostics.push(
+                    Diagnostic::new(format!("VM '{}' waits for unknown VM '{missing}'", vm.name))
+                        .with_location(scenario_location),
+                );
+            }
+        }
+        if let Some(cycle) = wait_for_cycle(&vms) {
+            diagnostics.push(
+                Diagnostic::new(format!("VMs wait for each other: {}", cycle.join(" -> ")))
+                    .with_location(scenario_location),
+            );
+        }
+
+        if network.has_services() && vms.len() < 2 {
+            diagnostics.push(
+                Diagnostic::new(
+                    "network dhcp and dns need a shared LAN, which only scenarios with more than one VM have",
+                )
+                .with_location(scenario_location),
+            );
+        }
+
+        if found_scenario && mode == ScenarioMode::Exam && unlock_sha256.is_none() {
+            diagnostics.push(
+                Diagnostic::new("Exam mode requires an 'unlock_sha256'")
+                    .with_location(scenario_location),
+            );
+        }
+
+        if !diagnostics.is_empty() || !base_diagnostics.is_empty() {
+            diagnostics.sort_by_key(|d| d.location.unwrap_or((usize::MAX, 0)));
+            diagnostics.extend(base_diagnostics);
+            return Err(CoreError::Diagnostics(Diagnostics(diagnostics)));
         }
 
         Ok(Scenario {
             name: scenario_name,
             description,
+            description_translations,
+            mode,
+            unlock_sha256,
             images,
             probes,
             vms,
+            clusters,
+            mock_services,
+            probe_plugins,
+            external_actions,
+            on_complete,
+            questions,
+            timeouts,
+            network,
         })
     }
 
@@ -246,13 +836,347 @@ impl Scenario {
                 }
             }
         }
+        for question in &self.questions {
+            if let Some(probe_name) = question
+                .after
+                .iter()
+                .find(|p| !self.probes.contains_key(*p))
+            {
+                return Err(CoreError::ProbeNotFound(probe_name.clone()));
+            }
+        }
+        for action in &self.on_complete {
+            if let CompletionAction::Vm { vm, .. } = action
+                && !self.vms.iter().any(|v| v.name == *vm)
+            {
+                return Err(CoreError::VmNotFound(vm.clone()));
+            }
+        }
         Ok(())
     }
 
+    /// Whether `vm` keeps IPv6, through its own `ipv6` or the `network` block's.
+    #[must_use]
+    pub fn ipv6_enabled(&self, vm: &VmDefinition) -> bool {
+        self.network.ipv6 || vm.ipv6
+    }
+
+    /// The VMs in scenario order, except that each comes after the VMs it `wait_for`s.
+    #[must_use]
+    pub fn boot_order(&self) -> Vec<&VmDefinition> {
+        let mut order: Vec<&VmDefinition> = Vec::with_capacity(self.vms.len());
+        while order.len() < self.vms.len() {
+            let next = self.vms.iter().find(|vm| {
+                !order.iter().any(|placed| placed.name == vm.name)
+                    && vm
+                        .wait_for
+                        .iter()
+                        .all(|name| order.iter().any(|placed| placed.name == *name))
+            });
+            match next {
+                Some(vm) => order.push(vm),
+                // Parsing rejects loops and unknown VMs; keep the rest in scenario order anyway.
+                None => {
+                    let rest: Vec<&VmDefinition> = self
+                        .vms
+                        .iter()
+                        .filter(|vm| !order.iter().any(|placed| placed.name == vm.name))
+                        .collect();
+                    order.extend(rest);
+                }
+            }
+        }
+        order
+    }
+
     #[must_use]
     pub fn total_probe_count(&self) -> usize {
         self.vms.iter().map(|vm| vm.probes.len()).sum()
     }
+
+    #[must_use]
+    pub fn is_exam(&self) -> bool {
+        self.mode == ScenarioMode::Exam
+    }
+
+    /// Show the scenario and probe descriptions in `language` (e.g. `de_AT`, falling back to
+    /// `de`) where the scenario translates them; the rest keep their default text.
+    pub fn localize(&mut self, language: &str) {
+        if let Some(text) = pick_translation(&self.description_translations, language) {
+            self.description = text.clone();
+        }
+        for probe in self.probes.values_mut() {
+            if let Some(text) = pick_translation(&probe.description_translations, language) {
+                probe.description = Some(text.clone());
+            }
+        }
+    }
+
+    /// The first part of `other` a running copy of this scenario cannot pick up, or `None` when
+    /// the two differ only in what [`Scenario::reload_probes`] swaps in.
+    #[must_use]
+    pub fn topology_change(&self, other: &Self) -> Option<String> {
+        let (Ok(current), Ok(changed)) = (
+            serde_json::to_value(self).map(without_probes),
+            serde_json::to_value(other).map(without_probes),
+        ) else {
+            return Some("scenario".into());
+        };
+        let keys: BTreeSet<&String> = current
+            .as_object()
+            .into_iter()
+            .chain(changed.as_object())
+            .flat_map(serde_json::Map::keys)
+            .collect();
+        keys.into_iter()
+            .find(|key| current.get(key.as_str()) != changed.get(key.as_str()))
+            .cloned()
+    }
+
+    /// Take the probes, the probes each VM lists, and the descriptions from `other`, a newer
+    /// version of this scenario with no [`Scenario::topology_change`].
+    pub fn reload_probes(&mut self, other: &Self) {
+        self.description.clone_from(&other.description);
+        self.description_translations
+            .clone_from(&other.description_translations);
+        self.probes.clone_from(&other.probes);
+        for vm in &mut self.vms {
+            if let Some(changed) = other.vms.iter().find(|changed| changed.name == vm.name) {
+                vm.probes.clone_from(&changed.probes);
+            }
+        }
+    }
+}
+
+/// A serialized scenario without the parts [`Scenario::reload_probes`] replaces.
+fn without_probes(mut scenario: serde_json::Value) -> serde_json::Value {
+    if let Some(fields) = scenario.as_object_mut() {
+        for key in ["description", "description_translations", "probes"] {
+            fields.remove(key);
+        }
+        if let Some(vms) = fields
+            .get_mut("vms")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            for vm in vms.iter_mut().filter_map(serde_json::Value::as_object_mut) {
+                vm.remove("probes");
+            }
+        }
+    }
+    scenario
+}
+
+/// Language tag a localized text falls back to when the requested one is missing.
+pub const DEFAULT_CONTENT_LANGUAGE: &str = "en";
+
+fn pick_translation<'a>(
+    translations: &'a BTreeMap<String, String>,
+    language: &str,
+) -> Option<&'a String> {
+    translations.get(language).or_else(|| {
+        let (base, _) = language.split_once(['_', '-'])?;
+        translations.get(base)
+    })
+}
+
+/// Name plus the optional `description` (with its translations), `mode`, and `unlock_sha256`.
+type ScenarioHeader = (
+    String,
+    Option<(String, BTreeMap<String, String>)>,
+    Option<ScenarioMode>,
+    Option<String>,
+);
+
+fn parse_scenario_header(block: &hcl::Block) -> Result<ScenarioHeader, CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario("Missing scenario name".into()))?;
+    let description = block
+        .body
+        .attributes()
+        .find(|a| a.key.as_str() == "description")
+        .map(|attr| extract_localized_string(&attr.expr))
+        .transpose()?;
+    let mode = extract_optional_attr_string(block, "mode")?
+        .map(|mode| parse_scenario_mode(&mode))
+        .transpose()?;
+    let unlock_sha256 = extract_optional_attr_string(block, "unlock_sha256")?
+        .map(|hash| {
+            if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                Ok(hash.to_ascii_lowercase())
+            } else {
+                Err(CoreError::InvalidScenario(
+                    "scenario unlock_sha256 must be 64 hex characters".into(),
+                ))
+            }
+        })
+        .transpose()?;
+    Ok((name, description, mode, unlock_sha256))
+}
+
+fn parse_on_complete(block: &hcl::Block) -> Result<Vec<CompletionAction>, CoreError> {
+    let mut actions = Vec::new();
+    for inner_block in block.body.blocks() {
+        let action = match inner_block.identifier.as_str() {
+            "vm" => {
+                let vm = inner_block
+                    .labels
+                    .first()
+                    .map(|l| l.as_str().to_string())
+                    .ok_or_else(|| {
+                        CoreError::InvalidScenario("on_complete vm block missing name".into())
+                    })?;
+                let step_actions = inner_block
+                    .body
+                    .blocks()
+                    .map(parse_vm_action)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if step_actions.is_empty() {
+                    return Err(CoreError::InvalidScenario(format!(
+                        "on_complete vm '{vm}' must contain at least one action block"
+                    )));
+                }
+                CompletionAction::Vm {
+                    vm,
+                    step: VmStep {
+                        name: "on-complete".into(),
+                        actions: step_actions,
+                        trigger: StepTrigger::Manual,
+                    },
+                }
+            }
+            "host_command" => CompletionAction::HostCommand {
+                cmd: extract_required_attr_string(inner_block, "cmd")?,
+            },
+            "webhook" => {
+                let url = extract_required_attr_string(inner_block, "url")?;
+                if !(url.starts_with("https://") || url.starts_with("http://")) {
+                    return Err(CoreError::InvalidScenario(format!(
+                        "on_complete webhook url must be http(s), got '{url}'"
+                    )));
+                }
+                CompletionAction::Webhook { url }
+            }
+            other => {
+                return Err(CoreError::InvalidScenario(format!(
+                    "Unknown on_complete action '{other}' (expected vm, host_command, or webhook)"
+                )));
+            }
+        };
+        actions.push(action);
+    }
+    Ok(actions)
+}
+
+fn parse_question(block: &hcl::Block) -> Result<Question, CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario("question block missing name".into()))?;
+
+    let choices = extract_optional_attr_string_array(block, "choices")?.unwrap_or_default();
+    if choices.len() == 1 {
+        return Err(CoreError::InvalidScenario(format!(
+            "question '{name}' needs at least two choices (or none for free text)"
+        )));
+    }
+
+    let answer_sha256 = extract_optional_attr_string(block, "answer_sha256")?
+        .map(|hash| {
+            if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                Ok(hash.to_ascii_lowercase())
+            } else {
+                Err(CoreError::InvalidScenario(format!(
+                    "question '{name}' answer_sha256 must be 64 hex characters"
+                )))
+            }
+        })
+        .transpose()?;
+
+    Ok(Question {
+        prompt: extract_required_attr_string(block, "prompt")?,
+        choices,
+        answer_sha256,
+        after: extract_optional_attr_string_array(block, "after")?.unwrap_or_default(),
+        name,
+    })
+}
+
+fn parse_timeouts(block: &hcl::Block) -> Result<ScenarioTimeouts, CoreError> {
+    let duration = |key: &str| {
+        extract_optional_attr_string(block, key)?
+            .map(|value| {
+                parse_duration_secs(&value)
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| {
+                        CoreError::InvalidScenario(format!(
+                            "timeouts {key} '{value}' is invalid (expected e.g. 90s, 15m, 1h)"
+                        ))
+                    })
+            })
+            .transpose()
+    };
+
+    Ok(ScenarioTimeouts {
+        agent_secs: duration("agent")?,
+        boot_probes_secs: duration("boot_probes")?,
+        ttl_secs: duration("ttl")?,
+    })
+}
+
+/// Parse a `network` block; unset attributes keep their value from `base`.
+fn parse_network(block: &hcl::Block, base: &ScenarioNetwork) -> Result<ScenarioNetwork, CoreError> {
+    let domain = extract_optional_attr_string(block, "domain")?
+        .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase());
+    if let Some(domain) = &domain
+        && (domain.is_empty()
+            || domain.split('.').any(|label| {
+                label.is_empty()
+                    || label.len() > 63
+                    || label.starts_with('-')
+                    || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            }))
+    {
+        return Err(CoreError::InvalidScenario(format!(
+            "network domain '{domain}' is not a valid DNS domain"
+        )));
+    }
+    let subnet = |key: &str| -> Result<Option<Ipv4Subnet>, CoreError> {
+        extract_optional_attr_string(block, key)?
+            .map(|subnet| subnet.parse::<Ipv4Subnet>())
+            .transpose()
+            .map_err(|err| CoreError::InvalidScenario(format!("network {key}: {err}")))
+    };
+    let management_subnet = subnet("management_subnet")?.or(base.management_subnet);
+    let lan_subnet = subnet("lan_subnet")?.or(base.lan_subnet);
+    if let (Some(management), Some(lan)) = (management_subnet, lan_subnet)
+        && management.overlaps(&lan)
+    {
+        return Err(CoreError::InvalidScenario(format!(
+            "network management_subnet {management} overlaps lan_subnet {lan}"
+        )));
+    }
+    Ok(ScenarioNetwork {
+        dhcp: extract_optional_attr_bool(block, "dhcp")?.unwrap_or(base.dhcp),
+        dns: extract_optional_attr_bool(block, "dns")?.unwrap_or(base.dns),
+        domain: domain.unwrap_or_else(|| base.domain.clone()),
+        ipv6: extract_optional_attr_bool(block, "ipv6")?.unwrap_or(base.ipv6),
+        management_subnet,
+        lan_subnet,
+    })
+}
+
+fn parse_scenario_mode(mode: &str) -> Result<ScenarioMode, CoreError> {
+    match mode {
+        "practice" => Ok(ScenarioMode::Practice),
+        "exam" => Ok(ScenarioMode::Exam),
+        other => Err(CoreError::InvalidScenario(format!(
+            "Scenario mode must be 'practice' or 'exam', got '{other}'"
+        ))),
+    }
 }
 
 fn parse_image(block: &hcl::Block) -> Result<ImageSpec, CoreError> {
@@ -282,13 +1206,19 @@ fn parse_image(block: &hcl::Block) -> Result<ImageSpec, CoreError> {
 
 fn parse_image_source(block: &hcl::Block) -> Result<ImageSource, CoreError> {
     let mut arch = String::new();
-    let mut url = String::new();
+    let mut urls = Vec::new();
     let mut checksum = String::new();
 
     for attr in block.body.attributes() {
         match attr.key.as_str() {
             "arch" => arch = extract_string(&attr.expr)?,
-            "url" => url = extract_string(&attr.expr)?,
+            // A list names mirrors of the same image after the primary URL.
+            "url" => {
+                urls = match &attr.expr {
+                    hcl::Expression::Array(_) => extract_string_array(&attr.expr)?,
+                    expr => vec![extract_string(expr)?],
+                };
+            }
             "checksum" => checksum = extract_string(&attr.expr)?,
             _ => {}
         }
@@ -299,11 +1229,12 @@ fn parse_image_source(block: &hcl::Block) -> Result<ImageSource, CoreError> {
             "Image source missing 'arch'".into(),
         ));
     }
-    if url.is_empty() {
+    if urls.is_empty() || urls.iter().any(String::is_empty) {
         return Err(CoreError::InvalidScenario(
             "Image source missing 'url'".into(),
         ));
     }
+    let url = urls.remove(0);
     if checksum.is_empty() {
         return Err(CoreError::InvalidScenario(
             "Image source missing 'checksum' (required for verification)".into(),
@@ -313,11 +1244,12 @@ fn parse_image_source(block: &hcl::Block) -> Result<ImageSource, CoreError> {
     Ok(ImageSource {
         arch,
         url,
+        mirrors: urls,
         checksum,
     })
 }
 
-fn parse_probe(block: &hcl::Block) -> Result<ProbeDefinition, CoreError> {
+pub(crate) fn parse_probe(block: &hcl::Block) -> Result<ProbeDefinition, CoreError> {
     let name = block
         .labels
         .first()
@@ -326,26 +1258,42 @@ fn parse_probe(block: &hcl::Block) -> Result<ProbeDefinition, CoreError> {
 
     let mut probe_type = String::new();
     let mut description: Option<String> = None;
+    let mut description_translations = BTreeMap::new();
     let mut config = HashMap::new();
     let mut phase = ProbePhase::Scenario;
 
+    // A preset provides the starting point; the block's own attributes override it.
+    if let Some(attr) = block.body.attributes().find(|a| a.key.as_str() == "use") {
+        let preset = builtin_probe(&extract_string(&attr.expr)?)
+            .map_err(|e| CoreError::InvalidScenario(format!("Probe '{name}': {e}")))?;
+        probe_type.clone_from(&preset.probe_type);
+        description.clone_from(&preset.description);
+        description_translations.clone_from(&preset.description_translations);
+        phase = preset.phase;
+        config.clone_from(&preset.config);
+    }
+
     for attr in block.body.attributes() {
         let key = attr.key.as_str();
         match key {
+            "use" => {}
             "type" => {
                 probe_type = extract_string(&attr.expr)?;
             }
             "description" => {
-                description = Some(extract_string(&attr.expr)?);
+                let (text, translations) = extract_localized_string(&attr.expr)?;
+                description = Some(text);
+                description_translations = translations;
             }
             "phase" => {
                 let val = extract_string(&attr.expr)?;
                 phase = match val.as_str() {
                     "boot" => ProbePhase::Boot,
                     "scenario" => ProbePhase::Scenario,
+                    "teardown" => ProbePhase::Teardown,
                     other => {
                         return Err(CoreError::InvalidScenario(format!(
-                            "Probe '{name}' phase must be 'boot' or 'scenario', got '{other}'"
+                            "Probe '{name}' phase must be 'boot', 'scenario', or 'teardown', got '{other}'"
                         )));
                     }
                 };
@@ -358,7 +1306,7 @@ fn parse_probe(block: &hcl::Block) -> Result<ProbeDefinition, CoreError> {
 
     if probe_type.is_empty() {
         return Err(CoreError::InvalidScenario(format!(
-            "Probe '{name}' missing type"
+            "Probe '{name}' missing type (or `use` of a built-in preset)"
         )));
     }
 
@@ -366,33 +1314,59 @@ fn parse_probe(block: &hcl::Block) -> Result<ProbeDefinition, CoreError> {
         name,
         probe_type,
         description,
+        description_translations,
         phase,
         config,
     })
 }
 
-fn parse_vm(block: &hcl::Block) -> Result<VmDefinition, CoreError> {
+/// Parse a `vm` block, on top of `base` when it overrides a VM from an extended scenario or a
+/// `k8s_cluster` node.
+fn parse_vm(
+    block: &hcl::Block,
+    base_dir: &Path,
+    base: Option<&VmDefinition>,
+) -> Result<VmDefinition, CoreError> {
     let name = block
         .labels
         .first()
         .map(|l| l.as_str().to_string())
         .ok_or_else(|| CoreError::InvalidScenario("Missing VM name".into()))?;
 
-    let mut cpu: u32 = 1;
-    let mut memory = 1024;
-    let mut disk = 10;
-    let mut image = String::new();
-    let mut cloud_init = CloudInitConfig::default();
+    let mut vm = base.cloned().unwrap_or_else(|| VmDefinition {
+        name: name.clone(),
+        cpu: 1,
+        memory: 1024,
+        disk: 10,
+        image: String::new(),
+        cloud_init: Some(CloudInitConfig::default()),
+        steps: Vec::new(),
+        random_steps: Vec::new(),
+        probes: Vec::new(),
+        ipv6: false,
+        track_changes: Vec::new(),
+        disk_io: DiskIo::default(),
+        users: Vec::new(),
+        ssh_password_auth: false,
+        console_autologin: None,
+        wait_for: Vec::new(),
+    });
     let mut steps: Vec<VmStep> = Vec::new();
-    let mut probes = Vec::new();
+    let mut random_steps: Vec<RandomStepGroup> = Vec::new();
+    let mut users: Vec<GuestUser> = Vec::new();
 
     for attr in block.body.attributes() {
         match attr.key.as_str() {
-            "cpu" => cpu = extract_u32(&attr.expr)?,
-            "memory" => memory = extract_u32(&attr.expr)?,
-            "disk" => disk = extract_u32(&attr.expr)?,
-            "image" => image = extract_string(&attr.expr)?,
-            "probes" => probes = extract_string_array(&attr.expr)?,
+            "cpu" => vm.cpu = extract_u32(&attr.expr)?,
+            "memory" => vm.memory = extract_u32(&attr.expr)?,
+            "disk" => vm.disk = extract_u32(&attr.expr)?,
+            "image" => vm.image = extract_string(&attr.expr)?,
+            "probes" => vm.probes = extract_string_array(&attr.expr)?,
+            "ipv6" => vm.ipv6 = extract_bool(&attr.expr)?,
+            "track_changes" => vm.track_changes = extract_string_array(&attr.expr)?,
+            "ssh_password_auth" => vm.ssh_password_auth = extract_bool(&attr.expr)?,
+            "console_autologin" => vm.console_autologin = Some(extract_string(&attr.expr)?),
+            "wait_for" => vm.wait_for = extract_string_array(&attr.expr)?,
             _ => {}
         }
     }
@@ -400,49 +1374,449 @@ fn parse_vm(block: &hcl::Block) -> Result<VmDefinition, CoreError> {
     for inner_block in block.body.blocks() {
         match inner_block.identifier.as_str() {
             "cloud_init" => {
-                cloud_init = parse_cloud_init(inner_block)?;
+                vm.cloud_init = Some(parse_cloud_init(inner_block, base_dir)?);
             }
             "step" => {
                 steps.push(parse_vm_step(inner_block)?);
             }
+            "random_one_of" => {
+                random_steps.push(parse_random_step_group(inner_block)?);
+            }
+            "disk_io" => {
+                vm.disk_io = parse_disk_io(inner_block, &name)?;
+            }
+            "user" => {
+                let user = parse_guest_user(inner_block, &name)?;
+                if users.iter().any(|u: &GuestUser| u.name == user.name) {
+                    return Err(CoreError::InvalidScenario(format!(
+                        "VM '{name}' has duplicate user '{}'.",
+                        user.name
+                    )));
+                }
+                users.push(user);
+            }
+            _ => {}
+        }
+    }
+
+    check_unique_steps(&name, &steps, &random_steps)?;
+    for step in steps {
+        replace_or_push(&mut vm.steps, step, |s| &s.name);
+    }
+    for group in random_steps {
+        replace_or_push(&mut vm.random_steps, group, |g| &g.name);
+    }
+    for user in users {
+        replace_or_push(&mut vm.users, user, |u| &u.name);
+    }
+    if base.is_some() {
+        check_unique_steps(&name, &vm.steps, &vm.random_steps)?;
+    }
+
+    if vm.image.is_empty() {
+        return Err(CoreError::InvalidScenario(format!(
+            "VM '{name}' missing image"
+        )));
+    }
+
+    if vm.cpu == 0 {
+        return Err(CoreError::InvalidScenario(format!(
+            "VM '{name}' cpu must be > 0"
+        )));
+    }
+
+    if let Some(user) = vm.console_autologin.as_deref()
+        && user != DEFAULT_GUEST_USER
+        && user != "root"
+        && !vm.users.iter().any(|u| u.name == user)
+    {
+        return Err(CoreError::InvalidScenario(format!(
+            "VM '{name}' console_autologin user '{user}' is not '{DEFAULT_GUEST_USER}', 'root', \
+             or one of its user blocks"
+        )));
+    }
+
+    if vm.wait_for.contains(&name) {
+        return Err(CoreError::InvalidScenario(format!(
+            "VM '{name}' cannot wait_for itself"
+        )));
+    }
+
+    if let Some(path) = vm.track_changes.iter().find(|path| !path.starts_with('/')) {
+        return Err(CoreError::InvalidScenario(format!(
+            "VM '{name}' track_changes path '{path}' must be absolute"
+        )));
+    }
+
+    Ok(vm)
+}
+
+/// A `wait_for` loop among `vms`, as the names along it with the first repeated at the end.
+fn wait_for_cycle(vms: &[VmDefinition]) -> Option<Vec<String>> {
+    let mut done = HashSet::new();
+    vms.iter()
+        .find_map(|vm| visit_wait_for(vm, vms, &mut Vec::new(), &mut done))
+}
+
+/// Depth-first step of [`wait_for_cycle`]; `path` holds the VMs waiting on `vm`.
+fn visit_wait_for<'a>(
+    vm: &'a VmDefinition,
+    vms: &'a [VmDefinition],
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+) -> Option<Vec<String>> {
+    if let Some(start) = path.iter().position(|name| *name == vm.name) {
+        let mut cycle: Vec<String> = path[start..].iter().map(ToString::to_string).collect();
+        cycle.push(vm.name.clone());
+        return Some(cycle);
+    }
+    if !done.insert(&vm.name) {
+        return None;
+    }
+    path.push(&vm.name);
+    for name in &vm.wait_for {
+        if let Some(next) = vms.iter().find(|other| other.name == *name)
+            && let Some(cycle) = visit_wait_for(next, vms, path, done)
+        {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    None
+}
+
+/// Parse the `disk_io` block of VM `vm`.
+fn parse_disk_io(block: &hcl::Block, vm: &str) -> Result<DiskIo, CoreError> {
+    let invalid =
+        |message: String| CoreError::InvalidScenario(format!("VM '{vm}' disk_io {message}"));
+    let mut disk_io = DiskIo::default();
+    for attr in block.body.attributes() {
+        match attr.key.as_str() {
+            "cache" => {
+                let value = extract_string(&attr.expr)?;
+                disk_io.cache = Some(DiskCache::parse(&value).ok_or_else(|| {
+                    invalid(format!(
+                        "cache '{value}' is unknown (expected writeback, none, writethrough, \
+                         directsync, or unsafe)"
+                    ))
+                })?);
+            }
+            "aio" => {
+                let value = extract_string(&attr.expr)?;
+                disk_io.aio = Some(DiskAio::parse(&value).ok_or_else(|| {
+                    invalid(format!(
+                        "aio '{value}' is unknown (expected threads, native, or io_uring)"
+                    ))
+                })?);
+            }
+            "discard" => disk_io.discard = extract_bool(&attr.expr)?,
+            "iops" => disk_io.iops = Some(extract_u32(&attr.expr)?),
+            "bps" => disk_io.bps = Some(extract_u64(&attr.expr)?),
             _ => {}
         }
     }
+    disk_io.validate().map_err(invalid)?;
+    Ok(disk_io)
+}
+
+/// Parse a `user` block of VM `vm`.
+fn parse_guest_user(block: &hcl::Block, vm: &str) -> Result<GuestUser, CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario(format!("VM '{vm}' user block missing name")))?;
+    let invalid =
+        |message: String| CoreError::InvalidScenario(format!("VM '{vm}' user '{name}' {message}"));
+    let sudo = match extract_optional_attr_string(block, "sudo")? {
+        Some(value) => SudoPolicy::parse(&value).ok_or_else(|| {
+            invalid(format!(
+                "sudo '{value}' is unknown (expected none, password, or nopasswd)"
+            ))
+        })?,
+        None => SudoPolicy::None,
+    };
+    let user = GuestUser {
+        password: extract_optional_attr_string(block, "password")?,
+        ssh_keys: extract_optional_attr_string_array(block, "ssh_keys")?.unwrap_or_default(),
+        sudo,
+        shell: extract_optional_attr_string(block, "shell")?,
+        groups: extract_optional_attr_string_array(block, "groups")?.unwrap_or_default(),
+        locked: extract_optional_attr_bool(block, "locked")?.unwrap_or(false),
+        ..GuestUser::new(&name)
+    };
+    user.validate().map_err(invalid)?;
+    Ok(user)
+}
+
+/// Parse a `k8s_cluster` block into the cluster and its node VMs.
+fn parse_k8s_cluster(block: &hcl::Block) -> Result<(K8sCluster, Vec<VmDefinition>), CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario("k8s_cluster block missing name".into()))?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(CoreError::InvalidScenario(format!(
+            "k8s_cluster name '{name}' must use only lowercase letters, digits, and '-'"
+        )));
+    }
+
+    let cluster = K8sCluster {
+        servers: extract_optional_attr_u32(block, "servers")?.unwrap_or(1),
+        agents: extract_optional_attr_u32(block, "agents")?.unwrap_or(0),
+        version: extract_optional_attr_string(block, "version")?,
+        name,
+    };
+    if cluster.servers == 0 {
+        return Err(CoreError::InvalidScenario(format!(
+            "k8s_cluster '{}' needs at least one server",
+            cluster.name
+        )));
+    }
+
+    let template = VmDefinition {
+        name: String::new(),
+        cpu: extract_optional_attr_u32(block, "cpu")?.unwrap_or(2),
+        memory: extract_optional_attr_u32(block, "memory")?.unwrap_or(2048),
+        disk: extract_optional_attr_u32(block, "disk")?.unwrap_or(10),
+        image: extract_required_attr_string(block, "image")?,
+        cloud_init: Some(CloudInitConfig::default()),
+        steps: Vec::new(),
+        random_steps: Vec::new(),
+        probes: Vec::new(),
+        ipv6: false,
+        track_changes: Vec::new(),
+        disk_io: DiskIo::default(),
+        users: Vec::new(),
+        ssh_password_auth: false,
+        console_autologin: None,
+        wait_for: Vec::new(),
+    };
+    if template.cpu == 0 {
+        return Err(CoreError::InvalidScenario(format!(
+            "k8s_cluster '{}' cpu must be > 0",
+            cluster.name
+        )));
+    }
+
+    let nodes = cluster.node_vms(&template);
+    Ok((cluster, nodes))
+}
+
+fn parse_mock_service(block: &hcl::Block) -> Result<MockService, CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario("mock_service block missing name".into()))?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(CoreError::InvalidScenario(format!(
+            "mock_service name '{name}' must use only lowercase letters, digits, and '-'"
+        )));
+    }
+
+    let routes = block
+        .body
+        .blocks()
+        .filter(|b| b.identifier.as_str() == "route")
+        .map(parse_mock_route)
+        .collect::<Result<Vec<_>, _>>()?;
+    let kind = match extract_required_attr_string(block, "type")?.as_str() {
+        "http" => MockServiceKind::Http { routes },
+        other => {
+            if !routes.is_empty() {
+                return Err(CoreError::InvalidScenario(format!(
+                    "mock_service '{name}': only http services have routes"
+                )));
+            }
+            match other {
+                "smtp" => MockServiceKind::Smtp,
+                "ntp" => MockServiceKind::Ntp,
+                _ => {
+                    return Err(CoreError::InvalidScenario(format!(
+                        "mock_service '{name}' type '{other}' is unknown (expected http, smtp, or ntp)"
+                    )));
+                }
+            }
+        }
+    };
+
+    let port = extract_optional_attr_u16(block, "port")?.unwrap_or_else(|| kind.default_port());
+    if port == 0 {
+        return Err(CoreError::InvalidScenario(format!(
+            "mock_service '{name}' port must be > 0"
+        )));
+    }
+    Ok(MockService { name, kind, port })
+}
+
+fn parse_mock_route(block: &hcl::Block) -> Result<MockRoute, CoreError> {
+    let path = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .filter(|path| path.starts_with('/'))
+        .ok_or_else(|| {
+            CoreError::InvalidScenario("route block needs a path label starting with '/'".into())
+        })?;
+    let status = extract_optional_attr_u16(block, "status")?.unwrap_or(200);
+    if !(100..=599).contains(&status) {
+        return Err(CoreError::InvalidScenario(format!(
+            "route '{path}' status {status} is not an HTTP status"
+        )));
+    }
+    Ok(MockRoute {
+        method: extract_optional_attr_string(block, "method")?
+            .map(|method| method.to_ascii_uppercase()),
+        status,
+        content_type: extract_optional_attr_string(block, "content_type")?
+            .unwrap_or_else(|| "text/plain".into()),
+        body: extract_optional_attr_string(block, "body")?.unwrap_or_default(),
+        path,
+    })
+}
 
+fn check_unique_steps(
+    vm: &str,
+    steps: &[VmStep],
+    random_steps: &[RandomStepGroup],
+) -> Result<(), CoreError> {
     let mut seen_step_names: HashSet<&str> = HashSet::new();
-    for step in &steps {
+    let grouped_steps = random_steps.iter().flat_map(|g| g.steps.iter());
+    for step in steps.iter().chain(grouped_steps) {
         if !seen_step_names.insert(step.name.as_str()) {
             return Err(CoreError::InvalidScenario(format!(
-                "VM '{name}' has duplicate step '{}'.",
+                "VM '{vm}' has duplicate step '{}'.",
                 step.name
             )));
         }
     }
+    Ok(())
+}
 
-    if image.is_empty() {
+/// Replace the entry named like `item`, keeping its position, or append `item`.
+fn parse_probe_plugin(block: &hcl::Block, base_dir: &Path) -> Result<ProbePlugin, CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario("probe_plugin block missing name".into()))?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
         return Err(CoreError::InvalidScenario(format!(
-            "VM '{name}' missing image"
+            "probe_plugin name '{name}' must use only letters, digits, '-', and '_'"
         )));
     }
+    let source = base_dir.join(extract_required_attr_string(block, "source")?);
+    let bytes = std::fs::read(&source).map_err(|e| {
+        CoreError::InvalidScenario(format!(
+            "probe_plugin '{name}': cannot read source {}: {e}",
+            source.display()
+        ))
+    })?;
+    Ok(ProbePlugin {
+        name,
+        content: base64::engine::general_purpose::STANDARD.encode(bytes),
+    })
+}
+
+fn parse_external_action(block: &hcl::Block, base_dir: &Path) -> Result<ExternalAction, CoreError> {
+    match block.labels.first().map(hcl::BlockLabel::as_str) {
+        Some("external") => {}
+        Some(other) => {
+            return Err(CoreError::InvalidScenario(format!(
+                "Unknown action kind '{other}' (expected external)"
+            )));
+        }
+        None => {
+            return Err(CoreError::InvalidScenario(
+                "action block missing kind, e.g. action \"external\"".into(),
+            ));
+        }
+    }
 
-    if cpu == 0 {
+    let mut provider = None;
+    let mut settings = BTreeMap::new();
+    for attr in block.body.attributes() {
+        match attr.key.as_str() {
+            "provider" => provider = Some(extract_string(&attr.expr)?),
+            key => {
+                settings.insert(key.to_string(), expr_to_json(&attr.expr)?);
+            }
+        }
+    }
+    let provider = provider
+        .filter(|provider| !provider.is_empty())
+        .ok_or_else(|| {
+            CoreError::InvalidScenario("action \"external\" missing 'provider'".into())
+        })?;
+    if !provider
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
         return Err(CoreError::InvalidScenario(format!(
-            "VM '{name}' cpu must be > 0"
+            "action \"external\" provider '{provider}' must use only letters, digits, '-', and '_'"
         )));
     }
 
-    Ok(VmDefinition {
-        name,
-        cpu,
-        memory,
-        disk,
-        image,
-        cloud_init: Some(cloud_init),
-        steps,
-        probes,
+    Ok(ExternalAction {
+        provider,
+        settings,
+        dir: base_dir
+            .canonicalize()
+            .unwrap_or_else(|_| base_dir.to_path_buf()),
     })
 }
 
+fn replace_or_push<T>(items: &mut Vec<T>, item: T, name: impl Fn(&T) -> &String) {
+    match items
+        .iter()
+        .position(|existing| name(existing) == name(&item))
+    {
+        Some(index) => items[index] = item,
+        None => items.push(item),
+    }
+}
+
+fn parse_random_step_group(block: &hcl::Block) -> Result<RandomStepGroup, CoreError> {
+    let name = block
+        .labels
+        .first()
+        .map(|l| l.as_str().to_string())
+        .ok_or_else(|| CoreError::InvalidScenario("random_one_of block missing name".into()))?;
+
+    let mut steps: Vec<VmStep> = Vec::new();
+    for inner_block in block.body.blocks() {
+        if inner_block.identifier.as_str() != "step" {
+            return Err(CoreError::InvalidScenario(format!(
+                "random_one_of '{name}' may only contain step blocks, found '{}'",
+                inner_block.identifier
+            )));
+        }
+        steps.push(parse_vm_step(inner_block)?);
+    }
+
+    if steps.len() < 2 {
+        return Err(CoreError::InvalidScenario(format!(
+            "random_one_of '{name}' must contain at least two step blocks"
+        )));
+    }
+
+    Ok(RandomStepGroup { name, steps })
+}
+
 fn parse_vm_step(block: &hcl::Block) -> Result<VmStep, CoreError> {
     let name = block
         .labels
@@ -450,6 +1824,14 @@ fn parse_vm_step(block: &hcl::Block) -> Result<VmStep, CoreError> {
         .map(|l| l.as_str().to_string())
         .ok_or_else(|| CoreError::InvalidScenario("step block missing name".into()))?;
 
+    let trigger = block
+        .body
+        .attributes()
+        .find(|a| a.key.as_str() == "trigger")
+        .map(|attr| parse_step_trigger(&name, &attr.expr))
+        .transpose()?
+        .unwrap_or_default();
+
     let mut actions: Vec<VmAction> = Vec::new();
     for inner_block in block.body.blocks() {
         let action = parse_vm_action(inner_block)?;
@@ -462,7 +1844,52 @@ fn parse_vm_step(block: &hcl::Block) -> Result<VmStep, CoreError> {
         )));
     }
 
-    Ok(VmStep { name, actions })
+    Ok(VmStep {
+        name,
+        actions,
+        trigger,
+    })
+}
+
+fn parse_step_trigger(step: &str, expr: &hcl::Expression) -> Result<StepTrigger, CoreError> {
+    if let hcl::Expression::Object(obj) = expr {
+        let after = obj
+            .iter()
+            .find(|(k, _)| k.to_string() == "after")
+            .map(|(_, v)| extract_string(v))
+            .transpose()?
+            .ok_or_else(|| {
+                CoreError::InvalidScenario(format!("step '{step}' trigger object must set 'after'"))
+            })?;
+        let secs = parse_duration_secs(&after).ok_or_else(|| {
+            CoreError::InvalidScenario(format!(
+                "step '{step}' trigger delay '{after}' is invalid (expected e.g. 30s, 10m, 1h)"
+            ))
+        })?;
+        return Ok(StepTrigger::After { secs });
+    }
+
+    match extract_string(expr)?.as_str() {
+        "boot" => Ok(StepTrigger::Boot),
+        "manual" => Ok(StepTrigger::Manual),
+        other => Err(CoreError::InvalidScenario(format!(
+            "step '{step}' trigger must be 'boot', 'manual', or {{ after = \"<duration>\" }}, got '{other}'"
+        ))),
+    }
+}
+
+/// Parse a duration such as `45`, `30s`, `10m`, or `1h` into seconds.
+#[must_use]
+pub fn parse_duration_secs(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last()? {
+        's' => (&value[..value.len() - 1], 1),
+        'm' => (&value[..value.len() - 1], 60),
+        'h' => (&value[..value.len() - 1], 3600),
+        c if c.is_ascii_digit() => (value, 1),
+        _ => return None,
+    };
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
 }
 
 fn parse_vm_action(block: &hcl::Block) -> Result<VmAction, CoreError> {
@@ -488,6 +1915,42 @@ fn parse_vm_action(block: &hcl::Block) -> Result<VmAction, CoreError> {
         "command" => Ok(VmAction::Command {
             cmd: extract_required_attr_string(block, "cmd")?,
         }),
+        "user_create" => Ok(VmAction::UserCreate {
+            name: extract_required_attr_string(block, "name")?,
+            shell: extract_optional_attr_string(block, "shell")?,
+            groups: extract_optional_attr_string_array(block, "groups")?.unwrap_or_default(),
+            system: extract_optional_attr_bool(block, "system")?.unwrap_or(false),
+        }),
+        "user_delete" => Ok(VmAction::UserDelete {
+            name: extract_required_attr_string(block, "name")?,
+            remove_home: extract_optional_attr_bool(block, "remove_home")?.unwrap_or(false),
+        }),
+        "group_add" => Ok(VmAction::GroupAdd {
+            name: extract_required_attr_string(block, "name")?,
+            users: extract_optional_attr_string_array(block, "users")?.unwrap_or_default(),
+        }),
+        "download" => {
+            let url = extract_required_attr_string(block, "url")?;
+            let sha256 = extract_required_attr_string(block, "sha256")?;
+            validate_download(&url, &sha256)?;
+            Ok(VmAction::Download {
+                url,
+                path: extract_required_attr_string(block, "path")?,
+                sha256: sha256.to_ascii_lowercase(),
+                permissions: extract_optional_attr_string(block, "permissions")?,
+            })
+        }
+        "cron_entry" => {
+            let name = extract_required_attr_string(block, "name")?;
+            let schedule = extract_required_attr_string(block, "schedule")?;
+            validate_cron_schedule(&name, &schedule)?;
+            Ok(VmAction::CronEntry {
+                name,
+                schedule,
+                command: extract_required_attr_string(block, "command")?,
+                user: extract_optional_attr_string(block, "user")?.unwrap_or_else(|| "root".into()),
+            })
+        }
         "k8s_apply" => {
             reject_attr(block, "kubectl")?;
             Ok(VmAction::K8sApply {
@@ -534,6 +1997,37 @@ fn parse_vm_action(block: &hcl::Block) -> Result<VmAction, CoreError> {
     }
 }
 
+fn validate_download(url: &str, sha256: &str) -> Result<(), CoreError> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(CoreError::InvalidScenario(format!(
+            "download url must be http(s), got '{url}'"
+        )));
+    }
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(CoreError::InvalidScenario(format!(
+            "download '{url}' sha256 must be 64 hex characters"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_cron_schedule(name: &str, schedule: &str) -> Result<(), CoreError> {
+    let schedule = schedule.trim();
+    let valid = if schedule.starts_with('@') {
+        schedule.split_whitespace().count() == 1
+    } else {
+        schedule.split_whitespace().count() == 5
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(CoreError::InvalidScenario(format!(
+            "cron_entry '{name}' schedule must have five fields or be an @keyword, got '{schedule}'"
+        )))
+    }
+}
+
 fn parse_systemctl_action(action: &str) -> Result<SystemctlAction, CoreError> {
     match action {
         "start" => Ok(SystemctlAction::Start),
@@ -548,7 +2042,7 @@ fn parse_systemctl_action(action: &str) -> Result<SystemctlAction, CoreError> {
     }
 }
 
-fn parse_cloud_init(block: &hcl::Block) -> Result<CloudInitConfig, CoreError> {
+fn parse_cloud_init(block: &hcl::Block, base_dir: &Path) -> Result<CloudInitConfig, CoreError> {
     let mut config = CloudInitConfig::default();
 
     for attr in block.body.attributes() {
@@ -562,23 +2056,29 @@ fn parse_cloud_init(block: &hcl::Block) -> Result<CloudInitConfig, CoreError> {
 
     for inner_block in block.body.blocks() {
         if inner_block.identifier.as_str() == "write_file" {
-            config.write_files.push(parse_write_file(inner_block)?);
+            config
+                .write_files
+                .push(parse_write_file(inner_block, base_dir)?);
         }
     }
 
     Ok(config)
 }
 
-fn parse_write_file(block: &hcl::Block) -> Result<WriteFile, CoreError> {
+fn parse_write_file(block: &hcl::Block, base_dir: &Path) -> Result<WriteFile, CoreError> {
     let mut path = String::new();
-    let mut content = String::new();
+    let mut content = None;
+    let mut source = None;
     let mut permissions = None;
+    let mut binary = false;
 
     for attr in block.body.attributes() {
         match attr.key.as_str() {
             "path" => path = extract_string(&attr.expr)?,
-            "content" => content = extract_string(&attr.expr)?,
+            "content" => content = Some(extract_string(&attr.expr)?),
+            "source" => source = Some(extract_string(&attr.expr)?),
             "permissions" => permissions = Some(extract_string(&attr.expr)?),
+            "binary" => binary = extract_bool(&attr.expr)?,
             _ => {}
         }
     }
@@ -589,29 +2089,111 @@ fn parse_write_file(block: &hcl::Block) -> Result<WriteFile, CoreError> {
         ));
     }
 
-    if content.is_empty() {
-        return Err(CoreError::InvalidScenario(
-            "write_file block missing 'content'".into(),
-        ));
-    }
+    let content = match (content, source) {
+        (Some(_), Some(_)) => {
+            return Err(CoreError::InvalidScenario(format!(
+                "write_file '{path}' sets both 'content' and 'source'"
+            )));
+        }
+        (None, Some(source)) => read_write_file_source(&path, &base_dir.join(source), binary)?,
+        (Some(content), None) if !content.is_empty() => {
+            if binary
+                && base64::engine::general_purpose::STANDARD
+                    .decode(content.trim())
+                    .is_err()
+            {
+                return Err(CoreError::InvalidScenario(format!(
+                    "write_file '{path}' is binary but its content is not base64"
+                )));
+            }
+            content
+        }
+        _ => {
+            return Err(CoreError::InvalidScenario(
+                "write_file block missing 'content' or 'source'".into(),
+            ));
+        }
+    };
 
     Ok(WriteFile {
         path,
         content,
         permissions,
+        binary,
+    })
+}
+
+/// Read a `write_file` source: base64-encoded when `binary`, otherwise as UTF-8 text.
+fn read_write_file_source(path: &str, source: &Path, binary: bool) -> Result<String, CoreError> {
+    let bytes = std::fs::read(source).map_err(|e| {
+        CoreError::InvalidScenario(format!(
+            "write_file '{path}': cannot read source {}: {e}",
+            source.display()
+        ))
+    })?;
+    if binary {
+        return Ok(base64::engine::general_purpose::STANDARD.encode(bytes));
+    }
+    String::from_utf8(bytes).map_err(|_| {
+        CoreError::InvalidScenario(format!(
+            "write_file '{path}': source {} is not UTF-8 text; set binary = true",
+            source.display()
+        ))
     })
 }
 
+/// Read a string attribute. Quoted strings and heredocs are evaluated as HCL templates, so
+/// `$${`/`%%{` escapes and `<<-` indentation stripping apply; numbers and bools are accepted as
+/// their text.
 fn extract_string(expr: &hcl::Expression) -> Result<String, CoreError> {
-    match expr {
-        hcl::Expression::String(s) => Ok(s.clone()),
-        hcl::Expression::TemplateExpr(t) => Ok(t.to_string().trim_matches('"').to_string()),
+    use hcl::eval::Evaluate as _;
+
+    let value = match expr {
+        hcl::Expression::TemplateExpr(t) => {
+            t.evaluate(&hcl::eval::Context::new()).map_err(|e| {
+                CoreError::InvalidScenario(format!(
+                    "Cannot evaluate string template: {e} (write $${{ or %%{{ for a literal \
+                     ${{ or %{{)"
+                ))
+            })?
+        }
+        other => hcl::Value::from(other.clone()),
+    };
+
+    match value {
+        hcl::Value::String(s) => Ok(s),
+        hcl::Value::Number(n) => Ok(n.to_string()),
+        hcl::Value::Bool(b) => Ok(b.to_string()),
         _ => Err(CoreError::InvalidScenario(format!(
             "Expected string, got {expr:?}"
         ))),
     }
 }
 
+/// A plain string, or `{ en = "...", de = "..." }` with one text per language. The default
+/// text is the English one, or the first language's when there is no English.
+fn extract_localized_string(
+    expr: &hcl::Expression,
+) -> Result<(String, BTreeMap<String, String>), CoreError> {
+    let hcl::Expression::Object(obj) = expr else {
+        return Ok((extract_string(expr)?, BTreeMap::new()));
+    };
+    let translations = obj
+        .iter()
+        .map(|(language, text)| Ok((language.to_string(), extract_string(text)?)))
+        .collect::<Result<BTreeMap<_, _>, CoreError>>()?;
+    let default = translations
+        .get(DEFAULT_CONTENT_LANGUAGE)
+        .or_else(|| translations.values().next())
+        .cloned()
+        .ok_or_else(|| {
+            CoreError::InvalidScenario(
+                "A localized text needs at least one language, e.g. { en = \"...\" }".into(),
+            )
+        })?;
+    Ok((default, translations))
+}
+
 fn extract_bool(expr: &hcl::Expression) -> Result<bool, CoreError> {
     match expr {
         hcl::Expression::Bool(b) => Ok(*b),
@@ -635,6 +2217,17 @@ fn extract_u32(expr: &hcl::Expression) -> Result<u32, CoreError> {
     }
 }
 
+fn extract_u64(expr: &hcl::Expression) -> Result<u64, CoreError> {
+    match expr {
+        hcl::Expression::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| CoreError::InvalidScenario("Invalid number".into())),
+        _ => Err(CoreError::InvalidScenario(format!(
+            "Expected number, got {expr:?}"
+        ))),
+    }
+}
+
 fn extract_u16(expr: &hcl::Expression) -> Result<u16, CoreError> {
     let value = extract_u32(expr)?;
     u16::try_from(value).map_err(|_| CoreError::InvalidScenario("Invalid number".into()))
@@ -707,6 +2300,18 @@ fn extract_optional_attr_bool(block: &hcl::Block, key: &str) -> Result<Option<bo
         .transpose()
 }
 
+fn extract_optional_attr_string_array(
+    block: &hcl::Block,
+    key: &str,
+) -> Result<Option<Vec<String>>, CoreError> {
+    block
+        .body
+        .attributes()
+        .find(|a| a.key.as_str() == key)
+        .map(|attr| extract_string_array(&attr.expr))
+        .transpose()
+}
+
 fn extract_string_map(expr: &hcl::Expression) -> Result<HashMap<String, String>, CoreError> {
     match expr {
         hcl::Expression::Object(obj) => obj
@@ -780,6 +2385,11 @@ fn expr_to_json(expr: &hcl::Expression) -> Result<serde_json::Value, CoreError>
             let values: Result<Vec<_>, _> = arr.iter().map(expr_to_json).collect();
             Ok(serde_json::Value::Array(values?))
         }
+        hcl::Expression::Object(obj) => obj
+            .iter()
+            .map(|(k, v)| Ok((k.to_string(), expr_to_json(v)?)))
+            .collect::<Result<_, CoreError>>()
+            .map(serde_json::Value::Object),
         _ => Ok(serde_json::Value::String(format!("{expr:?}"))),
     }
 }
@@ -787,6 +2397,7 @@ fn expr_to_json(expr: &hcl::Expression) -> Result<serde_json::Value, CoreError>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scenario_json_schema;
 
     #[test]
     fn test_parse_scenario() {
@@ -802,7 +2413,7 @@ scenario "test-scenario" {
     }
     source {
       arch     = "arm64"
-      url      = "https://example.com/ubuntu-arm64.qcow2"
+      url      = ["https://example.com/ubuntu-arm64.qcow2", "https://mirror.example.org/ubuntu-arm64.qcow2"]
       checksum = "sha256:def456"
     }
   }
@@ -836,6 +2447,16 @@ scenario "test-scenario" {
         assert_eq!(scenario.images.len(), 1);
         assert!(scenario.images.contains_key("ubuntu-24.04"));
         assert_eq!(scenario.images["ubuntu-24.04"].sources.len(), 2);
+        let arm64 = scenario.images["ubuntu-24.04"]
+            .source_for_arch("arm64")
+            .unwrap();
+        assert_eq!(
+            arm64.urls().collect::<Vec<_>>(),
+            [
+                "https://example.com/ubuntu-arm64.qcow2",
+                "https://mirror.example.org/ubuntu-arm64.qcow2"
+            ]
+        );
         assert_eq!(scenario.probes.len(), 1);
         assert_eq!(
             scenario.probes["test-probe"].description.as_deref(),
@@ -892,34 +2513,530 @@ scenario "write-file" {
         assert_eq!(file.path, "/tmp/test.txt");
         assert_eq!(file.content, "hello world");
         assert_eq!(file.permissions.as_deref(), Some("0644"));
+        assert!(!file.binary);
     }
 
     #[test]
-    fn test_parse_vm_step_actions() {
-        let hcl = r#"
-scenario "step-actions" {
-  description = "Parse step blocks inside vm"
+    fn test_parse_reports_all_problems_with_locations() {
+        let hcl = r#"scenario "typos" {
+  description = "Collect every problem"
 
-  image "ubuntu-24.04" {
-    source {
-      arch     = "amd64"
-      url      = "https://example.com/ubuntu-amd64.qcow2"
-      checksum = "sha256:abc123"
+  vm "web" {
+    image  = "ubuntu"
+    memroy = 2048
+    cloud_init {
+      pakages = ["nginx"]
     }
   }
 
-  probe "noop" {
-    type    = "service"
-    service = "nginx"
-    state   = "running"
+  vm "db" {
+    cpu = 2
   }
 
-  vm "web" {
-    image = "ubuntu-24.04"
+  timeout {
+    agent = "5m"
+  }
+}
+"#;
+        let Err(CoreError::Diagnostics(diagnostics)) = Scenario::parse(hcl) else {
+            panic!("expected diagnostics");
+        };
+        let messages: Vec<String> = diagnostics.0.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "6:5: vm 'web': unknown attribute 'memroy', did you mean 'memory'?",
+                "8:7: vm 'web' > cloud_init: unknown attribute 'pakages', did you mean 'packages'?",
+                "12:3: vm 'db': VM 'db' missing image",
+                "16:3: unknown block 'timeout', did you mean 'timeouts'?",
+            ]
+        );
 
-    step "break-nginx" {
-      systemctl {
-        unit   = "nginx"
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scenario.hcl");
+        std::fs::write(&path, "scenario \"broken\" {\n  vm \"web\" {\n").unwrap();
+        let err = Scenario::from_file(&path).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{}:", path.display())), "{err}");
+    }
+
+    #[test]
+    fn test_scenario_json_schema_matches_parser() {
+        let schema = scenario_json_schema(&serde_json::json!({ "type": "object" }));
+        let scenario = &schema["properties"]["scenario"]["additionalProperties"];
+        let vm = &scenario["properties"]["vm"]["additionalProperties"];
+        assert_eq!(vm["required"], serde_json::json!(["image"]));
+        assert_eq!(vm["properties"]["memory"]["type"], "integer");
+
+        let write_file = &vm["properties"]["cloud_init"]["oneOf"][0]["properties"]["write_file"];
+        assert_eq!(
+            write_file["oneOf"][0]["properties"]["binary"]["type"],
+            "boolean"
+        );
+    }
+
+    #[test]
+    fn test_extract_string_templates() {
+        let body: hcl::Body = hcl::from_str(
+            r#"
+conf = <<-EOF
+    server {
+        listen 80;
+        set $${upstream} backend;
+        return 200 "$host %%{ok}";
+    }
+    EOF
+raw    = <<EOF
+key: "value"
+EOF
+quoted = "a\tb $${HOME}"
+port   = 8080
+flag   = true
+"#,
+        )
+        .unwrap();
+        let attr = |key: &str| {
+            let expr = &body
+                .attributes()
+                .find(|a| a.key.as_str() == key)
+                .unwrap()
+                .expr;
+            extract_string(expr)
+        };
+
+        assert_eq!(
+            attr("conf").unwrap(),
+            "server {\n    listen 80;\n    set ${upstream} backend;\n    return 200 \"$host %{ok}\";\n}\n"
+        );
+        assert_eq!(attr("raw").unwrap(), "key: \"value\"\n");
+        assert_eq!(attr("quoted").unwrap(), "a\tb ${HOME}");
+        assert_eq!(attr("port").unwrap(), "8080");
+        assert_eq!(attr("flag").unwrap(), "true");
+
+        let body: hcl::Body = hcl::from_str(r#"x = "${unknown}""#).unwrap();
+        assert!(extract_string(&body.attributes().next().unwrap().expr).is_err());
+    }
+
+    #[test]
+    fn test_parse_probe_use_builtin_preset() {
+        let hcl = r#"
+scenario "presets" {
+  probe "nginx" {
+    use = "builtin/nginx-running"
+  }
+
+  probe "api" {
+    use         = "builtin/port-listening"
+    description = "API listens on 8080"
+    port        = 8080
+  }
+
+  vm "web" {
+    image  = "ubuntu"
+    probes = ["nginx", "api"]
+  }
+}
+"#;
+        let scenario = Scenario::parse(hcl).unwrap();
+        let nginx = &scenario.probes["nginx"];
+        assert_eq!(nginx.name, "nginx");
+        assert_eq!(nginx.probe_type, "service");
+        assert_eq!(nginx.config["service"], serde_json::json!("nginx"));
+
+        let api = &scenario.probes["api"];
+        assert_eq!(api.probe_type, "port");
+        assert_eq!(api.description.as_deref(), Some("API listens on 8080"));
+        assert_eq!(api.config["port"], serde_json::json!(8080));
+        assert_eq!(api.config["state"], serde_json::json!("listening"));
+
+        let err = Scenario::parse(&hcl.replace("builtin/nginx-running", "builtin/ngnix-running"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("did you mean 'builtin/nginx-running'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_parse_extends_merges_base_scenario() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("track")).unwrap();
+        std::fs::write(
+            dir.path().join("base-lab.hcl"),
+            r#"
+scenario "base-lab" {
+  description = "Shared lab"
+
+  image "ubuntu" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx" {
+    use = "builtin/nginx-running"
+  }
+
+  timeouts {
+    agent       = "10m"
+    boot_probes = "5m"
+  }
+
+  vm "web" {
+    image  = "ubuntu"
+    memory = 2048
+    probes = ["nginx"]
+
+    step "install" {
+      command { cmd = "apt-get install -y nginx" }
+    }
+
+    step "break-config" {
+      file_delete { path = "/etc/nginx/nginx.conf" }
+    }
+  }
+
+  vm "db" {
+    image = "ubuntu"
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let child = dir.path().join("track/lab-2.hcl");
+        std::fs::write(
+            &child,
+            r#"
+scenario "lab-2" {
+  extends = "../base-lab.hcl"
+
+  probe "port" {
+    use  = "builtin/port-listening"
+    port = 8080
+  }
+
+  timeouts {
+    agent = "20m"
+  }
+
+  vm "web" {
+    cpu    = 2
+    probes = ["nginx", "port"]
+
+    step "break-config" {
+      file_replace {
+        path        = "/etc/nginx/nginx.conf"
+        pattern     = "listen 80"
+        replacement = "listen 8080"
+      }
+    }
+
+    step "break-firewall" {
+      command { cmd = "iptables -A INPUT -p tcp --dport 8080 -j DROP" }
+    }
+  }
+
+  vm "cache" {
+    image = "ubuntu"
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let scenario = Scenario::from_file(&child).unwrap();
+        scenario.validate().unwrap();
+        assert_eq!(scenario.name, "lab-2");
+        assert_eq!(scenario.description, "Shared lab");
+        assert_eq!(scenario.probes.len(), 2);
+        assert_eq!(scenario.timeouts.agent_secs, Some(1200));
+        assert_eq!(scenario.timeouts.boot_probes_secs, Some(300));
+
+        let names: Vec<&str> = scenario.vms.iter().map(|vm| vm.name.as_str()).collect();
+        assert_eq!(names, vec!["web", "db", "cache"]);
+        let web = &scenario.vms[0];
+        assert_eq!((web.cpu, web.memory), (2, 2048));
+        assert_eq!(web.image, "ubuntu");
+        let steps: Vec<&str> = web.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(steps, vec!["install", "break-config", "break-firewall"]);
+        assert!(matches!(
+            web.steps[1].actions[0],
+            VmAction::FileReplace { .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_extends_reports_cycles_and_base_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.hcl");
+        std::fs::write(&a, "scenario \"a\" {\n  extends = \"b.hcl\"\n}\n").unwrap();
+        std::fs::write(
+            dir.path().join("b.hcl"),
+            "scenario \"b\" {\n  extends = \"a.hcl\"\n}\n",
+        )
+        .unwrap();
+        let err = Scenario::from_file(&a).unwrap_err().to_string();
+        assert!(err.contains("b.hcl:2:3: cannot extend 'a.hcl'"), "{err}");
+        assert!(err.contains("cycle"), "{err}");
+
+        std::fs::write(
+            dir.path().join("b.hcl"),
+            "scenario \"b\" {\n  memroy = 1\n}\n",
+        )
+        .unwrap();
+        let err = Scenario::from_file(&a).unwrap_err().to_string();
+        assert!(
+            err.contains("b.hcl:2:3: unknown attribute 'memroy'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_parse_k8s_cluster_expands_nodes() {
+        let scenario = Scenario::parse(
+            r#"
+scenario "k8s" {
+  image "ubuntu" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "echo-svc" {
+    type      = "k8s_endpoints_nonempty"
+    namespace = "default"
+    name      = "echo"
+  }
+
+  k8s_cluster "lab" {
+    image   = "ubuntu"
+    servers = 3
+    agents  = 1
+    memory  = 3072
+  }
+
+  vm "lab-server-1" {
+    probes = ["echo-svc"]
+
+    step "seed" {
+      command { cmd = "kubectl create deployment echo --image=nginx" }
+    }
+  }
+}
+"#,
+        )
+        .unwrap();
+        scenario.validate().unwrap();
+
+        assert_eq!(scenario.clusters.len(), 1);
+        let names: Vec<&str> = scenario.vms.iter().map(|vm| vm.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "lab-server-1",
+                "lab-server-2",
+                "lab-server-3",
+                "lab-agent-1"
+            ]
+        );
+        assert!(
+            scenario
+                .vms
+                .iter()
+                .all(|vm| vm.memory == 3072 && vm.cpu == 2)
+        );
+
+        let first = &scenario.vms[0];
+        let steps: Vec<&str> = first.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(steps, vec!["k3s-bootstrap", "seed"]);
+        assert_eq!(
+            first.probes,
+            vec!["lab-k3s-server", "lab-api", "lab-nodes-ready", "echo-svc"]
+        );
+        assert_eq!(scenario.probes["lab-nodes-ready"].phase, ProbePhase::Boot);
+
+        let err = Scenario::parse(
+            r#"
+scenario "k8s" {
+  vm "lab-server-1" {
+    image = "ubuntu"
+  }
+
+  k8s_cluster "lab" {
+    image   = "ubuntu"
+    servers = 0
+  }
+
+  k8s_cluster "Lab" {
+    image = "ubuntu"
+  }
+}
+"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("needs at least one server"), "{err}");
+        assert!(err.contains("lowercase letters"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_write_file_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("configs")).unwrap();
+        std::fs::write(
+            dir.path().join("configs/nginx.conf"),
+            "worker_processes 1;\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("blob.bin"), [0u8, 159, 146, 150]).unwrap();
+
+        let hcl = r#"
+scenario "write-file-source" {
+  description = "Load write_file content from disk"
+
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+    cloud_init {
+      write_file {
+        path   = "/etc/nginx/nginx.conf"
+        source = "./configs/nginx.conf"
+      }
+      write_file {
+        path   = "/opt/blob"
+        source = "blob.bin"
+        binary = true
+      }
+      write_file {
+        path    = "/opt/inline"
+        content = "AJ+Slg=="
+        binary  = true
+      }
+    }
+  }
+}
+"#;
+        let scenario_path = dir.path().join("scenario.hcl");
+        std::fs::write(&scenario_path, hcl).unwrap();
+
+        let scenario = Scenario::from_file(&scenario_path).unwrap();
+        let files = &scenario.vms[0].cloud_init.as_ref().unwrap().write_files;
+        assert_eq!(files[0].content, "worker_processes 1;\n");
+        assert!(!files[0].binary);
+        assert_eq!(files[1].content, "AJ+Slg==");
+        assert!(files[1].binary);
+        assert_eq!(files[2].content, "AJ+Slg==");
+
+        let non_utf8 = hcl.replace(
+            "source = \"blob.bin\"\n        binary = true",
+            "source = \"blob.bin\"",
+        );
+        assert!(Scenario::parse_in(&non_utf8, dir.path()).is_err());
+        let both = hcl.replace(
+            "path   = \"/etc/nginx/nginx.conf\"",
+            "path = \"/x\"\n        content = \"x\"",
+        );
+        assert!(Scenario::parse_in(&both, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_parse_probe_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("db-replicas"), "#!/bin/sh\n").unwrap();
+
+        let hcl = r#"
+scenario "plugins" {
+  probe_plugin "db-replicas" {
+    source = "db-replicas"
+  }
+
+  probe "replicas" {
+    type        = "plugin"
+    plugin      = "db-replicas"
+    min_in_sync = 3
+  }
+}
+"#;
+        let scenario = Scenario::parse_in(hcl, dir.path()).unwrap();
+        let [plugin] = scenario.probe_plugins.as_slice() else {
+            panic!("expected one plugin: {:?}", scenario.probe_plugins);
+        };
+        assert_eq!(plugin.name, "db-replicas");
+        assert_eq!(plugin.content, "IyEvYmluL3NoCg==");
+        let probe = &scenario.probes["replicas"];
+        assert_eq!(probe.probe_type, "plugin");
+        assert_eq!(probe.config["min_in_sync"], serde_json::json!(3));
+
+        let escaping = hcl.replace("probe_plugin \"db-replicas\"", "probe_plugin \"../sh\"");
+        assert!(Scenario::parse_in(&escaping, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_parse_external_action() {
+        let hcl = r#"
+scenario "provisioned" {
+  action "external" {
+    provider   = "ansible"
+    playbook   = "site.yml"
+    extra_vars = { port = 8080, tls = true }
+  }
+}
+"#;
+        let scenario = Scenario::parse(hcl).unwrap();
+        let [action] = scenario.external_actions.as_slice() else {
+            panic!("expected one action: {:?}", scenario.external_actions);
+        };
+        assert_eq!(action.provider, "ansible");
+        assert_eq!(action.settings["playbook"], serde_json::json!("site.yml"));
+        assert_eq!(
+            action.settings["extra_vars"],
+            serde_json::json!({ "port": 8080, "tls": true })
+        );
+
+        assert!(
+            Scenario::parse(&hcl.replace("action \"external\"", "action \"internal\"")).is_err()
+        );
+        assert!(Scenario::parse(&hcl.replace("provider   = \"ansible\"", "")).is_err());
+    }
+
+    #[test]
+    fn test_parse_vm_step_actions() {
+        let hcl = r#"
+scenario "step-actions" {
+  description = "Parse step blocks inside vm"
+
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "noop" {
+    type    = "service"
+    service = "nginx"
+    state   = "running"
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+
+    step "break-nginx" {
+      systemctl {
+        unit   = "nginx"
         action = "stop"
       }
 
@@ -975,9 +3092,47 @@ scenario "step-actions" {
     }
 
     #[test]
-    fn test_k8s_kubectl_override_rejected() {
+    fn test_parse_exam_mode() {
         let hcl = r#"
-scenario "reject-kubectl-override" {
+scenario "exam" {
+  mode          = "exam"
+  unlock_sha256 = "1C8BFE8F801D79745C4631D09FFF36C82AA37FC4CCE4FC946683D7B336B63032"
+
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        assert!(scenario.is_exam());
+        assert_eq!(
+            scenario.unlock_sha256.as_deref(),
+            Some("1c8bfe8f801d79745c4631d09fff36c82aa37fc4cce4fc946683d7b336b63032")
+        );
+
+        let missing_code = hcl.replace("  unlock_sha256", "  # unlock_sha256");
+        let err = Scenario::parse(&missing_code).unwrap_err();
+        match err {
+            CoreError::Diagnostics(d) => assert!(d.0[0].message.contains("unlock_sha256")),
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        let plaintext = hcl.replace(
+            "1C8BFE8F801D79745C4631D09FFF36C82AA37FC4CCE4FC946683D7B336B63032",
+            "letmein",
+        );
+        assert!(Scenario::parse(&plaintext).is_err());
+    }
+
+    #[test]
+    fn test_parse_random_one_of() {
+        let hcl = r#"
+scenario "random-faults" {
   image "ubuntu-24.04" {
     source {
       arch     = "amd64"
@@ -989,20 +3144,779 @@ scenario "reject-kubectl-override" {
   vm "web" {
     image = "ubuntu-24.04"
 
-    step "apply" {
-      k8s_namespace {
-        name    = "test"
-        kubectl = "k3s kubectl"
+    random_one_of "nginx-fault" {
+      step "break-stop" {
+        systemctl {
+          unit   = "nginx"
+          action = "stop"
+        }
+      }
+
+      step "break-config" {
+        file_delete {
+          path = "/etc/nginx/sites-enabled/default"
+        }
       }
     }
   }
 }
 "#;
 
-        let err = Scenario::parse(hcl).unwrap_err();
-        match err {
-            CoreError::InvalidScenario(msg) => assert!(msg.contains("kubectl")),
-            other => panic!("unexpected error: {other:?}"),
-        }
+        let scenario = Scenario::parse(hcl).unwrap();
+        let vm = &scenario.vms[0];
+        assert!(vm.steps.is_empty());
+        assert_eq!(vm.random_steps.len(), 1);
+        assert_eq!(vm.random_steps[0].name, "nginx-fault");
+        assert_eq!(vm.random_steps[0].steps.len(), 2);
+
+        let single = hcl.replace(
+            "      step \"break-config\" {\n        file_delete {\n          path = \"/etc/nginx/sites-enabled/default\"\n        }\n      }\n",
+            "",
+        );
+        assert!(Scenario::parse(&single).is_err());
+    }
+
+    #[test]
+    fn test_parse_identity_and_cron_actions() {
+        let hcl = r#"
+scenario "identity" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+
+    step "break-identity" {
+      user_create {
+        name   = "deploy"
+        shell  = "/bin/bash"
+        groups = ["www-data"]
+      }
+
+      user_delete {
+        name        = "olduser"
+        remove_home = true
+      }
+
+      group_add {
+        name  = "ops"
+        users = ["deploy"]
+      }
+
+      cron_entry {
+        name     = "cleanup"
+        schedule = "*/5 * * * *"
+        command  = "rm -rf /var/www/html/*"
+      }
+    }
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        let actions = &scenario.vms[0].steps[0].actions;
+        assert_eq!(actions.len(), 4);
+        match &actions[3] {
+            VmAction::CronEntry { user, schedule, .. } => {
+                assert_eq!(user, "root");
+                assert_eq!(schedule, "*/5 * * * *");
+            }
+            other => panic!("unexpected action: {other:?}"),
+        }
+
+        let bad = hcl.replace("*/5 * * * *", "*/5 * *");
+        assert!(Scenario::parse(&bad).is_err());
+    }
+
+    #[test]
+    fn test_parse_download_action() {
+        let hcl = r#"
+scenario "download" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+
+    step "stage-binary" {
+      download {
+        url         = "https://example.com/tool"
+        path        = "/usr/local/bin/tool"
+        sha256      = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855"
+        permissions = "0755"
+      }
+    }
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        match &scenario.vms[0].steps[0].actions[0] {
+            VmAction::Download { sha256, .. } => assert_eq!(
+                sha256,
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+            ),
+            other => panic!("unexpected action: {other:?}"),
+        }
+
+        assert!(Scenario::parse(&hcl.replace("E3B0C442", "nothex!!")).is_err());
+        assert!(Scenario::parse(&hcl.replace("https://example.com/tool", "ftp://x/tool")).is_err());
+    }
+
+    #[test]
+    fn test_parse_questions() {
+        let hcl = r#"
+scenario "quiz" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx-running" {
+    type    = "service"
+    service = "nginx"
+    state   = "running"
+  }
+
+  vm "web" {
+    image  = "ubuntu-24.04"
+    probes = ["nginx-running"]
+  }
+
+  question "default-port" {
+    prompt        = "Which port does nginx listen on by default?"
+    choices       = ["22", "80", "443"]
+    answer_sha256 = "48449A14A4FF7D79BB7A1B6F3D488EBA397C36EF25634C111B49BAF362511AFC"
+    after         = ["nginx-running"]
+  }
+
+  question "root-cause" {
+    prompt = "What broke nginx?"
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        scenario.validate().unwrap();
+        assert_eq!(scenario.questions.len(), 2);
+        let first = &scenario.questions[0];
+        assert_eq!(first.choices.len(), 3);
+        assert_eq!(
+            first.answer_sha256.as_deref(),
+            Some("48449a14a4ff7d79bb7a1b6f3d488eba397c36ef25634c111b49baf362511afc")
+        );
+        assert_eq!(first.after, vec!["nginx-running".to_string()]);
+        assert!(scenario.questions[1].answer_sha256.is_none());
+
+        let bad_after = Scenario::parse(&hcl.replace(
+            "after         = [\"nginx-running\"]",
+            "after         = [\"missing\"]",
+        ))
+        .unwrap();
+        assert!(bad_after.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_timeouts() {
+        let hcl = r#"
+scenario "slow" {
+  timeouts {
+    agent       = "20m"
+    boot_probes = "90"
+    ttl         = "4h"
+  }
+
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        assert_eq!(scenario.timeouts.agent_secs, Some(1200));
+        assert_eq!(scenario.timeouts.boot_probes_secs, Some(90));
+        assert_eq!(scenario.timeouts.ttl_secs, Some(4 * 3600));
+
+        let defaults = Scenario::parse(&hcl.replace("    boot_probes = \"90\"\n", "")).unwrap();
+        assert!(defaults.timeouts.boot_probes_secs.is_none());
+
+        assert!(Scenario::parse(&hcl.replace("\"20m\"", "\"soon\"")).is_err());
+        assert!(Scenario::parse(&hcl.replace("\"20m\"", "\"0s\"")).is_err());
+    }
+
+    #[test]
+    fn test_parse_disk_io() {
+        let hcl = r#"
+scenario "k3s" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "server" {
+    image = "ubuntu-24.04"
+    disk_io {
+      cache   = "none"
+      aio     = "threads"
+      discard = false
+      iops    = 2000
+      bps     = 104857600
+    }
+  }
+
+  vm "agent" {
+    image = "ubuntu-24.04"
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        let server = &scenario.vms[0].disk_io;
+        assert_eq!(server.cache, Some(DiskCache::None));
+        assert_eq!(server.aio, Some(DiskAio::Threads));
+        assert!(!server.discard);
+        assert_eq!(server.iops, Some(2000));
+        assert_eq!(server.bps, Some(104_857_600));
+        assert_eq!(scenario.vms[1].disk_io, DiskIo::default());
+
+        let err = Scenario::parse(
+            &hcl.replace("\"threads\"", "\"native\"")
+                .replace("\"none\"", "\"writeback\""),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("aio 'native' needs"), "{err}");
+        assert!(Scenario::parse(&hcl.replace("\"none\"", "\"fast\"")).is_err());
+        assert!(Scenario::parse(&hcl.replace("2000", "0")).is_err());
+    }
+
+    #[test]
+    fn test_parse_guest_users() {
+        let hcl = r#"
+scenario "privesc" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image             = "ubuntu-24.04"
+    ssh_password_auth = true
+    console_autologin = "app"
+
+    user "app" {
+      password = "app123"
+      sudo     = "password"
+      groups   = ["www-data"]
+    }
+
+    user "root" {
+      locked = true
+    }
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        let users = &scenario.vms[0].users;
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].name, "app");
+        assert_eq!(users[0].password.as_deref(), Some("app123"));
+        assert_eq!(users[0].sudo, SudoPolicy::Password);
+        assert_eq!(users[0].groups, vec!["www-data".to_string()]);
+        assert!(users[1].locked);
+        assert!(scenario.vms[0].ssh_password_auth);
+        assert_eq!(scenario.vms[0].console_autologin.as_deref(), Some("app"));
+        assert!(scenario.vms[0].check_login_user("user").is_ok());
+        assert!(scenario.vms[0].check_login_user("app").is_ok());
+        let err = scenario.vms[0].check_login_user("root").unwrap_err();
+        assert!(
+            err.contains("locked") && err.ends_with("user, app"),
+            "{err}"
+        );
+        assert!(scenario.vms[0].check_login_user("bob").is_err());
+
+        let err = Scenario::parse(&hcl.replace("\"password\"", "\"always\"")).unwrap_err();
+        assert!(err.to_string().contains("sudo 'always'"), "{err}");
+        assert!(Scenario::parse(&hcl.replace("user \"root\"", "user \"app\"")).is_err());
+        assert!(Scenario::parse(&hcl.replace("user \"app\"", "user \"user\"")).is_err());
+        let err = Scenario::parse(&hcl.replace("= \"app\"", "= \"bob\"")).unwrap_err();
+        assert!(
+            err.to_string().contains("console_autologin user 'bob'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_parse_wait_for() {
+        let hcl = r#"
+scenario "stack" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "db" {
+    image = "ubuntu-24.04"
+  }
+
+  vm "cache" {
+    image = "ubuntu-24.04"
+  }
+
+  vm "app" {
+    image    = "ubuntu-24.04"
+    wait_for = ["db", "cache"]
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        assert!(scenario.vms[0].wait_for.is_empty());
+        assert_eq!(scenario.vms[2].wait_for, vec!["db", "cache"]);
+        let order: Vec<&str> = scenario
+            .boot_order()
+            .iter()
+            .map(|vm| vm.name.as_str())
+            .collect();
+        assert_eq!(order, vec!["db", "cache", "app"]);
+
+        let mut reordered = scenario.clone();
+        reordered.vms.rotate_right(1);
+        let order: Vec<&str> = reordered
+            .boot_order()
+            .iter()
+            .map(|vm| vm.name.as_str())
+            .collect();
+        assert_eq!(order, vec!["db", "cache", "app"]);
+
+        let err = Scenario::parse(&hcl.replace("\"cache\"]", "\"queue\"]")).unwrap_err();
+        assert!(err.to_string().contains("unknown VM 'queue'"), "{err}");
+        let err = Scenario::parse(&hcl.replace("\"cache\"]", "\"app\"]")).unwrap_err();
+        assert!(err.to_string().contains("wait_for itself"), "{err}");
+        let looped = hcl.replace(
+            "vm \"db\" {\n    image = \"ubuntu-24.04\"",
+            "vm \"db\" {\n    image    = \"ubuntu-24.04\"\n    wait_for = [\"app\"]",
+        );
+        let err = Scenario::parse(&looped).unwrap_err();
+        assert!(err.to_string().contains("db -> app -> db"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_mock_services() {
+        let hcl = r#"
+scenario "mocks" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  mock_service "payments" {
+    type = "http"
+
+    route "/v1/charge" {
+      method       = "post"
+      status       = 201
+      content_type = "application/json"
+      body         = "{\"id\": \"ch_1\"}"
+    }
+
+    route "/health" {}
+  }
+
+  mock_service "mail" {
+    type = "smtp"
+    port = 2525
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        let [payments, mail] = scenario.mock_services.as_slice() else {
+            panic!("expected two mock services");
+        };
+        assert_eq!(payments.port, 80);
+        let MockServiceKind::Http { routes } = &payments.kind else {
+            panic!("expected an http service");
+        };
+        assert_eq!(routes[0].method.as_deref(), Some("POST"));
+        assert!(routes[0].matches("POST", "/v1/charge?retry=1"));
+        assert!(!routes[0].matches("GET", "/v1/charge"));
+        assert_eq!(
+            (routes[1].status, routes[1].content_type.as_str()),
+            (200, "text/plain")
+        );
+        assert!(routes[1].matches("GET", "/health"));
+        assert_eq!((&mail.kind, mail.port), (&MockServiceKind::Smtp, 2525));
+
+        assert!(Scenario::parse(&hcl.replace("\"smtp\"", "\"ftp\"")).is_err());
+        assert!(Scenario::parse(&hcl.replace("\"mail\"", "\"web\"")).is_err());
+        assert!(Scenario::parse(&hcl.replace("\"/health\"", "\"health\"")).is_err());
+    }
+
+    #[test]
+    fn test_parse_network() {
+        let hcl = r#"
+scenario "lan" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  network {
+    dhcp   = true
+    dns    = true
+    domain = "Lab.Example."
+
+    management_subnet = "172.30.8.0/22"
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+    ipv6  = true
+  }
+
+  vm "db" {
+    image = "ubuntu-24.04"
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        assert!(scenario.ipv6_enabled(&scenario.vms[0]));
+        assert!(!scenario.ipv6_enabled(&scenario.vms[1]));
+        assert_eq!(
+            scenario.network,
+            ScenarioNetwork {
+                dhcp: true,
+                dns: true,
+                domain: "lab.example".into(),
+                ipv6: false,
+                management_subnet: Some("172.30.8.0/22".parse().unwrap()),
+                lan_subnet: None,
+            }
+        );
+        assert_eq!(scenario.network.lan(), Ipv4Subnet::DEFAULT_LAN);
+        assert!(Scenario::parse(&hcl.replace("172.30.8.0/22", "172.30.8.1/22")).is_err());
+        let overlapping = hcl.replace(
+            "management_subnet = \"172.30.8.0/22\"",
+            "management_subnet = \"172.30.8.0/22\"\n    lan_subnet        = \"172.30.9.0/24\"",
+        );
+        assert!(Scenario::parse(&overlapping).is_err());
+        let without_dhcp = Scenario::parse(&hcl.replace("    dhcp   = true\n", "")).unwrap();
+        assert!(!without_dhcp.network.dhcp && without_dhcp.network.dns);
+        assert!(Scenario::parse(&hcl.replace("Lab.Example.", "lab..example")).is_err());
+
+        // Without a second VM there is no LAN to serve.
+        let single = hcl.replace("  vm \"db\" {\n    image = \"ubuntu-24.04\"\n  }\n", "");
+        assert!(Scenario::parse(&single).is_err());
+    }
+
+    #[test]
+    fn test_parse_on_complete_hooks() {
+        let hcl = r#"
+scenario "hooks" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+  }
+
+  on_complete {
+    vm "web" {
+      file_write {
+        path    = "/home/user/FLAG"
+        content = "well done"
+      }
+    }
+
+    host_command {
+      cmd = "echo unlocked > next-scenario.txt"
+    }
+
+    webhook {
+      url = "https://example.com/hooks/intar"
+    }
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        scenario.validate().unwrap();
+        assert_eq!(scenario.on_complete.len(), 3);
+        assert!(matches!(
+            &scenario.on_complete[0],
+            CompletionAction::Vm { vm, step } if vm == "web" && step.actions.len() == 1
+        ));
+        assert!(matches!(
+            &scenario.on_complete[2],
+            CompletionAction::Webhook { url } if url == "https://example.com/hooks/intar"
+        ));
+
+        let unknown_vm = Scenario::parse(&hcl.replace(
+            "vm \"web\" {\n      file_write",
+            "vm \"db\" {\n      file_write",
+        ))
+        .unwrap();
+        assert!(unknown_vm.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_teardown_probe_phase() {
+        let hcl = r#"
+scenario "cleanup" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "no-plaintext-passwords" {
+    type      = "command"
+    phase     = "teardown"
+    cmd       = "! grep -q 'password=' /home/user/.bash_history"
+    exit_code = 0
+  }
+
+  vm "web" {
+    image  = "ubuntu-24.04"
+    probes = ["no-plaintext-passwords"]
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        assert_eq!(
+            scenario.probes["no-plaintext-passwords"].phase,
+            ProbePhase::Teardown
+        );
+        assert!(Scenario::parse(&hcl.replace("\"teardown\"", "\"later\"")).is_err());
+    }
+
+    #[test]
+    fn test_parse_step_triggers() {
+        let hcl = r#"
+scenario "triggers" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+
+    step "setup" {
+      command {
+        cmd = "true"
+      }
+    }
+
+    step "break-later" {
+      trigger = { after = "10m" }
+      systemctl {
+        unit   = "nginx"
+        action = "stop"
+      }
+    }
+
+    step "break-on-demand" {
+      trigger = "manual"
+      file_delete {
+        path = "/etc/nginx/sites-enabled/default"
+      }
+    }
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        let steps = &scenario.vms[0].steps;
+        assert_eq!(steps[0].trigger, StepTrigger::Boot);
+        assert_eq!(steps[1].trigger, StepTrigger::After { secs: 600 });
+        assert_eq!(steps[2].trigger, StepTrigger::Manual);
+
+        assert!(Scenario::parse(&hcl.replace("\"10m\"", "\"soon\"")).is_err());
+        assert_eq!(parse_duration_secs("1h"), Some(3600));
+        assert_eq!(parse_duration_secs("45"), Some(45));
+        assert_eq!(parse_duration_secs("m"), None);
+    }
+
+    #[test]
+    fn test_k8s_kubectl_override_rejected() {
+        let hcl = r#"
+scenario "reject-kubectl-override" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  vm "web" {
+    image = "ubuntu-24.04"
+
+    step "apply" {
+      k8s_namespace {
+        name    = "test"
+        kubectl = "k3s kubectl"
+      }
+    }
+  }
+}
+"#;
+
+        let err = Scenario::parse(hcl).unwrap_err();
+        match err {
+            CoreError::Diagnostics(d) => assert!(d.0[0].message.contains("kubectl")),
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_localized_descriptions() {
+        let hcl = r#"
+scenario "localized" {
+  description = {
+    en = "Fix the web server"
+    de = "Repariere den Webserver"
+  }
+
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx" {
+    type        = "service"
+    service     = "nginx"
+    description = { de = "nginx läuft", fr = "nginx tourne" }
+  }
+
+  vm "web" {
+    image  = "ubuntu-24.04"
+    probes = ["nginx"]
+  }
+}
+"#;
+
+        let scenario = Scenario::parse(hcl).unwrap();
+        assert_eq!(scenario.description, "Fix the web server");
+        assert_eq!(scenario.description_translations.len(), 2);
+        assert_eq!(
+            scenario.probes["nginx"].description.as_deref(),
+            Some("nginx läuft")
+        );
+
+        let mut german = scenario.clone();
+        german.localize("de_AT");
+        assert_eq!(german.description, "Repariere den Webserver");
+        assert_eq!(
+            german.probes["nginx"].description.as_deref(),
+            Some("nginx läuft")
+        );
+
+        let mut french = scenario;
+        french.localize("fr");
+        assert_eq!(french.description, "Fix the web server");
+        assert_eq!(
+            french.probes["nginx"].description.as_deref(),
+            Some("nginx tourne")
+        );
+    }
+
+    #[test]
+    fn test_reload_probes_without_topology_change() {
+        let hcl = r#"
+scenario "reload" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx" {
+    type    = "service"
+    service = "nginx"
+  }
+
+  vm "web" {
+    image  = "ubuntu-24.04"
+    probes = ["nginx"]
+  }
+}
+"#;
+        let mut scenario = Scenario::parse(hcl).unwrap();
+        let edited = Scenario::parse(&hcl.replace(
+            "probes = [\"nginx\"]",
+            "probes = [\"nginx\", \"port\"]\n  }\n\n  probe \"port\" {\n    type = \"port\"\n    port = 80",
+        ))
+        .unwrap();
+        assert_eq!(scenario.topology_change(&edited), None);
+        scenario.reload_probes(&edited);
+        assert_eq!(scenario.vms[0].probes, ["nginx", "port"]);
+        assert!(scenario.probes.contains_key("port"));
+
+        let resized = Scenario::parse(&hcl.replace(
+            "image  = \"ubuntu-24.04\"",
+            "image  = \"ubuntu-24.04\"\n    memory = 4096",
+        ))
+        .unwrap();
+        assert_eq!(scenario.topology_change(&resized).as_deref(), Some("vms"));
     }
 }
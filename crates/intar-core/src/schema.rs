@@ -0,0 +1,663 @@
+use crate::diagnostics::{Diagnostic, LineIndex, did_you_mean};
+use crate::probe_library::{BUILTIN_PROBE_PREFIX, builtin_probes};
+use hcl::edit::Span as _;
+use hcl::edit::structure::{Block, Body};
+use serde_json::{Map, Value, json};
+
+/// Value types accepted by scenario attributes.
+#[derive(Debug, Clone, Copy)]
+enum AttrType {
+    String,
+    Number,
+    Bool,
+    StringList,
+    /// One string, or a list of them.
+    StringOrList,
+    StringMap,
+    /// One string, or an object of them keyed by language.
+    LocalizedString,
+    /// `"boot"`, `"manual"`, or `{ after = "<duration>" }`.
+    Trigger,
+}
+
+use AttrType::{
+    Bool, LocalizedString, Number, String as Str, StringList, StringMap, StringOrList, Trigger,
+};
+
+struct Attr {
+    name: &'static str,
+    ty: AttrType,
+    required: bool,
+}
+
+const fn opt(name: &'static str, ty: AttrType) -> Attr {
+    Attr {
+        name,
+        ty,
+        required: false,
+    }
+}
+
+const fn req(name: &'static str, ty: AttrType) -> Attr {
+    Attr {
+        name,
+        ty,
+        required: true,
+    }
+}
+
+/// Attributes and nested blocks a scenario block accepts. This is the single description of the
+/// format: unknown-name diagnostics and `intar schema` are both derived from it.
+struct BlockSchema {
+    description: &'static str,
+    /// Name of the block's label, for labelled blocks like `vm "web"`.
+    label: Option<&'static str>,
+    /// `None` for probes, whose attributes depend on the probe type.
+    attrs: Option<&'static [Attr]>,
+    /// Whether attributes beyond `attrs` are allowed; they are passed through to a plugin.
+    extra_attrs: bool,
+    blocks: &'static [(&'static str, &'static BlockSchema)],
+}
+
+const fn leaf(description: &'static str, attrs: &'static [Attr]) -> BlockSchema {
+    BlockSchema {
+        description,
+        label: None,
+        attrs: Some(attrs),
+        extra_attrs: false,
+        blocks: &[],
+    }
+}
+
+const ROOT: BlockSchema = BlockSchema {
+    description: "An intar scenario file.",
+    label: None,
+    attrs: Some(&[]),
+    extra_attrs: false,
+    blocks: &[("scenario", &SCENARIO)],
+};
+
+const SCENARIO: BlockSchema = BlockSchema {
+    description: "The scenario: images, probes, VMs, and completion settings.",
+    label: Some("name"),
+    attrs: Some(&[
+        opt("extends", Str),
+        opt("description", LocalizedString),
+        opt("mode", Str),
+        opt("unlock_sha256", Str),
+    ]),
+    extra_attrs: false,
+    blocks: &[
+        ("image", &IMAGE),
+        ("probe", &PROBE),
+        ("vm", &VM),
+        ("k8s_cluster", &K8S_CLUSTER),
+        ("mock_service", &MOCK_SERVICE),
+        ("probe_plugin", &PROBE_PLUGIN),
+        ("action", &ACTION),
+        ("on_complete", &ON_COMPLETE),
+        ("question", &QUESTION),
+        ("timeouts", &TIMEOUTS),
+        ("network", &NETWORK),
+    ],
+};
+
+const IMAGE: BlockSchema = BlockSchema {
+    description: "A base image with one source per architecture.",
+    label: Some("name"),
+    attrs: Some(&[]),
+    extra_attrs: false,
+    blocks: &[("source", &IMAGE_SOURCE)],
+};
+
+const IMAGE_SOURCE: BlockSchema = leaf(
+    "Download location of the image for one architecture; a list of URLs adds mirrors.",
+    &[
+        req("arch", Str),
+        req("url", StringOrList),
+        req("checksum", Str),
+    ],
+);
+
+const PROBE: BlockSchema = BlockSchema {
+    description: "A check run in the guest; the other attributes depend on `type`.",
+    label: Some("name"),
+    attrs: None,
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const PROBE_PLUGIN: BlockSchema = BlockSchema {
+    description: "An executable installed in every guest for `type = \"plugin\"` probes; `source` is relative to the scenario file.",
+    label: Some("name"),
+    attrs: Some(&[req("source", Str)]),
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const ACTION: BlockSchema = BlockSchema {
+    description: "A host-side provisioner run once the VMs have booted; the other attributes are passed to its `provider`.",
+    label: Some("kind"),
+    attrs: Some(&[req("provider", Str)]),
+    extra_attrs: true,
+    blocks: &[],
+};
+
+const VM: BlockSchema = BlockSchema {
+    description: "A virtual machine.",
+    label: Some("name"),
+    attrs: Some(&[
+        opt("cpu", Number),
+        opt("memory", Number),
+        opt("disk", Number),
+        req("image", Str),
+        opt("probes", StringList),
+        opt("ipv6", Bool),
+        opt("track_changes", StringList),
+        opt("ssh_password_auth", Bool),
+        opt("console_autologin", Str),
+        opt("wait_for", StringList),
+    ]),
+    extra_attrs: false,
+    blocks: &[
+        ("cloud_init", &CLOUD_INIT),
+        ("step", &STEP),
+        ("random_one_of", &RANDOM_ONE_OF),
+        ("disk_io", &DISK_IO),
+        ("user", &GUEST_USER),
+    ],
+};
+
+const GUEST_USER: BlockSchema = BlockSchema {
+    description: "A guest account besides `user`; `sudo` is none, password, or nopasswd, and a locked user cannot log in.",
+    label: Some("name"),
+    attrs: Some(&[
+        opt("password", Str),
+        opt("ssh_keys", StringList),
+        opt("sudo", Str),
+        opt("shell", Str),
+        opt("groups", StringList),
+        opt("locked", Bool),
+    ]),
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const DISK_IO: BlockSchema = BlockSchema {
+    description: "How QEMU backs the VM's disk: host cache mode, AIO backend, TRIM passthrough, and I/O limits.",
+    label: None,
+    attrs: Some(&[
+        opt("cache", Str),
+        opt("aio", Str),
+        opt("discard", Bool),
+        opt("iops", Number),
+        opt("bps", Number),
+    ]),
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const K8S_CLUSTER: BlockSchema = BlockSchema {
+    description: "A k3s cluster, expanded into `<name>-server-<n>` and `<name>-agent-<n>` VMs.",
+    label: Some("name"),
+    attrs: Some(&[
+        req("image", Str),
+        opt("servers", Number),
+        opt("agents", Number),
+        opt("cpu", Number),
+        opt("memory", Number),
+        opt("disk", Number),
+        opt("version", Str),
+    ]),
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const MOCK_SERVICE: BlockSchema = BlockSchema {
+    description: "A stub HTTP, SMTP, or NTP server on the host that guests reach by name.",
+    label: Some("name"),
+    attrs: Some(&[req("type", Str), opt("port", Number)]),
+    extra_attrs: false,
+    blocks: &[("route", &MOCK_ROUTE)],
+};
+
+const MOCK_ROUTE: BlockSchema = BlockSchema {
+    description: "A canned response of an HTTP mock service.",
+    label: Some("path"),
+    attrs: Some(&[
+        opt("method", Str),
+        opt("status", Number),
+        opt("content_type", Str),
+        opt("body", Str),
+    ]),
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const CLOUD_INIT: BlockSchema = BlockSchema {
+    description: "First-boot provisioning.",
+    label: None,
+    attrs: Some(&[
+        opt("packages", StringList),
+        opt("network_config", Str),
+        opt("runcmd", Str),
+    ]),
+    extra_attrs: false,
+    blocks: &[("write_file", &WRITE_FILE)],
+};
+
+const WRITE_FILE: BlockSchema = leaf(
+    "A file written at first boot, from `content` or a `source` file.",
+    &[
+        req("path", Str),
+        opt("content", Str),
+        opt("source", Str),
+        opt("permissions", Str),
+        opt("binary", Bool),
+    ],
+);
+
+const RANDOM_ONE_OF: BlockSchema = BlockSchema {
+    description: "Alternative steps; exactly one is applied per run.",
+    label: Some("name"),
+    attrs: Some(&[]),
+    extra_attrs: false,
+    blocks: &[("step", &STEP)],
+};
+
+const STEP: BlockSchema = BlockSchema {
+    description: "Actions applied to the VM at boot, manually, or after a delay.",
+    label: Some("name"),
+    attrs: Some(&[opt("trigger", Trigger)]),
+    extra_attrs: false,
+    blocks: ACTIONS,
+};
+
+const ON_COMPLETE: BlockSchema = BlockSchema {
+    description: "Actions run once every probe passes.",
+    label: None,
+    attrs: Some(&[]),
+    extra_attrs: false,
+    blocks: &[
+        (
+            "vm",
+            &BlockSchema {
+                description: "Actions applied to a VM.",
+                label: Some("vm"),
+                attrs: Some(&[]),
+                extra_attrs: false,
+                blocks: ACTIONS,
+            },
+        ),
+        (
+            "host_command",
+            &leaf("A command run on the host.", &[req("cmd", Str)]),
+        ),
+        (
+            "webhook",
+            &leaf("An HTTP(S) URL notified on completion.", &[req("url", Str)]),
+        ),
+    ],
+};
+
+const QUESTION: BlockSchema = BlockSchema {
+    description: "A quiz question the learner must answer.",
+    label: Some("name"),
+    attrs: Some(&[
+        req("prompt", Str),
+        opt("choices", StringList),
+        opt("answer_sha256", Str),
+        opt("after", StringList),
+    ]),
+    extra_attrs: false,
+    blocks: &[],
+};
+
+const TIMEOUTS: BlockSchema = leaf(
+    "Boot waits and run lifetime; unset boot waits are calibrated from the host.",
+    &[opt("agent", Str), opt("boot_probes", Str), opt("ttl", Str)],
+);
+
+const NETWORK: BlockSchema = leaf(
+    "Guest networks: subnets, shared LAN DHCP and DNS services, and IPv6.",
+    &[
+        opt("dhcp", Bool),
+        opt("dns", Bool),
+        opt("domain", Str),
+        opt("ipv6", Bool),
+        opt("lan_subnet", Str),
+        opt("management_subnet", Str),
+    ],
+);
+
+// `kubectl` is accepted here so the k8s actions can explain that it is unsupported.
+const ACTIONS: &[(&str, &BlockSchema)] = &[
+    ("file_delete", &leaf("Delete a file.", &[req("path", Str)])),
+    (
+        "file_write",
+        &leaf(
+            "Write a file.",
+            &[
+                req("path", Str),
+                req("content", Str),
+                opt("permissions", Str),
+            ],
+        ),
+    ),
+    (
+        "file_replace",
+        &leaf(
+            "Replace text in a file.",
+            &[
+                req("path", Str),
+                req("pattern", Str),
+                req("replacement", Str),
+                opt("regex", Bool),
+            ],
+        ),
+    ),
+    (
+        "systemctl",
+        &leaf(
+            "Start, stop, enable, or disable a unit.",
+            &[req("unit", Str), req("action", Str)],
+        ),
+    ),
+    ("command", &leaf("Run a shell command.", &[req("cmd", Str)])),
+    (
+        "user_create",
+        &leaf(
+            "Create a user.",
+            &[
+                req("name", Str),
+                opt("shell", Str),
+                opt("groups", StringList),
+                opt("system", Bool),
+            ],
+        ),
+    ),
+    (
+        "user_delete",
+        &leaf(
+            "Delete a user.",
+            &[req("name", Str), opt("remove_home", Bool)],
+        ),
+    ),
+    (
+        "group_add",
+        &leaf(
+            "Create a group.",
+            &[req("name", Str), opt("users", StringList)],
+        ),
+    ),
+    (
+        "download",
+        &leaf(
+            "Download a checksummed file.",
+            &[
+                req("url", Str),
+                req("sha256", Str),
+                req("path", Str),
+                opt("permissions", Str),
+            ],
+        ),
+    ),
+    (
+        "cron_entry",
+        &leaf(
+            "Install a cron job.",
+            &[
+                req("name", Str),
+                req("schedule", Str),
+                req("command", Str),
+                opt("user", Str),
+            ],
+        ),
+    ),
+    (
+        "k8s_apply",
+        &leaf(
+            "Apply a Kubernetes manifest.",
+            &[
+                req("manifest", Str),
+                opt("kubeconfig", Str),
+                opt("kubectl", Str),
+            ],
+        ),
+    ),
+    (
+        "k8s_namespace",
+        &leaf(
+            "Create a Kubernetes namespace.",
+            &[
+                req("name", Str),
+                opt("kubeconfig", Str),
+                opt("kubectl", Str),
+            ],
+        ),
+    ),
+    (
+        "k8s_deployment",
+        &leaf(
+            "Create a Kubernetes deployment.",
+            &[
+                req("name", Str),
+                req("namespace", Str),
+                req("image", Str),
+                opt("replicas", Number),
+                opt("labels", StringMap),
+                req("container_port", Number),
+                opt("kubeconfig", Str),
+                opt("kubectl", Str),
+            ],
+        ),
+    ),
+    (
+        "k8s_service",
+        &leaf(
+            "Create a Kubernetes service.",
+            &[
+                req("name", Str),
+                req("namespace", Str),
+                req("selector", StringMap),
+                req("port", Number),
+                opt("target_port", Number),
+                opt("kubeconfig", Str),
+                opt("kubectl", Str),
+            ],
+        ),
+    ),
+];
+
+/// Report attributes and blocks the parser would silently ignore, with a suggestion when the
+/// name looks like a typo.
+pub(crate) fn check_schema(source: &str, lines: &LineIndex, body: &Body) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_body(source, lines, body, &ROOT, None, &mut diagnostics);
+    diagnostics
+}
+
+fn check_body(
+    source: &str,
+    lines: &LineIndex,
+    body: &Body,
+    schema: &BlockSchema,
+    context: Option<&str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let push = |diagnostics: &mut Vec<Diagnostic>, diagnostic: Diagnostic| {
+        diagnostics.push(match context {
+            Some(context) => diagnostic.with_context(context),
+            None => diagnostic,
+        });
+    };
+
+    if let Some(attrs) = schema.attrs.filter(|_| !schema.extra_attrs) {
+        let known: Vec<&str> = attrs.iter().map(|attr| attr.name).collect();
+        for attr in body.attributes() {
+            let key = attr.key.as_str();
+            if !known.contains(&key) {
+                let location = lines.locate(source, attr.span());
+                push(
+                    diagnostics,
+                    Diagnostic::new(unknown("attribute", key, &known)).with_location(location),
+                );
+            }
+        }
+    }
+
+    let block_names: Vec<&str> = schema.blocks.iter().map(|(name, _)| *name).collect();
+    for block in body.blocks() {
+        let ident = block.ident.as_str();
+        let Some((_, inner)) = schema.blocks.iter().find(|(name, _)| *name == ident) else {
+            let location = lines.locate(source, block.span());
+            push(
+                diagnostics,
+                Diagnostic::new(unknown("block", ident, &block_names)).with_location(location),
+            );
+            continue;
+        };
+        let inner_context = match context {
+            _ if ident == "scenario" => None,
+            Some(context) => Some(format!("{context} > {}", block_context(block))),
+            None => Some(block_context(block)),
+        };
+        check_body(
+            source,
+            lines,
+            &block.body,
+            inner,
+            inner_context.as_deref(),
+            diagnostics,
+        );
+    }
+}
+
+/// `vm 'web'`, or just the identifier for unlabelled blocks.
+pub(crate) fn block_context(block: &Block) -> String {
+    match block.labels.first() {
+        Some(label) => format!("{} '{}'", block.ident.as_str(), label.as_str()),
+        None => block.ident.as_str().to_string(),
+    }
+}
+
+fn unknown(kind: &str, name: &str, known: &[&str]) -> String {
+    match did_you_mean(name, known) {
+        Some(suggestion) => format!("unknown {kind} '{name}', did you mean '{suggestion}'?"),
+        None => format!("unknown {kind} '{name}'"),
+    }
+}
+
+/// JSON Schema (draft 2020-12) for scenario files in HCL's JSON form, where labelled blocks are
+/// objects keyed by label. `probe_schema` describes the type-specific probe attributes; the
+/// common `description` and `phase` attributes are added here.
+#[must_use]
+pub fn scenario_json_schema(probe_schema: &Value) -> Value {
+    let mut schema = body_schema(&ROOT, probe_schema);
+    if let Value::Object(map) = &mut schema {
+        map.insert(
+            "$schema".into(),
+            json!("https://json-schema.org/draft/2020-12/schema"),
+        );
+        map.insert("title".into(), json!("intar scenario"));
+    }
+    schema
+}
+
+fn body_schema(schema: &BlockSchema, probe_schema: &Value) -> Value {
+    let Some(attrs) = schema.attrs else {
+        // With `use`, the preset supplies `type` and the attributes it needs.
+        let presets: Vec<String> = builtin_probes()
+            .unwrap_or_default()
+            .iter()
+            .map(|preset| format!("{BUILTIN_PROBE_PREFIX}{}", preset.name))
+            .collect();
+        return json!({
+            "description": schema.description,
+            "allOf": [
+                { "anyOf": [probe_schema, { "required": ["use"] }] },
+                {
+                    "type": "object",
+                    "properties": {
+                        "use": { "enum": presets },
+                        "description": { "type": "string" },
+                        "phase": { "enum": ["boot", "scenario", "teardown"] },
+                    },
+                },
+            ],
+        });
+    };
+
+    let mut properties = Map::new();
+    for attr in attrs {
+        properties.insert(attr.name.into(), attr_schema(attr.ty));
+    }
+    for (name, inner) in schema.blocks {
+        properties.insert((*name).into(), block_schema(inner, probe_schema));
+    }
+    let required: Vec<&str> = attrs
+        .iter()
+        .filter(|attr| attr.required)
+        .map(|attr| attr.name)
+        .collect();
+
+    json!({
+        "description": schema.description,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": schema.extra_attrs,
+    })
+}
+
+/// A nested block: an object keyed by label for labelled blocks, otherwise one body or a list
+/// of them.
+fn block_schema(schema: &BlockSchema, probe_schema: &Value) -> Value {
+    let body = body_schema(schema, probe_schema);
+    match schema.label {
+        Some(label) => json!({
+            "description": format!("{} Keyed by {label}.", schema.description),
+            "type": "object",
+            "additionalProperties": body,
+        }),
+        None => json!({ "oneOf": [body, { "type": "array", "items": body }] }),
+    }
+}
+
+fn attr_schema(ty: AttrType) -> Value {
+    match ty {
+        AttrType::String => json!({ "type": "string" }),
+        AttrType::Number => json!({ "type": "integer", "minimum": 0 }),
+        AttrType::Bool => json!({ "type": "boolean" }),
+        AttrType::StringList => json!({ "type": "array", "items": { "type": "string" } }),
+        AttrType::StringOrList => json!({
+            "oneOf": [
+                { "type": "string" },
+                { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+            ],
+        }),
+        AttrType::StringMap => {
+            json!({ "type": "object", "additionalProperties": { "type": "string" } })
+        }
+        AttrType::LocalizedString => json!({
+            "oneOf": [
+                { "type": "string" },
+                {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "minProperties": 1,
+                },
+            ],
+        }),
+        AttrType::Trigger => json!({
+            "oneOf": [
+                { "enum": ["boot", "manual"] },
+                {
+                    "type": "object",
+                    "properties": { "after": { "type": "string" } },
+                    "required": ["after"],
+                },
+            ],
+        }),
+    }
+}
@@ -0,0 +1,156 @@
+use crate::CoreError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+/// An IPv4 network intar addresses guests in, written `a.b.c.d/prefix`. Prefixes from 8 to 24
+/// are accepted so every subnet has room for intar's fixed host numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ipv4Subnet {
+    network: Ipv4Addr,
+    prefix: u8,
+}
+
+impl Ipv4Subnet {
+    /// User-mode management network every VM gets (QEMU's default).
+    pub const DEFAULT_MANAGEMENT: Self = Self {
+        network: Ipv4Addr::new(10, 0, 2, 0),
+        prefix: 24,
+    };
+
+    /// Shared LAN between the VMs of a multi-VM scenario.
+    pub const DEFAULT_LAN: Self = Self {
+        network: Ipv4Addr::new(10, 11, 0, 0),
+        prefix: 24,
+    };
+
+    #[must_use]
+    pub fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    #[must_use]
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// The address `index` past the network address, e.g. `10.0.2.100` for 100 in
+    /// `10.0.2.0/24`. Every accepted subnet spans at least 256 addresses.
+    #[must_use]
+    pub fn host(&self, index: u8) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from(self.network) + u32::from(index))
+    }
+
+    /// Netmask of the prefix, e.g. `255.255.255.0` for `/24`.
+    #[must_use]
+    pub fn netmask(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.mask())
+    }
+
+    #[must_use]
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        u32::from(ip) & self.mask() == u32::from(self.network)
+    }
+
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.contains(other.network) || other.contains(self.network)
+    }
+
+    /// Reverse lookup zone covering the subnet, e.g. `0.11.10.in-addr.arpa` for `10.11.0.0/24`.
+    /// Prefixes that are not a multiple of 8 get the enclosing zone.
+    #[must_use]
+    pub fn reverse_zone(&self) -> String {
+        let octets = self.network.octets();
+        let mut labels: Vec<String> = octets[..usize::from(self.prefix / 8)]
+            .iter()
+            .rev()
+            .map(ToString::to_string)
+            .collect();
+        labels.push("in-addr.arpa".into());
+        labels.join(".")
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX << (32 - self.prefix)
+    }
+}
+
+impl FromStr for Ipv4Subnet {
+    type Err = CoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| CoreError::InvalidScenario(format!("subnet '{s}' {reason}"));
+        let (addr, prefix) = s
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| invalid("must be written a.b.c.d/prefix"))?;
+        let network: Ipv4Addr = addr
+            .parse()
+            .map_err(|_| invalid("has an invalid address"))?;
+        let prefix: u8 = prefix
+            .parse()
+            .ok()
+            .filter(|prefix| (8..=24).contains(prefix))
+            .ok_or_else(|| invalid("needs a prefix length from 8 to 24"))?;
+        let subnet = Self { network, prefix };
+        if u32::from(network) & !subnet.mask() != 0 {
+            return Err(invalid("has host bits set"));
+        }
+        Ok(subnet)
+    }
+}
+
+impl TryFrom<String> for Ipv4Subnet {
+    type Error = CoreError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Ipv4Subnet> for String {
+    fn from(subnet: Ipv4Subnet) -> Self {
+        subnet.to_string()
+    }
+}
+
+impl fmt::Display for Ipv4Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subnet() {
+        let subnet: Ipv4Subnet = "172.30.8.0/22".parse().unwrap();
+        assert_eq!(subnet.to_string(), "172.30.8.0/22");
+        assert_eq!(subnet.host(2), Ipv4Addr::new(172, 30, 8, 2));
+        assert_eq!(subnet.netmask(), Ipv4Addr::new(255, 255, 252, 0));
+        assert!(subnet.contains(Ipv4Addr::new(172, 30, 11, 255)));
+        assert!(!subnet.contains(Ipv4Addr::new(172, 30, 12, 0)));
+        assert_eq!(subnet.reverse_zone(), "30.172.in-addr.arpa");
+        assert_eq!(
+            Ipv4Subnet::DEFAULT_LAN.reverse_zone(),
+            "0.11.10.in-addr.arpa"
+        );
+
+        for bad in ["10.0.2.0", "10.0.2.5/24", "10.0.2.0/28", "10.0.300.0/24"] {
+            assert!(bad.parse::<Ipv4Subnet>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_subnet_overlap() {
+        let wide: Ipv4Subnet = "10.0.0.0/16".parse().unwrap();
+        assert!(wide.overlaps(&Ipv4Subnet::DEFAULT_MANAGEMENT));
+        assert!(Ipv4Subnet::DEFAULT_MANAGEMENT.overlaps(&wide));
+        assert!(!Ipv4Subnet::DEFAULT_MANAGEMENT.overlaps(&Ipv4Subnet::DEFAULT_LAN));
+    }
+}
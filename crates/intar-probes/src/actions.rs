@@ -1,4 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Session output the agent forwards per second before it drops the rest, so `yes` or a noisy
+/// build cannot flood the actions port.
+pub const OUTPUT_BYTES_PER_SEC: usize = 64 * 1024;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -21,27 +26,204 @@ pub enum ActionEvent {
         ts_unix_ms: u64,
         data_b64: String,
     },
+    /// A command line typed in a session. Sent before the input that ends it, so the host can
+    /// tell where the command it derives from the raw input ran.
     SshLine {
         ts_unix_ms: u64,
         line: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<ShellContext>,
     },
     SshOutput {
         ts_unix_ms: u64,
         line: String,
     },
+    /// Session output the agent dropped over its output budget since the last chunk it sent.
+    /// The learner's terminal still received all of it.
+    SshOutputSuppressed {
+        ts_unix_ms: u64,
+        lines: u64,
+        bytes: u64,
+    },
     SshSessionEnd {
         ts_unix_ms: u64,
         exit_code: i32,
     },
+    /// Written by cloud-init as the guest passes a boot milestone.
+    BootStage {
+        ts_unix_ms: u64,
+        stage: BootStage,
+    },
     Error {
         ts_unix_ms: u64,
         message: String,
     },
 }
 
+/// Guest boot milestones, in the order cloud-init reaches them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootStage {
+    /// Networking is configured (cloud-init `bootcmd`).
+    Network,
+    /// Scenario packages are installed (start of `runcmd`).
+    Packages,
+    /// Scenario steps and `runcmd` entries have run.
+    Steps,
+}
+
+impl BootStage {
+    pub const ALL: [Self; 3] = [Self::Network, Self::Packages, Self::Steps];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::Packages => "packages",
+            Self::Steps => "steps",
+        }
+    }
+}
+
+/// Where a command line was typed: the working directory and effective user of the process in
+/// the session's foreground when it was entered.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellContext {
+    pub cwd: String,
+    pub user: String,
+}
+
+impl ShellContext {
+    /// Whether the command ran as root, e.g. after `sudo -i`.
+    #[must_use]
+    pub fn is_root(&self) -> bool {
+        self.user == "root"
+    }
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SshSessionKind {
     Interactive,
     Command,
 }
+
+/// Decides which chunks of a session's output the agent forwards: up to a byte budget per
+/// second, dropping and counting the rest until the next second starts.
+#[derive(Debug, Clone)]
+pub struct OutputLimiter {
+    bytes_per_sec: usize,
+    window_start: Option<Instant>,
+    window_bytes: usize,
+    suppressed_lines: u64,
+    suppressed_bytes: u64,
+}
+
+impl OutputLimiter {
+    #[must_use]
+    pub fn new(bytes_per_sec: usize) -> Self {
+        Self {
+            bytes_per_sec,
+            window_start: None,
+            window_bytes: 0,
+            suppressed_lines: 0,
+            suppressed_bytes: 0,
+        }
+    }
+
+    /// Whether `chunk`, read at `now`, fits the current second's budget. The first chunk of a
+    /// second always does, however large.
+    pub fn admit(&mut self, chunk: &[u8], now: Instant) -> bool {
+        if self
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= Duration::from_secs(1))
+        {
+            self.window_start = Some(now);
+            self.window_bytes = 0;
+        }
+        if self.window_bytes == 0 || self.window_bytes + chunk.len() <= self.bytes_per_sec {
+            self.window_bytes += chunk.len();
+            return true;
+        }
+        let lines = chunk.iter().filter(|byte| **byte == b'\n').count();
+        self.suppressed_lines += u64::try_from(lines).unwrap_or(u64::MAX);
+        self.suppressed_bytes += u64::try_from(chunk.len()).unwrap_or(u64::MAX);
+        false
+    }
+
+    /// The marker for output dropped since the last call, if any was.
+    pub fn take_suppressed(&mut self, ts_unix_ms: u64) -> Option<ActionEvent> {
+        if self.suppressed_bytes == 0 {
+            return None;
+        }
+        Some(ActionEvent::SshOutputSuppressed {
+            ts_unix_ms,
+            lines: std::mem::take(&mut self.suppressed_lines),
+            bytes: std::mem::take(&mut self.suppressed_bytes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_stage_from_guest_json() {
+        let line = r#"{"type":"boot_stage","ts_unix_ms":1700000000000,"stage":"packages"}"#;
+        assert!(matches!(
+            serde_json::from_str::<ActionEvent>(line).unwrap(),
+            ActionEvent::BootStage {
+                stage: BootStage::Packages,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_ssh_line_context_is_optional() {
+        let line = r#"{"type":"ssh_line","ts_unix_ms":1,"line":"ls"}"#;
+        assert!(matches!(
+            serde_json::from_str::<ActionEvent>(line).unwrap(),
+            ActionEvent::SshLine { context: None, .. }
+        ));
+
+        let event = ActionEvent::SshLine {
+            ts_unix_ms: 1,
+            line: "systemctl restart nginx".into(),
+            context: Some(ShellContext {
+                cwd: "/etc/nginx".into(),
+                user: "root".into(),
+            }),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""context":{"cwd":"/etc/nginx","user":"root"}"#));
+        let ActionEvent::SshLine { context, .. } = serde_json::from_str(&json).unwrap() else {
+            panic!("expected ssh_line");
+        };
+        assert!(context.unwrap().is_root());
+    }
+
+    #[test]
+    fn test_output_limiter() {
+        let start = Instant::now();
+        let mut limiter = OutputLimiter::new(10);
+        assert!(limiter.admit(b"y\ny\ny\n", start));
+        assert!(limiter.admit(b"y\ny\n", start));
+        assert!(!limiter.admit(b"y\ny\n", start));
+        assert!(!limiter.admit(b"y\n", start + Duration::from_millis(500)));
+        assert!(limiter.take_suppressed(1).is_some());
+        assert!(limiter.take_suppressed(1).is_none());
+
+        assert!(!limiter.admit(b"y\ny\n", start + Duration::from_millis(900)));
+        assert!(limiter.admit(&[b'y'; 32], start + Duration::from_secs(1)));
+        assert!(matches!(
+            limiter.take_suppressed(2),
+            Some(ActionEvent::SshOutputSuppressed {
+                lines: 2,
+                bytes: 4,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{ProbeEvaluator, ProbeResult, ProbeSpec};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Evaluate a cached probe again after this long even when nothing it watches changed, so
+/// state the watch list misses still shows up eventually.
+const MAX_CACHE_AGE: Duration = Duration::from_secs(60);
+
+/// Wraps another evaluator and reuses a probe's last result while nothing in its
+/// [`ProbeSpec::watch`] list has changed. Probes without a watch list are always evaluated.
+#[derive(Debug)]
+pub struct CachingEvaluator<E> {
+    inner: E,
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    spec: ProbeSpec,
+    inputs: Vec<String>,
+    result: ProbeResult,
+    at: Instant,
+}
+
+impl<E: ProbeEvaluator> CachingEvaluator<E> {
+    #[must_use]
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<E: ProbeEvaluator> ProbeEvaluator for CachingEvaluator<E> {
+    fn evaluate(&mut self, id: &str, spec: &ProbeSpec) -> ProbeResult {
+        let watch = spec.watch();
+        if watch.is_empty() {
+            self.entries.remove(id);
+            return self.inner.evaluate(id, spec);
+        }
+
+        let inputs = watched_inputs(watch);
+        if let Some(entry) = self.entries.get(id)
+            && entry.spec == *spec
+            && entry.inputs == inputs
+            && entry.at.elapsed() < MAX_CACHE_AGE
+        {
+            return entry.result.clone();
+        }
+
+        let result = self.inner.evaluate(id, spec);
+        self.entries.insert(
+            id.to_string(),
+            CacheEntry {
+                spec: spec.clone(),
+                inputs,
+                result: result.clone(),
+                at: Instant::now(),
+            },
+        );
+        result
+    }
+}
+
+/// A snapshot of everything in `watch`: the modification time of each path and of a
+/// directory's direct children, then the state of the named systemd units.
+fn watched_inputs(watch: &[String]) -> Vec<String> {
+    let (paths, units): (Vec<&String>, Vec<&String>) =
+        watch.iter().partition(|item| item.starts_with('/'));
+
+    let mut inputs = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        inputs.push(format!("{}={}", path.display(), modified(path)));
+        if let Ok(entries) = fs::read_dir(path) {
+            let mut children: Vec<_> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+            children.sort();
+            for child in children {
+                inputs.push(format!("{}={}", child.display(), modified(&child)));
+            }
+        }
+    }
+
+    if !units.is_empty() {
+        let state = Command::new("systemctl")
+            .args([
+                "show",
+                "-p",
+                "ActiveState",
+                "-p",
+                "ActiveEnterTimestampMonotonic",
+            ])
+            .args(units)
+            .output()
+            .map_or_else(
+                |e| format!("systemctl failed: {e}"),
+                |output| String::from_utf8_lossy(&output.stdout).into_owned(),
+            );
+        inputs.push(state);
+    }
+    inputs
+}
+
+fn modified(path: &Path) -> String {
+    fs::symlink_metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or_else(
+            || "missing".to_string(),
+            |since| since.as_nanos().to_string(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FixtureEvaluator, ProbeFixture};
+
+    fn fixture() -> ProbeFixture {
+        serde_json::from_str(
+            r#"{ "probes": { "api": [
+                { "passed": false, "message": "connection refused" },
+                { "passed": true }
+            ] } }"#,
+        )
+        .unwrap()
+    }
+
+    fn http(watch: Vec<String>) -> ProbeSpec {
+        ProbeSpec::Http {
+            url: "http://localhost:8080/health".into(),
+            status: 200,
+            body_contains: None,
+            watch,
+        }
+    }
+
+    #[test]
+    fn test_caching_evaluator_reuses_until_watched_file_changes() {
+        let path = std::env::temp_dir().join(format!("intar-probe-cache-{}", std::process::id()));
+        fs::write(&path, "port = 8080").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+            .unwrap();
+
+        let spec = http(vec![path.to_string_lossy().into_owned()]);
+        let mut evaluator = CachingEvaluator::new(FixtureEvaluator::new(fixture()));
+        assert!(!evaluator.evaluate("api", &spec).passed);
+        assert!(!evaluator.evaluate("api", &spec).passed);
+        assert_eq!(evaluator.inner.evaluations("api"), 1);
+
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(2_000))
+            .unwrap();
+        assert!(evaluator.evaluate("api", &spec).passed);
+        assert_eq!(evaluator.inner.evaluations("api"), 2);
+        drop(file);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_caching_evaluator_without_watch_always_evaluates() {
+        let mut evaluator = CachingEvaluator::new(FixtureEvaluator::new(fixture()));
+        assert!(!evaluator.evaluate("api", &http(Vec::new())).passed);
+        assert!(evaluator.evaluate("api", &http(Vec::new())).passed);
+        assert_eq!(evaluator.inner.evaluations("api"), 2);
+    }
+}
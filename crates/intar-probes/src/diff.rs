@@ -0,0 +1,189 @@
+use std::fmt::Write as _;
+
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Largest `expected.len() * actual.len()` compared line by line; bigger inputs are shown
+/// as replaced wholesale.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A unified diff turning `expected` into `actual`, labelled `from` and `to` in its header,
+/// or an empty string when they are the same.
+#[must_use]
+pub fn unified_diff(expected: &[&str], actual: &[&str], from: &str, to: &str) -> String {
+    let ops = line_ops(expected, actual);
+    if ops.iter().all(|op| *op == Op::Equal) {
+        return String::new();
+    }
+
+    // Position of each op in `expected` and `actual` before it applies.
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old, mut new) = (0, 0);
+    for op in &ops {
+        positions.push((old, new));
+        match op {
+            Op::Equal => {
+                old += 1;
+                new += 1;
+            }
+            Op::Delete => old += 1,
+            Op::Insert => new += 1,
+        }
+    }
+
+    let mut out = format!("--- {from}\n+++ {to}\n");
+    let mut start = 0;
+    while let Some(first_change) = ops[start..]
+        .iter()
+        .position(|op| *op != Op::Equal)
+        .map(|i| i + start)
+    {
+        let hunk_start = first_change.saturating_sub(CONTEXT_LINES).max(start);
+        // Extend the hunk while the next change is close enough to share context.
+        let mut hunk_end = first_change;
+        let mut i = first_change;
+        while i < ops.len() {
+            if ops[i] == Op::Equal {
+                i += 1;
+                continue;
+            }
+            if i > hunk_end + 2 * CONTEXT_LINES {
+                break;
+            }
+            hunk_end = i;
+            i += 1;
+        }
+        let hunk_end = (hunk_end + 1 + CONTEXT_LINES).min(ops.len());
+
+        let (old_start, new_start) = positions[hunk_start];
+        let old_len = ops[hunk_start..hunk_end]
+            .iter()
+            .filter(|op| **op != Op::Insert)
+            .count();
+        let new_len = ops[hunk_start..hunk_end]
+            .iter()
+            .filter(|op| **op != Op::Delete)
+            .count();
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            hunk_line(old_start, old_len),
+            hunk_line(new_start, new_len)
+        );
+        for (op, (old, new)) in ops[hunk_start..hunk_end]
+            .iter()
+            .zip(&positions[hunk_start..hunk_end])
+        {
+            let _ = match op {
+                Op::Equal => writeln!(out, " {}", expected[*old]),
+                Op::Delete => writeln!(out, "-{}", expected[*old]),
+                Op::Insert => writeln!(out, "+{}", actual[*new]),
+            };
+        }
+        start = hunk_end;
+    }
+    out
+}
+
+/// 1-based start line of a hunk; empty ranges name the line before them.
+fn hunk_line(start: usize, len: usize) -> usize {
+    if len == 0 { start } else { start + 1 }
+}
+
+/// The edits of a longest-common-subsequence alignment of the two inputs.
+fn line_ops(expected: &[&str], actual: &[&str]) -> Vec<Op> {
+    let (n, m) = (expected.len(), actual.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        let mut ops = vec![Op::Delete; n];
+        ops.extend(std::iter::repeat_n(Op::Insert, m));
+        return ops;
+    }
+
+    // lcs[i][j]: longest common subsequence of expected[i..] and actual[j..].
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if expected[i] == actual[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Delete, n - i));
+    ops.extend(std::iter::repeat_n(Op::Insert, m - j));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let expected = [
+            "user www-data;",
+            "worker_processes auto;",
+            "",
+            "events {",
+            "}",
+        ];
+        let actual = [
+            "user www-data;",
+            "worker_processes 4;",
+            "",
+            "events {",
+            "}",
+            "# added",
+        ];
+        assert_eq!(
+            unified_diff(&expected, &actual, "template", "/etc/nginx/nginx.conf"),
+            "--- template\n+++ /etc/nginx/nginx.conf\n\
+             @@ -1,5 +1,6 @@\n \
+             user www-data;\n\
+             -worker_processes auto;\n\
+             +worker_processes 4;\n \
+             \n \
+             events {\n \
+             }\n\
+             +# added\n"
+        );
+        assert_eq!(unified_diff(&expected, &expected, "a", "b"), "");
+    }
+
+    #[test]
+    fn test_unified_diff_splits_distant_hunks() {
+        let expected: Vec<String> = (1..=20).map(|i| format!("line {i}")).collect();
+        let mut actual = expected.clone();
+        actual[1] = "changed 2".into();
+        actual[17] = "changed 18".into();
+        let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+        let actual: Vec<&str> = actual.iter().map(String::as_str).collect();
+        let diff = unified_diff(&expected, &actual, "a", "b");
+        let hunks: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(hunks, vec!["@@ -1,5 +1,5 @@", "@@ -15,6 +15,6 @@"]);
+    }
+}
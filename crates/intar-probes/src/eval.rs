@@ -1,4 +1,7 @@
-use crate::{PortState, ProbeResult, ProbeSpec, Protocol, ServiceState};
+use crate::{
+    PortState, ProbeDetails, ProbeResult, ProbeSpec, Protocol, ServiceState, flag_hash, run_plugin,
+    sudo_invocations, unified_diff,
+};
 use std::fs;
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
@@ -6,57 +9,178 @@ use std::process::Command;
 use std::sync::OnceLock;
 use std::time::Duration;
 
+/// What an evaluator reports: its message and the structured details behind it.
+struct Finding {
+    message: String,
+    details: ProbeDetails,
+}
+
+impl Finding {
+    fn new(message: String, details: ProbeDetails) -> Self {
+        Self { message, details }
+    }
+}
+
+impl From<String> for Finding {
+    fn from(message: String) -> Self {
+        Self::new(message, ProbeDetails::default())
+    }
+}
+
 #[must_use]
 pub fn evaluate_probe(id: &str, spec: &ProbeSpec) -> ProbeResult {
     match evaluate_probe_inner(spec) {
-        Ok(message) => ProbeResult::pass(id, message),
-        Err(message) => ProbeResult::fail(id, message),
+        Ok(found) => ProbeResult::pass(id, found.message).with_details(found.details),
+        Err(found) => ProbeResult::fail(id, found.message).with_details(found.details),
+    }
+}
+
+/// A result of an evaluator without structured details.
+fn plain(result: Result<String, String>) -> Result<Finding, Finding> {
+    result.map(Finding::from).map_err(Finding::from)
+}
+
+/// `result` with `details` attached, whether it passed or not.
+fn detailed(result: Result<String, String>, details: ProbeDetails) -> Result<Finding, Finding> {
+    match result {
+        Ok(message) => Ok(Finding::new(message, details)),
+        Err(message) => Err(Finding::new(message, details)),
     }
 }
 
-fn evaluate_probe_inner(spec: &ProbeSpec) -> Result<String, String> {
+fn evaluate_probe_inner(spec: &ProbeSpec) -> Result<Finding, Finding> {
     match spec {
         ProbeSpec::FileContent {
             path,
             contains,
             regex,
-        } => eval_file_content(path, contains.as_deref(), regex.as_deref()),
-        ProbeSpec::FileExists { path, exists } => eval_file_exists(path, *exists),
+        } => plain(eval_file_content(
+            path,
+            contains.as_deref(),
+            regex.as_deref(),
+        )),
+        ProbeSpec::FileExists { path, exists } => plain(eval_file_exists(path, *exists)),
+        ProbeSpec::FileMatchesTemplate {
+            path,
+            template,
+            ignore_patterns,
+        } => eval_file_matches_template(path, template, ignore_patterns),
         ProbeSpec::Service { service, state } => eval_service(service, *state),
         ProbeSpec::Port {
             port,
             state,
             protocol,
-        } => eval_port(*port, *state, *protocol),
+            address,
+        } => eval_port(*port, *state, *protocol, address.as_deref()),
         ProbeSpec::Command {
             cmd,
             exit_code,
             stdout_contains,
+            ..
         } => eval_command(cmd, *exit_code, stdout_contains.as_deref()),
         ProbeSpec::Http {
             url,
             status,
             body_contains,
+            ..
         } => eval_http(url, *status, body_contains.as_deref()),
         ProbeSpec::K8sNodesReady {
             expected_ready,
             kubeconfig,
             context,
-        } => eval_k8s_nodes_ready(*expected_ready, kubeconfig.as_deref(), context.as_deref()),
+            ..
+        } => plain(eval_k8s_nodes_ready(
+            *expected_ready,
+            kubeconfig.as_deref(),
+            context.as_deref(),
+        )),
         ProbeSpec::K8sEndpointsNonEmpty {
             namespace,
             name,
             kubeconfig,
             context,
-        } => {
-            eval_k8s_endpoints_nonempty(namespace, name, kubeconfig.as_deref(), context.as_deref())
-        }
+            ..
+        } => plain(eval_k8s_endpoints_nonempty(
+            namespace,
+            name,
+            kubeconfig.as_deref(),
+            context.as_deref(),
+        )),
         ProbeSpec::TcpPing {
             host,
             port,
             timeout_ms,
             state,
-        } => eval_tcp_ping(host, *port, Duration::from_millis(*timeout_ms), *state),
+        } => plain(eval_tcp_ping(
+            host,
+            *port,
+            Duration::from_millis(*timeout_ms),
+            *state,
+        )),
+        ProbeSpec::Flag { sha256, path } => plain(eval_flag(sha256, path.as_deref())),
+        ProbeSpec::SudoUsed { used } => eval_sudo_used(*used),
+        ProbeSpec::Plugin {
+            plugin,
+            privileged,
+            config,
+        } => eval_plugin(plugin, config, *privileged),
+    }
+}
+
+fn eval_plugin(
+    plugin: &str,
+    config: &serde_json::Map<String, serde_json::Value>,
+    privileged: bool,
+) -> Result<Finding, Finding> {
+    let reply = run_plugin(plugin, config, privileged)?;
+    let message = if reply.message.is_empty() {
+        format!(
+            "Plugin '{plugin}' {}",
+            if reply.passed { "passed" } else { "failed" }
+        )
+    } else {
+        reply.message
+    };
+    let details = ProbeDetails {
+        observed: reply.observed,
+        expected: reply.expected,
+        ..ProbeDetails::default()
+    };
+    let found = Finding::new(message, details);
+    if reply.passed { Ok(found) } else { Err(found) }
+}
+
+fn eval_sudo_used(expected: bool) -> Result<Finding, Finding> {
+    let invocations = sudo_invocations()?;
+    let details = ProbeDetails::default().with_values(
+        format!("{} invocation(s)", invocations.len()),
+        if expected { "at least one" } else { "none" },
+    );
+    let summary = match invocations.last() {
+        None => "sudo was not used".to_string(),
+        Some(last) => format!(
+            "sudo was used {} time(s), last by {last}",
+            invocations.len()
+        ),
+    };
+    let found = Finding::new(summary, details);
+    if invocations.is_empty() == expected {
+        Err(found)
+    } else {
+        Ok(found)
+    }
+}
+
+fn eval_flag(sha256: &str, path: Option<&str>) -> Result<String, String> {
+    let Some(path) = path else {
+        return Err("Waiting for flag submission".into());
+    };
+    let content = fs::read_to_string(path).map_err(|_| format!("No flag found at '{path}'"))?;
+
+    if flag_hash(&content).eq_ignore_ascii_case(sha256) {
+        Ok(format!("Correct flag at '{path}'"))
+    } else {
+        Err(format!("Flag at '{path}' is incorrect"))
     }
 }
 
@@ -85,6 +209,41 @@ fn eval_file_content(
     Ok(format!("File '{path}' content matches criteria"))
 }
 
+fn eval_file_matches_template(
+    path: &str,
+    template: &str,
+    ignore_patterns: &[String],
+) -> Result<Finding, Finding> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read file '{path}': {e}"))?;
+    let ignore = ignore_patterns
+        .iter()
+        .map(|pattern| {
+            regex::Regex::new(pattern).map_err(|e| format!("Invalid regex '{pattern}': {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let diff = unified_diff(
+        &compared_lines(template, &ignore),
+        &compared_lines(&content, &ignore),
+        "template",
+        path,
+    );
+    if diff.is_empty() {
+        Ok(format!("File '{path}' matches its template").into())
+    } else {
+        Err(Finding::new(
+            format!("File '{path}' differs from its template:\n{diff}"),
+            ProbeDetails::default().with_diff(diff),
+        ))
+    }
+}
+
+fn compared_lines<'a>(text: &'a str, ignore: &[regex::Regex]) -> Vec<&'a str> {
+    text.lines()
+        .filter(|line| !ignore.iter().any(|re| re.is_match(line)))
+        .collect()
+}
+
 fn eval_file_exists(path: &str, should_exist: bool) -> Result<String, String> {
     let exists = std::path::Path::new(path).exists();
 
@@ -101,7 +260,7 @@ fn eval_file_exists(path: &str, should_exist: bool) -> Result<String, String> {
     }
 }
 
-fn eval_service(service: &str, expected_state: ServiceState) -> Result<String, String> {
+fn eval_service(service: &str, expected_state: ServiceState) -> Result<Finding, Finding> {
     match expected_state {
         ServiceState::Running | ServiceState::Stopped => {
             let output = Command::new("systemctl")
@@ -112,7 +271,7 @@ fn eval_service(service: &str, expected_state: ServiceState) -> Result<String, S
             let is_active = output.status.success();
             let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
-            match (expected_state, is_active) {
+            let result = match (expected_state, is_active) {
                 (ServiceState::Running, true) => Ok(format!("Service '{service}' is {status}")),
                 (ServiceState::Running, false) => {
                     Err(format!("Service '{service}' is not running ({status})"))
@@ -122,7 +281,16 @@ fn eval_service(service: &str, expected_state: ServiceState) -> Result<String, S
                     "Service '{service}' is running but should be stopped"
                 )),
                 _ => unreachable!(),
-            }
+            };
+            let expected = if expected_state == ServiceState::Running {
+                "active"
+            } else {
+                "inactive"
+            };
+            detailed(
+                result,
+                ProbeDetails::default().with_values(status, expected),
+            )
         }
         ServiceState::Enabled | ServiceState::Disabled => {
             let output = Command::new("systemctl")
@@ -133,7 +301,7 @@ fn eval_service(service: &str, expected_state: ServiceState) -> Result<String, S
             let is_enabled = output.status.success();
             let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
-            match (expected_state, is_enabled) {
+            let result = match (expected_state, is_enabled) {
                 (ServiceState::Enabled, true) => Ok(format!("Service '{service}' is {status}")),
                 (ServiceState::Enabled, false) => {
                     Err(format!("Service '{service}' is not enabled ({status})"))
@@ -143,22 +311,64 @@ fn eval_service(service: &str, expected_state: ServiceState) -> Result<String, S
                     "Service '{service}' is enabled but should be disabled"
                 )),
                 _ => unreachable!(),
-            }
+            };
+            let expected = if expected_state == ServiceState::Enabled {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            detailed(
+                result,
+                ProbeDetails::default().with_values(status, expected),
+            )
         }
     }
 }
 
-fn eval_port(port: u16, expected_state: PortState, protocol: Protocol) -> Result<String, String> {
+fn eval_port(
+    port: u16,
+    expected_state: PortState,
+    protocol: Protocol,
+    address: Option<&str>,
+) -> Result<Finding, Finding> {
+    let address = address
+        .map(|address| {
+            unbracket(address)
+                .parse::<IpAddr>()
+                .map_err(|_| format!("Invalid address '{address}'"))
+        })
+        .transpose()?;
+    let target = match address {
+        Some(ip) => format!("{port} on {ip}"),
+        None => port.to_string(),
+    };
     let is_listening = tokio_runtime()?
-        .block_on(is_port_listening(port, protocol))
-        .map_err(|e| format!("Failed to check port {port}: {e}"))?;
+        .block_on(is_port_listening(port, protocol, address))
+        .map_err(|e| format!("Failed to check port {target}: {e}"))?;
+
+    let result = match (expected_state, is_listening) {
+        (PortState::Listening, true) => Ok(format!("Port {target} is listening")),
+        (PortState::Listening, false) => Err(format!("Port {target} is not listening")),
+        (PortState::Closed, false) => Ok(format!("Port {target} is closed")),
+        (PortState::Closed, true) => {
+            Err(format!("Port {target} is listening but should be closed"))
+        }
+    };
+    let state = |listening: bool| if listening { "listening" } else { "closed" };
+    detailed(
+        result,
+        ProbeDetails::default().with_values(
+            state(is_listening),
+            state(expected_state == PortState::Listening),
+        ),
+    )
+}
 
-    match (expected_state, is_listening) {
-        (PortState::Listening, true) => Ok(format!("Port {port} is listening")),
-        (PortState::Listening, false) => Err(format!("Port {port} is not listening")),
-        (PortState::Closed, false) => Ok(format!("Port {port} is closed")),
-        (PortState::Closed, true) => Err(format!("Port {port} is listening but should be closed")),
-    }
+/// `host` without the brackets of an IPv6 literal such as `[fd00:11::10]`.
+fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .unwrap_or(host)
 }
 
 fn tokio_runtime() -> Result<&'static tokio::runtime::Runtime, String> {
@@ -174,10 +384,26 @@ fn tokio_runtime() -> Result<&'static tokio::runtime::Runtime, String> {
     runtime.as_ref().map_err(Clone::clone)
 }
 
-async fn is_port_listening(port: u16, protocol: Protocol) -> Result<bool, String> {
-    match protocol {
-        Protocol::Tcp => is_tcp_port_listening(port).await,
-        Protocol::Udp => is_udp_port_listening(port).await,
+async fn is_port_listening(
+    port: u16,
+    protocol: Protocol,
+    address: Option<IpAddr>,
+) -> Result<bool, String> {
+    let Some(ip) = address else {
+        return match protocol {
+            Protocol::Tcp => is_tcp_port_listening(port).await,
+            Protocol::Udp => is_udp_port_listening(port).await,
+        };
+    };
+    let addr = SocketAddr::new(ip, port);
+    let attempt = match protocol {
+        Protocol::Tcp => tcp_connect_attempt(addr, Duration::from_millis(500)).await?,
+        Protocol::Udp => udp_bind_attempt(addr).await?,
+    };
+    match attempt {
+        PortReachability::Listening => Ok(true),
+        PortReachability::Closed => Ok(false),
+        PortReachability::AddressUnavailable => Err(format!("{ip} is not an address of this host")),
     }
 }
 
@@ -288,9 +514,15 @@ fn eval_tcp_ping(
     timeout: Duration,
     expected_state: crate::ReachabilityState,
 ) -> Result<String, String> {
+    let host = unbracket(host);
+    let endpoint = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
     let reachable = tokio_runtime()?
         .block_on(tcp_reachable(host, port, timeout))
-        .map_err(|e| format!("Failed to check reachability for {host}:{port}: {e}"))?;
+        .map_err(|e| format!("Failed to check reachability for {endpoint}: {e}"))?;
 
     match (expected_state, reachable) {
         (crate::ReachabilityState::Reachable, true) => Ok(format!("{host} is reachable")),
@@ -578,7 +810,7 @@ fn eval_command(
     cmd: &str,
     expected_exit_code: i32,
     stdout_contains: Option<&str>,
-) -> Result<String, String> {
+) -> Result<Finding, Finding> {
     let output = Command::new("sh")
         .args(["-c", cmd])
         .output()
@@ -586,21 +818,29 @@ fn eval_command(
 
     let actual_exit_code = output.status.code().unwrap_or(-1);
 
+    let exit_codes = ProbeDetails::default()
+        .with_values(actual_exit_code.to_string(), expected_exit_code.to_string());
     if actual_exit_code != expected_exit_code {
-        return Err(format!(
-            "Command exited with code {actual_exit_code} (expected {expected_exit_code})"
+        return Err(Finding::new(
+            format!("Command exited with code {actual_exit_code} (expected {expected_exit_code})"),
+            exit_codes,
         ));
     }
 
     if let Some(needle) = stdout_contains {
         let stdout = String::from_utf8_lossy(&output.stdout);
         if !stdout.contains(needle) {
-            return Err(format!("Command output does not contain '{needle}'"));
+            return Err(Finding::new(
+                format!("Command output does not contain '{needle}'"),
+                ProbeDetails::default()
+                    .with_values(stdout.trim(), format!("output containing '{needle}'")),
+            ));
         }
     }
 
-    Ok(format!(
-        "Command succeeded with exit code {expected_exit_code}"
+    Ok(Finding::new(
+        format!("Command succeeded with exit code {expected_exit_code}"),
+        exit_codes,
     ))
 }
 
@@ -608,7 +848,7 @@ fn eval_http(
     url: &str,
     expected_status: u16,
     body_contains: Option<&str>,
-) -> Result<String, String> {
+) -> Result<Finding, Finding> {
     tokio_runtime()?.block_on(http_check(url, expected_status, body_contains))
 }
 
@@ -616,7 +856,7 @@ async fn http_check(
     url: &str,
     expected_status: u16,
     body_contains: Option<&str>,
-) -> Result<String, String> {
+) -> Result<Finding, Finding> {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(5))
         .redirect(reqwest::redirect::Policy::none())
@@ -630,8 +870,13 @@ async fn http_check(
         .map_err(|e| format!("Failed to make HTTP request: {e}"))?;
 
     let status = response.status().as_u16();
+    let statuses =
+        ProbeDetails::default().with_values(status.to_string(), expected_status.to_string());
     if status != expected_status {
-        return Err(format!("HTTP status {status} (expected {expected_status})"));
+        return Err(Finding::new(
+            format!("HTTP status {status} (expected {expected_status})"),
+            statuses,
+        ));
     }
 
     if let Some(needle) = body_contains {
@@ -641,9 +886,12 @@ async fn http_check(
             .map_err(|e| format!("Failed to read HTTP body: {e}"))?;
         let body = String::from_utf8_lossy(&body);
         if !body.contains(needle) {
-            return Err(format!("HTTP body does not contain '{needle}'"));
+            return Err(format!("HTTP body does not contain '{needle}'").into());
         }
     }
 
-    Ok(format!("HTTP {url} returned status {status}"))
+    Ok(Finding::new(
+        format!("HTTP {url} returned status {status}"),
+        statuses,
+    ))
 }
@@ -0,0 +1,207 @@
+use crate::{ProbeError, ProbeResult, ProbeSpec, evaluate_probe};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Something that turns a probe into a result: the real system, or a fixture in tests.
+pub trait ProbeEvaluator {
+    fn evaluate(&mut self, id: &str, spec: &ProbeSpec) -> ProbeResult;
+}
+
+/// Evaluates probes against the system it runs on, as the guest agent does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LiveEvaluator;
+
+impl ProbeEvaluator for LiveEvaluator {
+    fn evaluate(&mut self, id: &str, spec: &ProbeSpec) -> ProbeResult {
+        evaluate_probe(id, spec)
+    }
+}
+
+/// Probe results by probe id, in the order a run saw them. Written by [`RecordingEvaluator`]
+/// and replayed by [`FixtureEvaluator`]:
+///
+/// ```json
+/// { "probes": { "nginx-running": [
+///     { "passed": false, "message": "Service 'nginx' is not running (inactive)" },
+///     { "passed": true } ] } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeFixture {
+    #[serde(default)]
+    pub probes: BTreeMap<String, Vec<FixtureResult>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureResult {
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+}
+
+impl ProbeFixture {
+    /// Load a fixture from a JSON file.
+    ///
+    /// # Errors
+    /// Returns `ProbeError` if the file cannot be read or is not a fixture.
+    pub fn load(path: &Path) -> Result<Self, ProbeError> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            ProbeError::EvaluationFailed(format!("invalid fixture {}: {e}", path.display()))
+        })
+    }
+
+    /// Write the fixture as JSON.
+    ///
+    /// # Errors
+    /// Returns `ProbeError` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), ProbeError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| ProbeError::EvaluationFailed(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Append `result` to its probe's sequence.
+    pub fn push(&mut self, result: &ProbeResult) {
+        self.probes
+            .entry(result.id.clone())
+            .or_default()
+            .push(FixtureResult {
+                passed: result.passed,
+                message: result.message.clone(),
+            });
+    }
+}
+
+/// Replays a [`ProbeFixture`] without touching the system: each evaluation of a probe returns
+/// its next recorded result, and the last one repeats once the sequence runs out. Probes the
+/// fixture does not mention fail.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureEvaluator {
+    fixture: ProbeFixture,
+    evaluations: HashMap<String, usize>,
+}
+
+impl FixtureEvaluator {
+    #[must_use]
+    pub fn new(fixture: ProbeFixture) -> Self {
+        Self {
+            fixture,
+            evaluations: HashMap::new(),
+        }
+    }
+
+    /// How often `id` has been evaluated.
+    #[must_use]
+    pub fn evaluations(&self, id: &str) -> usize {
+        self.evaluations.get(id).copied().unwrap_or_default()
+    }
+}
+
+impl ProbeEvaluator for FixtureEvaluator {
+    fn evaluate(&mut self, id: &str, _spec: &ProbeSpec) -> ProbeResult {
+        let count = self.evaluations.entry(id.to_string()).or_default();
+        let index = *count;
+        *count += 1;
+
+        let Some(results) = self.fixture.probes.get(id).filter(|r| !r.is_empty()) else {
+            return ProbeResult::fail(id, format!("No fixture result for probe '{id}'"));
+        };
+        let result = &results[index.min(results.len() - 1)];
+        if result.passed {
+            ProbeResult::pass(id, result.message.clone())
+        } else {
+            ProbeResult::fail(id, result.message.clone())
+        }
+    }
+}
+
+/// Passes evaluations through to another evaluator and keeps every result, to save as a
+/// fixture for later replay.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingEvaluator<E> {
+    inner: E,
+    fixture: ProbeFixture,
+}
+
+impl<E: ProbeEvaluator> RecordingEvaluator<E> {
+    #[must_use]
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            fixture: ProbeFixture::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn fixture(&self) -> &ProbeFixture {
+        &self.fixture
+    }
+
+    #[must_use]
+    pub fn into_fixture(self) -> ProbeFixture {
+        self.fixture
+    }
+}
+
+impl<E: ProbeEvaluator> ProbeEvaluator for RecordingEvaluator<E> {
+    fn evaluate(&mut self, id: &str, spec: &ProbeSpec) -> ProbeResult {
+        let result = self.inner.evaluate(id, spec);
+        self.fixture.push(&result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ProbeSpec {
+        ProbeSpec::FileExists {
+            path: "/nonexistent/intar-fixture-test".into(),
+            exists: true,
+        }
+    }
+
+    #[test]
+    fn test_fixture_replay() {
+        let fixture: ProbeFixture = serde_json::from_str(
+            r#"{ "probes": { "nginx": [
+                { "passed": false, "message": "nginx is inactive" },
+                { "passed": true }
+            ] } }"#,
+        )
+        .unwrap();
+        let mut evaluator = FixtureEvaluator::new(fixture);
+
+        let first = evaluator.evaluate("nginx", &spec());
+        assert!(!first.passed);
+        assert_eq!(first.message, "nginx is inactive");
+        assert!(evaluator.evaluate("nginx", &spec()).passed);
+        assert!(evaluator.evaluate("nginx", &spec()).passed);
+        assert_eq!(evaluator.evaluations("nginx"), 3);
+
+        let unknown = evaluator.evaluate("postgres", &spec());
+        assert!(!unknown.passed);
+        assert!(unknown.message.contains("No fixture result"));
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let mut recorder = RecordingEvaluator::new(LiveEvaluator);
+        let live = recorder.evaluate("missing-file", &spec());
+        assert!(!live.passed);
+
+        let path =
+            std::env::temp_dir().join(format!("intar-probe-fixture-{}.json", std::process::id()));
+        recorder.fixture().save(&path).unwrap();
+        let fixture = ProbeFixture::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(&fixture, recorder.fixture());
+
+        let replayed = FixtureEvaluator::new(fixture).evaluate("missing-file", &spec());
+        assert_eq!(replayed.passed, live.passed);
+        assert_eq!(replayed.message, live.message);
+    }
+}
@@ -1,11 +1,23 @@
 mod actions;
+mod cache;
+mod diff;
 mod error;
 mod eval;
+mod fixture;
+mod manifest;
+mod plugin;
 mod protocol;
 mod spec;
+mod sudo;
 
 pub use actions::*;
+pub use cache::*;
+pub use diff::*;
 pub use error::*;
 pub use eval::*;
+pub use fixture::*;
+pub use manifest::*;
+pub use plugin::*;
 pub use protocol::*;
 pub use spec::*;
+pub use sudo::*;
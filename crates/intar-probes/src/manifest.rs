@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Stop scanning after this many files so a stray `/` cannot flood the agent connection.
+const MAX_MANIFEST_FILES: usize = 50_000;
+
+/// What every file under some guest directories holds, keyed by path: the sha256 of regular
+/// files, `symlink:<target>` for symlinks. Taken by the agent at a checkpoint and again at the
+/// end of a run to tell which files the learner changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileManifest {
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+/// Files that differ between two [`FileManifest`]s, each list sorted by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl FileManifest {
+    /// Hash every file under `roots`, without following symlinked directories. Missing roots
+    /// are skipped and unreadable files are recorded as `unreadable`.
+    #[must_use]
+    pub fn scan(roots: &[String]) -> Self {
+        let mut manifest = Self::default();
+        for root in roots {
+            manifest.scan_path(Path::new(root));
+        }
+        manifest
+    }
+
+    fn scan_path(&mut self, path: &Path) {
+        if self.files.len() >= MAX_MANIFEST_FILES {
+            return;
+        }
+        let Ok(meta) = fs::symlink_metadata(path) else {
+            return;
+        };
+        let key = path.to_string_lossy().into_owned();
+        if meta.is_dir() {
+            let Ok(entries) = fs::read_dir(path) else {
+                self.files.insert(key, "unreadable".into());
+                return;
+            };
+            let mut children: Vec<_> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+            children.sort();
+            for child in children {
+                self.scan_path(&child);
+            }
+        } else if meta.file_type().is_symlink() {
+            let target = fs::read_link(path).map_or_else(
+                |_| "unreadable".to_string(),
+                |target| format!("symlink:{}", target.display()),
+            );
+            self.files.insert(key, target);
+        } else if meta.is_file() {
+            self.files.insert(key, file_hash(path));
+        }
+    }
+
+    /// The files added, removed, or changed in `after` compared with `self`.
+    #[must_use]
+    pub fn diff(&self, after: &Self) -> FileChanges {
+        let mut changes = FileChanges::default();
+        for (path, hash) in &after.files {
+            match self.files.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(before) if before != hash => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = self
+            .files
+            .keys()
+            .filter(|path| !after.files.contains_key(*path))
+            .cloned()
+            .collect();
+        changes
+    }
+}
+
+impl FileChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// One line per changed file, sorted by path, e.g. `/etc/nginx/nginx.conf (modified)`.
+    #[must_use]
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<(&str, &str)> = self
+            .added
+            .iter()
+            .map(|path| (path.as_str(), "added"))
+            .chain(self.removed.iter().map(|path| (path.as_str(), "removed")))
+            .chain(self.modified.iter().map(|path| (path.as_str(), "modified")))
+            .collect();
+        lines.sort_unstable();
+        lines
+            .into_iter()
+            .map(|(path, change)| format!("{path} ({change})"))
+            .collect()
+    }
+}
+
+fn file_hash(path: &Path) -> String {
+    let Ok(mut file) = fs::File::open(path) else {
+        return "unreadable".into();
+    };
+    let mut hasher = Sha256::new();
+    if std::io::copy(&mut file, &mut hasher).is_err() {
+        return "unreadable".into();
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_manifest_diff() {
+        let dir = std::env::temp_dir().join(format!("intar-manifest-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(dir.join("main.conf"), "listen 80").unwrap();
+        fs::write(dir.join("conf.d/old.conf"), "old").unwrap();
+        let roots = vec![dir.to_string_lossy().into_owned()];
+        let before = FileManifest::scan(&roots);
+        assert_eq!(before.files.len(), 2);
+
+        fs::write(dir.join("main.conf"), "listen 8080").unwrap();
+        fs::remove_file(dir.join("conf.d/old.conf")).unwrap();
+        fs::write(dir.join("conf.d/new.conf"), "new").unwrap();
+        let after = FileManifest::scan(&roots);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let changes = before.diff(&after);
+        let root = dir.to_string_lossy();
+        assert_eq!(
+            changes.summary(),
+            vec![
+                format!("{root}/conf.d/new.conf (added)"),
+                format!("{root}/conf.d/old.conf (removed)"),
+                format!("{root}/main.conf (modified)"),
+            ]
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+}
@@ -0,0 +1,420 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write as _};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Guest directory probe plugins are installed to, one executable per plugin name.
+pub const PLUGIN_DIR: &str = "/usr/local/lib/intar/probe-plugins";
+
+/// Kill a plugin that has not exited after this long.
+const PLUGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Plugins a `check_all` reaches after its plugins have run this long in total are reported
+/// as failed without starting, so the reply still beats the host's 30-second request timeout.
+pub const CHECK_ALL_PLUGIN_BUDGET: Duration = Duration::from_secs(15);
+
+const PLUGIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Read at most this much of a plugin's stdout and stderr; the rest is discarded.
+const MAX_PLUGIN_OUTPUT: u64 = 64 * 1024;
+
+/// The only environment a plugin sees besides `INTAR_PLUGIN`.
+const PLUGIN_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// `nobody`, which unprivileged plugins run as.
+#[cfg(unix)]
+const NOBODY: u32 = 65_534;
+
+/// Address space, open files, processes (unprivileged plugins only), and file size a plugin
+/// may use. CPU time is capped at [`PLUGIN_TIMEOUT`] and core dumps are off.
+#[cfg(target_os = "linux")]
+const PLUGIN_MEMORY: u64 = 1024 * 1024 * 1024;
+#[cfg(target_os = "linux")]
+const PLUGIN_FILES: u64 = 256;
+#[cfg(target_os = "linux")]
+const PLUGIN_PROCESSES: u64 = 64;
+#[cfg(target_os = "linux")]
+const PLUGIN_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// `CAP_DAC_READ_SEARCH` and `CAP_SYS_PTRACE`: the capabilities a privileged plugin keeps, so
+/// it can read any file and inspect any process but change nothing it does not own.
+#[cfg(target_os = "linux")]
+const PRIVILEGED_CAPS: [u32; 2] = [2, 19];
+
+/// Higher than any capability number the kernel knows; dropping unknown ones fails harmlessly.
+#[cfg(target_os = "linux")]
+const MAX_CAP: u32 = 63;
+
+/// Mount points below these stay writable for plugins; everything else is read-only.
+#[cfg(target_os = "linux")]
+const WRITABLE_MOUNTS: [&str; 2] = ["/dev", "/tmp"];
+
+/// What a plugin prints on stdout: one JSON object, e.g.
+/// `{"passed": false, "message": "2 of 3 replicas in sync", "observed": "2", "expected": "3"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginReply {
+    pub passed: bool,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+}
+
+/// Whether `name` can name a plugin: letters, digits, `-`, and `_`, so it cannot leave
+/// [`PLUGIN_DIR`].
+#[must_use]
+pub fn is_valid_plugin_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parse a plugin's stdout as a [`PluginReply`].
+///
+/// # Errors
+/// Returns an error message when the output is not a reply object.
+pub fn parse_plugin_reply(plugin: &str, stdout: &[u8]) -> Result<PluginReply, String> {
+    serde_json::from_slice(stdout.trim_ascii()).map_err(|e| {
+        format!(
+            "Plugin '{plugin}' printed no valid reply ({e}): {}",
+            String::from_utf8_lossy(stdout).trim()
+        )
+    })
+}
+
+/// Run plugin `plugin` from [`PLUGIN_DIR`] with `{"config": config}` on stdin and parse its
+/// reply. The plugin runs in `/` with an empty environment apart from `PATH` and
+/// `INTAR_PLUGIN`, sandboxed as `nobody` unless `privileged`, and is killed with its process group after 10
+/// seconds. Its exit status only matters when it prints no reply.
+///
+/// # Errors
+/// Returns an error message when the plugin cannot be started, times out, or prints no reply.
+pub fn run_plugin(
+    plugin: &str,
+    config: &serde_json::Map<String, serde_json::Value>,
+    privileged: bool,
+) -> Result<PluginReply, String> {
+    if !is_valid_plugin_name(plugin) {
+        return Err(format!(
+            "Plugin name '{plugin}' must use only letters, digits, '-', and '_'"
+        ));
+    }
+    let path = Path::new(PLUGIN_DIR).join(plugin);
+    let mut command = Command::new(&path);
+    command
+        .env_clear()
+        .env("PATH", PLUGIN_PATH)
+        .env("INTAR_PLUGIN", plugin)
+        .current_dir("/")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    sandbox(&mut command, privileged);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run plugin {}: {e}", path.display()))?;
+
+    let request = serde_json::json!({ "config": config }).to_string();
+    let stdin = child.stdin.take();
+    thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(request.as_bytes());
+        }
+    });
+    let stdout = capture(child.stdout.take());
+    let stderr = capture(child.stderr.take());
+
+    let deadline = Instant::now() + PLUGIN_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(PLUGIN_POLL_INTERVAL),
+            Ok(None) => {
+                kill_plugin(&mut child);
+                let _ = child.wait();
+                return Err(format!(
+                    "Plugin '{plugin}' timed out after {}s",
+                    PLUGIN_TIMEOUT.as_secs()
+                ));
+            }
+            Err(e) => return Err(format!("Failed to wait for plugin '{plugin}': {e}")),
+        }
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    match parse_plugin_reply(plugin, &stdout) {
+        Ok(reply) => Ok(reply),
+        Err(_) if !status.success() => {
+            let stderr = stderr.join().unwrap_or_default();
+            let stderr = String::from_utf8_lossy(&stderr);
+            Err(format!(
+                "Plugin '{plugin}' failed ({status}): {}",
+                stderr.trim().lines().last().unwrap_or("no output")
+            ))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Read up to [`MAX_PLUGIN_OUTPUT`] of `pipe` on a thread, draining the rest so the plugin
+/// never blocks on a full pipe.
+fn capture<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = (&mut pipe).take(MAX_PLUGIN_OUTPUT).read_to_end(&mut output);
+            let _ = io::copy(&mut pipe, &mut io::sink());
+        }
+        output
+    })
+}
+
+/// Confine the plugin `command` starts. On Linux it gets its own process group and mount,
+/// IPC, and UTS namespaces, with every filesystem outside [`WRITABLE_MOUNTS`] remounted
+/// read-only, runs under the `PLUGIN_*` resource limits with `no_new_privs` set, and runs as
+/// `nobody`, or as root with only [`PRIVILEGED_CAPS`] when `privileged`. Namespaces the
+/// kernel refuses, e.g. inside a container, are skipped; the rest is required.
+#[cfg(target_os = "linux")]
+fn sandbox(command: &mut Command, privileged: bool) {
+    use nix::libc;
+    use std::os::unix::process::CommandExt as _;
+    use std::ptr;
+
+    // Read now: the child must not allocate between fork and exec.
+    let mounts =
+        read_only_mounts(&std::fs::read_to_string("/proc/self/mounts").unwrap_or_default());
+    let mut limits = vec![
+        (libc::RLIMIT_CPU, PLUGIN_TIMEOUT.as_secs()),
+        (libc::RLIMIT_AS, PLUGIN_MEMORY),
+        (libc::RLIMIT_NOFILE, PLUGIN_FILES),
+        (libc::RLIMIT_FSIZE, PLUGIN_FILE_SIZE),
+        (libc::RLIMIT_CORE, 0),
+    ];
+    // Counted per user, so root's own processes would leave a privileged plugin none.
+    if !privileged {
+        limits.push((libc::RLIMIT_NPROC, PLUGIN_PROCESSES));
+    }
+
+    command.process_group(0);
+    unsafe {
+        command.pre_exec(move || {
+            let namespaces = libc::CLONE_NEWNS | libc::CLONE_NEWIPC | libc::CLONE_NEWUTS;
+            // Only remount once nothing propagates back to the guest's own mounts.
+            if libc::unshare(namespaces) == 0
+                && libc::mount(
+                    ptr::null(),
+                    c"/".as_ptr(),
+                    ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    ptr::null(),
+                ) == 0
+            {
+                for (target, flags) in &mounts {
+                    libc::mount(
+                        ptr::null(),
+                        target.as_ptr(),
+                        ptr::null(),
+                        libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY | flags,
+                        ptr::null(),
+                    );
+                }
+            }
+            for &(resource, limit) in &limits {
+                let limit = libc::rlimit {
+                    rlim_cur: limit,
+                    rlim_max: limit,
+                };
+                if libc::setrlimit(resource, &raw const limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            // Ambient capabilities are newer than some guest kernels.
+            libc::prctl(
+                libc::PR_CAP_AMBIENT,
+                libc::PR_CAP_AMBIENT_CLEAR_ALL,
+                0,
+                0,
+                0,
+            );
+            for cap in 0..=MAX_CAP {
+                if !(privileged && PRIVILEGED_CAPS.contains(&cap)) {
+                    libc::prctl(libc::PR_CAPBSET_DROP, libc::c_ulong::from(cap), 0, 0, 0);
+                }
+            }
+            if !privileged
+                && (libc::setgroups(0, ptr::null()) != 0
+                    || libc::setgid(NOBODY) != 0
+                    || libc::setuid(NOBODY) != 0)
+            {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn sandbox(command: &mut Command, privileged: bool) {
+    use std::os::unix::process::CommandExt as _;
+    command.process_group(0);
+    if !privileged {
+        command.uid(NOBODY).gid(NOBODY);
+    }
+}
+
+#[cfg(not(unix))]
+fn sandbox(_command: &mut Command, _privileged: bool) {}
+
+/// Mount points in `mounts` (as in `/proc/self/mounts`) to remount read-only for a plugin,
+/// each with the `MS_*` flags it already has so none of them is loosened.
+#[cfg(target_os = "linux")]
+fn read_only_mounts(mounts: &str) -> Vec<(std::ffi::CString, nix::libc::c_ulong)> {
+    use nix::libc;
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let target = fields.nth(1)?;
+            let options = fields.nth(1)?;
+            if WRITABLE_MOUNTS
+                .iter()
+                .any(|dir| Path::new(target).starts_with(dir))
+            {
+                return None;
+            }
+            let flags = options
+                .split(',')
+                .map(|option| match option {
+                    "nosuid" => libc::MS_NOSUID,
+                    "nodev" => libc::MS_NODEV,
+                    "noexec" => libc::MS_NOEXEC,
+                    "noatime" => libc::MS_NOATIME,
+                    "nodiratime" => libc::MS_NODIRATIME,
+                    "relatime" => libc::MS_RELATIME,
+                    _ => 0,
+                })
+                .fold(0, |all, flag| all | flag);
+            Some((
+                std::ffi::CString::new(unescape_mount_path(target)).ok()?,
+                flags,
+            ))
+        })
+        .collect()
+}
+
+/// Undo the octal escapes (`\040` for a space) mount tables use in paths.
+#[cfg(target_os = "linux")]
+fn unescape_mount_path(path: &str) -> Vec<u8> {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        if let Some(byte) = escaped {
+            out.push(byte);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Kill a plugin that overran [`PLUGIN_TIMEOUT`] along with anything it started.
+fn kill_plugin(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = i32::try_from(child.id()) {
+        unsafe {
+            nix::libc::kill(-pid, nix::libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plugin_reply() {
+        let reply = parse_plugin_reply(
+            "replicas",
+            br#"  {"passed": false, "message": "2 of 3 in sync", "observed": "2", "expected": "3"}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            PluginReply {
+                passed: false,
+                message: "2 of 3 in sync".into(),
+                observed: Some("2".into()),
+                expected: Some("3".into()),
+            }
+        );
+        assert!(
+            parse_plugin_reply("replicas", br#"{"passed": true}"#)
+                .unwrap()
+                .passed
+        );
+
+        let err = parse_plugin_reply("replicas", b"ok\n").unwrap_err();
+        assert!(
+            err.starts_with("Plugin 'replicas' printed no valid reply"),
+            "{err}"
+        );
+        assert!(err.ends_with(": ok"), "{err}");
+    }
+
+    #[test]
+    fn test_plugin_name_stays_in_plugin_dir() {
+        assert!(is_valid_plugin_name("db-replicas_v2"));
+        for name in ["", "../bin/sh", "a/b", "check.sh"] {
+            assert!(!is_valid_plugin_name(name), "{name}");
+            assert!(run_plugin(name, &serde_json::Map::new(), false).is_err());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_only_mounts_keep_flags_and_skip_writable() {
+        use nix::libc;
+
+        let mounts = read_only_mounts(
+            "/dev/vda1 / ext4 rw,relatime 0 0\n\
+             proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0\n\
+             devpts /dev/pts devpts rw,nosuid,noexec 0 0\n\
+             tmpfs /tmp tmpfs rw,nosuid,nodev 0 0\n\
+             /dev/vdb1 /srv/my\\040data ext4 rw,noatime 0 0\n",
+        );
+        let targets: Vec<_> = mounts
+            .iter()
+            .map(|(target, flags)| (target.to_str().unwrap(), *flags))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                ("/", libc::MS_RELATIME),
+                (
+                    "/proc",
+                    libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC | libc::MS_RELATIME
+                ),
+                ("/srv/my data", libc::MS_NOATIME),
+            ]
+        );
+    }
+}
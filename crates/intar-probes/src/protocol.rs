@@ -1,4 +1,4 @@
-use crate::ProbeSpec;
+use crate::{FileManifest, ProbeSpec, SudoInvocation};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 pub enum Request {
     CheckProbe { id: String, spec: ProbeSpec },
     CheckAll { probes: Vec<(String, ProbeSpec)> },
+    Exec { script: String },
+    SudoLog,
+    FileManifest { paths: Vec<String> },
     Ping,
 }
 
@@ -16,6 +19,8 @@ pub enum Response {
         id: String,
         passed: bool,
         message: String,
+        #[serde(default, skip_serializing_if = "ProbeDetails::is_empty")]
+        details: ProbeDetails,
     },
     AllResults {
         results: Vec<ProbeResult>,
@@ -23,6 +28,17 @@ pub enum Response {
     Pong {
         uptime_secs: u64,
     },
+    ExecResult {
+        exit_code: i32,
+        stdout: String,
+        stderr: String,
+    },
+    SudoLog {
+        invocations: Vec<SudoInvocation>,
+    },
+    FileManifest {
+        manifest: FileManifest,
+    },
     Error {
         message: String,
     },
@@ -33,6 +49,71 @@ pub struct ProbeResult {
     pub id: String,
     pub passed: bool,
     pub message: String,
+    #[serde(default, skip_serializing_if = "ProbeDetails::is_empty")]
+    pub details: ProbeDetails,
+}
+
+/// How much a failing probe matters, set per probe with `severity` in the scenario.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl Severity {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Machine-readable detail behind a probe's message, for reports and tools.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeDetails {
+    /// What the probe found, e.g. `inactive` for a service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed: Option<String>,
+    /// What it should have found, e.g. `running`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    /// Unified diff of the expected against the observed content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+impl ProbeDetails {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    #[must_use]
+    pub fn with_values(mut self, observed: impl Into<String>, expected: impl Into<String>) -> Self {
+        self.observed = Some(observed.into());
+        self.expected = Some(expected.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_diff(mut self, diff: impl Into<String>) -> Self {
+        self.diff = Some(diff.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
 }
 
 impl ProbeResult {
@@ -41,6 +122,7 @@ impl ProbeResult {
             id: id.into(),
             passed: true,
             message: message.into(),
+            details: ProbeDetails::default(),
         }
     }
 
@@ -49,8 +131,22 @@ impl ProbeResult {
             id: id.into(),
             passed: false,
             message: message.into(),
+            details: ProbeDetails::default(),
         }
     }
+
+    #[must_use]
+    pub fn with_details(mut self, details: ProbeDetails) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// How much this result matters: nothing when it passed, [`Severity::Error`] unless the
+    /// probe lowers it.
+    #[must_use]
+    pub fn severity(&self) -> Option<Severity> {
+        (!self.passed).then(|| self.details.severity.unwrap_or_default())
+    }
 }
 
 #[cfg(test)]
@@ -74,4 +170,41 @@ mod tests {
         assert!(json.contains("pong"));
         assert!(json.contains("42"));
     }
+
+    #[test]
+    fn test_probe_result_details() {
+        let old: ProbeResult =
+            serde_json::from_str(r#"{"id":"nginx","passed":true,"message":"ok"}"#).unwrap();
+        assert!(old.details.is_empty());
+        assert_eq!(old.severity(), None);
+        assert!(!serde_json::to_string(&old).unwrap().contains("details"));
+
+        let result = ProbeResult::fail("nginx", "Service 'nginx' is not running (inactive)")
+            .with_details(
+                ProbeDetails::default()
+                    .with_values("inactive", "active")
+                    .with_severity(Severity::Warning),
+            );
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains(
+            r#""details":{"observed":"inactive","expected":"active","severity":"warning"}"#
+        ));
+        let parsed: ProbeResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.details, result.details);
+        assert_eq!(parsed.severity(), Some(Severity::Warning));
+        assert_eq!(ProbeResult::fail("x", "").severity(), Some(Severity::Error));
+    }
+
+    #[test]
+    fn test_exec_round_trip() {
+        let req = Request::Exec {
+            script: "echo hi".into(),
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        assert!(json.contains("\"type\":\"exec\""));
+        assert!(matches!(
+            serde_json::from_str::<Request>(&json).unwrap(),
+            Request::Exec { script } if script == "echo hi"
+        ));
+    }
 }
@@ -1,6 +1,8 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ProbeSpec {
     FileContent {
@@ -14,6 +16,14 @@ pub enum ProbeSpec {
         path: String,
         exists: bool,
     },
+    /// Compare a guest file line by line with `template`, its expected content; lines matching
+    /// any of `ignore_patterns` are left out on both sides. Fails with a unified diff.
+    FileMatchesTemplate {
+        path: String,
+        template: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        ignore_patterns: Vec<String>,
+    },
     Service {
         service: String,
         state: ServiceState,
@@ -23,18 +33,27 @@ pub enum ProbeSpec {
         state: PortState,
         #[serde(default = "default_protocol")]
         protocol: Protocol,
+        /// Local address to check, e.g. `fd00:11::10`; IPv4 and IPv6 loopback when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        address: Option<String>,
     },
     Command {
         cmd: String,
         exit_code: i32,
         #[serde(skip_serializing_if = "Option::is_none")]
         stdout_contains: Option<String>,
+        /// Files, directories, and systemd units the result depends on; see [`ProbeSpec::watch`].
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        watch: Vec<String>,
     },
     Http {
         url: String,
         status: u16,
         #[serde(skip_serializing_if = "Option::is_none")]
         body_contains: Option<String>,
+        /// Files, directories, and systemd units the result depends on; see [`ProbeSpec::watch`].
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        watch: Vec<String>,
     },
     K8sNodesReady {
         expected_ready: u32,
@@ -42,6 +61,9 @@ pub enum ProbeSpec {
         kubeconfig: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         context: Option<String>,
+        /// Files, directories, and systemd units the result depends on; see [`ProbeSpec::watch`].
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        watch: Vec<String>,
     },
     #[serde(alias = "k8s_endpoints_nonempty")]
     K8sEndpointsNonEmpty {
@@ -51,6 +73,9 @@ pub enum ProbeSpec {
         kubeconfig: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         context: Option<String>,
+        /// Files, directories, and systemd units the result depends on; see [`ProbeSpec::watch`].
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        watch: Vec<String>,
     },
     TcpPing {
         host: String,
@@ -61,6 +86,37 @@ pub enum ProbeSpec {
         #[serde(default = "default_tcp_ping_state")]
         state: ReachabilityState,
     },
+    /// CTF-style secret, stored only as the sha256 of the expected value (see [`flag_hash`]).
+    Flag {
+        sha256: String,
+        /// Guest file the learner writes the flag to; without it the flag can only be
+        /// submitted from the host (`intar submit` or the TUI).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    /// Whether anyone ran a command through sudo since the guest booted, e.g. `used = false`
+    /// for least-privilege exercises.
+    SudoUsed {
+        used: bool,
+    },
+    /// A check the scenario ships itself: executable `plugin` from
+    /// [`PLUGIN_DIR`](crate::PLUGIN_DIR), run by [`run_plugin`](crate::run_plugin). The
+    /// probe's other attributes are passed to it as its config.
+    Plugin {
+        plugin: String,
+        /// Run as root, able to read any file and inspect any process but not to change the
+        /// system, instead of as `nobody`, for checks that read root-only state.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        privileged: bool,
+        #[serde(flatten)]
+        config: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+/// Hash a submitted flag the same way scenario authors do: sha256 of the trimmed value.
+#[must_use]
+pub fn flag_hash(flag: &str) -> String {
+    hex::encode(Sha256::digest(flag.trim().as_bytes()))
 }
 
 fn default_protocol() -> Protocol {
@@ -79,7 +135,7 @@ fn default_tcp_ping_state() -> ReachabilityState {
     ReachabilityState::Reachable
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ServiceState {
     Running,
@@ -88,14 +144,14 @@ pub enum ServiceState {
     Disabled,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PortState {
     Listening,
     Closed,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Protocol {
     #[default]
@@ -103,7 +159,7 @@ pub enum Protocol {
     Udp,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ReachabilityState {
     Reachable,
@@ -111,6 +167,20 @@ pub enum ReachabilityState {
 }
 
 impl ProbeSpec {
+    /// What an expensive probe's result depends on: absolute paths, whose modification times
+    /// are compared, and systemd units, whose states are. While none of them changes the agent
+    /// answers with the previous result instead of evaluating the probe again.
+    #[must_use]
+    pub fn watch(&self) -> &[String] {
+        match self {
+            Self::Command { watch, .. }
+            | Self::Http { watch, .. }
+            | Self::K8sNodesReady { watch, .. }
+            | Self::K8sEndpointsNonEmpty { watch, .. } => watch,
+            _ => &[],
+        }
+    }
+
     /// Construct a `ProbeSpec` from a probe type string and config map.
     ///
     /// # Errors
@@ -130,10 +200,46 @@ impl ProbeSpec {
     }
 }
 
+/// JSON Schema for probe attributes, one variant per probe `type`, with no external references
+/// so it can be embedded in the scenario schema.
+#[must_use]
+pub fn probe_json_schema() -> serde_json::Value {
+    let mut schema = schemars::generate::SchemaSettings::draft2020_12()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator()
+        .into_root_schema_for::<ProbeSpec>()
+        .to_value();
+    if let serde_json::Value::Object(map) = &mut schema {
+        map.remove("$schema");
+    }
+    schema
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_probe_json_schema_lists_every_type() {
+        let schema = probe_json_schema().to_string();
+        for probe_type in [
+            "file_content",
+            "file_matches_template",
+            "service",
+            "tcp_ping",
+            "flag",
+            "sudo_used",
+            "k8s_nodes_ready",
+            "plugin",
+        ] {
+            assert!(
+                schema.contains(&format!("\"{probe_type}\"")),
+                "{probe_type}"
+            );
+        }
+        assert!(!schema.contains("$ref"));
+    }
+
     #[test]
     fn test_probe_spec_serialization() {
         let spec = ProbeSpec::Service {
@@ -165,6 +271,22 @@ mod tests {
         assert!(matches!(spec, ProbeSpec::Service { .. }));
     }
 
+    #[test]
+    fn test_from_definition_port_address() {
+        let mut config = serde_json::Map::new();
+        config.insert("port".to_string(), serde_json::json!(443));
+        config.insert("state".to_string(), serde_json::json!("listening"));
+
+        let spec = ProbeSpec::from_definition("port", &config).unwrap();
+        assert!(matches!(spec, ProbeSpec::Port { address: None, .. }));
+
+        config.insert("address".to_string(), serde_json::json!("[fd00:11::10]"));
+        let spec = ProbeSpec::from_definition("port", &config).unwrap();
+        assert!(
+            matches!(spec, ProbeSpec::Port { address: Some(address), .. } if address == "[fd00:11::10]")
+        );
+    }
+
     #[test]
     fn test_from_definition_alias_k8s_endpoints_nonempty() {
         let mut config = serde_json::Map::new();
@@ -180,4 +302,44 @@ mod tests {
         let spec = ProbeSpec::from_definition("k8s_endpoints_nonempty", &config).unwrap();
         assert!(matches!(spec, ProbeSpec::K8sEndpointsNonEmpty { .. }));
     }
+
+    #[test]
+    fn test_from_definition_plugin_keeps_config() {
+        let config = serde_json::json!({
+            "plugin": "db-replicas",
+            "min_in_sync": 3,
+            "tables": ["orders", "users"],
+        });
+        let serde_json::Value::Object(config) = config else {
+            unreachable!()
+        };
+
+        let spec = ProbeSpec::from_definition("plugin", &config).unwrap();
+        let ProbeSpec::Plugin {
+            plugin,
+            privileged,
+            config: plugin_config,
+        } = &spec
+        else {
+            panic!("not a plugin probe: {spec:?}");
+        };
+        assert_eq!(plugin, "db-replicas");
+        assert!(!privileged);
+        assert_eq!(
+            serde_json::Value::Object(plugin_config.clone()),
+            serde_json::json!({ "min_in_sync": 3, "tables": ["orders", "users"] })
+        );
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(serde_json::from_str::<ProbeSpec>(&json).unwrap(), spec);
+    }
+
+    #[test]
+    fn test_flag_hash_trims_input() {
+        assert_eq!(flag_hash("FLAG{x}\n"), flag_hash("FLAG{x}"));
+        assert_eq!(
+            flag_hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
 }
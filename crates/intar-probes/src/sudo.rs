@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Command;
+
+/// Syslog files that hold sudo's log on guests without a readable journal.
+const AUTH_LOGS: &[&str] = &["/var/log/auth.log", "/var/log/secure"];
+
+/// One command run through sudo, as sudo logged it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SudoInvocation {
+    /// Who ran sudo.
+    pub user: String,
+    /// Who the command ran as, usually `root`.
+    pub run_as: String,
+    pub cwd: String,
+    pub command: String,
+}
+
+impl fmt::Display for SudoInvocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} as {} in {}: {}",
+            self.user, self.run_as, self.cwd, self.command
+        )
+    }
+}
+
+/// The sudo invocations in `log`, either journal messages (`journalctl -o cat`) or syslog
+/// lines such as
+///
+/// ```text
+/// Oct 16 10:00:00 web sudo:    alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/apt update
+/// ```
+///
+/// Session and PAM messages, which name no command, are skipped.
+#[must_use]
+pub fn parse_sudo_log(log: &str) -> Vec<SudoInvocation> {
+    log.lines().filter_map(parse_sudo_line).collect()
+}
+
+fn parse_sudo_line(line: &str) -> Option<SudoInvocation> {
+    let message = line.split_once(" sudo: ").map_or(line, |(_, rest)| rest);
+    let (user, fields) = message.split_once(" : ")?;
+    // Everything after COMMAND= is the command, which may itself contain " ; ".
+    let (fields, command) = fields.split_once("COMMAND=")?;
+    let field = |key: &str| {
+        fields
+            .split(" ; ")
+            .find_map(|field| field.trim().strip_prefix(key)?.strip_prefix('='))
+            .map(ToString::to_string)
+    };
+    Some(SudoInvocation {
+        user: user.trim().to_string(),
+        run_as: field("USER").unwrap_or_else(|| "root".into()),
+        cwd: field("PWD").unwrap_or_default(),
+        command: command.trim().to_string(),
+    })
+}
+
+/// Every sudo invocation since the guest booted, from the journal or, without one, the
+/// syslog auth log.
+///
+/// # Errors
+/// Returns an error message when neither log can be read.
+pub fn sudo_invocations() -> Result<Vec<SudoInvocation>, String> {
+    if let Ok(output) = Command::new("journalctl")
+        .args(["--boot", "--no-pager", "--output=cat", "_COMM=sudo"])
+        .output()
+        && output.status.success()
+    {
+        return Ok(parse_sudo_log(&String::from_utf8_lossy(&output.stdout)));
+    }
+    AUTH_LOGS
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|log| parse_sudo_log(&log))
+        .ok_or_else(|| "Failed to read the sudo log: no journal or auth log".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sudo_log() {
+        let log = "\
+Oct 16 10:00:00 web sudo:    alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/apt update
+Oct 16 10:00:00 web sudo: pam_unix(sudo:session): session opened for user root(uid=0) by alice(uid=1000)
+bob : TTY=pts/1 ; PWD=/srv ; USER=postgres ; COMMAND=/usr/bin/psql -c select 1 ; select 2
+";
+        let invocations = parse_sudo_log(log);
+        assert_eq!(
+            invocations,
+            vec![
+                SudoInvocation {
+                    user: "alice".into(),
+                    run_as: "root".into(),
+                    cwd: "/home/alice".into(),
+                    command: "/usr/bin/apt update".into(),
+                },
+                SudoInvocation {
+                    user: "bob".into(),
+                    run_as: "postgres".into(),
+                    cwd: "/srv".into(),
+                    command: "/usr/bin/psql -c select 1 ; select 2".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            invocations[0].to_string(),
+            "alice as root in /home/alice: /usr/bin/apt update"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Python bindings for the [`intar`] crate, so labs can be run and graded from scripts and
+//! notebooks with the engine the CLI uses.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+create_exception!(intar, IntarError, PyException);
+
+/// How long a blocking wait runs before checking for Ctrl-C.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+fn intar_error(e: &intar::Error) -> PyErr {
+    IntarError::new_err(e.to_string())
+}
+
+/// A parsed and validated scenario.
+#[pyclass(module = "intar", name = "Scenario", frozen)]
+struct Scenario {
+    inner: intar::Scenario,
+}
+
+#[pymethods]
+impl Scenario {
+    /// Load the scenario at `path`.
+    #[staticmethod]
+    fn from_file(path: PathBuf) -> PyResult<Self> {
+        intar::Scenario::from_file(&path)
+            .map(|inner| Self { inner })
+            .map_err(|e| intar_error(&e))
+    }
+
+    /// Parse a scenario from HCL source.
+    #[staticmethod]
+    fn parse(source: &str) -> PyResult<Self> {
+        intar::Scenario::parse(source)
+            .map(|inner| Self { inner })
+            .map_err(|e| intar_error(&e))
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    #[getter]
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    #[getter]
+    fn vm_names(&self) -> Vec<String> {
+        self.inner
+            .vm_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Scenario({:?})", self.inner.name())
+    }
+}
+
+/// What one wait for an event ended with.
+enum Wait {
+    Event(intar::Event),
+    Timeout,
+    Ended,
+}
+
+/// A running scenario. Iterating it yields its events as dicts with a `type` key until the
+/// run has stopped; leaving a `with` block or dropping it stops it.
+#[pyclass(module = "intar", name = "Run")]
+struct Run {
+    runtime: tokio::runtime::Runtime,
+    handle: Mutex<Option<intar::RunHandle>>,
+    run_id: String,
+    seed: u64,
+}
+
+impl Run {
+    fn wait(&self, limit: Duration) -> Wait {
+        let Ok(mut handle) = self.handle.lock() else {
+            return Wait::Ended;
+        };
+        let Some(handle) = handle.as_mut() else {
+            return Wait::Ended;
+        };
+        self.runtime.block_on(async {
+            match tokio::time::timeout(limit, handle.next_event()).await {
+                Ok(Some(event)) => Wait::Event(event),
+                Ok(None) => Wait::Ended,
+                Err(_) => Wait::Timeout,
+            }
+        })
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        // The runtime goes with the run, so the VMs have to be stopped before it does.
+        if let Some(handle) = self.handle.get_mut().ok().and_then(Option::take) {
+            let _ = self.runtime.block_on(handle.stop());
+        }
+    }
+}
+
+#[pymethods]
+impl Run {
+    #[getter]
+    fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The next event, waiting at most `timeout` seconds; `None` on timeout or once the run
+    /// has stopped.
+    #[pyo3(signature = (timeout=None))]
+    fn next_event<'py>(
+        &self,
+        py: Python<'py>,
+        timeout: Option<f64>,
+    ) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let deadline = timeout
+            .map(|secs| Duration::try_from_secs_f64(secs).map(|limit| Instant::now() + limit))
+            .transpose()
+            .map_err(|e| PyValueError::new_err(format!("Invalid timeout: {e}")))?;
+        loop {
+            let limit = deadline.map_or(SIGNAL_CHECK_INTERVAL, |deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .min(SIGNAL_CHECK_INTERVAL)
+            });
+            match py.allow_threads(|| self.wait(limit)) {
+                Wait::Event(event) => return event_dict(py, event).map(Some),
+                Wait::Ended => return Ok(None),
+                Wait::Timeout if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                    return Ok(None);
+                }
+                Wait::Timeout => py.check_signals()?,
+            }
+        }
+    }
+
+    /// Reset the VMs to how they were when the run became ready.
+    fn reset(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| {
+            let handle = self
+                .handle
+                .lock()
+                .map_err(|_| IntarError::new_err("The run's state is poisoned"))?;
+            let handle = handle
+                .as_ref()
+                .ok_or_else(|| intar_error(&intar::Error::Ended))?;
+            self.runtime
+                .block_on(handle.reset())
+                .map_err(|e| intar_error(&e))
+        })
+    }
+
+    /// Stop the VMs and remove the run's files. Does nothing once the run is stopped.
+    fn stop(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| {
+            let handle = self
+                .handle
+                .lock()
+                .map_err(|_| IntarError::new_err("The run's state is poisoned"))?
+                .take();
+            match handle {
+                Some(handle) => self
+                    .runtime
+                    .block_on(handle.stop())
+                    .map_err(|e| intar_error(&e)),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        self.next_event(py, None)
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_exc))]
+    fn __exit__(&self, py: Python<'_>, _exc: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+        self.stop(py)?;
+        Ok(false)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Run({:?})", self.run_id)
+    }
+}
+
+/// `event` as a dict, e.g. `{"type": "probe_changed", "vm": "web", "probe": "nginx",
+/// "passed": True, "message": "active"}`.
+fn event_dict(py: Python<'_>, event: intar::Event) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    let kind = match event {
+        intar::Event::Downloading {
+            image,
+            index,
+            total,
+        } => {
+            dict.set_item("image", image)?;
+            dict.set_item("index", index)?;
+            dict.set_item("total", total)?;
+            "downloading"
+        }
+        intar::Event::CreatingVm { vm } => {
+            dict.set_item("vm", vm)?;
+            "creating_vm"
+        }
+        intar::Event::Booting => "booting",
+        intar::Event::AgentReady { vm } => {
+            dict.set_item("vm", vm)?;
+            "agent_ready"
+        }
+        intar::Event::BootProbes { passing, total } => {
+            dict.set_item("passing", passing)?;
+            dict.set_item("total", total)?;
+            "boot_probes"
+        }
+        intar::Event::Ready { ssh } => {
+            dict.set_item("ssh", ssh)?;
+            "ready"
+        }
+        intar::Event::ProbeChanged(status) => {
+            dict.set_item("vm", status.vm)?;
+            dict.set_item("probe", status.probe)?;
+            dict.set_item("passed", status.passed)?;
+            dict.set_item("message", status.message)?;
+            "probe_changed"
+        }
+        intar::Event::Completed => "completed",
+        intar::Event::Reset => "reset",
+        intar::Event::Expired => "expired",
+        intar::Event::Failed(message) => {
+            dict.set_item("message", message)?;
+            "failed"
+        }
+        intar::Event::Stopped => "stopped",
+        // Events added after this module was built.
+        _ => "unknown",
+    };
+    dict.set_item("type", kind)?;
+    Ok(dict)
+}
+
+/// Start `scenario` and boot it in the background. `agent_x86_64` and `agent_aarch64` are the
+/// `intar-agent` binaries installed into the guests.
+#[pyfunction]
+#[pyo3(signature = (scenario, agent_x86_64, agent_aarch64, *, seed=None, ttl_secs=None, probe_interval_secs=None))]
+fn run(
+    scenario: &Scenario,
+    agent_x86_64: Vec<u8>,
+    agent_aarch64: Vec<u8>,
+    seed: Option<u64>,
+    ttl_secs: Option<u64>,
+    probe_interval_secs: Option<f64>,
+) -> PyResult<Run> {
+    let mut options = intar::Options::new(agent_x86_64, agent_aarch64);
+    if let Some(seed) = seed {
+        options = options.with_seed(seed);
+    }
+    if let Some(ttl) = ttl_secs {
+        options = options.with_ttl(Duration::from_secs(ttl));
+    }
+    if let Some(secs) = probe_interval_secs {
+        let interval = Duration::try_from_secs_f64(secs)
+            .map_err(|e| PyValueError::new_err(format!("Invalid probe interval: {e}")))?;
+        options = options.with_probe_interval(interval);
+    }
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| IntarError::new_err(format!("Failed to start the async runtime: {e}")))?;
+    let handle = {
+        let _runtime = runtime.enter();
+        intar::run(scenario.inner.clone(), options).map_err(|e| intar_error(&e))?
+    };
+    Ok(Run {
+        run_id: handle.run_id().to_string(),
+        seed: handle.seed(),
+        handle: Mutex::new(Some(handle)),
+        runtime,
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "intar")]
+fn intar_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("IntarError", m.py().get_type::<IntarError>())?;
+    m.add_class::<Scenario>()?;
+    m.add_class::<Run>()?;
+    m.add_function(wrap_pyfunction!(run, m)?)?;
+    Ok(())
+}
@@ -1,8 +1,13 @@
+use crate::i18n::{init_messages, tr};
+use crate::terminal_pane::TerminalPane;
 use crate::widgets::{
-    BriefingScreen, CompletedScreen, ConfirmDialog, HelpMode, HelpOverlay, ProbeStatus,
-    ScenarioTreeScreen, VmStatus, VmTreeNode, VmTreeProbe,
+    BootProgress, BriefingScreen, CheckpointDialog, CompletedScreen, ConfirmDialog, CrashDialog,
+    ExpiryDialog, FailedScreen, FlagDialog, GuestPanicDialog, HelpMode, HelpOverlay, HookDialog,
+    IdleDialog, LowSpaceDialog, ObjectiveTiming, ProbeStatus, ProbeTraceTab, QuestionDialog,
+    QuitDialog, ScenarioTreeScreen, TERMINAL_TAB_CHROME, TerminalTab, UnlockDialog, VmStatus,
+    VmTreeNode, VmTreeProbe,
 };
-use crate::{ColorLevel, Theme, ThemeMode, ThemeSettings};
+use crate::{ColorLevel, FinalReport, Theme, ThemeMode, ThemeSettings};
 use crossterm::{
     event::{
         self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
@@ -12,8 +17,13 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use intar_core::Scenario;
+use intar_probes::{BootStage, ProbeResult};
 use intar_vm::{
-    ActionLineEvent, ActionLineKind, ImageCache, IntarDirs, ScenarioRunner, ScenarioState, VmError,
+    ActionLineEvent, ActionLineKind, AnswerOutcome, AuditOperation, AuditSummary, BackendKind,
+    BootStageEvent, CheckpointProgress, DownloadEvent, FakeBackend, ImageCache, IntarConfig,
+    IntarDirs, LOW_SPACE_BYTES, QEMU_EXITED_EVENT, QemuPriority, RESUME_SPACE_BYTES, RunProgress,
+    ScenarioRunner, ScenarioState, SessionScreen, SleepDetector, VmError, archive_run,
+    check_disk_space, random_seed, required_run_space,
 };
 use ratatui::{
     Terminal,
@@ -25,16 +35,37 @@ use ratatui::{
 };
 use std::{
     borrow::Cow,
-    io::{self, Stdout},
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, Read, Seek, SeekFrom, Stdout},
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 use thiserror::Error;
 use tokio::sync::mpsc;
-use tracing::warn;
+use tracing::{info, warn};
+
+/// Minutes before a run's TTL runs out at which the TUI warns.
+const TTL_WARNING_MINUTES: [u64; 2] = [15, 5];
+
+/// How much of the end of intar.log the debug tab reads.
+const DEBUG_LOG_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Ticks between re-reads of intar.log while the debug tab is open.
+const DEBUG_LOG_REFRESH_TICKS: usize = 10;
+
+/// Action lines kept in memory while the logs tab follows the end; older ones stay in the
+/// run's action log file.
+const ACTION_LINES_KEPT: usize = 2000;
+
+/// Action lines read back from the action log each time the logs tab reaches the top.
+const ACTION_LINES_PAGE: usize = 500;
+
+/// How often `--watch` looks for a newer scenario file.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Error, Debug)]
 pub enum UiError {
@@ -54,6 +85,8 @@ pub enum AppPhase {
     Running,
     Completed,
     ShuttingDown,
+    /// Initialization failed; retrying, reading the log, or quitting is offered.
+    Failed,
 }
 
 #[derive(Clone, Debug)]
@@ -66,6 +99,13 @@ pub enum ProgressUpdate {
     DownloadProgress {
         progress: f64,
     },
+    /// The download failed transiently; `attempt` of `max_attempts` starts after `delay`.
+    DownloadRetry {
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+        error: String,
+    },
     DownloadComplete,
     VmStart {
         name: String,
@@ -77,11 +117,38 @@ pub enum ProgressUpdate {
         step: String,
     },
     VmComplete,
-    BootingVms,
+    BootingVms {
+        /// Calibrated boot duration, when VMs are booted from scratch.
+        expected: Option<Duration>,
+    },
+    BootStage {
+        vm: String,
+        stage: BootStage,
+    },
+    /// The VM's QEMU started; until then it is queued for a later boot wave.
+    VmStarted {
+        name: String,
+    },
+    AgentReady {
+        name: String,
+    },
+    BootProbes {
+        passing: usize,
+        total: usize,
+    },
     Ready,
     Error(String),
 }
 
+/// What happens to the run's VMs when the TUI exits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ExitAction {
+    #[default]
+    Destroy,
+    Keep,
+    KeepPaused,
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 struct StageTimer {
     started_at: Option<Instant>,
@@ -101,11 +168,26 @@ impl StageTimer {
         }
     }
 
+    /// Leave `gap` out of a running stage, e.g. time the host spent asleep.
+    fn exclude(&mut self, gap: Duration) {
+        if self.ended_at.is_none()
+            && let Some(started_at) = self.started_at
+        {
+            self.started_at = Some(started_at + gap);
+        }
+    }
+
     fn reset_to_running(&mut self, now: Instant) {
         self.started_at = Some(now);
         self.ended_at = None;
     }
 
+    /// Rebuild a timer from a previous session's elapsed time.
+    fn restore(&mut self, elapsed: Duration, now: Instant, running: bool) {
+        self.started_at = Some(now.checked_sub(elapsed).unwrap_or(now));
+        self.ended_at = (!running).then_some(now);
+    }
+
     fn elapsed(&self, now: Instant) -> Option<Duration> {
         self.started_at
             .map(|t0| self.ended_at.unwrap_or(now).duration_since(t0))
@@ -125,23 +207,38 @@ struct StageTimers {
 pub enum MainTab {
     Briefing,
     Logs,
+    /// Live view of a VM's SSH session, as the learner sees it.
+    Session,
+    /// An ssh session to a VM inside the TUI.
+    Terminal,
     System,
+    /// Every probe evaluation; only in the tab cycle under `--author`.
+    Probes,
+    /// Tail of intar.log; left out of the tab cycle and opened with `D` or `F12`.
+    Debug,
 }
 
 impl MainTab {
-    fn next(self) -> Self {
+    fn next(self, author: bool) -> Self {
         match self {
             Self::Briefing => Self::Logs,
-            Self::Logs => Self::System,
-            Self::System => Self::Briefing,
+            Self::Logs => Self::Session,
+            Self::Session => Self::Terminal,
+            Self::Terminal => Self::System,
+            Self::System if author => Self::Probes,
+            Self::System | Self::Probes | Self::Debug => Self::Briefing,
         }
     }
 
-    fn prev(self) -> Self {
+    fn prev(self, author: bool) -> Self {
         match self {
-            Self::Briefing => Self::System,
+            Self::Briefing if author => Self::Probes,
+            Self::Briefing | Self::Probes => Self::System,
             Self::Logs => Self::Briefing,
-            Self::System => Self::Logs,
+            Self::Session => Self::Logs,
+            Self::Terminal => Self::Session,
+            Self::System => Self::Terminal,
+            Self::Debug => Self::System,
         }
     }
 }
@@ -158,6 +255,49 @@ impl StageTimers {
             run: StageTimer::default(),
         }
     }
+
+    fn named_mut(&mut self) -> [(&'static str, &mut StageTimer); 5] {
+        [
+            ("init", &mut self.init),
+            ("images", &mut self.images),
+            ("vms", &mut self.vms),
+            ("boot", &mut self.boot),
+            ("run", &mut self.run),
+        ]
+    }
+
+    fn exclude(&mut self, gap: Duration) {
+        for (_, timer) in self.named_mut() {
+            timer.exclude(gap);
+        }
+    }
+
+    /// Whole seconds spent in each stage so far, for `progress.json`.
+    fn elapsed_secs(&self, now: Instant) -> BTreeMap<String, u64> {
+        [
+            ("init", self.init),
+            ("images", self.images),
+            ("vms", self.vms),
+            ("boot", self.boot),
+            ("run", self.run),
+        ]
+        .into_iter()
+        .filter_map(|(name, timer)| {
+            timer
+                .elapsed(now)
+                .map(|elapsed| (name.to_string(), elapsed.as_secs()))
+        })
+        .collect()
+    }
+
+    /// Restore timings saved by a previous session; only the run stage keeps ticking.
+    fn restore(&mut self, secs: &BTreeMap<String, u64>, now: Instant) {
+        for (name, timer) in self.named_mut() {
+            if let Some(elapsed) = secs.get(name) {
+                timer.restore(Duration::from_secs(*elapsed), now, name == "run");
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -177,11 +317,127 @@ impl AltScreenMode {
     }
 }
 
+/// Settings of a new run, applied once its runner exists.
+#[derive(Debug, Clone)]
+struct RunnerSettings {
+    seed: u64,
+    ttl: Option<Duration>,
+    qemu_priority: QemuPriority,
+    boot_concurrency: Option<u32>,
+    backend: BackendKind,
+    author: bool,
+    ephemeral: bool,
+    paranoid: bool,
+    allow_host_hooks: bool,
+}
+
+impl RunnerSettings {
+    fn apply(self, runner: &mut ScenarioRunner, config: &IntarConfig) -> Result<(), VmError> {
+        match self.backend {
+            BackendKind::Qemu => {}
+            BackendKind::Libvirt => runner.set_libvirt(config.libvirt.uri.clone())?,
+            BackendKind::Cloud => runner.set_cloud(config.cloud.clone())?,
+            BackendKind::Fake => runner.set_backend(Box::new(FakeBackend::from_env()?)),
+        }
+        runner.set_seed(self.seed);
+        runner.set_author_mode(self.author);
+        if let Some(ttl) = self.ttl {
+            runner.set_ttl(ttl);
+        }
+        if self.ephemeral {
+            runner.set_ephemeral()?;
+        }
+        if self.paranoid {
+            runner.set_paranoid()?;
+        }
+        if self.allow_host_hooks || config.host_execution.allow {
+            runner.set_allow_host_hooks();
+        }
+        runner.set_allowed_providers(config.host_execution.providers.clone());
+        runner.check_host_execution()?;
+        runner.set_boot_concurrency(self.boot_concurrency);
+        runner.set_qemu_priority(self.qemu_priority)
+    }
+}
+
+/// The scenario file `intar start --watch` reloads probes from.
+#[derive(Debug)]
+struct ScenarioWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl ScenarioWatch {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            modified: file_modified(&path),
+            path,
+            checked: Instant::now(),
+        }
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+#[derive(Debug)]
+struct QuestionPrompt {
+    name: String,
+    input: String,
+    selected: usize,
+    feedback: Option<AnswerOutcome>,
+}
+
+impl QuestionPrompt {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            input: String::new(),
+            selected: 0,
+            feedback: None,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct UiFlags {
     should_quit: bool,
+    show_confirm_quit: bool,
     show_confirm_reset: bool,
+    /// How far restoring the initial checkpoint has got, while a restart runs.
+    checkpoint: Option<CheckpointProgress>,
     show_help: bool,
+    unlock_input: Option<String>,
+    unlock_failed: bool,
+    flag_input: Option<String>,
+    flag_rejected: bool,
+    question: Option<QuestionPrompt>,
+    /// Question closed with Esc; it is not reopened automatically.
+    dismissed_question: Option<String>,
+    /// Free bytes in the runs directory while VMs are paused for low disk space.
+    low_space: Option<u64>,
+    /// VMs are paused because the run was idle past the configured threshold.
+    idle_suspended: bool,
+    /// Show the warning that the run's TTL is about to run out.
+    ttl_warning: bool,
+    /// Minutes-left threshold of the last TTL warning shown (see [`TTL_WARNING_MINUTES`]).
+    ttl_warned: Option<u64>,
+    /// The run's TTL ran out; it is being torn down and archived.
+    ttl_expired: bool,
+    /// VM whose guest kernel panicked, until the notice is dismissed.
+    guest_panic: Option<String>,
+    /// VM whose QEMU process died, while restarting it is offered.
+    crashed_vm: Option<String>,
+    /// Start initialization over after it failed.
+    retry_init: bool,
+    /// `on_complete` hook that runs on the next tick, shown until it has run.
+    running_hook: Option<String>,
+    /// Show this session's intar.log on the failure screen instead of the excerpt.
+    show_failure_log: bool,
     alt_screen: AltScreenMode,
 }
 
@@ -209,17 +465,73 @@ pub struct App {
     agent_binary_x86_64: Vec<u8>,
     agent_binary_aarch64: Vec<u8>,
     stages: StageTimers,
+    sleep_detector: SleepDetector,
+    /// The latest action lines, a window onto the runner's action log.
     action_lines: Vec<ActionLineEvent>,
     actions_since: Instant,
     pub active_tab: MainTab,
+    /// Tab to go back to when the debug tab is closed.
+    tab_before_debug: MainTab,
+    log_path: Option<PathBuf>,
+    /// Size of intar.log when the TUI started; the failure screen shows what came after.
+    log_offset: u64,
+    debug_log: Vec<String>,
+    /// VM shown in the session tab; `None` follows the latest session.
+    session_vm: Option<String>,
+    terminal: Option<TerminalPane>,
+    /// VM the terminal tab connects to; the first VM until switched.
+    terminal_vm: Option<String>,
+    /// Why the terminal tab's last ssh could not start.
+    terminal_error: Option<String>,
+    /// Why initialization failed, returned from [`App::run`] once the user quits.
+    failure: Option<VmError>,
+    /// intar.log lines written since the TUI started, read when initialization failed.
+    failure_log: Vec<String>,
     download_image: Option<String>,
     download_total: usize,
     download_index: usize,
     download_progress: f64,
+    /// Attempt and maximum of the retry the current download is on.
+    download_retry: Option<(u32, u32)>,
     vm_progress_name: Option<String>,
     vm_progress_total: usize,
     vm_progress_index: usize,
     vm_progress_step: Option<String>,
+    boot_expected: Option<Duration>,
+    boot_stages: HashMap<String, HashSet<BootStage>>,
+    vms_started: HashSet<String>,
+    agents_ready: HashSet<String>,
+    boot_probes: Option<(usize, usize)>,
+    audit_summary: Option<AuditSummary>,
+    /// Seed of the new run, from `--seed` or picked when its directory is claimed.
+    seed: Option<u64>,
+    final_report: Option<FinalReport>,
+    keep_on_exit: bool,
+    auto_balloon: bool,
+    idle_suspend: Option<Duration>,
+    ttl: Option<Duration>,
+    qemu_priority: QemuPriority,
+    boot_concurrency: Option<u32>,
+    backend: BackendKind,
+    exit_action: ExitAction,
+    resume_dir: Option<PathBuf>,
+    /// Name asked for with `--name`; generated when unset.
+    requested_run_name: Option<String>,
+    /// Directory of the new run, claimed before initialization so its name shows right away.
+    run_dir: Option<PathBuf>,
+    watch: Option<ScenarioWatch>,
+    /// Trace every probe evaluation in the probes tab.
+    author: bool,
+    /// Index into [`App::traced_probes`] of the probe the probes tab re-evaluates.
+    traced_probe: usize,
+    /// Keep the new run's disks on a tmpfs.
+    ephemeral: bool,
+    /// Keep the new run's listeners to this host.
+    paranoid: bool,
+    /// Let the new run's completion hooks and external actions run on this host.
+    allow_host_hooks: bool,
+    kept_run: Option<String>,
+    archived_run: Option<PathBuf>,
 }
 
 impl App {
@@ -246,40 +558,235 @@ impl App {
             agent_binary_x86_64,
             agent_binary_aarch64,
             stages: StageTimers::new(now),
+            sleep_detector: SleepDetector::default(),
             action_lines: Vec::new(),
             actions_since: now,
             active_tab: MainTab::Briefing,
+            tab_before_debug: MainTab::Briefing,
+            log_path: IntarDirs::new()
+                .ok()
+                .map(|dirs| dirs.logs_dir().join("intar.log")),
+            log_offset: 0,
+            debug_log: Vec::new(),
+            session_vm: None,
+            terminal: None,
+            terminal_vm: None,
+            terminal_error: None,
+            failure: None,
+            failure_log: Vec::new(),
             download_image: None,
             download_total: 0,
             download_index: 0,
             download_progress: 0.0,
+            download_retry: None,
             vm_progress_name: None,
             vm_progress_total: 0,
             vm_progress_index: 0,
             vm_progress_step: None,
+            boot_expected: None,
+            boot_stages: HashMap::new(),
+            vms_started: HashSet::new(),
+            agents_ready: HashSet::new(),
+            boot_probes: None,
+            audit_summary: None,
+            seed: None,
+            final_report: None,
+            keep_on_exit: false,
+            auto_balloon: false,
+            idle_suspend: None,
+            ttl: None,
+            qemu_priority: QemuPriority::default(),
+            boot_concurrency: None,
+            backend: BackendKind::default(),
+            exit_action: ExitAction::Destroy,
+            resume_dir: None,
+            requested_run_name: None,
+            run_dir: None,
+            watch: None,
+            author: false,
+            traced_probe: 0,
+            ephemeral: false,
+            paranoid: false,
+            allow_host_hooks: false,
+            kept_run: None,
+            archived_run: None,
         }
     }
 
+    /// Use a fixed seed for the run's name and `random_one_of` step groups instead of a random
+    /// one.
+    #[must_use]
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Leave the VMs running on quit, without asking, so the run can be picked up with
+    /// `intar attach`.
+    #[must_use]
+    pub fn with_keep_on_exit(mut self, keep: bool) -> Self {
+        self.keep_on_exit = keep;
+        self.exit_action = if keep {
+            ExitAction::Keep
+        } else {
+            ExitAction::Destroy
+        };
+        self
+    }
+
+    /// Reclaim memory from idle VMs while the host is short on RAM.
+    #[must_use]
+    pub fn with_auto_balloon(mut self, enabled: bool) -> Self {
+        self.auto_balloon = enabled;
+        self
+    }
+
+    /// Pause the VMs once no SSH action or probe status change happened for `after`.
+    #[must_use]
+    pub fn with_idle_suspend(mut self, after: Option<Duration>) -> Self {
+        self.idle_suspend = after;
+        self
+    }
+
+    /// Stop and archive the run once `ttl` has passed, instead of the scenario's `timeouts.ttl`.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Start QEMU with lower scheduling priority and/or pinned to a CPU set.
+    #[must_use]
+    pub fn with_qemu_priority(mut self, priority: QemuPriority) -> Self {
+        self.qemu_priority = priority;
+        self
+    }
+
+    /// Boot at most `limit` VMs at once, in waves (see [`ScenarioRunner::set_boot_concurrency`]).
+    #[must_use]
+    pub fn with_boot_concurrency(mut self, limit: Option<u32>) -> Self {
+        self.boot_concurrency = limit;
+        self
+    }
+
+    /// Run the VMs on `backend`; [`BackendKind::Fake`] simulates them without QEMU.
+    #[must_use]
+    pub fn with_backend(mut self, backend: BackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Name the new run's directory `name` instead of a generated name.
+    #[must_use]
+    pub fn with_run_name(mut self, name: Option<String>) -> Self {
+        self.requested_run_name = name;
+        self
+    }
+
+    /// Reload the probes and descriptions from the scenario file at `path` whenever it is
+    /// saved while the run is up.
+    #[must_use]
+    pub fn with_watch(mut self, path: Option<PathBuf>) -> Self {
+        self.watch = path.map(ScenarioWatch::new);
+        self
+    }
+
+    /// Show every probe evaluation, with its timing and the agent's reply, in a probes tab
+    /// that can also re-evaluate a probe on demand.
+    #[must_use]
+    pub fn with_author(mut self, enabled: bool) -> Self {
+        self.author = enabled;
+        self
+    }
+
+    /// Keep the new run's overlay disks in RAM for fast resets; they do not survive a host
+    /// reboot.
+    #[must_use]
+    pub fn with_ephemeral(mut self, enabled: bool) -> Self {
+        self.ephemeral = enabled;
+        self
+    }
+
+    /// Keep the new run to this host: SSH forwards on loopback, a private run directory, and no
+    /// QEMU listener beyond those (see [`ScenarioRunner::set_paranoid`]).
+    #[must_use]
+    pub fn with_paranoid(mut self, enabled: bool) -> Self {
+        self.paranoid = enabled;
+        self
+    }
+
+    /// Let the new run's `on_complete` host commands and webhooks and its external action
+    /// providers run on this host (see
+    /// [`ScenarioRunner::set_allow_host_hooks`]).
+    #[must_use]
+    pub fn with_allow_host_hooks(mut self, enabled: bool) -> Self {
+        self.allow_host_hooks = enabled;
+        self
+    }
+
+    /// Re-attach to the kept run in `run_dir` instead of creating new VMs.
+    #[must_use]
+    pub fn with_resume(mut self, run_dir: PathBuf) -> Self {
+        self.resume_dir = Some(run_dir);
+        self
+    }
+
+    /// Name of the run left running on quit (see [`App::with_keep_on_exit`]).
+    #[must_use]
+    pub fn kept_run(&self) -> Option<&str> {
+        self.kept_run.as_deref()
+    }
+
+    /// Whether the run's disks are in RAM, including a resumed run started with `--ephemeral`.
+    #[must_use]
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral
+            || self
+                .runner
+                .as_ref()
+                .is_some_and(ScenarioRunner::is_ephemeral)
+    }
+
+    /// Where the run's records were archived after its TTL ran out.
+    #[must_use]
+    pub fn archived_run(&self) -> Option<&Path> {
+        self.archived_run.as_deref()
+    }
+
+    /// Objectives and teardown probe results gathered while shutting down, if the scenario
+    /// reached the running phase.
+    #[must_use]
+    pub fn final_report(&self) -> Option<&FinalReport> {
+        self.final_report.as_ref()
+    }
+
     /// Run the TUI event loop.
     ///
     /// # Errors
     /// Returns `UiError` when terminal I/O or VM interactions fail.
     pub async fn run(&mut self) -> Result<(), UiError> {
+        // Claimed before the terminal is taken over, so a taken name is reported plainly.
+        let run_dir = match self.resume_dir.clone() {
+            Some(run_dir) => run_dir,
+            None => self.claim_run_dir()?,
+        };
         let mut terminal = setup_terminal(self.flags.alt_screen.enabled())?;
         self.apply_theme(ThemeSettings::resolve());
+        let language = init_messages();
+        if let Some(language) = &language {
+            self.scenario.localize(language);
+        }
 
         Self::spawn_shutdown_listener(self.shutdown_signal.clone());
 
         let (progress_tx, mut progress_rx) = mpsc::channel::<ProgressUpdate>(100);
 
-        let mut init_handle = Some(tokio::spawn(Self::start_initialization(
-            self.scenario.clone(),
-            self.agent_binary_x86_64.clone(),
-            self.agent_binary_aarch64.clone(),
-            progress_tx,
-        )));
-
-        let mut init_result: Option<Result<ScenarioRunner, VmError>> = None;
+        self.log_offset = self
+            .log_path
+            .as_deref()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map_or(0, |meta| meta.len());
+        let mut init_handle = Some(self.spawn_initialization(run_dir.clone(), progress_tx.clone()));
 
         let tick_rate = Duration::from_millis(100);
         let probe_check_interval = Duration::from_secs(2);
@@ -291,10 +798,17 @@ impl App {
                 break;
             }
 
-            self.poll_initialization(&mut init_handle, &mut init_result)
-                .await?;
+            self.poll_initialization(&mut init_handle).await?;
+            if std::mem::take(&mut self.flags.retry_init) {
+                self.reset_initialization();
+                init_handle = Some(self.spawn_initialization(run_dir.clone(), progress_tx.clone()));
+            }
             self.drain_progress_updates(&mut progress_rx);
             self.drain_action_lines();
+            if let Some(pane) = self.terminal.as_mut() {
+                pane.poll();
+            }
+            self.apply_vm_events();
 
             terminal.draw(|f| self.draw(f))?;
 
@@ -303,19 +817,41 @@ impl App {
             }
 
             self.tick = self.tick.wrapping_add(1);
+            if self.active_tab == MainTab::Debug
+                && self.tick.is_multiple_of(DEBUG_LOG_REFRESH_TICKS)
+            {
+                self.refresh_debug_log();
+            }
 
+            if self.check_ttl(&mut terminal).await? {
+                break;
+            }
+
+            if self.recover_from_host_sleep().await
+                || self.maybe_reload_scenario(language.as_deref())
+            {
+                // Re-verify probe state right away.
+                last_probe_check = Instant::now()
+                    .checked_sub(probe_check_interval)
+                    .unwrap_or(last_probe_check);
+            }
             self.maybe_check_probes(&mut last_probe_check, probe_check_interval)
                 .await?;
+            self.run_completion_hook().await;
 
             if self.flags.should_quit {
                 break;
             }
         }
 
-        self.finish_initialization(&mut terminal, &mut init_handle, &mut init_result)
+        self.finish_initialization(&mut terminal, &mut init_handle)
             .await?;
+        if let Some(run_dir) = &self.run_dir {
+            // Still empty if the run failed or was quit before its VMs were created.
+            let _ = std::fs::remove_dir(run_dir);
+        }
 
-        if let Some(Err(e)) = init_result {
+        if let Some(e) = self.failure.take() {
             restore_terminal(&mut terminal, self.flags.alt_screen.enabled())?;
             return Err(e.into());
         }
@@ -323,6 +859,60 @@ impl App {
         Ok(())
     }
 
+    fn spawn_initialization(
+        &mut self,
+        run_dir: PathBuf,
+        progress_tx: mpsc::Sender<ProgressUpdate>,
+    ) -> tokio::task::JoinHandle<Result<ScenarioRunner, VmError>> {
+        if self.resume_dir.is_some() {
+            match RunProgress::load(&run_dir) {
+                Ok(Some(progress)) => self.stages.restore(&progress.stage_secs, Instant::now()),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to load run progress: {}", e),
+            }
+            tokio::spawn(Self::resume_initialization(
+                run_dir,
+                self.agent_binary_x86_64.clone(),
+                self.agent_binary_aarch64.clone(),
+                progress_tx,
+            ))
+        } else {
+            tokio::spawn(Self::start_initialization(
+                self.scenario.clone(),
+                run_dir,
+                self.agent_binary_x86_64.clone(),
+                self.agent_binary_aarch64.clone(),
+                RunnerSettings {
+                    seed: self.seed.unwrap_or_else(random_seed),
+                    ttl: self
+                        .ttl
+                        .or(self.scenario.timeouts.ttl_secs.map(Duration::from_secs)),
+                    qemu_priority: self.qemu_priority.clone(),
+                    boot_concurrency: self.boot_concurrency,
+                    backend: self.backend,
+                    author: self.author,
+                    ephemeral: self.ephemeral,
+                    paranoid: self.paranoid,
+                    allow_host_hooks: self.allow_host_hooks,
+                },
+                progress_tx,
+            ))
+        }
+    }
+
+    /// The new run's directory, created with the requested or a generated name on first use.
+    fn claim_run_dir(&mut self) -> Result<PathBuf, UiError> {
+        if let Some(run_dir) = &self.run_dir {
+            return Ok(run_dir.clone());
+        }
+        let dirs = IntarDirs::new()?;
+        dirs.ensure_dirs()?;
+        let seed = *self.seed.get_or_insert_with(random_seed);
+        let run_dir = dirs.create_seeded_run_dir(self.requested_run_name.as_deref(), seed)?;
+        self.run_dir = Some(run_dir.clone());
+        Ok(run_dir)
+    }
+
     fn spawn_shutdown_listener(shutdown_signal: Arc<AtomicBool>) {
         tokio::spawn(async move {
             let _ = tokio::signal::ctrl_c().await;
@@ -333,7 +923,6 @@ impl App {
     async fn poll_initialization(
         &mut self,
         init_handle: &mut Option<tokio::task::JoinHandle<Result<ScenarioRunner, VmError>>>,
-        init_result: &mut Option<Result<ScenarioRunner, VmError>>,
     ) -> Result<(), UiError> {
         if self.runner.is_none()
             && init_handle
@@ -342,25 +931,57 @@ impl App {
             && let Some(handle) = init_handle.take()
         {
             match handle.await {
-                Ok(Ok(runner)) => {
+                Ok(Ok(mut runner)) => {
+                    if self.keep_on_exit {
+                        runner.keep_vms();
+                    }
+                    if self.auto_balloon {
+                        runner.enable_auto_balloon();
+                    }
                     self.runner = Some(runner);
                 }
-                Ok(Err(e)) => {
-                    *init_result = Some(Err(e));
-                    self.flags.should_quit = true;
-                }
-                Err(e) => {
-                    *init_result = Some(Err(VmError::Qemu(format!(
-                        "Initialization task failed: {e}",
-                    ))));
-                    self.flags.should_quit = true;
-                }
+                Ok(Err(e)) => self.fail_initialization(e),
+                Err(e) => self.fail_initialization(VmError::Qemu(format!(
+                    "Initialization task failed: {e}",
+                ))),
             }
         }
 
         Ok(())
     }
 
+    fn fail_initialization(&mut self, error: VmError) {
+        warn!("Initialization failed: {}", error);
+        self.failure_log = self
+            .log_path
+            .as_deref()
+            .and_then(|path| read_log_tail(path, self.log_offset, DEBUG_LOG_TAIL_BYTES).ok())
+            .unwrap_or_default();
+        self.failure = Some(error);
+        self.phase = AppPhase::Failed;
+        self.flags.show_failure_log = false;
+        self.scroll = 0;
+    }
+
+    /// Forget the failed attempt's progress before initialization starts over.
+    fn reset_initialization(&mut self) {
+        self.failure = None;
+        self.failure_log.clear();
+        self.error_message = None;
+        self.phase = AppPhase::Initializing;
+        self.stages = StageTimers::new(Instant::now());
+        self.download_image = None;
+        self.download_progress = 0.0;
+        self.download_retry = None;
+        self.vm_progress_name = None;
+        self.vm_progress_step = None;
+        self.boot_expected = None;
+        self.boot_stages.clear();
+        self.vms_started.clear();
+        self.agents_ready.clear();
+        self.boot_probes = None;
+    }
+
     fn drain_progress_updates(&mut self, progress_rx: &mut mpsc::Receiver<ProgressUpdate>) {
         while let Ok(update) = progress_rx.try_recv() {
             self.handle_progress_update(update);
@@ -383,6 +1004,62 @@ impl App {
         Ok(false)
     }
 
+    /// Under `--watch`, swap in the probes of the scenario file once it was saved. Edits a
+    /// running scenario cannot pick up, like new VMs, are logged and skipped. Returns whether
+    /// the probes were reloaded.
+    fn maybe_reload_scenario(&mut self, language: Option<&str>) -> bool {
+        let Some(watch) = self.watch.as_mut() else {
+            return false;
+        };
+        if !matches!(self.phase, AppPhase::Running) || watch.checked.elapsed() < WATCH_INTERVAL {
+            return false;
+        }
+        watch.checked = Instant::now();
+        let modified = file_modified(&watch.path);
+        if modified == watch.modified {
+            return false;
+        }
+        watch.modified = modified;
+
+        let path = watch.path.display();
+        let mut scenario = match Scenario::from_file(&watch.path)
+            .and_then(|scenario| scenario.validate().map(|()| scenario))
+        {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                warn!("Not reloading {path}: {e}");
+                return false;
+            }
+        };
+        if let Some(language) = language {
+            scenario.localize(language);
+        }
+        if let Some(section) = self.scenario.topology_change(&scenario) {
+            warn!("Not reloading {path}: '{section}' changed; start a new run to apply it");
+            return false;
+        }
+        if let Some(runner) = self.runner.as_mut()
+            && let Err(e) = runner.reload_probes(&scenario)
+        {
+            warn!("Failed to save the reloaded scenario: {e}");
+        }
+        self.scenario.reload_probes(&scenario);
+        info!("Reloaded probes from {path}");
+        true
+    }
+
+    /// Run the completion hook shown since the last tick, then show the next queued one so
+    /// the learner sees which hook runs before it does.
+    async fn run_completion_hook(&mut self) {
+        let Some(runner) = self.runner.as_mut() else {
+            return;
+        };
+        if self.flags.running_hook.is_some() {
+            runner.run_next_completion_hook().await;
+        }
+        self.flags.running_hook = runner.next_completion_hook();
+    }
+
     async fn maybe_check_probes(
         &mut self,
         last_probe_check: &mut Instant,
@@ -392,48 +1069,218 @@ impl App {
             && last_probe_check.elapsed() >= interval
             && let Some(ref mut runner) = self.runner
         {
+            if self.flags.idle_suspended
+                || Self::guard_disk_space(runner, &mut self.flags.low_space).await?
+            {
+                *last_probe_check = Instant::now();
+                return Ok(());
+            }
+            if let Some(after) = self.idle_suspend
+                && runner.idle_for() >= after
+            {
+                runner.pause_vms().await?;
+                self.flags.idle_suspended = true;
+                *last_probe_check = Instant::now();
+                return Ok(());
+            }
+            runner.rebalance_memory().await;
+            runner.sample_host_cpu();
+            let since_ready = self.stages.run.elapsed(Instant::now()).unwrap_or_default();
+            runner.fire_pending_triggers(since_ready).await;
             runner.check_probes().await?;
-            if runner.state == ScenarioState::Completed {
+            runner
+                .record_probe_history(self.stages.run.elapsed(Instant::now()).unwrap_or_default());
+            if self.flags.question.is_none()
+                && let Some(question) = runner.pending_question()
+                && self.flags.dismissed_question.as_deref() != Some(question.name.as_str())
+            {
+                self.flags.question = Some(QuestionPrompt::new(question.name.clone()));
+            }
+            if runner.state == ScenarioState::Completed && runner.results_visible() {
                 let now = Instant::now();
+                self.audit_summary = Some(runner.audit_summary());
                 self.phase = AppPhase::Completed;
                 self.scroll = 0;
                 self.stages.run.end_if_needed(now);
             }
+            self.save_progress();
             *last_probe_check = Instant::now();
         }
 
         Ok(())
     }
 
+    /// After the host slept, leave the sleep out of the stage timers and reconnect to the VMs.
+    /// Returns whether it slept.
+    async fn recover_from_host_sleep(&mut self) -> bool {
+        let Some(sleep) = self.sleep_detector.check() else {
+            return false;
+        };
+
+        info!(
+            "Host was asleep for {}s; reconnecting to the VMs",
+            sleep.duration.as_secs()
+        );
+        self.stages.exclude(sleep.monotonic);
+        if let Some(runner) = self.runner.as_mut() {
+            runner.reconnect().await;
+        }
+        true
+    }
+
+    /// Warn as the run's TTL runs out and tear the run down once it has. Returns whether the
+    /// run expired and was shut down.
+    async fn check_ttl(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<bool, UiError> {
+        if !matches!(self.phase, AppPhase::Running | AppPhase::Completed) {
+            return Ok(false);
+        }
+        let Some(runner) = self.runner.as_mut() else {
+            return Ok(false);
+        };
+        let Some(left) = runner.time_left() else {
+            return Ok(false);
+        };
+
+        if left.is_zero() {
+            if let Err(e) = runner.record_audit(AuditOperation::RunExpired, runner.run_id()) {
+                warn!("Failed to record run expiry in audit log: {}", e);
+            }
+            self.flags.ttl_expired = true;
+            self.flags.ttl_warning = false;
+            self.exit_action = ExitAction::Destroy;
+            self.initiate_shutdown(terminal).await?;
+            return Ok(true);
+        }
+
+        let minutes_left = left.as_secs().div_ceil(60);
+        if let Some(threshold) = TTL_WARNING_MINUTES
+            .into_iter()
+            .filter(|threshold| minutes_left <= *threshold)
+            .min()
+            && self
+                .flags
+                .ttl_warned
+                .is_none_or(|warned| threshold < warned)
+        {
+            self.flags.ttl_warned = Some(threshold);
+            self.flags.ttl_warning = true;
+        }
+        Ok(false)
+    }
+
+    /// Pause the VMs before qcow2 writes start failing on a full disk, and resume them once
+    /// space is freed. Returns whether the VMs are paused.
+    async fn guard_disk_space(
+        runner: &ScenarioRunner,
+        low_space: &mut Option<u64>,
+    ) -> Result<bool, UiError> {
+        let free = match runner.available_space() {
+            Ok(free) => free,
+            Err(e) => {
+                warn!("Failed to check free disk space: {}", e);
+                return Ok(low_space.is_some());
+            }
+        };
+
+        if low_space.is_some() {
+            if free >= RESUME_SPACE_BYTES {
+                runner.resume_vms().await?;
+                *low_space = None;
+                return Ok(false);
+            }
+        } else if free < LOW_SPACE_BYTES {
+            warn!("Only {} bytes free in the run directory; pausing VMs", free);
+            runner.pause_vms().await?;
+        } else {
+            return Ok(false);
+        }
+
+        *low_space = Some(free);
+        Ok(true)
+    }
+
+    fn save_progress(&self) {
+        if let Some(runner) = self.runner.as_ref()
+            && let Err(e) = runner.save_progress(self.stages.elapsed_secs(Instant::now()))
+        {
+            warn!("Failed to save run progress: {}", e);
+        }
+    }
+
     fn drain_action_lines(&mut self) {
         let Some(runner) = self.runner.as_mut() else {
             return;
         };
 
         let mut new = runner.drain_action_lines();
+        new.retain(|ev| ev.received_at >= self.actions_since);
         if new.is_empty() {
             return;
         }
 
         self.action_lines.append(&mut new);
-        self.action_lines
-            .retain(|ev| ev.received_at >= self.actions_since);
-        self.action_lines
-            .sort_by(|a, b| a.received_at.cmp(&b.received_at));
+        self.action_lines.sort_by_key(|ev| ev.received_at);
+        // Lines read back for scrolling stay until the view follows the end again.
+        if self.scroll == 0 && self.action_lines.len() > ACTION_LINES_KEPT {
+            let excess = self.action_lines.len() - ACTION_LINES_KEPT;
+            self.action_lines.drain(..excess);
+        }
+    }
+
+    /// Prepend older action lines from the action log once the logs tab is scrolled to the
+    /// top of the lines in memory.
+    fn backfill_action_lines(&mut self) {
+        let Some(runner) = self.runner.as_ref() else {
+            return;
+        };
+        let (_, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        if usize::from(self.scroll) + usize::from(rows) < self.action_lines.len() {
+            return;
+        }
+        let log = runner.action_log();
+        // The lines in memory are the last ones the log holds.
+        let end = log.len().saturating_sub(self.action_lines.len());
+        match log.read_before(end, ACTION_LINES_PAGE) {
+            Ok(mut older) if !older.is_empty() => {
+                older.append(&mut self.action_lines);
+                self.action_lines = older;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to read the action log: {}", e),
+        }
+    }
+
+    fn apply_vm_events(&mut self) {
+        let Some(runner) = self.runner.as_mut() else {
+            return;
+        };
+
+        for event in runner.apply_qmp_events() {
+            if event.name == "GUEST_PANICKED" && self.flags.guest_panic.is_none() {
+                self.flags.guest_panic = Some(event.vm);
+            } else if event.name == QEMU_EXITED_EVENT
+                && self.flags.crashed_vm.is_none()
+                && runner.crash(&event.vm).is_some()
+            {
+                self.flags.crashed_vm = Some(event.vm);
+            }
+        }
     }
 
     async fn finish_initialization(
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
         init_handle: &mut Option<tokio::task::JoinHandle<Result<ScenarioRunner, VmError>>>,
-        init_result: &mut Option<Result<ScenarioRunner, VmError>>,
     ) -> Result<(), UiError> {
         let Some(handle) = init_handle.take() else {
             return Ok(());
         };
 
         // If the user quit before initialization finished, abort to avoid hanging.
-        if self.runner.is_none() && init_result.is_none() && !handle.is_finished() {
+        if self.runner.is_none() && !handle.is_finished() {
             handle.abort();
             let _ = handle.await;
             return Ok(());
@@ -462,13 +1309,83 @@ impl App {
         key: crossterm::event::KeyEvent,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     ) -> Result<bool, UiError> {
+        if self.flags.show_confirm_quit {
+            return self.handle_confirm_quit(key, terminal).await;
+        }
+
         if self.flags.show_confirm_reset {
-            self.handle_confirm_reset(key).await?;
+            self.handle_confirm_reset(key, terminal).await?;
+            return Ok(false);
+        }
+
+        if self.flags.ttl_warning {
+            if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+                self.flags.ttl_warning = false;
+            }
+            return Ok(false);
+        }
+
+        if let Some(vm) = self.flags.crashed_vm.clone() {
+            match key.code {
+                KeyCode::Char('r' | 'R') => {
+                    self.flags.crashed_vm = None;
+                    if let Some(runner) = self.runner.as_mut() {
+                        runner.restart_vm(&vm).await?;
+                    }
+                }
+                KeyCode::Esc => self.flags.crashed_vm = None,
+                _ => {}
+            }
+            return Ok(false);
+        }
+
+        if self.flags.guest_panic.is_some() {
+            if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+                self.flags.guest_panic = None;
+            }
+            return Ok(false);
+        }
+
+        if self.flags.unlock_input.is_some() {
+            self.handle_unlock_input(key);
+            return Ok(false);
+        }
+
+        if self.flags.flag_input.is_some() {
+            self.handle_flag_input(key);
+            return Ok(false);
+        }
+
+        if self.flags.question.is_some() {
+            self.handle_question_input(key);
             return Ok(false);
         }
 
         let is_ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
 
+        // While suspended for inactivity, only resuming and quitting are offered.
+        if self.flags.idle_suspended {
+            if key.code == KeyCode::Enter {
+                if let Some(runner) = self.runner.as_mut() {
+                    runner.resume_vms().await?;
+                    runner.note_activity();
+                }
+                self.flags.idle_suspended = false;
+                return Ok(false);
+            }
+            if !Self::should_quit(key, is_ctrl) {
+                return Ok(false);
+            }
+        }
+
+        if self.active_tab == MainTab::Terminal && self.handle_terminal_key(key) {
+            return Ok(false);
+        }
+
+        if self.phase == AppPhase::Failed {
+            return Ok(self.handle_failed(key, is_ctrl));
+        }
+
         if self.is_briefing_phase() {
             if self.handle_overlay_toggles(key) {
                 return Ok(false);
@@ -485,6 +1402,10 @@ impl App {
         }
 
         if Self::should_quit(key, is_ctrl) {
+            if !is_ctrl && !self.keep_on_exit {
+                self.flags.show_confirm_quit = true;
+                return Ok(false);
+            }
             self.initiate_shutdown(terminal).await?;
             return Ok(true);
         }
@@ -494,24 +1415,98 @@ impl App {
             return Ok(false);
         }
 
+        if self.should_answer_question(key) {
+            self.flags.question = self
+                .runner
+                .as_ref()
+                .and_then(ScenarioRunner::pending_question)
+                .map(|q| QuestionPrompt::new(q.name.clone()));
+            return Ok(false);
+        }
+
+        if self.should_submit_flag(key) {
+            self.flags.flag_input = Some(String::new());
+            self.flags.flag_rejected = false;
+            return Ok(false);
+        }
+
+        if self.should_fire_trigger(key) {
+            self.fire_next_manual_trigger();
+            return Ok(false);
+        }
+
+        if self.should_toggle_capture(key) {
+            self.toggle_lan_capture();
+            return Ok(false);
+        }
+
+        if self.should_unlock(key) {
+            self.flags.unlock_input = Some(String::new());
+            self.flags.unlock_failed = false;
+            return Ok(false);
+        }
+
+        if self.should_toggle_debug(key) {
+            self.toggle_debug_tab();
+            return Ok(false);
+        }
+
+        if self.active_tab == MainTab::Probes && key.code == KeyCode::Char('e') {
+            self.recheck_traced_probe().await;
+            return Ok(false);
+        }
+
         self.handle_navigation(key);
 
         Ok(false)
     }
 
-    async fn handle_confirm_reset(&mut self, key: KeyEvent) -> Result<(), UiError> {
+    async fn handle_confirm_quit(
+        &mut self,
+        key: KeyEvent,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<bool, UiError> {
+        let action = match key.code {
+            KeyCode::Char('d' | 'D' | 'q' | 'Q') => ExitAction::Destroy,
+            KeyCode::Char('k' | 'K') => ExitAction::Keep,
+            KeyCode::Char('p' | 'P') => ExitAction::KeepPaused,
+            KeyCode::Esc | KeyCode::Char('n' | 'N') => {
+                self.flags.show_confirm_quit = false;
+                return Ok(false);
+            }
+            _ => return Ok(false),
+        };
+
+        self.flags.show_confirm_quit = false;
+        self.exit_action = action;
+        self.initiate_shutdown(terminal).await?;
+        Ok(true)
+    }
+
+    async fn handle_confirm_reset(
+        &mut self,
+        key: KeyEvent,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<(), UiError> {
         match key.code {
             KeyCode::Char('y' | 'Y') => {
                 self.flags.show_confirm_reset = false;
-                if let Some(ref mut runner) = self.runner {
-                    runner.reset().await?;
+                if let Some(mut runner) = self.runner.take() {
+                    let reset = self.reset_with_dialog(&mut runner, terminal).await;
+                    self.runner = Some(runner);
+                    self.flags.checkpoint = None;
+                    reset?;
                     let now = Instant::now();
                     self.phase = AppPhase::Running;
                     self.error_message = None;
                     self.stages.run.reset_to_running(now);
+                    // Waiting for the agents again is not a host sleep.
+                    self.sleep_detector = SleepDetector::default();
                     self.scroll = 0;
                     self.action_lines.clear();
                     self.actions_since = now;
+                    self.flags.question = None;
+                    self.flags.dismissed_question = None;
                 }
             }
             KeyCode::Char('n' | 'N') | KeyCode::Esc => {
@@ -522,6 +1517,201 @@ impl App {
         Ok(())
     }
 
+    /// Reset `runner`, showing how far restoring the checkpoint has got until it is back.
+    async fn reset_with_dialog(
+        &mut self,
+        runner: &mut ScenarioRunner,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<(), UiError> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let reset = runner.reset_with_progress(move |progress| {
+            let _ = tx.send(progress.clone());
+        });
+        tokio::pin!(reset);
+        loop {
+            tokio::select! {
+                result = &mut reset => return Ok(result?),
+                Some(progress) = rx.recv() => {
+                    self.flags.checkpoint = Some(progress);
+                    terminal.draw(|f| self.draw(f))?;
+                }
+            }
+        }
+    }
+
+    fn handle_unlock_input(&mut self, key: KeyEvent) {
+        let Some(input) = self.flags.unlock_input.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(c) => {
+                input.push(c);
+                self.flags.unlock_failed = false;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let code = std::mem::take(input);
+                let unlocked = self
+                    .runner
+                    .as_mut()
+                    .is_some_and(|runner| runner.unlock_results(&code));
+                if unlocked {
+                    self.flags.unlock_input = None;
+                }
+                self.flags.unlock_failed = !unlocked;
+            }
+            KeyCode::Esc => {
+                self.flags.unlock_input = None;
+                self.flags.unlock_failed = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_flag_input(&mut self, key: KeyEvent) {
+        let Some(input) = self.flags.flag_input.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(c) => {
+                input.push(c);
+                self.flags.flag_rejected = false;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let flag = std::mem::take(input);
+                let accepted = self
+                    .runner
+                    .as_mut()
+                    .is_some_and(|runner| runner.submit_flag(&flag));
+                if accepted {
+                    self.flags.flag_input = None;
+                }
+                self.flags.flag_rejected = !accepted;
+            }
+            KeyCode::Esc => {
+                self.flags.flag_input = None;
+                self.flags.flag_rejected = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_question_input(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.flags.question.as_mut() else {
+            return;
+        };
+
+        if prompt.feedback.is_some_and(AnswerOutcome::closes_question) {
+            self.flags.question = None;
+            return;
+        }
+
+        let choice_count = self
+            .scenario
+            .questions
+            .iter()
+            .find(|q| q.name == prompt.name)
+            .map_or(0, |q| q.choices.len());
+
+        match key.code {
+            KeyCode::Esc => {
+                self.flags.dismissed_question = Some(prompt.name.clone());
+                self.flags.question = None;
+            }
+            KeyCode::Up if choice_count > 0 => {
+                prompt.selected = prompt.selected.saturating_sub(1);
+                prompt.feedback = None;
+            }
+            KeyCode::Down if choice_count > 0 => {
+                prompt.selected = (prompt.selected + 1).min(choice_count - 1);
+                prompt.feedback = None;
+            }
+            KeyCode::Char(c) if choice_count > 0 => {
+                if let Some(idx) = c.to_digit(10).and_then(|d| usize::try_from(d).ok())
+                    && (1..=choice_count).contains(&idx)
+                {
+                    prompt.selected = idx - 1;
+                    prompt.feedback = None;
+                }
+            }
+            KeyCode::Char(c) => {
+                prompt.input.push(c);
+                prompt.feedback = None;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Enter => {
+                let answer = if choice_count > 0 {
+                    self.scenario
+                        .questions
+                        .iter()
+                        .find(|q| q.name == prompt.name)
+                        .and_then(|q| q.choices.get(prompt.selected))
+                        .cloned()
+                        .unwrap_or_default()
+                } else {
+                    prompt.input.clone()
+                };
+                if answer.trim().is_empty() {
+                    return;
+                }
+                let Some(runner) = self.runner.as_mut() else {
+                    return;
+                };
+                match runner.answer_question(&prompt.name, &answer) {
+                    Ok(outcome) => {
+                        if outcome == AnswerOutcome::Incorrect {
+                            prompt.input.clear();
+                        }
+                        prompt.feedback = Some(outcome);
+                    }
+                    Err(e) => warn!("Failed to record answer: {}", e),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys of the failure screen; returns whether to quit.
+    fn handle_failed(&mut self, key: KeyEvent, is_ctrl: bool) -> bool {
+        if Self::should_quit(key, is_ctrl) || key.code == KeyCode::Esc {
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('r' | 'R') => self.flags.retry_init = true,
+            KeyCode::Char('d' | 'D') => {
+                if let Some(VmError::CorruptImage(path)) = &self.failure {
+                    // Initialization downloads the evicted image again.
+                    let evicted = IntarDirs::new()
+                        .and_then(|dirs| ImageCache::new(dirs.images_dir()).evict(path));
+                    match evicted {
+                        Ok(()) => self.flags.retry_init = true,
+                        Err(e) => warn!("Failed to remove corrupt image {}: {}", path.display(), e),
+                    }
+                }
+            }
+            KeyCode::Char('l' | 'L') => {
+                self.flags.show_failure_log = !self.flags.show_failure_log;
+                self.scroll = 0;
+            }
+            KeyCode::Char('t') => {
+                self.handle_overlay_toggles(key);
+            }
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(10),
+            _ => {}
+        }
+        false
+    }
+
     fn handle_overlay_toggles(&mut self, key: KeyEvent) -> bool {
         match key.code {
             KeyCode::Char('?') => {
@@ -541,69 +1731,462 @@ impl App {
         (is_ctrl && matches!(key.code, KeyCode::Char('c' | 'q'))) || key.code == KeyCode::Char('q')
     }
 
-    fn should_reset(&self, key: KeyEvent, is_ctrl: bool) -> bool {
-        key.code == KeyCode::Char('r')
-            && (is_ctrl || matches!(self.phase, AppPhase::Running | AppPhase::Completed))
-            && self.runner.is_some()
+    fn should_reset(&self, key: KeyEvent, is_ctrl: bool) -> bool {
+        key.code == KeyCode::Char('r')
+            && (is_ctrl || matches!(self.phase, AppPhase::Running | AppPhase::Completed))
+            && self.runner.is_some()
+            && !self.scenario.is_exam()
+    }
+
+    fn should_answer_question(&self, key: KeyEvent) -> bool {
+        key.code == KeyCode::Char('a')
+            && matches!(self.phase, AppPhase::Running)
+            && self
+                .runner
+                .as_ref()
+                .is_some_and(|runner| runner.pending_question().is_some())
+    }
+
+    fn should_submit_flag(&self, key: KeyEvent) -> bool {
+        key.code == KeyCode::Char('s')
+            && matches!(self.phase, AppPhase::Running)
+            && self.runner.is_some()
+            && self
+                .scenario
+                .probes
+                .values()
+                .any(|def| def.probe_type == "flag")
+    }
+
+    fn should_fire_trigger(&self, key: KeyEvent) -> bool {
+        key.code == KeyCode::Char('f') && matches!(self.phase, AppPhase::Running)
+    }
+
+    fn fire_next_manual_trigger(&mut self) {
+        let Some(runner) = self.runner.as_mut() else {
+            return;
+        };
+        let Some(next) = runner.pending_manual_triggers().into_iter().next() else {
+            return;
+        };
+        if let Err(e) = runner.trigger_step(&next) {
+            warn!("Failed to trigger step {}: {}", next, e);
+        }
+    }
+
+    fn should_toggle_capture(&self, key: KeyEvent) -> bool {
+        key.code == KeyCode::Char('c')
+            && matches!(self.phase, AppPhase::Running)
+            && self
+                .runner
+                .as_ref()
+                .is_some_and(|runner| runner.lan_stats().is_some())
+    }
+
+    /// Start capturing the shared LAN into the run directory, or stop the running capture.
+    fn toggle_lan_capture(&mut self) {
+        let Some(runner) = self.runner.as_mut() else {
+            return;
+        };
+        let result = if runner.lan_capture().is_some() {
+            runner.stop_lan_capture()
+        } else {
+            runner.start_lan_capture(None)
+        };
+        match result {
+            Ok(()) => match runner.lan_capture() {
+                Some(path) => info!("Capturing the shared LAN to {}", path.display()),
+                None => info!("Stopped the shared LAN capture"),
+            },
+            Err(e) => warn!("Failed to toggle the LAN capture: {}", e),
+        }
+    }
+
+    fn should_unlock(&self, key: KeyEvent) -> bool {
+        key.code == KeyCode::Char('u')
+            && self
+                .runner
+                .as_ref()
+                .is_some_and(|runner| !runner.results_visible())
+    }
+
+    fn should_toggle_debug(&self, key: KeyEvent) -> bool {
+        matches!(key.code, KeyCode::Char('D') | KeyCode::F(12))
+            && matches!(self.phase, AppPhase::Running)
+    }
+
+    /// Every scenario probe as `(vm, probe)`, in the order the probes tab lists them.
+    fn traced_probes(&self) -> Vec<(&str, &str)> {
+        self.scenario
+            .vms
+            .iter()
+            .flat_map(|vm| {
+                vm.probes
+                    .iter()
+                    .filter(|probe| {
+                        self.scenario
+                            .probes
+                            .get(*probe)
+                            .is_some_and(|def| def.phase == intar_core::ProbePhase::Scenario)
+                    })
+                    .map(|probe| (vm.name.as_str(), probe.as_str()))
+            })
+            .collect()
+    }
+
+    /// Evaluate the probe selected in the probes tab right away.
+    async fn recheck_traced_probe(&mut self) {
+        let Some((vm, probe)) = self
+            .traced_probes()
+            .get(self.traced_probe)
+            .map(|(vm, probe)| ((*vm).to_string(), (*probe).to_string()))
+        else {
+            return;
+        };
+        if let Some(runner) = self.runner.as_mut()
+            && let Err(e) = runner.recheck_probe(&vm, &probe).await
+        {
+            warn!("Failed to re-evaluate probe {probe} on {vm}: {e}");
+        }
+    }
+
+    fn toggle_debug_tab(&mut self) {
+        if self.active_tab == MainTab::Debug {
+            self.active_tab = self.tab_before_debug;
+        } else {
+            self.tab_before_debug = self.active_tab;
+            self.active_tab = MainTab::Debug;
+            self.refresh_debug_log();
+        }
+        self.scroll = 0;
+    }
+
+    /// Keys in the terminal tab go to its ssh session, except `Ctrl+]` to leave the tab and
+    /// `F2` to switch VMs. Returns whether `key` was used.
+    fn handle_terminal_key(&mut self, key: KeyEvent) -> bool {
+        if !matches!(self.phase, AppPhase::Running | AppPhase::Completed) {
+            return false;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char(']') {
+            self.active_tab = self.active_tab.next(self.author);
+            return true;
+        }
+        if key.code == KeyCode::F(2) {
+            self.switch_terminal_vm();
+            return true;
+        }
+        if let Some(pane) = self
+            .terminal
+            .as_mut()
+            .filter(|pane| pane.exit_status().is_none())
+        {
+            if let Err(e) = pane.send_key(key) {
+                warn!("Failed to write to ssh in the terminal tab: {}", e);
+            }
+            return true;
+        }
+        if key.code == KeyCode::Enter {
+            self.open_terminal();
+            return true;
+        }
+        false
+    }
+
+    fn terminal_vm(&self) -> Option<&str> {
+        self.terminal_vm
+            .as_deref()
+            .or_else(|| self.scenario.vms.first().map(|vm| vm.name.as_str()))
+    }
+
+    /// Connect the terminal tab to the next VM, reconnecting if a session was open.
+    fn switch_terminal_vm(&mut self) {
+        let vms = &self.scenario.vms;
+        if vms.is_empty() {
+            return;
+        }
+        let current = self
+            .terminal_vm()
+            .and_then(|name| vms.iter().position(|vm| vm.name == name))
+            .unwrap_or_default();
+        self.terminal_vm = Some(vms[(current + 1) % vms.len()].name.clone());
+        let reconnect = self
+            .terminal
+            .take()
+            .is_some_and(|pane| pane.exit_status().is_none());
+        self.terminal_error = None;
+        if reconnect {
+            self.open_terminal();
+        }
+    }
+
+    /// Start ssh to the terminal tab's VM, sized to fill the tab.
+    fn open_terminal(&mut self) {
+        self.terminal = None;
+        let Some(vm) = self.terminal_vm().map(str::to_string) else {
+            return;
+        };
+        let Some(args) = self.runner.as_ref().and_then(|r| r.ssh_args(&vm)) else {
+            self.terminal_error = Some(format!("{vm} is not running"));
+            return;
+        };
+        let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        let (chrome_columns, chrome_rows) = TERMINAL_TAB_CHROME;
+        match TerminalPane::open(
+            &vm,
+            &args,
+            columns.saturating_sub(chrome_columns),
+            rows.saturating_sub(chrome_rows),
+        ) {
+            Ok(pane) => {
+                self.terminal = Some(pane);
+                self.terminal_error = None;
+            }
+            Err(e) => self.terminal_error = Some(format!("ssh failed to start: {e}")),
+        }
+    }
+
+    /// Show the next (or previous) VM that has had a session in the session tab.
+    fn cycle_session_vm(&mut self, forward: bool) {
+        let Some(runner) = self.runner.as_ref() else {
+            return;
+        };
+        let mirror = runner.session_mirror();
+        let vms = mirror.vms();
+        if vms.is_empty() {
+            return;
+        }
+        let current = self
+            .session_vm
+            .clone()
+            .or_else(|| mirror.latest_vm())
+            .and_then(|vm| vms.iter().position(|v| *v == vm))
+            .unwrap_or_default();
+        let next = if forward {
+            (current + 1) % vms.len()
+        } else {
+            (current + vms.len() - 1) % vms.len()
+        };
+        self.session_vm = Some(vms[next].clone());
+    }
+
+    /// The VM and screen to show in the session tab.
+    fn session_screen(&self) -> Option<(String, SessionScreen)> {
+        let mirror = self.runner.as_ref()?.session_mirror();
+        let vm = self.session_vm.clone().or_else(|| mirror.latest_vm())?;
+        let screen = mirror.screen(&vm)?;
+        Some((vm, screen))
+    }
+
+    fn refresh_debug_log(&mut self) {
+        let Some(path) = self.log_path.as_deref() else {
+            return;
+        };
+        match read_log_tail(path, 0, DEBUG_LOG_TAIL_BYTES) {
+            Ok(lines) => self.debug_log = lines,
+            Err(e) => self.debug_log = vec![format!("Failed to read {}: {e}", path.display())],
+        }
+    }
+
+    fn results_visible(&self) -> bool {
+        self.runner
+            .as_ref()
+            .map_or(!self.scenario.is_exam(), ScenarioRunner::results_visible)
     }
 
     fn handle_navigation(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Tab => {
                 if key.modifiers.contains(KeyModifiers::SHIFT) {
-                    self.active_tab = self.active_tab.prev();
+                    self.active_tab = self.active_tab.prev(self.author);
                 } else {
-                    self.active_tab = self.active_tab.next();
+                    self.active_tab = self.active_tab.next(self.author);
                 }
                 self.scroll = 0;
             }
             KeyCode::BackTab => {
-                self.active_tab = self.active_tab.prev();
+                self.active_tab = self.active_tab.prev(self.author);
                 self.scroll = 0;
             }
             KeyCode::PageUp => {
-                if self.active_tab == MainTab::Logs {
+                if matches!(self.active_tab, MainTab::Logs | MainTab::Debug) {
                     self.scroll = self.scroll.saturating_add(10);
                 }
+                if self.active_tab == MainTab::Logs {
+                    self.backfill_action_lines();
+                }
             }
             KeyCode::PageDown => {
-                if self.active_tab == MainTab::Logs {
+                if matches!(self.active_tab, MainTab::Logs | MainTab::Debug) {
                     self.scroll = self.scroll.saturating_sub(10);
                 }
             }
             KeyCode::Home => {
-                if self.active_tab == MainTab::Logs {
+                if matches!(self.active_tab, MainTab::Logs | MainTab::Debug) {
                     self.scroll = u16::MAX;
                 }
+                if self.active_tab == MainTab::Logs {
+                    self.backfill_action_lines();
+                }
             }
             KeyCode::End => {
-                if self.active_tab == MainTab::Logs {
+                if matches!(self.active_tab, MainTab::Logs | MainTab::Debug) {
                     self.scroll = 0;
                 }
             }
+            KeyCode::Left | KeyCode::Right if self.active_tab == MainTab::Session => {
+                self.cycle_session_vm(key.code == KeyCode::Right);
+            }
+            KeyCode::Up if self.active_tab == MainTab::Probes => {
+                self.traced_probe = self.traced_probe.saturating_sub(1);
+            }
+            KeyCode::Down if self.active_tab == MainTab::Probes => {
+                let last = self.traced_probes().len().saturating_sub(1);
+                self.traced_probe = (self.traced_probe + 1).min(last);
+            }
             _ => {}
         }
     }
 
     async fn start_initialization(
         scenario: Scenario,
+        run_dir: PathBuf,
         agent_binary_x86_64: Vec<u8>,
         agent_binary_aarch64: Vec<u8>,
+        settings: RunnerSettings,
         progress_tx: mpsc::Sender<ProgressUpdate>,
     ) -> Result<ScenarioRunner, VmError> {
         let dirs = IntarDirs::new()?;
         dirs.ensure_dirs()?;
 
-        let image_cache = ImageCache::new(dirs.images_dir());
+        let config = IntarConfig::load(&dirs)?;
+        let image_cache = ImageCache::new(dirs.images_dir()).with_settings(&config.downloads);
         let arch = detect_arch();
+        // Simulated VMs need no images, and cloud instances boot the provider's.
+        let downloaded = if !matches!(settings.backend, BackendKind::Fake | BackendKind::Cloud) {
+            Self::fetch_images(&scenario, &dirs, &image_cache, &arch, &progress_tx).await?
+        } else {
+            0
+        };
+
+        let mut runner = ScenarioRunner::new_in_run_dir(
+            scenario.clone(),
+            agent_binary_x86_64,
+            agent_binary_aarch64,
+            &dirs,
+            run_dir,
+        )?;
+        settings.apply(&mut runner, &config)?;
+        runner.note_downloaded(downloaded);
+
+        let total_vms = scenario.vms.len();
+        for (i, vm_def) in scenario.vms.iter().enumerate() {
+            let _ = progress_tx
+                .send(ProgressUpdate::VmStart {
+                    name: vm_def.name.clone(),
+                    step: "Creating overlay disk".to_string(),
+                    total: total_vms,
+                    index: i,
+                })
+                .await;
+
+            runner.create_vm(vm_def, &image_cache, &arch)?;
+
+            let _ = progress_tx.send(ProgressUpdate::VmComplete).await;
+        }
+
+        let boot = runner.calibrate_boot_timeouts();
+        let _ = progress_tx
+            .send(ProgressUpdate::BootingVms {
+                expected: Some(boot.expected),
+            })
+            .await;
+
+        let (boot_tx, mut boot_rx) = mpsc::channel::<BootStageEvent>(64);
+        runner.report_boot_stages(boot_tx);
+        let tx = progress_tx.clone();
+        tokio::spawn(async move {
+            while let Some(event) = boot_rx.recv().await {
+                let update = ProgressUpdate::BootStage {
+                    vm: event.vm,
+                    stage: event.stage,
+                };
+                if tx.send(update).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (start_tx, mut start_rx) = mpsc::channel::<String>(64);
+        runner.report_vm_starts(start_tx);
+        let tx = progress_tx.clone();
+        tokio::spawn(async move {
+            while let Some(name) = start_rx.recv().await {
+                if tx.send(ProgressUpdate::VmStarted { name }).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        runner.start_vms()?;
+        runner.start_action_recording()?;
+        runner.start_event_tracking().await;
+
+        let tx = progress_tx.clone();
+        runner
+            .wait_for_agents_with_progress(|name| {
+                let _ = tx.try_send(ProgressUpdate::AgentReady {
+                    name: name.to_string(),
+                });
+            })
+            .await?;
+        let tx = progress_tx.clone();
+        runner
+            .wait_for_boot_probes_with_progress(|passing, total| {
+                let _ = tx.try_send(ProgressUpdate::BootProbes { passing, total });
+            })
+            .await?;
+        let tx = progress_tx.clone();
+        runner
+            .run_external_actions(|line| {
+                let _ = tx.try_send(ProgressUpdate::VmStep {
+                    step: line.to_string(),
+                });
+            })
+            .await?;
+
+        // Create a snapshot for fast resets.
+        let tx = progress_tx.clone();
+        runner
+            .save_checkpoint_with_progress("init", |progress| {
+                let _ = tx.try_send(ProgressUpdate::VmStep {
+                    step: progress.to_string(),
+                });
+            })
+            .await?;
+
+        runner.state = ScenarioState::Running;
+
+        let _ = progress_tx.send(ProgressUpdate::Ready).await;
+
+        Ok(runner)
+    }
 
+    /// Download the base images of `scenario` that are not cached yet, after checking there is
+    /// room for them and the run. Returns the bytes downloaded.
+    async fn fetch_images(
+        scenario: &Scenario,
+        dirs: &IntarDirs,
+        image_cache: &ImageCache,
+        arch: &str,
+        progress_tx: &mpsc::Sender<ProgressUpdate>,
+    ) -> Result<u64, VmError> {
         let mut images_needed: Vec<(String, &intar_core::ImageSource)> = Vec::new();
         for vm_def in &scenario.vms {
             let image_spec = scenario.images.get(&vm_def.image).ok_or_else(|| {
                 VmError::Qemu(format!("Image '{}' not defined in scenario", vm_def.image))
             })?;
 
-            let source = image_spec.source_for_arch(&arch).ok_or_else(|| {
+            let source = image_spec.source_for_arch(arch).ok_or_else(|| {
                 VmError::Qemu(format!(
                     "No image source for architecture '{}' in image '{}'",
                     arch, vm_def.image
@@ -615,6 +2198,20 @@ impl App {
             }
         }
 
+        let mut image_bytes = 0;
+        for (_, source) in &images_needed {
+            if !image_cache.is_cached(source) {
+                image_bytes += image_cache.download_size(source).await.unwrap_or(0);
+            }
+        }
+        check_disk_space(
+            &dirs.images_dir(),
+            image_bytes,
+            &dirs.runs_dir(),
+            required_run_space(scenario),
+        )?;
+
+        let mut downloaded = 0;
         let total_images = images_needed.len();
         for (i, (image_name, source)) in images_needed.iter().enumerate() {
             let _ = progress_tx
@@ -627,51 +2224,52 @@ impl App {
 
             if !image_cache.is_cached(source) {
                 let tx = progress_tx.clone();
-                image_cache
-                    .ensure_image_with_progress(source, move |progress| {
-                        let _ = tx.try_send(ProgressUpdate::DownloadProgress { progress });
+                let path = image_cache
+                    .ensure_image_with_progress(source, move |event| {
+                        let update = match event {
+                            DownloadEvent::Progress(progress) => {
+                                ProgressUpdate::DownloadProgress { progress }
+                            }
+                            DownloadEvent::Retry {
+                                attempt,
+                                max_attempts,
+                                delay,
+                                error,
+                                ..
+                            } => ProgressUpdate::DownloadRetry {
+                                attempt,
+                                max_attempts,
+                                delay,
+                                error,
+                            },
+                        };
+                        let _ = tx.try_send(update);
                     })
                     .await?;
+                downloaded += std::fs::metadata(&path).map_or(0, |meta| meta.len());
             }
 
             let _ = progress_tx.send(ProgressUpdate::DownloadComplete).await;
         }
 
-        let mut runner = ScenarioRunner::new_with_dirs(
-            scenario.clone(),
-            agent_binary_x86_64,
-            agent_binary_aarch64,
-            &dirs,
-        )?;
-
-        let total_vms = scenario.vms.len();
-        for (i, vm_def) in scenario.vms.iter().enumerate() {
-            let _ = progress_tx
-                .send(ProgressUpdate::VmStart {
-                    name: vm_def.name.clone(),
-                    step: "Creating overlay disk".to_string(),
-                    total: total_vms,
-                    index: i,
-                })
-                .await;
-
-            runner.create_vm(vm_def, &image_cache, &arch)?;
-
-            let _ = progress_tx.send(ProgressUpdate::VmComplete).await;
-        }
+        Ok(downloaded)
+    }
 
-        let _ = progress_tx.send(ProgressUpdate::BootingVms).await;
+    async fn resume_initialization(
+        run_dir: PathBuf,
+        agent_binary_x86_64: Vec<u8>,
+        agent_binary_aarch64: Vec<u8>,
+        progress_tx: mpsc::Sender<ProgressUpdate>,
+    ) -> Result<ScenarioRunner, VmError> {
+        let _ = progress_tx
+            .send(ProgressUpdate::BootingVms { expected: None })
+            .await;
 
-        runner.start_vms()?;
+        let mut runner =
+            ScenarioRunner::resume(&run_dir, agent_binary_x86_64, agent_binary_aarch64).await?;
         runner.start_action_recording()?;
-
+        runner.start_event_tracking().await;
         runner.wait_for_agents().await?;
-        runner.wait_for_boot_probes().await?;
-
-        // Create a snapshot for fast resets.
-        runner.save_checkpoint("init").await?;
-
-        runner.state = ScenarioState::Running;
 
         let _ = progress_tx.send(ProgressUpdate::Ready).await;
 
@@ -679,6 +2277,10 @@ impl App {
     }
 
     fn handle_progress_update(&mut self, update: ProgressUpdate) {
+        // Left over from the failed attempt.
+        if self.phase == AppPhase::Failed {
+            return;
+        }
         let now = Instant::now();
 
         match update {
@@ -691,6 +2293,7 @@ impl App {
                 self.download_total = total;
                 self.download_index = index;
                 self.download_progress = 0.0;
+                self.download_retry = None;
                 self.stages.init.end_if_needed(now);
                 self.stages.images.start_if_needed(now);
                 self.phase = AppPhase::DownloadingImages;
@@ -698,8 +2301,16 @@ impl App {
             ProgressUpdate::DownloadProgress { progress } => {
                 self.download_progress = progress.clamp(0.0, 1.0);
             }
+            ProgressUpdate::DownloadRetry {
+                attempt,
+                max_attempts,
+                ..
+            } => {
+                self.download_retry = Some((attempt, max_attempts));
+            }
             ProgressUpdate::DownloadComplete => {
                 self.download_progress = 1.0;
+                self.download_retry = None;
             }
             ProgressUpdate::VmStep { step } => {
                 self.vm_progress_step = Some(step);
@@ -719,12 +2330,25 @@ impl App {
                 self.stages.vms.start_if_needed(now);
                 self.phase = AppPhase::CreatingVms;
             }
-            ProgressUpdate::BootingVms => {
+            ProgressUpdate::BootingVms { expected } => {
                 self.stages.vms.end_if_needed(now);
                 self.stages.boot.start_if_needed(now);
                 self.phase = AppPhase::BootingVms;
                 self.vm_progress_name = None;
                 self.vm_progress_step = None;
+                self.boot_expected = expected;
+            }
+            ProgressUpdate::BootStage { vm, stage } => {
+                self.boot_stages.entry(vm).or_default().insert(stage);
+            }
+            ProgressUpdate::VmStarted { name } => {
+                self.vms_started.insert(name);
+            }
+            ProgressUpdate::AgentReady { name } => {
+                self.agents_ready.insert(name);
+            }
+            ProgressUpdate::BootProbes { passing, total } => {
+                self.boot_probes = Some((passing, total));
             }
             ProgressUpdate::Ready => {
                 self.stages.boot.end_if_needed(now);
@@ -744,16 +2368,76 @@ impl App {
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     ) -> Result<(), UiError> {
+        let was_running = matches!(self.phase, AppPhase::Running | AppPhase::Completed);
         self.phase = AppPhase::ShuttingDown;
         terminal.draw(|f| self.draw(f))?;
 
         if let Some(mut runner) = self.runner.take() {
             let run_dir = runner.work_dir.clone();
 
+            // Teardown probes and runs kept running need the VMs back.
+            if self.flags.idle_suspended
+                && self.exit_action != ExitAction::KeepPaused
+                && let Err(e) = runner.resume_vms().await
+            {
+                warn!("Failed to resume idle VMs: {}", e);
+            }
+
+            if was_running && self.exit_action != ExitAction::Destroy {
+                if let Err(e) = runner.save_progress(self.stages.elapsed_secs(Instant::now())) {
+                    warn!("Failed to save run progress: {}", e);
+                }
+                if self.exit_action == ExitAction::KeepPaused
+                    && let Err(e) = runner.pause_vms().await
+                {
+                    warn!("Failed to pause VMs: {}", e);
+                }
+                if let Err(e) = runner.detach() {
+                    warn!("Failed to record run detach: {}", e);
+                }
+                self.kept_run = Some(runner.run_id());
+                restore_terminal(terminal, self.flags.alt_screen.enabled())?;
+                return Ok(());
+            }
+
+            if was_running {
+                let teardown = runner.check_teardown_probes().await.unwrap_or_else(|e| {
+                    warn!("Failed to check teardown probes: {}", e);
+                    Vec::new()
+                });
+                let sudo = runner.sudo_usage().await;
+                let changed_files = runner.file_changes().await;
+                let resources = runner.resource_usage(self.stages.elapsed_secs(Instant::now()));
+                if let Err(e) = runner.save_report(&resources, &sudo, &changed_files) {
+                    warn!("Failed to save run report: {}", e);
+                }
+                self.final_report = Some(FinalReport {
+                    scenario_name: self.scenario.name.clone(),
+                    run_name: runner.run_id(),
+                    learner: runner.learner().map(ToString::to_string),
+                    seed: runner.seed(),
+                    objectives_passed: runner.passing_probe_count(),
+                    objectives_total: runner.total_probe_count(),
+                    teardown,
+                    sudo,
+                    changed_files,
+                    audit: runner.audit_summary(),
+                    resources,
+                    results_hidden: !runner.results_visible(),
+                });
+            }
+
             if let Err(e) = runner.stop().await {
                 warn!("Failed to stop scenario cleanly: {}", e);
             }
 
+            if self.flags.ttl_expired {
+                match IntarDirs::new().and_then(|dirs| archive_run(&run_dir, &dirs.archive_dir())) {
+                    Ok(archived) => self.archived_run = Some(archived),
+                    Err(e) => warn!("Failed to archive expired run: {}", e),
+                }
+            }
+
             if let Err(e) = runner.cleanup() {
                 warn!(
                     "Failed to delete scenario artifacts at {}: {}",
@@ -779,6 +2463,7 @@ impl App {
             | AppPhase::CreatingVms
             | AppPhase::BootingVms => self.draw_briefing(f, area),
             AppPhase::Running => self.draw_hud(f, area),
+            AppPhase::Failed => self.draw_failed(f, area),
         }
 
         self.draw_overlays(f, area);
@@ -808,6 +2493,19 @@ impl App {
         let boot_elapsed = self.boot_elapsed(now);
         let run_elapsed = self.run_elapsed(now);
         let action_lines = self.action_lines_for_display();
+        let session = self.session_screen();
+        let terminal = TerminalTab {
+            vm: self
+                .terminal
+                .as_ref()
+                .map(TerminalPane::vm)
+                .or(self.terminal_vm()),
+            screen: self.terminal.as_ref().map(TerminalPane::screen),
+            status: self.terminal_error.clone().or_else(|| {
+                let status = self.terminal.as_ref()?.exit_status()?;
+                Some(format!("ssh exited ({status}); Enter reconnects"))
+            }),
+        };
 
         let screen = ScenarioTreeScreen {
             scenario_name: &self.scenario.name,
@@ -818,10 +2516,20 @@ impl App {
             run_elapsed,
             vms: &vms,
             action_lines: &action_lines,
+            session: session.as_ref().map(|(vm, screen)| (vm.as_str(), screen)),
+            terminal,
+            debug_log: &self.debug_log,
+            probe_trace: self.author.then(|| ProbeTraceTab {
+                probes: self.traced_probes(),
+                selected: self.traced_probe,
+                trace: self.runner.as_ref().and_then(ScenarioRunner::probe_trace),
+            }),
+            log_path: self.log_path.as_deref(),
             scroll: self.scroll,
             theme: &self.theme,
             tick: self.tick,
             active_tab: self.active_tab,
+            exam: self.scenario.is_exam(),
         };
         f.render_widget(screen, area);
     }
@@ -839,6 +2547,9 @@ impl App {
             run_name,
             phase: self.phase_label(),
             boot_elapsed,
+            boot_progress: self.boot_progress(now),
+            download_retry: self.download_retry,
+            step: self.vm_progress_step.as_deref(),
             run_elapsed,
             vms: &vms,
             theme: &self.theme,
@@ -847,10 +2558,33 @@ impl App {
         f.render_widget(screen, area);
     }
 
+    fn draw_failed(&self, f: &mut ratatui::Frame, area: Rect) {
+        let error = self
+            .failure
+            .as_ref()
+            .map_or_else(String::new, ToString::to_string);
+        let screen = FailedScreen {
+            scenario_name: &self.scenario.name,
+            run_name: self.run_name(),
+            error: &error,
+            corrupt_image: matches!(self.failure, Some(VmError::CorruptImage(_))),
+            log: &self.failure_log,
+            show_full_log: self.flags.show_failure_log,
+            log_path: self.log_path.as_deref(),
+            scroll: self.scroll,
+            theme: &self.theme,
+        };
+        f.render_widget(screen, area);
+    }
+
+    /// Shown from the start: the run directory is claimed before initialization.
     fn run_name(&self) -> Option<&str> {
         self.runner
             .as_ref()
-            .and_then(|runner| runner.work_dir.file_name())
+            .map(|runner| runner.work_dir.as_path())
+            .or(self.run_dir.as_deref())
+            .or(self.resume_dir.as_deref())
+            .and_then(Path::file_name)
             .and_then(|name| name.to_str())
     }
 
@@ -863,25 +2597,53 @@ impl App {
 
         let credits = self.action_lines_for_display_with_start(run_start);
 
-        let run_name = self
-            .runner
-            .as_ref()
-            .and_then(|runner| runner.work_dir.file_name())
-            .and_then(|name| name.to_str());
+        let run_name = self.run_name();
 
         let screen = CompletedScreen {
             scenario_name: &self.scenario.name,
             run_name,
             solve_duration,
+            audit: self.audit_summary,
+            objectives: self.objective_timings(),
             credits,
             credits_elapsed,
             theme: &self.theme,
+            exam: self.scenario.is_exam(),
         };
         f.render_widget(screen, area);
     }
 
+    /// Every scenario objective in VM order with its timing from the runner's probe history.
+    fn objective_timings(&self) -> Vec<ObjectiveTiming<'_>> {
+        let history = self.runner.as_ref().map(|runner| &runner.probe_history);
+        self.scenario
+            .vms
+            .iter()
+            .flat_map(|vm| {
+                vm.probes.iter().filter_map(move |probe| {
+                    let def = self.scenario.probes.get(probe)?;
+                    if def.phase != intar_core::ProbePhase::Scenario {
+                        return None;
+                    }
+                    let record = history.and_then(|history| history.get(&vm.name, probe));
+                    Some(ObjectiveTiming {
+                        vm: &vm.name,
+                        probe,
+                        description: def.description.as_deref(),
+                        first_passed: record
+                            .and_then(|record| record.first_passed_secs)
+                            .map(Duration::from_secs),
+                        failed_checks: record.map_or(0, |record| record.failed_checks),
+                    })
+                })
+            })
+            .collect()
+    }
+
     fn vm_tree_nodes(&self) -> Vec<VmTreeNode<'_>> {
         let runner = self.runner.as_ref();
+        let results_visible = self.results_visible();
+        let lan_stats = runner.and_then(ScenarioRunner::lan_stats);
 
         self.scenario
             .vms
@@ -889,13 +2651,20 @@ impl App {
             .map(|vm_def| {
                 let vm_results = runner.and_then(|r| r.probe_results.get(&vm_def.name));
                 let vm_state = runner.and_then(|r| r.vms.get(&vm_def.name));
-                let status = vm_state.map_or(VmStatus::Unknown, |vm| match vm.state {
-                    intar_vm::VmState::Starting => VmStatus::Starting,
-                    intar_vm::VmState::Booting => VmStatus::Booting,
-                    intar_vm::VmState::CloudInit => VmStatus::CloudInit,
-                    intar_vm::VmState::Ready => VmStatus::Ready,
-                    intar_vm::VmState::Error => VmStatus::Error,
-                });
+                let status = vm_state.map_or_else(
+                    || self.briefing_vm_status(&vm_def.name),
+                    |vm| match vm.state {
+                        intar_vm::VmState::Waiting => VmStatus::Queued,
+                        intar_vm::VmState::Starting => VmStatus::Starting,
+                        intar_vm::VmState::Booting => VmStatus::Booting,
+                        intar_vm::VmState::CloudInit => VmStatus::CloudInit,
+                        intar_vm::VmState::Ready => VmStatus::Ready,
+                        intar_vm::VmState::Paused => VmStatus::Paused,
+                        intar_vm::VmState::Stopped => VmStatus::Stopped,
+                        intar_vm::VmState::Panicked => VmStatus::Panicked,
+                        intar_vm::VmState::Error => VmStatus::Error,
+                    },
+                );
 
                 let mut boot_passing = 0usize;
                 let mut boot_total = 0usize;
@@ -916,22 +2685,41 @@ impl App {
                                 boot_passing += 1;
                             }
                         }
+                        intar_core::ProbePhase::Scenario if !results_visible => {
+                            scenario_probes.push(VmTreeProbe {
+                                name: Cow::Owned(format!(
+                                    "objective {}",
+                                    scenario_probes.len() + 1
+                                )),
+                                status: ProbeStatus::Pending,
+                                description: None,
+                                severity: None,
+                                detail: None,
+                            });
+                        }
+                        // Teardown probes only run at shutdown and appear in the final report.
+                        intar_core::ProbePhase::Teardown => {}
                         intar_core::ProbePhase::Scenario => {
-                            let status = vm_results.and_then(|m| m.get(probe_name)).map_or(
-                                ProbeStatus::Pending,
-                                |r| {
-                                    if r.passed {
-                                        ProbeStatus::Passed
-                                    } else {
-                                        ProbeStatus::Failed
-                                    }
-                                },
-                            );
+                            let result = vm_results.and_then(|m| m.get(probe_name));
+                            let status = result.map_or(ProbeStatus::Pending, |r| {
+                                if r.passed {
+                                    ProbeStatus::Passed
+                                } else {
+                                    ProbeStatus::Failed
+                                }
+                            });
+                            let detail = result.filter(|r| !r.passed).and_then(|r| {
+                                let observed = r.details.observed.as_ref()?;
+                                let expected = r.details.expected.as_ref()?;
+                                Some(format!("{observed} ({} {expected})", tr("expected")))
+                            });
 
                             scenario_probes.push(VmTreeProbe {
                                 name: Cow::Borrowed(probe_name.as_str()),
                                 status,
                                 description: def.description.as_deref().map(Cow::Borrowed),
+                                severity: result.and_then(ProbeResult::severity),
+                                detail,
                             });
                         }
                     }
@@ -944,6 +2732,15 @@ impl App {
                     memory: vm_def.memory,
                     disk: vm_def.disk,
                     ssh_port: vm_state.map(|vm| vm.ssh_port),
+                    host_cpu: vm_state.and_then(|vm| vm.host_cpu),
+                    lan: lan_stats
+                        .as_ref()
+                        .and_then(|stats| stats.port(&vm_def.name))
+                        .cloned(),
+                    lan_capture: lan_stats
+                        .as_ref()
+                        .is_some_and(|stats| stats.capture.is_some()),
+                    boot_checklist: self.boot_checklist(&vm_def.name),
                     boot_passing,
                     boot_total,
                     scenario_probes,
@@ -952,15 +2749,46 @@ impl App {
             .collect()
     }
 
+    /// Status of `vm` while it boots and the runner is busy: queued until it starts, then
+    /// booting until its agent answers. Runs that do not report VM starts, like remote ones,
+    /// show it as unknown until then.
+    fn briefing_vm_status(&self, vm: &str) -> VmStatus {
+        if self.agents_ready.contains(vm) {
+            VmStatus::Ready
+        } else if self.vms_started.contains(vm) {
+            VmStatus::Booting
+        } else if self.phase == AppPhase::BootingVms && !self.vms_started.is_empty() {
+            VmStatus::Queued
+        } else {
+            VmStatus::Unknown
+        }
+    }
+
+    /// Boot milestones of `vm` for the briefing screen, as `(label, reached)`.
+    fn boot_checklist(&self, vm: &str) -> Vec<(&'static str, bool)> {
+        let stages = self.boot_stages.get(vm);
+        BootStage::ALL
+            .iter()
+            .map(|stage| {
+                (
+                    stage.as_str(),
+                    stages.is_some_and(|reached| reached.contains(stage)),
+                )
+            })
+            .chain(std::iter::once(("agent", self.agents_ready.contains(vm))))
+            .collect()
+    }
+
     fn phase_label(&self) -> &'static str {
         match self.phase {
-            AppPhase::Initializing => "INIT",
-            AppPhase::DownloadingImages => "IMAGES",
-            AppPhase::CreatingVms => "VMS",
-            AppPhase::BootingVms => "BOOT",
-            AppPhase::Running => "RUN",
-            AppPhase::Completed => "DONE",
-            AppPhase::ShuttingDown => "SHUTDOWN",
+            AppPhase::Initializing => tr("INIT"),
+            AppPhase::DownloadingImages => tr("IMAGES"),
+            AppPhase::CreatingVms => tr("VMS"),
+            AppPhase::BootingVms => tr("BOOT"),
+            AppPhase::Running => tr("RUN"),
+            AppPhase::Completed => tr("DONE"),
+            AppPhase::ShuttingDown => tr("SHUTDOWN"),
+            AppPhase::Failed => tr("FAILED"),
         }
     }
 
@@ -978,6 +2806,40 @@ impl App {
         self.stages.boot.elapsed(now)
     }
 
+    /// Boot progress from agent and boot probe events, projected against the calibrated boot
+    /// duration. `None` outside the boot phase or when the boot was not calibrated.
+    fn boot_progress(&self, now: Instant) -> Option<BootProgress> {
+        if !matches!(self.phase, AppPhase::BootingVms) {
+            return None;
+        }
+        let expected = self.boot_expected?;
+        let elapsed = self.boot_elapsed(now)?;
+
+        // Each VM passes every cloud-init stage and then its agent comes up.
+        let milestones =
+            self.boot_stages.values().map(HashSet::len).sum::<usize>() + self.agents_ready.len();
+        let vm_progress = fraction(
+            milestones,
+            self.scenario.vms.len() * (BootStage::ALL.len() + 1),
+        );
+        let probes = self
+            .boot_probes
+            .map_or(0.0, |(passing, total)| fraction(passing, total));
+        let done = 0.8 * vm_progress + 0.2 * probes;
+
+        let projected = if done > 0.0 {
+            expected.max(elapsed.div_f64(done))
+        } else {
+            expected
+        };
+        Some(BootProgress {
+            fraction: done
+                .max(elapsed.as_secs_f64() / projected.as_secs_f64())
+                .min(0.99),
+            eta: projected.checked_sub(elapsed),
+        })
+    }
+
     fn run_elapsed(&self, now: Instant) -> Option<Duration> {
         self.stages.run.elapsed(now)
     }
@@ -992,9 +2854,22 @@ impl App {
         for ev in &self.action_lines {
             let rel = ev.received_at.saturating_duration_since(run_start);
             let ts = format_mm_ss(rel);
-            let (prefix, line_style) = match ev.kind {
-                ActionLineKind::Input => ("$ ", Style::default().fg(self.theme.primary)),
-                ActionLineKind::Output => ("  ", Style::default().fg(self.theme.fg)),
+            let (prefix, line_style) = match (&ev.kind, &ev.context) {
+                (ActionLineKind::Input, Some(context)) => (
+                    format!(
+                        "{}:{}{} ",
+                        context.user,
+                        context.cwd,
+                        if context.is_root() { "#" } else { "$" }
+                    ),
+                    Style::default().fg(self.theme.primary),
+                ),
+                (ActionLineKind::Input, None) => {
+                    ("$ ".to_string(), Style::default().fg(self.theme.primary))
+                }
+                (ActionLineKind::Output, _) => {
+                    ("  ".to_string(), Style::default().fg(self.theme.fg))
+                }
             };
 
             lines.push(Line::from(vec![
@@ -1010,10 +2885,125 @@ impl App {
     }
 
     fn draw_overlays(&self, f: &mut ratatui::Frame, area: Rect) {
+        if self.flags.show_confirm_quit {
+            f.render_widget(QuitDialog { theme: &self.theme }, area);
+            return;
+        }
+
+        if let Some(free) = self.flags.low_space {
+            let dialog = LowSpaceDialog {
+                available_mb: free / (1024 * 1024),
+                resume_mb: RESUME_SPACE_BYTES / (1024 * 1024),
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if self.flags.ttl_warning
+            && let Some(left) = self.runner.as_ref().and_then(ScenarioRunner::time_left)
+        {
+            let dialog = ExpiryDialog {
+                minutes_left: left.as_secs().div_ceil(60),
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(vm) = &self.flags.crashed_vm
+            && let Some(reason) = self.runner.as_ref().and_then(|r| r.crash(vm))
+        {
+            let dialog = CrashDialog {
+                vm,
+                reason,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(vm) = &self.flags.guest_panic {
+            let dialog = GuestPanicDialog {
+                vm,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if self.flags.idle_suspended
+            && let Some(after) = self.idle_suspend
+        {
+            let dialog = IdleDialog {
+                idle_minutes: after.as_secs() / 60,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(hook) = &self.flags.running_hook {
+            let dialog = HookDialog {
+                hook,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(progress) = &self.flags.checkpoint {
+            let dialog = CheckpointDialog {
+                progress,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
         if self.flags.show_confirm_reset {
             let dialog = ConfirmDialog {
-                title: "Restart Scenario",
-                message: "Restart scenario from the initial state?\nAll progress will be lost.",
+                title: tr("Restart Scenario"),
+                message: tr("Restart scenario from the initial state?\nAll progress will be lost."),
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(input) = self.flags.unlock_input.as_deref() {
+            let dialog = UnlockDialog {
+                input,
+                failed: self.flags.unlock_failed,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(prompt) = self.flags.question.as_ref()
+            && let Some(question) = self
+                .scenario
+                .questions
+                .iter()
+                .find(|q| q.name == prompt.name)
+        {
+            let dialog = QuestionDialog {
+                prompt: &question.prompt,
+                choices: &question.choices,
+                selected: prompt.selected,
+                input: &prompt.input,
+                feedback: prompt.feedback,
+                theme: &self.theme,
+            };
+            f.render_widget(dialog, area);
+            return;
+        }
+
+        if let Some(input) = self.flags.flag_input.as_deref() {
+            let dialog = FlagDialog {
+                input,
+                rejected: self.flags.flag_rejected,
                 theme: &self.theme,
             };
             f.render_widget(dialog, area);
@@ -1031,6 +3021,7 @@ impl App {
             let help = HelpOverlay {
                 theme: &self.theme,
                 mode,
+                exam: self.scenario.is_exam(),
             };
             f.render_widget(help, area);
         }
@@ -1078,9 +3069,35 @@ fn detect_arch() -> String {
     return std::env::consts::ARCH.to_string();
 }
 
+/// `part / whole` as a fraction in `0.0..=1.0`.
+fn fraction(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 1.0;
+    }
+    let part = f64::from(u32::try_from(part.min(whole)).unwrap_or(u32::MAX));
+    let whole = f64::from(u32::try_from(whole).unwrap_or(u32::MAX));
+    part / whole
+}
+
 fn format_mm_ss(d: Duration) -> String {
     let secs = d.as_secs();
     let mins = secs / 60;
     let secs = secs % 60;
     format!("{mins:02}:{secs:02}")
 }
+
+/// The complete lines in the last `max_bytes` of the log at `path`, skipping the first `from`
+/// bytes.
+fn read_log_tail(path: &Path, from: u64, max_bytes: u64) -> io::Result<Vec<String>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes).max(from.min(len));
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    // Reading from the middle of the file usually starts mid-line; `from` is a line start.
+    let skip = usize::from(start > 0 && start != from);
+    Ok(text.lines().skip(skip).map(str::to_string).collect())
+}
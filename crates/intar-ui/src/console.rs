@@ -0,0 +1,51 @@
+use crate::UiError;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use intar_vm::{HostStream, console_input};
+use std::io;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Relay this terminal to a VM's serial console (`intar console`) until `Ctrl+]` is typed or
+/// the VM closes the console. The terminal is in raw mode meanwhile, so keys like `Ctrl+C`
+/// reach the guest.
+///
+/// # Errors
+/// Returns `UiError::Io` if the terminal or the console connection fails.
+pub async fn attach_console(stream: HostStream) -> Result<(), UiError> {
+    enable_raw_mode()?;
+    let result = relay(stream).await;
+    disable_raw_mode()?;
+    result.map_err(UiError::from)
+}
+
+async fn relay(stream: HostStream) -> io::Result<()> {
+    let (mut from_vm, mut to_vm) = tokio::io::split(stream);
+    let mut stdin = tokio::io::stdin();
+    let mut stdout = tokio::io::stdout();
+    let mut input = [0u8; 1024];
+    let mut output = [0u8; 4096];
+    // The getty printed its prompt before we connected; a carriage return brings it back.
+    to_vm.write_all(b"\r").await?;
+    loop {
+        tokio::select! {
+            read = stdin.read(&mut input) => {
+                let read = read?;
+                if read == 0 {
+                    return Ok(());
+                }
+                let (data, done) = console_input(&input[..read]);
+                to_vm.write_all(data).await?;
+                if done {
+                    return Ok(());
+                }
+            }
+            read = from_vm.read(&mut output) => {
+                let read = read?;
+                if read == 0 {
+                    return Ok(());
+                }
+                stdout.write_all(&output[..read]).await?;
+                stdout.flush().await?;
+            }
+        }
+    }
+}
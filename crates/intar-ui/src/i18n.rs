@@ -0,0 +1,60 @@
+use intar_core::MessageCatalog;
+use intar_vm::{IntarConfig, IntarDirs, VmError, load_message_catalog, resolve_language};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+static CATALOG: OnceLock<MessageCatalog> = OnceLock::new();
+
+/// Load the message catalog for the configured language and return that language, if it is
+/// not English. Without a catalog, or when it cannot be loaded, the interface stays in
+/// English. Later calls keep the first catalog.
+pub(crate) fn init_messages() -> Option<String> {
+    let (language, catalog) = match load_catalog() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            warn!("Failed to load interface messages: {}", e);
+            return None;
+        }
+    };
+    if let Some(catalog) = catalog {
+        info!(
+            "Using {} interface messages for language {}",
+            catalog.len(),
+            catalog.language()
+        );
+        let _ = CATALOG.set(catalog);
+    }
+    language
+}
+
+type LoadedCatalog = (Option<String>, Option<MessageCatalog>);
+
+fn load_catalog() -> Result<LoadedCatalog, VmError> {
+    let dirs = IntarDirs::new()?;
+    let config = IntarConfig::load(&dirs)?;
+    let Some(language) = resolve_language(&config) else {
+        return Ok((None, None));
+    };
+    let catalog = load_message_catalog(&dirs.locales_dir(), &language)?;
+    if catalog.is_none() {
+        info!(
+            "No message catalog for language {} in {}",
+            language,
+            dirs.locales_dir().display()
+        );
+    }
+    Ok((Some(language), catalog))
+}
+
+/// Fixed interface text in the current language.
+pub(crate) fn tr(text: &'static str) -> &'static str {
+    CATALOG.get().map_or(text, |catalog| catalog.get(text))
+}
+
+/// A message built at runtime, such as a probe result, in the current language.
+pub(crate) fn tr_message(message: &str) -> Cow<'_, str> {
+    CATALOG
+        .get()
+        .map_or(Cow::Borrowed(message), |catalog| catalog.translate(message))
+}
@@ -1,6 +1,12 @@
 mod app;
 mod colors;
+mod console;
+mod i18n;
+mod report;
+mod terminal_pane;
 mod widgets;
 
 pub use app::{App, AppPhase, MainTab, ProgressUpdate, UiError};
 pub use colors::{ColorLevel, Theme, ThemeMode, ThemeSettings};
+pub use console::attach_console;
+pub use report::FinalReport;
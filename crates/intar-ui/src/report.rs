@@ -0,0 +1,94 @@
+use crate::i18n::{tr, tr_message};
+use intar_probes::{FileChanges, ProbeResult, Severity, SudoInvocation};
+use intar_vm::{AuditSummary, ResourceUsage};
+use std::fmt;
+
+/// Summary printed to the terminal after the TUI exits.
+#[derive(Debug, Clone)]
+pub struct FinalReport {
+    pub scenario_name: String,
+    pub run_name: String,
+    /// The `intar login` profile the run was started under.
+    pub learner: Option<String>,
+    /// Seed to pass to `intar start --seed` to reproduce the run.
+    pub seed: u64,
+    pub objectives_passed: usize,
+    pub objectives_total: usize,
+    /// `teardown` probe results as `(vm, result)`, checked while shutting down.
+    pub teardown: Vec<(String, ProbeResult)>,
+    /// Commands run through sudo as `(vm, invocation)`.
+    pub sudo: Vec<(String, SudoInvocation)>,
+    /// Tracked files that changed since the last checkpoint, as `(vm, changes)`.
+    pub changed_files: Vec<(String, FileChanges)>,
+    /// Host-initiated mutations from the run's audit log, shown even when results are hidden.
+    pub audit: AuditSummary,
+    /// What the run cost the host, shown even when results are hidden.
+    pub resources: ResourceUsage,
+    /// Exam runs that were never unlocked only report that the run ended.
+    pub results_hidden: bool,
+}
+
+impl fmt::Display for FinalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} ({})", self.scenario_name, self.run_name)?;
+        if let Some(learner) = &self.learner {
+            writeln!(f, "  learner: {learner}")?;
+        }
+        if self.results_hidden {
+            writeln!(f, "  results hidden (exam mode)")?;
+            writeln!(f, "  audit: {}", self.audit)?;
+            return write!(f, "{}", self.resources);
+        }
+        writeln!(f, "  seed: {}", self.seed)?;
+
+        writeln!(
+            f,
+            "  objectives: {}/{} passed",
+            self.objectives_passed, self.objectives_total
+        )?;
+
+        if !self.teardown.is_empty() {
+            writeln!(f, "  teardown checks:")?;
+        }
+        for (vm, result) in &self.teardown {
+            let status = match result.severity() {
+                None => tr("PASS"),
+                Some(Severity::Info) => tr("INFO"),
+                Some(Severity::Warning) => tr("WARN"),
+                Some(Severity::Error) => tr("FAIL"),
+            };
+            write!(f, "    [{status}] {vm}/{}", result.id)?;
+            if result.message.is_empty() {
+                writeln!(f)?;
+            } else {
+                writeln!(f, ": {}", tr_message(&result.message))?;
+            }
+            if !result.passed
+                && let (Some(observed), Some(expected)) =
+                    (&result.details.observed, &result.details.expected)
+            {
+                writeln!(f, "      observed: {observed}, expected: {expected}")?;
+            }
+        }
+
+        if self.sudo.is_empty() {
+            writeln!(f, "  sudo: not used")?;
+        } else {
+            writeln!(f, "  sudo: {} command(s)", self.sudo.len())?;
+        }
+        for (vm, invocation) in &self.sudo {
+            writeln!(f, "    {vm}: {invocation}")?;
+        }
+
+        if !self.changed_files.is_empty() {
+            writeln!(f, "  changed files:")?;
+        }
+        for (vm, changes) in &self.changed_files {
+            for line in changes.summary() {
+                writeln!(f, "    {vm}: {line}")?;
+            }
+        }
+        writeln!(f, "  audit: {}", self.audit)?;
+        write!(f, "{}", self.resources)
+    }
+}
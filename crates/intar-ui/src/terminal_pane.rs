@@ -0,0 +1,194 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use intar_vm::SessionScreen;
+use std::fs::File;
+use std::io::{self, Write};
+use std::process::{Child, ExitStatus};
+use std::sync::mpsc;
+
+/// An `ssh` session to a VM on a pseudo-terminal, drawn in the terminal tab. A reader thread
+/// passes the session's output to the event loop, which feeds it into a [`SessionScreen`] each
+/// tick and writes the keys typed in the tab back to the session.
+pub(crate) struct TerminalPane {
+    vm: String,
+    screen: SessionScreen,
+    master: File,
+    output: mpsc::Receiver<Vec<u8>>,
+    child: Child,
+    exit: Option<ExitStatus>,
+}
+
+impl TerminalPane {
+    /// Start `ssh` with `ssh_args` on a `width` x `height` pseudo-terminal.
+    #[cfg(unix)]
+    pub(crate) fn open(vm: &str, ssh_args: &[String], width: u16, height: u16) -> io::Result<Self> {
+        use nix::pty::{Winsize, openpty};
+        use std::io::Read;
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        let size = Winsize {
+            ws_row: height,
+            ws_col: width,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = openpty(Some(&size), None).map_err(io::Error::from)?;
+        let child = Command::new("ssh")
+            .arg("-tt")
+            .args(ssh_args)
+            .env("TERM", "xterm")
+            .stdin(Stdio::from(pty.slave.try_clone()?))
+            .stdout(Stdio::from(pty.slave.try_clone()?))
+            .stderr(Stdio::from(pty.slave))
+            // Keep signals from the TUI's own terminal away from ssh.
+            .process_group(0)
+            .spawn()?;
+
+        let master = File::from(pty.master);
+        let mut reader = master.try_clone()?;
+        let (tx, output) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            // Reading fails with EIO once ssh has exited.
+            while let Ok(n) = reader.read(&mut buf) {
+                if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            vm: vm.to_string(),
+            screen: SessionScreen::new(width, height),
+            master,
+            output,
+            child,
+            exit: None,
+        })
+    }
+
+    /// Pseudo-terminals need a Unix host.
+    #[cfg(not(unix))]
+    pub(crate) fn open(
+        _vm: &str,
+        _ssh_args: &[String],
+        _width: u16,
+        _height: u16,
+    ) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the terminal tab needs a Unix host; use intar ssh in another window",
+        ))
+    }
+
+    pub(crate) fn vm(&self) -> &str {
+        &self.vm
+    }
+
+    pub(crate) fn screen(&self) -> &SessionScreen {
+        &self.screen
+    }
+
+    /// How ssh exited, once it has.
+    pub(crate) fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit
+    }
+
+    /// Apply output that arrived since the last call and notice when ssh exits.
+    pub(crate) fn poll(&mut self) {
+        while let Ok(chunk) = self.output.try_recv() {
+            self.screen.feed(&chunk);
+        }
+        if self.exit.is_none()
+            && let Ok(Some(status)) = self.child.try_wait()
+        {
+            self.exit = Some(status);
+        }
+    }
+
+    /// Type `key` into the session.
+    ///
+    /// # Errors
+    /// Returns `io::Error` if the session has closed.
+    pub(crate) fn send_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let bytes = key_bytes(key);
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        self.master.write_all(&bytes)?;
+        self.master.flush()
+    }
+}
+
+impl Drop for TerminalPane {
+    fn drop(&mut self) {
+        if self.exit.is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// The bytes an xterm sends for `key`.
+fn key_bytes(key: KeyEvent) -> Vec<u8> {
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let mut bytes = Vec::new();
+    if alt {
+        bytes.push(0x1b);
+    }
+    match key.code {
+        KeyCode::Char(c) if ctrl => match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => bytes.extend(u8::try_from(c).map(|b| b & 0x1f)),
+            ' ' | '@' => bytes.push(0x00),
+            '[' => bytes.push(0x1b),
+            '\\' => bytes.push(0x1c),
+            ']' => bytes.push(0x1d),
+            _ => {}
+        },
+        KeyCode::Char(c) => {
+            let mut utf8 = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        }
+        KeyCode::Enter => bytes.push(b'\r'),
+        KeyCode::Backspace => bytes.push(0x7f),
+        KeyCode::Tab => bytes.push(b'\t'),
+        KeyCode::Esc => bytes.push(0x1b),
+        code => {
+            let Some(sequence) = escape_sequence(code) else {
+                return Vec::new();
+            };
+            bytes.extend_from_slice(sequence.as_bytes());
+        }
+    }
+    bytes
+}
+
+fn escape_sequence(code: KeyCode) -> Option<&'static str> {
+    Some(match code {
+        KeyCode::BackTab => "\x1b[Z",
+        KeyCode::Up => "\x1b[A",
+        KeyCode::Down => "\x1b[B",
+        KeyCode::Right => "\x1b[C",
+        KeyCode::Left => "\x1b[D",
+        KeyCode::Home => "\x1b[H",
+        KeyCode::End => "\x1b[F",
+        KeyCode::Insert => "\x1b[2~",
+        KeyCode::Delete => "\x1b[3~",
+        KeyCode::PageUp => "\x1b[5~",
+        KeyCode::PageDown => "\x1b[6~",
+        KeyCode::F(1) => "\x1bOP",
+        KeyCode::F(2) => "\x1bOQ",
+        KeyCode::F(3) => "\x1bOR",
+        KeyCode::F(4) => "\x1bOS",
+        KeyCode::F(5) => "\x1b[15~",
+        KeyCode::F(6) => "\x1b[17~",
+        KeyCode::F(7) => "\x1b[18~",
+        KeyCode::F(8) => "\x1b[19~",
+        KeyCode::F(9) => "\x1b[20~",
+        KeyCode::F(10) => "\x1b[21~",
+        KeyCode::F(11) => "\x1b[23~",
+        KeyCode::F(12) => "\x1b[24~",
+        _ => return None,
+    })
+}
@@ -1,5 +1,11 @@
 use crate::app::MainTab;
 use crate::colors::Theme;
+use crate::i18n::tr;
+use intar_probes::Severity;
+use intar_vm::{
+    AnswerOutcome, AuditSummary, CheckpointProgress, LanPortStats, ProbeEvaluation, ProbeTrace,
+    SessionScreen,
+};
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Constraint, Layout, Rect},
@@ -7,10 +13,16 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph, Widget, Wrap},
 };
-use std::{borrow::Cow, time::Duration};
+use std::{
+    borrow::Cow,
+    path::Path,
+    time::{Duration, Instant},
+};
 
 const SPINNER_FRAMES: [char; 4] = ['◐', '◓', '◑', '◒'];
 const CREDITS_SCROLL_MS_PER_LINE: u128 = 700;
+/// QMP/agent errors pinned above the log tail in the debug tab.
+const DEBUG_RECENT_ERRORS: usize = 5;
 
 #[must_use]
 pub fn spinner_char(tick: usize) -> char {
@@ -26,10 +38,15 @@ pub enum ProbeStatus {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VmStatus {
+    /// Waiting for a later boot wave or for the VMs it `wait_for`s.
+    Queued,
     Starting,
     Booting,
     CloudInit,
     Ready,
+    Paused,
+    Stopped,
+    Panicked,
     Error,
     Unknown,
 }
@@ -38,6 +55,10 @@ pub struct VmTreeProbe<'a> {
     pub name: Cow<'a, str>,
     pub status: ProbeStatus,
     pub description: Option<Cow<'a, str>>,
+    /// How much a failed probe matters; failures below [`Severity::Error`] are shown as such.
+    pub severity: Option<Severity>,
+    /// What a failed probe found against what it expected, e.g. `inactive (expected active)`.
+    pub detail: Option<String>,
 }
 
 pub struct VmTreeNode<'a> {
@@ -47,6 +68,14 @@ pub struct VmTreeNode<'a> {
     pub memory: u32,
     pub disk: u32,
     pub ssh_port: Option<u16>,
+    /// Host CPU used by the VM's QEMU process, in percent of one core.
+    pub host_cpu: Option<f64>,
+    /// Traffic of the VM's port on the shared LAN switch, for runs with one.
+    pub lan: Option<LanPortStats>,
+    /// Whether the shared LAN is being captured to a pcap file.
+    pub lan_capture: bool,
+    /// Guest boot milestones as `(label, reached)`, shown while booting.
+    pub boot_checklist: Vec<(&'static str, bool)>,
     pub boot_passing: usize,
     pub boot_total: usize,
     pub scenario_probes: Vec<VmTreeProbe<'a>>,
@@ -61,10 +90,47 @@ pub struct ScenarioTreeScreen<'a> {
     pub run_elapsed: Option<Duration>,
     pub vms: &'a [VmTreeNode<'a>],
     pub action_lines: &'a [Line<'static>],
+    /// VM and live screen shown in the session tab.
+    pub session: Option<(&'a str, &'a SessionScreen)>,
+    pub terminal: TerminalTab<'a>,
+    /// Tail of intar.log, shown in the debug tab.
+    pub debug_log: &'a [String],
+    /// The probes tab, under `--author`.
+    pub probe_trace: Option<ProbeTraceTab<'a>>,
+    pub log_path: Option<&'a Path>,
     pub scroll: u16,
     pub theme: &'a Theme,
     pub tick: usize,
     pub active_tab: MainTab,
+    pub exam: bool,
+}
+
+/// Columns and rows of the HUD around the terminal tab's session screen.
+pub const TERMINAL_TAB_CHROME: (u16, u16) = (2, 12);
+
+/// What the terminal tab shows: the VM it connects to and its ssh session, once opened.
+pub struct TerminalTab<'a> {
+    pub vm: Option<&'a str>,
+    pub screen: Option<&'a SessionScreen>,
+    /// Why there is no session: ssh exited or could not start.
+    pub status: Option<String>,
+}
+
+/// What the probes tab shows under `--author`.
+pub struct ProbeTraceTab<'a> {
+    /// Every scenario probe as `(vm, probe)`.
+    pub probes: Vec<(&'a str, &'a str)>,
+    /// Index into `probes` of the probe `E` re-evaluates.
+    pub selected: usize,
+    pub trace: Option<&'a ProbeTrace>,
+}
+
+/// How far the VMs are through booting, and the estimated time left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BootProgress {
+    pub fraction: f64,
+    /// `None` once the boot takes longer than projected.
+    pub eta: Option<Duration>,
 }
 
 pub struct BriefingScreen<'a> {
@@ -73,19 +139,37 @@ pub struct BriefingScreen<'a> {
     pub run_name: Option<&'a str>,
     pub phase: &'a str,
     pub boot_elapsed: Option<Duration>,
+    pub boot_progress: Option<BootProgress>,
+    /// Attempt and maximum while an image download is being retried.
+    pub download_retry: Option<(u32, u32)>,
+    /// What setup is doing right now, e.g. a line of an external action's output.
+    pub step: Option<&'a str>,
     pub run_elapsed: Option<Duration>,
     pub vms: &'a [VmTreeNode<'a>],
     pub theme: &'a Theme,
     pub tick: usize,
 }
 
+/// One objective on the completion screen: when it first passed, relative to the run start,
+/// and how many checks failed before that.
+pub struct ObjectiveTiming<'a> {
+    pub vm: &'a str,
+    pub probe: &'a str,
+    pub description: Option<&'a str>,
+    pub first_passed: Option<Duration>,
+    pub failed_checks: u32,
+}
+
 pub struct CompletedScreen<'a> {
     pub scenario_name: &'a str,
     pub run_name: Option<&'a str>,
     pub solve_duration: Duration,
+    pub audit: Option<AuditSummary>,
+    pub objectives: Vec<ObjectiveTiming<'a>>,
     pub credits: Vec<Line<'static>>,
     pub credits_elapsed: Duration,
     pub theme: &'a Theme,
+    pub exam: bool,
 }
 
 impl CompletedScreen<'_> {
@@ -96,7 +180,7 @@ impl CompletedScreen<'_> {
             .border_type(BorderType::Plain)
             .border_style(Style::default().fg(self.theme.success))
             .style(Style::default().bg(self.theme.surface))
-            .title(" EXECUTION SUMMARY ")
+            .title(format!(" {} ", tr("EXECUTION SUMMARY")))
             .title_style(Style::default().fg(self.theme.primary).bold());
 
         let inner = block.inner(area);
@@ -108,7 +192,7 @@ impl CompletedScreen<'_> {
 
         let duration = format_duration(self.solve_duration);
         let run = self.run_name.unwrap_or("—");
-        let lines = vec![
+        let mut lines = vec![
             Line::from(vec![
                 Span::styled("SCENARIO: ", Style::default().fg(self.theme.secondary)),
                 Span::styled(
@@ -122,16 +206,122 @@ impl CompletedScreen<'_> {
                 Span::styled("  |  TIME ", Style::default().fg(self.theme.secondary)),
                 Span::styled(duration, Style::default().fg(self.theme.primary).bold()),
                 Span::styled("  |  STATUS ", Style::default().fg(self.theme.secondary)),
-                Span::styled("COMPLETE", Style::default().fg(self.theme.success).bold()),
+                Span::styled(
+                    tr("COMPLETE"),
+                    Style::default().fg(self.theme.success).bold(),
+                ),
             ]),
         ];
 
+        if let Some(audit) = self.audit {
+            lines.push(self.audit_line(audit));
+        }
+
         Paragraph::new(lines)
             .alignment(Alignment::Center)
             .style(Style::default().bg(self.theme.surface))
             .render(inner, buf);
     }
 
+    fn audit_line(&self, audit: AuditSummary) -> Line<'static> {
+        let (integrity, integrity_color) = if audit.verified {
+            (tr("VERIFIED"), self.theme.success)
+        } else {
+            (tr("TAMPERED"), self.theme.error)
+        };
+
+        Line::from(vec![
+            Span::styled(
+                format!("{} ", tr("RESETS")),
+                Style::default().fg(self.theme.secondary),
+            ),
+            Span::styled(
+                audit.resets.to_string(),
+                Style::default().fg(self.theme.primary).bold(),
+            ),
+            Span::styled("  |  RESTORES ", Style::default().fg(self.theme.secondary)),
+            Span::styled(
+                audit.restores.to_string(),
+                Style::default().fg(self.theme.primary).bold(),
+            ),
+            Span::styled("  |  RESTARTS ", Style::default().fg(self.theme.secondary)),
+            Span::styled(
+                audit.vm_restarts.to_string(),
+                Style::default().fg(self.theme.primary).bold(),
+            ),
+            Span::styled("  |  STEPS ", Style::default().fg(self.theme.secondary)),
+            Span::styled(
+                audit.steps_triggered.to_string(),
+                Style::default().fg(self.theme.primary).bold(),
+            ),
+            Span::styled("  |  QMP ", Style::default().fg(self.theme.secondary)),
+            Span::styled(
+                audit.qmp_commands.to_string(),
+                Style::default().fg(self.theme.primary).bold(),
+            ),
+            Span::styled("  |  AUDIT ", Style::default().fg(self.theme.secondary)),
+            Span::styled(integrity, Style::default().fg(integrity_color).bold()),
+        ])
+    }
+
+    fn render_objectives(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::new(1, 1, 0, 0))
+            .border_type(BorderType::Plain)
+            .border_style(Style::default().fg(self.theme.border))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("OBJECTIVES")))
+            .title_style(Style::default().fg(self.theme.secondary));
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if inner.height == 0 || inner.width == 0 {
+            return;
+        }
+
+        let header = Style::default().fg(self.theme.secondary).bold();
+        let mut lines = vec![Line::from(vec![
+            Span::styled(format!("{:>8}", tr("PASSED")), header),
+            Span::styled(format!("  {:>6}", tr("FAILED")), header),
+            Span::styled(format!("  {}", tr("OBJECTIVE")), header),
+        ])];
+        for objective in &self.objectives {
+            let (time, time_color) = match objective.first_passed {
+                Some(at) => (format_duration(at), self.theme.success),
+                None => ("--:--".to_string(), self.theme.error),
+            };
+            let failed_color = if objective.failed_checks == 0 {
+                self.theme.dim
+            } else {
+                self.theme.warning
+            };
+            let mut spans = vec![
+                Span::styled(format!("{time:>8}"), Style::default().fg(time_color)),
+                Span::styled(
+                    format!("  {:>6}", objective.failed_checks),
+                    Style::default().fg(failed_color),
+                ),
+                Span::styled(
+                    format!("  {}/{}", objective.vm, objective.probe),
+                    Style::default().fg(self.theme.fg),
+                ),
+            ];
+            if let Some(description) = objective.description {
+                spans.push(Span::styled(
+                    format!("  {description}"),
+                    Style::default().fg(self.theme.dim),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        Paragraph::new(lines)
+            .style(Style::default().bg(self.theme.surface))
+            .render(inner, buf);
+    }
+
     fn render_credits(&self, area: Rect, buf: &mut Buffer) {
         let block = Block::default()
             .borders(Borders::ALL)
@@ -139,7 +329,7 @@ impl CompletedScreen<'_> {
             .border_type(BorderType::Plain)
             .border_style(Style::default().fg(self.theme.border))
             .style(Style::default().bg(self.theme.surface))
-            .title(" SYSTEM LOG ")
+            .title(format!(" {} ", tr("SYSTEM LOG")))
             .title_style(Style::default().fg(self.theme.secondary));
 
         let inner = block.inner(area);
@@ -189,12 +379,10 @@ impl CompletedScreen<'_> {
                 .bold()
         };
 
-        let keys = vec![
-            ("?", "Help"),
-            ("R", "Restart"),
-            ("T", "Theme"),
-            ("Q", "Quit"),
-        ];
+        let mut keys = vec![("?", tr("Help")), ("T", tr("Theme")), ("Q", tr("Quit"))];
+        if !self.exam {
+            keys.insert(1, ("R", tr("Restart")));
+        }
 
         let mut spans = Vec::new();
         for (key, desc) in keys {
@@ -223,7 +411,7 @@ impl BriefingScreen<'_> {
             .border_type(BorderType::Thick)
             .border_style(Style::default().fg(self.theme.primary))
             .style(Style::default().bg(self.theme.surface))
-            .title(" MISSION BRIEFING ")
+            .title(format!(" {} ", tr("MISSION BRIEFING")))
             .title_style(Style::default().fg(self.theme.primary).bold());
 
         let inner = block.inner(area);
@@ -236,34 +424,75 @@ impl BriefingScreen<'_> {
         let run_id = self.run_name.unwrap_or("—");
         let boot_timer = format_duration_or_placeholder(self.boot_elapsed);
         let run_timer = format_duration_or_placeholder(self.run_elapsed);
-        let spinner = spinner_char(self.tick);
+        let boot_indicator = self.boot_progress.map_or_else(
+            || spinner_char(self.tick).to_string(),
+            |progress| progress_bar(progress.fraction),
+        );
 
-        let lines = vec![
-            Line::from(vec![
-                Span::styled("OPERATION ", Style::default().fg(self.theme.secondary)),
+        let mut status = vec![
+            Span::styled(
+                format!("{} ", tr("STATUS")),
+                Style::default().fg(self.theme.dim),
+            ),
+            Span::styled(
+                self.phase.to_uppercase(),
+                Style::default().fg(self.theme.warning).bold(),
+            ),
+            Span::raw("  "),
+            Span::styled(
+                format!("{boot_indicator} BOOT {boot_timer}"),
+                Style::default().fg(self.theme.primary),
+            ),
+        ];
+        if let Some((attempt, max_attempts)) = self.download_retry {
+            status.extend([
+                Span::raw("  "),
                 Span::styled(
-                    self.scenario_name.to_uppercase(),
-                    Style::default().fg(self.theme.fg).bold(),
+                    format!("DOWNLOAD RETRY {attempt}/{max_attempts}"),
+                    Style::default().fg(self.theme.warning),
                 ),
-            ]),
-            Line::from(vec![
-                Span::styled("STATUS ", Style::default().fg(self.theme.dim)),
+            ]);
+        }
+        if let Some(progress) = self.boot_progress {
+            status.extend([
+                Span::raw("  "),
                 Span::styled(
-                    self.phase.to_uppercase(),
-                    Style::default().fg(self.theme.warning).bold(),
+                    format!("{} ", tr("ETA")),
+                    Style::default().fg(self.theme.dim),
                 ),
-                Span::raw("  "),
                 Span::styled(
-                    format!("{spinner} BOOT {boot_timer}"),
+                    format_duration_or_placeholder(progress.eta),
                     Style::default().fg(self.theme.primary),
                 ),
-                Span::raw("  "),
-                Span::styled("RUN ", Style::default().fg(self.theme.dim)),
-                Span::styled(run_timer, Style::default().fg(self.theme.primary)),
-                Span::raw("  "),
-                Span::styled("ID ", Style::default().fg(self.theme.dim)),
-                Span::styled(run_id, Style::default().fg(self.theme.info)),
+            ]);
+        }
+        status.extend([
+            Span::raw("  "),
+            Span::styled(
+                format!("{} ", tr("RUN")),
+                Style::default().fg(self.theme.dim),
+            ),
+            Span::styled(run_timer, Style::default().fg(self.theme.primary)),
+            Span::raw("  "),
+            Span::styled(
+                format!("{} ", tr("ID")),
+                Style::default().fg(self.theme.dim),
+            ),
+            Span::styled(run_id, Style::default().fg(self.theme.info)),
+        ]);
+
+        let lines = vec![
+            Line::from(vec![
+                Span::styled(
+                    format!("{} ", tr("OPERATION")),
+                    Style::default().fg(self.theme.secondary),
+                ),
+                Span::styled(
+                    self.scenario_name.to_uppercase(),
+                    Style::default().fg(self.theme.fg).bold(),
+                ),
             ]),
+            Line::from(status),
         ];
 
         Paragraph::new(lines)
@@ -283,7 +512,7 @@ impl BriefingScreen<'_> {
             .border_type(BorderType::Plain)
             .border_style(Style::default().fg(self.theme.border))
             .style(Style::default().bg(self.theme.surface))
-            .title(" BOOT STATUS ")
+            .title(format!(" {} ", tr("BOOT STATUS")))
             .title_style(Style::default().fg(self.theme.secondary));
 
         let inner = block.inner(area);
@@ -320,7 +549,7 @@ impl BriefingScreen<'_> {
                 format!("boot {}/{}", vm.boot_passing, boot_total)
             };
 
-            lines.push(Line::from(vec![
+            let mut spans = vec![
                 Span::styled(format!("{status_icon} "), Style::default().fg(icon_color)),
                 Span::styled(
                     format!(
@@ -337,7 +566,27 @@ impl BriefingScreen<'_> {
                 ),
                 Span::raw("  "),
                 Span::styled(boot_label, Style::default().fg(self.theme.secondary)),
-            ]));
+            ];
+            for (label, reached) in &vm.boot_checklist {
+                let (mark, color) = if *reached {
+                    ("✓", self.theme.success)
+                } else {
+                    ("·", self.theme.dim)
+                };
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(
+                    format!("{mark} {label}"),
+                    Style::default().fg(color),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        if let Some(step) = self.step {
+            lines.truncate(usize::from(inner.height).saturating_sub(1));
+            lines.push(Line::from(Span::styled(
+                format!("» {step}"),
+                Style::default().fg(self.theme.dim),
+            )));
         }
 
         Paragraph::new(lines)
@@ -352,7 +601,8 @@ impl BriefingScreen<'_> {
 
         let needed = u16::try_from(self.vms.len())
             .unwrap_or(u16::MAX)
-            .saturating_add(2);
+            .saturating_add(2)
+            .saturating_add(u16::from(self.step.is_some()));
         let min_boot = 3u16.min(total_height);
         needed.min(total_height).max(min_boot)
     }
@@ -380,7 +630,7 @@ impl BriefingScreen<'_> {
                 .bold()
         };
 
-        let keys = vec![("?", "Help"), ("T", "Theme"), ("Q", "Quit")];
+        let keys = vec![("?", tr("Help")), ("T", tr("Theme")), ("Q", tr("Quit"))];
 
         let mut spans = Vec::new();
         for (key, desc) in keys {
@@ -466,10 +716,16 @@ impl ScenarioTreeScreen<'_> {
                 Style::default().fg(self.theme.warning).bold(),
             ),
             Span::styled(" | ", Style::default().fg(self.theme.dim)),
-            Span::styled("BOOT ", Style::default().fg(self.theme.dim)),
+            Span::styled(
+                format!("{} ", tr("BOOT")),
+                Style::default().fg(self.theme.dim),
+            ),
             Span::styled(boot_timer, Style::default().fg(self.theme.primary).bold()),
             Span::styled(" | ", Style::default().fg(self.theme.dim)),
-            Span::styled("RUN ", Style::default().fg(self.theme.dim)),
+            Span::styled(
+                format!("{} ", tr("RUN")),
+                Style::default().fg(self.theme.dim),
+            ),
             Span::styled(run_timer, Style::default().fg(self.theme.primary).bold()),
             Span::styled(" | ", Style::default().fg(self.theme.dim)),
             Span::styled(format!("[{run_id}] "), Style::default().fg(self.theme.dim)),
@@ -508,16 +764,28 @@ impl ScenarioTreeScreen<'_> {
         match self.active_tab {
             MainTab::Briefing => self.render_briefing_view(content_area, buf),
             MainTab::Logs => self.render_logs_view(content_area, buf),
+            MainTab::Session => self.render_session_view(content_area, buf),
+            MainTab::Terminal => self.render_terminal_view(content_area, buf),
             MainTab::System => self.render_system_view(content_area, buf),
+            MainTab::Probes => self.render_probes_view(content_area, buf),
+            MainTab::Debug => self.render_debug_view(content_area, buf),
         }
     }
 
     fn render_tab_header(&self, area: Rect, buf: &mut Buffer) {
-        let tabs = vec![
-            (MainTab::Briefing, " BRIEFING "),
-            (MainTab::Logs, " LOGS "),
-            (MainTab::System, " SYSTEM "),
+        let mut tabs = vec![
+            (MainTab::Briefing, tr("BRIEFING")),
+            (MainTab::Logs, tr("LOGS")),
+            (MainTab::Session, tr("SESSION")),
+            (MainTab::Terminal, tr("TERMINAL")),
+            (MainTab::System, tr("SYSTEM")),
         ];
+        if self.probe_trace.is_some() {
+            tabs.push((MainTab::Probes, tr("PROBES")));
+        }
+        if self.active_tab == MainTab::Debug {
+            tabs.push((MainTab::Debug, tr("DEBUG")));
+        }
 
         let mut spans = Vec::new();
         for (tab, label) in tabs {
@@ -541,7 +809,7 @@ impl ScenarioTreeScreen<'_> {
             if !spans.is_empty() {
                 spans.push(Span::raw("  "));
             }
-            spans.push(Span::styled(format!("{prefix}{label}{suffix}"), style));
+            spans.push(Span::styled(format!("{prefix} {label} {suffix}"), style));
         }
 
         let block = Block::default()
@@ -562,7 +830,7 @@ impl ScenarioTreeScreen<'_> {
 
     fn render_system_view(&self, area: Rect, buf: &mut Buffer) {
         if self.vms.is_empty() {
-            Paragraph::new("No VMs available")
+            Paragraph::new(tr("No VMs available"))
                 .alignment(Alignment::Center)
                 .style(Style::default().fg(self.theme.dim))
                 .render(area, buf);
@@ -629,12 +897,39 @@ impl ScenarioTreeScreen<'_> {
             let mem_line = leaf_line("├─", "MEM", format!("{memory} MB", memory = vm.memory));
             let disk_line = leaf_line("├─", "DISK", format!("{disk} GB", disk = vm.disk));
             let ssh_line = leaf_line(
-                "└─",
+                "├─",
                 "SSH",
                 vm.ssh_port.map_or("—".to_string(), |p| p.to_string()),
             );
+            let host_line = leaf_line(
+                if vm.lan.is_some() { "├─" } else { "└─" },
+                "HOST",
+                vm.host_cpu
+                    .map_or("—".to_string(), |cpu| format!("{cpu:.0}% CPU")),
+            );
+            let lan_line = vm.lan.as_ref().map(|lan| {
+                leaf_line(
+                    "└─",
+                    "LAN",
+                    format!(
+                        "tx {} pkt {} KB  rx {} pkt {} KB{}",
+                        lan.sent_packets,
+                        lan.sent_bytes / 1024,
+                        lan.received_packets,
+                        lan.received_bytes / 1024,
+                        if vm.lan_capture {
+                            "  ● capturing"
+                        } else {
+                            ""
+                        }
+                    ),
+                )
+            });
 
-            for line in [header, cpu_line, mem_line, disk_line, ssh_line] {
+            let lines = [header, cpu_line, mem_line, disk_line, ssh_line, host_line]
+                .into_iter()
+                .chain(lan_line);
+            for line in lines {
                 if row >= area.height {
                     break;
                 }
@@ -667,7 +962,7 @@ impl ScenarioTreeScreen<'_> {
         if let Some(header_area) = header_area {
             let header_lines = vec![
                 Line::from(Span::styled(
-                    "SSH session transcript",
+                    tr("SSH session transcript"),
                     Style::default().fg(self.theme.secondary).bold(),
                 )),
                 Line::from(Span::styled(
@@ -704,6 +999,299 @@ impl ScenarioTreeScreen<'_> {
             .render(logs_area, buf);
     }
 
+    fn render_session_view(&self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 || area.width == 0 {
+            return;
+        }
+
+        let Some((vm, screen)) = self.session else {
+            Paragraph::new(tr("No SSH session yet."))
+                .style(Style::default().fg(self.theme.dim))
+                .alignment(Alignment::Center)
+                .render(area, buf);
+            return;
+        };
+
+        let chunks = Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).split(area);
+        let status = if screen.is_active() {
+            Span::styled(tr("live"), Style::default().fg(self.theme.success).bold())
+        } else {
+            Span::styled(tr("closed"), Style::default().fg(self.theme.dim))
+        };
+        self.render_screen_header(vm, status, &format!("←/→ {}", tr("Switch session VM")))
+            .render(chunks[0], buf);
+        self.render_screen(screen, screen.is_active(), chunks[1], buf);
+    }
+
+    fn render_terminal_view(&self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 || area.width == 0 {
+            return;
+        }
+
+        let Some(vm) = self.terminal.vm else {
+            Paragraph::new(tr("No VMs to connect to."))
+                .style(Style::default().fg(self.theme.dim))
+                .alignment(Alignment::Center)
+                .render(area, buf);
+            return;
+        };
+
+        let chunks = Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).split(area);
+        let connected = self.terminal.screen.is_some() && self.terminal.status.is_none();
+        let status = match &self.terminal.status {
+            Some(status) => Span::styled(status.clone(), Style::default().fg(self.theme.warning)),
+            None if connected => Span::styled(
+                tr("connected"),
+                Style::default().fg(self.theme.success).bold(),
+            ),
+            None => Span::styled(
+                tr("Press Enter to connect"),
+                Style::default().fg(self.theme.dim),
+            ),
+        };
+        let keys = format!("F2 {}  CTRL+] {}", tr("Switch VM"), tr("Leave terminal"));
+        self.render_screen_header(vm, status, &keys)
+            .render(chunks[0], buf);
+        if let Some(screen) = self.terminal.screen {
+            self.render_screen(screen, connected, chunks[1], buf);
+        }
+    }
+
+    /// The line above a session screen: the VM, its status, and the keys that apply.
+    fn render_screen_header(
+        &self,
+        vm: &str,
+        status: Span<'static>,
+        keys: &str,
+    ) -> Paragraph<'static> {
+        Paragraph::new(Line::from(vec![
+            Span::styled(
+                format!("{vm} "),
+                Style::default().fg(self.theme.secondary).bold(),
+            ),
+            status,
+            Span::styled(format!("  {keys}"), Style::default().fg(self.theme.dim)),
+        ]))
+        .style(Style::default().bg(self.theme.surface))
+    }
+
+    fn render_screen(
+        &self,
+        screen: &SessionScreen,
+        show_cursor: bool,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        // Keep the cursor row in view when the session is taller than the tab.
+        let view_height = usize::from(area.height);
+        let (cursor_row, cursor_col) = screen.cursor();
+        let start = (cursor_row + 1).saturating_sub(view_height);
+        let cursor_style = Style::default().add_modifier(Modifier::REVERSED);
+        let lines: Vec<Line> = screen
+            .lines()
+            .into_iter()
+            .enumerate()
+            .skip(start)
+            .take(view_height)
+            .map(|(row, text)| {
+                if row != cursor_row || !show_cursor {
+                    return Line::from(text);
+                }
+                let mut chars: Vec<char> = text.chars().collect();
+                if chars.len() <= cursor_col {
+                    chars.resize(cursor_col + 1, ' ');
+                }
+                let before: String = chars[..cursor_col].iter().collect();
+                let after: String = chars[cursor_col + 1..].iter().collect();
+                Line::from(vec![
+                    Span::raw(before),
+                    Span::styled(chars[cursor_col].to_string(), cursor_style),
+                    Span::raw(after),
+                ])
+            })
+            .collect();
+
+        Paragraph::new(lines)
+            .style(Style::default().bg(self.theme.surface).fg(self.theme.fg))
+            .render(area, buf);
+    }
+
+    fn render_probes_view(&self, area: Rect, buf: &mut Buffer) {
+        let Some(tab) = self.probe_trace.as_ref() else {
+            return;
+        };
+        let now = Instant::now();
+        let heading = Style::default().fg(self.theme.secondary).bold();
+        let dim = Style::default().fg(self.theme.dim);
+
+        let mut header = vec![Line::from(vec![
+            Span::styled(tr("Probes"), heading),
+            Span::styled(format!("  {}", tr("↑/↓ select, E re-evaluates")), dim),
+        ])];
+        for (i, (vm, probe)) in tab.probes.iter().enumerate() {
+            let selected = i == tab.selected;
+            let mut spans = vec![Span::styled(
+                format!("{}{vm}/{probe}", if selected { "▶ " } else { "  " }),
+                if selected {
+                    Style::default().fg(self.theme.fg).bold()
+                } else {
+                    Style::default().fg(self.theme.fg)
+                },
+            )];
+            if let Some(latest) = tab.trace.and_then(|trace| trace.latest(vm, probe)) {
+                spans.push(Span::raw("  "));
+                spans.push(self.evaluation_status(latest));
+                spans.push(Span::styled(
+                    format!(
+                        "  {}ms, {} ago",
+                        latest.duration.as_millis(),
+                        format_duration(now.saturating_duration_since(latest.at))
+                    ),
+                    dim,
+                ));
+            }
+            header.push(Line::from(spans));
+        }
+        header.push(Line::default());
+        header.push(Line::from(Span::styled(tr("Evaluations"), heading)));
+
+        let header_height = u16::try_from(header.len())
+            .unwrap_or(u16::MAX)
+            .min(area.height / 2);
+        let chunks =
+            Layout::vertical([Constraint::Length(header_height), Constraint::Min(0)]).split(area);
+        Paragraph::new(header)
+            .style(Style::default().bg(self.theme.surface))
+            .render(chunks[0], buf);
+
+        let trace_area = chunks[1];
+        let evaluations = tab.trace.map(ProbeTrace::evaluations);
+        let Some(evaluations) = evaluations.filter(|evaluations| !evaluations.is_empty()) else {
+            Paragraph::new(tr("No probe has been evaluated yet."))
+                .style(dim)
+                .alignment(Alignment::Center)
+                .render(trace_area, buf);
+            return;
+        };
+
+        let lines: Vec<Line> = evaluations
+            .iter()
+            .flat_map(|evaluation| {
+                let mut lines = vec![Line::from(vec![
+                    Span::styled(
+                        format!("{}/{}  ", evaluation.vm, evaluation.probe),
+                        Style::default().fg(self.theme.fg),
+                    ),
+                    self.evaluation_status(evaluation),
+                    Span::styled(format!("  {}ms  ", evaluation.duration.as_millis()), dim),
+                    Span::styled(
+                        evaluation.message.as_str(),
+                        Style::default().fg(self.theme.fg),
+                    ),
+                ])];
+                if !evaluation.raw.is_empty() {
+                    lines.push(Line::from(Span::styled(
+                        format!("    {}", evaluation.raw),
+                        dim,
+                    )));
+                }
+                lines
+            })
+            .collect();
+        let total = lines.len();
+        let view_height = trace_area.height as usize;
+        let max_scroll = u16::try_from(total.saturating_sub(view_height)).unwrap_or(u16::MAX);
+        let scroll = self.scroll.min(max_scroll) as usize;
+        let start = total.saturating_sub(view_height).saturating_sub(scroll);
+        let end = (start + view_height).min(total);
+        Paragraph::new(lines[start..end].to_vec())
+            .style(Style::default().bg(self.theme.surface))
+            .render(trace_area, buf);
+    }
+
+    fn evaluation_status(&self, evaluation: &ProbeEvaluation) -> Span<'static> {
+        if evaluation.passed {
+            Span::styled(tr("PASS"), Style::default().fg(self.theme.success).bold())
+        } else {
+            Span::styled(tr("FAIL"), Style::default().fg(self.theme.error).bold())
+        }
+    }
+
+    fn render_debug_view(&self, area: Rect, buf: &mut Buffer) {
+        let errors: Vec<&String> = self
+            .debug_log
+            .iter()
+            .filter(|line| is_qmp_or_agent_error(line))
+            .collect();
+        let errors = &errors[errors.len().saturating_sub(DEBUG_RECENT_ERRORS)..];
+
+        let heading = Style::default().fg(self.theme.secondary).bold();
+        let mut header = vec![Line::from(vec![
+            Span::styled("intar.log", heading),
+            Span::styled(
+                self.log_path
+                    .map(|path| format!("  {}", path.display()))
+                    .unwrap_or_default(),
+                Style::default().fg(self.theme.dim),
+            ),
+        ])];
+        header.push(Line::from(Span::styled(
+            tr("Recent QMP/agent errors"),
+            heading,
+        )));
+        if errors.is_empty() {
+            header.push(Line::from(Span::styled(
+                "  none",
+                Style::default().fg(self.theme.dim),
+            )));
+        }
+        header.extend(errors.iter().map(|line| {
+            Line::from(Span::styled(
+                format!("  {line}"),
+                log_line_style(self.theme, line),
+            ))
+        }));
+        header.push(Line::default());
+
+        let header_height = u16::try_from(header.len())
+            .unwrap_or(u16::MAX)
+            .min(area.height);
+        let chunks =
+            Layout::vertical([Constraint::Length(header_height), Constraint::Min(0)]).split(area);
+        Paragraph::new(header)
+            .style(Style::default().bg(self.theme.surface))
+            .render(chunks[0], buf);
+
+        let log_area = chunks[1];
+        if self.debug_log.is_empty() {
+            Paragraph::new("intar.log is empty or could not be found.")
+                .style(Style::default().fg(self.theme.dim))
+                .alignment(Alignment::Center)
+                .render(log_area, buf);
+            return;
+        }
+
+        let total = self.debug_log.len();
+        let view_height = log_area.height as usize;
+        let max_scroll = u16::try_from(total.saturating_sub(view_height)).unwrap_or(u16::MAX);
+        let scroll = self.scroll.min(max_scroll) as usize;
+        let start = total.saturating_sub(view_height).saturating_sub(scroll);
+        let end = (start + view_height).min(total);
+        let lines: Vec<Line> = self.debug_log[start..end]
+            .iter()
+            .map(|line| {
+                Line::from(Span::styled(
+                    line.as_str(),
+                    log_line_style(self.theme, line),
+                ))
+            })
+            .collect();
+
+        Paragraph::new(lines)
+            .style(Style::default().bg(self.theme.surface))
+            .render(log_area, buf);
+    }
+
     fn render_footer(&self, area: Rect, buf: &mut Buffer) {
         // Footer now has a block
         let block = Block::default()
@@ -715,14 +1303,22 @@ impl ScenarioTreeScreen<'_> {
         let inner = block.inner(area);
         block.render(area, buf);
 
-        let keys = vec![
-            ("TAB", "View"),
-            ("PGUP/PGDN", "Scroll"),
-            ("?", "Help"),
-            ("T", "Theme"),
-            ("R", "Restart"),
-            ("Q", "Quit"),
+        let restart_or_unlock = if self.exam {
+            ("U", tr("Unlock"))
+        } else {
+            ("R", tr("Restart"))
+        };
+        let mut keys = vec![
+            ("TAB", tr("View")),
+            ("PGUP/PGDN", tr("Scroll")),
+            ("?", tr("Help")),
+            ("T", tr("Theme")),
+            restart_or_unlock,
+            ("Q", tr("Quit")),
         ];
+        if self.active_tab == MainTab::Probes {
+            keys.insert(2, ("E", tr("Re-evaluate")));
+        }
 
         let mut spans = Vec::new();
         for (key, desc) in keys {
@@ -753,13 +1349,33 @@ impl ScenarioTreeScreen<'_> {
     }
 }
 
+fn log_line_style(theme: &Theme, line: &str) -> Style {
+    if line.contains(" ERROR ") {
+        Style::default().fg(theme.error)
+    } else if line.contains(" WARN ") {
+        Style::default().fg(theme.warning)
+    } else {
+        Style::default().fg(theme.dim)
+    }
+}
+
+/// Warnings and errors about a VM's QMP socket or guest agent in an intar.log line.
+fn is_qmp_or_agent_error(line: &str) -> bool {
+    (line.contains(" ERROR ") || line.contains(" WARN "))
+        && (line.contains("QMP") || line.to_lowercase().contains("agent"))
+}
+
 fn vm_status_label(theme: &Theme, status: VmStatus) -> (&'static str, Color) {
     match status {
-        VmStatus::Ready => ("READY", theme.success),
-        VmStatus::Booting | VmStatus::CloudInit => ("BOOT", theme.warning),
-        VmStatus::Starting => ("START", theme.warning),
-        VmStatus::Error => ("ERROR", theme.error),
-        VmStatus::Unknown => ("WAIT", theme.dim),
+        VmStatus::Ready => (tr("READY"), theme.success),
+        VmStatus::Booting | VmStatus::CloudInit => (tr("BOOT"), theme.warning),
+        VmStatus::Queued => (tr("QUEUE"), theme.secondary),
+        VmStatus::Starting => (tr("START"), theme.warning),
+        VmStatus::Paused => (tr("PAUSED"), theme.dim),
+        VmStatus::Stopped => (tr("OFF"), theme.error),
+        VmStatus::Panicked => (tr("PANIC"), theme.error),
+        VmStatus::Error => (tr("ERROR"), theme.error),
+        VmStatus::Unknown => (tr("WAIT"), theme.dim),
     }
 }
 
@@ -767,7 +1383,9 @@ fn vm_status_icon(theme: &Theme, status: VmStatus) -> (&'static str, Color) {
     match status {
         VmStatus::Ready => ("●", theme.success),
         VmStatus::Booting | VmStatus::CloudInit | VmStatus::Starting => ("●", theme.warning),
-        VmStatus::Error => ("●", theme.error),
+        VmStatus::Paused => ("●", theme.dim),
+        VmStatus::Stopped | VmStatus::Panicked | VmStatus::Error => ("●", theme.error),
+        VmStatus::Queued => ("○", theme.secondary),
         VmStatus::Unknown => ("○", theme.dim),
     }
 }
@@ -778,19 +1396,33 @@ impl Widget for CompletedScreen<'_> {
 
         let footer_height = 3u16.min(area.height);
         let available = area.height.saturating_sub(footer_height);
-        let header_height = 4u16.min(available);
+        let header_rows = if self.audit.is_some() { 5u16 } else { 4u16 };
+        let header_height = header_rows.min(available);
+
+        let body_height = area.height.saturating_sub(header_height + footer_height);
+        // Header row plus borders; the log below keeps at least half of the body.
+        let objectives_height = if self.objectives.is_empty() {
+            0
+        } else {
+            let rows = u16::try_from(self.objectives.len()).unwrap_or(u16::MAX);
+            rows.saturating_add(3).min(body_height / 2)
+        };
 
         let chunks = Layout::vertical([
             Constraint::Length(header_height),
-            Constraint::Min(area.height.saturating_sub(header_height + footer_height)),
+            Constraint::Length(objectives_height),
+            Constraint::Min(body_height.saturating_sub(objectives_height)),
             Constraint::Length(footer_height),
         ])
         .split(area);
 
         self.render_header(chunks[0], buf);
-        self.render_credits(chunks[1], buf);
-        self.render_footer(chunks[2], buf);
-    }
+        if objectives_height > 0 {
+            self.render_objectives(chunks[1], buf);
+        }
+        self.render_credits(chunks[2], buf);
+        self.render_footer(chunks[3], buf);
+    }
 }
 
 impl Widget for BriefingScreen<'_> {
@@ -855,32 +1487,700 @@ impl Widget for BriefingScreen<'_> {
     }
 }
 
-impl Widget for ScenarioTreeScreen<'_> {
+impl Widget for ScenarioTreeScreen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        self.render_main_layout(area, buf);
+    }
+}
+
+pub struct ConfirmDialog<'a> {
+    pub title: &'a str,
+    pub message: &'a str,
+    pub theme: &'a Theme,
+}
+
+pub struct QuitDialog<'a> {
+    pub theme: &'a Theme,
+}
+
+/// Shown while a restart restores the VMs' checkpoint and waits for them to come back.
+pub struct CheckpointDialog<'a> {
+    pub progress: &'a CheckpointProgress,
+    pub theme: &'a Theme,
+}
+
+/// Shown while one of the scenario's `on_complete` hooks runs.
+pub struct HookDialog<'a> {
+    /// The hook, e.g. `host command 'notify-send done'`.
+    pub hook: &'a str,
+    pub theme: &'a Theme,
+}
+
+pub struct LowSpaceDialog<'a> {
+    pub available_mb: u64,
+    pub resume_mb: u64,
+    pub theme: &'a Theme,
+}
+
+pub struct ExpiryDialog<'a> {
+    pub minutes_left: u64,
+    pub theme: &'a Theme,
+}
+
+pub struct GuestPanicDialog<'a> {
+    pub vm: &'a str,
+    pub theme: &'a Theme,
+}
+
+pub struct CrashDialog<'a> {
+    pub vm: &'a str,
+    pub reason: &'a str,
+    pub theme: &'a Theme,
+}
+
+pub struct IdleDialog<'a> {
+    pub idle_minutes: u64,
+    pub theme: &'a Theme,
+}
+
+pub struct HelpOverlay<'a> {
+    pub theme: &'a Theme,
+    pub mode: HelpMode,
+    pub exam: bool,
+}
+
+pub struct UnlockDialog<'a> {
+    pub input: &'a str,
+    pub failed: bool,
+    pub theme: &'a Theme,
+}
+
+pub struct FlagDialog<'a> {
+    pub input: &'a str,
+    pub rejected: bool,
+    pub theme: &'a Theme,
+}
+
+pub struct QuestionDialog<'a> {
+    pub prompt: &'a str,
+    pub choices: &'a [String],
+    pub selected: usize,
+    pub input: &'a str,
+    pub feedback: Option<AnswerOutcome>,
+    pub theme: &'a Theme,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HelpMode {
+    Briefing,
+    Running,
+    Completed,
+}
+
+impl Widget for ConfirmDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 50u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(self.theme.warning))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {title} ", title = self.title))
+            .title_style(Style::default().fg(self.theme.primary).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        for (i, line) in self.message.lines().enumerate() {
+            if i < 2 {
+                Paragraph::new(line)
+                    .style(Style::default().fg(self.theme.primary))
+                    .alignment(Alignment::Center)
+                    .render(chunks[i + 1], buf);
+            }
+        }
+
+        let buttons = Line::from(vec![
+            Span::styled("[Y]", Style::default().fg(self.theme.success).bold()),
+            Span::styled("es", Style::default().fg(self.theme.primary)),
+            Span::raw("          "),
+            Span::styled("[N]", Style::default().fg(self.theme.error).bold()),
+            Span::styled("o", Style::default().fg(self.theme.primary)),
+        ]);
+        Paragraph::new(buttons)
+            .alignment(Alignment::Center)
+            .render(chunks[4], buf);
+    }
+}
+
+impl Widget for QuitDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(self.theme.warning))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Quit")))
+            .title_style(Style::default().fg(self.theme.primary).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new("Destroy the run, or keep it to attach later?")
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+
+        let key =
+            |k: &'static str, color: Color| Span::styled(k, Style::default().fg(color).bold());
+        let label = |l: &'static str| Span::styled(l, Style::default().fg(self.theme.primary));
+        let buttons = Line::from(vec![
+            key("[D]", self.theme.error),
+            label("estroy"),
+            Span::raw("   "),
+            key("[K]", self.theme.success),
+            label("eep"),
+            Span::raw("   "),
+            key("[P]", self.theme.warning),
+            label("ause"),
+            Span::raw("   "),
+            key("[Esc]", self.theme.dim),
+            label(" Cancel"),
+        ]);
+        Paragraph::new(buttons)
+            .alignment(Alignment::Center)
+            .render(chunks[4], buf);
+    }
+}
+
+impl Widget for HookDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 7u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.info))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Completion hook")))
+            .title_style(Style::default().fg(self.theme.info).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).split(inner);
+
+        Paragraph::new(tr("Running on_complete hook"))
+            .style(Style::default().fg(self.theme.dim))
+            .alignment(Alignment::Center)
+            .render(chunks[0], buf);
+
+        Paragraph::new(self.hook)
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+    }
+}
+
+impl Widget for CheckpointDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 8u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.info))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Restarting")))
+            .title_style(Style::default().fg(self.theme.info).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        let fraction = self.progress.fraction();
+        let status = if fraction < 1.0 {
+            self.progress.to_string()
+        } else {
+            "Waiting for the agents and boot probes".to_string()
+        };
+        Paragraph::new(status)
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+
+        Paragraph::new(Line::from(vec![
+            Span::styled(progress_bar(fraction), Style::default().fg(self.theme.info)),
+            Span::styled(
+                format!(" {:>3.0}%", fraction * 100.0),
+                Style::default().fg(self.theme.dim),
+            ),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[2], buf);
+    }
+}
+
+impl Widget for LowSpaceDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(self.theme.error))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Low Disk Space")))
+            .title_style(Style::default().fg(self.theme.error).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new(format!(
+            "Only {} MB free. VMs are paused.",
+            self.available_mb
+        ))
+        .style(Style::default().fg(self.theme.primary))
+        .alignment(Alignment::Center)
+        .render(chunks[1], buf);
+
+        Paragraph::new(format!("They resume once {} MB are free.", self.resume_mb))
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[2], buf);
+
+        Paragraph::new(Line::from(Span::styled(
+            "[Q] Quit",
+            Style::default().fg(self.theme.dim),
+        )))
+        .alignment(Alignment::Center)
+        .render(chunks[4], buf);
+    }
+}
+
+impl Widget for ExpiryDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.warning))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Run Expiring")))
+            .title_style(Style::default().fg(self.theme.warning).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new(format!(
+            "This run stops in {} min when its TTL runs out.",
+            self.minutes_left
+        ))
+        .style(Style::default().fg(self.theme.primary))
+        .alignment(Alignment::Center)
+        .render(chunks[1], buf);
+
+        Paragraph::new("The VMs are destroyed; only the records are kept.")
+            .style(Style::default().fg(self.theme.dim))
+            .alignment(Alignment::Center)
+            .render(chunks[2], buf);
+
+        Paragraph::new(Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(self.theme.success).bold()),
+            Span::styled(" OK", Style::default().fg(self.theme.primary)),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[4], buf);
+    }
+}
+
+impl Widget for GuestPanicDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.error))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Guest Panicked")))
+            .title_style(Style::default().fg(self.theme.error).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new(format!("The kernel of VM '{}' panicked.", self.vm))
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+
+        Paragraph::new(format!(
+            "Check its console log: intar logs --vm {}",
+            self.vm
+        ))
+        .style(Style::default().fg(self.theme.dim))
+        .alignment(Alignment::Center)
+        .render(chunks[2], buf);
+
+        Paragraph::new(Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(self.theme.success).bold()),
+            Span::styled(" OK", Style::default().fg(self.theme.primary)),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[4], buf);
+    }
+}
+
+impl Widget for CrashDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.error))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("VM Crashed")))
+            .title_style(Style::default().fg(self.theme.error).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new(format!("VM '{}' stopped: {}", self.vm, self.reason))
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+
+        Paragraph::new("Restart keeps its disk and loads the latest checkpoint.")
+            .style(Style::default().fg(self.theme.dim))
+            .alignment(Alignment::Center)
+            .render(chunks[2], buf);
+
+        let key =
+            |k: &'static str, color: Color| Span::styled(k, Style::default().fg(color).bold());
+        let label = |l: &'static str| Span::styled(l, Style::default().fg(self.theme.primary));
+        Paragraph::new(Line::from(vec![
+            key("[R]", self.theme.success),
+            label("estart"),
+            Span::raw("   "),
+            key("[Esc]", self.theme.dim),
+            label(" Dismiss"),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[4], buf);
+    }
+}
+
+impl Widget for IdleDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 56u16;
+        let dialog_height = 9u16;
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(self.theme.warning))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Suspended")))
+            .title_style(Style::default().fg(self.theme.warning).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new("Suspended due to inactivity. VMs are paused.")
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+
+        Paragraph::new(format!("No activity for {} min.", self.idle_minutes))
+            .style(Style::default().fg(self.theme.dim))
+            .alignment(Alignment::Center)
+            .render(chunks[2], buf);
+
+        let key =
+            |k: &'static str, color: Color| Span::styled(k, Style::default().fg(color).bold());
+        let label = |l: &'static str| Span::styled(l, Style::default().fg(self.theme.primary));
+        Paragraph::new(Line::from(vec![
+            key("[Enter]", self.theme.success),
+            label(" Resume"),
+            Span::raw("   "),
+            key("[Q]", self.theme.dim),
+            label(" Quit"),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[4], buf);
+    }
+}
+
+impl Widget for UnlockDialog<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        Clear.render(area, buf);
-        self.render_main_layout(area, buf);
-    }
-}
+        let dialog_width = 50u16;
+        let dialog_height = 9u16;
 
-pub struct ConfirmDialog<'a> {
-    pub title: &'a str,
-    pub message: &'a str,
-    pub theme: &'a Theme,
-}
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
 
-pub struct HelpOverlay<'a> {
-    pub theme: &'a Theme,
-    pub mode: HelpMode,
-}
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum HelpMode {
-    Briefing,
-    Running,
-    Completed,
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(self.theme.warning))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Unlock Results")))
+            .title_style(Style::default().fg(self.theme.primary).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new("Enter the instructor unlock code:")
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
+
+        let masked = "*".repeat(self.input.chars().count());
+        Paragraph::new(Line::from(vec![
+            Span::styled(masked, Style::default().fg(self.theme.fg).bold()),
+            Span::styled("_", Style::default().fg(self.theme.dim)),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[2], buf);
+
+        let status = if self.failed {
+            Span::styled(
+                tr("Incorrect code"),
+                Style::default().fg(self.theme.error).bold(),
+            )
+        } else {
+            Span::styled(
+                "[Enter] Unlock   [Esc] Cancel",
+                Style::default().fg(self.theme.dim),
+            )
+        };
+        Paragraph::new(Line::from(status))
+            .alignment(Alignment::Center)
+            .render(chunks[4], buf);
+    }
 }
 
-impl Widget for ConfirmDialog<'_> {
+impl Widget for FlagDialog<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let dialog_width = 50u16;
         let dialog_height = 9u16;
@@ -901,9 +2201,9 @@ impl Widget for ConfirmDialog<'_> {
             .borders(Borders::ALL)
             .padding(Padding::uniform(1))
             .border_type(BorderType::Double)
-            .border_style(Style::default().fg(self.theme.warning))
+            .border_style(Style::default().fg(self.theme.info))
             .style(Style::default().bg(self.theme.surface))
-            .title(format!(" {title} ", title = self.title))
+            .title(format!(" {} ", tr("Submit Flag")))
             .title_style(Style::default().fg(self.theme.primary).bold());
 
         let inner = block.inner(dialog_area);
@@ -918,23 +2218,127 @@ impl Widget for ConfirmDialog<'_> {
         ])
         .split(inner);
 
-        for (i, line) in self.message.lines().enumerate() {
-            if i < 2 {
-                Paragraph::new(line)
-                    .style(Style::default().fg(self.theme.primary))
-                    .alignment(Alignment::Center)
-                    .render(chunks[i + 1], buf);
-            }
-        }
+        Paragraph::new("Enter the flag you found:")
+            .style(Style::default().fg(self.theme.primary))
+            .alignment(Alignment::Center)
+            .render(chunks[1], buf);
 
-        let buttons = Line::from(vec![
-            Span::styled("[Y]", Style::default().fg(self.theme.success).bold()),
-            Span::styled("es", Style::default().fg(self.theme.primary)),
-            Span::raw("          "),
-            Span::styled("[N]", Style::default().fg(self.theme.error).bold()),
-            Span::styled("o", Style::default().fg(self.theme.primary)),
-        ]);
-        Paragraph::new(buttons)
+        Paragraph::new(Line::from(vec![
+            Span::styled(
+                self.input.to_string(),
+                Style::default().fg(self.theme.fg).bold(),
+            ),
+            Span::styled("_", Style::default().fg(self.theme.dim)),
+        ]))
+        .alignment(Alignment::Center)
+        .render(chunks[2], buf);
+
+        let status = if self.rejected {
+            Span::styled(
+                tr("Incorrect flag"),
+                Style::default().fg(self.theme.error).bold(),
+            )
+        } else {
+            Span::styled(
+                "[Enter] Submit   [Esc] Cancel",
+                Style::default().fg(self.theme.dim),
+            )
+        };
+        Paragraph::new(Line::from(status))
+            .alignment(Alignment::Center)
+            .render(chunks[4], buf);
+    }
+}
+
+impl Widget for QuestionDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog_width = 64u16;
+        let answer_rows = u16::try_from(self.choices.len().max(1)).unwrap_or(u16::MAX);
+        let dialog_height = answer_rows.saturating_add(11);
+
+        let x = area.x + (area.width.saturating_sub(dialog_width)) / 2;
+        let y = area.y + (area.height.saturating_sub(dialog_height)) / 2;
+
+        let dialog_area = Rect {
+            x,
+            y,
+            width: dialog_width.min(area.width),
+            height: dialog_height.min(area.height),
+        };
+
+        Clear.render(dialog_area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::uniform(1))
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(self.theme.info))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("Question")))
+            .title_style(Style::default().fg(self.theme.primary).bold());
+
+        let inner = block.inner(dialog_area);
+        block.render(dialog_area, buf);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(answer_rows),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        Paragraph::new(self.prompt)
+            .style(Style::default().fg(self.theme.primary))
+            .wrap(Wrap { trim: true })
+            .render(chunks[0], buf);
+
+        let answer_lines: Vec<Line> = if self.choices.is_empty() {
+            vec![Line::from(vec![
+                Span::styled("> ", Style::default().fg(self.theme.dim)),
+                Span::styled(
+                    self.input.to_string(),
+                    Style::default().fg(self.theme.fg).bold(),
+                ),
+                Span::styled("_", Style::default().fg(self.theme.dim)),
+            ])]
+        } else {
+            self.choices
+                .iter()
+                .enumerate()
+                .map(|(i, choice)| {
+                    let style = if i == self.selected {
+                        Style::default().fg(self.theme.secondary).bold()
+                    } else {
+                        Style::default().fg(self.theme.fg)
+                    };
+                    let marker = if i == self.selected { "▸" } else { " " };
+                    Line::from(Span::styled(format!("{marker} {}. {choice}", i + 1), style))
+                })
+                .collect()
+        };
+        Paragraph::new(answer_lines).render(chunks[2], buf);
+
+        let status = match self.feedback {
+            Some(AnswerOutcome::Correct) => Span::styled(
+                "Correct! [any key] Continue",
+                Style::default().fg(self.theme.success).bold(),
+            ),
+            Some(AnswerOutcome::Recorded) => Span::styled(
+                "Answer recorded. [any key] Continue",
+                Style::default().fg(self.theme.info).bold(),
+            ),
+            Some(AnswerOutcome::Incorrect) => Span::styled(
+                "Incorrect, try again",
+                Style::default().fg(self.theme.error).bold(),
+            ),
+            None => Span::styled(
+                "[Enter] Answer   [Esc] Later",
+                Style::default().fg(self.theme.dim),
+            ),
+        };
+        Paragraph::new(Line::from(status))
             .alignment(Alignment::Center)
             .render(chunks[4], buf);
     }
@@ -951,56 +2355,104 @@ impl Widget for HelpOverlay<'_> {
                 .bold()
         };
 
+        let restart_line = if self.exam {
+            Line::from(vec![
+                Span::styled(" U ", key_style),
+                Span::raw(format!(" {}", tr("Unlock results"))),
+            ])
+        } else {
+            Line::from(vec![
+                Span::styled(" R ", key_style),
+                Span::raw(format!(" {}", tr("Restart scenario"))),
+            ])
+        };
+
         let lines = match self.mode {
             HelpMode::Briefing => vec![
                 Line::from(vec![
                     Span::styled(" ? ", key_style),
-                    Span::raw(" Close help"),
+                    Span::raw(format!(" {}", tr("Close help"))),
                 ]),
                 Line::from(vec![
                     Span::styled(" T ", key_style),
-                    Span::raw(" Toggle theme"),
+                    Span::raw(format!(" {}", tr("Toggle theme"))),
+                ]),
+                Line::from(vec![
+                    Span::styled(" Q ", key_style),
+                    Span::raw(format!(" {}", tr("Quit"))),
                 ]),
-                Line::from(vec![Span::styled(" Q ", key_style), Span::raw(" Quit")]),
             ],
             HelpMode::Running => vec![
                 Line::from(vec![
                     Span::styled(" TAB ", key_style),
-                    Span::raw(" Switch view"),
+                    Span::raw(format!(" {}", tr("Switch view"))),
                 ]),
                 Line::from(vec![
                     Span::styled(" PGUP/PGDN ", key_style),
-                    Span::raw(" Scroll logs"),
+                    Span::raw(format!(" {}", tr("Scroll logs"))),
                 ]),
                 Line::from(vec![
-                    Span::styled(" R ", key_style),
-                    Span::raw(" Restart scenario"),
+                    Span::styled(" ←/→ ", key_style),
+                    Span::raw(format!(" {}", tr("Switch session VM"))),
                 ]),
                 Line::from(vec![
-                    Span::styled(" T ", key_style),
-                    Span::raw(" Toggle theme"),
+                    Span::styled(" CTRL+] ", key_style),
+                    Span::raw(format!(" {}", tr("Leave terminal"))),
                 ]),
-                Line::from(vec![Span::styled(" Q ", key_style), Span::raw(" Quit")]),
+                restart_line,
                 Line::from(vec![
-                    Span::styled(" ? ", key_style),
-                    Span::raw(" Close help"),
+                    Span::styled(" F ", key_style),
+                    Span::raw(format!(" {}", tr("Fire next manual step"))),
+                ]),
+                Line::from(vec![
+                    Span::styled(" S ", key_style),
+                    Span::raw(format!(" {}", tr("Submit flag"))),
+                ]),
+                Line::from(vec![
+                    Span::styled(" A ", key_style),
+                    Span::raw(format!(" {}", tr("Answer open question"))),
                 ]),
-            ],
-            HelpMode::Completed => vec![
                 Line::from(vec![
-                    Span::styled(" R ", key_style),
-                    Span::raw(" Restart scenario"),
+                    Span::styled(" C ", key_style),
+                    Span::raw(format!(" {}", tr("Start/stop LAN capture"))),
+                ]),
+                Line::from(vec![
+                    Span::styled(" D ", key_style),
+                    Span::raw(format!(" {}", tr("Show/hide intar.log"))),
                 ]),
                 Line::from(vec![
                     Span::styled(" T ", key_style),
-                    Span::raw(" Toggle theme"),
+                    Span::raw(format!(" {}", tr("Toggle theme"))),
+                ]),
+                Line::from(vec![
+                    Span::styled(" Q ", key_style),
+                    Span::raw(format!(" {}", tr("Quit"))),
                 ]),
-                Line::from(vec![Span::styled(" Q ", key_style), Span::raw(" Quit")]),
                 Line::from(vec![
                     Span::styled(" ? ", key_style),
-                    Span::raw(" Close help"),
+                    Span::raw(format!(" {}", tr("Close help"))),
                 ]),
             ],
+            HelpMode::Completed => {
+                let mut lines = vec![
+                    Line::from(vec![
+                        Span::styled(" T ", key_style),
+                        Span::raw(format!(" {}", tr("Toggle theme"))),
+                    ]),
+                    Line::from(vec![
+                        Span::styled(" Q ", key_style),
+                        Span::raw(format!(" {}", tr("Quit"))),
+                    ]),
+                    Line::from(vec![
+                        Span::styled(" ? ", key_style),
+                        Span::raw(format!(" {}", tr("Close help"))),
+                    ]),
+                ];
+                if !self.exam {
+                    lines.insert(0, restart_line);
+                }
+                lines
+            }
         };
 
         let dialog_width = 64u16;
@@ -1026,7 +2478,7 @@ impl Widget for HelpOverlay<'_> {
             .border_type(BorderType::Plain)
             .border_style(Style::default().fg(self.theme.border))
             .style(Style::default().bg(self.theme.surface))
-            .title(" HELP ")
+            .title(format!(" {} ", tr("HELP")))
             .title_style(Style::default().fg(self.theme.primary).bold());
 
         let inner = block.inner(dialog_area);
@@ -1039,6 +2491,165 @@ impl Widget for HelpOverlay<'_> {
     }
 }
 
+pub struct FailedScreen<'a> {
+    pub scenario_name: &'a str,
+    pub run_name: Option<&'a str>,
+    pub error: &'a str,
+    /// The failure is a base image that failed its checksum; offer to download it again.
+    pub corrupt_image: bool,
+    /// intar.log lines written since the TUI started.
+    pub log: &'a [String],
+    /// Show all of `log` rather than its warnings and errors.
+    pub show_full_log: bool,
+    pub log_path: Option<&'a Path>,
+    pub scroll: u16,
+    pub theme: &'a Theme,
+}
+
+impl Widget for FailedScreen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(self.theme.error))
+            .padding(Padding::uniform(1))
+            .style(Style::default().bg(self.theme.surface))
+            .title(format!(" {} ", tr("INITIALIZATION FAILED")))
+            .title_style(Style::default().fg(self.theme.error).bold());
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.height == 0 || inner.width == 0 {
+            return;
+        }
+
+        let chunks = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Max(6),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+        let run = self
+            .run_name
+            .map(|name| format!("  run {name}"))
+            .unwrap_or_default();
+        Paragraph::new(Line::from(vec![
+            Span::styled(
+                self.scenario_name,
+                Style::default().fg(self.theme.fg).bold(),
+            ),
+            Span::styled(run, Style::default().fg(self.theme.dim)),
+        ]))
+        .render(chunks[0], buf);
+
+        Paragraph::new(self.error)
+            .style(Style::default().fg(self.theme.error))
+            .wrap(Wrap { trim: false })
+            .render(chunks[1], buf);
+
+        self.render_log(chunks[2], buf);
+        self.render_footer(chunks[3], buf);
+    }
+}
+
+impl FailedScreen<'_> {
+    fn render_log(&self, area: Rect, buf: &mut Buffer) {
+        if area.height < 2 {
+            return;
+        }
+        let chunks = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).split(area);
+
+        let title = if self.show_full_log {
+            "intar.log"
+        } else {
+            "Warnings and errors from intar.log"
+        };
+        Paragraph::new(Line::from(vec![
+            Span::styled(title, Style::default().fg(self.theme.secondary).bold()),
+            Span::styled(
+                self.log_path
+                    .map(|path| format!("  {}", path.display()))
+                    .unwrap_or_default(),
+                Style::default().fg(self.theme.dim),
+            ),
+        ]))
+        .render(chunks[0], buf);
+
+        let lines: Vec<&String> = self
+            .log
+            .iter()
+            .filter(|line| {
+                self.show_full_log || line.contains(" ERROR ") || line.contains(" WARN ")
+            })
+            .collect();
+        let log_area = chunks[1];
+        if lines.is_empty() {
+            Paragraph::new(tr("Nothing logged."))
+                .style(Style::default().fg(self.theme.dim))
+                .render(log_area, buf);
+            return;
+        }
+
+        let total = lines.len();
+        let view_height = log_area.height as usize;
+        let max_scroll = u16::try_from(total.saturating_sub(view_height)).unwrap_or(u16::MAX);
+        let scroll = self.scroll.min(max_scroll) as usize;
+        let start = total.saturating_sub(view_height).saturating_sub(scroll);
+        let end = (start + view_height).min(total);
+        let lines: Vec<Line> = lines[start..end]
+            .iter()
+            .map(|line| {
+                Line::from(Span::styled(
+                    line.as_str(),
+                    log_line_style(self.theme, line),
+                ))
+            })
+            .collect();
+        Paragraph::new(lines).render(log_area, buf);
+    }
+
+    fn render_footer(&self, area: Rect, buf: &mut Buffer) {
+        let key_style = if self.theme.is_monochrome() {
+            Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+        } else {
+            Style::default()
+                .fg(self.theme.on_secondary)
+                .bg(self.theme.secondary)
+                .bold()
+        };
+
+        let log_label = if self.show_full_log {
+            tr("Errors only")
+        } else {
+            tr("Full log")
+        };
+        let mut keys = vec![("R", tr("Retry"))];
+        if self.corrupt_image {
+            keys.push(("D", tr("Re-download image")));
+        }
+        keys.extend([
+            ("L", log_label),
+            ("PGUP/PGDN", tr("Scroll")),
+            ("Q", tr("Quit")),
+        ]);
+
+        let mut spans = Vec::new();
+        for (key, desc) in keys {
+            spans.push(Span::styled(format!(" {key} "), key_style));
+            spans.push(Span::styled(
+                format!(" {desc} "),
+                Style::default().fg(self.theme.dim),
+            ));
+            spans.push(Span::raw(" "));
+        }
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+}
+
 pub struct ShutdownScreen<'a> {
     pub vm_names: Vec<String>,
     pub theme: &'a Theme,
@@ -1111,6 +2722,19 @@ fn format_duration(d: Duration) -> String {
     }
 }
 
+fn progress_bar(fraction: f64) -> String {
+    const CELLS: u32 = 8;
+    (1..=CELLS)
+        .map(|cell| {
+            if f64::from(cell) <= fraction * f64::from(CELLS) {
+                '▰'
+            } else {
+                '▱'
+            }
+        })
+        .collect()
+}
+
 fn format_duration_or_placeholder(duration: Option<Duration>) -> String {
     duration.map_or_else(|| "--:--".to_string(), format_duration)
 }
@@ -1126,7 +2750,7 @@ fn render_context_panel(theme: &Theme, description: &str, area: Rect, buf: &mut
         .border_type(BorderType::Plain)
         .border_style(Style::default().fg(theme.border))
         .style(Style::default().bg(theme.surface))
-        .title(" CONTEXT ")
+        .title(format!(" {} ", tr("CONTEXT")))
         .title_style(Style::default().fg(theme.secondary));
 
     let inner = block.inner(area);
@@ -1161,7 +2785,7 @@ fn render_objectives_panel(theme: &Theme, vms: &[VmTreeNode<'_>], area: Rect, bu
         .border_type(BorderType::Plain)
         .border_style(Style::default().fg(theme.border))
         .style(Style::default().bg(theme.surface))
-        .title(" OBJECTIVES ")
+        .title(format!(" {} ", tr("OBJECTIVES")))
         .title_style(Style::default().fg(theme.secondary));
 
     let inner = block.inner(area);
@@ -1317,7 +2941,7 @@ fn objectives_lines<'a>(
             });
 
         let header = Line::from(vec![
-            Span::styled("VM ", Style::default().fg(theme.dim)),
+            Span::styled(format!("{} ", tr("VM")), Style::default().fg(theme.dim)),
             Span::styled(vm.name.as_ref(), Style::default().fg(theme.primary).bold()),
             Span::styled("  ", Style::default()),
             Span::styled("[", Style::default().fg(theme.dim)),
@@ -1338,10 +2962,12 @@ fn objectives_lines<'a>(
         }
 
         for probe in &vm.scenario_probes {
-            let (icon, color) = match probe.status {
-                ProbeStatus::Passed => ("✓", theme.success),
-                ProbeStatus::Failed => ("✗", theme.error),
-                ProbeStatus::Pending => ("·", theme.dim),
+            let (icon, color) = match (probe.status, probe.severity) {
+                (ProbeStatus::Passed, _) => ("✓", theme.success),
+                (ProbeStatus::Failed, Some(Severity::Info)) => ("✗", theme.info),
+                (ProbeStatus::Failed, Some(Severity::Warning)) => ("✗", theme.warning),
+                (ProbeStatus::Failed, _) => ("✗", theme.error),
+                (ProbeStatus::Pending, _) => ("·", theme.dim),
             };
 
             let text_style = if probe.status == ProbeStatus::Passed {
@@ -1350,10 +2976,12 @@ fn objectives_lines<'a>(
                 Style::default().fg(theme.fg)
             };
 
-            let status_label = match probe.status {
-                ProbeStatus::Passed => "PASS",
-                ProbeStatus::Failed => "FAIL",
-                ProbeStatus::Pending => "WAIT",
+            let status_label = match (probe.status, probe.severity) {
+                (ProbeStatus::Passed, _) => tr("PASS"),
+                (ProbeStatus::Failed, Some(Severity::Info)) => tr("INFO"),
+                (ProbeStatus::Failed, Some(Severity::Warning)) => tr("WARN"),
+                (ProbeStatus::Failed, _) => tr("FAIL"),
+                (ProbeStatus::Pending, _) => tr("WAIT"),
             };
 
             lines.push(Line::from(vec![
@@ -1374,13 +3002,19 @@ fn objectives_lines<'a>(
                     Span::styled(desc.as_ref(), Style::default().fg(theme.dim)),
                 ]));
             }
+            if let Some(detail) = probe.detail.as_ref() {
+                lines.push(Line::from(vec![
+                    Span::raw("      "),
+                    Span::styled(detail.as_str(), Style::default().fg(color)),
+                ]));
+            }
         }
         lines.push(Line::raw(""));
     }
 
     if !has_objectives {
         lines.push(Line::from(Span::styled(
-            "No objectives configured.",
+            tr("No objectives configured."),
             Style::default().fg(theme.dim),
         )));
     }
@@ -1,7 +1,9 @@
-use crate::{HostSocket, connect_host_socket};
+use crate::terminal::{VtAction, VtParser, alt_screen_switch};
+use crate::{HostSocket, HostStream, SessionMirror, VmError, connect_host_socket};
 use base64::Engine as _;
-use intar_probes::ActionEvent;
-use serde::Serialize;
+use intar_probes::{ActionEvent, BootStage, ShellContext};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead as _, Write as _};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -13,14 +15,137 @@ pub struct ActionLineEvent {
     pub received_at: Instant,
     pub line: String,
     pub kind: ActionLineKind,
+    /// Where an input line was typed, when the guest agent reported it.
+    pub context: Option<ShellContext>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ActionLineKind {
     Input,
     Output,
 }
 
+/// File in a run's `logs` directory holding every action line of the run.
+pub const ACTION_LOG_FILE: &str = "ssh-actions.ndjson";
+
+/// One line of [`ACTION_LOG_FILE`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ActionLogRecord {
+    ts_unix_ms: u64,
+    vm: String,
+    kind: ActionLineKind,
+    line: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context: Option<ShellContext>,
+}
+
+/// The full action line stream of a run, one JSON record per line, so the TUI only keeps the
+/// latest lines in memory and reads older ones back when they are scrolled to.
+#[derive(Debug)]
+pub struct ActionLog {
+    path: PathBuf,
+    len: usize,
+}
+
+impl ActionLog {
+    /// The log in `logs_dir`, continuing after the records a resumed run already wrote.
+    #[must_use]
+    pub fn new(logs_dir: &Path) -> Self {
+        let path = logs_dir.join(ACTION_LOG_FILE);
+        let len = std::fs::File::open(&path)
+            .map(|file| std::io::BufReader::new(file).lines().count())
+            .unwrap_or_default();
+        Self { path, len }
+    }
+
+    /// Number of lines recorded.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append `events` in order.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the log cannot be written.
+    pub fn append(&mut self, events: &[ActionLineEvent]) -> Result<(), VmError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let (now, now_unix_ms) = (Instant::now(), unix_ms());
+        let mut out = String::new();
+        for ev in events {
+            let age = now.saturating_duration_since(ev.received_at);
+            let record = ActionLogRecord {
+                ts_unix_ms: now_unix_ms
+                    .saturating_sub(u64::try_from(age.as_millis()).unwrap_or(u64::MAX)),
+                vm: ev.vm.clone(),
+                kind: ev.kind,
+                line: ev.line.clone(),
+                context: ev.context.clone(),
+            };
+            out.push_str(&serde_json::to_string(&record)?);
+            out.push('\n');
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(out.as_bytes())?;
+        self.len += events.len();
+        Ok(())
+    }
+
+    /// Up to `limit` lines recorded before line `end`, oldest first. Lines that cannot be
+    /// parsed are skipped.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the log cannot be read.
+    pub fn read_before(&self, end: usize, limit: usize) -> Result<Vec<ActionLineEvent>, VmError> {
+        let start = end.saturating_sub(limit);
+        if start == end {
+            return Ok(Vec::new());
+        }
+        let file = std::fs::File::open(&self.path)?;
+        let (now, now_unix_ms) = (Instant::now(), unix_ms());
+        let mut events = Vec::with_capacity(end - start);
+        for line in std::io::BufReader::new(file)
+            .lines()
+            .skip(start)
+            .take(end - start)
+        {
+            let Ok(record) = serde_json::from_str::<ActionLogRecord>(&line?) else {
+                continue;
+            };
+            let age = Duration::from_millis(now_unix_ms.saturating_sub(record.ts_unix_ms));
+            events.push(ActionLineEvent {
+                vm: record.vm,
+                received_at: now.checked_sub(age).unwrap_or(now),
+                line: record.line,
+                kind: record.kind,
+                context: record.context,
+            });
+        }
+        Ok(events)
+    }
+}
+
+/// A boot milestone reported by a VM's cloud-init.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootStageEvent {
+    pub vm: String,
+    pub stage: BootStage,
+}
+
 #[derive(Debug, Serialize)]
 struct CastHeader {
     version: u8,
@@ -85,19 +210,43 @@ impl CastWriter {
 
 #[derive(Default)]
 struct LineCapture {
-    input_line: String,
-    input_escape: bool,
-    output_line: String,
-    output_escape: bool,
+    input: TerminalText,
+    output: TerminalText,
     prefer_raw: bool,
+    /// Last command typed at the shell, naming the program that takes over the screen.
+    last_command: String,
+    /// When a full-screen program (vim, htop, less) switched to the alternate screen.
+    interactive_since: Option<Instant>,
+    /// Live screens for the TUI's Session tab.
+    mirror: SessionMirror,
+    /// Where the line the next raw input ends was typed, as the agent reported it.
+    pending_context: Option<ShellContext>,
 }
 
 impl LineCapture {
     fn reset_buffers(&mut self) {
-        self.input_line.clear();
-        self.output_line.clear();
-        self.input_escape = false;
-        self.output_escape = false;
+        self.input = TerminalText::default();
+        self.output = TerminalText::default();
+        self.interactive_since = None;
+        self.pending_context = None;
+    }
+
+    /// Close a full-screen span left open, e.g. when the session ends inside vim.
+    fn end_interactive(
+        &mut self,
+        received_at: Instant,
+        vm_name: &str,
+        tx_lines: &mpsc::Sender<ActionLineEvent>,
+    ) {
+        if let Some(since) = self.interactive_since.take() {
+            let _ = tx_lines.try_send(ActionLineEvent {
+                vm: vm_name.to_string(),
+                received_at,
+                line: interactive_marker(&self.last_command, received_at.duration_since(since)),
+                kind: ActionLineKind::Output,
+                context: None,
+            });
+        }
     }
 
     fn note_raw(&mut self) {
@@ -110,16 +259,65 @@ impl LineCapture {
         vm_name: &str,
         tx_lines: &mpsc::Sender<ActionLineEvent>,
     ) {
-        let trimmed = self.output_line.trim();
-        if !trimmed.is_empty() && !is_prompt_line(trimmed) {
+        let line = self.output.take_line();
+        let trimmed = line.trim();
+        if self.interactive_since.is_some() {
+            self.end_interactive(received_at, vm_name, tx_lines);
+        } else if !trimmed.is_empty() && !is_prompt_line(trimmed) {
             let _ = tx_lines.try_send(ActionLineEvent {
                 vm: vm_name.to_string(),
                 received_at,
                 line: trimmed.to_string(),
                 kind: ActionLineKind::Output,
+                context: None,
             });
         }
-        self.output_line.clear();
+    }
+}
+
+/// What [`TerminalText`] found in a chunk, in stream order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TerminalEvent<'a> {
+    /// A line ended by CR or LF.
+    Line(&'a str),
+    /// A program entered (`true`) or left the alternate screen, as full-screen programs do.
+    AltScreen(bool),
+}
+
+/// Plain text lines from a raw terminal stream, as a VT parser would see them: escape
+/// sequences are dropped whole, UTF-8 is decoded across chunk boundaries, and backspace erases
+/// the last character.
+#[derive(Debug, Default)]
+struct TerminalText {
+    parser: VtParser,
+    line: String,
+}
+
+impl TerminalText {
+    /// Feed `chunk`, calling `on_event` with each line and alternate screen switch.
+    fn feed(&mut self, chunk: &[u8], mut on_event: impl FnMut(TerminalEvent<'_>)) {
+        let line = &mut self.line;
+        self.parser.feed(chunk, |action| match action {
+            VtAction::Print(c) => line.push(c),
+            VtAction::Execute(b'\r' | b'\n') => {
+                on_event(TerminalEvent::Line(line.as_str()));
+                line.clear();
+            }
+            VtAction::Execute(0x7f | 0x08) => {
+                let _ = line.pop();
+            }
+            VtAction::Execute(b'\t') => line.push('\t'),
+            VtAction::Execute(_) => {}
+            VtAction::Csi { params, final_byte } => {
+                if let Some(enter) = alt_screen_switch(params, final_byte) {
+                    on_event(TerminalEvent::AltScreen(enter));
+                }
+            }
+        });
+    }
+
+    fn take_line(&mut self) -> String {
+        std::mem::take(&mut self.line)
     }
 }
 
@@ -140,6 +338,7 @@ async fn handle_action_event(
         } => {
             line_state.reset_buffers();
             line_state.note_raw();
+            line_state.mirror.start(vm_name, width, height);
             if let Some(mut writer) = cast_writer.take() {
                 let _ = writer.finish().await;
             }
@@ -161,14 +360,7 @@ async fn handle_action_event(
                     let text = String::from_utf8_lossy(&bytes);
                     let _ = writer.write_event(ts_unix_ms, "i", &text).await;
                 }
-                derive_lines_from_input(
-                    &bytes,
-                    &mut line_state.input_line,
-                    &mut line_state.input_escape,
-                    received_at,
-                    vm_name,
-                    tx_lines,
-                );
+                derive_lines_from_input(&bytes, line_state, received_at, vm_name, tx_lines);
             }
         }
         ActionEvent::SshRawOutput {
@@ -181,23 +373,21 @@ async fn handle_action_event(
                     let text = String::from_utf8_lossy(&bytes);
                     let _ = writer.write_event(ts_unix_ms, "o", &text).await;
                 }
-                derive_lines_from_output(
-                    &bytes,
-                    &mut line_state.output_line,
-                    &mut line_state.output_escape,
-                    received_at,
-                    vm_name,
-                    tx_lines,
-                );
+                line_state.mirror.feed(vm_name, &bytes);
+                derive_lines_from_output(&bytes, line_state, received_at, vm_name, tx_lines);
             }
         }
-        ActionEvent::SshLine { line, .. } => {
-            if !line_state.prefer_raw {
+        ActionEvent::SshLine { line, context, .. } => {
+            if line_state.prefer_raw {
+                // The raw input that follows ends the same line.
+                line_state.pending_context = context;
+            } else {
                 let _ = tx_lines.try_send(ActionLineEvent {
                     vm: vm_name.to_string(),
                     received_at,
                     line,
                     kind: ActionLineKind::Input,
+                    context,
                 });
             }
         }
@@ -208,25 +398,47 @@ async fn handle_action_event(
                     received_at,
                     line,
                     kind: ActionLineKind::Output,
+                    context: None,
                 });
             }
         }
+        ActionEvent::SshOutputSuppressed { lines, bytes, .. } => {
+            // The dropped output may have ended the line in progress.
+            line_state.flush_output(received_at, vm_name, tx_lines);
+            let _ = tx_lines.try_send(ActionLineEvent {
+                vm: vm_name.to_string(),
+                received_at,
+                line: format!("[{lines} lines ({bytes} bytes) of output suppressed]"),
+                kind: ActionLineKind::Output,
+                context: None,
+            });
+        }
         ActionEvent::SshSessionEnd { .. } => {
             line_state.flush_output(received_at, vm_name, tx_lines);
+            line_state.mirror.end(vm_name);
             if let Some(mut writer) = cast_writer.take() {
                 let _ = writer.finish().await;
             }
         }
-        ActionEvent::Error { .. } => {}
+        ActionEvent::BootStage { .. } | ActionEvent::Error { .. } => {}
     }
 }
 
+/// Events read from a VM's actions port but not yet handled. Once it is full, reading waits
+/// for the handler to catch up, which backs the guest agent off instead of queueing without
+/// bound.
+const ACTION_EVENT_BUFFER: usize = 256;
+
+/// Read a VM's actions port: SSH activity goes to `tx_lines` (and cast recordings in
+/// `log_dir`, and the live screen in `mirror`), boot milestones to `tx_boot` when set.
 #[must_use]
 pub fn start_vm_actions_task(
     vm_name: String,
     actions_socket: HostSocket,
     log_dir: PathBuf,
     tx_lines: mpsc::Sender<ActionLineEvent>,
+    tx_boot: Option<mpsc::Sender<BootStageEvent>>,
+    mirror: SessionMirror,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         loop {
@@ -237,132 +449,167 @@ pub fn start_vm_actions_task(
 
             let _ = tokio::fs::create_dir_all(&log_dir).await;
 
-            let mut reader = BufReader::new(stream);
+            let (tx_events, mut rx_events) = mpsc::channel(ACTION_EVENT_BUFFER);
             let mut cast_writer: Option<CastWriter> = None;
-            let mut line_state = LineCapture::default();
-
-            loop {
-                let mut line = String::new();
-                let Ok(bytes) = reader.read_line(&mut line).await else {
-                    break;
-                };
-                if bytes == 0 {
-                    break;
-                }
+            let mut line_state = LineCapture {
+                mirror: mirror.clone(),
+                ..LineCapture::default()
+            };
 
-                let trimmed = line.trim();
-                if trimmed.is_empty() {
-                    continue;
+            let handle_events = async {
+                while let Some((received_at, event)) = rx_events.recv().await {
+                    if let ActionEvent::BootStage { stage, .. } = event {
+                        if let Some(tx) = &tx_boot {
+                            let _ = tx.try_send(BootStageEvent {
+                                vm: vm_name.clone(),
+                                stage,
+                            });
+                        }
+                        continue;
+                    }
+
+                    handle_action_event(
+                        event,
+                        received_at,
+                        &vm_name,
+                        &tx_lines,
+                        &log_dir,
+                        &mut cast_writer,
+                        &mut line_state,
+                    )
+                    .await;
                 }
+            };
+            tokio::join!(read_action_events(stream, tx_events), handle_events);
+        }
+    })
+}
 
-                let received_at = Instant::now();
-                let received_unix_ms = unix_ms();
+/// Parse the events on an actions port into `tx` until the port closes, waiting while `tx`
+/// is full.
+async fn read_action_events(stream: HostStream, tx: mpsc::Sender<(Instant, ActionEvent)>) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        let Ok(bytes) = reader.read_line(&mut line).await else {
+            break;
+        };
+        if bytes == 0 {
+            break;
+        }
 
-                let event = match serde_json::from_str::<ActionEvent>(trimmed) {
-                    Ok(e) => e,
-                    Err(e) => ActionEvent::Error {
-                        ts_unix_ms: received_unix_ms,
-                        message: format!("Failed to parse action event: {e}"),
-                    },
-                };
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
 
-                handle_action_event(
-                    event,
-                    received_at,
-                    &vm_name,
-                    &tx_lines,
-                    &log_dir,
-                    &mut cast_writer,
-                    &mut line_state,
-                )
-                .await;
-            }
+        let received_at = Instant::now();
+        let event = match serde_json::from_str::<ActionEvent>(trimmed) {
+            Ok(e) => e,
+            Err(e) => ActionEvent::Error {
+                ts_unix_ms: unix_ms(),
+                message: format!("Failed to parse action event: {e}"),
+            },
+        };
+        if tx.send((received_at, event)).await.is_err() {
+            break;
         }
-    })
+    }
 }
 
 fn derive_lines_from_input(
     chunk: &[u8],
-    line: &mut String,
-    in_escape: &mut bool,
+    capture: &mut LineCapture,
     received_at: Instant,
     vm_name: &str,
     tx_lines: &mpsc::Sender<ActionLineEvent>,
 ) {
-    for &b in chunk {
-        if *in_escape {
-            if (b as char).is_ascii_alphabetic() || b == b'~' {
-                *in_escape = false;
-            }
-            continue;
-        }
-
-        match b {
-            0x1b => {
-                *in_escape = true;
-            }
-            b'\r' | b'\n' => {
-                let trimmed = line.trim();
-                if !trimmed.is_empty() {
-                    let _ = tx_lines.try_send(ActionLineEvent {
-                        vm: vm_name.to_string(),
-                        received_at,
-                        line: trimmed.to_string(),
-                        kind: ActionLineKind::Input,
-                    });
-                }
-                line.clear();
-            }
-            0x7f | 0x08 => {
-                let _ = line.pop();
-            }
-            b'\t' => line.push('\t'),
-            b if b.is_ascii_graphic() || b == b' ' => line.push(char::from(b)),
-            _ => {}
+    let LineCapture {
+        input,
+        last_command,
+        interactive_since,
+        pending_context,
+        ..
+    } = capture;
+    input.feed(chunk, |event| {
+        let TerminalEvent::Line(line) = event else {
+            return;
+        };
+        let trimmed = line.trim();
+        // Keys typed inside a full-screen program are not commands.
+        if trimmed.is_empty() || interactive_since.is_some() {
+            return;
         }
-    }
+        trimmed.clone_into(last_command);
+        let _ = tx_lines.try_send(ActionLineEvent {
+            vm: vm_name.to_string(),
+            received_at,
+            line: trimmed.to_string(),
+            kind: ActionLineKind::Input,
+            context: pending_context.take(),
+        });
+    });
 }
 
+/// Send the output lines in `chunk`, collapsing each full-screen program's output into one
+/// `[interactive: vim (2m13s)]` line when it leaves the alternate screen.
 fn derive_lines_from_output(
     chunk: &[u8],
-    line: &mut String,
-    in_escape: &mut bool,
+    capture: &mut LineCapture,
     received_at: Instant,
     vm_name: &str,
     tx_lines: &mpsc::Sender<ActionLineEvent>,
 ) {
-    for &b in chunk {
-        if *in_escape {
-            if (b as char).is_ascii_alphabetic() || b == b'~' {
-                *in_escape = false;
+    let LineCapture {
+        output,
+        last_command,
+        interactive_since,
+        ..
+    } = capture;
+    let send = |line: String| {
+        let _ = tx_lines.try_send(ActionLineEvent {
+            vm: vm_name.to_string(),
+            received_at,
+            line,
+            kind: ActionLineKind::Output,
+            context: None,
+        });
+    };
+    output.feed(chunk, |event| match event {
+        TerminalEvent::Line(line) => {
+            let trimmed = line.trim();
+            if interactive_since.is_none() && !trimmed.is_empty() && !is_prompt_line(trimmed) {
+                send(trimmed.to_string());
             }
-            continue;
         }
-
-        match b {
-            0x1b => {
-                *in_escape = true;
-            }
-            b'\r' | b'\n' => {
-                let trimmed = line.trim();
-                if !trimmed.is_empty() && !is_prompt_line(trimmed) {
-                    let _ = tx_lines.try_send(ActionLineEvent {
-                        vm: vm_name.to_string(),
-                        received_at,
-                        line: trimmed.to_string(),
-                        kind: ActionLineKind::Output,
-                    });
-                }
-                line.clear();
-            }
-            0x7f | 0x08 => {
-                let _ = line.pop();
+        TerminalEvent::AltScreen(true) => {
+            interactive_since.get_or_insert(received_at);
+        }
+        TerminalEvent::AltScreen(false) => {
+            if let Some(since) = interactive_since.take() {
+                send(interactive_marker(
+                    last_command,
+                    received_at.duration_since(since),
+                ));
             }
-            b'\t' => line.push('\t'),
-            b if b.is_ascii_graphic() || b == b' ' => line.push(char::from(b)),
-            _ => {}
         }
-    }
+    });
+}
+
+/// The line standing in for a full-screen program started by `command`, e.g.
+/// `[interactive: vim (2m13s)]`.
+fn interactive_marker(command: &str, duration: Duration) -> String {
+    let program = command
+        .split_whitespace()
+        .find(|word| !matches!(*word, "sudo" | "doas") && !word.contains('='))
+        .map_or("program", |word| word.rsplit('/').next().unwrap_or(word));
+    let secs = duration.as_secs();
+    let duration = if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m{}s", secs / 60, secs % 60)
+    };
+    format!("[interactive: {program} ({duration})]")
 }
 
 fn is_prompt_line(line: &str) -> bool {
@@ -401,3 +648,114 @@ fn unix_ms() -> u64 {
     )
     .unwrap_or(u64::MAX)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_log_reads_back_pages() {
+        let dir = std::env::temp_dir().join(format!("intar-action-log-{}", std::process::id()));
+        let mut log = ActionLog::new(&dir);
+        assert!(log.is_empty());
+        let now = Instant::now();
+        let events: Vec<ActionLineEvent> = (0..5)
+            .map(|i| ActionLineEvent {
+                vm: "web".into(),
+                received_at: now,
+                line: format!("line {i}"),
+                kind: ActionLineKind::Output,
+                context: None,
+            })
+            .collect();
+        log.append(&events[..3]).unwrap();
+        log.append(&events[3..]).unwrap();
+        assert_eq!(ActionLog::new(&dir).len(), 5);
+
+        let page = log.read_before(4, 2).unwrap();
+        let lines: Vec<&str> = page.iter().map(|ev| ev.line.as_str()).collect();
+        assert_eq!(lines, vec!["line 2", "line 3"]);
+        assert_eq!(log.read_before(1, 10).unwrap().len(), 1);
+        assert!(log.read_before(0, 10).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn lines(chunks: &[&[u8]]) -> Vec<String> {
+        let mut text = TerminalText::default();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            text.feed(chunk, |event| match event {
+                TerminalEvent::Line(line) => out.push(line.trim().to_string()),
+                TerminalEvent::AltScreen(enter) => out.push(format!("<alt {enter}>")),
+            });
+        }
+        out.retain(|line| !line.is_empty());
+        out
+    }
+
+    #[test]
+    fn test_terminal_text_drops_escape_sequences() {
+        // Prompt with a window title (OSC ended by BEL) and colors.
+        assert_eq!(
+            lines(&[
+                b"\x1b]0;user@web: ~\x07\x1b[01;32muser@web\x1b[00m:\x1b[01;34m~\x1b[00m$ ls\r\n"
+            ]),
+            ["user@web:~$ ls"]
+        );
+        // vim entering the alternate screen, then its status line.
+        assert_eq!(
+            lines(&[
+                b"\x1b[?1049h\x1b[22;0;0t\x1b[1;24r\x1b[?12h\x1b[?12l\x1b[27m\x1b[m\x1b[H\x1b[2J",
+                b"\x1b[?25l\x1b[24;1H\"notes.txt\" 0L, 0B\r\n",
+            ]),
+            ["<alt true>", "\"notes.txt\" 0L, 0B"]
+        );
+        // htop: charset designation, OSC ended by ST, and a DCS string.
+        assert_eq!(
+            lines(&[b"\x1b(B\x1b[m\x1b]2;htop\x1b\\\x1bP+q544e\x1b\\  PID USER\r\n"]),
+            ["PID USER"]
+        );
+    }
+
+    #[test]
+    fn test_terminal_text_reports_alternate_screen() {
+        assert_eq!(
+            lines(&[
+                b"vim notes.txt\r\n\x1b[?1049h\x1b[Hhello\r\n",
+                b"\x1b[?25h\x1b[?1049l\x1b[?2004hdone\r\n",
+            ]),
+            [
+                "vim notes.txt",
+                "<alt true>",
+                "hello",
+                "<alt false>",
+                "done"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interactive_marker() {
+        assert_eq!(
+            interactive_marker("vim notes.txt", Duration::from_secs(133)),
+            "[interactive: vim (2m13s)]"
+        );
+        assert_eq!(
+            interactive_marker("sudo TERM=xterm /usr/bin/htop", Duration::from_secs(7)),
+            "[interactive: htop (7s)]"
+        );
+        assert_eq!(
+            interactive_marker("", Duration::ZERO),
+            "[interactive: program (0s)]"
+        );
+    }
+
+    #[test]
+    fn test_terminal_text_decodes_utf8_across_chunks() {
+        let bar = "│ grüße │".as_bytes();
+        let (head, tail) = bar.split_at(1);
+        assert_eq!(lines(&[head, tail, b"\n"]), ["│ grüße │"]);
+        // Backspace removes whole characters, and stray continuation bytes are dropped.
+        assert_eq!(lines(&["ü".as_bytes(), b"\x7fok\x80\n"]), ["ok"]);
+    }
+}
@@ -0,0 +1,486 @@
+use crate::VmError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const AUDIT_FILE: &str = "audit.log";
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    RunStarted,
+    RunDetached,
+    RunResumed,
+    CheckpointSaved,
+    CheckpointRestored,
+    Reset,
+    /// A QMP command sent on the user's behalf, like the balloon target of `intar vm scale`.
+    QmpCommand,
+    StepTriggered,
+    FlagSubmitted,
+    QuestionAnswered,
+    RunStopped,
+    RunExpired,
+    VmRestarted,
+    RunMigrated,
+}
+
+impl AuditOperation {
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditOperation::RunStarted => "run started",
+            AuditOperation::RunDetached => "run detached",
+            AuditOperation::RunResumed => "run resumed",
+            AuditOperation::CheckpointSaved => "checkpoint saved",
+            AuditOperation::CheckpointRestored => "checkpoint restored",
+            AuditOperation::Reset => "reset",
+            AuditOperation::QmpCommand => "qmp command",
+            AuditOperation::StepTriggered => "step triggered",
+            AuditOperation::FlagSubmitted => "flag submitted",
+            AuditOperation::QuestionAnswered => "question answered",
+            AuditOperation::RunStopped => "run stopped",
+            AuditOperation::RunExpired => "run expired",
+            AuditOperation::VmRestarted => "vm restarted",
+            AuditOperation::RunMigrated => "run migrated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub ts_unix_ms: u64,
+    pub operation: AuditOperation,
+    pub detail: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(
+        seq: u64,
+        ts_unix_ms: u64,
+        operation: AuditOperation,
+        detail: &str,
+        prev_hash: &str,
+    ) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash.as_bytes());
+        hasher.update(seq.to_be_bytes());
+        hasher.update(ts_unix_ms.to_be_bytes());
+        hasher.update(operation.label().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(detail.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Entry count and last hash of an audit chain, pinned in the run's `state.json` so a deleted
+/// log or a cut-off tail fails verification too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditHead {
+    pub entries: u64,
+    pub hash: String,
+}
+
+/// Append-only, hash-chained record of host-initiated mutations for a run.
+///
+/// Each entry commits to the previous entry's hash, so editing or deleting a
+/// line breaks verification from that point on. Removing whole entries from the end is caught
+/// by checking the log against the [`AuditHead`] pinned in `state.json`.
+pub struct AuditLog {
+    path: PathBuf,
+    chain: ChainCursor,
+}
+
+/// How far an audit log has been read and verified, so appends only read what other
+/// processes wrote since.
+#[derive(Debug, Clone)]
+struct ChainCursor {
+    /// Bytes of the log verified so far.
+    offset: u64,
+    /// Lines read so far, for error messages.
+    lines: usize,
+    next_seq: u64,
+    last_hash: String,
+}
+
+impl Default for ChainCursor {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            lines: 0,
+            next_seq: 0,
+            last_hash: GENESIS_HASH.to_string(),
+        }
+    }
+}
+
+impl ChainCursor {
+    /// Verify the complete lines written to `path` after the cursor and move past them,
+    /// returning their entries. A line still being written is left for the next call.
+    fn advance(&mut self, path: &Path) -> Result<Vec<AuditEntry>, VmError> {
+        let mut file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return if self.offset == 0 {
+                    Ok(Vec::new())
+                } else {
+                    Err(VmError::Audit(format!(
+                        "{AUDIT_FILE} is missing but {} entries were verified",
+                        self.next_seq
+                    )))
+                };
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if file.metadata()?.len() < self.offset {
+            return Err(VmError::Audit(format!(
+                "{AUDIT_FILE} shrank below the {} entries already verified",
+                self.next_seq
+            )));
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+
+        let mut cursor = self.clone();
+        let mut entries = Vec::new();
+        let mut reader = BufReader::new(file);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+            cursor.offset = reader.stream_position()?;
+            cursor.lines += 1;
+            let lineno = cursor.lines;
+            let text = String::from_utf8_lossy(&line);
+            if text.trim().is_empty() {
+                continue;
+            }
+            let entry: AuditEntry = serde_json::from_str(&text)
+                .map_err(|e| VmError::Audit(format!("line {lineno}: invalid entry: {e}")))?;
+
+            if entry.seq != cursor.next_seq {
+                return Err(VmError::Audit(format!(
+                    "line {lineno}: expected seq {}, found {}",
+                    cursor.next_seq, entry.seq
+                )));
+            }
+            if entry.prev_hash != cursor.last_hash {
+                return Err(VmError::Audit(format!(
+                    "line {lineno}: chain broken (prev_hash mismatch)"
+                )));
+            }
+            let expected = AuditEntry::compute_hash(
+                entry.seq,
+                entry.ts_unix_ms,
+                entry.operation,
+                &entry.detail,
+                &entry.prev_hash,
+            );
+            if entry.hash != expected {
+                return Err(VmError::Audit(format!(
+                    "line {lineno}: hash mismatch (entry was modified)"
+                )));
+            }
+
+            cursor.next_seq += 1;
+            cursor.last_hash.clone_from(&entry.hash);
+            entries.push(entry);
+        }
+        *self = cursor;
+        Ok(entries)
+    }
+}
+
+impl AuditLog {
+    /// Open (or create) the audit log in `run_dir`, resuming the existing chain. `pinned` is
+    /// the head recorded in the run's `state.json`, if any.
+    ///
+    /// # Errors
+    /// Returns `VmError` if an existing log cannot be read or fails verification, or if the
+    /// log is missing or shorter than `pinned`.
+    pub fn open(run_dir: &Path, pinned: Option<&AuditHead>) -> Result<Self, VmError> {
+        let path = run_dir.join(AUDIT_FILE);
+        let (_, chain) = Self::verify_chain(&path, pinned)?;
+        Ok(Self { path, chain })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The chain's head to pin in `state.json`, as of the last entry this log recorded or
+    /// verified. Entries other processes appended since (`intar vm scale` records its QMP
+    /// commands while the TUI runs) are verified and included by the next
+    /// [`AuditLog::record`]; until then they still verify, as they extend the pinned head.
+    #[must_use]
+    pub fn head(&self) -> AuditHead {
+        AuditHead {
+            entries: self.chain.next_seq,
+            hash: self.chain.last_hash.clone(),
+        }
+    }
+
+    /// Append an entry to the chain, after the entries other processes appended since this
+    /// log last read it.
+    ///
+    /// # Errors
+    /// Returns `VmError::Audit` if those entries fail verification, and `VmError` if the entry
+    /// cannot be serialized or written.
+    pub fn record(
+        &mut self,
+        operation: AuditOperation,
+        detail: impl Into<String>,
+    ) -> Result<(), VmError> {
+        let detail = detail.into();
+        self.chain.advance(&self.path)?;
+        let seq = self.chain.next_seq;
+        let ts_unix_ms = unix_ms();
+        let prev_hash = self.chain.last_hash.clone();
+        let hash = AuditEntry::compute_hash(seq, ts_unix_ms, operation, &detail, &prev_hash);
+
+        let entry = AuditEntry {
+            seq,
+            ts_unix_ms,
+            operation,
+            detail,
+            prev_hash,
+            hash,
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        file.flush()?;
+
+        self.chain.advance(&self.path)?;
+        Ok(())
+    }
+
+    /// Read and verify an audit log, returning its entries in order. With `pinned`, the log
+    /// must also reach that head; entries appended after it are accepted.
+    ///
+    /// # Errors
+    /// Returns `VmError::Audit` if any entry is malformed, out of sequence, or its hash does not
+    /// match the chain, or if the log is missing or does not reach `pinned`.
+    pub fn verify(path: &Path, pinned: Option<&AuditHead>) -> Result<Vec<AuditEntry>, VmError> {
+        Self::verify_chain(path, pinned).map(|(entries, _)| entries)
+    }
+
+    fn verify_chain(
+        path: &Path,
+        pinned: Option<&AuditHead>,
+    ) -> Result<(Vec<AuditEntry>, ChainCursor), VmError> {
+        let pinned = pinned.filter(|head| head.entries > 0);
+        if !path.exists() {
+            return match pinned {
+                Some(head) => Err(VmError::Audit(format!(
+                    "{AUDIT_FILE} is missing but the run recorded {} entries",
+                    head.entries
+                ))),
+                None => Ok((Vec::new(), ChainCursor::default())),
+            };
+        }
+
+        let mut chain = ChainCursor::default();
+        let entries = chain.advance(path)?;
+        if chain.offset < std::fs::metadata(path)?.len() {
+            return Err(VmError::Audit(format!(
+                "line {}: incomplete entry",
+                chain.lines + 1
+            )));
+        }
+
+        if let Some(head) = pinned {
+            let last = usize::try_from(head.entries - 1)
+                .ok()
+                .and_then(|idx| entries.get(idx));
+            match last {
+                None => {
+                    return Err(VmError::Audit(format!(
+                        "log ends after {} entries but the run recorded {} (entries were removed)",
+                        entries.len(),
+                        head.entries
+                    )));
+                }
+                Some(entry) if entry.hash != head.hash => {
+                    return Err(VmError::Audit(format!(
+                        "entry {} does not match the one the run recorded",
+                        entry.seq
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok((entries, chain))
+    }
+}
+
+/// Counts of audited operations, used by the completion screen, the final report, and
+/// `report.json`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditSummary {
+    pub resets: usize,
+    pub restores: usize,
+    pub vm_restarts: usize,
+    pub steps_triggered: usize,
+    pub qmp_commands: usize,
+    pub verified: bool,
+}
+
+impl AuditSummary {
+    /// Summarize the audit log at `path`, checked against the `pinned` head; an unverifiable
+    /// log yields `verified = false`.
+    #[must_use]
+    pub fn load(path: &Path, pinned: Option<&AuditHead>) -> Self {
+        let Ok(entries) = AuditLog::verify(path, pinned) else {
+            return Self::default();
+        };
+
+        let count = |op: AuditOperation| entries.iter().filter(|e| e.operation == op).count();
+        Self {
+            resets: count(AuditOperation::Reset),
+            restores: count(AuditOperation::CheckpointRestored),
+            vm_restarts: count(AuditOperation::VmRestarted),
+            steps_triggered: count(AuditOperation::StepTriggered),
+            qmp_commands: count(AuditOperation::QmpCommand),
+            verified: true,
+        }
+    }
+}
+
+impl fmt::Display for AuditSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} reset(s), {} checkpoint restore(s), {} VM restart(s), {} triggered step(s), {} QMP command(s); chain {}",
+            self.resets,
+            self.restores,
+            self.vm_restarts,
+            self.steps_triggered,
+            self.qmp_commands,
+            if self.verified { "verified" } else { "broken" }
+        )
+    }
+}
+
+pub(crate) fn unix_ms() -> u64 {
+    u64::try_from(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis(),
+    )
+    .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_chain_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = AuditLog::open(dir.path(), None).unwrap();
+        log.record(AuditOperation::RunStarted, "run").unwrap();
+        log.record(AuditOperation::Reset, "user reset").unwrap();
+
+        let mut reopened = AuditLog::open(dir.path(), None).unwrap();
+        reopened
+            .record(AuditOperation::CheckpointRestored, "init")
+            .unwrap();
+
+        let entries = AuditLog::verify(log.path(), None).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].seq, 2);
+        assert_eq!(entries[2].prev_hash, entries[1].hash);
+
+        let summary = AuditSummary::load(log.path(), None);
+        assert!(summary.verified);
+        assert_eq!(summary.resets, 1);
+        assert_eq!(summary.restores, 1);
+    }
+
+    #[test]
+    fn test_audit_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = AuditLog::open(dir.path(), None).unwrap();
+        log.record(AuditOperation::Reset, "first").unwrap();
+        log.record(AuditOperation::Reset, "second").unwrap();
+
+        let content = std::fs::read_to_string(log.path()).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        std::fs::write(log.path(), format!("{}\n", lines[1])).unwrap();
+
+        assert!(AuditLog::verify(log.path(), None).is_err());
+        assert!(!AuditSummary::load(log.path(), None).verified);
+    }
+
+    #[test]
+    fn test_audit_pinned_head_detects_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = AuditLog::open(dir.path(), None).unwrap();
+        log.record(AuditOperation::RunStarted, "run").unwrap();
+        log.record(AuditOperation::Reset, "user reset").unwrap();
+        let head = log.head();
+        assert_eq!(head.entries, 2);
+        assert!(AuditLog::verify(log.path(), Some(&head)).is_ok());
+
+        let content = std::fs::read_to_string(log.path()).unwrap();
+        let first = content.lines().next().unwrap();
+        std::fs::write(log.path(), format!("{first}\n")).unwrap();
+        assert!(AuditLog::verify(log.path(), None).is_ok());
+        assert!(AuditLog::verify(log.path(), Some(&head)).is_err());
+        assert!(!AuditSummary::load(log.path(), Some(&head)).verified);
+
+        std::fs::remove_file(log.path()).unwrap();
+        assert!(AuditLog::open(dir.path(), Some(&head)).is_err());
+    }
+
+    #[test]
+    fn test_audit_follows_other_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut runner = AuditLog::open(dir.path(), None).unwrap();
+        runner.record(AuditOperation::RunStarted, "run").unwrap();
+
+        let mut cli = AuditLog::open(dir.path(), Some(&runner.head())).unwrap();
+        cli.record(AuditOperation::QmpCommand, "balloon web 512")
+            .unwrap();
+        runner.record(AuditOperation::Reset, "user reset").unwrap();
+
+        let entries = AuditLog::verify(runner.path(), Some(&runner.head())).unwrap();
+        assert_eq!(entries.len(), 3);
+        let summary = AuditSummary::load(runner.path(), Some(&runner.head()));
+        assert!(summary.verified);
+        assert_eq!(summary.qmp_commands, 1);
+        assert_eq!(summary.resets, 1);
+    }
+
+    #[test]
+    fn test_audit_record_rejects_broken_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = AuditLog::open(dir.path(), None).unwrap();
+        log.record(AuditOperation::RunStarted, "run").unwrap();
+        let head = log.head();
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(log.path())
+            .unwrap();
+        writeln!(file, "{{\"seq\": 7}}").unwrap();
+        assert!(log.record(AuditOperation::Reset, "user reset").is_err());
+        assert_eq!(log.head(), head);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{ActionLineEvent, ActionLineKind, VmError};
+use intar_core::ProbePhase;
+use intar_probes::{FixtureEvaluator, ProbeEvaluator, ProbeFixture, ProbeResult, ProbeSpec};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Environment variable naming a probe fixture for `--backend fake` to replay.
+pub const FAKE_FIXTURE_ENV: &str = "INTAR_FAKE_FIXTURE";
+
+/// What runs the VMs of a new run, picked with `intar start --backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Real VMs under QEMU.
+    #[default]
+    Qemu,
+    /// Real VMs under QEMU, run as libvirt domains (`libvirt.uri` in `config.yaml`).
+    Libvirt,
+    /// Cloud instances (`cloud` in `config.yaml`); experimental.
+    Cloud,
+    /// Simulated VMs from [`FakeBackend`]; nothing is downloaded or booted.
+    Fake,
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qemu" => Ok(Self::Qemu),
+            "libvirt" => Ok(Self::Libvirt),
+            "cloud" => Ok(Self::Cloud),
+            "fake" => Ok(Self::Fake),
+            other => Err(format!(
+                "unknown backend '{other}' (expected qemu, libvirt, cloud, or fake)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Qemu => "qemu",
+            Self::Libvirt => "libvirt",
+            Self::Cloud => "cloud",
+            Self::Fake => "fake",
+        })
+    }
+}
+
+/// Stands in for QEMU and the guest agents when a run should not boot real VMs. The runner
+/// asks it how long each VM takes to boot, what each probe returns, and what the learner typed.
+pub trait VirtBackend: Send + Sync {
+    /// Time from starting `vm` until its agent answers.
+    fn boot_delay(&self, vm: &str) -> Duration;
+
+    /// Evaluate probe `id` of `phase` on `vm`.
+    fn evaluate(&mut self, vm: &str, phase: ProbePhase, id: &str, spec: &ProbeSpec) -> ProbeResult;
+
+    /// Action lines recorded since the last call.
+    fn take_action_lines(&mut self) -> Vec<ActionLineEvent>;
+
+    /// The VMs went back to their initial checkpoint.
+    fn reset(&mut self);
+}
+
+/// An in-memory [`VirtBackend`] for integration tests and CI. VMs answer after a fixed boot
+/// delay, boot and teardown probes pass, and each objective fails `solve_after` checks before
+/// it passes, with a `fake-solve <probe>` command in the action log. Probes named in a
+/// [`ProbeFixture`] replay the fixture instead.
+#[derive(Debug)]
+pub struct FakeBackend {
+    boot_delay: Duration,
+    solve_after: usize,
+    fixture: Option<FixtureEvaluator>,
+    fixture_probes: HashSet<String>,
+    checks: HashMap<(String, String), usize>,
+    action_lines: Vec<ActionLineEvent>,
+}
+
+impl Default for FakeBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeBackend {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            boot_delay: Duration::from_millis(500),
+            solve_after: 1,
+            fixture: None,
+            fixture_probes: HashSet::new(),
+            checks: HashMap::new(),
+            action_lines: Vec::new(),
+        }
+    }
+
+    /// The backend of `intar start --backend fake`, replaying the fixture named by
+    /// [`FAKE_FIXTURE_ENV`] when it is set.
+    ///
+    /// # Errors
+    /// Returns `VmError::InvalidPath` if the fixture cannot be loaded.
+    pub fn from_env() -> Result<Self, VmError> {
+        let backend = Self::new();
+        let Some(path) = std::env::var_os(FAKE_FIXTURE_ENV) else {
+            return Ok(backend);
+        };
+        let fixture = ProbeFixture::load(Path::new(&path))
+            .map_err(|e| VmError::InvalidPath(format!("{FAKE_FIXTURE_ENV}: {e}")))?;
+        Ok(backend.with_fixture(fixture))
+    }
+
+    #[must_use]
+    pub fn with_boot_delay(mut self, delay: Duration) -> Self {
+        self.boot_delay = delay;
+        self
+    }
+
+    /// Fail each objective this many checks before it passes.
+    #[must_use]
+    pub fn with_solve_after(mut self, checks: usize) -> Self {
+        self.solve_after = checks;
+        self
+    }
+
+    #[must_use]
+    pub fn with_fixture(mut self, fixture: ProbeFixture) -> Self {
+        self.fixture_probes = fixture.probes.keys().cloned().collect();
+        self.fixture = Some(FixtureEvaluator::new(fixture));
+        self
+    }
+}
+
+impl VirtBackend for FakeBackend {
+    fn boot_delay(&self, _vm: &str) -> Duration {
+        self.boot_delay
+    }
+
+    fn evaluate(&mut self, vm: &str, phase: ProbePhase, id: &str, spec: &ProbeSpec) -> ProbeResult {
+        if self.fixture_probes.contains(id)
+            && let Some(fixture) = self.fixture.as_mut()
+        {
+            return fixture.evaluate(id, spec);
+        }
+        if phase != ProbePhase::Scenario {
+            return ProbeResult::pass(id, "Simulated");
+        }
+
+        let checks = self
+            .checks
+            .entry((vm.to_string(), id.to_string()))
+            .or_default();
+        *checks += 1;
+        if *checks <= self.solve_after {
+            return ProbeResult::fail(id, "Not solved yet (simulated)");
+        }
+        if *checks == self.solve_after + 1 {
+            self.action_lines.push(ActionLineEvent {
+                vm: vm.to_string(),
+                received_at: Instant::now(),
+                line: format!("fake-solve {id}"),
+                kind: ActionLineKind::Input,
+                context: None,
+            });
+        }
+        ProbeResult::pass(id, "Solved (simulated)")
+    }
+
+    fn take_action_lines(&mut self) -> Vec<ActionLineEvent> {
+        std::mem::take(&mut self.action_lines)
+    }
+
+    fn reset(&mut self) {
+        self.checks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ProbeSpec {
+        ProbeSpec::FileExists {
+            path: "/etc/hostname".into(),
+            exists: true,
+        }
+    }
+
+    #[test]
+    fn test_backend_kind_parse() {
+        assert_eq!("fake".parse::<BackendKind>(), Ok(BackendKind::Fake));
+        assert_eq!(BackendKind::default().to_string(), "qemu");
+        assert_eq!("libvirt".parse::<BackendKind>(), Ok(BackendKind::Libvirt));
+        assert!("vmware".parse::<BackendKind>().is_err());
+    }
+
+    #[test]
+    fn test_fake_backend_solves_objectives() {
+        let mut backend = FakeBackend::new().with_solve_after(2);
+
+        assert!(
+            backend
+                .evaluate("web", ProbePhase::Boot, "ssh-up", &spec())
+                .passed
+        );
+        for _ in 0..2 {
+            assert!(
+                !backend
+                    .evaluate("web", ProbePhase::Scenario, "nginx", &spec())
+                    .passed
+            );
+        }
+        assert!(backend.take_action_lines().is_empty());
+        assert!(
+            backend
+                .evaluate("web", ProbePhase::Scenario, "nginx", &spec())
+                .passed
+        );
+        let lines = backend.take_action_lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].vm, "web");
+        assert_eq!(lines[0].line, "fake-solve nginx");
+
+        backend.reset();
+        assert!(
+            !backend
+                .evaluate("web", ProbePhase::Scenario, "nginx", &spec())
+                .passed
+        );
+    }
+
+    #[test]
+    fn test_fake_backend_replays_fixture() {
+        let fixture: ProbeFixture =
+            serde_json::from_str(r#"{ "probes": { "ssh-up": [ { "passed": false } ] } }"#).unwrap();
+        let mut backend = FakeBackend::new().with_fixture(fixture);
+        assert!(
+            !backend
+                .evaluate("web", ProbePhase::Boot, "ssh-up", &spec())
+                .passed
+        );
+        assert!(
+            backend
+                .evaluate("web", ProbePhase::Boot, "other", &spec())
+                .passed
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    ImageCache, IntarConfig, IntarDirs, ScenarioRunner, VmError, detect_arch, path_to_str,
+    try_connect,
+};
+use intar_core::{Scenario, StepTrigger, VmDefinition};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// How long the guest may take to power off after provisioning.
+const POWER_DOWN_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Undo what intar itself put into the guest, so the baked image boots like the base cloud image
+/// and the next run installs its own agent, user, and hosts entries. Only the scenario's
+/// provisioning is kept.
+const BAKE_CLEANUP_SCRIPT: &str = r#"set -u
+systemctl disable intar-agent >/dev/null 2>&1 || true
+rm -f /etc/systemd/system/intar-agent.service /usr/local/bin/intar-agent /usr/local/bin/intar-shell
+sed -i '\#^/usr/local/bin/intar-shell$#d' /etc/shells
+if [ -f /etc/hosts.intar ]; then
+  grep -vxF -f /etc/hosts.intar /etc/hosts > /etc/hosts.baked || true
+  { echo '127.0.0.1 localhost'; cat /etc/hosts.baked; } > /etc/hosts
+  rm -f /etc/hosts.baked /etc/hosts.intar
+fi
+userdel -r user >/dev/null 2>&1 || true
+rm -f /etc/sudoers.d/90-cloud-init-users
+apt-get clean >/dev/null 2>&1 || true
+cloud-init clean --logs --seed --machine-id >/dev/null 2>&1 || cloud-init clean --logs
+sync
+"#;
+
+/// A compressed golden image produced by [`bake_image`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BakedImage {
+    pub path: PathBuf,
+    /// Image architecture as written in `source` blocks (`amd64` or `arm64`).
+    pub arch: String,
+    /// `sha256:<hex>` of the file at `path`.
+    pub checksum: String,
+    pub size: u64,
+}
+
+impl BakedImage {
+    /// `image` block that scenarios can use once the file is published at `url`.
+    #[must_use]
+    pub fn image_block(&self, name: &str, url: &str) -> String {
+        format!(
+            "image \"{name}\" {{\n  source {{\n    arch     = \"{}\"\n    url      = \"{url}\"\n    checksum = \"{}\"\n  }}\n}}\n",
+            self.arch, self.checksum
+        )
+    }
+}
+
+/// Boot `vm_name` from `scenario`, let cloud-init apply its packages, files, `runcmd`, and boot
+/// steps, then shut it down and write the flattened, compressed disk to `output`. Random fault
+/// groups, triggered steps, and probes are left out. `progress` is called with a short
+/// description of each stage.
+///
+/// # Errors
+/// Returns `VmError` if the VM or its image is missing, provisioning fails, or the disk cannot be
+/// converted.
+pub async fn bake_image<F>(
+    scenario: &Scenario,
+    vm_name: &str,
+    output: &Path,
+    agent_binary_x86_64: Vec<u8>,
+    agent_binary_aarch64: Vec<u8>,
+    mut progress: F,
+) -> Result<BakedImage, VmError>
+where
+    F: FnMut(&str),
+{
+    let vm = scenario
+        .vms
+        .iter()
+        .find(|vm| vm.name == vm_name)
+        .map(bake_definition)
+        .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+    let bake_scenario = Scenario {
+        name: format!("bake-{vm_name}"),
+        probes: HashMap::new(),
+        vms: vec![vm.clone()],
+        on_complete: Vec::new(),
+        questions: Vec::new(),
+        ..scenario.clone()
+    };
+
+    let dirs = IntarDirs::new()?;
+    dirs.ensure_dirs()?;
+    let config = IntarConfig::load(&dirs)?;
+    let image_cache = ImageCache::new(dirs.images_dir()).with_settings(&config.downloads);
+    let arch = detect_arch();
+    let source = bake_scenario
+        .images
+        .get(&vm.image)
+        .and_then(|image| image.source_for_arch(&arch))
+        .ok_or_else(|| {
+            VmError::Qemu(format!(
+                "No image source for architecture '{arch}' in image '{}'",
+                vm.image
+            ))
+        })?;
+    progress("Fetching base image");
+    image_cache.ensure_image(source).await?;
+
+    let mut runner = ScenarioRunner::new_with_dirs(
+        bake_scenario,
+        agent_binary_x86_64,
+        agent_binary_aarch64,
+        &dirs,
+    )?;
+    let provisioned = provision(&mut runner, &vm, &image_cache, &arch, &mut progress).await;
+    let disk = runner.vms.get(vm_name).map(|vm| vm.disk_path.clone());
+    let stopped = runner.stop().await;
+
+    let baked = match (provisioned.and(stopped), disk) {
+        (Ok(()), Some(disk)) => {
+            progress("Compressing disk");
+            compress_disk(&disk, output).await
+        }
+        (Ok(()), None) => Err(VmError::VmNotFound(vm_name.to_string())),
+        (Err(e), _) => Err(e),
+    };
+    if let Err(e) = runner.cleanup() {
+        warn!("Failed to remove bake run directory: {}", e);
+    }
+    baked?;
+
+    progress("Computing checksum");
+    let checksum = sha256_file(output)?;
+    Ok(BakedImage {
+        path: output.to_path_buf(),
+        arch: match arch.as_str() {
+            "x86_64" => "amd64".into(),
+            "aarch64" => "arm64".into(),
+            other => other.into(),
+        },
+        checksum: format!("sha256:{checksum}"),
+        size: std::fs::metadata(output)?.len(),
+    })
+}
+
+/// The parts of a VM that belong in a golden image: everything applied at first boot.
+fn bake_definition(vm: &VmDefinition) -> VmDefinition {
+    VmDefinition {
+        steps: vm
+            .steps
+            .iter()
+            .filter(|step| step.trigger == StepTrigger::Boot)
+            .cloned()
+            .collect(),
+        random_steps: Vec::new(),
+        probes: Vec::new(),
+        // Baked alone, so there is nothing to wait for.
+        wait_for: Vec::new(),
+        ..vm.clone()
+    }
+}
+
+async fn provision<F>(
+    runner: &mut ScenarioRunner,
+    vm: &VmDefinition,
+    image_cache: &ImageCache,
+    arch: &str,
+    progress: &mut F,
+) -> Result<(), VmError>
+where
+    F: FnMut(&str),
+{
+    runner.create_vm(vm, image_cache, arch)?;
+    runner.calibrate_boot_timeouts();
+
+    // The agent is started last in runcmd, so once it answers provisioning is done.
+    progress("Booting and provisioning");
+    runner.start_vms()?;
+    runner.wait_for_agents().await?;
+
+    progress("Cleaning up guest");
+    let instance = runner
+        .vms
+        .get_mut(&vm.name)
+        .ok_or_else(|| VmError::VmNotFound(vm.name.clone()))?;
+    let mut conn = try_connect(&instance.serial_socket, 3, 500).await?;
+    let output = conn.exec(BAKE_CLEANUP_SCRIPT).await?;
+    if output.exit_code != 0 {
+        return Err(VmError::Serial(format!(
+            "guest cleanup exited with status {}: {}",
+            output.exit_code,
+            output.stderr.trim()
+        )));
+    }
+    drop(conn);
+
+    progress("Shutting down");
+    instance.power_down(POWER_DOWN_TIMEOUT).await
+}
+
+/// Flatten the overlay and its base into one compressed qcow2.
+async fn compress_disk(disk: &Path, output: &Path) -> Result<(), VmError> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let result = tokio::process::Command::new("qemu-img")
+        .args([
+            "convert",
+            "-c",
+            "-O",
+            "qcow2",
+            path_to_str(disk)?,
+            path_to_str(output)?,
+        ])
+        .output()
+        .await
+        .map_err(|e| VmError::Qemu(format!("Failed to run qemu-img convert: {e}")))?;
+
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(VmError::Qemu(format!("qemu-img convert failed: {stderr}")));
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String, VmError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bake_definition_keeps_boot_provisioning_only() {
+        let scenario = Scenario::parse(
+            r#"
+scenario "bake" {
+  vm "web" {
+    image  = "ubuntu"
+    probes = ["nginx"]
+
+    cloud_init {
+      packages = ["nginx"]
+    }
+
+    step "configure" {
+      command { cmd = "systemctl enable nginx" }
+    }
+
+    step "later" {
+      trigger = "manual"
+      command { cmd = "true" }
+    }
+
+    random_one_of "fault" {
+      step "break-a" {
+        command { cmd = "true" }
+      }
+      step "break-b" {
+        command { cmd = "true" }
+      }
+    }
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let vm = bake_definition(&scenario.vms[0]);
+        let steps: Vec<&str> = vm.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(steps, vec!["configure"]);
+        assert!(vm.random_steps.is_empty());
+        assert!(vm.probes.is_empty());
+        assert_eq!(vm.cloud_init.unwrap().packages, vec!["nginx".to_string()]);
+    }
+
+    #[test]
+    fn test_image_block_parses_as_scenario_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("web.qcow2");
+        std::fs::write(&path, b"qcow2").unwrap();
+        let baked = BakedImage {
+            checksum: format!("sha256:{}", sha256_file(&path).unwrap()),
+            path,
+            arch: "amd64".into(),
+            size: 5,
+        };
+
+        let block = baked.image_block("web-golden", "https://example.com/web.qcow2");
+        let hcl = format!("scenario \"s\" {{\n{block}}}\n");
+        let scenario = Scenario::parse(&hcl).unwrap();
+        let source = scenario.images["web-golden"]
+            .source_for_arch("amd64")
+            .unwrap();
+        assert_eq!(source.url, "https://example.com/web.qcow2");
+        assert_eq!(source.checksum, baked.checksum);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    AuditLog, AuditOperation, HostSocket, RunState, VmError, load_run_scenario, set_balloon_target,
+};
+use intar_core::VmDefinition;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Smallest balloon target accepted; below this most distro images stop being usable.
+pub const MIN_MEMORY_MB: u32 = 256;
+
+/// A VM without SSH activity for this long may have memory reclaimed under host pressure.
+const IDLE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Host memory is under pressure below this share of available memory (percent).
+const PRESSURE_PERCENT: u64 = 10;
+
+/// Reclaimed memory is given back above this share of available memory (percent).
+const RELIEF_PERCENT: u64 = 25;
+
+/// Check that `memory_mb` is a valid balloon target for `vm`.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` if the target is below [`MIN_MEMORY_MB`] or above the VM's
+/// configured memory (the balloon cannot grow a guest past its boot size).
+pub fn validate_memory_target(vm: &VmDefinition, memory_mb: u32) -> Result<(), VmError> {
+    if memory_mb < MIN_MEMORY_MB || memory_mb > vm.memory {
+        return Err(VmError::NotPermitted(format!(
+            "memory for VM '{}' must be between {MIN_MEMORY_MB} and {} MB",
+            vm.name, vm.memory
+        )));
+    }
+    Ok(())
+}
+
+/// Change the memory of a VM in the run at `run_dir` (`intar vm scale`), recording the QMP
+/// command in the run's audit log.
+///
+/// # Errors
+/// Returns `VmError` if the VM is unknown, the target is invalid, or QMP fails.
+pub async fn scale_vm_memory(run_dir: &Path, vm_name: &str, memory_mb: u32) -> Result<(), VmError> {
+    let scenario = load_run_scenario(run_dir)?;
+    let vm = scenario
+        .vms
+        .iter()
+        .find(|vm| vm.name == vm_name)
+        .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+    validate_memory_target(vm, memory_mb)?;
+
+    let state = RunState::load(run_dir)?;
+    let info = state
+        .vms
+        .iter()
+        .find(|vm| vm.name == vm_name)
+        .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+
+    #[cfg(unix)]
+    let qmp = HostSocket::unix(run_dir.join(format!("{}-qmp.sock", info.name)));
+    #[cfg(windows)]
+    let qmp = HostSocket::tcp(
+        info.socket_ports
+            .first()
+            .copied()
+            .ok_or_else(|| VmError::Qmp(format!("No QMP port recorded for VM {vm_name}")))?,
+    );
+
+    set_balloon_target(&qmp, memory_mb).await?;
+
+    let mut audit = AuditLog::open(run_dir, state.audit.as_ref())?;
+    audit.record(
+        AuditOperation::QmpCommand,
+        format!("balloon {vm_name} {memory_mb} MB"),
+    )?;
+    // Reloaded so changes the TUI saved meanwhile are kept.
+    let mut state = RunState::load(run_dir)?;
+    state.audit = Some(audit.head());
+    state.save(run_dir)
+}
+
+/// Host RAM, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostMemory {
+    pub total: u64,
+    pub available: u64,
+}
+
+impl HostMemory {
+    /// Read host memory from `/proc/meminfo`. `None` on hosts without it.
+    #[must_use]
+    pub fn read() -> Option<Self> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let field = |name: &str| {
+            meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|rest| {
+                    rest.trim()
+                        .trim_end_matches("kB")
+                        .trim()
+                        .parse::<u64>()
+                        .ok()
+                })
+                .map(|kb| kb * 1024)
+        };
+        Some(Self {
+            total: field("MemTotal:")?,
+            available: field("MemAvailable:")?,
+        })
+    }
+
+    fn available_percent(self) -> u64 {
+        if self.total == 0 {
+            return 100;
+        }
+        self.available * 100 / self.total
+    }
+}
+
+/// Reclaims memory from idle VMs while the host is short on RAM and gives it back once the
+/// pressure is gone or the VM is used again.
+#[derive(Debug)]
+pub struct AutoBalloon {
+    since: Instant,
+    last_activity: HashMap<String, Instant>,
+    reduced: HashSet<String>,
+}
+
+impl AutoBalloon {
+    #[must_use]
+    pub fn new(now: Instant) -> Self {
+        Self {
+            since: now,
+            last_activity: HashMap::new(),
+            reduced: HashSet::new(),
+        }
+    }
+
+    /// Record SSH activity on `vm`.
+    pub fn note_activity(&mut self, vm: &str, at: Instant) {
+        self.last_activity.insert(vm.to_string(), at);
+    }
+
+    /// Balloon targets to apply now, as `(vm, memory_mb)`.
+    pub fn plan(
+        &mut self,
+        vms: &[VmDefinition],
+        host: HostMemory,
+        now: Instant,
+    ) -> Vec<(String, u32)> {
+        let percent = host.available_percent();
+        let mut changes = Vec::new();
+
+        for vm in vms {
+            let last = self
+                .last_activity
+                .get(&vm.name)
+                .copied()
+                .unwrap_or(self.since);
+            let idle = now.duration_since(last) >= IDLE_AFTER;
+            let reduced = self.reduced.contains(&vm.name);
+
+            if reduced && (!idle || percent >= RELIEF_PERCENT) {
+                self.reduced.remove(&vm.name);
+                changes.push((vm.name.clone(), vm.memory));
+            } else if !reduced && idle && percent < PRESSURE_PERCENT {
+                let target = (vm.memory / 2).max(MIN_MEMORY_MB);
+                if target < vm.memory {
+                    self.reduced.insert(vm.name.clone());
+                    changes.push((vm.name.clone(), target));
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intar_core::DiskIo;
+
+    fn vm(name: &str, memory: u32) -> VmDefinition {
+        VmDefinition {
+            name: name.into(),
+            cpu: 1,
+            memory,
+            disk: 10,
+            image: "ubuntu".into(),
+            cloud_init: None,
+            steps: Vec::new(),
+            random_steps: Vec::new(),
+            probes: Vec::new(),
+            ipv6: false,
+            track_changes: Vec::new(),
+            disk_io: DiskIo::default(),
+            users: Vec::new(),
+            ssh_password_auth: false,
+            console_autologin: None,
+            wait_for: Vec::new(),
+        }
+    }
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_auto_balloon_reclaims_idle_vms_under_pressure() {
+        let start = Instant::now();
+        let mut auto = AutoBalloon::new(start);
+        let vms = [vm("web", 2048), vm("db", 2048)];
+        let tight = HostMemory {
+            total: 16 * GIB,
+            available: GIB,
+        };
+        let later = start + IDLE_AFTER;
+        auto.note_activity("web", later);
+
+        assert_eq!(
+            auto.plan(&vms, tight, later),
+            vec![("db".to_string(), 1024)]
+        );
+        assert!(auto.plan(&vms, tight, later).is_empty());
+
+        let relaxed = HostMemory {
+            total: 16 * GIB,
+            available: 8 * GIB,
+        };
+        assert_eq!(
+            auto.plan(&vms, relaxed, later),
+            vec![("db".to_string(), 2048)]
+        );
+    }
+
+    #[test]
+    fn test_validate_memory_target_bounds() {
+        let vm = vm("web", 2048);
+        assert!(validate_memory_target(&vm, 1024).is_ok());
+        assert!(validate_memory_target(&vm, 4096).is_err());
+        assert!(validate_memory_target(&vm, 128).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use intar_core::ScenarioTimeouts;
+use std::time::{Duration, Instant};
+
+/// First boot (cloud-init, packages, agent start) of a small image on the reference host.
+const REFERENCE_BOOT: Duration = Duration::from_secs(90);
+
+/// Extra first-boot time per GiB of base image.
+const BOOT_PER_GIB: Duration = Duration::from_secs(20);
+
+/// Boot probes usually settle within this long on the reference host.
+const REFERENCE_BOOT_PROBES: Duration = Duration::from_secs(60);
+
+/// Timeouts allow this many times the expected duration before giving up.
+const TIMEOUT_MARGIN: u32 = 4;
+
+const MIN_AGENT_TIMEOUT: Duration = Duration::from_secs(180);
+const MAX_AGENT_TIMEOUT: Duration = Duration::from_secs(3600);
+const MIN_BOOT_PROBES_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_BOOT_PROBES_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// Guests run roughly this much slower without hardware virtualization (TCG).
+const EMULATION_SLOWDOWN: f64 = 5.0;
+
+/// Iterations of the benchmark loop, and how long they take on the reference host.
+const BENCH_ITERATIONS: u64 = 20_000_000;
+const BENCH_REFERENCE: Duration = Duration::from_millis(25);
+
+/// How much slower than the reference host this one boots VMs (`1.0` = reference).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HostSpeed {
+    pub slowdown: f64,
+}
+
+impl HostSpeed {
+    /// Time a short CPU benchmark and check for hardware acceleration. Takes a few tens of
+    /// milliseconds on a typical host.
+    #[must_use]
+    pub fn measure() -> Self {
+        let start = Instant::now();
+        let mut x: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..BENCH_ITERATIONS {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+        }
+        std::hint::black_box(x);
+        let cpu = start.elapsed().as_secs_f64() / BENCH_REFERENCE.as_secs_f64();
+
+        let accel = if hardware_acceleration_available() {
+            1.0
+        } else {
+            EMULATION_SLOWDOWN
+        };
+        Self {
+            slowdown: cpu.clamp(0.5, 4.0) * accel,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn hardware_acceleration_available() -> bool {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/kvm")
+        .is_ok()
+}
+
+// HVF and WHPX cannot be probed without starting QEMU; assume they are there.
+#[cfg(not(target_os = "linux"))]
+fn hardware_acceleration_available() -> bool {
+    true
+}
+
+/// How long to wait for each boot stage, and how long the boot is expected to take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootTimeouts {
+    /// Expected time until every agent answers; drives the boot ETA.
+    pub expected: Duration,
+    pub agent: Duration,
+    pub boot_probes: Duration,
+}
+
+impl Default for BootTimeouts {
+    fn default() -> Self {
+        Self {
+            expected: REFERENCE_BOOT,
+            agent: Duration::from_secs(600),
+            boot_probes: Duration::from_secs(300),
+        }
+    }
+}
+
+impl BootTimeouts {
+    /// Scale the reference boot to this host, the largest base image, and CPU contention
+    /// (`vcpus` across all VMs against the host's cores). Values set in the scenario's
+    /// `timeouts` block win.
+    #[must_use]
+    pub fn calibrate(
+        speed: HostSpeed,
+        largest_image_bytes: u64,
+        vcpus: u32,
+        host_cpus: u32,
+        overrides: &ScenarioTimeouts,
+    ) -> Self {
+        let image_mib = u32::try_from(largest_image_bytes / (1024 * 1024)).unwrap_or(u32::MAX);
+        let contention = (f64::from(vcpus) / f64::from(host_cpus.max(1))).max(1.0);
+        let slowdown = speed.slowdown * contention;
+
+        let expected = (REFERENCE_BOOT + BOOT_PER_GIB.mul_f64(f64::from(image_mib) / 1024.0))
+            .mul_f64(slowdown);
+        let agent = overrides.agent_secs.map_or_else(
+            || (expected * TIMEOUT_MARGIN).clamp(MIN_AGENT_TIMEOUT, MAX_AGENT_TIMEOUT),
+            Duration::from_secs,
+        );
+        let boot_probes = overrides.boot_probes_secs.map_or_else(
+            || {
+                (REFERENCE_BOOT_PROBES.mul_f64(slowdown) * TIMEOUT_MARGIN)
+                    .clamp(MIN_BOOT_PROBES_TIMEOUT, MAX_BOOT_PROBES_TIMEOUT)
+            },
+            Duration::from_secs,
+        );
+
+        Self {
+            expected: expected.min(agent),
+            agent,
+            boot_probes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_calibrate_scales_with_host_and_image() {
+        let reference = HostSpeed { slowdown: 1.0 };
+        let slow = HostSpeed { slowdown: 6.0 };
+        let none = ScenarioTimeouts::default();
+
+        let fast = BootTimeouts::calibrate(reference, GIB, 2, 8, &none);
+        assert_eq!(fast.expected, Duration::from_secs(110));
+        assert_eq!(fast.agent, Duration::from_secs(440));
+
+        let slow = BootTimeouts::calibrate(slow, 4 * GIB, 2, 8, &none);
+        assert!(slow.expected > fast.expected);
+        assert_eq!(slow.agent, MAX_AGENT_TIMEOUT);
+
+        let contended = BootTimeouts::calibrate(reference, GIB, 16, 8, &none);
+        assert_eq!(contended.expected, Duration::from_secs(220));
+    }
+
+    #[test]
+    fn test_calibrate_prefers_scenario_overrides() {
+        let overrides = ScenarioTimeouts {
+            agent_secs: Some(60),
+            boot_probes_secs: Some(30),
+            ttl_secs: None,
+        };
+        let timeouts = BootTimeouts::calibrate(HostSpeed { slowdown: 1.0 }, GIB, 1, 4, &overrides);
+        assert_eq!(timeouts.agent, Duration::from_secs(60));
+        assert_eq!(timeouts.boot_probes, Duration::from_secs(30));
+        assert_eq!(timeouts.expected, Duration::from_secs(60));
+    }
+}
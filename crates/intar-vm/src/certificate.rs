@@ -0,0 +1,201 @@
+use crate::audit::unix_ms;
+use crate::{LearnerProfile, RunProgress, ScenarioState, VmError, load_run_scenario};
+use intar_core::ProbePhase;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// What a completion certificate vouches for. The signature covers these fields, serialized
+/// as JSON in this order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateClaims {
+    pub scenario: String,
+    pub run: String,
+    pub learner: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_id: Option<String>,
+    pub objectives_passed: usize,
+    pub objectives_total: usize,
+    /// Seconds the learner spent solving, without boot and host sleep.
+    pub duration_secs: u64,
+    pub issued_at_ms: u64,
+}
+
+/// A completed run signed with an instructor's key (HMAC-SHA256), as written by
+/// `intar certificate`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Certificate {
+    #[serde(flatten)]
+    pub claims: CertificateClaims,
+    /// Hex HMAC-SHA256 of the claims.
+    pub signature: String,
+}
+
+impl Certificate {
+    /// Sign `claims` with `key`.
+    ///
+    /// # Errors
+    /// Returns `VmError::Json` if the claims cannot be serialized.
+    pub fn sign(claims: CertificateClaims, key: &[u8]) -> Result<Self, VmError> {
+        let signature = hex::encode(hmac_sha256(key, &serde_json::to_vec(&claims)?));
+        Ok(Self { claims, signature })
+    }
+
+    /// Whether the signature was made with `key` over these exact claims.
+    ///
+    /// # Errors
+    /// Returns `VmError::Json` if the claims cannot be serialized.
+    pub fn verify(&self, key: &[u8]) -> Result<bool, VmError> {
+        let expected = hmac_sha256(key, &serde_json::to_vec(&self.claims)?);
+        let Ok(actual) = hex::decode(&self.signature) else {
+            return Ok(false);
+        };
+        // Compare every byte so the time taken does not reveal how much matched.
+        let matches = actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(expected)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0;
+        Ok(matches)
+    }
+}
+
+/// The claims for the completed run at `run_dir`, read from its saved progress and scenario.
+/// The learner is `learner`, else the profile the run was started under, else the current
+/// user name.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` if the run has not completed or no learner is known, or
+/// `VmError` if its records cannot be read.
+pub fn run_certificate_claims(
+    run_dir: &Path,
+    learner: Option<&str>,
+) -> Result<CertificateClaims, VmError> {
+    let run = run_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| VmError::InvalidPath(run_dir.display().to_string()))?;
+    let progress = RunProgress::load(run_dir)?
+        .ok_or_else(|| VmError::NotPermitted(format!("run '{run}' never started")))?;
+    if progress.state != ScenarioState::Completed {
+        return Err(VmError::NotPermitted(format!(
+            "run '{run}' has not completed the scenario"
+        )));
+    }
+    let scenario = load_run_scenario(run_dir)?;
+    let profile = progress.learner.as_ref();
+    let learner = learner
+        .or_else(|| profile.and_then(LearnerProfile::identifier))
+        .map(str::to_string)
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .ok_or_else(|| {
+            VmError::NotPermitted("no learner known: pass --learner or run intar login".into())
+        })?;
+
+    let mut objectives_total = 0;
+    let mut objectives_passed = 0;
+    for vm in &scenario.vms {
+        for probe in &vm.probes {
+            if scenario
+                .probes
+                .get(probe)
+                .is_none_or(|def| def.phase != ProbePhase::Scenario)
+            {
+                continue;
+            }
+            objectives_total += 1;
+            if progress
+                .probe_results
+                .get(&vm.name)
+                .and_then(|results| results.get(probe))
+                .is_some_and(|result| result.passed)
+            {
+                objectives_passed += 1;
+            }
+        }
+    }
+
+    Ok(CertificateClaims {
+        scenario: scenario.name,
+        run: run.to_string(),
+        learner,
+        class_id: profile.and_then(|profile| profile.class_id.clone()),
+        objectives_passed,
+        objectives_total,
+        duration_secs: progress.stage_secs.get("run").copied().unwrap_or_default(),
+        issued_at_ms: unix_ms(),
+    })
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+
+    let mut block_key = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block_key.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block_key.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> CertificateClaims {
+        CertificateClaims {
+            scenario: "broken-nginx".into(),
+            run: "calm-otter-1234".into(),
+            learner: "ada@example.com".into(),
+            class_id: Some("linux-101".into()),
+            objectives_passed: 3,
+            objectives_total: 3,
+            duration_secs: 754,
+            issued_at_ms: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex::encode(hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_certificate_sign_and_verify() {
+        let certificate = Certificate::sign(claims(), b"instructor-key").unwrap();
+        assert!(certificate.verify(b"instructor-key").unwrap());
+        assert!(!certificate.verify(b"other-key").unwrap());
+
+        let json = serde_json::to_string(&certificate).unwrap();
+        let mut parsed: Certificate = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, certificate);
+
+        parsed.claims.objectives_passed = 4;
+        assert!(!parsed.verify(b"instructor-key").unwrap());
+
+        parsed.claims.objectives_passed = 3;
+        parsed.signature = "not hex".into();
+        assert!(!parsed.verify(b"instructor-key").unwrap());
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::sync::Mutex;
+
+const MIB: u64 = 1024 * 1024;
+
+/// How far saving or loading a checkpoint of every VM has got.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointProgress {
+    /// Checkpoint tag, e.g. `init`.
+    pub name: String,
+    /// Loading (for a reset) rather than saving.
+    pub loading: bool,
+    pub vms_done: usize,
+    pub vms_total: usize,
+    /// Guest state written so far, estimated from the growth of the overlay disks. Always zero
+    /// while loading, which QEMU does not report on.
+    pub bytes_done: u64,
+    /// Guest RAM of the VMs, the most a save writes.
+    pub bytes_total: u64,
+}
+
+impl CheckpointProgress {
+    /// Completed fraction, from the bytes written while saving and from the VMs done while
+    /// loading. Stays below 1 until every VM is done, since zero pages take no room.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.vms_total > 0 && self.vms_done == self.vms_total {
+            return 1.0;
+        }
+        let (done, total) = if self.loading || self.bytes_total == 0 {
+            let count = |n: usize| u64::try_from(n).unwrap_or(u64::MAX);
+            (count(self.vms_done), count(self.vms_total))
+        } else {
+            (self.bytes_done / MIB, self.bytes_total / MIB)
+        };
+        if total == 0 {
+            return 0.0;
+        }
+        let to_f64 = |n: u64| f64::from(u32::try_from(n).unwrap_or(u32::MAX));
+        (to_f64(done.min(total)) / to_f64(total)).min(0.99)
+    }
+}
+
+impl fmt::Display for CheckpointProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.loading { "Restoring" } else { "Saving" };
+        write!(
+            f,
+            "{verb} checkpoint '{}': {}/{} VMs",
+            self.name, self.vms_done, self.vms_total
+        )?;
+        if !self.loading && self.bytes_total > 0 {
+            write!(
+                f,
+                ", {} of {} MB",
+                self.bytes_done / MIB,
+                self.bytes_total / MIB
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Combines the progress of the VMs' checkpoint jobs, which run concurrently, and reports the
+/// total after every change.
+pub(crate) struct CheckpointTracker<F> {
+    state: Mutex<(CheckpointProgress, Vec<u64>, F)>,
+}
+
+impl<F: FnMut(&CheckpointProgress)> CheckpointTracker<F> {
+    /// Track a checkpoint of VMs with `memory` bytes of RAM each.
+    pub(crate) fn new(name: &str, loading: bool, memory: &[u64], on_progress: F) -> Self {
+        let progress = CheckpointProgress {
+            name: name.to_string(),
+            loading,
+            vms_done: 0,
+            vms_total: memory.len(),
+            bytes_done: 0,
+            bytes_total: memory.iter().sum(),
+        };
+        Self {
+            state: Mutex::new((progress, vec![0; memory.len()], on_progress)),
+        }
+    }
+
+    /// Note that VM `index` has written `bytes` so far.
+    pub(crate) fn written(&self, index: usize, bytes: u64) {
+        self.update(|progress, written| {
+            if let Some(slot) = written.get_mut(index) {
+                *slot = bytes;
+            }
+            progress.bytes_done = written.iter().sum::<u64>().min(progress.bytes_total);
+        });
+    }
+
+    /// Note that one more VM's checkpoint is complete.
+    pub(crate) fn vm_done(&self) {
+        self.update(|progress, _| progress.vms_done += 1);
+    }
+
+    fn update(&self, change: impl FnOnce(&mut CheckpointProgress, &mut Vec<u64>)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let (progress, written, on_progress) = &mut *state;
+        change(progress, written);
+        on_progress(progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_tracker_sums_vms() {
+        let mut reports = Vec::new();
+        {
+            let tracker =
+                CheckpointTracker::new("init", false, &[1024 * MIB, 3072 * MIB], |progress| {
+                    reports.push(progress.clone());
+                });
+            tracker.written(0, 512 * MIB);
+            tracker.written(1, 1536 * MIB);
+            tracker.written(1, 9999 * MIB);
+            tracker.vm_done();
+        }
+
+        assert_eq!(reports[1].bytes_done, 2048 * MIB);
+        assert!((reports[1].fraction() - 0.5).abs() < f64::EPSILON);
+        assert_eq!(
+            reports[1].to_string(),
+            "Saving checkpoint 'init': 0/2 VMs, 2048 of 4096 MB"
+        );
+        // Never past the VMs' RAM, nor done before every VM is.
+        assert_eq!(reports[2].bytes_done, 4096 * MIB);
+        assert!(reports[3].fraction() < 1.0);
+    }
+
+    #[test]
+    fn test_loading_progress_counts_vms() {
+        let progress = CheckpointProgress {
+            name: "init".into(),
+            loading: true,
+            vms_done: 1,
+            vms_total: 4,
+            bytes_done: 0,
+            bytes_total: 4096 * MIB,
+        };
+        assert!((progress.fraction() - 0.25).abs() < f64::EPSILON);
+        assert_eq!(progress.to_string(), "Restoring checkpoint 'init': 1/4 VMs");
+        let done = CheckpointProgress {
+            vms_done: 4,
+            ..progress
+        };
+        assert!((done.fraction() - 1.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,713 @@
+use crate::{SshHost, VmError, tunnel_args};
+use intar_core::{K3S_API_PORT, Scenario};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// Where intar-agent answers probe requests on a guest without a probe port.
+const GUEST_AGENT_SOCKET: &str = "/run/intar/agent.sock";
+
+/// Where intar-agent hands recorded sessions to the host on a guest without an actions port.
+const GUEST_ACTIONS_SOCKET: &str = "/run/intar/actions-port.sock";
+
+/// How long the link waits before asking the provider, ssh, or the tunnel again.
+const LINK_RETRY: Duration = Duration::from_secs(5);
+
+/// Name an instance's host keys are listed under in its `known_hosts`, whatever its address.
+const HOST_KEY_ALIAS: &str = "intar-cloud-instance";
+
+/// Lines around the host public keys cloud-init prints to the console.
+const CONSOLE_KEYS_BEGIN: &str = "-----BEGIN SSH HOST KEY KEYS-----";
+const CONSOLE_KEYS_END: &str = "-----END SSH HOST KEY KEYS-----";
+
+/// Installs the agent from stdin unless cloud-init's copy is already there, then (re)starts it.
+const AGENT_INSTALL_SCRIPT: &str = "if [ -x /usr/local/bin/intar-agent ]; then cat >/dev/null; \
+     else sudo install -m 0755 /dev/stdin /usr/local/bin/intar-agent; fi && \
+     sudo systemctl daemon-reload && sudo systemctl reset-failed intar-agent; \
+     sudo systemctl restart intar-agent";
+
+/// The cloud a `--backend cloud` run provisions its VMs on, through the provider's own CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudProvider {
+    /// Amazon EC2, through `aws`.
+    Aws,
+    /// Google Compute Engine, through `gcloud`.
+    Gcp,
+}
+
+impl CloudProvider {
+    fn cli(self) -> &'static str {
+        match self {
+            Self::Aws => "aws",
+            Self::Gcp => "gcloud",
+        }
+    }
+}
+
+impl fmt::Display for CloudProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Aws => "aws",
+            Self::Gcp => "gcp",
+        })
+    }
+}
+
+/// `cloud` in `config.yaml`: where `intar start --backend cloud` provisions instances. The
+/// provider's CLI must be installed and logged in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloudSettings {
+    pub provider: Option<CloudProvider>,
+    /// AWS region, e.g. `eu-central-1`.
+    pub region: Option<String>,
+    /// GCP zone, e.g. `europe-west3-a`.
+    pub zone: Option<String>,
+    /// GCP project; `gcloud`'s default when unset.
+    pub project: Option<String>,
+    /// Instance type (AWS) or machine type (GCP); the provider default is small.
+    pub machine_type: Option<String>,
+    /// Cloud image for each scenario image: an AMI ID on AWS, `<image project>/<image family>`
+    /// on GCP.
+    pub images: BTreeMap<String, String>,
+    /// AWS security group the instances join, which should admit only SSH from this host.
+    /// Required on AWS, as the VPC's default group may admit anything.
+    pub security_group: Option<String>,
+    /// CPU architecture of the instances, which picks the agent build.
+    pub arch: String,
+}
+
+impl Default for CloudSettings {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            region: None,
+            zone: None,
+            project: None,
+            machine_type: None,
+            images: BTreeMap::new(),
+            security_group: None,
+            arch: "x86_64".to_string(),
+        }
+    }
+}
+
+impl CloudSettings {
+    /// Check that instances for `scenario` can be launched: the provider and its location (and
+    /// on AWS the security group) are set, every VM's image has a cloud image, and the
+    /// provider's CLI runs.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` naming what is missing.
+    pub fn check(&self, scenario: &Scenario) -> Result<(), VmError> {
+        let missing = |what: &str| {
+            VmError::NotPermitted(format!("--backend cloud needs {what} in config.yaml"))
+        };
+        let provider = self.provider.ok_or_else(|| missing("cloud.provider"))?;
+        self.location()?;
+        if provider == CloudProvider::Aws && self.security_group.is_none() {
+            return Err(missing("cloud.security_group"));
+        }
+        for vm in &scenario.vms {
+            if !self.images.contains_key(&vm.image) {
+                return Err(missing(&format!("cloud.images.{}", vm.image)));
+            }
+        }
+        let runs = Command::new(provider.cli())
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !runs {
+            return Err(VmError::NotPermitted(format!(
+                "--backend cloud with provider {provider} needs the `{}` CLI",
+                provider.cli()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Launch instance `name` from the cloud image of scenario image `image`, booting it with
+    /// the cloud-init user-data in `user_data`.
+    ///
+    /// # Errors
+    /// Returns `VmError::Cloud` with the CLI's message if the instance cannot be launched.
+    pub fn launch(
+        &self,
+        name: &str,
+        image: &str,
+        user_data: &Path,
+    ) -> Result<CloudInstance, VmError> {
+        let provider = self
+            .provider
+            .ok_or_else(|| VmError::Cloud("No cloud provider configured".into()))?;
+        let location = self.location()?;
+        let cloud_image = self
+            .images
+            .get(image)
+            .ok_or_else(|| VmError::Cloud(format!("No cloud image for image '{image}'")))?;
+        let mut instance = CloudInstance {
+            provider,
+            location: location.to_string(),
+            project: self.project.clone(),
+            id: name.to_string(),
+            address: None,
+        };
+        match provider {
+            CloudProvider::Aws => {
+                let args = [
+                    "ec2".to_string(),
+                    "run-instances".into(),
+                    "--image-id".into(),
+                    cloud_image.clone(),
+                    "--instance-type".into(),
+                    self.machine_type
+                        .clone()
+                        .unwrap_or_else(|| "t3.medium".into()),
+                    "--count".into(),
+                    "1".into(),
+                    "--user-data".into(),
+                    format!("file://{}", user_data.display()),
+                    "--tag-specifications".into(),
+                    format!("ResourceType=instance,Tags=[{{Key=Name,Value={name}}}]"),
+                    "--security-group-ids".into(),
+                    self.security_group
+                        .clone()
+                        .ok_or_else(|| VmError::Cloud("No AWS security group configured".into()))?,
+                    "--query".into(),
+                    "Instances[0].InstanceId".into(),
+                ];
+                instance.id = instance.cli(&args)?;
+            }
+            CloudProvider::Gcp => {
+                let (image_project, image_family) =
+                    cloud_image.split_once('/').ok_or_else(|| {
+                        VmError::Cloud(format!(
+                            "GCP image '{cloud_image}' is not <image project>/<image family>"
+                        ))
+                    })?;
+                let args = [
+                    "compute".to_string(),
+                    "instances".into(),
+                    "create".into(),
+                    name.to_string(),
+                    "--machine-type".into(),
+                    self.machine_type
+                        .clone()
+                        .unwrap_or_else(|| "e2-medium".into()),
+                    "--image-project".into(),
+                    image_project.to_string(),
+                    "--image-family".into(),
+                    image_family.to_string(),
+                    "--metadata-from-file".into(),
+                    format!("user-data={}", user_data.display()),
+                    "--format".into(),
+                    "value(networkInterfaces[0].accessConfigs[0].natIP)".into(),
+                ];
+                instance.address = Some(instance.cli(&args)?).filter(|ip| !ip.is_empty());
+            }
+        }
+        Ok(instance)
+    }
+
+    fn location(&self) -> Result<&str, VmError> {
+        let (location, key) = match self.provider {
+            Some(CloudProvider::Aws) => (&self.region, "cloud.region"),
+            Some(CloudProvider::Gcp) => (&self.zone, "cloud.zone"),
+            None => return Err(VmError::Cloud("No cloud provider configured".into())),
+        };
+        location.as_deref().ok_or_else(|| {
+            VmError::NotPermitted(format!("--backend cloud needs {key} in config.yaml"))
+        })
+    }
+}
+
+/// A launched instance, recorded in the run directory so later intar processes can reach and
+/// terminate it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloudInstance {
+    pub provider: CloudProvider,
+    /// AWS region or GCP zone.
+    pub location: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// EC2 instance ID or GCE instance name.
+    pub id: String,
+    /// Public IP address, once the provider has assigned one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+impl CloudInstance {
+    /// Load the record at `path`.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, VmError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Write the record to `path`.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), VmError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Terminate the instance; its disk goes with it.
+    ///
+    /// # Errors
+    /// Returns `VmError::Cloud` with the CLI's message if the provider refuses.
+    pub fn terminate(&self) -> Result<(), VmError> {
+        let args: Vec<String> = match self.provider {
+            CloudProvider::Aws => vec![
+                "ec2".into(),
+                "terminate-instances".into(),
+                "--instance-ids".into(),
+                self.id.clone(),
+            ],
+            CloudProvider::Gcp => vec![
+                "compute".into(),
+                "instances".into(),
+                "delete".into(),
+                self.id.clone(),
+                "--quiet".into(),
+            ],
+        };
+        self.cli(&args).map(|_| ())
+    }
+
+    /// Ask the provider for the instance's public IP address.
+    async fn find_address(&self) -> Result<Option<String>, VmError> {
+        let args: Vec<String> = match self.provider {
+            CloudProvider::Aws => vec![
+                "ec2".into(),
+                "describe-instances".into(),
+                "--instance-ids".into(),
+                self.id.clone(),
+                "--query".into(),
+                "Reservations[0].Instances[0].PublicIpAddress".into(),
+            ],
+            CloudProvider::Gcp => vec![
+                "compute".into(),
+                "instances".into(),
+                "describe".into(),
+                self.id.clone(),
+                "--format".into(),
+                "value(networkInterfaces[0].accessConfigs[0].natIP)".into(),
+            ],
+        };
+        let output = tokio::process::Command::new(self.provider.cli())
+            .args(self.cli_args(&args))
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| VmError::Cloud(format!("Failed to run {}: {e}", self.provider.cli())))?;
+        let address = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(
+            (output.status.success() && !address.is_empty() && address != "None")
+                .then_some(address),
+        )
+    }
+
+    /// The SSH host keys cloud-init printed to the instance's console, read through the
+    /// provider's API; empty until cloud-init has printed them.
+    async fn console_host_keys(&self) -> Result<Vec<String>, VmError> {
+        let args: Vec<String> = match self.provider {
+            CloudProvider::Aws => vec![
+                "ec2".into(),
+                "get-console-output".into(),
+                "--instance-id".into(),
+                self.id.clone(),
+                "--latest".into(),
+                "--query".into(),
+                "Output".into(),
+            ],
+            CloudProvider::Gcp => vec![
+                "compute".into(),
+                "instances".into(),
+                "get-serial-port-output".into(),
+                self.id.clone(),
+            ],
+        };
+        let output = tokio::process::Command::new(self.provider.cli())
+            .args(self.cli_args(&args))
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| VmError::Cloud(format!("Failed to run {}: {e}", self.provider.cli())))?;
+        if !output.status.success() {
+            return Err(VmError::Cloud(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(console_host_keys(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// `args` plus the location, project, and output options the provider's CLI needs.
+    fn cli_args(&self, args: &[String]) -> Vec<String> {
+        let mut args = args.to_vec();
+        match self.provider {
+            CloudProvider::Aws => {
+                args.extend(["--region".into(), self.location.clone()]);
+                args.extend(["--output".into(), "text".into()]);
+            }
+            CloudProvider::Gcp => {
+                args.extend(["--zone".into(), self.location.clone()]);
+                if let Some(project) = &self.project {
+                    args.extend(["--project".into(), project.clone()]);
+                }
+            }
+        }
+        args
+    }
+
+    fn cli(&self, args: &[String]) -> Result<String, VmError> {
+        let output = Command::new(self.provider.cli())
+            .args(self.cli_args(args))
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| VmError::Cloud(format!("Failed to run {}: {e}", self.provider.cli())))?;
+        if !output.status.success() {
+            return Err(VmError::Cloud(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// A VM run as a cloud instance (`--backend cloud`).
+#[derive(Clone)]
+pub struct CloudVm {
+    pub settings: CloudSettings,
+    /// Instance name, `intar-<run>-<vm>`.
+    pub name: String,
+    /// Scenario image the instance boots.
+    pub image: String,
+    /// Agent binary for the instances' architecture, installed over ssh since it does not fit
+    /// into user-data.
+    pub agent: Vec<u8>,
+    /// The run's SSH key.
+    pub key: PathBuf,
+}
+
+impl CloudVm {
+    #[must_use]
+    pub fn new(
+        settings: CloudSettings,
+        run_id: &str,
+        vm: &str,
+        image: &str,
+        agent: Vec<u8>,
+        key: PathBuf,
+    ) -> Self {
+        Self {
+            settings,
+            name: format!("intar-{run_id}-{vm}"),
+            image: image.to_string(),
+            agent,
+            key,
+        }
+    }
+}
+
+/// What keeps an instance reachable like a local VM: an ssh tunnel from the VM's SSH and API
+/// ports and its serial and actions sockets to the instance.
+pub(crate) struct CloudLink {
+    pub(crate) record: PathBuf,
+    pub(crate) key: PathBuf,
+    pub(crate) known_hosts: PathBuf,
+    pub(crate) agent: Vec<u8>,
+    pub(crate) forwards: Vec<String>,
+}
+
+impl CloudLink {
+    /// Keep the tunnel up until the task is aborted: wait for the instance's address and host
+    /// keys, install the agent, and reopen the tunnel whenever ssh exits.
+    pub(crate) async fn hold(self) {
+        loop {
+            let host = match self.host().await {
+                Ok(Some(host)) => host,
+                Ok(None) => {
+                    tokio::time::sleep(LINK_RETRY).await;
+                    continue;
+                }
+                Err(e) => {
+                    warn!(
+                        "Cloud instance in {} is unusable: {}",
+                        self.record.display(),
+                        e
+                    );
+                    return;
+                }
+            };
+            if let Err(e) = self.pin_host_keys().await {
+                info!("Waiting for the host keys of {}: {}", host.destination, e);
+                tokio::time::sleep(LINK_RETRY).await;
+                continue;
+            }
+            if let Err(e) = self.install_agent(&host).await {
+                info!("Waiting for {}: {}", host.destination, e);
+                tokio::time::sleep(LINK_RETRY).await;
+                continue;
+            }
+            let status = self
+                .ssh(&host)
+                .args(tunnel_args(&self.forwards))
+                .arg(&host.destination)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .status()
+                .await;
+            warn!("ssh tunnel to {} ended: {:?}", host.destination, status);
+            tokio::time::sleep(LINK_RETRY).await;
+        }
+    }
+
+    /// The instance as an ssh host, once it has an address.
+    async fn host(&self) -> Result<Option<SshHost>, VmError> {
+        let mut instance = CloudInstance::load(&self.record)?;
+        if instance.address.is_none() {
+            instance.address = instance.find_address().await?;
+            if instance.address.is_some() {
+                instance.save(&self.record)?;
+            }
+        }
+        Ok(instance.address.map(|address| SshHost {
+            destination: format!("user@{address}"),
+            port: None,
+        }))
+    }
+
+    /// Record the host keys the instance printed to its console in [`CloudLink::known_hosts`],
+    /// unless they are there already.
+    async fn pin_host_keys(&self) -> Result<(), VmError> {
+        let pinned = std::fs::read_to_string(&self.known_hosts).is_ok_and(|content| {
+            content
+                .lines()
+                .any(|line| line.split_whitespace().next() == Some(HOST_KEY_ALIAS))
+        });
+        if pinned {
+            return Ok(());
+        }
+        let keys = CloudInstance::load(&self.record)?
+            .console_host_keys()
+            .await?;
+        if keys.is_empty() {
+            return Err(VmError::Cloud(
+                "cloud-init has not printed them to the console yet".into(),
+            ));
+        }
+        let content: String = keys
+            .iter()
+            .map(|key| format!("{HOST_KEY_ALIAS} {key}\n"))
+            .collect();
+        std::fs::write(&self.known_hosts, content)?;
+        Ok(())
+    }
+
+    async fn install_agent(&self, host: &SshHost) -> Result<(), VmError> {
+        let mut ssh = self
+            .ssh(host)
+            .arg(&host.destination)
+            .arg(AGENT_INSTALL_SCRIPT)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| VmError::Cloud(format!("Failed to run ssh: {e}")))?;
+        if let Some(mut stdin) = ssh.stdin.take() {
+            // ssh exits early while the instance still refuses connections.
+            stdin.write_all(&self.agent).await.ok();
+        }
+        let output = ssh.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(VmError::Cloud(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `ssh` with the run's key, accepting only the host keys [`CloudLink::pin_host_keys`]
+    /// recorded.
+    fn ssh(&self, host: &SshHost) -> tokio::process::Command {
+        let mut ssh = host.command();
+        ssh.arg("-i").arg(&self.key).args([
+            "-o",
+            "StrictHostKeyChecking=yes",
+            "-o",
+            "LogLevel=ERROR",
+        ]);
+        ssh.arg("-o").arg(format!("HostKeyAlias={HOST_KEY_ALIAS}"));
+        // Quoted, as ssh splits the option's value into several files at spaces.
+        ssh.arg("-o").arg(format!(
+            "UserKnownHostsFile=\"{}\"",
+            self.known_hosts.display()
+        ));
+        ssh
+    }
+}
+
+/// The `<type> <base64>` host keys in the last block cloud-init printed between
+/// [`CONSOLE_KEYS_BEGIN`] and [`CONSOLE_KEYS_END`] in `console`, which may prefix each line.
+fn console_host_keys(console: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut block: Option<Vec<String>> = None;
+    for line in console.lines() {
+        if line.contains(CONSOLE_KEYS_BEGIN) {
+            block = Some(Vec::new());
+        } else if line.contains(CONSOLE_KEYS_END) {
+            if let Some(found) = block.take() {
+                keys = found;
+            }
+        } else if let Some(found) = block.as_mut() {
+            let mut fields = line
+                .split_whitespace()
+                .skip_while(|field| !field.starts_with("ssh-") && !field.starts_with("ecdsa-"));
+            if let (Some(kind), Some(key)) = (fields.next(), fields.next()) {
+                found.push(format!("{kind} {key}"));
+            }
+        }
+    }
+    keys
+}
+
+/// `-L` forwards that make an instance look like a local VM: the SSH and API ports on this
+/// host, and the serial and actions sockets the runner connects to.
+pub(crate) fn cloud_forwards(
+    ssh_port: u16,
+    api_port: Option<u16>,
+    serial: &Path,
+    actions: &Path,
+) -> Vec<String> {
+    let mut forwards = vec![format!("{ssh_port}:127.0.0.1:22")];
+    if let Some(api_port) = api_port {
+        forwards.push(format!("{api_port}:127.0.0.1:{K3S_API_PORT}"));
+    }
+    forwards.push(format!("{}:{GUEST_AGENT_SOCKET}", serial.display()));
+    forwards.push(format!("{}:{GUEST_ACTIONS_SOCKET}", actions.display()));
+    forwards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cloud_forwards() {
+        let forwards = cloud_forwards(
+            2222,
+            Some(6500),
+            Path::new("/runs/a/web-serial.sock"),
+            Path::new("/runs/a/web-actions.sock"),
+        );
+        assert_eq!(
+            forwards,
+            [
+                "2222:127.0.0.1:22",
+                "6500:127.0.0.1:6443",
+                "/runs/a/web-serial.sock:/run/intar/agent.sock",
+                "/runs/a/web-actions.sock:/run/intar/actions-port.sock",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cloud_cli_args() {
+        let instance = CloudInstance {
+            provider: CloudProvider::Gcp,
+            location: "europe-west3-a".into(),
+            project: Some("labs".into()),
+            id: "intar-calm-otter-web".into(),
+            address: None,
+        };
+        assert_eq!(
+            instance.cli_args(&["compute".into()]),
+            ["compute", "--zone", "europe-west3-a", "--project", "labs"]
+        );
+
+        let aws = CloudInstance {
+            provider: CloudProvider::Aws,
+            location: "eu-central-1".into(),
+            project: None,
+            ..instance
+        };
+        assert_eq!(
+            aws.cli_args(&["ec2".into()]),
+            ["ec2", "--region", "eu-central-1", "--output", "text"]
+        );
+    }
+
+    #[test]
+    fn test_cloud_settings_yaml() {
+        let settings: CloudSettings = serde_yaml::from_str(
+            "provider: aws\nregion: eu-central-1\nimages:\n  ubuntu: ami-0123456789abcdef0\n",
+        )
+        .unwrap();
+        assert_eq!(settings.provider, Some(CloudProvider::Aws));
+        assert_eq!(settings.location().unwrap(), "eu-central-1");
+        assert_eq!(settings.arch, "x86_64");
+        assert!(
+            CloudSettings {
+                region: None,
+                ..settings
+            }
+            .location()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_console_host_keys() {
+        let console = "\
+[   42.1] cloud-init[812]: Cloud-init v. 24.1 running 'modules:final'
+-----BEGIN SSH HOST KEY KEYS-----
+ecdsa-sha2-nistp256 AAAAE2VjZHNh root@ip-10-0-0-5
+ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOld root@ip-10-0-0-5
+-----END SSH HOST KEY KEYS-----
+ec2: -----BEGIN SSH HOST KEY KEYS-----
+ec2: ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINew root@ip-10-0-0-5
+ec2: -----END SSH HOST KEY KEYS-----
+-----BEGIN SSH HOST KEY KEYS-----
+ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAITruncated
+";
+        assert_eq!(
+            console_host_keys(console),
+            ["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINew"]
+        );
+        assert!(console_host_keys("login: ").is_empty());
+    }
+
+    #[test]
+    fn test_aws_needs_security_group() {
+        let settings: CloudSettings = serde_yaml::from_str(
+            "provider: aws\nregion: eu-central-1\nimages:\n  ubuntu: ami-0123456789abcdef0\n",
+        )
+        .unwrap();
+        let scenario = Scenario::parse(
+            r#"
+scenario "lab" {
+  vm "web" {
+    image = "ubuntu"
+  }
+}
+"#,
+        )
+        .unwrap();
+        let err = settings.check(&scenario).unwrap_err().to_string();
+        assert!(err.contains("cloud.security_group"), "{err}");
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{
+    CloudSettings, DownloadRate, IntarDirs, LanBackend, LibvirtSettings, VmError, WorkspaceSettings,
+};
+use intar_core::{Ipv4Subnet, Scenario};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// User configuration file inside the intar config directory.
+pub const CONFIG_FILE: &str = "config.yaml";
+
+/// User configuration (`<config dir>/intar/config.yaml`). A missing or empty file means defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IntarConfig {
+    #[serde(default)]
+    pub quota: RunQuota,
+    /// Pause a run's VMs after this many minutes without SSH activity or probe status changes.
+    #[serde(default)]
+    pub idle_suspend_minutes: Option<u32>,
+    /// Boot at most this many VMs at once, in waves, unless `intar start --boot-concurrency`
+    /// says otherwise.
+    #[serde(default)]
+    pub boot_concurrency: Option<u32>,
+    #[serde(default)]
+    pub network: NetworkDefaults,
+    #[serde(default)]
+    pub downloads: DownloadSettings,
+    /// Interface language, e.g. `de`, when it should differ from the system locale.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Layout of `intar workspace`.
+    #[serde(default)]
+    pub workspace: WorkspaceSettings,
+    /// Connection of `intar start --backend libvirt`.
+    #[serde(default)]
+    pub libvirt: LibvirtSettings,
+    /// Where `intar start --backend cloud` provisions instances.
+    #[serde(default)]
+    pub cloud: CloudSettings,
+    /// What scenarios may run on this host rather than in their VMs.
+    #[serde(default)]
+    pub host_execution: HostExecution,
+}
+
+impl IntarConfig {
+    #[must_use]
+    pub fn path(dirs: &IntarDirs) -> PathBuf {
+        dirs.config.join(CONFIG_FILE)
+    }
+
+    /// Idle time after which a run is suspended; `None` (or 0 minutes) never suspends.
+    #[must_use]
+    pub fn idle_suspend_after(&self) -> Option<Duration> {
+        self.idle_suspend_minutes
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+    }
+
+    /// Load the configuration from the intar config directory.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the file exists but cannot be read or parsed.
+    pub fn load(dirs: &IntarDirs) -> Result<Self, VmError> {
+        Self::load_from(&Self::path(dirs))
+    }
+
+    /// Load the configuration from `path`.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the file exists but cannot be read or parsed.
+    pub fn load_from(path: &Path) -> Result<Self, VmError> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_str(&content)?)
+    }
+}
+
+/// Guest subnets used by scenarios that do not set their own, e.g. to stay clear of a VPN's
+/// routes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkDefaults {
+    pub management_subnet: Option<Ipv4Subnet>,
+    pub lan_subnet: Option<Ipv4Subnet>,
+    /// What carries the shared LAN of new runs.
+    #[serde(default)]
+    pub lan_backend: LanBackend,
+}
+
+impl NetworkDefaults {
+    /// Fill the subnets `scenario` leaves unset.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if the resulting management and LAN subnets overlap.
+    pub fn apply(&self, scenario: &mut Scenario) -> Result<(), VmError> {
+        let network = &mut scenario.network;
+        network.management_subnet = network.management_subnet.or(self.management_subnet);
+        network.lan_subnet = network.lan_subnet.or(self.lan_subnet);
+        let (management, lan) = (network.management(), network.lan());
+        if management.overlaps(&lan) {
+            return Err(VmError::NotPermitted(format!(
+                "management subnet {management} overlaps shared LAN subnet {lan}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How base images are fetched, e.g. to share a classroom's uplink.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownloadSettings {
+    /// Most each image download may use of the uplink, e.g. `10MB/s`.
+    pub limit: Option<DownloadRate>,
+    /// Base URLs of `intar images serve` on other hosts, asked for an image before its own
+    /// URLs, e.g. `http://192.168.1.20:8470`.
+    #[serde(default)]
+    pub peers: Vec<String>,
+}
+
+/// Scenario code that runs on the host: `on_complete` host commands and webhooks, and
+/// `action "external"` providers. Refused unless allowed here or with
+/// `intar start --allow-host-hooks`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostExecution {
+    /// Let every run use them, as if started with `--allow-host-hooks`.
+    #[serde(default)]
+    pub allow: bool,
+    /// `intar-provider-<name>` executables scenarios may run, beside
+    /// [`BUILTIN_PROVIDERS`](crate::BUILTIN_PROVIDERS).
+    #[serde(default)]
+    pub providers: Vec<String>,
+}
+
+/// Most a single run may request, summed over its VMs. Unset limits are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunQuota {
+    pub max_memory_mb: Option<u32>,
+    pub max_cpus: Option<u32>,
+    pub max_disk_gb: Option<u32>,
+    #[serde(default)]
+    pub on_exceed: QuotaAction,
+}
+
+/// What `intar start` does with a scenario over quota.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaAction {
+    /// Refuse to start it.
+    #[default]
+    Reject,
+    /// Ask on the terminal whether to start anyway; refuse when there is no terminal.
+    Prompt,
+}
+
+impl RunQuota {
+    /// Every limit `scenario` exceeds, e.g. `memory: 65536 MB requested, limit 8192 MB`.
+    #[must_use]
+    pub fn violations(&self, scenario: &Scenario) -> Vec<String> {
+        let total = |field: fn(&intar_core::VmDefinition) -> u32| -> u64 {
+            scenario.vms.iter().map(|vm| u64::from(field(vm))).sum()
+        };
+        [
+            ("memory", " MB", self.max_memory_mb, total(|vm| vm.memory)),
+            ("CPUs", "", self.max_cpus, total(|vm| vm.cpu)),
+            ("disk", " GB", self.max_disk_gb, total(|vm| vm.disk)),
+        ]
+        .into_iter()
+        .filter_map(|(resource, unit, limit, requested)| {
+            let limit = limit?;
+            (requested > u64::from(limit))
+                .then(|| format!("{resource}: {requested}{unit} requested, limit {limit}{unit}"))
+        })
+        .collect()
+    }
+
+    /// Fail if `scenario` exceeds any limit.
+    ///
+    /// # Errors
+    /// Returns `VmError::QuotaExceeded` listing every exceeded limit.
+    pub fn check(&self, scenario: &Scenario) -> Result<(), VmError> {
+        let violations = self.violations(scenario);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(VmError::QuotaExceeded(format!(
+                "scenario '{}' requests more than allowed ({})",
+                scenario.name,
+                violations.join("; ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario() -> Scenario {
+        Scenario::parse(
+            r#"
+scenario "big" {
+  vm "a" {
+    image  = "ubuntu"
+    cpu    = 4
+    memory = 32768
+  }
+
+  vm "b" {
+    image  = "ubuntu"
+    cpu    = 4
+    memory = 32768
+    disk   = 20
+  }
+}
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        assert_eq!(
+            IntarConfig::load_from(&path).unwrap(),
+            IntarConfig::default()
+        );
+
+        std::fs::write(
+            &path,
+            "idle_suspend_minutes: 30\nquota:\n  max_memory_mb: 8192\n  max_cpus: 8\n  on_exceed: prompt\n",
+        )
+        .unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert_eq!(
+            config.quota,
+            RunQuota {
+                max_memory_mb: Some(8192),
+                max_cpus: Some(8),
+                max_disk_gb: None,
+                on_exceed: QuotaAction::Prompt,
+            }
+        );
+        assert_eq!(config.idle_suspend_after(), Some(Duration::from_secs(1800)));
+        assert_eq!(config.language, None);
+
+        std::fs::write(&path, "language: de\n").unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert_eq!(config.language.as_deref(), Some("de"));
+        assert_eq!(config.boot_concurrency, None);
+
+        std::fs::write(&path, "boot_concurrency: 2\n").unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert_eq!(config.boot_concurrency, Some(2));
+        assert!(!config.host_execution.allow);
+
+        std::fs::write(
+            &path,
+            "host_execution:\n  allow: true\n  providers: [terraform]\n",
+        )
+        .unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert!(config.host_execution.allow);
+        assert_eq!(config.host_execution.providers, ["terraform"]);
+
+        std::fs::write(
+            &path,
+            "downloads:\n  limit: 10MB/s\n  peers: [\"http://192.168.1.20:8470\"]\n",
+        )
+        .unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert_eq!(
+            config.downloads.limit.map(DownloadRate::bytes_per_sec),
+            Some(10 * 1024 * 1024)
+        );
+        assert_eq!(config.downloads.peers, ["http://192.168.1.20:8470"]);
+        std::fs::write(&path, "downloads:\n  limit: fast\n").unwrap();
+        assert!(IntarConfig::load_from(&path).is_err());
+
+        std::fs::write(&path, "quota:\n  max_ram: 8192\n").unwrap();
+        assert!(IntarConfig::load_from(&path).is_err());
+    }
+
+    #[test]
+    fn test_network_lan_subnet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+
+        std::fs::write(&path, "network:\n  lan_subnet: 192.168.77.0/24\n").unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        let mut lab = scenario();
+        config.network.apply(&mut lab).unwrap();
+        assert_eq!(lab.network.lan().to_string(), "192.168.77.0/24");
+        assert_eq!(lab.network.management(), Ipv4Subnet::DEFAULT_MANAGEMENT);
+
+        std::fs::write(&path, "network:\n  lan_subnet: 10.0.0.0/16\n").unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert!(config.network.apply(&mut scenario()).is_err());
+
+        std::fs::write(&path, "network:\n  lan_subnet: 10.11.0.0\n").unwrap();
+        assert!(IntarConfig::load_from(&path).is_err());
+    }
+
+    #[test]
+    fn test_network_lan_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        assert_eq!(
+            IntarConfig::load_from(&path).unwrap().network.lan_backend,
+            LanBackend::Switch
+        );
+
+        std::fs::write(&path, "network:\n  lan_backend: socket_vmnet\n").unwrap();
+        let config = IntarConfig::load_from(&path).unwrap();
+        assert_eq!(config.network.lan_backend, LanBackend::SocketVmnet);
+    }
+
+    #[test]
+    fn test_quota_violations() {
+        let scenario = scenario();
+        assert!(RunQuota::default().check(&scenario).is_ok());
+
+        let quota = RunQuota {
+            max_memory_mb: Some(8192),
+            max_cpus: Some(8),
+            max_disk_gb: Some(20),
+            on_exceed: QuotaAction::Reject,
+        };
+        assert_eq!(
+            quota.violations(&scenario),
+            vec![
+                "memory: 65536 MB requested, limit 8192 MB".to_string(),
+                "disk: 30 GB requested, limit 20 GB".to_string(),
+            ]
+        );
+        assert!(matches!(
+            quota.check(&scenario),
+            Err(VmError::QuotaExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{HostSocket, HostStream, RunState, VmError, VmInfo, connect_host_socket};
+use std::path::Path;
+
+/// Byte that ends an `intar console` session: `Ctrl+]`, as in telnet.
+pub const CONSOLE_ESCAPE: u8 = 0x1d;
+
+/// Connect to the serial console of VM `vm_name` in the run at `run_dir` (`intar console`).
+///
+/// # Errors
+/// Returns `VmError::VmNotFound` if the run has no such VM, `VmError::NotPermitted` if the VM
+/// has no console socket (cloud instances and runs started before consoles), and
+/// `VmError::Serial` if the connection fails.
+pub async fn connect_console(run_dir: &Path, vm_name: &str) -> Result<HostStream, VmError> {
+    let state = RunState::load(run_dir)?;
+    let info = state
+        .vms
+        .iter()
+        .find(|vm| vm.name == vm_name)
+        .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+    connect_host_socket(&console_socket(run_dir, info)?).await
+}
+
+#[cfg(unix)]
+fn console_socket(run_dir: &Path, info: &VmInfo) -> Result<HostSocket, VmError> {
+    let path = run_dir.join(format!("{}-console.sock", info.name));
+    if !path.exists() {
+        return Err(no_console(info));
+    }
+    Ok(HostSocket::unix(path))
+}
+
+#[cfg(windows)]
+fn console_socket(_run_dir: &Path, info: &VmInfo) -> Result<HostSocket, VmError> {
+    info.socket_ports
+        .get(4)
+        .copied()
+        .map(HostSocket::tcp)
+        .ok_or_else(|| no_console(info))
+}
+
+fn no_console(info: &VmInfo) -> VmError {
+    VmError::NotPermitted(format!(
+        "VM '{}' has no serial console socket (cloud instances and runs started by older \
+         versions of intar have none)",
+        info.name
+    ))
+}
+
+/// Split what was typed into a console session at [`CONSOLE_ESCAPE`]: the bytes to send to the
+/// guest, and whether the session ends.
+#[must_use]
+pub fn console_input(input: &[u8]) -> (&[u8], bool) {
+    match input.iter().position(|&byte| byte == CONSOLE_ESCAPE) {
+        Some(end) => (&input[..end], true),
+        None => (input, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_input() {
+        assert_eq!(console_input(b"ls\r"), (&b"ls\r"[..], false));
+        assert_eq!(console_input(b"ls\x1dexit"), (&b"ls"[..], true));
+        assert_eq!(console_input(b"\x1d"), (&b""[..], true));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_console_socket_needs_socket_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let info = VmInfo {
+            name: "web".into(),
+            ssh_port: 2201,
+            api_port: None,
+            image: "ubuntu".into(),
+            lan_port: None,
+            socket_ports: Vec::new(),
+            host_key_fingerprint: None,
+        };
+        let err = console_socket(dir.path(), &info).unwrap_err();
+        assert!(matches!(err, VmError::NotPermitted(_)));
+
+        std::fs::write(dir.path().join("web-console.sock"), "").unwrap();
+        assert!(console_socket(dir.path(), &info).is_ok());
+    }
+}
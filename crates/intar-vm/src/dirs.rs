@@ -1,6 +1,14 @@
 use crate::VmError;
+use petname::Petnames;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
 use std::path::PathBuf;
 
+/// Generated names tried before giving up on finding a free one.
+const RUN_NAME_ATTEMPTS: u32 = 16;
+const MAX_RUN_NAME_LEN: usize = 64;
+
 pub struct IntarDirs {
     pub cache: PathBuf,
     pub state: PathBuf,
@@ -43,10 +51,71 @@ impl IntarDirs {
         self.state.join("runs")
     }
 
+    /// Where intar writes its own log, `intar.log`.
     #[must_use]
-    pub fn new_run_dir(&self) -> PathBuf {
-        let name = generate_run_name();
-        self.runs_dir().join(name)
+    pub fn logs_dir(&self) -> PathBuf {
+        self.state.join("logs")
+    }
+
+    /// Message catalogs, `<language>.yaml`, that translate the interface.
+    #[must_use]
+    pub fn locales_dir(&self) -> PathBuf {
+        self.config.join("locales")
+    }
+
+    /// Records of runs torn down when their TTL ran out.
+    #[must_use]
+    pub fn archive_dir(&self) -> PathBuf {
+        self.state.join("archive")
+    }
+
+    /// Create the directory of a new run named `name`, or a generated name when `None`.
+    /// Names of existing and archived runs are taken; generated names are retried until a free
+    /// one comes up.
+    ///
+    /// # Errors
+    /// Returns `VmError::Directory` if `name` is invalid or taken, or no free name was found,
+    /// and another `VmError` if the directory cannot be created.
+    pub fn create_run_dir(&self, name: Option<&str>) -> Result<PathBuf, VmError> {
+        self.create_seeded_run_dir(name, random_seed())
+    }
+
+    /// Like [`IntarDirs::create_run_dir`], drawing generated names from `seed`: the same seed
+    /// picks the same name as long as it is free.
+    ///
+    /// # Errors
+    /// Returns `VmError::Directory` if `name` is invalid or taken, or no free name was found,
+    /// and another `VmError` if the directory cannot be created.
+    pub fn create_seeded_run_dir(&self, name: Option<&str>, seed: u64) -> Result<PathBuf, VmError> {
+        std::fs::create_dir_all(self.runs_dir())?;
+        if let Some(name) = name {
+            validate_run_name(name)?;
+            return self
+                .try_create_run_dir(name)?
+                .ok_or_else(|| VmError::Directory(format!("a run named '{name}' already exists")));
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..RUN_NAME_ATTEMPTS {
+            if let Some(dir) = self.try_create_run_dir(&generate_run_name_from(&mut rng))? {
+                return Ok(dir);
+            }
+        }
+        Err(VmError::Directory(
+            "no free run name found; pass one with --name".into(),
+        ))
+    }
+
+    /// The new run directory, or `None` if a run or archived run already has the name.
+    fn try_create_run_dir(&self, name: &str) -> Result<Option<PathBuf>, VmError> {
+        if self.archive_dir().join(name).exists() {
+            return Ok(None);
+        }
+        let dir = self.runs_dir().join(name);
+        match std::fs::create_dir(&dir) {
+            Ok(()) => Ok(Some(dir)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
     /// Create cache/state/config directories if they do not exist.
@@ -61,16 +130,49 @@ impl IntarDirs {
     }
 }
 
+/// Check a user-chosen run name: lowercase letters, digits, and inner dashes, at most 64
+/// characters, so it is a valid directory name everywhere.
+///
+/// # Errors
+/// Returns `VmError::Directory` describing what is wrong with `name`.
+pub fn validate_run_name(name: &str) -> Result<(), VmError> {
+    if name.is_empty() || name.len() > MAX_RUN_NAME_LEN {
+        return Err(VmError::Directory(format!(
+            "run name '{name}' must be 1 to {MAX_RUN_NAME_LEN} characters long"
+        )));
+    }
+    if name.starts_with('-')
+        || name.ends_with('-')
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(VmError::Directory(format!(
+            "run name '{name}' may only contain lowercase letters, digits, and inner dashes"
+        )));
+    }
+    Ok(())
+}
+
 #[must_use]
 pub fn generate_run_name() -> String {
-    use rand::Rng;
-    let mut rng = rand::rng();
+    generate_run_name_from(&mut rand::rng())
+}
+
+/// A run name drawn from `rng`: an adjective, a noun, and a four-digit suffix.
+pub fn generate_run_name_from<R: Rng>(rng: &mut R) -> String {
+    let words = Petnames::default();
     let suffix: u16 = rng.random_range(1000..9999);
+    match (words.adjectives.choose(rng), words.nouns.choose(rng)) {
+        (Some(adjective), Some(noun)) => format!("{adjective}-{noun}-{suffix}"),
+        _ => format!("run-{}", rng.random::<u32>()),
+    }
+}
 
-    petname::petname(2, "-").map_or_else(
-        || format!("run-{}", rng.random::<u32>()),
-        |name| format!("{name}-{suffix}"),
-    )
+/// A fresh seed for a run started without `--seed`.
+#[must_use]
+pub fn random_seed() -> u64 {
+    rand::random()
 }
 
 #[cfg(test)]
@@ -82,6 +184,50 @@ mod tests {
         let name = generate_run_name();
         assert!(!name.is_empty());
         assert!(name.contains('-'));
+
+        let seeded = |seed| generate_run_name_from(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(seeded(42), seeded(42));
+        assert_ne!(seeded(42), seeded(43));
+    }
+
+    #[test]
+    fn test_validate_run_name() {
+        assert!(validate_run_name("my-lab").is_ok());
+        assert!(validate_run_name("lab2").is_ok());
+        for bad in [
+            "",
+            "My-Lab",
+            "-lab",
+            "lab-",
+            "lab/1",
+            "../lab",
+            "a".repeat(65).as_str(),
+        ] {
+            assert!(validate_run_name(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_create_run_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let dirs = IntarDirs {
+            cache: dir.path().join("cache"),
+            state: dir.path().join("state"),
+            config: dir.path().join("config"),
+        };
+
+        let run_dir = dirs.create_run_dir(Some("my-lab")).unwrap();
+        assert_eq!(run_dir, dirs.runs_dir().join("my-lab"));
+        assert!(run_dir.is_dir());
+        assert!(dirs.create_run_dir(Some("my-lab")).is_err());
+
+        std::fs::create_dir_all(dirs.archive_dir().join("old-lab")).unwrap();
+        assert!(dirs.create_run_dir(Some("old-lab")).is_err());
+        assert!(dirs.create_run_dir(Some("Bad Name")).is_err());
+
+        let generated = dirs.create_run_dir(None).unwrap();
+        assert!(generated.is_dir());
+        assert_ne!(generated, run_dir);
     }
 
     #[test]
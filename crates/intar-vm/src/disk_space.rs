@@ -0,0 +1,117 @@
+use crate::VmError;
+use intar_core::Scenario;
+use std::path::{Path, PathBuf};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Overlay growth budgeted per VM for provisioning (packages, logs, steps).
+const OVERLAY_RESERVE_BYTES: u64 = 2048 * MIB;
+
+/// Below this much free space in the runs directory the VMs are paused.
+pub const LOW_SPACE_BYTES: u64 = 1024 * MIB;
+
+/// Paused VMs resume once this much space is free again.
+pub const RESUME_SPACE_BYTES: u64 = 2 * LOW_SPACE_BYTES;
+
+/// The tmpfs `intar start --ephemeral` keeps overlay disks on.
+const EPHEMERAL_ROOT: &str = "/dev/shm";
+
+/// Bytes available to unprivileged writes on the filesystem holding `path`.
+///
+/// # Errors
+/// Returns `VmError::Io` if the filesystem cannot be queried.
+pub fn available_space(path: &Path) -> Result<u64, VmError> {
+    Ok(fs4::available_space(path)?)
+}
+
+/// Disk a run needs in the runs directory: overlay growth plus the RAM captured by the `init`
+/// checkpoint, per VM.
+#[must_use]
+pub fn required_run_space(scenario: &Scenario) -> u64 {
+    scenario
+        .vms
+        .iter()
+        .map(|vm| OVERLAY_RESERVE_BYTES + u64::from(vm.memory) * MIB)
+        .sum()
+}
+
+/// Refuse to start when the image downloads or the run itself would not fit.
+///
+/// # Errors
+/// Returns `VmError::DiskSpace` naming the directory and the shortfall, or `VmError::Io` if free
+/// space cannot be queried.
+pub fn check_disk_space(
+    images_dir: &Path,
+    image_bytes: u64,
+    runs_dir: &Path,
+    run_bytes: u64,
+) -> Result<(), VmError> {
+    let mut needs = vec![(images_dir, image_bytes)];
+    if same_filesystem(images_dir, runs_dir) {
+        needs[0].1 += run_bytes;
+    } else {
+        needs.push((runs_dir, run_bytes));
+    }
+
+    for (dir, required) in needs {
+        let available = available_space(dir)?;
+        if available < required {
+            return Err(VmError::DiskSpace(format!(
+                "{} needs about {} MB but only {} MB are free. Free up space or remove cached \
+                 images from {}",
+                dir.display(),
+                required.div_ceil(MIB),
+                available / MIB,
+                images_dir.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the directory in RAM that holds the disks of the ephemeral run `run_id`, after
+/// checking `run_bytes` fit there.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` on hosts without a tmpfs at `/dev/shm`, `VmError::DiskSpace`
+/// if the run does not fit in it, and `VmError::Io` if the directory cannot be created.
+pub fn create_ephemeral_dir(run_id: &str, run_bytes: u64) -> Result<PathBuf, VmError> {
+    let root = Path::new(EPHEMERAL_ROOT);
+    if !cfg!(target_os = "linux") || !root.is_dir() {
+        return Err(VmError::NotPermitted(format!(
+            "--ephemeral needs a tmpfs at {EPHEMERAL_ROOT}, which only Linux hosts provide"
+        )));
+    }
+    let available = available_space(root)?;
+    if available < run_bytes {
+        return Err(VmError::DiskSpace(format!(
+            "{EPHEMERAL_ROOT} needs about {} MB for the run's disks but only {} MB are free. \
+             Start without --ephemeral or free memory",
+            run_bytes.div_ceil(MIB),
+            available / MIB
+        )));
+    }
+    let dir = root.join(format!("intar-{run_id}"));
+    let mut builder = std::fs::DirBuilder::new();
+    // Every user can list /dev/shm, and the disks hold the run's keys.
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.recursive(true).create(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    a.components().next() == b.components().next()
+}
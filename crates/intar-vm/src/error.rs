@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,6 +12,9 @@ pub enum VmError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
     #[error("Cloud-init error: {0}")]
     CloudInit(String),
 
@@ -35,6 +38,51 @@ pub enum VmError {
 
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+
+    #[error("Audit log error: {0}")]
+    Audit(String),
+
+    #[error("Not permitted: {0}")]
+    NotPermitted(String),
+
+    #[error("Completion hook error: {0}")]
+    Hook(String),
+
+    #[error("External action error: {0}")]
+    ExternalAction(String),
+
+    #[error("Probe target error: {0}")]
+    ProbeTarget(String),
+
+    #[error("Not enough disk space: {0}")]
+    DiskSpace(String),
+
+    #[error("Run quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Image bundle error: {0}")]
+    Bundle(String),
+
+    #[error("Run archive error: {0}")]
+    Archive(String),
+
+    #[error("Migration error: {0}")]
+    Migration(String),
+
+    #[error("Remote host error: {0}")]
+    Remote(String),
+
+    #[error("Libvirt error: {0}")]
+    Libvirt(String),
+
+    #[error("Cloud error: {0}")]
+    Cloud(String),
+
+    #[error("SSH host key error: {0}")]
+    HostKey(String),
+
+    #[error("Cached image {} is corrupt: it no longer matches its checksum", .0.display())]
+    CorruptImage(PathBuf),
 }
 
 /// Convert a `Path` to `&str` for use with external commands.
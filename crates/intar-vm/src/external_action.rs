@@ -0,0 +1,265 @@
+use crate::{AnsibleInventory, VmError};
+use intar_core::ExternalAction;
+use serde_json::{Value, json};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// Host executables named `intar-provider-<provider>` implement the providers intar does not
+/// ship itself.
+pub const PROVIDER_PREFIX: &str = "intar-provider-";
+
+/// Providers a run may use once host execution is allowed; others must also be listed in
+/// `host_execution.providers` of config.yaml.
+pub const BUILTIN_PROVIDERS: &[&str] = &["ansible"];
+
+/// What a provider learns about the run it provisions.
+#[derive(Debug, Clone)]
+pub struct ProviderContext {
+    pub scenario: String,
+    pub run_id: String,
+    pub run_dir: PathBuf,
+    /// The run's static Ansible inventory, already written.
+    pub inventory_path: PathBuf,
+    pub inventory: AnsibleInventory,
+}
+
+impl ProviderContext {
+    /// The JSON an `intar-provider-*` executable reads on stdin.
+    #[must_use]
+    pub fn request(&self, action: &ExternalAction) -> Value {
+        let hosts: Vec<Value> = self
+            .inventory
+            .hosts
+            .iter()
+            .map(|host| {
+                json!({
+                    "name": host.name,
+                    "address": "127.0.0.1",
+                    "ssh_port": host.ssh_port,
+                    "user": "user",
+                })
+            })
+            .collect();
+        json!({
+            "scenario": self.scenario,
+            "run_id": self.run_id,
+            "run_dir": self.run_dir,
+            "inventory": self.inventory_path,
+            "private_key": self.inventory.private_key,
+            "hosts": hosts,
+            "settings": action.settings,
+        })
+    }
+}
+
+/// Run `action` in its scenario's directory, calling `on_line` with every line it prints and
+/// keeping them all in `log_path`. `ansible` runs `ansible-playbook` on the run's inventory;
+/// any other provider runs `intar-provider-<provider>` with [`ProviderContext::request`] on
+/// stdin. Both also get the run in `INTAR_*` environment variables.
+///
+/// # Errors
+/// Returns `VmError::ExternalAction` if the provider cannot be started or exits with an error.
+pub async fn run_external_action(
+    action: &ExternalAction,
+    context: &ProviderContext,
+    log_path: &Path,
+    mut on_line: impl FnMut(&str),
+) -> Result<(), VmError> {
+    let (mut command, stdin) = provider_command(action, context)?;
+    command
+        .current_dir(&action.dir)
+        .env("INTAR_SCENARIO", &context.scenario)
+        .env("INTAR_RUN_ID", &context.run_id)
+        .env("INTAR_RUN_DIR", &context.run_dir)
+        .env("INTAR_INVENTORY", &context.inventory_path)
+        .env("INTAR_SSH_KEY", &context.inventory.private_key)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+    let mut child = command.spawn().map_err(|e| {
+        VmError::ExternalAction(format!(
+            "cannot start '{program}' for provider '{}': {e}",
+            action.provider
+        ))
+    })?;
+
+    if let (Some(request), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // A provider that ignores its request may close stdin early.
+        let _ = pipe.write_all(request.as_bytes()).await;
+    }
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    forward_lines(child.stdout.take(), tx.clone());
+    forward_lines(child.stderr.take(), tx);
+
+    let mut log = std::fs::File::create(log_path)?;
+    let mut last_line = String::new();
+    while let Some(line) = rx.recv().await {
+        writeln!(log, "{line}")?;
+        if !line.trim().is_empty() {
+            on_line(&line);
+            last_line = line;
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(VmError::ExternalAction(format!(
+            "provider '{}' exited with {status}: {} (full output in {})",
+            action.provider,
+            last_line.trim(),
+            log_path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// The command for `action`'s provider and what to write to its stdin.
+fn provider_command(
+    action: &ExternalAction,
+    context: &ProviderContext,
+) -> Result<(Command, Option<String>), VmError> {
+    if action.provider != "ansible" {
+        let command = Command::new(format!("{PROVIDER_PREFIX}{}", action.provider));
+        return Ok((command, Some(context.request(action).to_string())));
+    }
+
+    let playbook = action
+        .settings
+        .get("playbook")
+        .and_then(Value::as_str)
+        .ok_or_else(|| VmError::ExternalAction("provider 'ansible' needs a 'playbook'".into()))?;
+    let mut command = Command::new("ansible-playbook");
+    command
+        .arg("-i")
+        .arg(&context.inventory_path)
+        .arg(playbook)
+        .env("ANSIBLE_HOST_KEY_CHECKING", "False")
+        .env("ANSIBLE_NOCOLOR", "1");
+    if let Some(limit) = action.settings.get("limit").and_then(Value::as_str) {
+        command.args(["--limit", limit]);
+    }
+    if let Some(extra_vars) = action.settings.get("extra_vars") {
+        command.arg("--extra-vars").arg(extra_vars.to_string());
+    }
+    Ok((command, None))
+}
+
+/// Send each line of `pipe` to `tx` until it closes.
+fn forward_lines<R>(pipe: Option<R>, tx: mpsc::UnboundedSender<String>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let Some(pipe) = pipe else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(pipe).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InventoryHost;
+    use std::collections::BTreeMap;
+
+    fn action(provider: &str, settings: Value) -> ExternalAction {
+        let Value::Object(settings) = settings else {
+            unreachable!()
+        };
+        ExternalAction {
+            provider: provider.into(),
+            settings: settings.into_iter().collect(),
+            dir: PathBuf::from("/labs/web"),
+        }
+    }
+
+    fn context() -> ProviderContext {
+        ProviderContext {
+            scenario: "web".into(),
+            run_id: "brave-otter".into(),
+            run_dir: PathBuf::from("/runs/brave-otter"),
+            inventory_path: PathBuf::from("/runs/brave-otter/inventory.ini"),
+            inventory: AnsibleInventory {
+                hosts: vec![InventoryHost {
+                    name: "web".into(),
+                    ssh_port: 2201,
+                    host_key_fingerprint: None,
+                }],
+                groups: BTreeMap::new(),
+                private_key: "/runs/brave-otter/id_ed25519".into(),
+                known_hosts: "/runs/brave-otter/known_hosts".into(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_provider_request() {
+        let action = action("terraform", json!({ "workspace": "lab" }));
+        assert_eq!(
+            context().request(&action),
+            json!({
+                "scenario": "web",
+                "run_id": "brave-otter",
+                "run_dir": "/runs/brave-otter",
+                "inventory": "/runs/brave-otter/inventory.ini",
+                "private_key": "/runs/brave-otter/id_ed25519",
+                "hosts": [{
+                    "name": "web",
+                    "address": "127.0.0.1",
+                    "ssh_port": 2201,
+                    "user": "user",
+                }],
+                "settings": { "workspace": "lab" },
+            })
+        );
+        let (command, stdin) = provider_command(&action, &context()).unwrap();
+        assert_eq!(command.as_std().get_program(), "intar-provider-terraform");
+        assert!(stdin.is_some());
+    }
+
+    #[test]
+    fn test_ansible_provider_command() {
+        let playbook = action(
+            "ansible",
+            json!({ "playbook": "site.yml", "extra_vars": { "port": 8080 } }),
+        );
+        let (command, stdin) = provider_command(&playbook, &context()).unwrap();
+        let command = command.as_std();
+        assert_eq!(command.get_program(), "ansible-playbook");
+        assert_eq!(
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>(),
+            [
+                "-i",
+                "/runs/brave-otter/inventory.ini",
+                "site.yml",
+                "--extra-vars",
+                r#"{"port":8080}"#,
+            ]
+        );
+        assert!(stdin.is_none());
+        assert!(provider_command(&action("ansible", json!({})), &context()).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{VmError, path_to_str};
+use base64::Engine as _;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::Command;
+
+/// File in the run directory with each VM's SSH host key, listed under the VM's name so ssh
+/// finds it through `HostKeyAlias` whichever port reaches the VM.
+pub const KNOWN_HOSTS_FILE: &str = "known_hosts";
+
+/// An ed25519 SSH host key generated on the host for a guest, which cloud-init installs in
+/// place of the keys the guest would generate, so the host knows it before the first login.
+#[derive(Clone)]
+pub struct SshHostKey {
+    pub private_key: String,
+    /// `ssh-ed25519 <base64>`.
+    pub public_key: String,
+}
+
+impl SshHostKey {
+    /// Generate a key with `ssh-keygen`.
+    ///
+    /// # Errors
+    /// Returns `VmError::HostKey` if `ssh-keygen` fails.
+    pub fn generate() -> Result<Self, VmError> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ssh_host_ed25519_key");
+        let status = Command::new("ssh-keygen")
+            .args([
+                "-t",
+                "ed25519",
+                "-f",
+                path_to_str(&path)?,
+                "-N",
+                "",
+                "-C",
+                "",
+                "-q",
+            ])
+            .status()
+            .map_err(|e| VmError::HostKey(format!("Failed to run ssh-keygen: {e}")))?;
+        if !status.success() {
+            return Err(VmError::HostKey(format!("ssh-keygen failed ({status})")));
+        }
+        Ok(Self {
+            private_key: std::fs::read_to_string(&path)?,
+            public_key: std::fs::read_to_string(path.with_extension("pub"))?
+                .trim()
+                .to_string(),
+        })
+    }
+
+    /// The key's fingerprint, as `ssh` shows it.
+    ///
+    /// # Errors
+    /// Returns `VmError::HostKey` if the public key is malformed.
+    pub fn fingerprint(&self) -> Result<String, VmError> {
+        host_key_fingerprint(&self.public_key)
+    }
+}
+
+/// The `SHA256:` fingerprint of the public key `public_key` (`<type> <base64> [comment]`), as
+/// `ssh-keygen -l` prints it.
+///
+/// # Errors
+/// Returns `VmError::HostKey` if the key is not base64.
+pub fn host_key_fingerprint(public_key: &str) -> Result<String, VmError> {
+    let blob = public_key
+        .split_whitespace()
+        .nth(1)
+        .and_then(|key| base64::engine::general_purpose::STANDARD.decode(key).ok())
+        .ok_or_else(|| VmError::HostKey(format!("'{public_key}' is not an SSH public key")))?;
+    let digest = Sha256::digest(blob);
+    Ok(format!(
+        "SHA256:{}",
+        base64::engine::general_purpose::STANDARD_NO_PAD.encode(digest)
+    ))
+}
+
+/// Record `public_key` as the host key of VM `vm` in the run's [`KNOWN_HOSTS_FILE`],
+/// replacing any earlier key of that VM.
+///
+/// # Errors
+/// Returns `VmError::Io` if the file cannot be written.
+pub fn record_host_key(run_dir: &Path, vm: &str, public_key: &str) -> Result<(), VmError> {
+    let path = run_dir.join(KNOWN_HOSTS_FILE);
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut content: String = existing
+        .lines()
+        .filter(|line| line.split_whitespace().next() != Some(vm))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    let _ = writeln!(content, "{vm} {public_key}");
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// `ssh` options that accept only the host key recorded for VM `vm`, after checking that the
+/// run's [`KNOWN_HOSTS_FILE`] still has the key with `fingerprint`. Without a fingerprint, the
+/// run predates recorded host keys and any key is accepted, as it was then.
+///
+/// # Errors
+/// Returns `VmError::HostKey` if the recorded key is missing or has another fingerprint.
+pub fn host_key_options(
+    run_dir: &Path,
+    vm: &str,
+    fingerprint: Option<&str>,
+) -> Result<Vec<String>, VmError> {
+    let Some(fingerprint) = fingerprint else {
+        return Ok(ssh_options(&[
+            "StrictHostKeyChecking=no",
+            "UserKnownHostsFile=/dev/null",
+        ]));
+    };
+    let path = run_dir.join(KNOWN_HOSTS_FILE);
+    let recorded = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let (alias, key) = line.split_once(' ')?;
+                if alias != vm {
+                    return None;
+                }
+                host_key_fingerprint(key).ok()
+            })
+        })
+        .ok_or_else(|| VmError::HostKey(format!("no host key of VM {vm} in {}", path.display())))?;
+    if recorded != fingerprint {
+        return Err(VmError::HostKey(format!(
+            "the host key of VM {vm} in {} is {recorded}, not {fingerprint} as the run recorded",
+            path.display()
+        )));
+    }
+    Ok(ssh_options(&[
+        "StrictHostKeyChecking=yes",
+        // Quoted, as ssh splits the option's value into several files at spaces.
+        &format!("UserKnownHostsFile=\"{}\"", path.display()),
+        &format!("HostKeyAlias={vm}"),
+        "HostKeyAlgorithms=ssh-ed25519",
+    ]))
+}
+
+fn ssh_options(options: &[&str]) -> Vec<String> {
+    options
+        .iter()
+        .flat_map(|option| ["-o".to_string(), (*option).to_string()])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKp4z6T2U1b5mC2U8n3m0w2o9mT5c4wq0xQ8N8Uq1yZb";
+
+    #[test]
+    fn test_host_key_fingerprint() {
+        let fingerprint = host_key_fingerprint(PUBLIC_KEY).unwrap();
+        assert_eq!(
+            fingerprint,
+            "SHA256:kKsq46EPenxWKokBvkBa/lQImA5OLVrSyD50pSFAeW0"
+        );
+        assert_eq!(
+            host_key_fingerprint(&format!("{PUBLIC_KEY} web")).unwrap(),
+            fingerprint
+        );
+        assert!(host_key_fingerprint("ssh-ed25519 !!").is_err());
+    }
+
+    #[test]
+    fn test_host_key_options() {
+        let dir = tempfile::tempdir().unwrap();
+        record_host_key(dir.path(), "web", "ssh-ed25519 AAAA").unwrap();
+        record_host_key(dir.path(), "db", PUBLIC_KEY).unwrap();
+        record_host_key(dir.path(), "web", PUBLIC_KEY).unwrap();
+        let known_hosts = std::fs::read_to_string(dir.path().join(KNOWN_HOSTS_FILE)).unwrap();
+        assert_eq!(known_hosts, format!("db {PUBLIC_KEY}\nweb {PUBLIC_KEY}\n"));
+
+        let fingerprint = host_key_fingerprint(PUBLIC_KEY).unwrap();
+        let options = host_key_options(dir.path(), "web", Some(&fingerprint)).unwrap();
+        assert!(options.contains(&"StrictHostKeyChecking=yes".to_string()));
+        assert!(options.contains(&"HostKeyAlias=web".to_string()));
+
+        let err = host_key_options(dir.path(), "web", Some("SHA256:other")).unwrap_err();
+        assert!(matches!(err, VmError::HostKey(_)));
+        assert!(host_key_options(dir.path(), "cache", Some(&fingerprint)).is_err());
+
+        let legacy = host_key_options(dir.path(), "web", None).unwrap();
+        assert!(legacy.contains(&"StrictHostKeyChecking=no".to_string()));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::VmError;
+use std::process::Command;
+#[cfg(target_os = "linux")]
+use std::{collections::HashMap, time::Instant};
+
+/// Scheduling limits applied to QEMU so a running lab leaves the host usable.
+#[derive(Debug, Clone, Default)]
+pub struct QemuPriority {
+    /// `nice` increment (1-19); on Linux also drops QEMU to the lowest best-effort I/O priority.
+    pub nice: Option<i32>,
+    /// CPU list for `taskset -c` (e.g. `2-5` or `0,2,4`); Linux only.
+    pub cpus: Option<String>,
+}
+
+impl QemuPriority {
+    /// Check the values and warn about the parts this host cannot apply.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if `nice` is out of range or `cpus` is not a CPU list.
+    pub fn validate(&self) -> Result<(), VmError> {
+        if let Some(nice) = self.nice
+            && !(1..=19).contains(&nice)
+        {
+            return Err(VmError::NotPermitted(format!(
+                "nice must be between 1 and 19, got {nice}"
+            )));
+        }
+        if let Some(cpus) = &self.cpus
+            && !is_cpu_list(cpus)
+        {
+            return Err(VmError::NotPermitted(format!(
+                "'{cpus}' is not a CPU list like '0-3' or '0,2,4'"
+            )));
+        }
+
+        if self.nice.is_some() && cfg!(windows) {
+            tracing::warn!("--nice is not supported on Windows hosts; ignoring");
+        }
+        if self.cpus.is_some() && !cfg!(target_os = "linux") {
+            tracing::warn!("--cpus is only supported on Linux hosts; ignoring");
+        }
+        Ok(())
+    }
+
+    /// A command running `program` under `nice`, `ionice`, and `taskset` as configured. The
+    /// wrappers `exec` into QEMU, so the PID stays the same.
+    pub(crate) fn command(&self, program: &str) -> Command {
+        let mut argv: Vec<String> = Vec::new();
+        if let Some(nice) = self.nice
+            && cfg!(unix)
+        {
+            argv.extend(["nice".into(), "-n".into(), nice.to_string()]);
+            if cfg!(target_os = "linux") {
+                argv.extend(["ionice", "-c", "2", "-n", "7"].map(String::from));
+            }
+        }
+        if let Some(cpus) = &self.cpus
+            && cfg!(target_os = "linux")
+        {
+            argv.extend(["taskset".into(), "-c".into(), cpus.clone()]);
+        }
+        argv.push(program.to_string());
+
+        let mut cmd = Command::new(&argv[0]);
+        cmd.args(&argv[1..]);
+        cmd
+    }
+}
+
+fn is_cpu_list(value: &str) -> bool {
+    !value.is_empty()
+        && value.split(',').all(|part| {
+            part.splitn(2, '-')
+                .all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        })
+}
+
+/// Host CPU usage of QEMU processes, as a percentage of one core since the previous sample.
+#[derive(Debug, Default)]
+pub struct CpuSampler {
+    #[cfg(target_os = "linux")]
+    previous: HashMap<u32, (u64, Instant)>,
+}
+
+impl CpuSampler {
+    /// Sample `pid`. Returns `None` on the first sample of a process or if it cannot be read.
+    #[cfg(target_os = "linux")]
+    pub fn sample(&mut self, pid: u32) -> Option<f64> {
+        // /proc reports CPU time in USER_HZ ticks, which is 100 on every Linux ABI.
+        const TICKS_PER_SEC: f64 = 100.0;
+
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        let ticks = utime + stime;
+        let now = Instant::now();
+
+        let (prev_ticks, prev_at) = self.previous.insert(pid, (ticks, now))?;
+        let secs = now.duration_since(prev_at).as_secs_f64();
+        if secs <= 0.0 {
+            return None;
+        }
+        let ticks_used = u32::try_from(ticks.saturating_sub(prev_ticks)).unwrap_or(u32::MAX);
+        let used = f64::from(ticks_used) / TICKS_PER_SEC;
+        Some(used / secs * 100.0)
+    }
+
+    /// Sample `pid` via `ps`, which already reports a recent average on BSD-style systems.
+    #[cfg(all(unix, not(target_os = "linux")))]
+    pub fn sample(&mut self, pid: u32) -> Option<f64> {
+        let output = Command::new("ps")
+            .args(["-o", "%cpu=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+
+    #[cfg(not(unix))]
+    pub fn sample(&mut self, _pid: u32) -> Option<f64> {
+        None
+    }
+}
+
+/// Resident memory of `pid` in bytes. On Linux this is the process's peak (`VmHWM`); elsewhere
+/// the current size, so callers sample it and keep the largest value.
+#[cfg(target_os = "linux")]
+#[must_use]
+pub fn resident_memory(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kib: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+#[must_use]
+pub fn resident_memory(pid: u32) -> Option<u64> {
+    let output = Command::new("ps")
+        .args(["-o", "rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let kib: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(unix))]
+#[must_use]
+pub fn resident_memory(_pid: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_priority() {
+        let ok = QemuPriority {
+            nice: Some(10),
+            cpus: Some("0-3,6".into()),
+        };
+        assert!(ok.validate().is_ok());
+
+        let bad_nice = QemuPriority {
+            nice: Some(-5),
+            cpus: None,
+        };
+        assert!(bad_nice.validate().is_err());
+
+        for cpus in ["", "0-", "a-b", "1,,2"] {
+            let bad_cpus = QemuPriority {
+                nice: None,
+                cpus: Some(cpus.into()),
+            };
+            assert!(bad_cpus.validate().is_err(), "{cpus}");
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_resident_memory_of_self() {
+        assert!(resident_memory(std::process::id()).is_some_and(|bytes| bytes > 0));
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// Gap between two checks that counts as the host having slept. The TUI checks every tick, so
+/// anything this long means the process was not running.
+pub const HOST_SLEEP_GAP: Duration = Duration::from_secs(30);
+
+/// A stretch of time the host was asleep (or intar otherwise frozen).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostSleep {
+    /// Length of the gap, by whichever clock saw more of it.
+    pub duration: Duration,
+    /// Part of the gap the monotonic clock counted, and so `Instant`-based timers. On Linux and
+    /// macOS it stops during sleep; on Windows it keeps running.
+    pub monotonic: Duration,
+}
+
+/// Notices host sleep by comparing the wall clock and the monotonic clock between checks: either
+/// jumps by at least [`HOST_SLEEP_GAP`].
+#[derive(Debug, Clone, Copy)]
+pub struct SleepDetector {
+    wall: SystemTime,
+    monotonic: Instant,
+}
+
+impl Default for SleepDetector {
+    fn default() -> Self {
+        Self {
+            wall: SystemTime::now(),
+            monotonic: Instant::now(),
+        }
+    }
+}
+
+impl SleepDetector {
+    /// The sleep since the last check, if there was one. Call regularly.
+    pub fn check(&mut self) -> Option<HostSleep> {
+        self.check_at(SystemTime::now(), Instant::now())
+    }
+
+    fn check_at(&mut self, wall: SystemTime, monotonic: Instant) -> Option<HostSleep> {
+        // A wall clock set backwards reads as no time passing.
+        let wall_gap = wall.duration_since(self.wall).unwrap_or_default();
+        let monotonic_gap = monotonic.saturating_duration_since(self.monotonic);
+        self.wall = wall;
+        self.monotonic = monotonic;
+
+        let duration = wall_gap.max(monotonic_gap);
+        (duration >= HOST_SLEEP_GAP).then_some(HostSleep {
+            duration,
+            monotonic: monotonic_gap,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_clock_jumps() {
+        let wall = SystemTime::now();
+        let monotonic = Instant::now();
+        let mut detector = SleepDetector { wall, monotonic };
+
+        let tick = Duration::from_millis(100);
+        assert_eq!(detector.check_at(wall + tick, monotonic + tick), None);
+
+        // Linux and macOS: the monotonic clock stood still while the host slept.
+        let hour = Duration::from_secs(3600);
+        assert_eq!(
+            detector.check_at(wall + tick + hour, monotonic + tick * 2),
+            Some(HostSleep {
+                duration: hour,
+                monotonic: tick,
+            })
+        );
+
+        // Windows: both clocks moved on.
+        assert_eq!(
+            detector.check_at(wall + tick + hour * 2, monotonic + tick * 2 + hour),
+            Some(HostSleep {
+                duration: hour,
+                monotonic: hour,
+            })
+        );
+
+        // Setting the wall clock back is not a sleep.
+        assert_eq!(detector.check_at(wall, monotonic + tick * 3 + hour), None);
+    }
+}
@@ -59,6 +59,15 @@ impl HostSocket {
         }
     }
 
+    #[must_use]
+    pub fn tcp_port(&self) -> Option<u16> {
+        match self {
+            #[cfg(unix)]
+            HostSocket::Unix(_) => None,
+            HostSocket::Tcp(addr) => Some(addr.port()),
+        }
+    }
+
     #[must_use]
     pub fn cleanup_path(&self) -> Option<&Path> {
         match self {
@@ -0,0 +1,440 @@
+use crate::VmError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bundle entry listing every image in the bundle with its size and SHA-256. Written last, so
+/// an export streams each image only once.
+pub const BUNDLE_MANIFEST: &str = "intar-images.json";
+
+/// Size of a tar header and of the unit entry data is padded to.
+pub(crate) const BLOCK: usize = 512;
+
+/// Longest manifest an import reads.
+const MAX_MANIFEST_BYTES: u64 = 1024 * 1024;
+
+/// Largest size the 11 octal digits of a ustar header hold; bigger images use GNU base-256.
+const MAX_OCTAL_SIZE: u64 = 0o777_7777_7777;
+
+/// One image in a bundle, named as in the image cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledImage {
+    pub file: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    images: Vec<BundledImage>,
+}
+
+/// What `import_image_bundle` did with the bundle's images.
+#[derive(Debug, Default)]
+pub struct BundleImport {
+    pub imported: Vec<BundledImage>,
+    /// Images the cache already had, left untouched.
+    pub skipped: Vec<String>,
+}
+
+/// Write `images` (files in the image cache) to a tar file at `output`, followed by a manifest of
+/// their checksums. The images keep their cache file names so an import restores the cache
+/// layout as-is.
+///
+/// # Errors
+/// Returns `VmError` if an image cannot be read, its name does not fit a tar header, or the
+/// bundle cannot be written. A partly written bundle is removed.
+pub fn export_image_bundle(
+    images: &[PathBuf],
+    output: &Path,
+) -> Result<Vec<BundledImage>, VmError> {
+    let result = write_bundle(images, output);
+    if result.is_err() {
+        let _ = std::fs::remove_file(output);
+    }
+    result
+}
+
+fn write_bundle(images: &[PathBuf], output: &Path) -> Result<Vec<BundledImage>, VmError> {
+    let mut out = BufWriter::new(File::create(output)?);
+    let mut bundled = Vec::new();
+    for path in images {
+        let file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| is_image_file_name(name))
+            .ok_or_else(|| VmError::InvalidPath(path.display().to_string()))?;
+        let mut input = File::open(path)?;
+        let meta = input.metadata()?;
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |age| age.as_secs());
+        write_header(&mut out, file, meta.len(), mtime)?;
+        let (size, sha256) = copy_hashed(&mut (&mut input).take(meta.len()), &mut out)?;
+        if size != meta.len() {
+            return Err(VmError::Bundle(format!("{file} changed while exporting")));
+        }
+        write_padding(&mut out, size)?;
+        bundled.push(BundledImage {
+            file: file.to_string(),
+            size,
+            sha256,
+        });
+    }
+
+    let manifest = serde_json::to_vec_pretty(&BundleManifest {
+        images: bundled.clone(),
+    })?;
+    let manifest_len = u64::try_from(manifest.len()).unwrap_or(u64::MAX);
+    write_header(&mut out, BUNDLE_MANIFEST, manifest_len, 0)?;
+    out.write_all(&manifest)?;
+    write_padding(&mut out, manifest_len)?;
+    // Two empty blocks end a tar archive.
+    out.write_all(&[0; 2 * BLOCK])?;
+    out.into_inner()
+        .map_err(io::IntoInnerError::into_error)?
+        .sync_all()?;
+    Ok(bundled)
+}
+
+/// Unpack a bundle written by `export_image_bundle` into `images_dir`. Every image is checked
+/// against the manifest before any is moved into place; images already in the cache are kept.
+///
+/// # Errors
+/// Returns `VmError` if the bundle is malformed, lacks its manifest, or holds an image whose
+/// size or checksum does not match it. Nothing is added to the cache in that case.
+pub fn import_image_bundle(bundle: &Path, images_dir: &Path) -> Result<BundleImport, VmError> {
+    std::fs::create_dir_all(images_dir)?;
+    let mut temps = Vec::new();
+    let result = read_bundle(bundle, images_dir, &mut temps);
+    if result.is_err() {
+        for (_, temp) in &temps {
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+    result
+}
+
+fn read_bundle(
+    bundle: &Path,
+    images_dir: &Path,
+    temps: &mut Vec<(BundledImage, PathBuf)>,
+) -> Result<BundleImport, VmError> {
+    let mut input = BufReader::new(File::open(bundle)?);
+    let mut manifest = None;
+    let mut import = BundleImport::default();
+
+    while let Some(entry) = read_header(&mut input)? {
+        let mut data = (&mut input).take(entry.size);
+        if entry.name == BUNDLE_MANIFEST {
+            if entry.size > MAX_MANIFEST_BYTES {
+                return Err(VmError::Bundle("manifest is too large".into()));
+            }
+            let mut text = Vec::new();
+            data.read_to_end(&mut text)?;
+            manifest = Some(serde_json::from_slice::<BundleManifest>(&text)?);
+        } else if !entry.regular || !is_image_file_name(&entry.name) {
+            return Err(VmError::Bundle(format!(
+                "unexpected entry '{}'; not an intar image bundle",
+                entry.name
+            )));
+        } else if images_dir.join(&entry.name).exists() {
+            io::copy(&mut data, &mut io::sink())?;
+            import.skipped.push(entry.name);
+        } else {
+            let temp = images_dir.join(format!("{}.tmp", entry.name));
+            let mut out = BufWriter::new(File::create(&temp)?);
+            let (size, sha256) = copy_hashed(&mut data, &mut out)?;
+            out.flush()?;
+            temps.push((
+                BundledImage {
+                    file: entry.name,
+                    size,
+                    sha256,
+                },
+                temp,
+            ));
+        }
+        if data.limit() > 0 {
+            return Err(VmError::Bundle("bundle is truncated".into()));
+        }
+        skip_padding(&mut input, entry.size)?;
+    }
+
+    let manifest =
+        manifest.ok_or_else(|| VmError::Bundle(format!("bundle has no {BUNDLE_MANIFEST}")))?;
+    for expected in &manifest.images {
+        let found = temps.iter().any(|(image, _)| image == expected)
+            || import.skipped.contains(&expected.file);
+        if !found {
+            return Err(VmError::Bundle(format!(
+                "{} is missing or does not match its checksum",
+                expected.file
+            )));
+        }
+    }
+    if let Some((image, _)) = temps
+        .iter()
+        .find(|(image, _)| !manifest.images.contains(image))
+    {
+        return Err(VmError::Bundle(format!(
+            "{} is not listed in the manifest",
+            image.file
+        )));
+    }
+
+    for (image, temp) in temps.drain(..) {
+        std::fs::rename(&temp, images_dir.join(&image.file))?;
+        import.imported.push(image);
+    }
+    Ok(import)
+}
+
+/// Whether `name` is a file an image cache holds, safe to join onto the cache directory.
+fn is_image_file_name(name: &str) -> bool {
+    let has_image_ext = Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext == "img" || ext == "qcow2");
+    has_image_ext && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
+
+/// Copy `input` to `out`, returning the byte count and hex SHA-256 of what was copied.
+fn copy_hashed(input: &mut impl Read, out: &mut impl Write) -> io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])?;
+        total += u64::try_from(n).unwrap_or(u64::MAX);
+    }
+    Ok((total, hex::encode(hasher.finalize())))
+}
+
+struct TarEntry {
+    name: String,
+    size: u64,
+    regular: bool,
+}
+
+pub(crate) fn write_header(
+    out: &mut impl Write,
+    name: &str,
+    size: u64,
+    mtime: u64,
+) -> Result<(), VmError> {
+    let mut header = [0u8; BLOCK];
+    if name.len() > 100 {
+        return Err(VmError::Bundle(format!(
+            "file name '{name}' is longer than 100 bytes"
+        )));
+    }
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    if size <= MAX_OCTAL_SIZE {
+        write_octal(&mut header[124..136], size);
+    } else {
+        header[124] = 0x80;
+        header[128..136].copy_from_slice(&size.to_be_bytes());
+    }
+    write_octal(&mut header[136..148], mtime.min(MAX_OCTAL_SIZE));
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[148..156].fill(b' ');
+    let sum: u64 = header.iter().map(|b| u64::from(*b)).sum();
+    // Six digits and a NUL, keeping the trailing space.
+    write_octal(&mut header[148..155], sum);
+    out.write_all(&header)?;
+    Ok(())
+}
+
+fn read_header(input: &mut impl Read) -> Result<Option<TarEntry>, VmError> {
+    let mut header = [0u8; BLOCK];
+    input.read_exact(&mut header).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => VmError::Bundle("bundle is truncated".into()),
+        _ => VmError::Io(e),
+    })?;
+    if header.iter().all(|b| *b == 0) {
+        return Ok(None);
+    }
+
+    let stored_sum = parse_octal(&header[148..156]);
+    header[148..156].fill(b' ');
+    let sum: u64 = header.iter().map(|b| u64::from(*b)).sum();
+    if stored_sum != Some(sum) {
+        return Err(VmError::Bundle(
+            "bad tar header checksum; not an intar image bundle".into(),
+        ));
+    }
+
+    let name = field_str(&header[..100]);
+    let prefix = field_str(&header[345..500]);
+    let name = if prefix.is_empty() {
+        name
+    } else {
+        format!("{prefix}/{name}")
+    };
+    let size = if header[124] & 0x80 == 0 {
+        parse_octal(&header[124..136])
+    } else {
+        header[125..136].iter().try_fold(0u64, |acc, b| {
+            acc.checked_mul(256).map(|acc| acc + u64::from(*b))
+        })
+    }
+    .ok_or_else(|| VmError::Bundle(format!("bad size for '{name}'")))?;
+
+    Ok(Some(TarEntry {
+        name,
+        size,
+        regular: matches!(header[156], b'0' | 0),
+    }))
+}
+
+/// Write `value` as zero-padded octal filling all but the last byte of `field`, then a NUL.
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{value:0digits$o}");
+    field[..digits].copy_from_slice(&text.as_bytes()[text.len() - digits..]);
+    field[digits] = 0;
+}
+
+fn parse_octal(field: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(field)
+        .ok()?
+        .trim_matches(|c| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn padding(size: u64) -> u64 {
+    size.next_multiple_of(512) - size
+}
+
+pub(crate) fn write_padding(out: &mut impl Write, size: u64) -> io::Result<()> {
+    io::copy(&mut io::repeat(0).take(padding(size)), out)?;
+    Ok(())
+}
+
+fn skip_padding(input: &mut impl Read, size: u64) -> Result<(), VmError> {
+    let pad = padding(size);
+    if io::copy(&mut input.take(pad), &mut io::sink())? < pad {
+        return Err(VmError::Bundle("bundle is truncated".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_image(dir: &Path, name: &str, data: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let images = vec![
+            write_image(
+                source.path(),
+                "noble-x86_64-0123456789abcdef.img",
+                b"disk one",
+            ),
+            write_image(source.path(), "baked.qcow2", &vec![7; 3 * BLOCK + 1]),
+        ];
+        let bundle = source.path().join("images.tar");
+        let exported = export_image_bundle(&images, &bundle).unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].size, 8);
+        assert_eq!(exported[1].size, 3 * 512 + 1);
+
+        let target = tempfile::tempdir().unwrap();
+        write_image(target.path(), "baked.qcow2", b"already here");
+        let import = import_image_bundle(&bundle, target.path()).unwrap();
+        assert_eq!(import.imported, exported[..1]);
+        assert_eq!(import.skipped, ["baked.qcow2"]);
+        assert_eq!(
+            std::fs::read(target.path().join("noble-x86_64-0123456789abcdef.img")).unwrap(),
+            b"disk one"
+        );
+        assert_eq!(
+            std::fs::read(target.path().join("baked.qcow2")).unwrap(),
+            b"already here"
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_corrupt_image() {
+        let source = tempfile::tempdir().unwrap();
+        let images = vec![write_image(source.path(), "a.img", b"original")];
+        let bundle = source.path().join("images.tar");
+        export_image_bundle(&images, &bundle).unwrap();
+
+        let mut data = std::fs::read(&bundle).unwrap();
+        data[BLOCK] = b'X';
+        std::fs::write(&bundle, &data).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let err = import_image_bundle(&bundle, target.path()).unwrap_err();
+        assert!(err.to_string().contains("a.img"), "{err}");
+        assert_eq!(std::fs::read_dir(target.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_import_rejects_truncated_bundle() {
+        let source = tempfile::tempdir().unwrap();
+        let images = vec![write_image(source.path(), "a.img", &vec![1; 2000])];
+        let bundle = source.path().join("images.tar");
+        export_image_bundle(&images, &bundle).unwrap();
+
+        let data = std::fs::read(&bundle).unwrap();
+        std::fs::write(&bundle, &data[..1000]).unwrap();
+        let target = tempfile::tempdir().unwrap();
+        assert!(import_image_bundle(&bundle, target.path()).is_err());
+        assert_eq!(std::fs::read_dir(target.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_header_sizes() {
+        for size in [0, 1, MAX_OCTAL_SIZE, MAX_OCTAL_SIZE + 1, 20 << 30] {
+            let mut block = Vec::new();
+            write_header(&mut block, "a.img", size, 1_700_000_000).unwrap();
+            let entry = read_header(&mut block.as_slice()).unwrap().unwrap();
+            assert_eq!(entry.name, "a.img");
+            assert_eq!(entry.size, size);
+            assert!(entry.regular);
+        }
+        assert!(write_header(&mut Vec::new(), &"a".repeat(101), 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_is_image_file_name() {
+        assert!(is_image_file_name("noble-x86_64-0123.img"));
+        assert!(is_image_file_name("baked.qcow2"));
+        assert!(!is_image_file_name("a.img.tmp"));
+        assert!(!is_image_file_name("../a.img"));
+        assert!(!is_image_file_name(".a.img"));
+        assert!(!is_image_file_name(BUNDLE_MANIFEST));
+    }
+}
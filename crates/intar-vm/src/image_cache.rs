@@ -1,19 +1,166 @@
-use crate::VmError;
+use crate::{DownloadSettings, VmError};
 use futures_util::StreamExt;
 use intar_core::ImageSource;
+use reqwest::StatusCode;
+use serde::Deserialize;
 use sha2::{Digest, Sha256};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
 use tracing::{info, warn};
 
+/// Attempts per URL before moving on to the next mirror.
+const DOWNLOAD_ATTEMPTS: u32 = 5;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Peers are on the local network; one that does not answer quickly is skipped.
+const PEER_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// A connection that sends nothing for this long is dropped and retried.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What an image download reports while it runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadEvent {
+    /// Fraction of the image downloaded, from 0.0 to 1.0.
+    Progress(f64),
+    /// A download from `url` failed; `attempt` of `max_attempts` resumes it after `delay`.
+    Retry {
+        url: String,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+        error: String,
+    },
+}
+
+/// A failed download attempt, and whether trying the same URL again may help.
+struct AttemptError {
+    error: VmError,
+    transient: bool,
+}
+
+impl AttemptError {
+    fn transient(message: String) -> Self {
+        Self {
+            error: VmError::CloudInit(message),
+            transient: true,
+        }
+    }
+
+    fn io(error: std::io::Error) -> Self {
+        Self {
+            error: VmError::Io(error),
+            transient: false,
+        }
+    }
+}
+
+/// A download speed limit in bytes per second, written like `10MB/s`, `512KB/s`, or `1GB`.
+/// Units are binary (1 MB = 1024 KB).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct DownloadRate(u64);
+
+impl DownloadRate {
+    #[must_use]
+    pub fn bytes_per_sec(self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for DownloadRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid download rate '{s}' (expected e.g. 10MB/s)");
+        let rate = s.trim();
+        let rate = rate.strip_suffix("/s").unwrap_or(rate).trim_end();
+        let split = rate
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rate.len());
+        let (number, unit) = rate.split_at(split);
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" => 1024,
+            "M" | "MB" => 1024 * 1024,
+            "G" | "GB" => 1024 * 1024 * 1024,
+            _ => return Err(invalid()),
+        };
+        number
+            .checked_mul(multiplier)
+            .filter(|bytes| *bytes > 0)
+            .map(Self)
+            .ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for DownloadRate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for DownloadRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} KB/s", self.0 / 1024)
+    }
+}
+
+/// Sleeps between chunks so a download averages at most its rate.
+struct Throttle {
+    rate: DownloadRate,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    fn new(rate: DownloadRate) -> Self {
+        Self {
+            rate,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    async fn consume(&mut self, bytes: u64) {
+        self.bytes = self.bytes.saturating_add(bytes);
+        let due_ms = u128::from(self.bytes) * 1000 / u128::from(self.rate.bytes_per_sec());
+        let due = Duration::from_millis(u64::try_from(due_ms).unwrap_or(u64::MAX));
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
 pub struct ImageCache {
     dir: PathBuf,
+    limit: Option<DownloadRate>,
+    /// Base URLs of peer caches, asked before an image's own URLs.
+    peers: Vec<String>,
 }
 
 impl ImageCache {
     #[must_use]
     pub fn new(dir: PathBuf) -> Self {
-        Self { dir }
+        Self {
+            dir,
+            limit: None,
+            peers: Vec::new(),
+        }
+    }
+
+    /// Apply the speed limit and peer caches from the user configuration.
+    #[must_use]
+    pub fn with_settings(mut self, settings: &DownloadSettings) -> Self {
+        self.limit = settings.limit;
+        self.peers.clone_from(&settings.peers);
+        self
     }
 
     #[must_use]
@@ -30,6 +177,27 @@ impl ImageCache {
         if path.exists() { Some(path) } else { None }
     }
 
+    /// Size of the image download as reported by the first server that says.
+    pub async fn download_size(&self, source: &ImageSource) -> Option<u64> {
+        for url in source.urls() {
+            if let Some(path) = local_image_path(url) {
+                if let Ok(meta) = tokio::fs::metadata(path).await {
+                    return Some(meta.len());
+                }
+                continue;
+            }
+            let Ok(response) = reqwest::Client::new().head(url).send().await else {
+                continue;
+            };
+            if response.status().is_success()
+                && let Some(len) = response.content_length().filter(|len| *len > 0)
+            {
+                return Some(len);
+            }
+        }
+        None
+    }
+
     /// Ensure the image is cached locally, downloading if needed.
     ///
     /// # Errors
@@ -38,36 +206,58 @@ impl ImageCache {
         self.ensure_image_with_progress(source, |_| {}).await
     }
 
-    /// Ensure the image is cached locally, emitting progress callbacks while downloading.
+    /// Ensure the image is cached locally, emitting progress and retry events while
+    /// downloading. Peer caches are asked first; then transient failures are retried with
+    /// backoff, resuming the partial download, before moving on to the source's mirrors.
     ///
     /// # Errors
-    /// Returns `VmError` if download or verification fails.
+    /// Returns `VmError` if every URL fails or verification fails.
     pub async fn ensure_image_with_progress<F>(
         &self,
         source: &ImageSource,
         progress_callback: F,
     ) -> Result<PathBuf, VmError>
     where
-        F: Fn(f64) + Send + 'static,
+        F: Fn(DownloadEvent) + Send + 'static,
     {
         let filename = Self::cache_filename(&source.url, &source.arch);
         let path = self.dir.join(&filename);
 
         if path.exists() {
             info!("Image already cached: {}", path.display());
-            progress_callback(1.0);
-            self.verify_checksum(&path, &source.checksum).await?;
-            return Ok(path);
+            match self.verify_checksum(&path, &source.checksum).await {
+                Ok(()) => {
+                    progress_callback(DownloadEvent::Progress(1.0));
+                    return Ok(path);
+                }
+                Err(VmError::Io(e)) => return Err(e.into()),
+                Err(e) => {
+                    warn!(
+                        "Cached image {} is corrupt ({}); downloading it again",
+                        path.display(),
+                        e
+                    );
+                    tokio::fs::remove_file(&path).await?;
+                }
+            }
         }
 
         std::fs::create_dir_all(&self.dir)?;
 
-        info!("Downloading image from {}", source.url);
-        self.download_with_progress(&source.url, &path, progress_callback)
-            .await?;
+        let temp_path = path.with_extension("img.tmp");
+        if !self.download_from_peers(&filename, &temp_path).await {
+            self.download_from_mirrors(source, &temp_path, &progress_callback)
+                .await?;
+        }
 
-        self.verify_checksum(&path, &source.checksum).await?;
+        if let Err(e) = self.verify_checksum(&temp_path, &source.checksum).await {
+            // Never resume from a corrupt download.
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&temp_path, &path).await?;
 
+        info!("Image cached at {}", path.display());
         Ok(path)
     }
 
@@ -89,61 +279,69 @@ impl ImageCache {
         format!("{basename}-{arch}-{url_hash}.img")
     }
 
-    async fn download_with_progress<F>(
+    /// Fetch the cache file `filename` from the first peer cache that has it. Peers are on the
+    /// local network, so no proxy, retries, or speed limit apply.
+    async fn download_from_peers(&self, filename: &str, temp_path: &Path) -> bool {
+        if self.peers.is_empty() {
+            return false;
+        }
+        let Ok(client) = reqwest::Client::builder()
+            .connect_timeout(PEER_CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .no_proxy()
+            .build()
+        else {
+            return false;
+        };
+        for peer in &self.peers {
+            let url = format!("{}/{filename}", peer.trim_end_matches('/'));
+            match download_attempt(&client, &url, temp_path, None, &|_| {}).await {
+                Ok(()) => {
+                    info!("Fetched {} from peer cache {}", filename, peer);
+                    return true;
+                }
+                Err(AttemptError { error, .. }) => {
+                    info!("Peer cache {} cannot provide {}: {}", peer, filename, error);
+                }
+            }
+        }
+        false
+    }
+
+    /// Download the image to `temp_path` from the first of the source's URLs that works.
+    /// Proxies come from `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY`, and `NO_PROXY`.
+    async fn download_from_mirrors<F>(
         &self,
-        url: &str,
-        dest: &Path,
-        progress_callback: F,
+        source: &ImageSource,
+        temp_path: &Path,
+        progress_callback: &F,
     ) -> Result<(), VmError>
     where
-        F: Fn(f64),
+        F: Fn(DownloadEvent),
     {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| VmError::CloudInit(format!("Failed to start download: {e}")))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(VmError::CloudInit(format!(
-                "Download failed with status: {status}"
-            )));
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .build()
+            .map_err(|e| VmError::CloudInit(format!("Failed to set up downloads: {e}")))?;
+        if let Some(limit) = self.limit {
+            info!("Limiting image downloads to {}", limit);
         }
 
-        let total_size = response.content_length().unwrap_or(0);
-
-        let temp_path = dest.with_extension("img.tmp");
-        let mut file = tokio::fs::File::create(&temp_path)
-            .await
-            .map_err(VmError::Io)?;
-
-        let mut downloaded: u64 = 0;
-        let mut stream = response.bytes_stream();
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.map_err(|e| VmError::CloudInit(format!("Download error: {e}")))?;
-            file.write_all(&chunk).await?;
-            downloaded += chunk.len() as u64;
-
-            if total_size > 0 {
-                let scaled = (u128::from(downloaded) * 10_000u128) / u128::from(total_size);
-                let scaled = scaled.min(10_000u128);
-                let scaled = u32::try_from(scaled).unwrap_or(10_000);
-                let progress = f64::from(scaled) / 10_000.0;
-                progress_callback(progress);
+        let mut last_error = None;
+        for url in source.urls() {
+            info!("Downloading image from {}", url);
+            match download_with_retries(&client, url, temp_path, self.limit, progress_callback)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("Download from {} failed: {}", url, e);
+                    last_error = Some(e);
+                }
             }
         }
-
-        file.flush().await?;
-        drop(file);
-
-        progress_callback(1.0);
-
-        tokio::fs::rename(&temp_path, dest).await?;
-
-        info!("Image downloaded to {}", dest.display());
-        Ok(())
+        Err(last_error.unwrap_or_else(|| VmError::CloudInit("Image source has no URL".into())))
     }
 
     async fn verify_checksum(&self, path: &Path, expected: &str) -> Result<(), VmError> {
@@ -154,11 +352,10 @@ impl ImageCache {
 
         info!("Verifying checksum for {}", path.display());
 
-        let data = tokio::fs::read(path).await?;
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        let result = hasher.finalize();
-        let actual_hash = hex::encode(result);
+        let path = path.to_path_buf();
+        let actual_hash = tokio::task::spawn_blocking(move || file_sha256(&path))
+            .await
+            .map_err(std::io::Error::other)??;
 
         if actual_hash != expected_hash {
             return Err(VmError::CloudInit(format!(
@@ -170,6 +367,33 @@ impl ImageCache {
         Ok(())
     }
 
+    /// Whether the file at `path` still matches `checksum`. Checksums in a format other than
+    /// `sha256:` cannot be checked and count as a match.
+    ///
+    /// # Errors
+    /// Returns `VmError::Io` if the file cannot be read.
+    pub fn file_matches_checksum(path: &Path, checksum: &str) -> Result<bool, VmError> {
+        let Some(expected_hash) = checksum.strip_prefix("sha256:") else {
+            return Ok(true);
+        };
+        Ok(file_sha256(path)? == expected_hash)
+    }
+
+    /// Remove a cached image so the next run downloads it again.
+    ///
+    /// # Errors
+    /// Returns `VmError::InvalidPath` if `path` is not in the cache directory, or `VmError::Io`
+    /// if it cannot be removed.
+    pub fn evict(&self, path: &Path) -> Result<(), VmError> {
+        if path.parent() != Some(self.dir.as_path()) {
+            return Err(VmError::InvalidPath(path.display().to_string()));
+        }
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
     /// List cached image files.
     ///
     /// # Errors
@@ -195,3 +419,259 @@ impl ImageCache {
         Ok(images)
     }
 }
+
+async fn download_with_retries<F>(
+    client: &reqwest::Client,
+    url: &str,
+    temp_path: &Path,
+    limit: Option<DownloadRate>,
+    progress_callback: &F,
+) -> Result<(), VmError>
+where
+    F: Fn(DownloadEvent),
+{
+    if let Some(path) = local_image_path(url) {
+        tokio::fs::copy(path, temp_path).await?;
+        progress_callback(DownloadEvent::Progress(1.0));
+        return Ok(());
+    }
+
+    let mut attempt = 1;
+    loop {
+        match download_attempt(client, url, temp_path, limit, progress_callback).await {
+            Ok(()) => return Ok(()),
+            Err(AttemptError {
+                error,
+                transient: true,
+            }) if attempt < DOWNLOAD_ATTEMPTS => {
+                let delay = retry_delay(attempt);
+                warn!(
+                    "Download attempt {}/{} from {} failed: {}; retrying in {}s",
+                    attempt,
+                    DOWNLOAD_ATTEMPTS,
+                    url,
+                    error,
+                    delay.as_secs()
+                );
+                attempt += 1;
+                progress_callback(DownloadEvent::Retry {
+                    url: url.to_string(),
+                    attempt,
+                    max_attempts: DOWNLOAD_ATTEMPTS,
+                    delay,
+                    error: error.to_string(),
+                });
+                tokio::time::sleep(delay).await;
+            }
+            Err(AttemptError { error, .. }) => return Err(error),
+        }
+    }
+}
+
+/// Download `url` to `temp_path`, continuing a partial download left there by an earlier
+/// attempt when the server supports ranges.
+async fn download_attempt<F>(
+    client: &reqwest::Client,
+    url: &str,
+    temp_path: &Path,
+    limit: Option<DownloadRate>,
+    progress_callback: &F,
+) -> Result<(), AttemptError>
+where
+    F: Fn(DownloadEvent),
+{
+    let partial = tokio::fs::metadata(temp_path)
+        .await
+        .map_or(0, |meta| meta.len());
+    let mut request = client.get(url);
+    if partial > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={partial}-"));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AttemptError::transient(format!("Failed to start download: {e}")))?;
+
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file does not fit the server's image; start over.
+        tokio::fs::remove_file(temp_path)
+            .await
+            .map_err(AttemptError::io)?;
+        return Err(AttemptError::transient(format!(
+            "Download failed with status: {status}"
+        )));
+    }
+    if !status.is_success() {
+        return Err(AttemptError {
+            error: VmError::CloudInit(format!("Download failed with status: {status}")),
+            transient: is_transient_status(status),
+        });
+    }
+
+    // Servers without range support send the whole image again.
+    let resumed = status == StatusCode::PARTIAL_CONTENT;
+    let mut downloaded = if resumed { partial } else { 0 };
+    if resumed {
+        info!("Resuming download of {} at {} bytes", url, partial);
+    }
+    let total_size = response
+        .content_length()
+        .map_or(0, |len| len.saturating_add(downloaded));
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.create(true);
+    if resumed {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    let mut file = options.open(temp_path).await.map_err(AttemptError::io)?;
+
+    let mut throttle = limit.map(Throttle::new);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AttemptError::transient(format!("Download error: {e}")))?;
+        file.write_all(&chunk).await.map_err(AttemptError::io)?;
+        downloaded += chunk.len() as u64;
+        if let Some(throttle) = throttle.as_mut() {
+            throttle.consume(chunk.len() as u64).await;
+        }
+
+        if total_size > 0 {
+            let scaled = (u128::from(downloaded) * 10_000u128) / u128::from(total_size);
+            let scaled = scaled.min(10_000u128);
+            let scaled = u32::try_from(scaled).unwrap_or(10_000);
+            progress_callback(DownloadEvent::Progress(f64::from(scaled) / 10_000.0));
+        }
+    }
+
+    file.flush().await.map_err(AttemptError::io)?;
+    progress_callback(DownloadEvent::Progress(1.0));
+    Ok(())
+}
+
+/// Hex SHA-256 of a file, read in chunks so large images are not held in memory.
+fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Whether a `qemu-img` or QEMU error reads like a damaged qcow2 base image.
+#[must_use]
+pub fn indicates_image_corruption(message: &str) -> bool {
+    const SYMPTOMS: &[&str] = &[
+        "image is corrupt",
+        "marking image as corrupt",
+        "could not read l1 table",
+        "could not read l2 table",
+        "could not read qcow2 header",
+        "could not read snapshots",
+        "could not read refcount",
+        "image is not in qcow2 format",
+        "unknown backing file format",
+        "could not open backing file",
+        "could not open backing image",
+    ];
+    let haystack = message.to_lowercase();
+    SYMPTOMS.iter().any(|symptom| haystack.contains(symptom))
+}
+
+/// Statuses that may go away when the same request is repeated later.
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || matches!(
+            status,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+        )
+}
+
+/// Exponential backoff before the retry that follows failed attempt `attempt` (from 1).
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Local path of a `file://` image URL, e.g. one written by `intar bake`.
+fn local_image_path(url: &str) -> Option<&Path> {
+    url.strip_prefix("file://").map(Path::new)
+}
+
+/// `file://` URL for a local image.
+#[must_use]
+pub fn file_url(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(2), Duration::from_secs(4));
+        assert_eq!(retry_delay(4), Duration::from_secs(16));
+        assert_eq!(retry_delay(10), RETRY_MAX_DELAY);
+        assert_eq!(retry_delay(u32::MAX), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn test_parse_download_rate() {
+        let rate = |s: &str| s.parse::<DownloadRate>().map(DownloadRate::bytes_per_sec);
+        assert_eq!(rate("10MB/s"), Ok(10 * 1024 * 1024));
+        assert_eq!(rate("512 KB/s"), Ok(512 * 1024));
+        assert_eq!(rate("1g"), Ok(1024 * 1024 * 1024));
+        assert_eq!(rate("4096"), Ok(4096));
+        for bad in ["", "MB/s", "0MB/s", "10 TB/s", "1.5MB/s", "-1"] {
+            assert!(bad.parse::<DownloadRate>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_image_corruption_symptoms() {
+        assert!(indicates_image_corruption(
+            "qemu-img: disk.qcow2: Could not open backing file: qcow2: Image is corrupt; cannot be opened read/write"
+        ));
+        assert!(indicates_image_corruption(
+            "qcow2: Marking image as corrupt: L2 table offset 0x1234 unaligned"
+        ));
+        assert!(!indicates_image_corruption(
+            "qemu-system-x86_64: failed to initialize kvm: Permission denied"
+        ));
+    }
+
+    #[test]
+    fn test_file_matches_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.img");
+        std::fs::write(&path, b"hello").unwrap();
+        let good = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(ImageCache::file_matches_checksum(&path, good).unwrap());
+        std::fs::write(&path, b"hellO").unwrap();
+        assert!(!ImageCache::file_matches_checksum(&path, good).unwrap());
+        assert!(ImageCache::file_matches_checksum(&path, "md5:abc").unwrap());
+    }
+
+    #[test]
+    fn test_evict_only_in_cache_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ImageCache::new(dir.path().to_path_buf());
+        let image = dir.path().join("a.img");
+        std::fs::write(&image, b"x").unwrap();
+        cache.evict(&image).unwrap();
+        assert!(!image.exists());
+        cache.evict(&image).unwrap();
+        assert!(cache.evict(Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn test_transient_status() {
+        assert!(is_transient_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient_status(StatusCode::NOT_FOUND));
+        assert!(!is_transient_status(StatusCode::FORBIDDEN));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{KNOWN_HOSTS_FILE, RunState, VmError, load_run_scenario};
+use intar_core::Scenario;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// One reachable VM of a run, as Ansible connects to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryHost {
+    pub name: String,
+    pub ssh_port: u16,
+    /// Fingerprint of the VM's recorded host key; `None` in runs from before intar recorded
+    /// them, whose host keys are not checked.
+    pub host_key_fingerprint: Option<String>,
+}
+
+/// Ansible inventory of a running scenario (`intar inventory`). Hosts are reached through their
+/// forwarded SSH port on localhost with the run's key; groups come from the scenario.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnsibleInventory {
+    pub hosts: Vec<InventoryHost>,
+    /// Group name to member hosts, in host order.
+    pub groups: BTreeMap<String, Vec<String>>,
+    pub private_key: String,
+    /// The run's [`KNOWN_HOSTS_FILE`], with each VM's host key under the VM's name.
+    pub known_hosts: String,
+}
+
+impl AnsibleInventory {
+    /// Build the inventory of the run at `run_dir`.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the run's scenario or state cannot be read.
+    pub fn load(run_dir: &Path) -> Result<Self, VmError> {
+        let scenario = load_run_scenario(run_dir)?;
+        let state = RunState::load(run_dir)?;
+        let hosts = state
+            .vms
+            .iter()
+            .map(|vm| InventoryHost {
+                name: vm.name.clone(),
+                ssh_port: vm.ssh_port,
+                host_key_fingerprint: vm.host_key_fingerprint.clone(),
+            })
+            .collect();
+        Ok(Self::new(&scenario, hosts, run_dir))
+    }
+
+    /// Group `hosts` by image (`image_<name>`) and `k8s_cluster` (`<cluster>`,
+    /// `<cluster>_servers`, `<cluster>_agents`). Names are made safe for Ansible by replacing
+    /// anything but letters, digits, and `_`. The key and known hosts are those in `run_dir`.
+    #[must_use]
+    pub fn new(scenario: &Scenario, hosts: Vec<InventoryHost>, run_dir: &Path) -> Self {
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut add = |group: String, host: &str| {
+            groups
+                .entry(group_name(&group))
+                .or_default()
+                .push(host.to_string());
+        };
+
+        for host in &hosts {
+            if let Some(vm) = scenario.vms.iter().find(|vm| vm.name == host.name) {
+                add(format!("image_{}", vm.image), &host.name);
+            }
+            for cluster in &scenario.clusters {
+                let role = if (1..=cluster.servers).any(|i| cluster.server_name(i) == host.name) {
+                    "servers"
+                } else if (1..=cluster.agents).any(|i| cluster.agent_name(i) == host.name) {
+                    "agents"
+                } else {
+                    continue;
+                };
+                add(cluster.name.clone(), &host.name);
+                add(format!("{}_{role}", cluster.name), &host.name);
+            }
+        }
+
+        Self {
+            hosts,
+            groups,
+            private_key: run_dir.join("id_ed25519").display().to_string(),
+            known_hosts: run_dir.join(KNOWN_HOSTS_FILE).display().to_string(),
+        }
+    }
+
+    fn host_vars(&self, host: &InventoryHost) -> Vec<(&'static str, String)> {
+        let ssh_args = if host.host_key_fingerprint.is_some() {
+            format!(
+                "-o StrictHostKeyChecking=yes -o UserKnownHostsFile=\"{}\" -o HostKeyAlias={} \
+                 -o HostKeyAlgorithms=ssh-ed25519",
+                self.known_hosts, host.name
+            )
+        } else {
+            "-o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null".into()
+        };
+        vec![
+            ("ansible_host", "127.0.0.1".into()),
+            ("ansible_port", host.ssh_port.to_string()),
+            ("ansible_user", "user".into()),
+            ("ansible_ssh_private_key_file", self.private_key.clone()),
+            ("ansible_ssh_common_args", ssh_args),
+        ]
+    }
+
+    /// Dynamic-inventory JSON, as printed by an inventory script for `--list`.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let mut inventory = Map::new();
+        let hostvars: Map<String, Value> = self
+            .hosts
+            .iter()
+            .map(|host| {
+                let mut vars: Map<String, Value> = self
+                    .host_vars(host)
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), Value::String(value)))
+                    .collect();
+                vars.insert("ansible_port".into(), host.ssh_port.into());
+                (host.name.clone(), Value::Object(vars))
+            })
+            .collect();
+        inventory.insert("_meta".into(), json!({ "hostvars": hostvars }));
+
+        let names: Vec<&str> = self.hosts.iter().map(|h| h.name.as_str()).collect();
+        let mut children: Vec<&str> = self.groups.keys().map(String::as_str).collect();
+        children.push("ungrouped");
+        inventory.insert(
+            "all".into(),
+            json!({ "hosts": names, "children": children }),
+        );
+        for (group, members) in &self.groups {
+            inventory.insert(group.clone(), json!({ "hosts": members }));
+        }
+        inventory.insert("ungrouped".into(), json!({ "hosts": [] }));
+        Value::Object(inventory)
+    }
+
+    /// Static INI inventory, usable directly with `ansible -i`.
+    #[must_use]
+    pub fn to_ini(&self) -> String {
+        let mut out = String::from("[all]\n");
+        for host in &self.hosts {
+            out.push_str(&host.name);
+            for (key, value) in self.host_vars(host) {
+                if value.contains(' ') {
+                    let _ = write!(out, " {key}='{value}'");
+                } else {
+                    let _ = write!(out, " {key}={value}");
+                }
+            }
+            out.push('\n');
+        }
+        for (group, members) in &self.groups {
+            let _ = write!(out, "\n[{group}]\n");
+            for member in members {
+                out.push_str(member);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+fn group_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory() -> AnsibleInventory {
+        let scenario = Scenario::parse(
+            r#"
+scenario "lab" {
+  k8s_cluster "k3s" {
+    image   = "ubuntu-24.04"
+    servers = 1
+    agents  = 1
+  }
+
+  vm "bastion" {
+    image = "debian"
+  }
+}
+"#,
+        )
+        .unwrap();
+        let hosts = [
+            ("k3s-server-1", 2201),
+            ("k3s-agent-1", 2202),
+            ("bastion", 2203),
+        ]
+        .into_iter()
+        .map(|(name, ssh_port)| InventoryHost {
+            name: name.into(),
+            ssh_port,
+            host_key_fingerprint: Some("SHA256:abc".into()),
+        })
+        .collect();
+        AnsibleInventory::new(&scenario, hosts, Path::new("/runs/lab"))
+    }
+
+    #[test]
+    fn test_inventory_groups() {
+        let inventory = inventory();
+        let groups: Vec<(&str, Vec<&str>)> = inventory
+            .groups
+            .iter()
+            .map(|(g, m)| (g.as_str(), m.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                ("image_debian", vec!["bastion"]),
+                ("image_ubuntu_24_04", vec!["k3s-server-1", "k3s-agent-1"]),
+                ("k3s", vec!["k3s-server-1", "k3s-agent-1"]),
+                ("k3s_agents", vec!["k3s-agent-1"]),
+                ("k3s_servers", vec!["k3s-server-1"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inventory_formats() {
+        let inventory = inventory();
+        let json = inventory.to_json();
+        assert_eq!(json["_meta"]["hostvars"]["bastion"]["ansible_port"], 2203);
+        assert_eq!(json["k3s_servers"]["hosts"], json!(["k3s-server-1"]));
+
+        let ini = inventory.to_ini();
+        assert!(ini.starts_with(
+            "[all]\nk3s-server-1 ansible_host=127.0.0.1 ansible_port=2201 ansible_user=user \
+             ansible_ssh_private_key_file=/runs/lab/id_ed25519 \
+             ansible_ssh_common_args='-o StrictHostKeyChecking=yes \
+             -o UserKnownHostsFile=\"/runs/lab/known_hosts\" -o HostKeyAlias=k3s-server-1 \
+             -o HostKeyAlgorithms=ssh-ed25519'\n"
+        ));
+        assert!(ini.contains("\n[k3s_agents]\nk3s-agent-1\n"));
+
+        let mut unpinned = inventory;
+        unpinned.hosts[2].host_key_fingerprint = None;
+        assert!(unpinned.to_ini().contains(
+            "bastion ansible_host=127.0.0.1 ansible_port=2203 ansible_user=user \
+             ansible_ssh_private_key_file=/runs/lab/id_ed25519 \
+             ansible_ssh_common_args='-o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null'\n"
+        ));
+    }
+}
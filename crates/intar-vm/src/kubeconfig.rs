@@ -0,0 +1,103 @@
+use crate::{HostSocket, RunState, VmError, load_run_scenario, try_connect};
+use intar_core::K3S_KUBECONFIG;
+use std::path::Path;
+
+/// Admin kubeconfig of a running VM, pointed at its forwarded API port on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKubeconfig {
+    pub vm: String,
+    pub content: String,
+}
+
+/// Fetch the k3s admin kubeconfig from `vm_name` in the run at `run_dir` (`intar kubeconfig`).
+/// Without a VM name, the first server of the scenario's first `k8s_cluster` is used, or the
+/// only VM.
+///
+/// # Errors
+/// Returns `VmError` if the VM is unknown or ambiguous, was started without an API forward, or
+/// has no kubeconfig yet.
+pub async fn host_kubeconfig(
+    run_dir: &Path,
+    vm_name: Option<&str>,
+) -> Result<HostKubeconfig, VmError> {
+    let scenario = load_run_scenario(run_dir)?;
+    let state = RunState::load(run_dir)?;
+    let vm_name = match vm_name {
+        Some(name) => name.to_string(),
+        None => match (scenario.clusters.first(), state.vms.as_slice()) {
+            (Some(cluster), _) => cluster.server_name(1),
+            (None, [only]) => only.name.clone(),
+            (None, _) => {
+                return Err(VmError::NotPermitted(
+                    "the run has several VMs; choose one with --vm".into(),
+                ));
+            }
+        },
+    };
+    let info = state
+        .vms
+        .iter()
+        .find(|vm| vm.name == vm_name)
+        .ok_or_else(|| VmError::VmNotFound(vm_name.clone()))?;
+    let api_port = info.api_port.ok_or_else(|| {
+        VmError::NotPermitted(format!(
+            "VM '{vm_name}' was started without a Kubernetes API port forward"
+        ))
+    })?;
+
+    #[cfg(unix)]
+    let serial = HostSocket::unix(run_dir.join(format!("{}-serial.sock", info.name)));
+    #[cfg(windows)]
+    let serial = HostSocket::tcp(
+        info.socket_ports
+            .get(1)
+            .copied()
+            .ok_or_else(|| VmError::Serial(format!("No serial port recorded for VM {vm_name}")))?,
+    );
+
+    let mut conn = try_connect(&serial, 3, 500).await?;
+    let output = conn.exec(&format!("cat {K3S_KUBECONFIG}")).await?;
+    if output.exit_code != 0 {
+        return Err(VmError::NotPermitted(format!(
+            "VM '{vm_name}' has no {K3S_KUBECONFIG} yet: {}",
+            output.stderr.trim()
+        )));
+    }
+
+    Ok(HostKubeconfig {
+        content: point_at_host(&output.stdout, api_port),
+        vm: vm_name,
+    })
+}
+
+/// Replace every cluster `server:` address with the forwarded loopback port. k3s includes
+/// `127.0.0.1` in its serving certificate, so TLS verification still succeeds.
+fn point_at_host(kubeconfig: &str, api_port: u16) -> String {
+    kubeconfig
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("server: https://") {
+                let indent = &line[..line.len() - trimmed.len()];
+                format!("{indent}server: https://127.0.0.1:{api_port}\n")
+            } else {
+                format!("{line}\n")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_at_host_rewrites_server() {
+        let kubeconfig = "apiVersion: v1\nclusters:\n- cluster:\n    certificate-authority-data: abc\n    server: https://127.0.0.1:6443\n  name: default\n";
+        let rewritten = point_at_host(kubeconfig, 41234);
+        assert_eq!(
+            rewritten,
+            kubeconfig.replace("127.0.0.1:6443", "127.0.0.1:41234")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{SharedNetworkEndpoint, VmError};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+
+/// Where Homebrew and the socket_vmnet installer put the daemon's socket.
+const SOCKET_VMNET_PATHS: [&str; 3] = [
+    "/opt/homebrew/var/run/socket_vmnet",
+    "/usr/local/var/run/socket_vmnet",
+    "/var/run/socket_vmnet",
+];
+
+/// `IFF_MULTICAST` in `/sys/class/net/<dev>/flags`.
+#[cfg(target_os = "linux")]
+const IFF_MULTICAST: u32 = 0x1000;
+
+/// What carries a run's shared LAN between its VMs (`network.lan_backend` in `config.yaml`).
+/// None of them needs intar to run with privileges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LanBackend {
+    /// intar's own switch, reached over localhost UDP. Works on every host and provides the
+    /// LAN's DHCP and DNS, `intar net stats`, and captures, but copies every frame through
+    /// intar twice.
+    #[default]
+    Switch,
+    /// A loopback multicast group per run: the kernel hands each frame straight to the other
+    /// VMs. Linux and macOS; on Linux, `lo` needs multicast enabled once with
+    /// `sudo ip link set lo multicast on`.
+    Multicast,
+    /// The socket_vmnet daemon (macOS), which switches frames in vmnet.framework. Every run
+    /// using it shares one segment, so concurrent runs need distinct LAN subnets.
+    SocketVmnet,
+}
+
+impl LanBackend {
+    /// Whether frames pass through intar's [`crate::LanSwitch`].
+    #[must_use]
+    pub fn uses_switch(self) -> bool {
+        self == Self::Switch
+    }
+
+    /// Check that this host can carry a LAN on this backend, and that the scenario does not
+    /// need what only the switch offers.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` naming what is missing.
+    pub fn check_available(self, needs_lan_services: bool) -> Result<(), VmError> {
+        if needs_lan_services && !self.uses_switch() {
+            return Err(VmError::NotPermitted(format!(
+                "the scenario's shared LAN DHCP and DNS need network.lan_backend 'switch', not '{}'",
+                self.name()
+            )));
+        }
+        match self {
+            Self::Switch => Ok(()),
+            Self::Multicast => check_loopback_multicast(),
+            Self::SocketVmnet => socket_vmnet_path().map(|_| ()),
+        }
+    }
+
+    /// The shared LAN NIC of a VM on a run whose LAN got `hub_port`. `switch_port` is the VM's
+    /// own port on the switch, which only [`LanBackend::Switch`] uses; without it there is
+    /// none.
+    #[must_use]
+    pub fn endpoint(
+        self,
+        hub_port: u16,
+        switch_port: Option<u16>,
+    ) -> Option<SharedNetworkEndpoint> {
+        match self {
+            Self::Switch => switch_port.map(|local_port| SharedNetworkEndpoint::Dgram {
+                hub_port,
+                local_port,
+            }),
+            Self::Multicast => Some(SharedNetworkEndpoint::Multicast {
+                group: multicast_group(hub_port),
+                port: hub_port,
+            }),
+            Self::SocketVmnet => socket_vmnet_path()
+                .ok()
+                .map(|path| SharedNetworkEndpoint::SocketVmnet { path }),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Switch => "switch",
+            Self::Multicast => "multicast",
+            Self::SocketVmnet => "socket_vmnet",
+        }
+    }
+}
+
+/// Administratively scoped multicast group of the run whose LAN got `port`, so concurrent runs
+/// stay apart.
+#[must_use]
+pub fn multicast_group(port: u16) -> Ipv4Addr {
+    let [high, low] = port.to_be_bytes();
+    Ipv4Addr::new(239, 255, high, low)
+}
+
+/// Socket of the running socket_vmnet daemon.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` if the host is not macOS or the daemon is not running.
+pub fn socket_vmnet_path() -> Result<PathBuf, VmError> {
+    if !cfg!(target_os = "macos") {
+        return Err(VmError::NotPermitted(
+            "network.lan_backend 'socket_vmnet' is only available on macOS".into(),
+        ));
+    }
+    SOCKET_VMNET_PATHS
+        .iter()
+        .map(Path::new)
+        .find(|path| path.exists())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            VmError::NotPermitted(format!(
+                "socket_vmnet is not running (looked for {}); start it with \
+                 `sudo brew services start socket_vmnet`",
+                SOCKET_VMNET_PATHS.join(", ")
+            ))
+        })
+}
+
+#[cfg(target_os = "linux")]
+fn check_loopback_multicast() -> Result<(), VmError> {
+    let flags = std::fs::read_to_string("/sys/class/net/lo/flags")?;
+    let flags = u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16)
+        .map_err(|e| VmError::NotPermitted(format!("cannot read the flags of lo: {e}")))?;
+    if flags & IFF_MULTICAST == 0 {
+        return Err(VmError::NotPermitted(
+            "network.lan_backend 'multicast' needs multicast on lo; enable it with \
+             `sudo ip link set lo multicast on`"
+                .into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_loopback_multicast() -> Result<(), VmError> {
+    if cfg!(windows) {
+        return Err(VmError::NotPermitted(
+            "network.lan_backend 'multicast' is not available on Windows".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lan_backend_endpoints() {
+        assert!(matches!(
+            LanBackend::Switch.endpoint(40_000, Some(40_001)),
+            Some(SharedNetworkEndpoint::Dgram {
+                hub_port: 40_000,
+                local_port: 40_001,
+            })
+        ));
+        assert!(LanBackend::Switch.endpoint(40_000, None).is_none());
+        assert!(matches!(
+            LanBackend::Multicast.endpoint(40_000, None),
+            Some(SharedNetworkEndpoint::Multicast { group, port: 40_000 })
+                if group == Ipv4Addr::new(239, 255, 156, 64)
+        ));
+        assert!(
+            LanBackend::Multicast
+                .check_available(true)
+                .unwrap_err()
+                .to_string()
+                .contains("DHCP and DNS")
+        );
+        assert!(LanBackend::Switch.check_available(true).is_ok());
+    }
+}
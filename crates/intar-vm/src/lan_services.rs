@@ -0,0 +1,608 @@
+use intar_core::Ipv4Subnet;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use tracing::{debug, info};
+
+/// MAC address of the switch's services port.
+pub const LAN_SERVICES_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x57, 0xfe];
+
+const LEASE_SECS: u32 = 86_400;
+const DNS_TTL_SECS: u32 = 60;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const IP_PROTO_UDP: u8 = 17;
+const MIN_FRAME_LEN: usize = 60;
+
+const DHCP_SERVER_PORT: u16 = 67;
+const DHCP_CLIENT_PORT: u16 = 68;
+const DHCP_MAGIC: [u8; 4] = [99, 130, 83, 99];
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+const DNS_PORT: u16 = 53;
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const RCODE_NOERROR: u16 = 0;
+const RCODE_NXDOMAIN: u16 = 3;
+const RCODE_REFUSED: u16 = 5;
+
+/// Address the DHCP and DNS server answers on in the shared LAN `subnet`.
+#[must_use]
+pub fn lan_services_ip(subnet: Ipv4Subnet) -> Ipv4Addr {
+    subnet.host(2)
+}
+
+/// What the switch's services port answers, from the scenario's `network` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanServicesConfig {
+    pub subnet: Ipv4Subnet,
+    /// Lease each host its address over DHCP.
+    pub dhcp: bool,
+    /// Resolve host names under `domain`.
+    pub dns: bool,
+    pub domain: String,
+    pub hosts: Vec<LanHost>,
+}
+
+/// A VM's shared LAN NIC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanHost {
+    pub name: String,
+    pub mac: [u8; 6],
+    pub ip: Ipv4Addr,
+    /// ULA address, for VMs with IPv6 enabled.
+    pub ipv6: Option<Ipv6Addr>,
+    /// Further names resolving to `ip`, e.g. a cluster's API host.
+    pub aliases: Vec<String>,
+}
+
+/// Parse a MAC address written as `52:54:00:12:57:40`.
+#[must_use]
+pub fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mut bytes = [0u8; 6];
+    let mut parts = mac.split(':');
+    for byte in &mut bytes {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+    parts.next().is_none().then_some(bytes)
+}
+
+/// DHCP and DNS server behind the switch's services port. It only answers frames; the switch
+/// decides which frames reach it and sends the replies back to the asking port.
+pub(crate) struct LanServices {
+    config: LanServicesConfig,
+    ip: Ipv4Addr,
+    /// `in-addr.arpa` zone of the subnet, answered authoritatively.
+    reverse_zone: String,
+    /// Lower-case short and fully qualified names and their host.
+    names: HashMap<String, (Ipv4Addr, Option<Ipv6Addr>)>,
+    /// `in-addr.arpa` names and the fully qualified host name they point to.
+    reverse: HashMap<String, String>,
+}
+
+/// Ethernet address, IPv4 address, and UDP port of one end of a datagram.
+type Endpoint = ([u8; 6], Ipv4Addr, u16);
+
+impl LanServices {
+    pub(crate) fn new(config: LanServicesConfig) -> Self {
+        let mut names = HashMap::new();
+        let mut reverse = HashMap::new();
+        for host in &config.hosts {
+            for name in std::iter::once(&host.name).chain(&host.aliases) {
+                let name = name.to_ascii_lowercase();
+                names.insert(format!("{name}.{}", config.domain), (host.ip, host.ipv6));
+                names.insert(name, (host.ip, host.ipv6));
+            }
+            let octets: Vec<String> = host.ip.octets().iter().rev().map(u8::to_string).collect();
+            reverse.insert(
+                format!("{}.in-addr.arpa", octets.join(".")),
+                format!("{}.{}", host.name.to_ascii_lowercase(), config.domain),
+            );
+        }
+        Self {
+            ip: lan_services_ip(config.subnet),
+            reverse_zone: config.subnet.reverse_zone(),
+            config,
+            names,
+            reverse,
+        }
+    }
+
+    /// The reply to `frame`, if it is an ARP request for the services address, a DHCP request
+    /// from a known host, or a DNS query.
+    pub(crate) fn answer(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        let dst = frame.get(0..6)?;
+        if dst != LAN_SERVICES_MAC && dst != [0xff; 6] {
+            return None;
+        }
+        let src = <[u8; 6]>::try_from(frame.get(6..12)?).ok()?;
+        let payload = frame.get(14..)?;
+        match be16(frame, 12)? {
+            ETHERTYPE_ARP => self.answer_arp(src, payload),
+            ETHERTYPE_IPV4 => self.answer_ipv4(src, payload),
+            _ => None,
+        }
+    }
+
+    fn answer_arp(&self, src: [u8; 6], arp: &[u8]) -> Option<Vec<u8>> {
+        // Ethernet/IPv4 requests for the services address only.
+        if arp.get(0..8)? != [0, 1, 8, 0, 6, 4, 0, 1] || arp.get(24..28)? != self.ip.octets() {
+            return None;
+        }
+        let mut reply = ethernet_header(src, ETHERTYPE_ARP);
+        reply.extend_from_slice(&[0, 1, 8, 0, 6, 4, 0, 2]);
+        reply.extend_from_slice(&LAN_SERVICES_MAC);
+        reply.extend_from_slice(&self.ip.octets());
+        // The requester's hardware and protocol address.
+        reply.extend_from_slice(arp.get(8..18)?);
+        Some(padded(reply))
+    }
+
+    fn answer_ipv4(&self, src_mac: [u8; 6], packet: &[u8]) -> Option<Vec<u8>> {
+        let version_ihl = *packet.first()?;
+        let header_len = usize::from(version_ihl & 0x0f) * 4;
+        if version_ihl >> 4 != 4 || header_len < 20 || *packet.get(9)? != IP_PROTO_UDP {
+            return None;
+        }
+        // Drop the Ethernet padding after the datagram.
+        let packet = packet.get(..usize::from(be16(packet, 2)?))?;
+        let source = Ipv4Addr::from(<[u8; 4]>::try_from(packet.get(12..16)?).ok()?);
+        let destination = Ipv4Addr::from(<[u8; 4]>::try_from(packet.get(16..20)?).ok()?);
+        let udp = packet.get(header_len..)?;
+        let (src_port, dst_port) = (be16(udp, 0)?, be16(udp, 2)?);
+        let payload = udp.get(8..usize::from(be16(udp, 4)?))?;
+
+        match dst_port {
+            DHCP_SERVER_PORT
+                if self.config.dhcp
+                    && src_port == DHCP_CLIENT_PORT
+                    && (destination == self.ip || destination == Ipv4Addr::BROADCAST) =>
+            {
+                // Broadcast so clients without an address yet accept it.
+                let reply = self.answer_dhcp(payload)?;
+                udp_frame(
+                    (LAN_SERVICES_MAC, self.ip, DHCP_SERVER_PORT),
+                    (src_mac, Ipv4Addr::BROADCAST, DHCP_CLIENT_PORT),
+                    &reply,
+                )
+            }
+            DNS_PORT if self.config.dns && destination == self.ip => {
+                let reply = self.answer_dns(payload)?;
+                udp_frame(
+                    (LAN_SERVICES_MAC, self.ip, DNS_PORT),
+                    (src_mac, source, src_port),
+                    &reply,
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Offer and acknowledge each known host its fixed address; other clients get no answer.
+    fn answer_dhcp(&self, request: &[u8]) -> Option<Vec<u8>> {
+        // BOOTREQUEST for Ethernet, with the magic cookie after the fixed header.
+        if request.get(0..3)? != [1, 1, 6] || request.get(236..240)? != DHCP_MAGIC {
+            return None;
+        }
+        let options = dhcp_options(request.get(240..)?);
+        let mac = <[u8; 6]>::try_from(request.get(28..34)?).ok()?;
+        let host = self.config.hosts.iter().find(|host| host.mac == mac)?;
+
+        let reply_type = match *options.get(&53)?.first()? {
+            DHCPDISCOVER => DHCPOFFER,
+            DHCPREQUEST => {
+                if options
+                    .get(&54)
+                    .is_some_and(|server| *server != self.ip.octets())
+                {
+                    // The client took another server's offer.
+                    return None;
+                }
+                // A new lease names the address in an option, a renewal in `ciaddr`.
+                let requested = options.get(&50).copied().or_else(|| request.get(12..16))?;
+                if requested == host.ip.octets() {
+                    info!(vm = %host.name, ip = %host.ip, "DHCP lease acknowledged");
+                    DHCPACK
+                } else {
+                    info!(vm = %host.name, ?requested, "DHCP request refused");
+                    DHCPNAK
+                }
+            }
+            _ => return None,
+        };
+
+        let mut reply = vec![0u8; 236];
+        reply[0..3].copy_from_slice(&[2, 1, 6]);
+        // Transaction ID, flags, relay address, and client hardware address are echoed.
+        reply[4..8].copy_from_slice(&request[4..8]);
+        reply[10..12].copy_from_slice(&request[10..12]);
+        reply[24..44].copy_from_slice(&request[24..44]);
+        if reply_type != DHCPNAK {
+            reply[16..20].copy_from_slice(&host.ip.octets());
+        }
+
+        reply.extend_from_slice(&DHCP_MAGIC);
+        push_option(&mut reply, 53, &[reply_type]);
+        push_option(&mut reply, 54, &self.ip.octets());
+        if reply_type != DHCPNAK {
+            push_option(&mut reply, 51, &LEASE_SECS.to_be_bytes());
+            push_option(&mut reply, 1, &self.config.subnet.netmask().octets());
+            if self.config.dns {
+                push_option(&mut reply, 6, &self.ip.octets());
+                push_option(&mut reply, 15, self.config.domain.as_bytes());
+            }
+        }
+        reply.push(255);
+        // BOOTP's minimum message size.
+        reply.resize(reply.len().max(300), 0);
+        Some(reply)
+    }
+
+    /// Answer a standard query with one question; names outside the LAN's zones are refused
+    /// so the guest's resolver moves on to its next server.
+    fn answer_dns(&self, query: &[u8]) -> Option<Vec<u8>> {
+        let flags = be16(query, 2)?;
+        // Queries only (QR = 0, opcode 0).
+        if flags & 0xf800 != 0 || be16(query, 4)? != 1 {
+            return None;
+        }
+        let (name, end) = read_name(query, 12)?;
+        let qtype = be16(query, end)?;
+        let question = query.get(12..end + 4)?;
+        let (rcode, answer) = self.resolve(&name, qtype);
+        debug!(%name, qtype, rcode, "DNS query");
+
+        let mut reply = Vec::with_capacity(question.len() + 40);
+        reply.extend_from_slice(&query[0..2]);
+        // Authoritative response, echoing the client's recursion-desired bit.
+        reply.extend_from_slice(&(0x8400 | (flags & 0x0100) | rcode).to_be_bytes());
+        reply.extend_from_slice(&1u16.to_be_bytes());
+        reply.extend_from_slice(&u16::from(answer.is_some()).to_be_bytes());
+        reply.extend_from_slice(&[0, 0, 0, 0]);
+        reply.extend_from_slice(question);
+        if let Some((rtype, rdata)) = answer {
+            // The owner name points back at the question.
+            reply.extend_from_slice(&[0xc0, 0x0c]);
+            reply.extend_from_slice(&rtype.to_be_bytes());
+            reply.extend_from_slice(&1u16.to_be_bytes());
+            reply.extend_from_slice(&DNS_TTL_SECS.to_be_bytes());
+            reply.extend_from_slice(&u16::try_from(rdata.len()).ok()?.to_be_bytes());
+            reply.extend_from_slice(&rdata);
+        }
+        Some(reply)
+    }
+
+    /// Response code and answer record (type and data) for a `qtype` query of `name`.
+    fn resolve(&self, name: &str, qtype: u16) -> (u16, Option<(u16, Vec<u8>)>) {
+        if let Some((ip, ipv6)) = self.names.get(name) {
+            let answer = match qtype {
+                TYPE_A | TYPE_ANY => Some((TYPE_A, ip.octets().to_vec())),
+                TYPE_AAAA => ipv6.map(|ipv6| (TYPE_AAAA, ipv6.octets().to_vec())),
+                _ => None,
+            };
+            return (RCODE_NOERROR, answer);
+        }
+        if let Some(host) = self.reverse.get(name) {
+            let answer =
+                matches!(qtype, TYPE_PTR | TYPE_ANY).then(|| (TYPE_PTR, encode_name(host)));
+            return (RCODE_NOERROR, answer);
+        }
+        let domain = self.config.domain.as_str();
+        let reverse_zone = self.reverse_zone.as_str();
+        if name == domain || name == reverse_zone {
+            (RCODE_NOERROR, None)
+        } else if name.ends_with(&format!(".{domain}"))
+            || name.ends_with(&format!(".{reverse_zone}"))
+        {
+            (RCODE_NXDOMAIN, None)
+        } else {
+            (RCODE_REFUSED, None)
+        }
+    }
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        bytes.get(at..at.checked_add(2)?)?.try_into().ok()?,
+    ))
+}
+
+fn ethernet_header(dst: [u8; 6], ethertype: u16) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(MIN_FRAME_LEN);
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&LAN_SERVICES_MAC);
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame
+}
+
+fn padded(mut frame: Vec<u8>) -> Vec<u8> {
+    frame.resize(frame.len().max(MIN_FRAME_LEN), 0);
+    frame
+}
+
+/// An Ethernet frame carrying a UDP datagram from `from` to `to`. The UDP checksum is left out,
+/// which IPv4 allows.
+fn udp_frame(from: Endpoint, to: Endpoint, payload: &[u8]) -> Option<Vec<u8>> {
+    let udp_len = u16::try_from(payload.len().checked_add(8)?).ok()?;
+    let total_len = udp_len.checked_add(20)?;
+
+    let mut ip = [0u8; 20];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&total_len.to_be_bytes());
+    // Don't fragment.
+    ip[6] = 0x40;
+    ip[8] = 64;
+    ip[9] = IP_PROTO_UDP;
+    ip[12..16].copy_from_slice(&from.1.octets());
+    ip[16..20].copy_from_slice(&to.1.octets());
+    let checksum = ipv4_checksum(&ip);
+    ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    let mut frame = Vec::with_capacity(MIN_FRAME_LEN.max(usize::from(total_len) + 14));
+    frame.extend_from_slice(&to.0);
+    frame.extend_from_slice(&from.0);
+    frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(&from.2.to_be_bytes());
+    frame.extend_from_slice(&to.2.to_be_bytes());
+    frame.extend_from_slice(&udp_len.to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(payload);
+    Some(padded(frame))
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks_exact(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !u16::try_from(sum).unwrap_or(u16::MAX)
+}
+
+/// DHCP options by code; pad options are skipped and parsing stops at the end option.
+fn dhcp_options(mut options: &[u8]) -> HashMap<u8, &[u8]> {
+    let mut parsed = HashMap::new();
+    while let Some((&code, rest)) = options.split_first() {
+        match code {
+            0 => options = rest,
+            255 => break,
+            _ => {
+                let Some((&len, rest)) = rest.split_first() else {
+                    break;
+                };
+                let Some(value) = rest.get(..usize::from(len)) else {
+                    break;
+                };
+                parsed.insert(code, value);
+                options = &rest[usize::from(len)..];
+            }
+        }
+    }
+    parsed
+}
+
+fn push_option(message: &mut Vec<u8>, code: u8, value: &[u8]) {
+    let value = &value[..value.len().min(255)];
+    message.push(code);
+    message.push(u8::try_from(value.len()).unwrap_or(u8::MAX));
+    message.extend_from_slice(value);
+}
+
+/// Read the lower-cased name starting at `at`, returning it and the offset after it. Questions
+/// are never compressed, so pointers are rejected.
+fn read_name(message: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    loop {
+        let len = usize::from(*message.get(at)?);
+        at += 1;
+        if len == 0 {
+            break;
+        }
+        if len > 63 {
+            return None;
+        }
+        let label = message.get(at..at + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        at += len;
+    }
+    Some((labels.join("."), at))
+}
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        encoded.push(u8::try_from(label.len()).unwrap_or(63));
+        encoded.extend_from_slice(label);
+    }
+    encoded.push(0);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEB_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x57, 0x40];
+    const SERVICES_IP: Ipv4Addr = Ipv4Addr::new(10, 11, 0, 2);
+
+    fn lan_services(dhcp: bool, dns: bool) -> LanServices {
+        LanServices::new(LanServicesConfig {
+            subnet: Ipv4Subnet::DEFAULT_LAN,
+            dhcp,
+            dns,
+            domain: "intar".into(),
+            hosts: vec![LanHost {
+                name: "web".into(),
+                mac: WEB_MAC,
+                ip: Ipv4Addr::new(10, 11, 0, 10),
+                ipv6: Some("fd00:11::10".parse().unwrap()),
+                aliases: vec!["k8s-api".into()],
+            }],
+        })
+    }
+
+    fn dhcp_request(message_type: u8, mac: [u8; 6], requested: Option<Ipv4Addr>) -> Vec<u8> {
+        let mut request = vec![0u8; 236];
+        request[0..3].copy_from_slice(&[1, 1, 6]);
+        request[4..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        request[28..34].copy_from_slice(&mac);
+        request.extend_from_slice(&DHCP_MAGIC);
+        push_option(&mut request, 53, &[message_type]);
+        if let Some(ip) = requested {
+            push_option(&mut request, 50, &ip.octets());
+        }
+        request.push(255);
+        udp_frame(
+            (mac, Ipv4Addr::UNSPECIFIED, DHCP_CLIENT_PORT),
+            ([0xff; 6], Ipv4Addr::BROADCAST, DHCP_SERVER_PORT),
+            &request,
+        )
+        .unwrap()
+    }
+
+    fn dns_query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend_from_slice(&encode_name(name));
+        query.extend_from_slice(&qtype.to_be_bytes());
+        query.extend_from_slice(&1u16.to_be_bytes());
+        udp_frame(
+            (WEB_MAC, Ipv4Addr::new(10, 11, 0, 10), 40000),
+            (LAN_SERVICES_MAC, SERVICES_IP, DNS_PORT),
+            &query,
+        )
+        .unwrap()
+    }
+
+    /// UDP payload of a reply frame (20-byte IPv4 header assumed).
+    fn udp_payload(frame: &[u8]) -> &[u8] {
+        let len = usize::from(be16(frame, 38).unwrap());
+        &frame[42..34 + len]
+    }
+
+    #[test]
+    fn test_parse_mac() {
+        assert_eq!(parse_mac("52:54:00:12:57:40"), Some(WEB_MAC));
+        assert_eq!(parse_mac("52:54:00:12:57"), None);
+        assert_eq!(parse_mac("52:54:00:12:57:40:01"), None);
+    }
+
+    #[test]
+    fn test_arp_reply() {
+        let mut request = vec![0xff; 6];
+        request.extend_from_slice(&WEB_MAC);
+        request.extend_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+        request.extend_from_slice(&[0, 1, 8, 0, 6, 4, 0, 1]);
+        request.extend_from_slice(&WEB_MAC);
+        request.extend_from_slice(&[10, 11, 0, 10]);
+        request.extend_from_slice(&[0; 6]);
+        request.extend_from_slice(&SERVICES_IP.octets());
+
+        let reply = lan_services(false, true).answer(&request).unwrap();
+        assert_eq!(reply.len(), MIN_FRAME_LEN);
+        assert_eq!(&reply[0..6], &WEB_MAC);
+        assert_eq!(&reply[20..22], &[0, 2]);
+        assert_eq!(&reply[22..28], &LAN_SERVICES_MAC);
+        assert_eq!(&reply[38..42], &[10, 11, 0, 10]);
+
+        // Requests for other addresses are for the VMs to answer.
+        request[38..42].copy_from_slice(&[10, 11, 0, 11]);
+        assert!(lan_services(false, true).answer(&request).is_none());
+    }
+
+    #[test]
+    fn test_dhcp_leases_fixed_addresses() {
+        let services = lan_services(true, true);
+        let web_ip = Ipv4Addr::new(10, 11, 0, 10);
+
+        let offer = services
+            .answer(&dhcp_request(DHCPDISCOVER, WEB_MAC, None))
+            .unwrap();
+        assert_eq!(ipv4_checksum(&offer[14..34]), 0);
+        assert_eq!(&offer[30..34], &Ipv4Addr::BROADCAST.octets());
+        let message = udp_payload(&offer);
+        assert_eq!(&message[4..8], &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(&message[16..20], &web_ip.octets());
+        let options = dhcp_options(&message[240..]);
+        assert_eq!(options[&53], &[DHCPOFFER]);
+        assert_eq!(options[&1], &[255, 255, 255, 0]);
+        assert_eq!(options[&6], &SERVICES_IP.octets());
+        assert_eq!(options[&15], b"intar");
+        // No default route over the LAN.
+        assert!(!options.contains_key(&3));
+
+        let ack = services
+            .answer(&dhcp_request(DHCPREQUEST, WEB_MAC, Some(web_ip)))
+            .unwrap();
+        assert_eq!(dhcp_options(&udp_payload(&ack)[240..])[&53], &[DHCPACK]);
+        let nak = services
+            .answer(&dhcp_request(
+                DHCPREQUEST,
+                WEB_MAC,
+                Some(Ipv4Addr::new(10, 11, 0, 99)),
+            ))
+            .unwrap();
+        assert_eq!(&udp_payload(&nak)[16..20], &[0; 4]);
+        assert_eq!(dhcp_options(&udp_payload(&nak)[240..])[&53], &[DHCPNAK]);
+
+        // Unknown clients and runs without DHCP get no answer.
+        let stranger = [0x52, 0x54, 0x00, 0xaa, 0xbb, 0xcc];
+        assert!(
+            services
+                .answer(&dhcp_request(DHCPDISCOVER, stranger, None))
+                .is_none()
+        );
+        assert!(
+            lan_services(false, true)
+                .answer(&dhcp_request(DHCPDISCOVER, WEB_MAC, None))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_dns_answers() {
+        let services = lan_services(false, true);
+        let answer = |name: &str, qtype: u16| {
+            let reply = services.answer(&dns_query(name, qtype)).unwrap();
+            assert_eq!(&reply[0..6], &WEB_MAC);
+            assert_eq!(be16(&reply, 36), Some(40000));
+            udp_payload(&reply).to_vec()
+        };
+
+        let reply = answer("WEB.intar", TYPE_A);
+        assert_eq!(&reply[0..2], &[0x12, 0x34]);
+        assert_eq!(be16(&reply, 2), Some(0x8500));
+        assert_eq!(be16(&reply, 6), Some(1));
+        assert_eq!(&reply[reply.len() - 4..], &[10, 11, 0, 10]);
+        assert!(answer("k8s-api", TYPE_A).ends_with(&[10, 11, 0, 10]));
+
+        let reply = answer("web", TYPE_AAAA);
+        assert_eq!(be16(&reply, 6), Some(1));
+        assert!(reply.ends_with(&"fd00:11::10".parse::<Ipv6Addr>().unwrap().octets()));
+
+        // Known name without an address of that type.
+        let reply = answer("web", 16);
+        assert_eq!((be16(&reply, 2), be16(&reply, 6)), (Some(0x8500), Some(0)));
+
+        let reply = answer("10.0.11.10.in-addr.arpa", TYPE_PTR);
+        assert!(reply.ends_with(&encode_name("web.intar")));
+
+        assert_eq!(
+            be16(&answer("db.intar", TYPE_A), 2),
+            Some(0x8500 | RCODE_NXDOMAIN)
+        );
+        assert_eq!(
+            be16(&answer("example.com", TYPE_A), 2),
+            Some(0x8500 | RCODE_REFUSED)
+        );
+    }
+}
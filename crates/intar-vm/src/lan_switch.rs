@@ -1,27 +1,51 @@
-use crate::VmError;
+use crate::lan_services::LanServices;
+use crate::{CaptureConfig, LAN_SERVICES_MAC, LanServicesConfig, PcapWriter, VmError};
+use serde::{Deserialize, Serialize};
 use socket2::SockRef;
 use std::collections::HashMap;
+use std::fmt;
 use std::io::ErrorKind;
 use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
-use std::sync::mpsc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{info, warn};
 
+/// Switch counters in the run directory, refreshed while the run's TUI is running.
+pub const LAN_STATS_FILE: &str = "lan-stats.json";
+
+const STATS_WRITE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Requests from the runner to the switch thread.
+enum Control {
+    Stop,
+    StartCapture(PcapWriter),
+    StopCapture,
+}
+
 pub struct LanSwitch {
-    stop_tx: Option<mpsc::Sender<()>>,
+    control_tx: Option<mpsc::Sender<Control>>,
     handle: Option<thread::JoinHandle<()>>,
+    stats: Arc<Mutex<LanStats>>,
 }
 
 impl LanSwitch {
     /// Spawn a lightweight L2 switch that forwards raw Ethernet frames between peers.
     ///
-    /// Each peer is expected to be a localhost UDP endpoint used by QEMU's `-netdev dgram`.
+    /// Each peer is a VM's localhost UDP endpoint used by QEMU's `-netdev dgram`. With `services`,
+    /// the switch also answers DHCP and DNS on [`crate::lan_services_ip`]. Statistics are
+    /// written to [`LAN_STATS_FILE`] in `run_dir`.
     ///
     /// # Errors
     /// Returns `VmError` if the hub socket cannot be bound/configured or the switch thread cannot
     /// be started.
-    pub fn spawn(hub_port: u16, peers: Vec<SocketAddr>) -> Result<Self, VmError> {
+    pub fn spawn(
+        hub_port: u16,
+        peers: Vec<(String, SocketAddr)>,
+        services: Option<LanServicesConfig>,
+        run_dir: &Path,
+    ) -> Result<Self, VmError> {
         let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, hub_port))
             .map_err(|e| VmError::Qemu(format!("Failed to bind LAN hub UDP socket: {e}")))?;
         socket
@@ -36,21 +60,71 @@ impl LanSwitch {
             warn!("Failed to increase LAN hub send buffer: {e}");
         }
 
-        let (stop_tx, stop_rx) = mpsc::channel();
+        let switch = Switch::new(peers, services.map(LanServices::new));
+        let stats = Arc::new(Mutex::new(switch.stats.clone()));
+        let shared = Arc::clone(&stats);
+        let stats_path = run_dir.join(LAN_STATS_FILE);
+        let (control_tx, control_rx) = mpsc::channel();
         let handle = thread::Builder::new()
             .name("intar-lan-switch".into())
-            .spawn(move || run_switch(&socket, &peers, &stop_rx))
+            .spawn(move || run_switch(&socket, switch, &shared, &stats_path, &control_rx))
             .map_err(|e| VmError::Qemu(format!("Failed to start LAN switch thread: {e}")))?;
 
         Ok(Self {
-            stop_tx: Some(stop_tx),
+            control_tx: Some(control_tx),
             handle: Some(handle),
+            stats,
         })
     }
 
+    /// Packet and byte counters per port since the switch started.
+    #[must_use]
+    pub fn stats(&self) -> LanStats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Write every frame the switch receives to pcap files as described by `config`, replacing a
+    /// capture already running.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the capture file cannot be created or the switch has stopped.
+    pub fn start_capture(&self, config: CaptureConfig) -> Result<(), VmError> {
+        let path = config.path.clone();
+        let writer = PcapWriter::create(config)?;
+        self.send(Control::StartCapture(writer))?;
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .capture = Some(path);
+        Ok(())
+    }
+
+    /// Stop a running capture, flushing what was captured so far.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the switch has stopped.
+    pub fn stop_capture(&self) -> Result<(), VmError> {
+        self.send(Control::StopCapture)?;
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .capture = None;
+        Ok(())
+    }
+
+    fn send(&self, control: Control) -> Result<(), VmError> {
+        self.control_tx
+            .as_ref()
+            .and_then(|tx| tx.send(control).ok())
+            .ok_or_else(|| VmError::NotPermitted("the LAN switch is not running".into()))
+    }
+
     pub fn stop(&mut self) {
-        if let Some(tx) = self.stop_tx.take() {
-            let _ = tx.send(());
+        if let Some(tx) = self.control_tx.take() {
+            let _ = tx.send(Control::Stop);
         }
         if let Some(handle) = self.handle.take() {
             let _ = handle.join();
@@ -64,19 +138,220 @@ impl Drop for LanSwitch {
     }
 }
 
-fn run_switch(socket: &UdpSocket, peers: &[SocketAddr], stop_rx: &mpsc::Receiver<()>) {
-    let mut mac_table: HashMap<[u8; 6], SocketAddr> = HashMap::new();
+/// Traffic through one switch port, i.e. one VM's shared LAN NIC.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanPortStats {
+    pub vm: String,
+    /// Frames (and their bytes) the VM sent onto the LAN.
+    pub sent_packets: u64,
+    pub sent_bytes: u64,
+    /// Frames (and their bytes) delivered to the VM.
+    pub received_packets: u64,
+    pub received_bytes: u64,
+}
+
+/// Counters of the shared LAN switch (`intar net stats`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanStats {
+    pub ports: Vec<LanPortStats>,
+    /// Frames sent to every other port: broadcast, multicast, or to a MAC not learned yet.
+    pub flooded: u64,
+    /// Frames from senders that are not a port of the switch, dropped.
+    pub dropped: u64,
+    /// File a running capture is writing to (`intar net capture`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<PathBuf>,
+}
+
+impl LanStats {
+    /// Load the counters the running TUI last wrote for the run at `run_dir`.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if no counters were written for the run (it has no shared
+    /// LAN), or another `VmError` if the file cannot be read.
+    pub fn load(run_dir: &Path) -> Result<Self, VmError> {
+        let content = match std::fs::read_to_string(run_dir.join(LAN_STATS_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(VmError::NotPermitted(
+                    "the run has no shared LAN statistics (single-VM runs have no LAN)".into(),
+                ));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&content)
+            .map_err(|e| VmError::Qemu(format!("Failed to parse LAN statistics: {e}")))
+    }
+
+    /// Counters of `vm`'s port.
+    #[must_use]
+    pub fn port(&self, vm: &str) -> Option<&LanPortStats> {
+        self.ports.iter().find(|port| port.vm == vm)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), VmError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| VmError::Qemu(format!("Failed to serialize LAN statistics: {e}")))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for LanStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .ports
+            .iter()
+            .map(|port| port.vm.len())
+            .max()
+            .unwrap_or(0)
+            .max(2);
+        writeln!(
+            f,
+            "{:<width$}  {:>10}  {:>12}  {:>10}  {:>12}",
+            "VM", "SENT PKTS", "SENT BYTES", "RECV PKTS", "RECV BYTES"
+        )?;
+        for port in &self.ports {
+            writeln!(
+                f,
+                "{:<width$}  {:>10}  {:>12}  {:>10}  {:>12}",
+                port.vm,
+                port.sent_packets,
+                port.sent_bytes,
+                port.received_packets,
+                port.received_bytes
+            )?;
+        }
+        write!(
+            f,
+            "\n{} frames flooded, {} dropped",
+            self.flooded, self.dropped
+        )?;
+        if let Some(capture) = &self.capture {
+            write!(f, "\nCapturing to {}", capture.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Forwarding state: the MAC address learned behind each port, the services port, and the
+/// counters.
+struct Switch {
+    peers: Vec<SocketAddr>,
+    mac_table: HashMap<[u8; 6], usize>,
+    services: Option<LanServices>,
+    stats: LanStats,
+}
+
+impl Switch {
+    fn new(peers: Vec<(String, SocketAddr)>, services: Option<LanServices>) -> Self {
+        let (names, peers): (Vec<String>, Vec<SocketAddr>) = peers.into_iter().unzip();
+        Self {
+            peers,
+            mac_table: HashMap::new(),
+            services,
+            stats: LanStats {
+                ports: names
+                    .into_iter()
+                    .map(|vm| LanPortStats {
+                        vm,
+                        ..LanPortStats::default()
+                    })
+                    .collect(),
+                ..LanStats::default()
+            },
+        }
+    }
+
+    /// Ports to send `frame` from `from` to. Unicast frames go only to the port that owns the
+    /// destination MAC; broadcast, multicast, and unknown destinations are flooded.
+    fn route(&mut self, frame: &[u8], from: SocketAddr) -> Vec<usize> {
+        let Some(source) = self.peers.iter().position(|peer| *peer == from) else {
+            self.stats.dropped += 1;
+            return Vec::new();
+        };
+        let (Ok(dst), Ok(src)) = (
+            <[u8; 6]>::try_from(&frame[0..6]),
+            <[u8; 6]>::try_from(&frame[6..12]),
+        ) else {
+            self.stats.dropped += 1;
+            return Vec::new();
+        };
+
+        let bytes = u64::try_from(frame.len()).unwrap_or(u64::MAX);
+        let sender = &mut self.stats.ports[source];
+        sender.sent_packets += 1;
+        sender.sent_bytes += bytes;
+        self.mac_table.insert(src, source);
+        if dst == LAN_SERVICES_MAC && self.services.is_some() {
+            return Vec::new();
+        }
+
+        // Broadcast is a multicast address too.
+        let is_multicast = (dst[0] & 0x01) == 0x01;
+        let targets: Vec<usize> = match self.mac_table.get(&dst) {
+            Some(&owner) if !is_multicast => {
+                if owner == source {
+                    Vec::new()
+                } else {
+                    vec![owner]
+                }
+            }
+            _ => {
+                self.stats.flooded += 1;
+                (0..self.peers.len()).filter(|&i| i != source).collect()
+            }
+        };
+        for &target in &targets {
+            let receiver = &mut self.stats.ports[target];
+            receiver.received_packets += 1;
+            receiver.received_bytes += bytes;
+        }
+        targets
+    }
+
+    /// The services port's reply to `frame` from `from`, counted as delivered to the sender.
+    fn answer(&mut self, frame: &[u8], from: SocketAddr) -> Option<Vec<u8>> {
+        let source = self.peers.iter().position(|peer| *peer == from)?;
+        let reply = self.services.as_ref()?.answer(frame)?;
+        let receiver = &mut self.stats.ports[source];
+        receiver.received_packets += 1;
+        receiver.received_bytes += u64::try_from(reply.len()).unwrap_or(u64::MAX);
+        Some(reply)
+    }
+}
+
+fn run_switch(
+    socket: &UdpSocket,
+    mut switch: Switch,
+    shared: &Mutex<LanStats>,
+    stats_path: &Path,
+    control_rx: &mpsc::Receiver<Control>,
+) {
     let mut buf = vec![0u8; 2048];
+    let mut last_write: Option<Instant> = None;
+    let mut capture: Option<PcapWriter> = None;
 
     info!(
         hub = %socket.local_addr().map_or_else(|_| "<unknown>".into(), |a| a.to_string()),
-        peers = peers.len(),
+        peers = switch.peers.len(),
         "LAN switch started"
     );
 
     loop {
-        if stop_rx.try_recv().is_ok() {
-            break;
+        match control_rx.try_recv() {
+            Ok(Control::Stop) => break,
+            Ok(Control::StartCapture(writer)) => {
+                finish_capture(capture.take());
+                info!(path = %writer.config().path.display(), "LAN capture started");
+                switch.stats.capture = Some(writer.config().path.clone());
+                capture = Some(writer);
+            }
+            Ok(Control::StopCapture) => {
+                finish_capture(capture.take());
+                switch.stats.capture = None;
+            }
+            Err(_) => {}
         }
 
         match socket.recv_from(&mut buf) {
@@ -85,40 +360,13 @@ fn run_switch(socket: &UdpSocket, peers: &[SocketAddr], stop_rx: &mpsc::Receiver
                     continue;
                 }
                 let frame = &buf[..n];
-
-                let dst: [u8; 6] = match frame[0..6].try_into() {
-                    Ok(v) => v,
-                    Err(_) => continue,
-                };
-                let src: [u8; 6] = match frame[6..12].try_into() {
-                    Ok(v) => v,
-                    Err(_) => continue,
-                };
-
-                mac_table.insert(src, from);
-
-                let is_broadcast = dst.iter().all(|b| *b == 0xff);
-                let is_multicast = (dst[0] & 0x01) == 0x01;
-
-                if is_broadcast || is_multicast {
-                    for peer in peers {
-                        if *peer != from {
-                            let _ = socket.send_to(frame, peer);
-                        }
-                    }
-                    continue;
+                capture_frame(&mut capture, &mut switch.stats, frame);
+                for target in switch.route(frame, from) {
+                    let _ = socket.send_to(frame, switch.peers[target]);
                 }
-
-                if let Some(target) = mac_table.get(&dst) {
-                    if *target != from {
-                        let _ = socket.send_to(frame, target);
-                    }
-                } else {
-                    for peer in peers {
-                        if *peer != from {
-                            let _ = socket.send_to(frame, peer);
-                        }
-                    }
+                if let Some(reply) = switch.answer(frame, from) {
+                    capture_frame(&mut capture, &mut switch.stats, &reply);
+                    let _ = socket.send_to(&reply, from);
                 }
             }
             Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
@@ -127,7 +375,99 @@ fn run_switch(socket: &UdpSocket, peers: &[SocketAddr], stop_rx: &mpsc::Receiver
                 thread::sleep(Duration::from_millis(100));
             }
         }
+
+        if last_write.is_none_or(|at| at.elapsed() >= STATS_WRITE_INTERVAL) {
+            if let Some(writer) = capture.as_mut()
+                && let Err(e) = writer.flush()
+            {
+                warn!("Failed to flush LAN capture: {e}");
+            }
+            publish_stats(&switch.stats, shared, stats_path);
+            last_write = Some(Instant::now());
+        }
     }
 
+    finish_capture(capture);
+    switch.stats.capture = None;
+    publish_stats(&switch.stats, shared, stats_path);
     info!("LAN switch stopped");
 }
+
+/// Append `frame` to a running capture, dropping the capture if it cannot be written.
+fn capture_frame(capture: &mut Option<PcapWriter>, stats: &mut LanStats, frame: &[u8]) {
+    if let Some(writer) = capture.as_mut()
+        && let Err(e) = writer.write_frame(frame, SystemTime::now())
+    {
+        warn!("LAN capture stopped: {e}");
+        *capture = None;
+        stats.capture = None;
+    }
+}
+
+fn finish_capture(capture: Option<PcapWriter>) {
+    if let Some(mut writer) = capture {
+        if let Err(e) = writer.flush() {
+            warn!("Failed to flush LAN capture: {e}");
+        }
+        info!(path = %writer.config().path.display(), "LAN capture stopped");
+    }
+}
+
+fn publish_stats(stats: &LanStats, shared: &Mutex<LanStats>, path: &Path) {
+    let mut current = shared.lock().unwrap_or_else(PoisonError::into_inner);
+    if *current == *stats {
+        return;
+    }
+    current.clone_from(stats);
+    drop(current);
+    if let Err(e) = stats.save(path) {
+        warn!("Failed to write LAN statistics: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(dst: [u8; 6], src: [u8; 6]) -> Vec<u8> {
+        let mut frame = Vec::from(dst);
+        frame.extend_from_slice(&src);
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.resize(60, 0);
+        frame
+    }
+
+    #[test]
+    fn test_switch_learns_macs() {
+        let peer = |port: u16| SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let mut switch = Switch::new(
+            vec![
+                ("a".into(), peer(4000)),
+                ("b".into(), peer(4001)),
+                ("c".into(), peer(4002)),
+            ],
+            None,
+        );
+        let (mac_a, mac_b) = ([2, 0, 0, 0, 0, 1], [2, 0, 0, 0, 0, 2]);
+
+        // Unknown destination and broadcast are flooded.
+        assert_eq!(switch.route(&frame(mac_b, mac_a), peer(4000)), vec![1, 2]);
+        assert_eq!(
+            switch.route(&frame([0xff; 6], mac_b), peer(4001)),
+            vec![0, 2]
+        );
+        // Both MACs are learned now, so unicast goes to one port only.
+        assert_eq!(switch.route(&frame(mac_b, mac_a), peer(4000)), vec![1]);
+        assert_eq!(switch.route(&frame(mac_a, mac_b), peer(4001)), vec![0]);
+        // Strangers are not switched.
+        assert!(switch.route(&frame(mac_a, mac_b), peer(5000)).is_empty());
+
+        let stats = &switch.stats;
+        assert_eq!(stats.flooded, 2);
+        assert_eq!(stats.dropped, 1);
+        let a = stats.port("a").unwrap();
+        assert_eq!((a.sent_packets, a.sent_bytes), (2, 120));
+        assert_eq!((a.received_packets, a.received_bytes), (2, 120));
+        assert_eq!(stats.port("c").unwrap().received_packets, 2);
+    }
+}
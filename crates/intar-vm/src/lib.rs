@@ -1,25 +1,107 @@
 mod actions;
+mod archive;
+mod audit;
+mod backend;
+mod bake;
+mod balloon;
+mod boot_timeouts;
+mod certificate;
+mod checkpoint;
+mod cloud;
 mod cloud_init;
+mod config;
+mod console;
 mod dirs;
+mod disk_space;
 mod error;
+mod external_action;
+mod host_key;
+mod host_process;
+mod host_sleep;
 mod host_socket;
+mod image_bundle;
 mod image_cache;
+mod inventory;
+mod kubeconfig;
+mod lan_backend;
+mod lan_services;
 mod lan_switch;
+mod libvirt;
+mod locale;
+mod migration;
+mod mock_services;
+mod pcap;
+mod peer_cache;
+mod plan;
+mod probe_history;
+mod probe_target;
+mod probe_trace;
+mod profile;
+mod progress;
 mod qemu;
+mod quiz;
+mod remote;
+mod resource_usage;
 mod scenario_runner;
+mod security;
 mod serial;
+mod ssh_host;
 mod state;
+mod template;
+mod terminal;
 mod vm_steps;
+mod workspace;
 
 pub use actions::*;
+pub use archive::*;
+pub use audit::*;
+pub use backend::*;
+pub use bake::*;
+pub use balloon::*;
+pub use boot_timeouts::*;
+pub use certificate::*;
+pub use checkpoint::*;
+pub use cloud::*;
 pub use cloud_init::*;
+pub use config::*;
+pub use console::*;
 pub use dirs::*;
+pub use disk_space::*;
 pub use error::*;
+pub use external_action::*;
+pub use host_key::*;
+pub use host_process::*;
+pub use host_sleep::*;
 pub use host_socket::*;
+pub use image_bundle::*;
 pub use image_cache::*;
+pub use inventory::*;
+pub use kubeconfig::*;
+pub use lan_backend::*;
+pub use lan_services::*;
 pub use lan_switch::*;
+pub use libvirt::*;
+pub use locale::*;
+pub use migration::*;
+pub use mock_services::*;
+pub use pcap::*;
+pub use peer_cache::*;
+pub use plan::*;
+pub use probe_history::*;
+pub use probe_target::*;
+pub use probe_trace::*;
+pub use profile::*;
+pub use progress::*;
 pub use qemu::*;
+pub use quiz::*;
+pub use remote::*;
+pub use resource_usage::*;
 pub use scenario_runner::*;
+pub use security::*;
 pub use serial::*;
+pub use ssh_host::*;
 pub use state::*;
+pub use template::*;
+pub use terminal::*;
 pub use vm_steps::*;
+pub use workspace::*;
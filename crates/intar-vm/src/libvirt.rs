@@ -0,0 +1,554 @@
+use crate::{HostSocket, VmError};
+use intar_core::{DiskIo, Ipv4Subnet};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Connection used when `libvirt.uri` is not set: the user's own session daemon, whose QEMU
+/// runs as the user and can reach the run directory.
+pub const DEFAULT_LIBVIRT_URI: &str = "qemu:///session";
+
+/// Alias of the user-mode NIC; libvirt names its QEMU netdev `host` plus the alias.
+const USER_NET_ALIAS: &str = "ua-mgmt";
+
+/// `libvirt` in `config.yaml`: how `intar start --backend libvirt` reaches libvirt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibvirtSettings {
+    /// Connection URI, as `virsh -c` takes it.
+    pub uri: String,
+}
+
+impl Default for LibvirtSettings {
+    fn default() -> Self {
+        Self {
+            uri: DEFAULT_LIBVIRT_URI.to_string(),
+        }
+    }
+}
+
+/// A VM run as a transient libvirt domain, which goes away when its QEMU exits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibvirtDomain {
+    pub uri: String,
+    pub name: String,
+}
+
+impl LibvirtDomain {
+    /// The domain of VM `vm` in run `run_id`, named so concurrent runs do not clash.
+    #[must_use]
+    pub fn new(uri: &str, run_id: &str, vm: &str) -> Self {
+        Self {
+            uri: uri.to_string(),
+            name: format!("intar-{run_id}-{vm}"),
+        }
+    }
+
+    /// Create the domain described in the XML file `xml`, which starts its QEMU.
+    ///
+    /// # Errors
+    /// Returns `VmError::Libvirt` with virsh's message if the domain cannot be created.
+    pub fn create(&self, xml: &Path) -> Result<(), VmError> {
+        virsh(&self.uri, [OsStr::new("create"), xml.as_os_str()]).map(|_| ())
+    }
+
+    /// Kill the domain's QEMU.
+    ///
+    /// # Errors
+    /// Returns `VmError::Libvirt` if the domain is not running.
+    pub fn destroy(&self) -> Result<(), VmError> {
+        virsh(&self.uri, ["destroy", self.name.as_str()]).map(|_| ())
+    }
+
+    /// Forward a host port into the guest on the user-mode NIC, with `rule` as QEMU's
+    /// `hostfwd` spells it. libvirt has no element for this, so it goes through the monitor.
+    ///
+    /// # Errors
+    /// Returns `VmError::Libvirt` if QEMU refuses the forward.
+    pub fn add_host_forward(&self, rule: &str) -> Result<(), VmError> {
+        let command = format!("hostfwd_add host{USER_NET_ALIAS} {rule}");
+        let output = virsh(
+            &self.uri,
+            [
+                "qemu-monitor-command",
+                "--hmp",
+                self.name.as_str(),
+                command.as_str(),
+            ],
+        )?;
+        // The monitor answers a successful command with nothing.
+        match output.trim() {
+            "" => Ok(()),
+            message => Err(VmError::Libvirt(format!(
+                "Forward {rule} of domain {}: {message}",
+                self.name
+            ))),
+        }
+    }
+
+    /// PID of the domain's QEMU, from the PID file libvirt keeps for it. `None` when libvirt
+    /// runs on another host or the file is not readable.
+    #[must_use]
+    pub fn pid(&self) -> Option<u32> {
+        std::fs::read_to_string(libvirt_pid_dir(&self.uri)?.join(format!("{}.pid", self.name)))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+/// Where libvirt at `uri` keeps the PID files of its domains, for daemons on this host.
+fn libvirt_pid_dir(uri: &str) -> Option<PathBuf> {
+    match uri {
+        "qemu:///system" => Some(PathBuf::from("/run/libvirt/qemu")),
+        "qemu:///session" => {
+            let runtime = std::env::var_os("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                .or_else(|| dirs::home_dir().map(|home| home.join(".cache")))?;
+            Some(runtime.join("libvirt/qemu/run"))
+        }
+        _ => None,
+    }
+}
+
+/// Check that `virsh` can reach libvirt at `uri`.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` naming what is missing.
+pub fn check_libvirt(uri: &str) -> Result<(), VmError> {
+    virsh(uri, ["version"]).map(|_| ()).map_err(|e| {
+        VmError::NotPermitted(format!(
+            "--backend libvirt cannot reach libvirt at {uri} ({e}); install virsh and start \
+             libvirtd or virtqemud, or set libvirt.uri in config.yaml"
+        ))
+    })
+}
+
+fn virsh<I, S>(uri: &str, args: I) -> Result<String, VmError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("virsh")
+        .args(["-q", "-c", uri])
+        .args(args)
+        .output()
+        .map_err(|e| VmError::Libvirt(format!("Failed to run virsh: {e}")))?;
+    if !output.status.success() {
+        return Err(VmError::Libvirt(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// What a domain is made of. libvirt sets up the machine, disks, NICs, and sockets, so they
+/// fall under its cgroups and security labels. What it has no element for goes on QEMU's
+/// command line: the QMP monitors intar talks to, `-incoming`, and LAN links it cannot express.
+#[derive(Debug)]
+pub struct DomainSpec<'a> {
+    pub name: &'a str,
+    /// `kvm`, `hvf`, or `qemu` for TCG.
+    pub domain_type: &'a str,
+    pub arch: &'a str,
+    pub memory_mb: u32,
+    pub vcpus: u32,
+    /// Host CPUs the vCPUs may run on (`--cpus`).
+    pub cpuset: Option<&'a str>,
+    /// Firmware image, loaded as QEMU's `-bios`.
+    pub loader: Option<&'a str>,
+    pub disks: &'a [DomainDisk<'a>],
+    pub interfaces: &'a [DomainInterface<'a>],
+    /// virtio-serial ports the guest agent opens.
+    pub channels: &'a [DomainChannel<'a>],
+    pub console: DomainConsole<'a>,
+    pub qemu_args: &'a [String],
+}
+
+/// A disk image the guest sees as a virtio disk.
+#[derive(Debug)]
+pub struct DomainDisk<'a> {
+    pub path: &'a Path,
+    /// `qcow2` or `raw`.
+    pub format: &'a str,
+    /// Guest device name, e.g. `vda`.
+    pub target: &'a str,
+    pub readonly: bool,
+    /// Cache, I/O, and throttling of the disk; libvirt's defaults when `None`.
+    pub io: Option<&'a DiskIo>,
+}
+
+/// A virtio NIC.
+#[derive(Debug)]
+pub enum DomainInterface<'a> {
+    /// QEMU's user-mode network on `subnet`. Port forwards are added once the domain runs
+    /// (see [`LibvirtDomain::add_host_forward`]).
+    User {
+        subnet: Ipv4Subnet,
+        mac: Option<&'a str>,
+    },
+    /// Ethernet frames sent from `local_port` to `remote_port` on loopback.
+    Udp {
+        local_port: u16,
+        remote_port: u16,
+        mac: Option<&'a str>,
+    },
+}
+
+/// A virtio-serial port named `name` in the guest, served on `socket`.
+#[derive(Debug)]
+pub struct DomainChannel<'a> {
+    pub name: &'a str,
+    pub socket: &'a HostSocket,
+}
+
+/// The guest's serial console, written to `log` and served on `socket` if there is one.
+#[derive(Debug)]
+pub struct DomainConsole<'a> {
+    pub log: &'a Path,
+    pub socket: Option<&'a HostSocket>,
+}
+
+/// The domain XML of `spec`.
+#[must_use]
+pub fn domain_xml(spec: &DomainSpec<'_>) -> String {
+    let (arch, machine) = match spec.arch {
+        "aarch64" | "arm64" => ("aarch64", "virt"),
+        _ => ("x86_64", "q35"),
+    };
+    let mut xml = format!(
+        "<domain type='{}' xmlns:qemu='http://libvirt.org/schemas/domain/qemu/1.0'>\n",
+        spec.domain_type
+    );
+    let _ = writeln!(xml, "  <name>{}</name>", escape(spec.name));
+    let _ = writeln!(xml, "  <memory unit='MiB'>{}</memory>", spec.memory_mb);
+    match spec.cpuset {
+        Some(cpuset) => {
+            let _ = writeln!(
+                xml,
+                "  <vcpu cpuset='{}'>{}</vcpu>",
+                escape(cpuset),
+                spec.vcpus
+            );
+        }
+        None => {
+            let _ = writeln!(xml, "  <vcpu>{}</vcpu>", spec.vcpus);
+        }
+    }
+    let _ = writeln!(
+        xml,
+        "  <os>\n    <type arch='{arch}' machine='{machine}'>hvm</type>"
+    );
+    if let Some(loader) = spec.loader {
+        let _ = writeln!(
+            xml,
+            "    <loader readonly='yes' type='rom'>{}</loader>",
+            escape(loader)
+        );
+    }
+    xml.push_str("  </os>\n");
+    if spec.domain_type != "qemu" {
+        xml.push_str("  <cpu mode='host-passthrough'/>\n");
+    } else if arch == "x86_64" {
+        xml.push_str("  <cpu mode='custom'>\n    <model>qemu64</model>\n  </cpu>\n");
+    }
+    xml.push_str("  <on_poweroff>destroy</on_poweroff>\n  <on_crash>destroy</on_crash>\n");
+    xml.push_str("  <devices>\n");
+    let emulated = spec.domain_type == "qemu";
+    for disk in spec.disks {
+        write_disk(&mut xml, disk, emulated);
+    }
+    for interface in spec.interfaces {
+        write_interface(&mut xml, interface);
+    }
+    for channel in spec.channels {
+        let (kind, source) = socket_source(channel.socket);
+        let _ = writeln!(
+            xml,
+            "    <channel type='{kind}'>\n      {source}\n      \
+             <target type='virtio' name='{}'/>\n    </channel>",
+            escape(channel.name)
+        );
+    }
+    let log = escape(&spec.console.log.display().to_string());
+    match spec.console.socket {
+        Some(socket) => {
+            let (kind, source) = socket_source(socket);
+            let _ = writeln!(
+                xml,
+                "    <serial type='{kind}'>\n      {source}\n      \
+                 <log file='{log}' append='on'/>\n    </serial>"
+            );
+        }
+        None => {
+            let _ = writeln!(
+                xml,
+                "    <serial type='file'>\n      <source path='{log}'/>\n    </serial>"
+            );
+        }
+    }
+    // Lets `intar vm scale` and auto-ballooning hand guest memory back to the host.
+    xml.push_str("    <memballoon model='virtio' autodeflate='on'/>\n");
+    xml.push_str(
+        "    <rng model='virtio'>\n      <backend model='random'>/dev/urandom</backend>\n    \
+         </rng>\n",
+    );
+    // Reports guest kernel panics as `GUEST_PANICKED` QMP events.
+    xml.push_str("    <panic model='pvpanic'/>\n");
+    xml.push_str("  </devices>\n");
+    if !spec.qemu_args.is_empty() {
+        xml.push_str("  <qemu:commandline>\n");
+        for arg in spec.qemu_args {
+            let _ = writeln!(xml, "    <qemu:arg value='{}'/>", escape(arg));
+        }
+        xml.push_str("  </qemu:commandline>\n");
+    }
+    xml.push_str("</domain>\n");
+    xml
+}
+
+fn write_disk(xml: &mut String, disk: &DomainDisk<'_>, emulated: bool) {
+    let mut driver = format!("<driver name='qemu' type='{}'", escape(disk.format));
+    if let Some(io) = disk.io {
+        let _ = write!(
+            driver,
+            " cache='{}' io='{}'",
+            io.effective_cache(emulated).as_str(),
+            io.effective_aio().as_str()
+        );
+        if io.discard {
+            driver.push_str(" discard='unmap' detect_zeroes='unmap'");
+        }
+    }
+    driver.push_str("/>");
+    let _ = writeln!(
+        xml,
+        "    <disk type='file' device='disk'>\n      {driver}\n      \
+         <source file='{}'/>\n      <target dev='{}' bus='virtio'/>",
+        escape(&disk.path.display().to_string()),
+        escape(disk.target)
+    );
+    if disk.readonly {
+        xml.push_str("      <readonly/>\n");
+    }
+    if let Some(io) = disk.io
+        && (io.iops.is_some() || io.bps.is_some())
+    {
+        xml.push_str("      <iotune>\n");
+        if let Some(iops) = io.iops {
+            let _ = writeln!(xml, "        <total_iops_sec>{iops}</total_iops_sec>");
+        }
+        if let Some(bps) = io.bps {
+            let _ = writeln!(xml, "        <total_bytes_sec>{bps}</total_bytes_sec>");
+        }
+        xml.push_str("      </iotune>\n");
+    }
+    xml.push_str("    </disk>\n");
+}
+
+fn write_interface(xml: &mut String, interface: &DomainInterface<'_>) {
+    let mac = match interface {
+        DomainInterface::User { subnet, mac } => {
+            let _ = writeln!(
+                xml,
+                "    <interface type='user'>\n      \
+                 <ip family='ipv4' address='{}' prefix='{}'/>\n      \
+                 <alias name='{USER_NET_ALIAS}'/>",
+                subnet.network(),
+                subnet.prefix()
+            );
+            mac
+        }
+        DomainInterface::Udp {
+            local_port,
+            remote_port,
+            mac,
+        } => {
+            let _ = writeln!(
+                xml,
+                "    <interface type='udp'>\n      \
+                 <source address='127.0.0.1' port='{remote_port}'>\n        \
+                 <local address='127.0.0.1' port='{local_port}'/>\n      </source>"
+            );
+            mac
+        }
+    };
+    if let Some(mac) = mac {
+        let _ = writeln!(xml, "      <mac address='{}'/>", escape(mac));
+    }
+    xml.push_str("      <model type='virtio'/>\n    </interface>\n");
+}
+
+/// The type and `<source>` of a character device that listens on `socket`.
+fn socket_source(socket: &HostSocket) -> (&'static str, String) {
+    match socket {
+        #[cfg(unix)]
+        HostSocket::Unix(path) => (
+            "unix",
+            format!(
+                "<source mode='bind' path='{}'/>",
+                escape(&path.display().to_string())
+            ),
+        ),
+        HostSocket::Tcp(addr) => (
+            "tcp",
+            format!(
+                "<source mode='bind' host='{}' service='{}'/>\n      <protocol type='raw'/>",
+                addr.ip(),
+                addr.port()
+            ),
+        ),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\'', "&apos;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_xml() {
+        let args = [
+            "-qmp".to_string(),
+            "tcp:127.0.0.1:4444,server,nowait".to_string(),
+        ];
+        let spec = DomainSpec {
+            name: "intar-calm-otter-web",
+            domain_type: "kvm",
+            arch: "x86_64",
+            memory_mb: 2048,
+            vcpus: 2,
+            cpuset: Some("2-5"),
+            loader: None,
+            disks: &[],
+            interfaces: &[],
+            channels: &[],
+            console: DomainConsole {
+                log: Path::new("/runs/logs/web/console.log"),
+                socket: None,
+            },
+            qemu_args: &args,
+        };
+        let xml = domain_xml(&spec);
+
+        assert!(xml.starts_with("<domain type='kvm' xmlns:qemu="));
+        assert!(xml.contains("<name>intar-calm-otter-web</name>"));
+        assert!(xml.contains("<memory unit='MiB'>2048</memory>"));
+        assert!(xml.contains("<vcpu cpuset='2-5'>2</vcpu>"));
+        assert!(xml.contains("<type arch='x86_64' machine='q35'>hvm</type>"));
+        assert!(xml.contains("<cpu mode='host-passthrough'/>"));
+        assert!(xml.contains("<source path='/runs/logs/web/console.log'/>"));
+        assert!(xml.contains("<memballoon model='virtio' autodeflate='on'/>"));
+        assert!(xml.contains("<qemu:arg value='tcp:127.0.0.1:4444,server,nowait'/>"));
+
+        let tcg = domain_xml(&DomainSpec {
+            domain_type: "qemu",
+            arch: "aarch64",
+            cpuset: None,
+            loader: Some("/usr/share/AAVMF/AAVMF_CODE.fd"),
+            qemu_args: &[],
+            ..spec
+        });
+        assert!(tcg.contains("<vcpu>2</vcpu>"));
+        assert!(tcg.contains("machine='virt'"));
+        assert!(tcg.contains("<loader readonly='yes' type='rom'>/usr/share/AAVMF/AAVMF_CODE.fd"));
+        assert!(!tcg.contains("<cpu"));
+        assert!(!tcg.contains("qemu:commandline"));
+    }
+
+    #[test]
+    fn test_domain_xml_devices() {
+        let io = DiskIo {
+            iops: Some(500),
+            ..DiskIo::default()
+        };
+        let disks = [
+            DomainDisk {
+                path: Path::new("/runs/a&b/web.qcow2"),
+                format: "qcow2",
+                target: "vda",
+                readonly: false,
+                io: Some(&io),
+            },
+            DomainDisk {
+                path: Path::new("/runs/web-cloud-init.iso"),
+                format: "raw",
+                target: "vdb",
+                readonly: true,
+                io: None,
+            },
+        ];
+        let interfaces = [
+            DomainInterface::User {
+                subnet: Ipv4Subnet::DEFAULT_MANAGEMENT,
+                mac: Some("52:54:00:12:34:56"),
+            },
+            DomainInterface::Udp {
+                local_port: 40001,
+                remote_port: 40000,
+                mac: None,
+            },
+        ];
+        let agent = HostSocket::tcp(5555);
+        let channels = [DomainChannel {
+            name: "intar.agent",
+            socket: &agent,
+        }];
+        let xml = domain_xml(&DomainSpec {
+            name: "intar-calm-otter-web",
+            domain_type: "qemu",
+            arch: "x86_64",
+            memory_mb: 2048,
+            vcpus: 2,
+            cpuset: None,
+            loader: None,
+            disks: &disks,
+            interfaces: &interfaces,
+            channels: &channels,
+            console: DomainConsole {
+                log: Path::new("/runs/logs/web/console.log"),
+                socket: Some(&agent),
+            },
+            qemu_args: &[],
+        });
+
+        assert!(xml.contains(
+            "<driver name='qemu' type='qcow2' cache='unsafe' io='threads' discard='unmap' \
+             detect_zeroes='unmap'/>"
+        ));
+        assert!(xml.contains("<source file='/runs/a&amp;b/web.qcow2'/>"));
+        assert!(xml.contains("<total_iops_sec>500</total_iops_sec>"));
+        assert!(xml.contains(
+            "<driver name='qemu' type='raw'/>\n      <source file='/runs/web-cloud-init.iso'/>\n      \
+             <target dev='vdb' bus='virtio'/>\n      <readonly/>"
+        ));
+        assert!(xml.contains("<ip family='ipv4' address='10.0.2.0' prefix='24'/>"));
+        assert!(xml.contains("<mac address='52:54:00:12:34:56'/>"));
+        assert!(xml.contains(
+            "<source address='127.0.0.1' port='40000'>\n        \
+             <local address='127.0.0.1' port='40001'/>"
+        ));
+        assert!(xml.contains(
+            "<channel type='tcp'>\n      <source mode='bind' host='127.0.0.1' service='5555'/>"
+        ));
+        assert!(xml.contains("<target type='virtio' name='intar.agent'/>"));
+        assert!(xml.contains("<log file='/runs/logs/web/console.log' append='on'/>"));
+    }
+
+    #[test]
+    fn test_libvirt_domain_name() {
+        let domain = LibvirtDomain::new(DEFAULT_LIBVIRT_URI, "calm-otter", "web");
+        assert_eq!(domain.name, "intar-calm-otter-web");
+        assert_eq!(domain.uri, "qemu:///session");
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{IntarConfig, VmError};
+use intar_core::{MessageCatalog, locale_language};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Environment variable that picks the interface language ahead of config.yaml.
+pub const LANGUAGE_ENV: &str = "INTAR_LANG";
+
+/// System locale variables, most specific first.
+const LOCALE_ENV: &[&str] = &["LC_ALL", "LC_MESSAGES", "LANG"];
+
+static LANGUAGE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Pick the language for this process, as `intar --lang` does, ahead of every other setting.
+/// Later calls keep the first language.
+pub fn set_language_override(language: &str) {
+    let _ = LANGUAGE_OVERRIDE.set(language.to_string());
+}
+
+/// The language to show intar and scenario content in: `--lang`, then `INTAR_LANG`, then
+/// `language` in config.yaml, then the system locale. `None` means the built-in English.
+#[must_use]
+pub fn resolve_language(config: &IntarConfig) -> Option<String> {
+    let env = |name: &str| std::env::var(name).ok();
+    let mut candidates = vec![
+        LANGUAGE_OVERRIDE.get().cloned(),
+        env(LANGUAGE_ENV),
+        config.language.clone(),
+    ];
+    candidates.extend(LOCALE_ENV.iter().map(|name| env(name)));
+    first_language(candidates)
+}
+
+fn first_language(candidates: impl IntoIterator<Item = Option<String>>) -> Option<String> {
+    let locale = candidates
+        .into_iter()
+        .flatten()
+        .find(|value| !value.trim().is_empty())?;
+    let language = locale_language(&locale)?;
+    let english = language == "en" || language.starts_with("en_") || language.starts_with("en-");
+    (!english).then(|| language.to_string())
+}
+
+/// Load the catalog for `language` from `locales_dir`, trying `de_DE.yaml` before `de.yaml`.
+/// A catalog maps English text to its translation, one `"English": "Translation"` per line.
+///
+/// # Errors
+/// Returns `VmError` if the language name is not a plain locale name or its catalog cannot be
+/// read or parsed. `Ok(None)` when there is no catalog for the language.
+pub fn load_message_catalog(
+    locales_dir: &Path,
+    language: &str,
+) -> Result<Option<MessageCatalog>, VmError> {
+    let valid = !language.is_empty()
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(VmError::InvalidPath(format!(
+            "'{language}' is not a language name"
+        )));
+    }
+
+    let mut names = vec![language];
+    if let Some((base, _)) = language.split_once(['_', '-']) {
+        names.push(base);
+    }
+    for name in names {
+        let path = locales_dir.join(format!("{name}.yaml"));
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let messages: HashMap<String, String> = if content.trim().is_empty() {
+            HashMap::new()
+        } else {
+            serde_yaml::from_str(&content)?
+        };
+        return Ok(Some(MessageCatalog::new(language, messages)));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some(values: &[Option<&str>]) -> Option<String> {
+        first_language(values.iter().map(|value| value.map(str::to_string)))
+    }
+
+    #[test]
+    fn test_first_language() {
+        assert_eq!(some(&[Some("de"), Some("fr_FR.UTF-8")]), Some("de".into()));
+        assert_eq!(
+            some(&[None, Some(""), Some("fr_FR.UTF-8")]),
+            Some("fr_FR".into())
+        );
+        assert_eq!(some(&[Some("en_US.UTF-8"), Some("de")]), None);
+        assert_eq!(some(&[Some("C.UTF-8")]), None);
+        assert_eq!(some(&[None, None]), None);
+    }
+
+    #[test]
+    fn test_load_message_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.yaml"),
+            "Probes: Prüfungen\n\"Port {target} is listening\": \"Port {target} ist offen\"\n",
+        )
+        .unwrap();
+
+        let catalog = load_message_catalog(dir.path(), "de_AT").unwrap().unwrap();
+        assert_eq!(catalog.language(), "de_AT");
+        assert_eq!(catalog.get("Probes"), "Prüfungen");
+        assert_eq!(
+            catalog.translate("Port 22 is listening"),
+            "Port 22 ist offen"
+        );
+
+        assert!(load_message_catalog(dir.path(), "fr").unwrap().is_none());
+        assert!(load_message_catalog(dir.path(), "../de").is_err());
+
+        std::fs::write(dir.path().join("es.yaml"), "- not a map\n").unwrap();
+        assert!(load_message_catalog(dir.path(), "es").is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{
+    AuditLog, AuditOperation, HostSocket, ImageCache, RunState, ScenarioRunner, SshHost, VmError,
+    VmInfo, detect_arch, find_free_ports, find_free_udp_port, load_run_scenario, migrate_to_file,
+    path_to_str, qmp_execute,
+};
+use futures_util::future::try_join_all;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Files that belong to the QEMU processes of this host and are not copied.
+const HOST_LOCAL_FILES: [&str; 2] = ["*.sock", "*.pid"];
+
+/// File in `run_dir` that VM `vm`'s state is migrated through.
+#[must_use]
+pub fn migration_file(run_dir: &Path, vm: &str) -> PathBuf {
+    run_dir.join(format!("{vm}.migration"))
+}
+
+/// Move the kept run in `run_dir` to `target`, reporting each step to `on_step`, and return
+/// what the target printed once its VMs were running. The target needs `intar` on the `PATH`
+/// of its ssh sessions, QEMU of at least this host's version, and the same architecture.
+///
+/// The run is copied with rsync while its VMs keep running, then QEMU saves each VM's memory
+/// and device state into the run directory and the VMs stop; a second rsync sends what changed
+/// meanwhile and the target starts the VMs from the saved state. The VMs are paused from the
+/// save until the target has loaded it. If anything fails once they are paused, they are
+/// started again here from the saved state. The local run is removed once the target has it.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` for ephemeral and cloud runs, `VmError::Remote` if ssh or
+/// the target's intar fails, `VmError::Migration` if rsync fails, and another `VmError` if the
+/// VMs cannot be saved.
+pub async fn migrate_run(
+    run_dir: &Path,
+    target: &SshHost,
+    mut on_step: impl FnMut(&str),
+) -> Result<String, VmError> {
+    let mut state = RunState::load(run_dir)?;
+    if state.ephemeral_dir.is_some() {
+        return Err(VmError::NotPermitted(
+            "the disks of an ephemeral run are in this host's RAM and cannot be migrated".into(),
+        ));
+    }
+    if state.cloud.is_some() {
+        return Err(VmError::NotPermitted(
+            "the VMs of a cloud run are cloud instances, not QEMU processes to migrate".into(),
+        ));
+    }
+    let run_name = run_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| VmError::InvalidPath(run_dir.display().to_string()))?;
+
+    on_step(&format!(
+        "Preparing run {run_name} on {}",
+        target.destination
+    ));
+    let remote_dir = target
+        .run(&format!("intar migrate-receive {run_name} --prepare"))
+        .await?;
+    let remote_dir = remote_dir.trim();
+    if !remote_dir.starts_with('/') {
+        return Err(VmError::Migration(format!(
+            "{} answered with '{remote_dir}' instead of a run directory",
+            target.destination
+        )));
+    }
+
+    on_step("Copying the run while its VMs keep running");
+    rsync(run_dir, target, remote_dir).await?;
+
+    on_step("Saving the VMs' memory");
+    let mut audit = AuditLog::open(run_dir, state.audit.as_ref())?;
+    audit.record(AuditOperation::RunMigrated, target.destination.clone())?;
+    state.audit = Some(audit.head());
+    save_vms(run_dir, &state).await?;
+
+    let arrived = async {
+        on_step("Copying what changed meanwhile");
+        rsync(run_dir, target, remote_dir).await?;
+        on_step(&format!("Starting the VMs on {}", target.destination));
+        target
+            .run(&format!("intar migrate-receive {run_name}"))
+            .await
+    }
+    .await;
+    match arrived {
+        Ok(summary) => {
+            std::fs::remove_dir_all(run_dir)?;
+            Ok(summary)
+        }
+        Err(e) => {
+            warn!("Migrating run {} failed: {}", run_name, e);
+            on_step("Starting the VMs here again");
+            match receive_run(run_dir, None).await {
+                Ok(mut runner) => {
+                    runner.detach()?;
+                    Err(VmError::Migration(format!(
+                        "{e}; the run continues on this host"
+                    )))
+                }
+                Err(restart) => Err(VmError::Migration(format!(
+                    "{e}; starting the VMs on this host again failed too: {restart}"
+                ))),
+            }
+        }
+    }
+}
+
+/// Start the VMs of a run that `intar migrate-run` copied to `run_dir` from their saved state,
+/// with new host ports recorded in its state file, and wait for their agents. Overlays are
+/// pointed at the base images of `image_cache`, which downloads missing ones; without a cache
+/// they are left as they are.
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` if the run has no saved VMs, and another `VmError` if an
+/// image, port, or VM cannot be set up.
+pub async fn receive_run(
+    run_dir: &Path,
+    image_cache: Option<&ImageCache>,
+) -> Result<ScenarioRunner, VmError> {
+    let mut state = RunState::load(run_dir)?;
+    if !state.incoming_migration {
+        return Err(VmError::NotPermitted(format!(
+            "run {} has no migrated VMs to start",
+            run_dir.display()
+        )));
+    }
+
+    if let Some(image_cache) = image_cache {
+        let scenario = load_run_scenario(run_dir)?;
+        let arch = detect_arch();
+        for vm in &scenario.vms {
+            let source = scenario
+                .images
+                .get(&vm.image)
+                .and_then(|image| image.source_for_arch(&arch))
+                .ok_or_else(|| {
+                    VmError::Migration(format!(
+                        "image '{}' of VM {} has no source for {arch}",
+                        vm.image, vm.name
+                    ))
+                })?;
+            let base_image = image_cache.ensure_image(source).await?;
+            rebase_overlay(&run_dir.join(format!("{}.qcow2", vm.name)), &base_image)?;
+        }
+    }
+
+    reassign_ports(&mut state)?;
+    state.save(run_dir)?;
+
+    // Agent binaries are only installed into new VMs.
+    let mut runner = ScenarioRunner::resume(run_dir, Vec::new(), Vec::new()).await?;
+    runner.wait_for_agents().await?;
+    Ok(runner)
+}
+
+/// Migrate every VM of the run to its migration file, leaving them stopped, and mark the run as
+/// migrated. If any VM fails, those already saved are resumed.
+async fn save_vms(run_dir: &Path, state: &RunState) -> Result<(), VmError> {
+    let sockets: Vec<HostSocket> = state
+        .vms
+        .iter()
+        .map(|vm| qmp_socket(run_dir, vm))
+        .collect::<Result<_, _>>()?;
+    let saved = try_join_all(state.vms.iter().zip(&sockets).map(|(vm, socket)| {
+        let state_file = migration_file(run_dir, &vm.name);
+        async move {
+            info!("Saving the state of VM {} for migration", vm.name);
+            migrate_to_file(socket, &state_file).await
+        }
+    }))
+    .await;
+    if let Err(e) = saved {
+        for socket in &sockets {
+            let _ = qmp_execute(socket, "migrate_cancel", None).await;
+            let _ = qmp_execute(socket, "cont", None).await;
+        }
+        return Err(e);
+    }
+
+    let mut migrated = state.clone();
+    migrated.incoming_migration = true;
+    migrated.save(run_dir)?;
+
+    for (vm, socket) in state.vms.iter().zip(&sockets) {
+        let _ = qmp_execute(socket, "quit", None).await;
+        let pid_file = run_dir.join(format!("{}-qemu.pid", vm.name));
+        if pid_file.exists() {
+            std::fs::remove_file(pid_file)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn qmp_socket(run_dir: &Path, vm: &VmInfo) -> Result<HostSocket, VmError> {
+    Ok(HostSocket::unix(
+        run_dir.join(format!("{}-qmp.sock", vm.name)),
+    ))
+}
+
+#[cfg(windows)]
+fn qmp_socket(_run_dir: &Path, vm: &VmInfo) -> Result<HostSocket, VmError> {
+    vm.socket_ports
+        .first()
+        .copied()
+        .map(HostSocket::tcp)
+        .ok_or_else(|| VmError::Qmp(format!("No QMP port recorded for VM {}", vm.name)))
+}
+
+/// Give the run's VMs host ports that are free here. Mock services keep theirs, since the
+/// guests were configured with them.
+fn reassign_ports(state: &mut RunState) -> Result<(), VmError> {
+    let count = state
+        .vms
+        .iter()
+        .map(|vm| 1 + usize::from(vm.api_port.is_some()) + vm.socket_ports.len())
+        .sum();
+    let mut ports = find_free_ports(count)?.into_iter();
+    let mut next_port = || ports.next().ok_or(VmError::NoFreePort);
+    for vm in &mut state.vms {
+        vm.ssh_port = next_port()?;
+        if vm.api_port.is_some() {
+            vm.api_port = Some(next_port()?);
+        }
+        for port in &mut vm.socket_ports {
+            *port = next_port()?;
+        }
+        if vm.lan_port.is_some() {
+            vm.lan_port = Some(find_free_udp_port()?);
+        }
+    }
+    if state.lan_hub_port.is_some() {
+        state.lan_hub_port = Some(find_free_udp_port()?);
+    }
+    Ok(())
+}
+
+/// Point `overlay` at `base_image` without touching its data; the base image is the same one,
+/// only cached at another path here.
+fn rebase_overlay(overlay: &Path, base_image: &Path) -> Result<(), VmError> {
+    let output = std::process::Command::new("qemu-img")
+        .args(["rebase", "-u", "-F", "qcow2", "-b"])
+        .arg(base_image)
+        .arg(overlay)
+        .output()
+        .map_err(|e| VmError::Qemu(format!("Failed to run qemu-img rebase: {e}")))?;
+    if !output.status.success() {
+        return Err(VmError::Qemu(format!(
+            "qemu-img rebase of {} failed: {}",
+            overlay.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Bring `remote_dir` on `target` up to date with `run_dir`.
+async fn rsync(run_dir: &Path, target: &SshHost, remote_dir: &str) -> Result<(), VmError> {
+    let output = Command::new("rsync")
+        .args(rsync_args(run_dir, target, remote_dir)?)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| VmError::Migration(format!("Failed to run rsync: {e}")))?;
+    if !output.status.success() {
+        return Err(VmError::Migration(format!(
+            "rsync to {} failed ({}): {}",
+            target.destination,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Arguments of rsync copying `run_dir` into `remote_dir`. Overlays are updated in place by
+/// delta, so the second copy only sends the blocks written since the first.
+fn rsync_args(run_dir: &Path, target: &SshHost, remote_dir: &str) -> Result<Vec<String>, VmError> {
+    let mut args: Vec<String> = [
+        "--archive",
+        "--inplace",
+        "--no-whole-file",
+        "--protect-args",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    args.extend(["-e".to_string(), target.rsync_shell()]);
+    args.extend(
+        HOST_LOCAL_FILES
+            .iter()
+            .map(|pattern| format!("--exclude={pattern}")),
+    );
+    args.push(format!("{}/", path_to_str(run_dir)?));
+    args.push(format!("{}:{remote_dir}/", target.destination));
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LanBackend;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_rsync_args() {
+        let target = SshHost {
+            destination: "lab@server".into(),
+            port: Some(2222),
+        };
+        let args = rsync_args(
+            Path::new("/runs/calm-otter"),
+            &target,
+            "/home/lab/runs/calm-otter",
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            [
+                "--archive",
+                "--inplace",
+                "--no-whole-file",
+                "--protect-args",
+                "-e",
+                "ssh -o BatchMode=yes -o ConnectTimeout=10 -p 2222",
+                "--exclude=*.sock",
+                "--exclude=*.pid",
+                "/runs/calm-otter/",
+                "lab@server:/home/lab/runs/calm-otter/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reassign_ports_keeps_mock_ports() {
+        let vm = |name: &str, api_port| VmInfo {
+            name: name.into(),
+            ssh_port: 1,
+            api_port,
+            image: "ubuntu".into(),
+            lan_port: Some(2),
+            socket_ports: Vec::new(),
+            host_key_fingerprint: None,
+        };
+        let mut state = RunState {
+            scenario_name: "lab".into(),
+            vms: vec![vm("web", None), vm("k8s", Some(3))],
+            seed: None,
+            fault_selections: BTreeMap::new(),
+            triggers: Vec::new(),
+            lan_hub_port: Some(4),
+            lan_backend: LanBackend::Switch,
+            ephemeral_dir: None,
+            mock_ports: BTreeMap::from([("api".to_string(), 5)]),
+            expires_at_ms: None,
+            incoming_migration: true,
+            remote: None,
+            libvirt_uri: None,
+            cloud: None,
+            paranoid: false,
+            host_hooks: false,
+            audit: None,
+        };
+        reassign_ports(&mut state).unwrap();
+
+        let tcp = [state.vms[0].ssh_port, state.vms[1].ssh_port];
+        assert!(tcp.iter().all(|port| *port > 5));
+        assert_ne!(tcp[0], tcp[1]);
+        assert!(state.vms[0].api_port.is_none());
+        assert!(state.vms[1].api_port.is_some_and(|port| port > 5));
+        assert!(state.vms.iter().all(|vm| vm.lan_port != Some(2)));
+        assert_ne!(state.lan_hub_port, Some(4));
+        assert_eq!(state.mock_ports["api"], 5);
+    }
+}
@@ -0,0 +1,466 @@
+use crate::{VmError, find_free_port, find_free_udp_port};
+use intar_core::{MockRoute, MockService, MockServiceKind};
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tracing::{debug, info, warn};
+
+/// Directory in the run directory where each mock service logs what guests sent it, one JSON
+/// object per line in `<name>.jsonl`.
+pub const MOCK_SERVICES_DIR: &str = "mock-services";
+
+/// Largest HTTP request or mail a mock service reads from one connection.
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+
+/// The run's `mock_service` servers, each on a host loopback port. Stopped when dropped.
+pub struct MockServices {
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl MockServices {
+    /// Pick a free loopback port (TCP, or UDP for NTP) for each service.
+    ///
+    /// # Errors
+    /// Returns `VmError::NoFreePort` if a port cannot be found.
+    pub fn allocate_ports(services: &[MockService]) -> Result<BTreeMap<String, u16>, VmError> {
+        services
+            .iter()
+            .map(|service| {
+                let port = if service.kind.is_udp() {
+                    find_free_udp_port()?
+                } else {
+                    find_free_port()?
+                };
+                Ok((service.name.clone(), port))
+            })
+            .collect()
+    }
+
+    /// Serve every service with a port in `ports`. Must be called within a Tokio runtime.
+    ///
+    /// # Errors
+    /// Returns `VmError` if a port cannot be bound or the log directory cannot be created.
+    pub fn spawn(
+        services: &[MockService],
+        ports: &BTreeMap<String, u16>,
+        run_dir: &Path,
+    ) -> Result<Self, VmError> {
+        let log_dir = run_dir.join(MOCK_SERVICES_DIR);
+        std::fs::create_dir_all(&log_dir)?;
+
+        let mut tasks = Vec::new();
+        for service in services {
+            let Some(&port) = ports.get(&service.name) else {
+                continue;
+            };
+            let bind_error = |e: std::io::Error| {
+                VmError::Qemu(format!(
+                    "Failed to bind mock service '{}' on port {port}: {e}",
+                    service.name
+                ))
+            };
+            let log = Arc::new(log_dir.join(format!("{}.jsonl", service.name)));
+            let task = if service.kind.is_udp() {
+                let socket = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, port))
+                    .and_then(|socket| socket.set_nonblocking(true).map(|()| socket))
+                    .and_then(UdpSocket::from_std)
+                    .map_err(bind_error)?;
+                tokio::spawn(serve_ntp(socket, log))
+            } else {
+                let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+                    .and_then(|listener| listener.set_nonblocking(true).map(|()| listener))
+                    .and_then(TcpListener::from_std)
+                    .map_err(bind_error)?;
+                tokio::spawn(accept_loop(listener, service.clone(), log))
+            };
+            info!(
+                "Mock {} service '{}' listening on 127.0.0.1:{}",
+                service.kind.label(),
+                service.name,
+                port
+            );
+            tasks.push(task);
+        }
+        Ok(Self { tasks })
+    }
+
+    pub fn stop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for MockServices {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn accept_loop(listener: TcpListener, service: MockService, log: Arc<PathBuf>) {
+    let service = Arc::new(service);
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!("Mock service '{}' accept failed: {}", service.name, e);
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let service = Arc::clone(&service);
+        let log = Arc::clone(&log);
+        tokio::spawn(async move {
+            let result = match &service.kind {
+                MockServiceKind::Http { routes } => serve_http(stream, routes, &log).await,
+                MockServiceKind::Smtp => serve_smtp(stream, &service.name, &log).await,
+                MockServiceKind::Ntp => Ok(()),
+            };
+            if let Err(e) = result {
+                debug!("Mock service '{}' connection ended: {}", service.name, e);
+            }
+        });
+    }
+}
+
+async fn serve_http(stream: TcpStream, routes: &[MockRoute], log: &Path) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read.take(MAX_REQUEST_BYTES));
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await? == 0 {
+        return Ok(());
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    let mut content_length = 0u64;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    let mut body = Vec::new();
+    (&mut reader)
+        .take(content_length)
+        .read_to_end(&mut body)
+        .await?;
+
+    append_log(
+        log,
+        &serde_json::json!({
+            "method": method,
+            "path": path,
+            "body": String::from_utf8_lossy(&body),
+        }),
+    );
+    write
+        .write_all(&http_response(routes, &method, &path))
+        .await?;
+    write.shutdown().await
+}
+
+/// The first matching route's canned response, or 404. Connections are closed after each
+/// response.
+fn http_response(routes: &[MockRoute], method: &str, path: &str) -> Vec<u8> {
+    let (status, content_type, body) = routes
+        .iter()
+        .find(|route| route.matches(method, path))
+        .map_or_else(
+            || {
+                (
+                    404,
+                    "text/plain",
+                    format!("no mock route for {method} {path}\n"),
+                )
+            },
+            |route| {
+                (
+                    route.status,
+                    route.content_type.as_str(),
+                    route.body.clone(),
+                )
+            },
+        );
+    let body = if method.eq_ignore_ascii_case("HEAD") {
+        String::new()
+    } else {
+        body
+    };
+    format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason_phrase(status),
+        body.len()
+    )
+    .into_bytes()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Status",
+    }
+}
+
+/// Accept every message and log it; nothing is relayed.
+async fn serve_smtp(stream: TcpStream, name: &str, log: &Path) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read.take(MAX_REQUEST_BYTES));
+    write
+        .write_all(format!("220 {name} ESMTP intar mock\r\n").as_bytes())
+        .await?;
+
+    let mut from = String::new();
+    let mut to: Vec<String> = Vec::new();
+    loop {
+        let Some(line) = read_smtp_line(&mut reader).await? else {
+            return Ok(());
+        };
+        let verb = line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let reply = match verb.as_str() {
+            "HELO" | "EHLO" => format!("250 {name}"),
+            "MAIL" => {
+                from = smtp_address(&line);
+                "250 OK".into()
+            }
+            "RCPT" => {
+                to.push(smtp_address(&line));
+                "250 OK".into()
+            }
+            "DATA" if to.is_empty() => "503 RCPT first".into(),
+            "DATA" => {
+                write
+                    .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                    .await?;
+                let mut data = String::new();
+                loop {
+                    let Some(line) = read_smtp_line(&mut reader).await? else {
+                        return Ok(());
+                    };
+                    if line == "." {
+                        break;
+                    }
+                    // Undo dot-stuffing.
+                    data.push_str(line.strip_prefix('.').unwrap_or(&line));
+                    data.push('\n');
+                }
+                append_log(
+                    log,
+                    &serde_json::json!({ "from": from, "to": to, "data": data }),
+                );
+                from.clear();
+                to.clear();
+                "250 OK: queued".into()
+            }
+            "RSET" => {
+                from.clear();
+                to.clear();
+                "250 OK".into()
+            }
+            "NOOP" => "250 OK".into(),
+            "QUIT" => {
+                write.write_all(b"221 Bye\r\n").await?;
+                return write.shutdown().await;
+            }
+            _ => "502 Command not implemented".into(),
+        };
+        write.write_all(format!("{reply}\r\n").as_bytes()).await?;
+    }
+}
+
+async fn read_smtp_line(
+    reader: &mut (impl AsyncBufRead + Unpin),
+) -> std::io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// The address of `MAIL FROM:<a@b>` or `RCPT TO:<a@b>`.
+fn smtp_address(command: &str) -> String {
+    command
+        .split_once(':')
+        .map_or("", |(_, address)| address)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_matches(['<', '>'])
+        .to_string()
+}
+
+async fn serve_ntp(socket: UdpSocket, log: Arc<PathBuf>) {
+    let mut buf = [0u8; 512];
+    loop {
+        let (n, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Mock NTP service receive failed: {}", e);
+                continue;
+            }
+        };
+        let Some(response) = ntp_response(&buf[..n], SystemTime::now()) else {
+            continue;
+        };
+        append_log(&log, &serde_json::json!({ "request": "time" }));
+        if let Err(e) = socket.send_to(&response, from).await {
+            debug!("Mock NTP service reply failed: {}", e);
+        }
+    }
+}
+
+/// Server reply to an NTP client request, or `None` if `request` is not one.
+fn ntp_response(request: &[u8], now: SystemTime) -> Option<[u8; 48]> {
+    let header = request.get(..48)?;
+    let version = (header[0] >> 3) & 0x07;
+    let mode = header[0] & 0x07;
+    if mode != 3 {
+        return None;
+    }
+
+    let stamp = ntp_timestamp(now);
+    let mut response = [0u8; 48];
+    // No leap warning, the client's version, server mode.
+    response[0] = (version << 3) | 4;
+    // A primary server on a local clock, ~1 µs precision.
+    response[1] = 1;
+    response[2] = header[2];
+    response[3] = 0xec;
+    response[12..16].copy_from_slice(b"LOCL");
+    response[16..24].copy_from_slice(&stamp);
+    response[24..32].copy_from_slice(&header[40..48]);
+    response[32..40].copy_from_slice(&stamp);
+    response[40..48].copy_from_slice(&stamp);
+    Some(response)
+}
+
+/// 64-bit NTP timestamp: seconds since 1900 and a binary fraction, big-endian.
+fn ntp_timestamp(at: SystemTime) -> [u8; 8] {
+    const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    // Era 0 ends in 2036; clients resolve the era from their own clock.
+    let seconds =
+        u32::try_from((since_epoch.as_secs() + NTP_UNIX_OFFSET) & 0xffff_ffff).unwrap_or_default();
+    let fraction = u32::try_from((u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000)
+        .unwrap_or(u32::MAX);
+    let mut stamp = [0u8; 8];
+    stamp[..4].copy_from_slice(&seconds.to_be_bytes());
+    stamp[4..].copy_from_slice(&fraction.to_be_bytes());
+    stamp
+}
+
+fn append_log(path: &Path, entry: &serde_json::Value) {
+    let at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default();
+    let mut entry = entry.clone();
+    entry["at_ms"] = at_ms.into();
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{entry}"));
+    if let Err(e) = result {
+        warn!(
+            "Failed to log mock service request to {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_http_response_matches_routes() {
+        let routes = vec![MockRoute {
+            path: "/v1/charge".into(),
+            method: Some("POST".into()),
+            status: 201,
+            content_type: "application/json".into(),
+            body: "{\"id\":\"ch_1\"}".into(),
+        }];
+
+        let created = String::from_utf8(http_response(&routes, "POST", "/v1/charge")).unwrap();
+        assert!(created.starts_with("HTTP/1.1 201 Created\r\n"));
+        assert!(created.contains("Content-Type: application/json\r\nContent-Length: 13\r\n"));
+        assert!(created.ends_with("\r\n\r\n{\"id\":\"ch_1\"}"));
+
+        let missing = String::from_utf8(http_response(&routes, "GET", "/v1/charge")).unwrap();
+        assert!(missing.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(missing.ends_with("no mock route for GET /v1/charge\n"));
+    }
+
+    #[test]
+    fn test_smtp_address() {
+        assert_eq!(
+            smtp_address("MAIL FROM:<ops@example.com> SIZE=100"),
+            "ops@example.com"
+        );
+        assert_eq!(smtp_address("RCPT TO: <root@web>"), "root@web");
+        assert_eq!(smtp_address("MAIL"), "");
+    }
+
+    #[test]
+    fn test_ntp_response() {
+        let mut request = [0u8; 48];
+        request[0] = (4 << 3) | 3;
+        request[40..48].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+
+        let response = ntp_response(&request, now).unwrap();
+        assert_eq!(response[0], (4 << 3) | 4);
+        assert_eq!(response[1], 1);
+        assert_eq!(&response[24..32], &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let seconds = u32::from_be_bytes(response[40..44].try_into().unwrap());
+        assert_eq!(u64::from(seconds), 1_700_000_000 + 2_208_988_800);
+        let fraction = u32::from_be_bytes(response[44..48].try_into().unwrap());
+        assert_eq!(fraction, 1 << 31);
+
+        // Server replies and short packets are ignored.
+        request[0] = (4 << 3) | 4;
+        assert!(ntp_response(&request, now).is_none());
+        assert!(ntp_response(&[0x23; 12], now).is_none());
+    }
+}
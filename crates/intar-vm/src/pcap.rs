@@ -0,0 +1,214 @@
+use crate::VmError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Capture file the TUI writes into the run directory when no path is given.
+pub const DEFAULT_CAPTURE_FILE: &str = "capture.pcap";
+
+const DEFAULT_MAX_FILE_MB: u64 = 100;
+const DEFAULT_MAX_FILES: u32 = 5;
+
+/// Longest frame kept; the shared LAN's frames are far shorter.
+const SNAPLEN: u16 = u16::MAX;
+const LINKTYPE_ETHERNET: u32 = 1;
+const GLOBAL_HEADER_LEN: u64 = 24;
+const RECORD_HEADER_LEN: u64 = 16;
+
+/// Where a shared LAN capture is written and how much of it is kept (`intar net capture`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    /// File being written. Rotated files are `<stem>.1.pcap` (newest) up to `<stem>.<n>.pcap`.
+    pub path: PathBuf,
+    /// Start a new file once the current one would grow past this size.
+    pub max_file_bytes: u64,
+    /// Files kept, including the current one; the oldest is deleted on rotation.
+    pub max_files: u32,
+}
+
+impl CaptureConfig {
+    /// Capture to `path` in files of at most 100 MB, keeping 5 of them.
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_file_bytes: DEFAULT_MAX_FILE_MB * 1024 * 1024,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_file_mb(mut self, megabytes: u64) -> Self {
+        self.max_file_bytes = megabytes.saturating_mul(1024 * 1024);
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_files(mut self, files: u32) -> Self {
+        self.max_files = files;
+        self
+    }
+
+    /// Path of the `index`th file, 0 being the one currently written.
+    #[must_use]
+    pub fn file(&self, index: u32) -> PathBuf {
+        if index == 0 {
+            return self.path.clone();
+        }
+        let stem = self
+            .path
+            .file_stem()
+            .map_or_else(|| "capture".into(), |s| s.to_string_lossy().into_owned());
+        let name = match self.path.extension() {
+            Some(ext) => format!("{stem}.{index}.{}", ext.to_string_lossy()),
+            None => format!("{stem}.{index}"),
+        };
+        self.path.with_file_name(name)
+    }
+}
+
+/// Writes Ethernet frames as classic pcap files, rotating them by size.
+pub struct PcapWriter {
+    config: CaptureConfig,
+    file: BufWriter<File>,
+    written: u64,
+}
+
+impl PcapWriter {
+    /// Create the capture file (replacing an existing one) and write its header.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if the limits cannot hold a single frame, or another
+    /// `VmError` if the file cannot be created.
+    pub fn create(config: CaptureConfig) -> Result<Self, VmError> {
+        if config.max_files == 0 || config.max_file_bytes <= GLOBAL_HEADER_LEN + RECORD_HEADER_LEN {
+            return Err(VmError::NotPermitted(
+                "a capture needs at least one file and room for one frame".into(),
+            ));
+        }
+        let file = Self::open(&config.path)?;
+        Ok(Self {
+            config,
+            file,
+            written: GLOBAL_HEADER_LEN,
+        })
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    /// Append `frame` as seen at `at`, rotating first if it would not fit the current file.
+    ///
+    /// # Errors
+    /// Returns `VmError` if writing or rotating fails.
+    pub fn write_frame(&mut self, frame: &[u8], at: SystemTime) -> Result<(), VmError> {
+        let kept = &frame[..frame.len().min(usize::from(SNAPLEN))];
+        let original = u32::try_from(frame.len()).unwrap_or(u32::MAX);
+        let included = u32::from(SNAPLEN).min(original);
+        let record = RECORD_HEADER_LEN + u64::from(included);
+        if self.written + record > self.config.max_file_bytes && self.written > GLOBAL_HEADER_LEN {
+            self.rotate()?;
+        }
+
+        let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = u32::try_from(since_epoch.as_secs()).unwrap_or(u32::MAX);
+        self.file.write_all(&seconds.to_le_bytes())?;
+        self.file
+            .write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        self.file.write_all(&included.to_le_bytes())?;
+        self.file.write_all(&original.to_le_bytes())?;
+        self.file.write_all(kept)?;
+        self.written += record;
+        Ok(())
+    }
+
+    /// Push buffered frames to disk so the file can be read while the capture runs.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the write fails.
+    pub fn flush(&mut self) -> Result<(), VmError> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Shift every kept file up by one, dropping the oldest, and start a fresh current file.
+    fn rotate(&mut self) -> Result<(), VmError> {
+        self.file.flush()?;
+        for index in (1..self.config.max_files).rev() {
+            let from = self.config.file(index - 1);
+            if !from.exists() {
+                continue;
+            }
+            let to = self.config.file(index);
+            // Windows does not replace an existing file on rename.
+            let _ = std::fs::remove_file(&to);
+            std::fs::rename(&from, &to)?;
+        }
+        self.file = Self::open(&self.config.path)?;
+        self.written = GLOBAL_HEADER_LEN;
+        Ok(())
+    }
+
+    fn open(path: &Path) -> Result<BufWriter<File>, VmError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&0xa1b2_c3d4u32.to_le_bytes())?;
+        file.write_all(&2u16.to_le_bytes())?;
+        file.write_all(&4u16.to_le_bytes())?;
+        file.write_all(&0i32.to_le_bytes())?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(&u32::from(SNAPLEN).to_le_bytes())?;
+        file.write_all(&LINKTYPE_ETHERNET.to_le_bytes())?;
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_capture_file_names() {
+        let config = CaptureConfig::new("/runs/lab/capture.pcap".into());
+        assert_eq!(config.file(0), PathBuf::from("/runs/lab/capture.pcap"));
+        assert_eq!(config.file(2), PathBuf::from("/runs/lab/capture.2.pcap"));
+        assert_eq!(config.max_file_bytes, 100 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_pcap_writer_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        // Room for the header and two 60-byte frames per file.
+        let config = CaptureConfig {
+            max_file_bytes: GLOBAL_HEADER_LEN + 2 * (RECORD_HEADER_LEN + 60),
+            ..CaptureConfig::new(dir.path().join("lan.pcap")).with_max_files(2)
+        };
+        let mut writer = PcapWriter::create(config).unwrap();
+        let at = UNIX_EPOCH + Duration::from_micros(1_700_000_000_000_250);
+        for _ in 0..5 {
+            writer.write_frame(&[0xab; 60], at).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let current = std::fs::read(dir.path().join("lan.pcap")).unwrap();
+        let rotated = std::fs::read(dir.path().join("lan.1.pcap")).unwrap();
+        assert!(!dir.path().join("lan.2.pcap").exists());
+        assert_eq!(current.len(), 24 + 76);
+        assert_eq!(rotated.len(), 24 + 2 * 76);
+
+        assert_eq!(&current[0..4], &0xa1b2_c3d4u32.to_le_bytes());
+        assert_eq!(&current[20..24], &LINKTYPE_ETHERNET.to_le_bytes());
+        let record = &current[24..];
+        assert_eq!(&record[0..4], &1_700_000_000u32.to_le_bytes());
+        assert_eq!(&record[4..8], &250u32.to_le_bytes());
+        assert_eq!(&record[8..12], &60u32.to_le_bytes());
+        assert_eq!(&record[16..], &[0xab; 60]);
+
+        let empty = CaptureConfig::new(dir.path().join("empty.pcap")).with_max_files(0);
+        assert!(PcapWriter::create(empty).is_err());
+    }
+}
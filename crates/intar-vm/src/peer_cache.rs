@@ -0,0 +1,168 @@
+use crate::VmError;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// Port `intar images serve` listens on unless told otherwise.
+pub const PEER_CACHE_PORT: u16 = 8470;
+
+/// Largest request head a peer cache reads from one connection.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+
+/// Serve the cached images in `dir` over plain HTTP so other hosts can list this one under
+/// `downloads.peers`. Only `GET`/`HEAD` of top-level `.img` files is answered, with
+/// `Range: bytes=N-` support so interrupted transfers resume. Runs until the task is dropped.
+///
+/// # Errors
+/// Returns `VmError::Io` if `listen` cannot be bound.
+pub async fn serve_image_cache(dir: PathBuf, listen: SocketAddr) -> Result<(), VmError> {
+    let listener = TcpListener::bind(listen).await?;
+    info!("Serving image cache {} on {}", dir.display(), listen);
+    let dir = Arc::new(dir);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Image cache accept failed: {}", e);
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let dir = Arc::clone(&dir);
+        tokio::spawn(async move {
+            if let Err(e) = serve_request(stream, &dir).await {
+                debug!("Image cache connection from {} ended: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn serve_request(stream: TcpStream, dir: &Path) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read.take(MAX_REQUEST_BYTES));
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await? == 0 {
+        return Ok(());
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    let mut range_start = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("range")
+        {
+            range_start = parse_range_start(value);
+        }
+    }
+
+    let is_head = method.eq_ignore_ascii_case("HEAD");
+    if !is_head && !method.eq_ignore_ascii_case("GET") {
+        return write_status(&mut write, 405, "Method Not Allowed").await;
+    }
+    let Some(name) = cache_file_name(&path) else {
+        return write_status(&mut write, 404, "Not Found").await;
+    };
+    let Ok(mut file) = tokio::fs::File::open(dir.join(name)).await else {
+        return write_status(&mut write, 404, "Not Found").await;
+    };
+    let len = file.metadata().await?.len();
+
+    let start = range_start.unwrap_or(0);
+    let head = match range_start {
+        Some(start) if start >= len => {
+            let head = format!(
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{len}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+            write.write_all(head.as_bytes()).await?;
+            return write.shutdown().await;
+        }
+        Some(start) => format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes {start}-{}/{len}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            len - 1,
+            len - start
+        ),
+        None => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nAccept-Ranges: bytes\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n"
+        ),
+    };
+    write.write_all(head.as_bytes()).await?;
+    if !is_head {
+        info!("Serving {} from byte {} to a peer", name, start);
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        tokio::io::copy(&mut file, &mut write).await?;
+    }
+    write.shutdown().await
+}
+
+async fn write_status(
+    write: &mut tokio::net::tcp::OwnedWriteHalf,
+    status: u16,
+    reason: &str,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reason}\n",
+        reason.len() + 1
+    );
+    write.write_all(response.as_bytes()).await?;
+    write.shutdown().await
+}
+
+/// The cache file a request path names, if it is a plain `.img` file in the cache directory.
+fn cache_file_name(path: &str) -> Option<&str> {
+    let name = path.strip_prefix('/')?;
+    let valid = name.ends_with(".img")
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && !name.contains("..");
+    valid.then_some(name)
+}
+
+/// The first byte of an open-ended `bytes=N-` range, the only form downloads send.
+fn parse_range_start(value: &str) -> Option<u64> {
+    value
+        .trim()
+        .strip_prefix("bytes=")?
+        .strip_suffix('-')?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file_name() {
+        assert_eq!(
+            cache_file_name("/ubuntu-abc123.img"),
+            Some("ubuntu-abc123.img")
+        );
+        assert_eq!(cache_file_name("/ubuntu.img.tmp"), None);
+        assert_eq!(cache_file_name("/../secret.img"), None);
+        assert_eq!(cache_file_name("/sub/dir.img"), None);
+        assert_eq!(cache_file_name("/.hidden.img"), None);
+        assert_eq!(cache_file_name("ubuntu.img"), None);
+    }
+
+    #[test]
+    fn test_parse_range_start() {
+        assert_eq!(parse_range_start(" bytes=1024-"), Some(1024));
+        assert_eq!(parse_range_start("bytes=0-"), Some(0));
+        assert_eq!(parse_range_start("bytes=0-99"), None);
+        assert_eq!(parse_range_start("items=5-"), None);
+    }
+}
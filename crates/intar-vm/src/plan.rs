@@ -0,0 +1,388 @@
+use crate::scenario_runner::detect_arch;
+use crate::{ImageCache, ScenarioRunner, VmError};
+use intar_core::{Ipv4Subnet, K3S_API_PORT, ProbePhase, Scenario};
+use std::fmt;
+
+/// What starting a scenario would create (`intar plan`). Building it reads the image cache and
+/// asks image servers for sizes, but creates no run, disk, or VM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunPlan {
+    pub scenario: String,
+    /// Host architecture images are chosen for.
+    pub arch: String,
+    pub vms: Vec<PlannedVm>,
+    pub mgmt_subnet: Ipv4Subnet,
+    /// Whether the VMs share a LAN (scenarios with more than one VM).
+    pub shared_lan: bool,
+    pub lan_subnet: Ipv4Subnet,
+    pub images: Vec<PlannedImage>,
+    /// Referenced probes per phase.
+    pub probes: Vec<PlanProbePhase>,
+}
+
+/// The probes of one phase that a plan's VMs reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanProbePhase {
+    pub phase: ProbePhase,
+    pub probes: Vec<PlanProbe>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanProbe {
+    pub name: String,
+    /// VMs that run the probe.
+    pub vms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedVm {
+    pub name: String,
+    pub cpu: u32,
+    pub memory: u32,
+    pub disk: u32,
+    pub image: String,
+    pub mgmt_ip: String,
+    pub lan_ip: Option<String>,
+    pub lan_ipv6: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedImage {
+    pub name: String,
+    pub status: ImageStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageStatus {
+    /// Already in the image cache; size on disk.
+    Cached { size: Option<u64> },
+    /// Downloaded at start; size as reported by the server, if it says.
+    Download { url: String, size: Option<u64> },
+    /// The image has no source for the host architecture, so the scenario cannot start.
+    NoSource,
+}
+
+/// Plan `scenario` for this host, looking up each image in `image_cache`.
+///
+/// # Errors
+/// Returns `VmError` if the scenario has more VMs than intar can address.
+pub async fn plan_run(scenario: &Scenario, image_cache: &ImageCache) -> Result<RunPlan, VmError> {
+    let mut plan = RunPlan::new(scenario, &detect_arch())?;
+    for image in &mut plan.images {
+        let Some(source) = scenario
+            .images
+            .get(&image.name)
+            .and_then(|spec| spec.source_for_arch(&plan.arch))
+        else {
+            continue;
+        };
+        image.status = match image_cache.get_cached_path(source) {
+            Some(path) => ImageStatus::Cached {
+                size: std::fs::metadata(path).ok().map(|meta| meta.len()),
+            },
+            None => ImageStatus::Download {
+                url: source.url.clone(),
+                size: image_cache.download_size(source).await,
+            },
+        };
+    }
+    Ok(plan)
+}
+
+impl RunPlan {
+    /// The plan without image cache or network lookups: every image with a source for `arch` is
+    /// listed as a download of unknown size.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the scenario has more VMs than intar can address.
+    pub fn new(scenario: &Scenario, arch: &str) -> Result<Self, VmError> {
+        let shared_lan = scenario.vms.len() > 1;
+        let mut lan_ips = ScenarioRunner::assign_vm_addresses(scenario)?;
+        let mut lan_ipv6s = ScenarioRunner::assign_vm_ipv6_addresses(scenario)?;
+        let vms = scenario
+            .vms
+            .iter()
+            .enumerate()
+            .map(|(index, vm)| {
+                Ok(PlannedVm {
+                    name: vm.name.clone(),
+                    cpu: vm.cpu,
+                    memory: vm.memory,
+                    disk: vm.disk,
+                    image: vm.image.clone(),
+                    mgmt_ip: ScenarioRunner::mgmt_ip(scenario.network.management(), index)?,
+                    lan_ip: lan_ips.remove(&vm.name).filter(|_| shared_lan),
+                    lan_ipv6: lan_ipv6s.remove(&vm.name),
+                })
+            })
+            .collect::<Result<Vec<_>, VmError>>()?;
+
+        let mut images = Vec::new();
+        for vm in &scenario.vms {
+            if images
+                .iter()
+                .any(|image: &PlannedImage| image.name == vm.image)
+            {
+                continue;
+            }
+            let status = scenario
+                .images
+                .get(&vm.image)
+                .and_then(|spec| spec.source_for_arch(arch))
+                .map_or(ImageStatus::NoSource, |source| ImageStatus::Download {
+                    url: source.url.clone(),
+                    size: None,
+                });
+            images.push(PlannedImage {
+                name: vm.image.clone(),
+                status,
+            });
+        }
+
+        let probes = [ProbePhase::Boot, ProbePhase::Scenario, ProbePhase::Teardown]
+            .into_iter()
+            .map(|phase| {
+                let mut probes: Vec<PlanProbe> = Vec::new();
+                for vm in &scenario.vms {
+                    for probe in &vm.probes {
+                        if scenario.probes.get(probe).map(|def| def.phase) != Some(phase) {
+                            continue;
+                        }
+                        match probes.iter_mut().find(|planned| &planned.name == probe) {
+                            Some(planned) => planned.vms.push(vm.name.clone()),
+                            None => probes.push(PlanProbe {
+                                name: probe.clone(),
+                                vms: vec![vm.name.clone()],
+                            }),
+                        }
+                    }
+                }
+                PlanProbePhase { phase, probes }
+            })
+            .collect();
+
+        Ok(Self {
+            scenario: scenario.name.clone(),
+            arch: arch.to_string(),
+            vms,
+            mgmt_subnet: scenario.network.management(),
+            shared_lan,
+            lan_subnet: scenario.network.lan(),
+            images,
+            probes,
+        })
+    }
+
+    /// Total guest memory in MB.
+    #[must_use]
+    pub fn total_memory(&self) -> u32 {
+        self.vms.iter().map(|vm| vm.memory).sum()
+    }
+
+    /// Upper bound of the VM overlay disks in GB.
+    #[must_use]
+    pub fn total_disk(&self) -> u32 {
+        self.vms.iter().map(|vm| vm.disk).sum()
+    }
+
+    /// Whether every VM has an image it can boot on this host.
+    #[must_use]
+    pub fn is_startable(&self) -> bool {
+        self.images
+            .iter()
+            .all(|image| image.status != ImageStatus::NoSource)
+    }
+}
+
+impl fmt::Display for RunPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Plan for scenario '{}' on {}", self.scenario, self.arch)?;
+
+        writeln!(f, "\nVMs:")?;
+        for vm in &self.vms {
+            writeln!(
+                f,
+                "  + {}: {} CPU, {} MB RAM, {} GB disk, image {}",
+                vm.name, vm.cpu, vm.memory, vm.disk, vm.image
+            )?;
+            write!(f, "      addresses: {} (management)", vm.mgmt_ip)?;
+            if let Some(lan_ip) = &vm.lan_ip {
+                write!(f, ", {lan_ip} (shared LAN)")?;
+            }
+            if let Some(lan_ipv6) = &vm.lan_ipv6 {
+                write!(f, ", {lan_ipv6} (shared LAN IPv6)")?;
+            }
+            writeln!(
+                f,
+                "\n      forwards: host port -> 22 (SSH), 127.0.0.1 port -> {K3S_API_PORT} (Kubernetes API)"
+            )?;
+        }
+
+        writeln!(f, "\nNetworks:")?;
+        writeln!(
+            f,
+            "  + management: user-mode NAT per VM ({}), free host ports picked at start",
+            self.mgmt_subnet
+        )?;
+        if self.shared_lan {
+            writeln!(
+                f,
+                "  + shared LAN: {} through a local UDP switch, {} VMs",
+                self.lan_subnet,
+                self.vms.len()
+            )?;
+        }
+
+        writeln!(f, "\nImages ({}):", self.arch)?;
+        let mut download_total = Some(0u64);
+        for image in &self.images {
+            match &image.status {
+                ImageStatus::Cached { size } => {
+                    writeln!(f, "  = {}: cached{}", image.name, size_suffix(*size))?;
+                }
+                ImageStatus::Download { url, size } => {
+                    download_total = download_total.zip(*size).map(|(a, b)| a + b);
+                    writeln!(
+                        f,
+                        "  + {}: download{} from {url}",
+                        image.name,
+                        size_suffix(*size)
+                    )?;
+                }
+                ImageStatus::NoSource => {
+                    writeln!(f, "  ! {}: no source for {}", image.name, self.arch)?;
+                }
+            }
+        }
+
+        let cpus: u32 = self.vms.iter().map(|vm| vm.cpu).sum();
+        write!(
+            f,
+            "\nTotal: {} VMs, {cpus} CPUs, {} MB RAM, up to {} GB of VM disks",
+            self.vms.len(),
+            self.total_memory(),
+            self.total_disk()
+        )?;
+        let downloads = self
+            .images
+            .iter()
+            .filter(|image| matches!(image.status, ImageStatus::Download { .. }))
+            .count();
+        if downloads > 0 {
+            write!(f, ", {downloads} image download(s)")?;
+            if let Some(total) = download_total {
+                write!(f, " of {} MB", total / (1024 * 1024))?;
+            }
+        }
+        writeln!(f)?;
+
+        writeln!(f, "\nProbes:")?;
+        for PlanProbePhase { phase, probes } in &self.probes {
+            let phase = match phase {
+                ProbePhase::Boot => "boot",
+                ProbePhase::Scenario => "scenario",
+                ProbePhase::Teardown => "teardown",
+            };
+            writeln!(f, "  {phase} ({}):", probes.len())?;
+            for probe in probes {
+                writeln!(f, "    {} on {}", probe.name, probe.vms.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn size_suffix(size: Option<u64>) -> String {
+    size.map(|size| format!(" ({} MB)", size / (1024 * 1024)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HCL: &str = r#"
+scenario "plan" {
+  image "ubuntu" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx" {
+    type    = "service"
+    service = "nginx"
+    state   = "running"
+  }
+
+  probe "agent" {
+    phase   = "boot"
+    type    = "service"
+    service = "intar-agent"
+    state   = "running"
+  }
+
+  vm "web" {
+    image  = "ubuntu"
+    memory = 2048
+    probes = ["agent", "nginx"]
+  }
+
+  vm "db" {
+    image  = "ubuntu"
+    disk   = 20
+    probes = ["agent"]
+    ipv6   = true
+  }
+}
+"#;
+
+    #[test]
+    fn test_plan_lists_resources_and_probes() {
+        let scenario = Scenario::parse(HCL).unwrap();
+        let plan = RunPlan::new(&scenario, "x86_64").unwrap();
+
+        assert!(plan.shared_lan);
+        assert_eq!(plan.vms[1].mgmt_ip, "10.0.2.101");
+        assert_eq!(plan.vms[1].lan_ip.as_deref(), Some("10.11.0.11"));
+        assert_eq!(plan.vms[0].lan_ipv6, None);
+        assert_eq!(plan.vms[1].lan_ipv6.as_deref(), Some("fd00:11::11"));
+        assert!(plan.to_string().contains("(10.0.2.0/24)"));
+
+        let mut moved = scenario.clone();
+        moved.network.management_subnet = Some("172.30.8.0/22".parse().unwrap());
+        moved.network.lan_subnet = Some("192.168.77.0/24".parse().unwrap());
+        let moved = RunPlan::new(&moved, "x86_64").unwrap();
+        assert_eq!(moved.vms[1].mgmt_ip, "172.30.8.101");
+        assert_eq!(moved.vms[1].lan_ip.as_deref(), Some("192.168.77.11"));
+        assert!(moved.to_string().contains("shared LAN: 192.168.77.0/24"));
+        assert_eq!(plan.total_memory(), 3072);
+        assert_eq!(plan.total_disk(), 30);
+        assert_eq!(plan.images.len(), 1);
+        assert!(plan.is_startable());
+
+        let boot = &plan.probes[0];
+        assert_eq!(boot.phase, ProbePhase::Boot);
+        assert_eq!(
+            boot.probes,
+            [PlanProbe {
+                name: "agent".to_string(),
+                vms: vec!["web".to_string(), "db".to_string()],
+            }]
+        );
+
+        let text = plan.to_string();
+        assert!(text.contains("  + web: 1 CPU, 2048 MB RAM, 10 GB disk, image ubuntu"));
+        assert!(text.contains("    nginx on web"));
+    }
+
+    #[test]
+    fn test_plan_flags_missing_arch() {
+        let scenario = Scenario::parse(HCL).unwrap();
+        let plan = RunPlan::new(&scenario, "aarch64").unwrap();
+        assert!(!plan.is_startable());
+        assert!(plan.to_string().contains("! ubuntu: no source for aarch64"));
+    }
+}
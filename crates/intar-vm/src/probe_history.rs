@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How the learner got to each objective: when its probe first passed and how many checks
+/// failed before that. Keyed by VM, then probe.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProbeHistory {
+    objectives: BTreeMap<String, BTreeMap<String, ObjectiveRecord>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectiveRecord {
+    /// Seconds into the run when the probe first passed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_passed_secs: Option<u64>,
+    /// Checks that failed before the first pass.
+    #[serde(default)]
+    pub failed_checks: u32,
+}
+
+impl ProbeHistory {
+    /// Note one evaluation of `probe` on `vm`, `elapsed` into the run. Evaluations after the
+    /// first pass change nothing.
+    pub fn record(&mut self, vm: &str, probe: &str, passed: bool, elapsed: Duration) {
+        let record = self
+            .objectives
+            .entry(vm.to_string())
+            .or_default()
+            .entry(probe.to_string())
+            .or_default();
+        if record.first_passed_secs.is_some() {
+            return;
+        }
+        if passed {
+            record.first_passed_secs = Some(elapsed.as_secs());
+        } else {
+            record.failed_checks = record.failed_checks.saturating_add(1);
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, vm: &str, probe: &str) -> Option<&ObjectiveRecord> {
+        self.objectives.get(vm)?.get(probe)
+    }
+
+    pub fn clear(&mut self) {
+        self.objectives.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_until_first_pass() {
+        let mut history = ProbeHistory::default();
+        assert!(history.get("web", "nginx").is_none());
+
+        history.record("web", "nginx", false, Duration::from_secs(10));
+        history.record("web", "nginx", false, Duration::from_secs(20));
+        history.record("web", "nginx", true, Duration::from_secs(95));
+        history.record("web", "nginx", false, Duration::from_secs(120));
+        history.record("web", "nginx", true, Duration::from_secs(130));
+        history.record("db", "postgres", false, Duration::from_secs(10));
+
+        assert_eq!(
+            history.get("web", "nginx"),
+            Some(&ObjectiveRecord {
+                first_passed_secs: Some(95),
+                failed_checks: 2,
+            })
+        );
+        assert_eq!(
+            history.get("db", "postgres"),
+            Some(&ObjectiveRecord {
+                first_passed_secs: None,
+                failed_checks: 1,
+            })
+        );
+
+        let json = serde_json::to_string(&history).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ProbeHistory>(&json).unwrap(),
+            history
+        );
+
+        history.clear();
+        assert!(history.get("web", "nginx").is_none());
+    }
+}
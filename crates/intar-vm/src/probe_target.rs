@@ -0,0 +1,240 @@
+use crate::VmError;
+use base64::Engine as _;
+use intar_core::Scenario;
+use intar_probes::{ProbeResult, ProbeSpec, Request, Response, evaluate_probe};
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Where `intar probe run` evaluates a probe outside a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeTarget {
+    /// This machine, in the intar process.
+    Local,
+    /// A machine reached with the system `ssh`, which runs a copy of the agent for the one
+    /// check and removes it again.
+    Ssh {
+        destination: String,
+        port: Option<u16>,
+    },
+}
+
+impl FromStr for ProbeTarget {
+    type Err = VmError;
+
+    /// Parse `ssh://[user@]host[:port]`.
+    fn from_str(target: &str) -> Result<Self, VmError> {
+        let invalid = || {
+            VmError::ProbeTarget(format!(
+                "'{target}' is not a target like ssh://user@host or ssh://user@host:2222"
+            ))
+        };
+        let address = target
+            .strip_prefix("ssh://")
+            .map(|address| address.trim_end_matches('/'))
+            .ok_or_else(invalid)?;
+        let (destination, port) = match address.rsplit_once(':') {
+            Some((destination, port)) => (destination, Some(port.parse().map_err(|_| invalid())?)),
+            None => (address, None),
+        };
+        // A leading '-' would reach ssh as an option.
+        if destination.is_empty() || destination.starts_with('-') || destination.ends_with('@') {
+            return Err(invalid());
+        }
+        Ok(Self::Ssh {
+            destination: destination.to_string(),
+            port,
+        })
+    }
+}
+
+/// The spec of probe `id` as `scenario` defines it.
+///
+/// # Errors
+/// Returns `VmError::ProbeTarget` if the scenario has no such probe or its config is invalid.
+pub fn scenario_probe_spec(scenario: &Scenario, id: &str) -> Result<ProbeSpec, VmError> {
+    let def = scenario.probes.get(id).ok_or_else(|| {
+        let mut names: Vec<&str> = scenario.probes.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        VmError::ProbeTarget(format!(
+            "Scenario '{}' has no probe '{id}'. Available probes: {}",
+            scenario.name,
+            names.join(", ")
+        ))
+    })?;
+    let config = def
+        .config
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    ProbeSpec::from_definition(&def.probe_type, &config)
+        .map_err(|e| VmError::ProbeTarget(format!("Invalid config of probe '{id}': {e}")))
+}
+
+/// Evaluate probe `id` with `spec` on `target`. Over ssh, the agent binary for the remote
+/// architecture is streamed to a temporary file, answers one request, and is removed; the
+/// probe runs as the ssh user.
+///
+/// # Errors
+/// Returns `VmError::ProbeTarget` if the target cannot be reached or runs an architecture no
+/// agent is built for, and `VmError::Serial` if the agent's answer is unusable.
+pub fn run_probe_on_target(
+    target: &ProbeTarget,
+    id: &str,
+    spec: &ProbeSpec,
+    agent_x86_64: &[u8],
+    agent_aarch64: &[u8],
+) -> Result<ProbeResult, VmError> {
+    let ProbeTarget::Ssh { destination, port } = target else {
+        return Ok(evaluate_probe(id, spec));
+    };
+
+    let arch = ssh_output(destination, *port, "uname -m", None)?;
+    let agent = match arch.trim() {
+        "x86_64" | "amd64" => agent_x86_64,
+        "aarch64" | "arm64" => agent_aarch64,
+        other => {
+            return Err(VmError::ProbeTarget(format!(
+                "{destination} runs on {other}; the agent is only built for x86_64 and aarch64"
+            )));
+        }
+    };
+
+    let request = serde_json::to_vec(&Request::CheckProbe {
+        id: id.to_string(),
+        spec: spec.clone(),
+    })?;
+    let request = base64::engine::general_purpose::STANDARD.encode(request);
+    let script = format!(
+        "agent=$(mktemp) && cat > \"$agent\" && chmod 700 \"$agent\" && \
+         \"$agent\" request {request}; status=$?; rm -f \"$agent\"; exit $status"
+    );
+    let output = ssh_output(destination, *port, &script, Some(agent))?;
+
+    let line = output.lines().rev().find(|line| !line.trim().is_empty());
+    match line.map(serde_json::from_str::<Response>).transpose()? {
+        Some(Response::ProbeResult {
+            id,
+            passed,
+            message,
+            details,
+        }) => Ok(ProbeResult {
+            id,
+            passed,
+            message,
+            details,
+        }),
+        Some(Response::Error { message }) => Err(VmError::Serial(message)),
+        _ => Err(VmError::Serial(format!(
+            "The agent on {destination} gave no probe result"
+        ))),
+    }
+}
+
+/// Run `command` on `destination` and return its stdout, writing `stdin` to it first.
+fn ssh_output(
+    destination: &str,
+    port: Option<u16>,
+    command: &str,
+    stdin: Option<&[u8]>,
+) -> Result<String, VmError> {
+    let mut ssh = Command::new("ssh");
+    ssh.args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=10"]);
+    if let Some(port) = port {
+        ssh.arg("-p").arg(port.to_string());
+    }
+    ssh.arg(destination)
+        .arg(command)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = ssh
+        .spawn()
+        .map_err(|e| VmError::ProbeTarget(format!("Failed to run ssh: {e}")))?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(VmError::ProbeTarget(format!(
+            "ssh {destination} failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_target() {
+        assert_eq!(
+            "ssh://admin@web1.lab:2222".parse::<ProbeTarget>().unwrap(),
+            ProbeTarget::Ssh {
+                destination: "admin@web1.lab".into(),
+                port: Some(2222),
+            }
+        );
+        assert_eq!(
+            "ssh://web1/".parse::<ProbeTarget>().unwrap(),
+            ProbeTarget::Ssh {
+                destination: "web1".into(),
+                port: None,
+            }
+        );
+        for target in [
+            "web1",
+            "ssh://",
+            "ssh://admin@",
+            "ssh://-oProxyCommand=sh",
+            "ssh://web1:ssh",
+        ] {
+            assert!(target.parse::<ProbeTarget>().is_err(), "{target}");
+        }
+    }
+
+    #[test]
+    fn test_scenario_probe_spec() {
+        let scenario = Scenario::parse(
+            r#"
+scenario "web" {
+  image "ubuntu-24.04" {
+    source {
+      arch     = "amd64"
+      url      = "https://example.com/ubuntu-amd64.qcow2"
+      checksum = "sha256:abc123"
+    }
+  }
+
+  probe "nginx-running" {
+    type    = "service"
+    service = "nginx"
+    state   = "running"
+  }
+
+  vm "web" {
+    image  = "ubuntu-24.04"
+    probes = ["nginx-running"]
+  }
+}
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            scenario_probe_spec(&scenario, "nginx-running").unwrap(),
+            ProbeSpec::Service { .. }
+        ));
+        let err = scenario_probe_spec(&scenario, "nginx").unwrap_err();
+        assert!(
+            err.to_string().contains("Available probes: nginx-running"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use intar_probes::ProbeResult;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Evaluations a [`ProbeTrace`] keeps; older ones are dropped.
+const PROBE_TRACE_KEPT: usize = 1000;
+
+/// One probe evaluation, as traced under `intar start --author`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeEvaluation {
+    pub at: Instant,
+    pub vm: String,
+    pub probe: String,
+    pub passed: bool,
+    pub message: String,
+    /// From sending the probe to its result.
+    pub duration: Duration,
+    /// The agent's reply verbatim, the serialized result for simulated VMs, or empty when the
+    /// agent could not be asked.
+    pub raw: String,
+}
+
+/// The latest probe evaluations of a run, oldest first, including the ones that changed
+/// nothing.
+#[derive(Debug, Default)]
+pub struct ProbeTrace {
+    evaluations: VecDeque<ProbeEvaluation>,
+}
+
+impl ProbeTrace {
+    /// Note that `result` was evaluated on `vm` in `duration`, with the agent replying `raw`.
+    pub fn record(&mut self, vm: &str, result: &ProbeResult, duration: Duration, raw: String) {
+        if self.evaluations.len() == PROBE_TRACE_KEPT {
+            self.evaluations.pop_front();
+        }
+        self.evaluations.push_back(ProbeEvaluation {
+            at: Instant::now(),
+            vm: vm.to_string(),
+            probe: result.id.clone(),
+            passed: result.passed,
+            message: result.message.clone(),
+            duration,
+            raw,
+        });
+    }
+
+    #[must_use]
+    pub fn evaluations(&self) -> &VecDeque<ProbeEvaluation> {
+        &self.evaluations
+    }
+
+    /// The last evaluation of `probe` on `vm`.
+    #[must_use]
+    pub fn latest(&self, vm: &str, probe: &str) -> Option<&ProbeEvaluation> {
+        self.evaluations
+            .iter()
+            .rev()
+            .find(|evaluation| evaluation.vm == vm && evaluation.probe == probe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_keeps_latest_evaluations() {
+        let mut trace = ProbeTrace::default();
+        for i in 0..=PROBE_TRACE_KEPT {
+            let result = ProbeResult::fail("nginx", format!("check {i}"));
+            trace.record("web", &result, Duration::from_millis(12), String::new());
+        }
+        trace.record(
+            "web",
+            &ProbeResult::pass("nginx", "active"),
+            Duration::from_millis(8),
+            r#"{"type":"probe_result","id":"nginx","passed":true}"#.into(),
+        );
+
+        assert_eq!(trace.evaluations().len(), PROBE_TRACE_KEPT);
+        assert_eq!(trace.evaluations()[0].message, "check 2");
+        let latest = trace.latest("web", "nginx").unwrap();
+        assert!(latest.passed);
+        assert_eq!(latest.duration, Duration::from_millis(8));
+        assert!(latest.raw.contains("\"passed\":true"));
+        assert!(trace.latest("db", "nginx").is_none());
+    }
+}
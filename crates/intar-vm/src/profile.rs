@@ -0,0 +1,134 @@
+use crate::{IntarDirs, VmError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const PROFILE_FILE: &str = "profile.yaml";
+
+/// Who is doing the labs on this machine, set with `intar login` and stamped into each run's
+/// progress, final report, certificate, and completion webhook.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LearnerProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Course or class the learner belongs to, e.g. `linux-101-fall`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_id: Option<String>,
+}
+
+impl LearnerProfile {
+    #[must_use]
+    pub fn path(dirs: &IntarDirs) -> PathBuf {
+        dirs.config.join(PROFILE_FILE)
+    }
+
+    /// The saved profile, or `None` before `intar login`.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the profile exists but cannot be read or parsed.
+    pub fn load(dirs: &IntarDirs) -> Result<Option<Self>, VmError> {
+        Self::load_from(&Self::path(dirs))
+    }
+
+    /// Load a profile from `path`; a missing or empty file means no profile.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the file exists but cannot be read or parsed.
+    pub fn load_from(path: &Path) -> Result<Option<Self>, VmError> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+        let profile: Self = serde_yaml::from_str(&content)?;
+        Ok((!profile.is_empty()).then_some(profile))
+    }
+
+    /// Write the profile to `path`, creating its directory.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the file cannot be written.
+    pub fn save_to(&self, path: &Path) -> Result<(), VmError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Take the fields `other` sets, keeping the rest.
+    pub fn update(&mut self, other: Self) {
+        self.name = other.name.or(self.name.take());
+        self.email = other.email.or(self.email.take());
+        self.class_id = other.class_id.or(self.class_id.take());
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.email.is_none() && self.class_id.is_none()
+    }
+
+    /// The most specific way to tell learners apart: the email, else the name.
+    #[must_use]
+    pub fn identifier(&self) -> Option<&str> {
+        self.email.as_deref().or(self.name.as_deref())
+    }
+}
+
+impl fmt::Display for LearnerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, &self.email) {
+            (Some(name), Some(email)) => write!(f, "{name} <{email}>")?,
+            (Some(name), None) => write!(f, "{name}")?,
+            (None, Some(email)) => write!(f, "{email}")?,
+            (None, None) => write!(f, "anonymous")?,
+        }
+        if let Some(class_id) = &self.class_id {
+            write!(f, ", class {class_id}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_roundtrip_and_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("intar").join(PROFILE_FILE);
+        assert_eq!(LearnerProfile::load_from(&path).unwrap(), None);
+
+        let mut profile = LearnerProfile {
+            name: Some("Ada Lovelace".into()),
+            email: Some("ada@example.com".into()),
+            class_id: None,
+        };
+        profile.save_to(&path).unwrap();
+        assert_eq!(
+            LearnerProfile::load_from(&path).unwrap().as_ref(),
+            Some(&profile)
+        );
+
+        profile.update(LearnerProfile {
+            class_id: Some("linux-101".into()),
+            ..LearnerProfile::default()
+        });
+        assert_eq!(profile.name.as_deref(), Some("Ada Lovelace"));
+        assert_eq!(profile.identifier(), Some("ada@example.com"));
+        assert_eq!(
+            profile.to_string(),
+            "Ada Lovelace <ada@example.com>, class linux-101"
+        );
+
+        std::fs::write(&path, "name: Ada\nteam: blue\n").unwrap();
+        assert!(LearnerProfile::load_from(&path).is_err());
+    }
+}
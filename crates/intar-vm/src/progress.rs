@@ -0,0 +1,134 @@
+use crate::{LearnerProfile, ProbeHistory, ScenarioState, VmError};
+use intar_core::Scenario;
+use intar_probes::ProbeResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+const PROGRESS_FILE: &str = "progress.json";
+const SCENARIO_FILE: &str = "scenario.json";
+
+/// Bookkeeping for a run that `intar resume` restores instead of starting from scratch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunProgress {
+    pub state: ScenarioState,
+    #[serde(default)]
+    pub probe_results: HashMap<String, HashMap<String, ProbeResult>>,
+    /// When each objective first passed, for the completion screen.
+    #[serde(default)]
+    pub probe_history: ProbeHistory,
+    #[serde(default)]
+    pub results_unlocked: bool,
+    #[serde(default)]
+    pub accepted_flags: HashSet<String>,
+    #[serde(default)]
+    pub answered_questions: HashSet<String>,
+    /// Triggered steps that already ran, as `<vm>/<step>`.
+    #[serde(default)]
+    pub fired_triggers: HashSet<String>,
+    /// Seconds spent in each TUI stage (`init`, `images`, `vms`, `boot`, `run`).
+    #[serde(default)]
+    pub stage_secs: BTreeMap<String, u64>,
+    /// Profile from `intar login` when the run started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub learner: Option<LearnerProfile>,
+}
+
+impl RunProgress {
+    /// Load saved progress, or `None` if the run never reached the running phase.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the progress file exists but cannot be read or parsed.
+    pub fn load(run_dir: &Path) -> Result<Option<Self>, VmError> {
+        let path = run_dir.join(PROGRESS_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Save progress, replacing the file atomically so a crash never leaves it half-written.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the progress cannot be serialized or written.
+    pub fn save(&self, run_dir: &Path) -> Result<(), VmError> {
+        let path = run_dir.join(PROGRESS_FILE);
+        let tmp = run_dir.join(format!("{PROGRESS_FILE}.tmp"));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/// Keep a copy of the parsed scenario in the run directory so the run can be resumed without
+/// the original HCL file.
+///
+/// # Errors
+/// Returns `VmError` if the scenario cannot be serialized or written.
+pub fn save_run_scenario(run_dir: &Path, scenario: &Scenario) -> Result<(), VmError> {
+    let content = serde_json::to_string_pretty(scenario)?;
+    std::fs::write(run_dir.join(SCENARIO_FILE), content)?;
+    Ok(())
+}
+
+/// Load the scenario saved by [`save_run_scenario`].
+///
+/// # Errors
+/// Returns `VmError` if the file is missing or cannot be parsed.
+pub fn load_run_scenario(run_dir: &Path) -> Result<Scenario, VmError> {
+    let content = std::fs::read_to_string(run_dir.join(SCENARIO_FILE))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(RunProgress::load(dir.path()).unwrap().is_none());
+
+        let mut progress = RunProgress {
+            state: ScenarioState::Running,
+            probe_results: HashMap::new(),
+            probe_history: ProbeHistory::default(),
+            results_unlocked: true,
+            accepted_flags: HashSet::from(["ctf-flag".to_string()]),
+            answered_questions: HashSet::new(),
+            fired_triggers: HashSet::from(["web/break-nginx".to_string()]),
+            stage_secs: BTreeMap::from([("run".to_string(), 42)]),
+            learner: Some(LearnerProfile {
+                email: Some("ada@example.com".into()),
+                ..LearnerProfile::default()
+            }),
+        };
+        progress.probe_results.insert(
+            "web".into(),
+            HashMap::from([("nginx".to_string(), ProbeResult::pass("nginx", "ok"))]),
+        );
+        progress
+            .probe_history
+            .record("web", "nginx", true, std::time::Duration::from_secs(30));
+        progress.save(dir.path()).unwrap();
+
+        let loaded = RunProgress::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.state, ScenarioState::Running);
+        assert!(loaded.results_unlocked);
+        assert!(loaded.fired_triggers.contains("web/break-nginx"));
+        assert_eq!(loaded.stage_secs.get("run"), Some(&42));
+        assert_eq!(
+            loaded.learner.as_ref().and_then(LearnerProfile::identifier),
+            Some("ada@example.com")
+        );
+        assert!(loaded.probe_results["web"]["nginx"].passed);
+        assert_eq!(
+            loaded
+                .probe_history
+                .get("web", "nginx")
+                .and_then(|record| record.first_passed_secs),
+            Some(30)
+        );
+    }
+}
@@ -1,18 +1,30 @@
-use crate::{HostSocket, VmError, VmState, connect_host_socket, path_to_str};
-use intar_core::VmDefinition;
+use crate::{
+    CloudInstance, CloudLink, CloudVm, DomainChannel, DomainConsole, DomainDisk, DomainInterface,
+    DomainSpec, HostSocket, HostStream, LibvirtDomain, QemuPriority, VmError, VmState,
+    check_local_only, cloud_forwards, connect_host_socket, domain_xml, path_to_str, qemu_listeners,
+};
+use intar_core::{Ipv4Subnet, K3S_API_PORT, VmDefinition};
 use std::fs::File;
-use std::net::{TcpListener, UdpSocket};
+use std::net::{Ipv4Addr, TcpListener, UdpSocket};
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::time::{Duration, Instant};
 use tokio::io::AsyncBufRead;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tracing::warn;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, WriteHalf};
+use tokio::sync::{Mutex, broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 
 const MAIN_DISK_NODE_NAME: &str = "intar_disk0";
 const CLOUD_INIT_NODE_NAME: &str = "intar_cloud_init0";
 const SNAPSHOT_JOB_POLL_INTERVAL: Duration = Duration::from_millis(50);
 const SNAPSHOT_JOB_TIMEOUT: Duration = Duration::from_secs(120);
+/// How often a checkpoint being saved reports how far it got.
+const CHECKPOINT_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// How long saving or loading a VM's migrated state may take.
+const MIGRATION_TIMEOUT: Duration = Duration::from_secs(600);
+/// QMP events buffered per subscriber before the slowest one starts missing them.
+const QMP_EVENT_CAPACITY: usize = 64;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum QemuAccel {
@@ -20,6 +32,36 @@ enum QemuAccel {
     Tcg,
 }
 
+/// Address of the host, and its loopback, as seen from a guest on the user-mode management
+/// network `subnet`. Guests use it as their default gateway.
+#[must_use]
+pub fn user_net_host_ip(subnet: Ipv4Subnet) -> Ipv4Addr {
+    subnet.host(2)
+}
+
+/// Resolver the user-mode management network `subnet` offers, forwarding to the host's DNS.
+#[must_use]
+pub fn user_net_dns_ip(subnet: Ipv4Subnet) -> Ipv4Addr {
+    subnet.host(3)
+}
+
+/// UEFI firmware for aarch64 guests, from where Homebrew and the distributions install it.
+fn aarch64_firmware() -> Option<&'static str> {
+    [
+        "/opt/homebrew/share/qemu/edk2-aarch64-code.fd",
+        "/usr/share/qemu/edk2-aarch64-code.fd",
+        "/usr/share/AAVMF/AAVMF_CODE.fd",
+    ]
+    .into_iter()
+    .find(|path| Path::new(path).exists())
+}
+
+fn command_args(cmd: &Command) -> Vec<String> {
+    cmd.get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum SharedNetworkEndpoint {
     /// Per-scenario UDP datagram L2 segment routed through an intar-managed switch.
@@ -27,22 +69,149 @@ pub enum SharedNetworkEndpoint {
     /// Uses QEMU's `-netdev dgram` backend with localhost UDP sockets, so it is
     /// privilege-free and works on macOS, Linux, and Windows.
     Dgram { hub_port: u16, local_port: u16 },
+    /// Loopback multicast group shared by the run's VMs; see [`crate::LanBackend::Multicast`].
+    Multicast { group: Ipv4Addr, port: u16 },
+    /// Port on the host's socket_vmnet daemon; see [`crate::LanBackend::SocketVmnet`].
+    SocketVmnet { path: PathBuf },
+}
+
+impl SharedNetworkEndpoint {
+    /// The VM's own port on intar's switch, if the switch carries the LAN.
+    #[must_use]
+    pub fn switch_port(&self) -> Option<u16> {
+        match self {
+            Self::Dgram { local_port, .. } => Some(*local_port),
+            Self::Multicast { .. } | Self::SocketVmnet { .. } => None,
+        }
+    }
 }
 
 pub struct QemuSockets {
     pub qmp: HostSocket,
     pub serial: HostSocket,
     pub actions: HostSocket,
+    /// Second QMP monitor kept open by the owning intar process, so other intar commands can
+    /// still use `qmp`. `None` for VMs attached from runs started without one.
+    pub control: Option<HostSocket>,
+    /// The guest's serial console, for `intar console`. `None` for cloud instances and VMs
+    /// attached from runs started without one.
+    pub console: Option<HostSocket>,
+}
+
+/// Pseudo QMP event sent when QEMU closes the control connection, i.e. the process exited.
+pub const QEMU_EXITED_EVENT: &str = "QEMU_EXITED";
+
+/// Asynchronous QMP event (e.g. `STOP`, `SHUTDOWN`) received on a VM's control channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QmpEvent {
+    pub vm: String,
+    pub name: String,
+    pub data: serde_json::Value,
+}
+
+/// Persistent QMP connection on a VM's control socket. A reader task forwards events to the
+/// instance's broadcast channel and command responses, in order, to `responses`.
+struct QmpSession {
+    writer: WriteHalf<HostStream>,
+    responses: mpsc::Receiver<serde_json::Value>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl QmpSession {
+    async fn connect(
+        socket: &HostSocket,
+        vm: &str,
+        events: broadcast::Sender<QmpEvent>,
+    ) -> Result<Self, VmError> {
+        let stream = connect_host_socket(socket)
+            .await
+            .map_err(|e| VmError::Qmp(format!("Failed to connect to QMP: {e}")))?;
+        let (read_half, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read_half);
+
+        let _greeting = QemuInstance::read_qmp_greeting(&mut reader).await?;
+        send_qmp_command(&mut writer, "qmp_capabilities", None).await?;
+        let cap_response = QemuInstance::read_qmp_response(&mut reader).await?;
+        if let Some(err) = cap_response.get("error") {
+            return Err(VmError::Qmp(format!("qmp_capabilities error: {err}")));
+        }
+
+        let (responses_tx, responses) = mpsc::channel(8);
+        let vm = vm.to_string();
+        let reader = tokio::spawn(async move {
+            while let Ok(message) = QemuInstance::read_qmp_message(&mut reader).await {
+                if let Some(name) = message.get("event").and_then(serde_json::Value::as_str) {
+                    // No subscribers is fine; events are only of interest while someone listens.
+                    let _ = events.send(QmpEvent {
+                        vm: vm.clone(),
+                        name: name.to_string(),
+                        data: message
+                            .get("data")
+                            .cloned()
+                            .unwrap_or(serde_json::Value::Null),
+                    });
+                } else if (message.get("return").is_some() || message.get("error").is_some())
+                    && responses_tx.send(message).await.is_err()
+                {
+                    break;
+                }
+            }
+            // QEMU only closes the monitor when it exits; a session dropped on purpose (e.g. by
+            // `stop`) aborts this task before it gets here.
+            let _ = events.send(QmpEvent {
+                vm,
+                name: QEMU_EXITED_EVENT.to_string(),
+                data: serde_json::Value::Null,
+            });
+        });
+
+        Ok(Self {
+            writer,
+            responses,
+            reader,
+        })
+    }
+
+    /// Send one command and wait for its response. Fails only if the connection is gone.
+    async fn execute(
+        &mut self,
+        command: &str,
+        args: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, VmError> {
+        send_qmp_command(&mut self.writer, command, args).await?;
+        self.responses
+            .recv()
+            .await
+            .ok_or_else(|| VmError::Qmp("QMP connection closed".into()))
+    }
+}
+
+impl Drop for QmpSession {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
 }
 
 pub struct QemuInstanceConfig {
     pub definition: VmDefinition,
     pub ssh_port: u16,
+    /// Host port forwarded to the guest's Kubernetes API, on loopback only.
+    pub api_port: Option<u16>,
+    pub mgmt_subnet: Ipv4Subnet,
     pub mgmt_ip: String,
     pub shared_lan: Option<SharedNetworkEndpoint>,
     pub primary_mac: Option<String>,
     pub lan_mac: Option<String>,
     pub sockets: QemuSockets,
+    pub priority: QemuPriority,
+    /// Directory of the VM's overlay disk: the run directory, or a tmpfs for ephemeral runs.
+    pub disk_dir: PathBuf,
+    /// Run QEMU as this libvirt domain instead of spawning it.
+    pub libvirt: Option<LibvirtDomain>,
+    /// Run the VM as this cloud instance instead of under QEMU.
+    pub cloud: Option<CloudVm>,
+    /// Keep every listener to this host (`intar start --paranoid`).
+    pub paranoid: bool,
 }
 
 pub struct QemuInstance {
@@ -50,6 +219,8 @@ pub struct QemuInstance {
     pub definition: VmDefinition,
     pub state: VmState,
     pub ssh_port: u16,
+    pub api_port: Option<u16>,
+    pub mgmt_subnet: Ipv4Subnet,
     pub mgmt_ip: String,
     pub shared_lan: Option<SharedNetworkEndpoint>,
     pub primary_mac: Option<String>,
@@ -57,12 +228,30 @@ pub struct QemuInstance {
     pub qmp_socket: HostSocket,
     pub serial_socket: HostSocket,
     pub actions_socket: HostSocket,
+    pub control_socket: Option<HostSocket>,
+    pub console_socket: Option<HostSocket>,
     pub pid_file: PathBuf,
     pub disk_path: PathBuf,
     pub base_image: Option<PathBuf>,
     pub cloud_init_iso: PathBuf,
     pub logs_dir: PathBuf,
+    /// Host CPU used by the QEMU process, in percent of one core (see [`CpuSampler`]).
+    pub host_cpu: Option<f64>,
+    /// Fingerprint of the SSH host key cloud-init installs (see [`crate::SshHostKey`]).
+    pub host_key_fingerprint: Option<String>,
+    priority: QemuPriority,
+    libvirt: Option<LibvirtDomain>,
+    cloud: Option<CloudVm>,
+    /// Record of the cloud instance, which stands in for the PID file.
+    cloud_record: PathBuf,
+    /// Task holding the ssh tunnel to the cloud instance.
+    cloud_link: Option<JoinHandle<()>>,
+    paranoid: bool,
+    /// State file to start from instead of booting (see [`QemuInstance::start_incoming`]).
+    incoming: Option<PathBuf>,
     process: Option<Child>,
+    qmp_session: Mutex<Option<QmpSession>>,
+    qmp_events: broadcast::Sender<QmpEvent>,
 }
 
 impl QemuInstance {
@@ -75,6 +264,8 @@ impl QemuInstance {
             definition: config.definition,
             state: VmState::Starting,
             ssh_port: config.ssh_port,
+            api_port: config.api_port,
+            mgmt_subnet: config.mgmt_subnet,
             mgmt_ip: config.mgmt_ip,
             shared_lan: config.shared_lan,
             primary_mac: config.primary_mac,
@@ -82,12 +273,25 @@ impl QemuInstance {
             qmp_socket: config.sockets.qmp,
             serial_socket: config.sockets.serial,
             actions_socket: config.sockets.actions,
+            control_socket: config.sockets.control,
+            console_socket: config.sockets.console,
             pid_file: work_dir.join(format!("{name}-qemu.pid")),
-            disk_path: work_dir.join(format!("{name}.qcow2")),
+            disk_path: config.disk_dir.join(format!("{name}.qcow2")),
             base_image: None,
             cloud_init_iso: work_dir.join(format!("{name}-cloud-init.iso")),
             logs_dir,
+            host_cpu: None,
+            host_key_fingerprint: None,
+            priority: config.priority,
+            libvirt: config.libvirt,
+            cloud: config.cloud,
+            cloud_record: work_dir.join(format!("{name}-cloud.json")),
+            cloud_link: None,
+            paranoid: config.paranoid,
+            incoming: None,
             process: None,
+            qmp_session: Mutex::new(None),
+            qmp_events: broadcast::channel(QMP_EVENT_CAPACITY).0,
         }
     }
 
@@ -164,6 +368,12 @@ impl QemuInstance {
     /// Returns `VmError` if QEMU fails to launch.
     pub fn start(&mut self, arch: &str) -> Result<(), VmError> {
         std::fs::create_dir_all(&self.logs_dir)?;
+        if let Some(cloud) = self.cloud.clone() {
+            return self.start_cloud(&cloud);
+        }
+        if let Some(domain) = self.libvirt.clone() {
+            return self.start_libvirt(arch, &domain);
+        }
 
         let qemu_log_path = self.logs_dir.join("qemu.log");
         let mut child = self.spawn_qemu(arch, QemuAccel::Default, &qemu_log_path)?;
@@ -200,6 +410,237 @@ impl QemuInstance {
         Ok(())
     }
 
+    /// Start the QEMU process from the state [`migrate_to_file`] saved to `state_file`, so the
+    /// guest continues where it was instead of booting.
+    ///
+    /// # Errors
+    /// Returns `VmError` if QEMU fails to launch.
+    pub fn start_incoming(&mut self, arch: &str, state_file: &Path) -> Result<(), VmError> {
+        self.incoming = Some(state_file.to_path_buf());
+        let started = self.start(arch);
+        self.incoming = None;
+        started
+    }
+
+    /// Wait until QEMU has loaded the state it was started with by
+    /// [`QemuInstance::start_incoming`].
+    ///
+    /// # Errors
+    /// Returns `VmError::Qemu` if loading the state failed, `VmError::Timeout` if it is still
+    /// loading after ten minutes.
+    pub async fn wait_for_incoming(&self) -> Result<(), VmError> {
+        let deadline = Instant::now() + MIGRATION_TIMEOUT;
+        loop {
+            let response: serde_json::Value = self.qmp_command("query-status", None).await?;
+            match response
+                .pointer("/return/status")
+                .and_then(serde_json::Value::as_str)
+            {
+                Some("inmigrate") => {}
+                Some("running" | "paused") => return Ok(()),
+                status => {
+                    return Err(VmError::Qemu(format!(
+                        "VM {} did not load its migrated state (status {}); see {}",
+                        self.name,
+                        status.unwrap_or("unknown"),
+                        self.logs_dir.join("qemu.log").display()
+                    )));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(VmError::Timeout(format!(
+                    "VM {} was still loading its migrated state after {}s",
+                    self.name,
+                    MIGRATION_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(SNAPSHOT_JOB_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Start the VM as a transient libvirt domain, which libvirt launches under its own
+    /// policies, then add the port forwards the domain XML cannot declare.
+    fn start_libvirt(&mut self, arch: &str, domain: &LibvirtDomain) -> Result<(), VmError> {
+        if self.priority.nice.is_some() {
+            warn!("--nice does not apply to VM {} under libvirt", self.name);
+        }
+        // The domain listens where QEMU would without libvirt.
+        let mut cmd = Command::new("qemu");
+        self.configure_qemu_command(&mut cmd, arch, QemuAccel::Default);
+        self.check_listeners(&command_args(&cmd))?;
+        let xml_path = self.logs_dir.join("domain.xml");
+        let create = |accel| -> Result<(), VmError> {
+            std::fs::write(&xml_path, self.domain_xml(domain, arch, accel))?;
+            domain.create(&xml_path)
+        };
+        if let Err(e) = create(QemuAccel::Default) {
+            let reason = e.to_string();
+            if !Self::log_indicates_accel_failure(&reason) {
+                return Err(e);
+            }
+            warn!(
+                "Hardware acceleration unavailable for VM {} under libvirt. Retrying with tcg. \
+                 Error: {}",
+                self.name, reason
+            );
+            create(QemuAccel::Tcg)?;
+        }
+        for forward in self.host_forwards() {
+            if let Err(e) = domain.add_host_forward(&forward) {
+                domain.destroy().ok();
+                return Err(e);
+            }
+        }
+        if domain.pid().is_none() {
+            warn!(
+                "Cannot read the PID of VM {} from libvirt at {}; its host CPU and memory use \
+                 are not shown or reported",
+                self.name, domain.uri
+            );
+        }
+        self.state = VmState::Booting;
+        Ok(())
+    }
+
+    /// Launch the VM as a cloud instance that boots the user-data saved in its logs, and link
+    /// it to this host.
+    fn start_cloud(&mut self, cloud: &CloudVm) -> Result<(), VmError> {
+        let user_data = self.logs_dir.join("user-data.yaml");
+        let instance = cloud
+            .settings
+            .launch(&cloud.name, &cloud.image, &user_data)?;
+        instance.save(&self.cloud_record)?;
+        info!(
+            "Launched VM {} as {} instance {}",
+            self.name, instance.provider, instance.id
+        );
+        self.open_cloud_link();
+        self.state = VmState::Booting;
+        Ok(())
+    }
+
+    /// Reach the cloud instance through an ssh tunnel that a background task holds until the
+    /// VM is stopped or released. Does nothing for VMs that are not cloud instances.
+    pub fn open_cloud_link(&mut self) {
+        let Some(cloud) = &self.cloud else {
+            return;
+        };
+        let (Some(serial), Some(actions)) = (
+            self.serial_socket.cleanup_path(),
+            self.actions_socket.cleanup_path(),
+        ) else {
+            return;
+        };
+        let link = CloudLink {
+            record: self.cloud_record.clone(),
+            key: cloud.key.clone(),
+            known_hosts: self
+                .cloud_record
+                .with_file_name(format!("{}-known_hosts", self.name)),
+            agent: cloud.agent.clone(),
+            forwards: cloud_forwards(self.ssh_port, self.api_port, serial, actions),
+        };
+        if let Some(old) = self.cloud_link.replace(tokio::spawn(link.hold())) {
+            old.abort();
+        }
+    }
+
+    fn domain_xml(&self, domain: &LibvirtDomain, arch: &str, accel: QemuAccel) -> String {
+        let domain_type = match accel {
+            QemuAccel::Tcg => "qemu",
+            QemuAccel::Default if cfg!(target_os = "macos") => "hvf",
+            QemuAccel::Default => "kvm",
+        };
+        let disks = [
+            DomainDisk {
+                path: &self.disk_path,
+                format: "qcow2",
+                target: "vda",
+                readonly: false,
+                io: Some(&self.definition.disk_io),
+            },
+            DomainDisk {
+                path: &self.cloud_init_iso,
+                format: "raw",
+                target: "vdb",
+                readonly: true,
+                io: None,
+            },
+        ];
+        let mut interfaces = vec![DomainInterface::User {
+            subnet: self.mgmt_subnet,
+            mac: self.primary_mac.as_deref(),
+        }];
+        if let Some(SharedNetworkEndpoint::Dgram {
+            hub_port,
+            local_port,
+        }) = &self.shared_lan
+        {
+            interfaces.push(DomainInterface::Udp {
+                local_port: *local_port,
+                remote_port: *hub_port,
+                mac: self.lan_mac.as_deref(),
+            });
+        }
+        let channels = [
+            DomainChannel {
+                name: "intar.agent",
+                socket: &self.serial_socket,
+            },
+            DomainChannel {
+                name: "intar.actions",
+                socket: &self.actions_socket,
+            },
+        ];
+        let console_log = self.console_log_path();
+        domain_xml(&DomainSpec {
+            name: &domain.name,
+            domain_type,
+            arch,
+            memory_mb: self.definition.memory,
+            vcpus: self.vcpu_count(accel),
+            cpuset: self.priority.cpus.as_deref(),
+            loader: match arch {
+                "aarch64" | "arm64" => aarch64_firmware(),
+                _ => None,
+            },
+            disks: &disks,
+            interfaces: &interfaces,
+            channels: &channels,
+            console: DomainConsole {
+                log: &console_log,
+                socket: self.console_socket.as_ref(),
+            },
+            qemu_args: &self.libvirt_qemu_args(),
+        })
+    }
+
+    /// What goes on QEMU's command line under libvirt, which has no element for it: the QMP
+    /// monitors, the state to start from, and a multicast or socket_vmnet LAN, since libvirt
+    /// can neither keep a multicast group on loopback nor connect to socket_vmnet.
+    fn libvirt_qemu_args(&self) -> Vec<String> {
+        let mut cmd = Command::new("qemu");
+        self.apply_qmp_args(&mut cmd);
+        if let Some(
+            lan @ (SharedNetworkEndpoint::Multicast { .. }
+            | SharedNetworkEndpoint::SocketVmnet { .. }),
+        ) = &self.shared_lan
+        {
+            Self::apply_lan_args(&mut cmd, lan, self.lan_mac.as_deref());
+        }
+        self.apply_incoming_args(&mut cmd);
+        command_args(&cmd)
+    }
+
+    /// Refuse QEMU arguments `args` that would listen beyond this host or put a socket
+    /// outside the private run directory, under `--paranoid`.
+    fn check_listeners(&self, args: &[String]) -> Result<(), VmError> {
+        match self.pid_file.parent() {
+            Some(run_dir) if self.paranoid => check_local_only(&qemu_listeners(args), run_dir),
+            _ => Ok(()),
+        }
+    }
+
     fn qemu_binary_for_arch(arch: &str) -> Result<&'static str, VmError> {
         match arch {
             "x86_64" | "amd64" => Ok("qemu-system-x86_64"),
@@ -215,9 +656,14 @@ impl QemuInstance {
         qemu_log_path: &Path,
     ) -> Result<Child, VmError> {
         let qemu_binary = Self::qemu_binary_for_arch(arch)?;
-        let mut cmd = Command::new(qemu_binary);
+        let mut cmd = self.priority.command(qemu_binary);
         self.configure_qemu_command(&mut cmd, arch, accel);
+        self.check_listeners(&command_args(&cmd))?;
         Self::redirect_qemu_output(&mut cmd, qemu_log_path)?;
+        // Own process group: Ctrl-C or a closed terminal must not take the VMs down with intar,
+        // so a kept run can be resumed.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
 
         cmd.spawn()
             .map_err(|e| VmError::Qemu(format!("Failed to start QEMU: {e}")))
@@ -275,7 +721,7 @@ impl QemuInstance {
     }
 
     fn cleanup_runtime_files(&self) {
-        for socket in [&self.qmp_socket, &self.serial_socket, &self.actions_socket] {
+        for socket in self.sockets() {
             if let Some(path) = socket.cleanup_path()
                 && path.exists()
             {
@@ -288,11 +734,26 @@ impl QemuInstance {
         }
     }
 
+    /// Guest serial console output (kernel messages, including panic traces).
+    #[must_use]
+    pub fn console_log_path(&self) -> PathBuf {
+        self.logs_dir.join("console.log")
+    }
+
+    fn sockets(&self) -> impl Iterator<Item = &HostSocket> {
+        [&self.qmp_socket, &self.serial_socket, &self.actions_socket]
+            .into_iter()
+            .chain(self.control_socket.as_ref())
+            .chain(self.console_socket.as_ref())
+    }
+
     fn configure_qemu_command(&self, cmd: &mut Command, arch: &str, accel: QemuAccel) {
         cmd.args(["-name", &self.name]);
 
         Self::apply_machine_args(cmd, arch, accel);
+        Self::apply_platform_device_args(cmd, arch);
         self.apply_resource_args(cmd, accel);
+        Self::apply_balloon_args(cmd);
         self.apply_drive_args(cmd, accel);
         Self::apply_rng_args(cmd);
         self.apply_network_args(cmd);
@@ -300,6 +761,13 @@ impl QemuInstance {
         self.apply_console_args(cmd);
         self.apply_qmp_args(cmd);
         Self::apply_misc_args(cmd, accel);
+        self.apply_incoming_args(cmd);
+    }
+
+    fn apply_incoming_args(&self, cmd: &mut Command) {
+        if let Some(state_file) = &self.incoming {
+            cmd.args(["-incoming", &format!("file:{}", state_file.display())]);
+        }
     }
 
     fn apply_machine_args(cmd: &mut Command, arch: &str, accel: QemuAccel) {
@@ -309,15 +777,6 @@ impl QemuInstance {
                 if accel == QemuAccel::Default {
                     cmd.args(["-cpu", "host"]);
                 }
-
-                let efi_paths = [
-                    "/opt/homebrew/share/qemu/edk2-aarch64-code.fd",
-                    "/usr/share/qemu/edk2-aarch64-code.fd",
-                    "/usr/share/AAVMF/AAVMF_CODE.fd",
-                ];
-                if let Some(efi_path) = efi_paths.iter().find(|p| Path::new(p).exists()) {
-                    cmd.args(["-bios", efi_path]);
-                }
             }
             "x86_64" | "amd64" => {
                 cmd.args(["-machine", "q35"]);
@@ -330,30 +789,57 @@ impl QemuInstance {
         }
     }
 
+    /// Firmware and the panic device, which the machine type needs on each architecture.
+    fn apply_platform_device_args(cmd: &mut Command, arch: &str) {
+        match arch {
+            "aarch64" | "arm64" => {
+                if let Some(efi_path) = aarch64_firmware() {
+                    cmd.args(["-bios", efi_path]);
+                }
+                // Reports guest kernel panics as `GUEST_PANICKED` QMP events.
+                cmd.args(["-device", "pvpanic-pci"]);
+            }
+            "x86_64" | "amd64" => {
+                cmd.args(["-device", "pvpanic"]);
+            }
+            _ => {}
+        }
+    }
+
     fn apply_resource_args(&self, cmd: &mut Command, accel: QemuAccel) {
         cmd.args(["-m", &format!("{}M", self.definition.memory)]);
-        let mut cpu = self.definition.cpu;
+        cmd.args(["-smp", &self.vcpu_count(accel).to_string()]);
+    }
+
+    fn vcpu_count(&self, accel: QemuAccel) -> u32 {
+        let cpu = self.definition.cpu;
         if accel == QemuAccel::Tcg && cpu != 3 {
             warn!(
                 "Reducing vCPU count from {} to 3 for VM {} under TCG",
                 cpu, self.name
             );
-            cpu = 3;
+            return 3;
         }
-        cmd.args(["-smp", &cpu.to_string()]);
+        cpu
+    }
+
+    fn apply_balloon_args(cmd: &mut Command) {
+        // Lets `intar vm scale` and auto-ballooning hand guest memory back to the host.
+        cmd.args([
+            "-device",
+            "virtio-balloon-pci,id=balloon0,deflate-on-oom=on",
+        ]);
     }
 
     fn apply_drive_args(&self, cmd: &mut Command, accel: QemuAccel) {
-        let cache = if accel == QemuAccel::Tcg {
-            ",cache=unsafe"
-        } else {
-            ""
-        };
         cmd.args([
             "-drive",
             &format!(
-                "file={},format=qcow2,if=virtio,node-name={MAIN_DISK_NODE_NAME}{cache}",
+                "file={},format=qcow2,if=virtio,node-name={MAIN_DISK_NODE_NAME}{}",
                 self.disk_path.display(),
+                self.definition
+                    .disk_io
+                    .drive_options(accel == QemuAccel::Tcg),
             ),
         ]);
 
@@ -374,14 +860,37 @@ impl QemuInstance {
         }
     }
 
+    /// Host ports forwarded into the guest on the management network, as QEMU's `hostfwd`
+    /// spells them.
+    fn host_forwards(&self) -> Vec<String> {
+        // SSH is open to the network unless `--paranoid` keeps it on the host.
+        let ssh_host = if self.paranoid { "127.0.0.1" } else { "" };
+        let mut forwards = vec![format!(
+            "tcp:{ssh_host}:{}-{}:22",
+            self.ssh_port, self.mgmt_ip
+        )];
+        if let Some(api_port) = self.api_port {
+            // Cluster admin access stays on the host; it is not exposed to the network.
+            forwards.push(format!(
+                "tcp:127.0.0.1:{api_port}-{}:{K3S_API_PORT}",
+                self.mgmt_ip
+            ));
+        }
+        forwards
+    }
+
     fn apply_network_args(&self, cmd: &mut Command) {
-        cmd.args([
-            "-netdev",
-            &format!(
-                "user,id=net0,hostfwd=tcp::{}-{}:22",
-                self.ssh_port, self.mgmt_ip
-            ),
-        ]);
+        let mut netdev = format!(
+            "user,id=net0,net={},host={},dns={}",
+            self.mgmt_subnet,
+            user_net_host_ip(self.mgmt_subnet),
+            user_net_dns_ip(self.mgmt_subnet),
+        );
+        for forward in self.host_forwards() {
+            netdev.push_str(",hostfwd=");
+            netdev.push_str(&forward);
+        }
+        cmd.args(["-netdev", &netdev]);
 
         let mut net0 = String::from("virtio-net-pci,netdev=net0");
         if let Some(mac) = &self.primary_mac {
@@ -391,23 +900,35 @@ impl QemuInstance {
         cmd.args(["-device", &net0]);
 
         if let Some(lan) = &self.shared_lan {
-            let netdev = match lan {
-                SharedNetworkEndpoint::Dgram {
-                    hub_port,
-                    local_port,
-                } => format!(
-                    "dgram,id=net1,local.type=inet,local.host=127.0.0.1,local.port={local_port},remote.type=inet,remote.host=127.0.0.1,remote.port={hub_port}"
-                ),
-            };
-            cmd.args(["-netdev", &netdev]);
-
-            let mut net1 = String::from("virtio-net-pci,netdev=net1");
-            if let Some(mac) = &self.lan_mac {
-                net1.push_str(",mac=");
-                net1.push_str(mac);
-            }
-            cmd.args(["-device", &net1]);
+            Self::apply_lan_args(cmd, lan, self.lan_mac.as_deref());
+        }
+    }
+
+    fn apply_lan_args(cmd: &mut Command, lan: &SharedNetworkEndpoint, mac: Option<&str>) {
+        let netdev = match lan {
+            SharedNetworkEndpoint::Dgram {
+                hub_port,
+                local_port,
+            } => format!(
+                "dgram,id=net1,local.type=inet,local.host=127.0.0.1,local.port={local_port},remote.type=inet,remote.host=127.0.0.1,remote.port={hub_port}"
+            ),
+            // `local.host` keeps the group on loopback.
+            SharedNetworkEndpoint::Multicast { group, port } => format!(
+                "dgram,id=net1,remote.type=inet,remote.host={group},remote.port={port},local.type=inet,local.host=127.0.0.1"
+            ),
+            SharedNetworkEndpoint::SocketVmnet { path } => format!(
+                "stream,id=net1,server=off,addr.type=unix,addr.path={}",
+                path.display()
+            ),
+        };
+        cmd.args(["-netdev", &netdev]);
+
+        let mut net1 = String::from("virtio-net-pci,netdev=net1");
+        if let Some(mac) = mac {
+            net1.push_str(",mac=");
+            net1.push_str(mac);
         }
+        cmd.args(["-device", &net1]);
     }
 
     fn apply_agent_serial_args(&self, cmd: &mut Command) {
@@ -422,17 +943,27 @@ impl QemuInstance {
         ]);
     }
 
+    /// The guest's serial console goes to `console.log` and, with a console socket, also to
+    /// whoever is connected to it.
     fn apply_console_args(&self, cmd: &mut Command) {
-        let console_log_path = self.logs_dir.join("console.log");
-        cmd.args([
-            "-chardev",
-            &format!("file,id=console,path={}", console_log_path.display()),
-        ]);
+        let log = self.console_log_path();
+        let chardev = match &self.console_socket {
+            Some(socket) => format!(
+                "{},logfile={},logappend=on",
+                socket.chardev_arg("console"),
+                log.display()
+            ),
+            None => format!("file,id=console,path={}", log.display()),
+        };
+        cmd.args(["-chardev", &chardev]);
         cmd.args(["-serial", "chardev:console"]);
     }
 
     fn apply_qmp_args(&self, cmd: &mut Command) {
         cmd.args(["-qmp", &self.qmp_socket.qmp_arg()]);
+        if let Some(control) = &self.control_socket {
+            cmd.args(["-qmp", &control.qmp_arg()]);
+        }
     }
 
     fn apply_misc_args(cmd: &mut Command, accel: QemuAccel) {
@@ -468,7 +999,9 @@ impl QemuInstance {
         Ok(())
     }
 
-    /// Send a QMP command and return the JSON response.
+    /// Send a QMP command and return the JSON response. Commands share one persistent session on
+    /// the control socket, reconnecting once if it dropped (e.g. after QEMU restarted); without a
+    /// control socket each command opens its own connection.
     ///
     /// # Errors
     /// Returns `VmError::Qmp` on communication or parsing failures.
@@ -477,43 +1010,60 @@ impl QemuInstance {
         command: &str,
         args: Option<serde_json::Value>,
     ) -> Result<serde_json::Value, VmError> {
-        let stream = connect_host_socket(&self.qmp_socket)
-            .await
-            .map_err(|e| VmError::Qmp(format!("Failed to connect to QMP: {e}")))?;
-
-        let (read_half, mut write_half) = tokio::io::split(stream);
-        let mut reader = BufReader::new(read_half);
+        let Some(control) = &self.control_socket else {
+            return qmp_execute(&self.qmp_socket, command, args).await;
+        };
 
-        let _greeting = Self::read_qmp_greeting(&mut reader).await?;
+        let mut session = self.qmp_session.lock().await;
+        if let Some(open) = session.as_mut() {
+            match open.execute(command, args.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    debug!("QMP session of {} dropped, reconnecting: {}", self.name, e);
+                    *session = None;
+                }
+            }
+        }
 
-        let capabilities = r#"{"execute": "qmp_capabilities"}"#;
-        write_half
-            .write_all(format!("{capabilities}\n").as_bytes())
-            .await
-            .map_err(|e| VmError::Qmp(format!("Failed to send capabilities: {e}")))?;
+        let open = session
+            .insert(QmpSession::connect(control, &self.name, self.qmp_events.clone()).await?);
+        let response = open.execute(command, args).await;
+        if response.is_err() {
+            *session = None;
+        }
+        response
+    }
 
-        let cap_response = Self::read_qmp_response(&mut reader).await?;
-        if let Some(err) = cap_response.get("error") {
-            return Err(VmError::Qmp(format!("qmp_capabilities error: {err}")));
+    /// Subscribe to this VM's QMP events, opening the control session if it is not open yet.
+    /// VMs without a control socket never send events.
+    ///
+    /// # Errors
+    /// Returns `VmError::Qmp` if the control session cannot be opened.
+    pub async fn subscribe_qmp_events(&self) -> Result<broadcast::Receiver<QmpEvent>, VmError> {
+        let events = self.qmp_events.subscribe();
+        if let Some(control) = &self.control_socket {
+            let mut session = self.qmp_session.lock().await;
+            if session.is_none() {
+                *session =
+                    Some(QmpSession::connect(control, &self.name, self.qmp_events.clone()).await?);
+            }
         }
+        Ok(events)
+    }
 
-        let cmd_json = if let Some(args) = args {
-            serde_json::json!({
-                "execute": command,
-                "arguments": args
-            })
-        } else {
-            serde_json::json!({
-                "execute": command
-            })
+    /// Replace the control session with a fresh connection, e.g. after the host slept. Event
+    /// subscribers keep receiving events.
+    ///
+    /// # Errors
+    /// Returns `VmError::Qmp` if the control socket cannot be reached.
+    pub async fn reconnect_qmp(&self) -> Result<(), VmError> {
+        let Some(control) = &self.control_socket else {
+            return Ok(());
         };
-
-        write_half
-            .write_all(format!("{cmd_json}\n").as_bytes())
-            .await
-            .map_err(|e| VmError::Qmp(format!("Failed to send command: {e}")))?;
-
-        Self::read_qmp_response(&mut reader).await
+        let mut session = self.qmp_session.lock().await;
+        *session = None;
+        *session = Some(QmpSession::connect(control, &self.name, self.qmp_events.clone()).await?);
+        Ok(())
     }
 
     async fn read_qmp_message<R: AsyncBufRead + Unpin>(
@@ -560,20 +1110,33 @@ impl QemuInstance {
         }
     }
 
-    /// Save a QEMU checkpoint named `name`.
+    /// Save a QEMU checkpoint named `name`, calling `on_written` with the bytes of guest state
+    /// written so far while QEMU saves it. QEMU's job does not report progress and its monitor
+    /// is busy meanwhile, so the bytes are estimated from the overlay disk's growth.
     ///
     /// # Errors
     /// Returns `VmError::Qmp` if the command fails.
-    pub async fn save_checkpoint(&self, name: &str) -> Result<(), VmError> {
+    pub async fn save_checkpoint(
+        &self,
+        name: &str,
+        mut on_written: impl FnMut(u64),
+    ) -> Result<(), VmError> {
+        // Cloud instances have no snapshots; the runner refuses to reset them instead.
+        if self.cloud.is_some() {
+            return Ok(());
+        }
         let job_id = format!("intar_snapshot_save_{}_{}", self.name, name);
+        let node = self.disk_node().await?;
+        let disk_len = || std::fs::metadata(&self.disk_path).map_or(0, |meta| meta.len());
+        let start_len = disk_len();
         let response: serde_json::Value = self
             .qmp_command(
                 "snapshot-save",
                 Some(serde_json::json!({
                     "job-id": job_id.clone(),
                     "tag": name,
-                    "vmstate": MAIN_DISK_NODE_NAME,
-                    "devices": [MAIN_DISK_NODE_NAME],
+                    "vmstate": node,
+                    "devices": [node],
                 })),
             )
             .await?;
@@ -582,7 +1145,15 @@ impl QemuInstance {
             return Err(VmError::Qmp(format!("snapshot-save error: {err}")));
         }
 
-        self.wait_for_job(&job_id).await
+        let job = self.wait_for_job(&job_id);
+        tokio::pin!(job);
+        let mut ticks = tokio::time::interval(CHECKPOINT_PROGRESS_INTERVAL);
+        loop {
+            tokio::select! {
+                result = &mut job => return result,
+                _ = ticks.tick() => on_written(disk_len().saturating_sub(start_len)),
+            }
+        }
     }
 
     /// Load a previously saved QEMU checkpoint.
@@ -590,15 +1161,22 @@ impl QemuInstance {
     /// # Errors
     /// Returns `VmError::Qmp` if the command fails.
     pub async fn load_checkpoint(&self, name: &str) -> Result<(), VmError> {
+        if self.cloud.is_some() {
+            return Err(VmError::NotPermitted(format!(
+                "VM {} is a cloud instance, which has no checkpoints",
+                self.name
+            )));
+        }
         let job_id = format!("intar_snapshot_load_{}_{}", self.name, name);
+        let node = self.disk_node().await?;
         let response: serde_json::Value = self
             .qmp_command(
                 "snapshot-load",
                 Some(serde_json::json!({
                     "job-id": job_id.clone(),
                     "tag": name,
-                    "vmstate": MAIN_DISK_NODE_NAME,
-                    "devices": [MAIN_DISK_NODE_NAME],
+                    "vmstate": node,
+                    "devices": [node],
                 })),
             )
             .await?;
@@ -610,6 +1188,33 @@ impl QemuInstance {
         self.wait_for_job(&job_id).await
     }
 
+    /// Block node of the main disk, which holds the checkpoints. libvirt names its nodes
+    /// itself, so under libvirt the node is found by the disk's file.
+    async fn disk_node(&self) -> Result<String, VmError> {
+        if self.libvirt.is_none() {
+            return Ok(MAIN_DISK_NODE_NAME.to_string());
+        }
+        let response = self.qmp_command("query-block", None).await?;
+        let disk = self.disk_path.display().to_string();
+        response
+            .get("return")
+            .and_then(serde_json::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|device| device.get("inserted"))
+            .find(|inserted| {
+                inserted.get("file").and_then(serde_json::Value::as_str) == Some(disk.as_str())
+            })
+            .and_then(|inserted| inserted.get("node-name")?.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                VmError::Qmp(format!(
+                    "VM {} has no block node for its disk {disk}",
+                    self.name
+                ))
+            })
+    }
+
     async fn wait_for_job(&self, job_id: &str) -> Result<(), VmError> {
         let deadline = Instant::now() + SNAPSHOT_JOB_TIMEOUT;
 
@@ -689,11 +1294,58 @@ impl QemuInstance {
         Ok(())
     }
 
+    /// Ask the guest to power off (ACPI) and wait for QEMU to exit, so the disk is left
+    /// consistent.
+    ///
+    /// # Errors
+    /// Returns `VmError::Qmp` if the request fails, or `VmError::Timeout` if the guest is still
+    /// running after `wait`.
+    pub async fn power_down(&mut self, wait: Duration) -> Result<(), VmError> {
+        let response: serde_json::Value = self.qmp_command("system_powerdown", None).await?;
+
+        if let Some(err) = response.get("error") {
+            return Err(VmError::Qmp(format!("system_powerdown failed: {err}")));
+        }
+
+        let Some(child) = self.process.as_mut() else {
+            return Err(VmError::Qemu(format!(
+                "VM {} was not started by this instance",
+                self.name
+            )));
+        };
+        let deadline = Instant::now() + wait;
+        loop {
+            match child.try_wait() {
+                Ok(Some(_)) => {
+                    self.process = None;
+                    return Ok(());
+                }
+                Ok(None) if Instant::now() < deadline => {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+                Ok(None) => {
+                    return Err(VmError::Timeout(format!(
+                        "VM {} did not power off within {}s",
+                        self.name,
+                        wait.as_secs()
+                    )));
+                }
+                Err(e) => {
+                    return Err(VmError::Qemu(format!("Failed to check QEMU status: {e}")));
+                }
+            }
+        }
+    }
+
     /// Pause guest CPUs.
     ///
     /// # Errors
     /// Returns `VmError::Qmp` if the QMP command fails.
     pub async fn pause(&self) -> Result<(), VmError> {
+        // Cloud instances keep running.
+        if self.cloud.is_some() {
+            return Ok(());
+        }
         let response: serde_json::Value = self.qmp_command("stop", None).await?;
 
         if let Some(err) = response.get("error") {
@@ -708,6 +1360,9 @@ impl QemuInstance {
     /// # Errors
     /// Returns `VmError::Qmp` if the QMP command fails.
     pub async fn resume(&self) -> Result<(), VmError> {
+        if self.cloud.is_some() {
+            return Ok(());
+        }
         let response: serde_json::Value = self.qmp_command("cont", None).await?;
 
         if let Some(err) = response.get("error") {
@@ -717,12 +1372,85 @@ impl QemuInstance {
         Ok(())
     }
 
-    /// Stop the QEMU process.
+    /// Shrink or grow the guest's memory through the virtio balloon.
     ///
     /// # Errors
-    /// Returns `VmError` if QMP `quit` fails; ignores errors while killing the child.
+    /// Returns `VmError::Qmp` if the QMP command fails.
+    pub async fn set_memory_target(&self, memory_mb: u32) -> Result<(), VmError> {
+        let response = self
+            .qmp_command("balloon", Some(balloon_args(memory_mb)))
+            .await?;
+        balloon_result(&response)
+    }
+
+    /// How the QEMU process ended, if it has (e.g. `exit status: 1`, `signal: 9 (SIGKILL)`).
+    /// Always `None` for attached VMs, whose process is not a child of this one.
+    pub fn exit_status(&mut self) -> Option<String> {
+        let status = self.process.as_mut()?.try_wait().ok()??;
+        Some(status.to_string())
+    }
+
+    /// Release the QEMU process and the QMP session without stopping it, so the VM outlives
+    /// this instance and another process can connect. `stop` still shuts it down via QMP.
+    pub fn detach(&mut self) {
+        self.process.take();
+        *self.qmp_session.get_mut() = None;
+        if let Some(link) = self.cloud_link.take() {
+            link.abort();
+        }
+    }
+
+    /// PID of the QEMU process, from the PID file (also set for attached VMs), or under
+    /// libvirt from the one libvirt keeps.
+    #[must_use]
+    pub fn pid(&self) -> Option<u32> {
+        if let Some(domain) = &self.libvirt {
+            return domain.pid();
+        }
+        std::fs::read_to_string(&self.pid_file)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Whether the QEMU process recorded in the PID file still answers on QMP, or for a cloud
+    /// instance, whether it is recorded. libvirt domains have no PID file of intar's, so only
+    /// QMP is asked.
+    pub async fn is_alive(&self) -> bool {
+        if self.cloud.is_some() {
+            return self.cloud_record.exists();
+        }
+        if self.libvirt.is_some() {
+            return self.answers_qmp().await;
+        }
+        self.pid_file.exists() && self.answers_qmp().await
+    }
+
+    /// Whether a QEMU process answers on this VM's QMP socket.
+    pub async fn answers_qmp(&self) -> bool {
+        self.qmp_command("query-status", None).await.is_ok()
+    }
+
+    /// Stop the QEMU process, or terminate the cloud instance.
+    ///
+    /// # Errors
+    /// Returns `VmError` if QMP `quit` fails or the cloud instance cannot be terminated; ignores
+    /// errors while killing the child.
     pub async fn stop(&mut self) -> Result<(), VmError> {
         self.qmp_command("quit", None).await.ok();
+        *self.qmp_session.get_mut() = None;
+        // Usually gone with its QEMU already.
+        if let Some(domain) = &self.libvirt {
+            domain.destroy().ok();
+        }
+        if let Some(link) = self.cloud_link.take() {
+            link.abort();
+        }
+        if self.cloud.is_some() && self.cloud_record.exists() {
+            CloudInstance::load(&self.cloud_record)?.terminate()?;
+            std::fs::remove_file(&self.cloud_record)?;
+        }
 
         if let Some(mut child) = self.process.take() {
             let deadline = Instant::now() + Duration::from_secs(5);
@@ -747,30 +1475,139 @@ impl QemuInstance {
             }
         }
 
-        for socket in [&self.qmp_socket, &self.serial_socket, &self.actions_socket] {
-            if let Some(path) = socket.cleanup_path()
-                && path.exists()
-            {
-                std::fs::remove_file(path).ok();
+        self.cleanup_runtime_files();
+
+        Ok(())
+    }
+}
+
+/// Run one QMP command on `socket` and return the raw response (`return` or `error`).
+///
+/// # Errors
+/// Returns `VmError::Qmp` if the socket cannot be reached or the exchange fails.
+pub async fn qmp_execute(
+    socket: &HostSocket,
+    command: &str,
+    args: Option<serde_json::Value>,
+) -> Result<serde_json::Value, VmError> {
+    let stream = connect_host_socket(socket)
+        .await
+        .map_err(|e| VmError::Qmp(format!("Failed to connect to QMP: {e}")))?;
+
+    let (read_half, mut write_half) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read_half);
+
+    let _greeting = QemuInstance::read_qmp_greeting(&mut reader).await?;
+
+    send_qmp_command(&mut write_half, "qmp_capabilities", None).await?;
+    let cap_response = QemuInstance::read_qmp_response(&mut reader).await?;
+    if let Some(err) = cap_response.get("error") {
+        return Err(VmError::Qmp(format!("qmp_capabilities error: {err}")));
+    }
+
+    send_qmp_command(&mut write_half, command, args).await?;
+    QemuInstance::read_qmp_response(&mut reader).await
+}
+
+async fn send_qmp_command(
+    writer: &mut WriteHalf<HostStream>,
+    command: &str,
+    args: Option<serde_json::Value>,
+) -> Result<(), VmError> {
+    let cmd_json = if let Some(args) = args {
+        serde_json::json!({
+            "execute": command,
+            "arguments": args
+        })
+    } else {
+        serde_json::json!({
+            "execute": command
+        })
+    };
+
+    writer
+        .write_all(format!("{cmd_json}\n").as_bytes())
+        .await
+        .map_err(|e| VmError::Qmp(format!("Failed to send {command}: {e}")))
+}
+
+/// Save the state of the VM behind `qmp_socket` to `state_file` with QEMU's migration, which
+/// leaves the guest stopped once it is complete. `cont` resumes it where it was.
+///
+/// # Errors
+/// Returns `VmError::Qmp` if the migration fails, `VmError::Timeout` if it takes over ten
+/// minutes.
+pub async fn migrate_to_file(qmp_socket: &HostSocket, state_file: &Path) -> Result<(), VmError> {
+    let uri = format!("file:{}", path_to_str(state_file)?);
+    let response = qmp_execute(
+        qmp_socket,
+        "migrate",
+        Some(serde_json::json!({ "uri": uri })),
+    )
+    .await?;
+    if let Some(err) = response.get("error") {
+        return Err(VmError::Qmp(format!("migrate failed: {err}")));
+    }
+
+    let deadline = Instant::now() + MIGRATION_TIMEOUT;
+    loop {
+        let response = qmp_execute(qmp_socket, "query-migrate", None).await?;
+        let status = response
+            .pointer("/return/status")
+            .and_then(serde_json::Value::as_str);
+        match status {
+            Some("completed") => return Ok(()),
+            Some("failed" | "cancelled") => {
+                let reason = response
+                    .pointer("/return/error-desc")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or("no reason given");
+                return Err(VmError::Qmp(format!("migration failed: {reason}")));
+            }
+            _ if Instant::now() >= deadline => {
+                return Err(VmError::Timeout(format!(
+                    "migration to {} did not finish within {}s",
+                    state_file.display(),
+                    MIGRATION_TIMEOUT.as_secs()
+                )));
             }
+            _ => tokio::time::sleep(SNAPSHOT_JOB_POLL_INTERVAL).await,
         }
+    }
+}
 
-        if self.pid_file.exists() {
-            std::fs::remove_file(&self.pid_file).ok();
-        }
+/// Set the balloon target of the VM behind `qmp_socket` to `memory_mb`.
+///
+/// # Errors
+/// Returns `VmError::Qmp` if the QMP command fails.
+pub async fn set_balloon_target(qmp_socket: &HostSocket, memory_mb: u32) -> Result<(), VmError> {
+    let response = qmp_execute(qmp_socket, "balloon", Some(balloon_args(memory_mb))).await?;
+    balloon_result(&response)
+}
 
-        Ok(())
+fn balloon_args(memory_mb: u32) -> serde_json::Value {
+    serde_json::json!({ "value": u64::from(memory_mb) * 1024 * 1024 })
+}
+
+fn balloon_result(response: &serde_json::Value) -> Result<(), VmError> {
+    if let Some(err) = response.get("error") {
+        return Err(VmError::Qmp(format!("balloon failed: {err}")));
     }
+
+    Ok(())
 }
 
 impl Drop for QemuInstance {
     fn drop(&mut self) {
+        if let Some(link) = self.cloud_link.take() {
+            link.abort();
+        }
         if let Some(mut child) = self.process.take() {
             child.kill().ok();
-        }
 
-        if self.pid_file.exists() {
-            std::fs::remove_file(&self.pid_file).ok();
+            if self.pid_file.exists() {
+                std::fs::remove_file(&self.pid_file).ok();
+            }
         }
     }
 }
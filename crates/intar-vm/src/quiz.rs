@@ -0,0 +1,127 @@
+use crate::VmError;
+use crate::audit::unix_ms;
+use intar_core::Question;
+use intar_probes::flag_hash;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const ANSWERS_FILE: &str = "answers.jsonl";
+
+/// What the learner is told after answering a question.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerOutcome {
+    Correct,
+    /// The question stays open so the learner can try again.
+    Incorrect,
+    /// No local answer key (or exam mode): stored for grading without feedback.
+    Recorded,
+}
+
+impl AnswerOutcome {
+    /// Whether the question counts as answered.
+    #[must_use]
+    pub fn closes_question(self) -> bool {
+        self != AnswerOutcome::Incorrect
+    }
+}
+
+/// Grade `answer` against the question's hashed key. In exam mode the result is recorded but
+/// not revealed.
+#[must_use]
+pub fn grade_answer(
+    question: &Question,
+    answer: &str,
+    exam: bool,
+) -> (AnswerOutcome, Option<bool>) {
+    let correct = question
+        .answer_sha256
+        .as_deref()
+        .map(|expected| flag_hash(answer).eq_ignore_ascii_case(expected));
+
+    let outcome = match correct {
+        Some(_) if exam => AnswerOutcome::Recorded,
+        Some(true) => AnswerOutcome::Correct,
+        Some(false) => AnswerOutcome::Incorrect,
+        None => AnswerOutcome::Recorded,
+    };
+    (outcome, correct)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerRecord {
+    pub ts_unix_ms: u64,
+    pub question: String,
+    pub answer: String,
+    /// `None` when the question has no answer key.
+    pub correct: Option<bool>,
+}
+
+/// Every answer attempt for a run, appended to `answers.jsonl` for later grading.
+pub struct AnswerLog {
+    path: PathBuf,
+}
+
+impl AnswerLog {
+    #[must_use]
+    pub fn new(run_dir: &Path) -> Self {
+        Self {
+            path: run_dir.join(ANSWERS_FILE),
+        }
+    }
+
+    /// Append one answer attempt.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the record cannot be written.
+    pub fn record(
+        &self,
+        question: &str,
+        answer: &str,
+        correct: Option<bool>,
+    ) -> Result<(), VmError> {
+        let record = AnswerRecord {
+            ts_unix_ms: unix_ms(),
+            question: question.to_string(),
+            answer: answer.trim().to_string(),
+            correct,
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(answer: Option<&str>) -> Question {
+        Question {
+            name: "port".into(),
+            prompt: "Which port?".into(),
+            choices: vec!["22".into(), "80".into()],
+            answer_sha256: answer.map(flag_hash),
+            after: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_grade_answer() {
+        let q = question(Some("80"));
+        assert_eq!(grade_answer(&q, " 80 ", false).0, AnswerOutcome::Correct);
+        assert_eq!(grade_answer(&q, "22", false).0, AnswerOutcome::Incorrect);
+        assert_eq!(
+            grade_answer(&q, "22", true),
+            (AnswerOutcome::Recorded, Some(false))
+        );
+        assert_eq!(
+            grade_answer(&question(None), "anything", false),
+            (AnswerOutcome::Recorded, None)
+        );
+    }
+}
@@ -0,0 +1,659 @@
+use crate::{
+    ImageCache, IntarConfig, IntarDirs, KNOWN_HOSTS_FILE, RunState, ScenarioRunner, ScenarioState,
+    SshHost, VmError, check_disk_space, detect_arch, find_free_ports, random_seed,
+    required_run_space, validate_run_name,
+};
+use intar_core::Scenario;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin};
+
+/// Sockets of each VM that are tunnelled to the local mirror, as in `<vm>-<socket>.sock`.
+const VM_SOCKETS: [&str; 5] = ["qmp", "serial", "actions", "control", "console"];
+
+/// Files of a remote run directory its local mirror gets a copy of.
+const MIRRORED_FILES: [&str; 6] = [
+    "state.json",
+    "scenario.json",
+    "progress.json",
+    "id_ed25519",
+    "id_ed25519.pub",
+    KNOWN_HOSTS_FILE,
+];
+
+/// Line the local intar sends before closing the session when its TUI stopped the run.
+const STOPPED: &str = "stopped";
+
+/// How long the ssh tunnels get to bind the mirror's sockets.
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How long the remote intar gets to stop and remove a stopped run.
+const SERVE_EXIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where the VMs of a run started with `intar start --remote` run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteRun {
+    pub host: SshHost,
+    /// The run directory on `host`.
+    pub run_dir: PathBuf,
+}
+
+/// What the local intar asks `intar remote-serve` for, as the first line on its stdin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum RemoteRequest {
+    /// Boot a new run of `scenario`.
+    Start {
+        scenario: Box<Scenario>,
+        name: Option<String>,
+        seed: Option<u64>,
+        /// Overrides the scenario's `timeouts.ttl`.
+        ttl_secs: Option<u64>,
+        /// The scenario's host hooks may run (`--allow-host-hooks`).
+        #[serde(default)]
+        allow_host_hooks: bool,
+    },
+    /// Take up the kept run named `run` again.
+    Attach { run: String },
+}
+
+/// A line `intar remote-serve` answers with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "snake_case")]
+pub enum RemoteReply {
+    /// Progress while the run boots or resumes.
+    Step {
+        message: String,
+    },
+    /// The run is up and its VMs are handed over.
+    Ready(RemoteReady),
+    Failed {
+        message: String,
+    },
+}
+
+/// A remote run that is up, as far as its local mirror needs to know.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteReady {
+    pub run_dir: PathBuf,
+    /// Contents of the run's files the mirror copies, by file name.
+    pub files: BTreeMap<String, String>,
+    /// File names of the VMs' sockets in `run_dir`.
+    pub sockets: Vec<String>,
+}
+
+impl RemoteReady {
+    /// Name of the run, which its local mirror shares.
+    ///
+    /// # Errors
+    /// Returns `VmError::InvalidPath` if the run directory has no usable name.
+    pub fn run_name(&self) -> Result<&str, VmError> {
+        self.run_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| VmError::InvalidPath(self.run_dir.display().to_string()))
+    }
+}
+
+/// Serve one run to `intar start --remote` on another host, in JSON lines over stdin and
+/// stdout: read a [`RemoteRequest`], boot or resume the run while reporting its steps, answer
+/// [`RemoteReply::Ready`], and release the VMs to the other host, which drives them through ssh
+/// tunnels. The run's shared LAN switch and mock services keep running here until stdin ends.
+/// A `stopped` line before that means the run was stopped there, and it is stopped and removed
+/// here too; otherwise it is kept for the next `intar attach`.
+///
+/// # Errors
+/// Returns `VmError` if the request cannot be read or the run cannot be booted, resumed, or
+/// stopped. Boot and resume errors are also sent as [`RemoteReply::Failed`].
+pub async fn serve_remote(
+    agent_binary_x86_64: Vec<u8>,
+    agent_binary_aarch64: Vec<u8>,
+) -> Result<(), VmError> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let request = lines
+        .next_line()
+        .await?
+        .ok_or_else(|| VmError::Remote("No request on stdin".into()))?;
+    let request: RemoteRequest = serde_json::from_str(&request)
+        .map_err(|e| VmError::Remote(format!("Invalid request: {e}")))?;
+
+    let dirs = IntarDirs::new()?;
+    let opened = open_run(&dirs, request, agent_binary_x86_64, agent_binary_aarch64).await;
+    let mut runner = match opened {
+        Ok(runner) => runner,
+        Err(e) => {
+            send(&RemoteReply::Failed {
+                message: e.to_string(),
+            })?;
+            return Err(e);
+        }
+    };
+    send(&RemoteReply::Ready(ready_reply(&runner)?))?;
+    runner.release_vms();
+
+    let mut stopped = false;
+    while let Some(line) = lines.next_line().await? {
+        stopped |= line.trim() == STOPPED;
+    }
+    if stopped {
+        runner.stop().await?;
+        runner.cleanup()
+    } else {
+        runner.detach()
+    }
+}
+
+/// Boot or resume the run `request` names.
+async fn open_run(
+    dirs: &IntarDirs,
+    request: RemoteRequest,
+    agent_binary_x86_64: Vec<u8>,
+    agent_binary_aarch64: Vec<u8>,
+) -> Result<ScenarioRunner, VmError> {
+    match request {
+        RemoteRequest::Start {
+            scenario,
+            name,
+            seed,
+            ttl_secs,
+            allow_host_hooks,
+        } => {
+            let config = IntarConfig::load(dirs)?;
+            // Nobody is there to confirm a scenario over the quota.
+            config.quota.check(&scenario)?;
+            dirs.ensure_dirs()?;
+            let seed = seed.unwrap_or_else(random_seed);
+            let run_dir = dirs.create_seeded_run_dir(name.as_deref(), seed)?;
+            let mut runner = ScenarioRunner::new_in_run_dir(
+                *scenario,
+                agent_binary_x86_64,
+                agent_binary_aarch64,
+                dirs,
+                run_dir.clone(),
+            )
+            .inspect_err(|_| {
+                let _ = std::fs::remove_dir(&run_dir);
+            })?;
+            runner.set_seed(seed);
+            if let Some(ttl) = ttl_secs.or(runner.scenario.timeouts.ttl_secs) {
+                runner.set_ttl(Duration::from_secs(ttl));
+            }
+            if allow_host_hooks || config.host_execution.allow {
+                runner.set_allow_host_hooks();
+            }
+            runner.set_allowed_providers(config.host_execution.providers.clone());
+            if let Err(e) = runner.check_host_execution() {
+                let _ = runner.cleanup();
+                return Err(e);
+            }
+            if let Err(e) = boot(&mut runner, dirs, &config).await {
+                let _ = runner.stop().await;
+                let _ = runner.cleanup();
+                return Err(e);
+            }
+            Ok(runner)
+        }
+        RemoteRequest::Attach { run } => {
+            validate_run_name(&run)?;
+            step(&format!("Resuming run {run}"))?;
+            ScenarioRunner::resume(
+                &dirs.runs_dir().join(&run),
+                agent_binary_x86_64,
+                agent_binary_aarch64,
+            )
+            .await
+        }
+    }
+}
+
+/// Boot a new run up to its `init` checkpoint, like the TUI does. Actions are not recorded
+/// and QMP events not tracked here, since the guests' action streams take one reader at a time
+/// and the local TUI takes them over.
+async fn boot(
+    runner: &mut ScenarioRunner,
+    dirs: &IntarDirs,
+    config: &IntarConfig,
+) -> Result<(), VmError> {
+    let image_cache = ImageCache::new(dirs.images_dir()).with_settings(&config.downloads);
+    let arch = detect_arch();
+    let scenario = runner.scenario.clone();
+
+    let mut sources = Vec::new();
+    for vm in &scenario.vms {
+        let source = scenario
+            .images
+            .get(&vm.image)
+            .and_then(|image| image.source_for_arch(&arch))
+            .ok_or_else(|| {
+                VmError::Qemu(format!(
+                    "No image source for architecture '{arch}' in image '{}'",
+                    vm.image
+                ))
+            })?;
+        sources.push(source);
+    }
+    let mut image_bytes = 0;
+    for source in &sources {
+        if !image_cache.is_cached(source) {
+            image_bytes += image_cache.download_size(source).await.unwrap_or(0);
+        }
+    }
+    check_disk_space(
+        &dirs.images_dir(),
+        image_bytes,
+        &dirs.runs_dir(),
+        required_run_space(&scenario),
+    )?;
+
+    for (vm, source) in scenario.vms.iter().zip(sources) {
+        if !image_cache.is_cached(source) {
+            step(&format!("Downloading image {}", vm.image))?;
+            image_cache.ensure_image(source).await?;
+        }
+        step(&format!("Creating VM {}", vm.name))?;
+        runner.create_vm(vm, &image_cache, &arch)?;
+    }
+    runner.set_boot_concurrency(config.boot_concurrency);
+    let _ = runner.calibrate_boot_timeouts();
+
+    step("Booting the VMs")?;
+    runner.start_vms()?;
+    runner
+        .wait_for_agents_with_progress(|vm| {
+            let _ = step(&format!("Agent ready on {vm}"));
+        })
+        .await?;
+    let mut reported = None;
+    runner
+        .wait_for_boot_probes_with_progress(|passing, total| {
+            if reported != Some(passing) {
+                reported = Some(passing);
+                let _ = step(&format!("Boot probes passing: {passing}/{total}"));
+            }
+        })
+        .await?;
+    runner
+        .run_external_actions(|line| {
+            let _ = step(line);
+        })
+        .await?;
+    step("Saving checkpoint 'init'")?;
+    runner.save_checkpoint("init").await?;
+    runner.state = ScenarioState::Running;
+    runner.save_progress(BTreeMap::new())
+}
+
+/// What the local mirror of `runner`'s run needs.
+fn ready_reply(runner: &ScenarioRunner) -> Result<RemoteReady, VmError> {
+    let run_dir = &runner.work_dir;
+    let mut files = BTreeMap::new();
+    for name in MIRRORED_FILES {
+        match std::fs::read_to_string(run_dir.join(name)) {
+            Ok(content) => {
+                files.insert(name.to_string(), content);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let sockets = runner
+        .vm_order
+        .iter()
+        .flat_map(|vm| {
+            VM_SOCKETS
+                .iter()
+                .map(move |socket| format!("{vm}-{socket}.sock"))
+        })
+        .filter(|name| run_dir.join(name).exists())
+        .collect();
+    Ok(RemoteReady {
+        run_dir: run_dir.clone(),
+        files,
+        sockets,
+    })
+}
+
+fn step(message: &str) -> Result<(), VmError> {
+    send(&RemoteReply::Step {
+        message: message.to_string(),
+    })
+}
+
+fn send(reply: &RemoteReply) -> Result<(), VmError> {
+    let line = serde_json::to_string(reply)?;
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{line}")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The local end of a run that [`serve_remote`] serves on another host: the ssh session
+/// running `intar remote-serve` there and, once the run is mirrored, the ssh tunnels to its VMs.
+/// Dropping it ends both, which keeps the run on the host.
+pub struct RemoteSession {
+    host: SshHost,
+    server: Child,
+    stdin: ChildStdin,
+    tunnels: Option<Child>,
+}
+
+impl RemoteSession {
+    /// Run `intar remote-serve` on `host`, send it `request`, and wait until the run is up,
+    /// reporting the host's steps to `on_step`.
+    ///
+    /// # Errors
+    /// Returns `VmError::Remote` if ssh fails, the host cannot boot or resume the run, or its
+    /// intar ends without answering.
+    pub async fn open(
+        host: &SshHost,
+        request: &RemoteRequest,
+        mut on_step: impl FnMut(&str),
+    ) -> Result<(Self, RemoteReady), VmError> {
+        let mut server = host
+            .command()
+            .arg(&host.destination)
+            .arg("intar remote-serve")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| VmError::Remote(format!("Failed to run ssh: {e}")))?;
+        let (Some(mut stdin), Some(stdout)) = (server.stdin.take(), server.stdout.take()) else {
+            return Err(VmError::Remote("ssh has no stdin or stdout".into()));
+        };
+
+        let request = serde_json::to_string(request)?;
+        stdin.write_all(format!("{request}\n").as_bytes()).await?;
+        stdin.flush().await?;
+
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines.next_line().await? {
+            let reply = serde_json::from_str(&line).map_err(|e| {
+                VmError::Remote(format!(
+                    "{} answered '{line}' instead of a reply: {e}",
+                    host.destination
+                ))
+            })?;
+            match reply {
+                RemoteReply::Step { message } => on_step(&message),
+                RemoteReply::Failed { message } => {
+                    return Err(VmError::Remote(format!("{}: {message}", host.destination)));
+                }
+                RemoteReply::Ready(ready) => {
+                    let session = Self {
+                        host: host.clone(),
+                        server,
+                        stdin,
+                        tunnels: None,
+                    };
+                    return Ok((session, ready));
+                }
+            }
+        }
+        Err(VmError::Remote(format!(
+            "`intar remote-serve` on {} ended without answering; it needs an intar with \
+             remote-serve on the PATH of its ssh sessions",
+            host.destination
+        )))
+    }
+
+    /// Mirror the run `ready` describes into `run_dir`: copy its files, give the VMs' SSH and
+    /// API forwards ports free here, and tunnel those and the VMs' sockets to the host. Progress
+    /// already in `run_dir` is kept, since the local TUI is what records it.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the files cannot be written or the tunnels do not come up.
+    pub async fn mirror(&mut self, run_dir: &Path, ready: &RemoteReady) -> Result<(), VmError> {
+        for (name, content) in &ready.files {
+            let path = run_dir.join(name);
+            if name == "progress.json" && path.exists() {
+                continue;
+            }
+            std::fs::write(&path, content)?;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // ssh refuses private keys others can read.
+            std::fs::set_permissions(
+                run_dir.join("id_ed25519"),
+                std::fs::Permissions::from_mode(0o600),
+            )?;
+        }
+
+        let mut state = RunState::load(run_dir)?;
+        let ports = find_free_ports(forwarded_port_count(&state))?;
+        let mut forwards = forward_ports(&mut state, ports)?;
+        state.remote = Some(RemoteRun {
+            host: self.host.clone(),
+            run_dir: ready.run_dir.clone(),
+        });
+        state.save(run_dir)?;
+
+        let sockets: Vec<PathBuf> = ready
+            .sockets
+            .iter()
+            .map(|name| run_dir.join(name))
+            .collect();
+        forwards.extend(ready.sockets.iter().map(|name| {
+            format!(
+                "{}:{}",
+                run_dir.join(name).display(),
+                ready.run_dir.join(name).display()
+            )
+        }));
+        self.tunnels = Some(self.open_tunnels(&forwards, &sockets).await?);
+        Ok(())
+    }
+
+    /// End the session: close the tunnels and let the host know whether the run was
+    /// `stopped`, which stops and removes it there as well, or kept.
+    ///
+    /// # Errors
+    /// Returns `VmError::Remote` if the host's intar fails to stop the run.
+    pub async fn close(self, stopped: bool) -> Result<(), VmError> {
+        let Self {
+            host,
+            mut server,
+            mut stdin,
+            tunnels,
+        } = self;
+        if let Some(mut tunnels) = tunnels {
+            let _ = tunnels.kill().await;
+        }
+        if stopped {
+            stdin.write_all(format!("{STOPPED}\n").as_bytes()).await?;
+            stdin.flush().await?;
+        }
+        drop(stdin);
+
+        match tokio::time::timeout(SERVE_EXIT_TIMEOUT, server.wait()).await {
+            Ok(Ok(status)) if status.success() => Ok(()),
+            Ok(Ok(status)) => Err(VmError::Remote(format!(
+                "intar on {} failed to release the run ({status})",
+                host.destination
+            ))),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(VmError::Remote(format!(
+                "intar on {} did not release the run within {}s",
+                host.destination,
+                SERVE_EXIT_TIMEOUT.as_secs()
+            ))),
+        }
+    }
+
+    /// Run ssh with `forwards` until it is stopped, once every socket in `sockets` is bound.
+    async fn open_tunnels(
+        &self,
+        forwards: &[String],
+        sockets: &[PathBuf],
+    ) -> Result<Child, VmError> {
+        let mut tunnels = self
+            .host
+            .command()
+            .args(tunnel_args(forwards))
+            .arg(&self.host.destination)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| VmError::Remote(format!("Failed to run ssh: {e}")))?;
+
+        let deadline = Instant::now() + TUNNEL_TIMEOUT;
+        while !sockets.iter().all(|socket| socket.exists()) {
+            if let Some(status) = tunnels.try_wait()? {
+                return Err(VmError::Remote(format!(
+                    "ssh tunnels to {} exited ({status})",
+                    self.host.destination
+                )));
+            }
+            if Instant::now() >= deadline {
+                return Err(VmError::Remote(format!(
+                    "ssh tunnels to {} did not come up within {}s",
+                    self.host.destination,
+                    TUNNEL_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Ok(tunnels)
+    }
+}
+
+/// TCP ports a mirror of the run in `state` forwards: each VM's SSH port and API port.
+fn forwarded_port_count(state: &RunState) -> usize {
+    state
+        .vms
+        .iter()
+        .map(|vm| 1 + usize::from(vm.api_port.is_some()))
+        .sum()
+}
+
+/// Move the VMs' SSH and API ports in `state` to the local `ports`, returning a `-L` forward
+/// from each to the VM's port on the remote host.
+fn forward_ports(state: &mut RunState, ports: Vec<u16>) -> Result<Vec<String>, VmError> {
+    let mut ports = ports.into_iter();
+    let mut forwards = Vec::new();
+    let mut forward = |remote: u16| {
+        let local = ports.next().ok_or(VmError::NoFreePort)?;
+        forwards.push(format!("{local}:127.0.0.1:{remote}"));
+        Ok::<_, VmError>(local)
+    };
+    for vm in &mut state.vms {
+        vm.ssh_port = forward(vm.ssh_port)?;
+        if let Some(api_port) = vm.api_port {
+            vm.api_port = Some(forward(api_port)?);
+        }
+    }
+    Ok(forwards)
+}
+
+/// Options of the ssh process holding `forwards`. Stale socket files are replaced, and ssh
+/// exits rather than run with a forward missing.
+pub(crate) fn tunnel_args(forwards: &[String]) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-N",
+        "-o",
+        "ExitOnForwardFailure=yes",
+        "-o",
+        "StreamLocalBindUnlink=yes",
+        "-o",
+        "ServerAliveInterval=15",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    for forward in forwards {
+        args.extend(["-L".to_string(), forward.clone()]);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LanBackend, VmInfo};
+
+    #[test]
+    fn test_forward_ports() {
+        let vm = |name: &str, ssh_port, api_port| VmInfo {
+            name: name.into(),
+            ssh_port,
+            api_port,
+            image: "ubuntu".into(),
+            lan_port: None,
+            socket_ports: Vec::new(),
+            host_key_fingerprint: None,
+        };
+        let mut state = RunState {
+            scenario_name: "lab".into(),
+            vms: vec![vm("web", 2201, None), vm("k8s", 2202, Some(6443))],
+            seed: None,
+            fault_selections: BTreeMap::new(),
+            triggers: Vec::new(),
+            lan_hub_port: None,
+            lan_backend: LanBackend::Switch,
+            ephemeral_dir: None,
+            mock_ports: BTreeMap::new(),
+            expires_at_ms: None,
+            incoming_migration: false,
+            remote: None,
+            libvirt_uri: None,
+            cloud: None,
+            paranoid: false,
+            host_hooks: false,
+            audit: None,
+        };
+        assert_eq!(forwarded_port_count(&state), 3);
+        let forwards = forward_ports(&mut state, vec![40_001, 40_002, 40_003]).unwrap();
+
+        assert_eq!(
+            forwards,
+            [
+                "40001:127.0.0.1:2201",
+                "40002:127.0.0.1:2202",
+                "40003:127.0.0.1:6443",
+            ]
+        );
+        assert_eq!(state.vms[1].ssh_port, 40_002);
+        assert_eq!(state.vms[1].api_port, Some(40_003));
+        assert!(matches!(
+            forward_ports(&mut state, vec![40_004]),
+            Err(VmError::NoFreePort)
+        ));
+    }
+
+    #[test]
+    fn test_tunnel_args() {
+        let args = tunnel_args(&["/runs/a/web-qmp.sock:/srv/runs/a/web-qmp.sock".into()]);
+        assert_eq!(args[0], "-N");
+        assert_eq!(
+            &args[args.len() - 2..],
+            ["-L", "/runs/a/web-qmp.sock:/srv/runs/a/web-qmp.sock"]
+        );
+    }
+
+    #[test]
+    fn test_remote_reply_lines() {
+        let ready = RemoteReply::Ready(RemoteReady {
+            run_dir: "/srv/runs/calm-otter".into(),
+            files: BTreeMap::from([("state.json".to_string(), "{}".to_string())]),
+            sockets: vec!["web-qmp.sock".into()],
+        });
+        let line = serde_json::to_string(&ready).unwrap();
+        assert!(line.starts_with(r#"{"reply":"ready","run_dir":"/srv/runs/calm-otter""#));
+        assert_eq!(serde_json::from_str::<RemoteReply>(&line).unwrap(), ready);
+
+        let RemoteReply::Ready(ready) = ready else {
+            unreachable!()
+        };
+        assert_eq!(ready.run_name().unwrap(), "calm-otter");
+    }
+}
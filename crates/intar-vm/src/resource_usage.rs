@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Summary of a finished run in its run directory.
+pub const REPORT_FILE: &str = "report.json";
+
+const MIB: u64 = 1024 * 1024;
+
+/// Stages in the order a run goes through them.
+const STAGE_ORDER: &[&str] = &["init", "images", "vms", "boot", "run"];
+
+/// What a run cost the host, printed after teardown and saved in `report.json` to help size
+/// scenarios.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Peak resident memory of each VM's QEMU process, in bytes.
+    #[serde(default)]
+    pub peak_rss_bytes: BTreeMap<String, u64>,
+    /// Base images downloaded for the run, in bytes; zero when all were cached.
+    #[serde(default)]
+    pub downloaded_bytes: u64,
+    /// Disk taken by the VMs' overlay disks at teardown, in bytes.
+    #[serde(default)]
+    pub overlay_bytes: u64,
+    /// Wall-clock seconds spent in each stage.
+    #[serde(default)]
+    pub stage_secs: BTreeMap<String, u64>,
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "  resources: {} MB downloaded, {} MB of overlay disks",
+            self.downloaded_bytes.div_ceil(MIB),
+            self.overlay_bytes.div_ceil(MIB)
+        )?;
+        if !self.peak_rss_bytes.is_empty() {
+            let peaks: Vec<String> = self
+                .peak_rss_bytes
+                .iter()
+                .map(|(vm, bytes)| format!("{vm} {} MB", bytes.div_ceil(MIB)))
+                .collect();
+            writeln!(f, "  peak memory: {}", peaks.join(", "))?;
+        }
+        let known = STAGE_ORDER
+            .iter()
+            .filter_map(|stage| Some((*stage, *self.stage_secs.get(*stage)?)));
+        let other = self
+            .stage_secs
+            .iter()
+            .filter(|(stage, _)| !STAGE_ORDER.contains(&stage.as_str()))
+            .map(|(stage, secs)| (stage.as_str(), *secs));
+        let stages: Vec<String> = known
+            .chain(other)
+            .map(|(stage, secs)| format!("{stage} {}", format_secs(secs)))
+            .collect();
+        if !stages.is_empty() {
+            writeln!(f, "  time: {}", stages.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn format_secs(secs: u64) -> String {
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_usage_summary() {
+        let usage = ResourceUsage {
+            peak_rss_bytes: BTreeMap::from([("db".into(), 600 * MIB), ("web".into(), 1100 * MIB)]),
+            downloaded_bytes: 0,
+            overlay_bytes: 845 * MIB - 1,
+            stage_secs: BTreeMap::from([
+                ("boot".into(), 51),
+                ("images".into(), 0),
+                ("run".into(), 724),
+            ]),
+        };
+        assert_eq!(
+            usage.to_string(),
+            "  resources: 0 MB downloaded, 845 MB of overlay disks\n  \
+             peak memory: db 600 MB, web 1100 MB\n  \
+             time: images 0s, boot 51s, run 12m 4s\n"
+        );
+
+        let json = serde_json::to_string(&usage).unwrap();
+        assert_eq!(serde_json::from_str::<ResourceUsage>(&json).unwrap(), usage);
+    }
+}
@@ -1,20 +1,43 @@
 use crate::{
-    ActionLineEvent, CloudInitGenerator, HostSocket, ImageCache, IntarDirs, LanSwitch,
-    QemuInstance, QemuInstanceConfig, QemuSockets, ScenarioState, SharedNetworkEndpoint, VmError,
-    VmState, find_free_ports, find_free_udp_port, path_to_str, start_vm_actions_task, try_connect,
+    ActionLineEvent, ActionLog, AgentConnection, AnsibleInventory, AnswerLog, AnswerOutcome,
+    AuditHead, AuditLog, AuditOperation, AuditSummary, AutoBalloon, BUILTIN_PROVIDERS,
+    BootStageEvent, BootTimeouts, CaptureConfig, CheckpointProgress, CheckpointTracker,
+    CloudInitGenerator, CloudSettings, CloudVm, CpuSampler, DEFAULT_CAPTURE_FILE, ExecOutput,
+    HostMemory, HostSocket, HostSpeed, ImageCache, IntarConfig, IntarDirs, InventoryHost,
+    KNOWN_HOSTS_FILE, LanBackend, LanHost, LanServicesConfig, LanStats, LanSwitch, LearnerProfile,
+    LibvirtDomain, MockServices, ProbeHistory, ProbeTrace, ProviderContext, QEMU_EXITED_EVENT,
+    QemuInstance, QemuInstanceConfig, QemuPriority, QemuSockets, QmpEvent, QuotaAction,
+    REPORT_FILE, RemoteRun, ResourceUsage, RunProgress, ScenarioState, SessionMirror,
+    SharedNetworkEndpoint, SshHostKey, TemplateVars, VirtBackend, VmError, VmState,
+    available_space, check_libvirt, create_ephemeral_dir, find_free_ports, find_free_udp_port,
+    grade_answer, host_key_options, indicates_image_corruption, lan_services_ip, load_run_scenario,
+    make_private_dir, migration_file, parse_mac, path_to_str, random_seed, record_host_key,
+    render_triggered_step, required_run_space, resident_memory, run_external_action,
+    save_run_scenario, select_random_step, start_vm_actions_task, try_connect, user_net_dns_ip,
+    user_net_host_ip,
+};
+use base64::Engine as _;
+use intar_core::{
+    CloudInitConfig, CompletionAction, DiskCache, Ipv4Subnet, K3S_API_PORT, K3S_KUBECONFIG,
+    K8sCluster, ProbeDefinition, ProbePhase, Question, Scenario, ScenarioNetwork, StepTrigger,
+    VmDefinition, VmStep, WriteFile,
+};
+use intar_probes::{
+    FileChanges, FileManifest, PLUGIN_DIR, ProbeEvaluator, ProbeResult, ProbeSpec, Severity,
+    SudoInvocation, flag_hash,
 };
-use intar_core::{CloudInitConfig, ProbePhase, Scenario, VmDefinition, WriteFile};
-use intar_probes::{ProbeResult, ProbeSpec};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use tokio::time::{Duration, timeout};
 use tracing::{error, info, warn};
 
 use crate::apply_vm_steps_to_cloud_init;
+use crate::audit::unix_ms;
 use futures_util::future::try_join_all;
 
 const NET_SETUP_SCRIPT_PREFIX: &str = r#"#!/usr/bin/env bash
@@ -65,17 +88,93 @@ if exists_if "$MGMT_IF" && [ "$MGMT_IF" != "enp0s1" ]; then
 fi
 "#;
 
+/// ULA prefix of the shared LAN. A VM's address repeats the last octet of its IPv4 address:
+/// 10.11.0.12 is fd00:11::12.
+const LAN_IPV6_PREFIX: &str = "fd00:11::";
+
+/// Addresses of a VM's shared LAN NIC for its network config.
+#[derive(Debug, Clone, Copy)]
+struct LanNic<'a> {
+    mac: &'a str,
+    ip: &'a str,
+    /// Set for VMs with IPv6 enabled.
+    ipv6: Option<&'a str>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VmInfo {
     pub name: String,
     pub ssh_port: u16,
+    /// Host loopback port forwarded to the guest's Kubernetes API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_port: Option<u16>,
     pub image: String,
+    /// Local UDP port of the VM's shared LAN endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lan_port: Option<u16>,
+    /// TCP ports of the QMP, serial, actions, and (if any) control and console sockets (Windows
+    /// hosts only).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub socket_ports: Vec<u16>,
+    /// Fingerprint of the SSH host key generated for the VM; `None` in runs from before intar
+    /// generated host keys, whose VMs are reached without checking the key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_key_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunState {
     pub scenario_name: String,
     pub vms: Vec<VmInfo>,
+    /// Seed of the run's generated name and `random_one_of` step groups; `intar start --seed`
+    /// with it reproduces the run.
+    #[serde(default, alias = "fault_seed", skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Step chosen for each `random_one_of` group, keyed by `<vm>/<group>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fault_selections: BTreeMap<String, String>,
+    /// Steps that run after boot (`trigger = "manual"` or `{ after = ... }`), as `<vm>/<step>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<String>,
+    /// UDP port of the shared LAN switch, re-bound when the run is resumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lan_hub_port: Option<u16>,
+    /// What carries the shared LAN.
+    #[serde(default)]
+    pub lan_backend: LanBackend,
+    /// The tmpfs directory holding the overlay disks of an ephemeral run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ephemeral_dir: Option<PathBuf>,
+    /// Host loopback port of each `mock_service`, re-bound when the run is resumed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mock_ports: BTreeMap<String, u16>,
+    /// When the run's TTL runs out, in Unix milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_ms: Option<u64>,
+    /// The VMs were saved to their migration files by `intar migrate-run`, so resuming starts
+    /// QEMU from those instead of attaching to it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub incoming_migration: bool,
+    /// Where the VMs of a run started with `intar start --remote` run; this run directory then
+    /// only mirrors that run, reaching its VMs through ssh tunnels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<RemoteRun>,
+    /// Connection URI of the libvirt the VMs run under (`--backend libvirt`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub libvirt_uri: Option<String>,
+    /// Where the VMs run as cloud instances (`--backend cloud`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud: Option<CloudSettings>,
+    /// The run was started with `intar start --paranoid`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paranoid: bool,
+    /// The scenario's host commands and webhooks may run (`intar start --allow-host-hooks`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub host_hooks: bool,
+    /// Head of the run's audit log; a log that is missing or does not reach it fails
+    /// verification. `None` in runs from before intar pinned it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditHead>,
 }
 
 impl RunState {
@@ -101,6 +200,124 @@ impl RunState {
         std::fs::write(&state_file, content)?;
         Ok(())
     }
+
+    /// Ask the running TUI for `run_dir` to fire a triggered step (`<vm>/<step>` or `<step>`).
+    ///
+    /// # Errors
+    /// Returns `VmError` if the step is not triggerable in this run or the request cannot be
+    /// written.
+    pub fn request_trigger(&self, run_dir: &Path, step: &str) -> Result<(), VmError> {
+        let matches = self
+            .triggers
+            .iter()
+            .filter(|t| t.as_str() == step || t.rsplit('/').next() == Some(step))
+            .count();
+        if matches != 1 {
+            return Err(VmError::NotPermitted(format!(
+                "'{step}' is not a unique triggerable step (available: {})",
+                self.triggers.join(", ")
+            )));
+        }
+
+        write_run_request(run_dir, TRIGGERS_DIR, step)
+    }
+}
+
+const TRIGGERS_DIR: &str = "triggers";
+const SUBMISSIONS_DIR: &str = "submissions";
+const CAPTURES_DIR: &str = "captures";
+/// File manifests of the VMs' `track_changes` directories at the last checkpoint.
+const FILE_BASELINE_FILE: &str = "file-baseline.json";
+
+/// Queue a flag submission for the running TUI of `run_dir`; it is checked on the next probe pass.
+///
+/// # Errors
+/// Returns `VmError` if the request cannot be written.
+pub fn queue_flag_submission(run_dir: &Path, flag: &str) -> Result<(), VmError> {
+    write_run_request(run_dir, SUBMISSIONS_DIR, flag.trim())
+}
+
+/// Ask the running TUI of `run_dir` to start capturing its shared LAN as `capture` describes, or
+/// to stop capturing when `capture` is `None`. The request is handled on the next probe pass.
+///
+/// # Errors
+/// Returns `VmError` if the request cannot be written.
+pub fn queue_lan_capture(run_dir: &Path, capture: Option<&CaptureConfig>) -> Result<(), VmError> {
+    let content = match capture {
+        Some(config) => serde_json::to_string(config)?,
+        None => "stop".to_string(),
+    };
+    write_run_request(run_dir, CAPTURES_DIR, &content)
+}
+
+fn write_run_request(run_dir: &Path, kind: &str, content: &str) -> Result<(), VmError> {
+    let dir = run_dir.join(kind);
+    std::fs::create_dir_all(&dir)?;
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    std::fs::write(dir.join(format!("{nanos}.req")), content)?;
+    Ok(())
+}
+
+/// Read and delete queued requests of `kind`, oldest first.
+/// A probe's `severity` attribute, if it has one.
+fn probe_severity(def: &ProbeDefinition) -> Result<Option<Severity>, String> {
+    def.config
+        .get("severity")
+        .map(|value| {
+            serde_json::from_value(value.clone())
+                .map_err(|_| format!("severity must be 'info', 'warning', or 'error', got {value}"))
+        })
+        .transpose()
+}
+
+/// An `on_complete` hook as shown to the user, e.g. `webhook https://example.com/done`.
+fn describe_hook(hook: &CompletionAction) -> String {
+    match hook {
+        CompletionAction::Vm { vm, .. } => format!("actions on VM {vm}"),
+        CompletionAction::HostCommand { cmd } => format!("host command '{cmd}'"),
+        CompletionAction::Webhook { url } => format!("webhook {url}"),
+    }
+}
+
+fn take_run_requests(run_dir: &Path, kind: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(run_dir.join(kind)) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok();
+            let _ = std::fs::remove_file(&path);
+            content.map(|c| c.trim().to_string())
+        })
+        .filter(|content| !content.is_empty())
+        .collect()
+}
+
+/// A post-boot step with its template variables already expanded.
+struct TriggeredStep {
+    vm: String,
+    step: VmStep,
+    fired: bool,
+    /// The step's script while the agent runs it, until
+    /// [`ScenarioRunner::fire_pending_triggers`] collects the result.
+    running: Option<tokio::task::JoinHandle<Result<ExecOutput, VmError>>>,
+}
+
+impl TriggeredStep {
+    fn id(&self) -> String {
+        format!("{}/{}", self.vm, self.step.name)
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.id() == name || self.step.name == name
+    }
 }
 
 pub struct ScenarioRunner {
@@ -108,19 +325,78 @@ pub struct ScenarioRunner {
     pub state: ScenarioState,
     pub vms: HashMap<String, QemuInstance>,
     pub probe_results: HashMap<String, HashMap<String, ProbeResult>>,
+    /// When each scenario objective first passed; cleared by a reset.
+    pub probe_history: ProbeHistory,
     pub vm_order: Vec<String>,
     pub work_dir: PathBuf,
     pub ssh_private_key: String,
     pub ssh_public_key: String,
     pub vm_addresses: HashMap<String, String>,
+    /// Shared LAN IPv6 addresses of the VMs with `ipv6` enabled.
+    pub vm_ipv6_addresses: HashMap<String, String>,
     agent_binary_x86_64: Vec<u8>,
     agent_binary_aarch64: Vec<u8>,
     ports: Vec<u16>,
     port_index: usize,
     shared_lan_hub_port: Option<u16>,
+    lan_backend: LanBackend,
+    /// Where the overlay disks are when not in the run directory.
+    ephemeral_dir: Option<PathBuf>,
     lan_switch: Option<LanSwitch>,
+    mock_ports: BTreeMap<String, u16>,
+    mock_services: Option<MockServices>,
     action_rx: Option<mpsc::Receiver<ActionLineEvent>>,
+    action_log: ActionLog,
     action_tasks: Vec<tokio::task::JoinHandle<()>>,
+    qmp_event_rx: Option<mpsc::Receiver<QmpEvent>>,
+    /// Sender of `qmp_event_rx`, for VMs started after event tracking began.
+    qmp_event_tx: Option<mpsc::Sender<QmpEvent>>,
+    qmp_event_tasks: Vec<tokio::task::JoinHandle<()>>,
+    /// State each paused VM returns to when QEMU reports `RESUME`.
+    resume_states: HashMap<String, VmState>,
+    /// Why each crashed VM's QEMU process exited, until it is restarted.
+    crashes: HashMap<String, String>,
+    /// Checkpoint a crashed VM is restarted from.
+    latest_checkpoint: Option<String>,
+    audit: AuditLog,
+    results_unlocked: bool,
+    /// Every randomized choice of the run derives from this, so it can be reproduced.
+    seed: u64,
+    fault_selections: BTreeMap<String, String>,
+    triggered_steps: Vec<TriggeredStep>,
+    accepted_flags: HashSet<String>,
+    answers: AnswerLog,
+    answered_questions: HashSet<String>,
+    auto_balloon: Option<AutoBalloon>,
+    priority: QemuPriority,
+    cpu_sampler: CpuSampler,
+    /// Largest resident memory seen per VM's QEMU process.
+    peak_rss: BTreeMap<String, u64>,
+    downloaded_bytes: u64,
+    boot_timeouts: BootTimeouts,
+    boot_stage_tx: Option<mpsc::Sender<BootStageEvent>>,
+    /// Most VMs booting at once; the others wait in [`VmState::Waiting`] for a later wave.
+    boot_concurrency: Option<u32>,
+    vm_start_tx: Option<mpsc::Sender<String>>,
+    session_mirror: SessionMirror,
+    last_activity: std::time::Instant,
+    expires_at_ms: Option<u64>,
+    learner: Option<LearnerProfile>,
+    /// Simulates the VMs instead of QEMU when set.
+    backend: Option<Box<dyn VirtBackend>>,
+    /// Every probe evaluation, kept under `--author`.
+    probe_trace: Option<ProbeTrace>,
+    remote: Option<RemoteRun>,
+    /// Runs the VMs as libvirt domains through this connection when set.
+    libvirt_uri: Option<String>,
+    /// Runs the VMs as cloud instances when set.
+    cloud: Option<CloudSettings>,
+    paranoid: bool,
+    host_hooks: bool,
+    /// `action "external"` providers allowed beside [`BUILTIN_PROVIDERS`].
+    providers: Vec<String>,
+    /// `on_complete` hooks due to run, in order, since the scenario completed.
+    pending_hooks: VecDeque<CompletionAction>,
 }
 
 impl ScenarioRunner {
@@ -137,7 +413,8 @@ impl ScenarioRunner {
         Self::new_with_dirs(scenario, agent_binary_x86_64, agent_binary_aarch64, &dirs)
     }
 
-    /// Build a runner using explicit directories (useful for tests).
+    /// Build a runner using explicit directories (useful for tests), in a run directory with a
+    /// generated name.
     ///
     /// # Errors
     /// Returns `VmError` if directory setup fails.
@@ -148,16 +425,57 @@ impl ScenarioRunner {
         dirs: &IntarDirs,
     ) -> Result<Self, VmError> {
         dirs.ensure_dirs()?;
+        let seed = random_seed();
+        let work_dir = dirs.create_seeded_run_dir(None, seed)?;
+        let mut runner = Self::new_in_run_dir(
+            scenario,
+            agent_binary_x86_64,
+            agent_binary_aarch64,
+            dirs,
+            work_dir.clone(),
+        )
+        .inspect_err(|_| {
+            // Only removed while still empty, i.e. when the run never got going.
+            let _ = std::fs::remove_dir(&work_dir);
+        })?;
+        runner.set_seed(seed);
+        Ok(runner)
+    }
+
+    /// Build a runner in `work_dir`, a run directory claimed with [`IntarDirs::create_run_dir`].
+    ///
+    /// # Errors
+    /// Returns `VmError` if the scenario is over quota or the run's files cannot be written.
+    pub fn new_in_run_dir(
+        mut scenario: Scenario,
+        agent_binary_x86_64: Vec<u8>,
+        agent_binary_aarch64: Vec<u8>,
+        dirs: &IntarDirs,
+        work_dir: PathBuf,
+    ) -> Result<Self, VmError> {
+        let config = IntarConfig::load(dirs)?;
+        let learner = LearnerProfile::load(dirs)?;
+        // With `on_exceed: prompt`, asking the user is up to the caller before getting here.
+        if config.quota.on_exceed == QuotaAction::Reject {
+            config.quota.check(&scenario)?;
+        }
+        // Saved with the scenario below, so a resumed run keeps the same addresses.
+        config.network.apply(&mut scenario)?;
+        let lan_backend = config.network.lan_backend;
+        if scenario.vms.len() > 1 {
+            lan_backend.check_available(scenario.network.has_services())?;
+        }
 
-        let work_dir = dirs.new_run_dir();
         std::fs::create_dir_all(&work_dir)?;
 
         let (private_key, public_key) = generate_ssh_keypair(&work_dir)?;
 
+        // SSH and Kubernetes API forwards, plus QMP, serial, actions, and control sockets on
+        // Windows.
         let port_count = if cfg!(target_os = "windows") {
-            scenario.vms.len() * 4
+            scenario.vms.len() * 6
         } else {
-            scenario.vms.len()
+            scenario.vms.len() * 2
         };
         let ports = find_free_ports(port_count)?;
         let shared_lan_hub_port = if scenario.vms.len() > 1 {
@@ -165,30 +483,842 @@ impl ScenarioRunner {
         } else {
             None
         };
+        let mock_ports = MockServices::allocate_ports(&scenario.mock_services)?;
 
         let vm_addresses = Self::assign_vm_addresses(&scenario)?;
+        let vm_ipv6_addresses = Self::assign_vm_ipv6_addresses(&scenario)?;
+
+        let mut audit = AuditLog::open(&work_dir, None)?;
+        audit.record(AuditOperation::RunStarted, scenario.name.clone())?;
+        save_run_scenario(&work_dir, &scenario)?;
+
+        let answers = AnswerLog::new(&work_dir);
+        let action_log = ActionLog::new(&work_dir.join("logs"));
 
         Ok(Self {
             scenario,
             state: ScenarioState::Initializing,
             vms: HashMap::new(),
             probe_results: HashMap::new(),
+            probe_history: ProbeHistory::default(),
             vm_order: Vec::new(),
             work_dir,
             ssh_private_key: private_key,
             ssh_public_key: public_key,
             vm_addresses,
+            vm_ipv6_addresses,
             agent_binary_x86_64,
             agent_binary_aarch64,
             ports,
             port_index: 0,
             shared_lan_hub_port,
+            lan_backend,
+            ephemeral_dir: None,
             lan_switch: None,
+            mock_ports,
+            mock_services: None,
             action_rx: None,
+            action_log,
             action_tasks: Vec::new(),
+            qmp_event_rx: None,
+            qmp_event_tx: None,
+            qmp_event_tasks: Vec::new(),
+            resume_states: HashMap::new(),
+            crashes: HashMap::new(),
+            latest_checkpoint: None,
+            audit,
+            results_unlocked: false,
+            seed: random_seed(),
+            fault_selections: BTreeMap::new(),
+            triggered_steps: Vec::new(),
+            accepted_flags: HashSet::new(),
+            answers,
+            answered_questions: HashSet::new(),
+            auto_balloon: None,
+            priority: QemuPriority::default(),
+            cpu_sampler: CpuSampler::default(),
+            peak_rss: BTreeMap::new(),
+            downloaded_bytes: 0,
+            boot_timeouts: BootTimeouts::default(),
+            boot_stage_tx: None,
+            boot_concurrency: None,
+            vm_start_tx: None,
+            session_mirror: SessionMirror::default(),
+            last_activity: std::time::Instant::now(),
+            expires_at_ms: None,
+            learner,
+            backend: None,
+            probe_trace: None,
+            remote: None,
+            libvirt_uri: None,
+            cloud: None,
+            paranoid: false,
+            host_hooks: false,
+            providers: Vec::new(),
+            pending_hooks: VecDeque::new(),
         })
     }
 
+    /// Re-attach to a run whose VMs were left running, restoring probe, flag, question, and
+    /// trigger progress from the run directory.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the run's files are missing or one of its VMs is no longer running.
+    pub async fn resume(
+        run_dir: &Path,
+        agent_binary_x86_64: Vec<u8>,
+        agent_binary_aarch64: Vec<u8>,
+    ) -> Result<Self, VmError> {
+        let scenario = load_run_scenario(run_dir)?;
+        let run_state = RunState::load(run_dir)?;
+        let progress = RunProgress::load(run_dir)?
+            .ok_or_else(|| VmError::NotPermitted("run never reached the running phase".into()))?;
+
+        let ssh_private_key = std::fs::read_to_string(run_dir.join("id_ed25519"))?;
+        let ssh_public_key = std::fs::read_to_string(run_dir.join("id_ed25519.pub"))?
+            .trim()
+            .to_string();
+        let vm_addresses = Self::assign_vm_addresses(&scenario)?;
+        let vm_ipv6_addresses = Self::assign_vm_ipv6_addresses(&scenario)?;
+
+        let mut audit = AuditLog::open(run_dir, run_state.audit.as_ref())?;
+        audit.record(AuditOperation::RunResumed, scenario.name.clone())?;
+
+        let mut runner = Self {
+            scenario,
+            state: progress.state,
+            vms: HashMap::new(),
+            probe_results: progress.probe_results,
+            probe_history: progress.probe_history,
+            vm_order: Vec::new(),
+            work_dir: run_dir.to_path_buf(),
+            ssh_private_key,
+            ssh_public_key,
+            vm_addresses,
+            vm_ipv6_addresses,
+            agent_binary_x86_64,
+            agent_binary_aarch64,
+            ports: Vec::new(),
+            port_index: 0,
+            shared_lan_hub_port: run_state.lan_hub_port,
+            lan_backend: run_state.lan_backend,
+            ephemeral_dir: run_state.ephemeral_dir.clone(),
+            lan_switch: None,
+            mock_ports: run_state.mock_ports.clone(),
+            mock_services: None,
+            action_rx: None,
+            action_log: ActionLog::new(&run_dir.join("logs")),
+            action_tasks: Vec::new(),
+            qmp_event_rx: None,
+            qmp_event_tx: None,
+            qmp_event_tasks: Vec::new(),
+            resume_states: HashMap::new(),
+            crashes: HashMap::new(),
+            // Every run that reached the running phase has its `init` checkpoint.
+            latest_checkpoint: Some("init".into()),
+            audit,
+            results_unlocked: progress.results_unlocked,
+            seed: run_state.seed.unwrap_or_default(),
+            fault_selections: BTreeMap::new(),
+            triggered_steps: Vec::new(),
+            accepted_flags: progress.accepted_flags,
+            answers: AnswerLog::new(run_dir),
+            answered_questions: progress.answered_questions,
+            auto_balloon: None,
+            priority: QemuPriority::default(),
+            cpu_sampler: CpuSampler::default(),
+            peak_rss: BTreeMap::new(),
+            downloaded_bytes: 0,
+            boot_timeouts: BootTimeouts::default(),
+            boot_stage_tx: None,
+            boot_concurrency: None,
+            vm_start_tx: None,
+            session_mirror: SessionMirror::default(),
+            last_activity: std::time::Instant::now(),
+            expires_at_ms: run_state.expires_at_ms,
+            learner: progress.learner,
+            backend: None,
+            probe_trace: None,
+            remote: run_state.remote.clone(),
+            libvirt_uri: run_state.libvirt_uri.clone(),
+            cloud: run_state.cloud.clone(),
+            paranoid: run_state.paranoid,
+            host_hooks: run_state.host_hooks,
+            providers: Vec::new(),
+            pending_hooks: VecDeque::new(),
+        };
+
+        for vm_def in runner.scenario.vms.clone() {
+            let info = run_state
+                .vms
+                .iter()
+                .find(|vm| vm.name == vm_def.name)
+                .ok_or_else(|| VmError::VmNotFound(vm_def.name.clone()))?;
+            runner
+                .attach_vm(&vm_def, info, run_state.incoming_migration)
+                .await?;
+        }
+        if run_state.incoming_migration {
+            // The state files are spent once loaded.
+            runner.save_state()?;
+            for name in &runner.vm_order {
+                std::fs::remove_file(migration_file(run_dir, name))?;
+            }
+        }
+        for triggered in &mut runner.triggered_steps {
+            triggered.fired = progress.fired_triggers.contains(&triggered.id());
+        }
+        runner.start_lan_switch_if_needed()?;
+        runner.start_mock_services_if_needed()?;
+        // The run may have been kept paused.
+        runner.resume_vms().await?;
+
+        Ok(runner)
+    }
+
+    /// Rebuild the instance for a VM that is still running from a previous session, or with
+    /// `incoming`, start it from the state `intar migrate-run` saved.
+    async fn attach_vm(
+        &mut self,
+        vm_def: &VmDefinition,
+        info: &VmInfo,
+        incoming: bool,
+    ) -> Result<(), VmError> {
+        let vm_index = self.vm_order.len();
+        let (primary_mac, lan_mac) = Self::generate_macs(vm_index)?;
+        let mgmt_ip = Self::mgmt_ip(self.scenario.network.management(), vm_index)?;
+        let shared_lan = self
+            .shared_lan_hub_port
+            .and_then(|hub_port| self.lan_backend.endpoint(hub_port, info.lan_port));
+        #[cfg(unix)]
+        let sockets = {
+            // Runs started before the control monitor or the console socket existed lack them.
+            let existing = |socket: HostSocket| {
+                socket
+                    .cleanup_path()
+                    .is_some_and(Path::exists)
+                    .then_some(socket)
+            };
+            QemuSockets {
+                qmp: self.host_socket_for_vm(&vm_def.name, "qmp"),
+                serial: self.host_socket_for_vm(&vm_def.name, "serial"),
+                actions: self.host_socket_for_vm(&vm_def.name, "actions"),
+                control: existing(self.host_socket_for_vm(&vm_def.name, "control")),
+                console: existing(self.host_socket_for_vm(&vm_def.name, "console")),
+            }
+        };
+        #[cfg(windows)]
+        let sockets = {
+            let &[qmp, serial, actions, ref optional @ ..] = info.socket_ports.as_slice() else {
+                return Err(VmError::Qemu(format!(
+                    "No socket ports recorded for VM {}",
+                    vm_def.name
+                )));
+            };
+            QemuSockets {
+                qmp: HostSocket::tcp(qmp),
+                serial: HostSocket::tcp(serial),
+                actions: HostSocket::tcp(actions),
+                control: optional.first().copied().map(HostSocket::tcp),
+                console: optional.get(1).copied().map(HostSocket::tcp),
+            }
+        };
+
+        let mut vm = QemuInstance::new(
+            QemuInstanceConfig {
+                definition: vm_def.clone(),
+                ssh_port: info.ssh_port,
+                api_port: info.api_port,
+                mgmt_subnet: self.scenario.network.management(),
+                mgmt_ip: mgmt_ip.clone(),
+                shared_lan,
+                primary_mac: Some(primary_mac),
+                lan_mac,
+                sockets,
+                priority: self.priority.clone(),
+                disk_dir: self.disk_dir().to_path_buf(),
+                libvirt: self.libvirt_domain(&vm_def.name),
+                cloud: self.cloud_vm(vm_def)?,
+                paranoid: self.paranoid,
+            },
+            &self.work_dir,
+        );
+        vm.host_key_fingerprint
+            .clone_from(&info.host_key_fingerprint);
+        if incoming {
+            vm.start_incoming(
+                &detect_arch(),
+                &migration_file(&self.work_dir, &vm_def.name),
+            )?;
+            vm.wait_for_incoming().await?;
+        } else {
+            // A remote run's pid files are on its host.
+            let alive = if self.remote.is_some() {
+                vm.answers_qmp().await
+            } else {
+                vm.is_alive().await
+            };
+            if !alive {
+                return Err(VmError::Qemu(format!(
+                    "VM {} is no longer running",
+                    vm_def.name
+                )));
+            }
+            vm.open_cloud_link();
+        }
+        vm.state = VmState::Booting;
+
+        let steps = self.resolve_vm_steps(vm_def);
+        self.register_triggered_steps(vm_def, &mgmt_ip, &steps)?;
+
+        self.vms.insert(vm_def.name.clone(), vm);
+        self.probe_results.entry(vm_def.name.clone()).or_default();
+        self.vm_order.push(vm_def.name.clone());
+        Ok(())
+    }
+
+    /// Leave QEMU running even if intar exits or crashes without stopping the run, so the run
+    /// can be resumed. `stop` still shuts the VMs down.
+    pub fn keep_vms(&mut self) {
+        for vm in self.vms.values_mut() {
+            vm.detach();
+        }
+    }
+
+    /// Persist probe, flag, question, and trigger progress (plus the TUI's stage timings) so
+    /// `intar resume` can restore it.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the progress file cannot be written.
+    pub fn save_progress(&self, stage_secs: BTreeMap<String, u64>) -> Result<(), VmError> {
+        RunProgress {
+            state: self.state,
+            probe_results: self.probe_results.clone(),
+            probe_history: self.probe_history.clone(),
+            results_unlocked: self.results_unlocked,
+            accepted_flags: self.accepted_flags.clone(),
+            answered_questions: self.answered_questions.clone(),
+            fired_triggers: self
+                .triggered_steps
+                .iter()
+                .filter(|t| t.fired)
+                .map(TriggeredStep::id)
+                .collect(),
+            stage_secs,
+            learner: self.learner.clone(),
+        }
+        .save(&self.work_dir)
+    }
+
+    /// Reclaim memory from idle VMs while the host runs low on RAM (see
+    /// [`ScenarioRunner::rebalance_memory`]).
+    pub fn enable_auto_balloon(&mut self) {
+        self.auto_balloon = Some(AutoBalloon::new(std::time::Instant::now()));
+    }
+
+    /// Apply auto-balloon decisions; a no-op unless enabled and host memory is readable.
+    pub async fn rebalance_memory(&mut self) {
+        let Some(host) = HostMemory::read() else {
+            return;
+        };
+        let Some(auto) = self.auto_balloon.as_mut() else {
+            return;
+        };
+
+        let vm_defs: Vec<VmDefinition> =
+            self.vms.values().map(|vm| vm.definition.clone()).collect();
+        for (name, memory_mb) in auto.plan(&vm_defs, host, std::time::Instant::now()) {
+            let Some(vm) = self.vms.get(&name) else {
+                continue;
+            };
+            info!("Setting memory of VM {} to {} MB", name, memory_mb);
+            if let Err(e) = vm.set_memory_target(memory_mb).await {
+                warn!("Failed to balloon VM {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Start QEMU with lower scheduling priority and/or pinned to a CPU set. Applies to VMs
+    /// created (or restarted) after this call.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if the priority is invalid.
+    pub fn set_qemu_priority(&mut self, priority: QemuPriority) -> Result<(), VmError> {
+        priority.validate()?;
+        self.priority = priority;
+        Ok(())
+    }
+
+    /// Boot at most `limit` VMs at once (`intar start --boot-concurrency`); the others start in
+    /// waves as the earlier ones come up. `None` or 0 boots them all together.
+    pub fn set_boot_concurrency(&mut self, limit: Option<u32>) {
+        self.boot_concurrency = limit.filter(|limit| *limit > 0);
+    }
+
+    /// How many VMs one boot wave starts.
+    fn wave_size(&self) -> usize {
+        self.boot_concurrency
+            .and_then(|limit| usize::try_from(limit).ok())
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Refresh [`QemuInstance::host_cpu`] for every VM, and the peak memory of its QEMU
+    /// process for [`ScenarioRunner::resource_usage`].
+    pub fn sample_host_cpu(&mut self) {
+        for vm in self.vms.values_mut() {
+            let pid = vm.pid();
+            vm.host_cpu = pid.and_then(|pid| self.cpu_sampler.sample(pid));
+            if let Some(rss) = pid.and_then(resident_memory) {
+                let peak = self.peak_rss.entry(vm.name.clone()).or_default();
+                *peak = (*peak).max(rss);
+            }
+        }
+    }
+
+    /// Count `bytes` of base images downloaded for this run.
+    pub fn note_downloaded(&mut self, bytes: u64) {
+        self.downloaded_bytes = self.downloaded_bytes.saturating_add(bytes);
+    }
+
+    /// What the run has cost the host so far, with `stage_secs` as the time per stage. Call
+    /// before [`ScenarioRunner::stop`], while the QEMU processes and disks are still there.
+    pub fn resource_usage(&mut self, stage_secs: BTreeMap<String, u64>) -> ResourceUsage {
+        self.sample_host_cpu();
+        let overlay_bytes = self
+            .vms
+            .values()
+            .filter_map(|vm| std::fs::metadata(&vm.disk_path).ok())
+            .map(|meta| meta.len())
+            .sum();
+        ResourceUsage {
+            peak_rss_bytes: self.peak_rss.clone(),
+            downloaded_bytes: self.downloaded_bytes,
+            overlay_bytes,
+            stage_secs,
+        }
+    }
+
+    /// Write [`REPORT_FILE`] to the run directory: how the run ended and what it cost the host.
+    /// Objectives, the latest result of every probe with its details, `sudo` from
+    /// [`ScenarioRunner::sudo_usage`], and `changed_files` from [`ScenarioRunner::file_changes`]
+    /// are left out while an exam's results are hidden.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the report cannot be written.
+    pub fn save_report(
+        &self,
+        resources: &ResourceUsage,
+        sudo: &[(String, SudoInvocation)],
+        changed_files: &[(String, FileChanges)],
+    ) -> Result<(), VmError> {
+        let mut report = serde_json::json!({
+            "scenario": self.scenario.name,
+            "run_id": self.run_id(),
+            "seed": self.seed,
+            "completed": self.state == ScenarioState::Completed,
+            "resources": resources,
+            "audit": self.audit_summary(),
+        });
+        if self.results_visible() {
+            report["objectives_passed"] = self.passing_probe_count().into();
+            report["objectives_total"] = self.total_probe_count().into();
+            let probes: BTreeMap<&String, Vec<&ProbeResult>> = self
+                .probe_results
+                .iter()
+                .map(|(vm, results)| {
+                    let mut results: Vec<&ProbeResult> = results.values().collect();
+                    results.sort_by(|a, b| a.id.cmp(&b.id));
+                    (vm, results)
+                })
+                .collect();
+            report["probes"] = serde_json::to_value(probes)?;
+            report["sudo"] = sudo
+                .iter()
+                .map(|(vm, invocation)| {
+                    let mut entry = serde_json::to_value(invocation)?;
+                    entry["vm"] = vm.as_str().into();
+                    Ok(entry)
+                })
+                .collect::<Result<Vec<_>, serde_json::Error>>()?
+                .into();
+            report["changed_files"] = serde_json::to_value(
+                changed_files
+                    .iter()
+                    .map(|(vm, changes)| (vm, changes))
+                    .collect::<BTreeMap<_, _>>(),
+            )?;
+        }
+        if let Some(learner) = &self.learner {
+            report["learner"] = serde_json::to_value(learner)?;
+        }
+        std::fs::write(
+            self.work_dir.join(REPORT_FILE),
+            serde_json::to_string_pretty(&report)?,
+        )?;
+        Ok(())
+    }
+
+    /// Free bytes left on the filesystem holding the VMs' disks.
+    ///
+    /// # Errors
+    /// Returns `VmError::Io` if the filesystem cannot be queried.
+    pub fn available_space(&self) -> Result<u64, VmError> {
+        available_space(self.disk_dir())
+    }
+
+    /// Keep the overlay disks of VMs created after this call on a tmpfs instead of in the run
+    /// directory, so resets and disk-heavy steps run at memory speed. They are gone after a host
+    /// reboot.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if the host has no tmpfs for them or a VM's `disk_io`
+    /// bypasses the page cache, which tmpfs does not support, and `VmError::DiskSpace` if the
+    /// run does not fit in it.
+    pub fn set_ephemeral(&mut self) -> Result<(), VmError> {
+        if let Some(vm) = self
+            .scenario
+            .vms
+            .iter()
+            .find(|vm| vm.disk_io.cache.is_some_and(DiskCache::is_direct))
+        {
+            return Err(VmError::NotPermitted(format!(
+                "VM '{}' uses disk_io cache '{}', which cannot run on a tmpfs with --ephemeral",
+                vm.name,
+                vm.disk_io.effective_cache(false).as_str()
+            )));
+        }
+        let dir = create_ephemeral_dir(&self.run_id(), required_run_space(&self.scenario))?;
+        self.ephemeral_dir = Some(dir);
+        Ok(())
+    }
+
+    /// Whether the overlay disks live on a tmpfs (`intar start --ephemeral`).
+    #[must_use]
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral_dir.is_some()
+    }
+
+    fn disk_dir(&self) -> &Path {
+        self.ephemeral_dir.as_deref().unwrap_or(&self.work_dir)
+    }
+
+    /// Resume every VM paused by [`ScenarioRunner::pause_vms`].
+    ///
+    /// # Errors
+    /// Returns `VmError` if any VM cannot be resumed.
+    pub async fn resume_vms(&self) -> Result<(), VmError> {
+        try_join_all(self.vms.values().map(QemuInstance::resume)).await?;
+        Ok(())
+    }
+
+    /// Pause every VM (e.g. before keeping a run on disk without burning host CPU).
+    ///
+    /// # Errors
+    /// Returns `VmError` if any VM cannot be paused.
+    pub async fn pause_vms(&self) -> Result<(), VmError> {
+        try_join_all(self.vms.values().map(QemuInstance::pause)).await?;
+        Ok(())
+    }
+
+    /// Let go of the VMs' processes and their QMP and action streams without stopping them, so
+    /// another process can drive them. Unlike [`ScenarioRunner::detach`], the shared LAN switch
+    /// and mock services keep running, and `stop` still shuts the VMs down.
+    pub fn release_vms(&mut self) {
+        for handle in self
+            .action_tasks
+            .drain(..)
+            .chain(self.qmp_event_tasks.drain(..))
+        {
+            handle.abort();
+        }
+        self.action_rx = None;
+        self.qmp_event_rx = None;
+        self.qmp_event_tx = None;
+
+        self.keep_vms();
+    }
+
+    /// Release the VMs without stopping them; the run directory is kept for `intar resume`.
+    ///
+    /// The shared LAN switch and mock services live in this process, so guests lose their
+    /// cluster network and mock services until the run is resumed.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the detach cannot be recorded in the audit log.
+    pub fn detach(&mut self) -> Result<(), VmError> {
+        self.release_vms();
+        self.vms.clear();
+        if let Some(mut switch) = self.lan_switch.take() {
+            switch.stop();
+        }
+        self.mock_services = None;
+
+        self.audit
+            .record(AuditOperation::RunDetached, self.scenario.name.clone())
+    }
+
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Override the run's seed (e.g. to reproduce a graded run). Must be called before VMs are
+    /// created.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Simulate the VMs with `backend` instead of running them under QEMU. Must be called
+    /// before VMs are created.
+    pub fn set_backend(&mut self, backend: Box<dyn VirtBackend>) {
+        self.backend = Some(backend);
+    }
+
+    /// Run the VMs as libvirt domains through the connection `uri` instead of spawning QEMU.
+    /// Must be called before VMs are created.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if libvirt cannot be reached at `uri`.
+    pub fn set_libvirt(&mut self, uri: String) -> Result<(), VmError> {
+        check_libvirt(&uri)?;
+        self.libvirt_uri = Some(uri);
+        Ok(())
+    }
+
+    /// Run the VMs as cloud instances provisioned per `settings` instead of under QEMU. The
+    /// instances get the same cloud-init user-data, minus the network setup, and are reached
+    /// through ssh tunnels. Must be called before VMs are created.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if `settings` cannot launch this scenario's VMs.
+    pub fn set_cloud(&mut self, settings: CloudSettings) -> Result<(), VmError> {
+        settings.check(&self.scenario)?;
+        self.cloud = Some(settings);
+        Ok(())
+    }
+
+    /// Keep the run to this host (`intar start --paranoid`): SSH forwards listen on loopback
+    /// only, the run directory holding the VMs' sockets becomes private, QEMU is refused any
+    /// listener beyond that, and in the guests only [`ACTIONS_GROUP`](crate::ACTIONS_GROUP) may send recorded
+    /// sessions. Must be called before VMs are created.
+    ///
+    /// # Errors
+    /// Returns `VmError::Io` if the run directory cannot be made private.
+    pub fn set_paranoid(&mut self) -> Result<(), VmError> {
+        make_private_dir(&self.work_dir)?;
+        self.paranoid = true;
+        Ok(())
+    }
+
+    /// Whether the run was started with `intar start --paranoid`.
+    #[must_use]
+    pub fn is_paranoid(&self) -> bool {
+        self.paranoid
+    }
+
+    /// Let the scenario's `on_complete` host commands and webhooks and its `action "external"`
+    /// providers run on this host (`intar start --allow-host-hooks`, or
+    /// `host_execution.allow` in config.yaml).
+    pub fn set_allow_host_hooks(&mut self) {
+        self.host_hooks = true;
+    }
+
+    /// Allow the `action "external"` providers named in `host_execution.providers` of
+    /// config.yaml beside [`BUILTIN_PROVIDERS`].
+    pub fn set_allowed_providers(&mut self, providers: Vec<String>) {
+        self.providers = providers;
+    }
+
+    /// Fail if the scenario runs code on the host that the run may not: any under
+    /// `--paranoid`, any without [`ScenarioRunner::set_allow_host_hooks`], and providers
+    /// nobody allowed.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` naming the first such hook or provider.
+    pub fn check_host_execution(&self) -> Result<(), VmError> {
+        let hook = self
+            .scenario
+            .on_complete
+            .iter()
+            .find(|hook| !matches!(hook, CompletionAction::Vm { .. }))
+            .map(|hook| format!("the on_complete {}", describe_hook(hook)))
+            .or_else(|| {
+                self.scenario
+                    .external_actions
+                    .first()
+                    .map(|action| format!("external action provider '{}'", action.provider))
+            });
+        let Some(hook) = hook else {
+            return Ok(());
+        };
+        if self.paranoid {
+            return Err(VmError::NotPermitted(format!(
+                "scenario '{}' runs {hook} on the host, which --paranoid refuses",
+                self.scenario.name
+            )));
+        }
+        if !self.host_hooks {
+            return Err(VmError::NotPermitted(format!(
+                "scenario '{}' runs {hook} on the host; rerun with --allow-host-hooks or set \
+                 host_execution.allow in config.yaml",
+                self.scenario.name
+            )));
+        }
+        if let Some(action) = self.scenario.external_actions.iter().find(|action| {
+            !BUILTIN_PROVIDERS.contains(&action.provider.as_str())
+                && !self.providers.contains(&action.provider)
+        }) {
+            return Err(VmError::NotPermitted(format!(
+                "scenario '{}' uses external action provider '{}'; add it to \
+                 host_execution.providers in config.yaml to allow it",
+                self.scenario.name, action.provider
+            )));
+        }
+        Ok(())
+    }
+
+    /// Trace every probe evaluation, asking the agents one probe at a time so each is timed
+    /// with its own reply.
+    pub fn set_author_mode(&mut self, enabled: bool) {
+        self.probe_trace = enabled.then(ProbeTrace::default);
+    }
+
+    /// Every recent probe evaluation, under author mode.
+    #[must_use]
+    pub fn probe_trace(&self) -> Option<&ProbeTrace> {
+        self.probe_trace.as_ref()
+    }
+
+    /// Whether the VMs are simulated rather than run under QEMU.
+    #[must_use]
+    pub fn is_simulated(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// Tear the run down `ttl` from now. The deadline is saved with the run state, so it also
+    /// holds after `intar attach`.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        let ttl_ms = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        self.expires_at_ms = Some(unix_ms().saturating_add(ttl_ms));
+    }
+
+    /// Time until the run's TTL runs out; zero once it has, `None` without a TTL.
+    #[must_use]
+    pub fn time_left(&self) -> Option<Duration> {
+        self.expires_at_ms
+            .map(|expires| Duration::from_millis(expires.saturating_sub(unix_ms())))
+    }
+
+    /// Record a host-initiated mutation in the run's audit log.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the entry cannot be appended.
+    pub fn record_audit(
+        &mut self,
+        operation: AuditOperation,
+        detail: impl Into<String>,
+    ) -> Result<(), VmError> {
+        self.audit.record(operation, detail)
+    }
+
+    /// Summarize the audit log for the completion report.
+    #[must_use]
+    pub fn audit_summary(&self) -> AuditSummary {
+        AuditSummary::load(self.audit.path(), Some(&self.audit.head()))
+    }
+
+    /// Whether probe names, descriptions, and results may be shown to the learner.
+    #[must_use]
+    pub fn results_visible(&self) -> bool {
+        !self.scenario.is_exam() || self.results_unlocked
+    }
+
+    /// Reveal exam results if `code` hashes to the scenario's `unlock_sha256`.
+    pub fn unlock_results(&mut self, code: &str) -> bool {
+        if self.scenario.unlock_sha256.as_deref() == Some(flag_hash(code).as_str()) {
+            self.results_unlocked = true;
+        }
+        self.results_visible()
+    }
+
+    /// Check a submitted flag against the scenario's `flag` probes.
+    ///
+    /// Returns `true` if it matches at least one; matching probes pass on the next check.
+    pub fn submit_flag(&mut self, flag: &str) -> bool {
+        let hash = flag_hash(flag);
+        let matched = self.scenario.probes.values().any(|def| {
+            def.probe_type == "flag"
+                && def
+                    .config
+                    .get("sha256")
+                    .and_then(serde_json::Value::as_str)
+                    .is_some_and(|expected| expected.eq_ignore_ascii_case(&hash))
+        });
+        if matched {
+            self.accepted_flags.insert(hash);
+        }
+        if let Err(e) = self.audit.record(
+            AuditOperation::FlagSubmitted,
+            if matched { "accepted" } else { "rejected" },
+        ) {
+            warn!("Failed to record flag submission: {}", e);
+        }
+        matched
+    }
+
+    /// The next unanswered question whose `after` probes all pass.
+    #[must_use]
+    pub fn pending_question(&self) -> Option<&Question> {
+        self.scenario.questions.iter().find(|q| {
+            !self.answered_questions.contains(&q.name)
+                && q.after.iter().all(|probe| self.probe_passing(probe))
+        })
+    }
+
+    #[must_use]
+    pub fn all_questions_answered(&self) -> bool {
+        self.scenario
+            .questions
+            .iter()
+            .all(|q| self.answered_questions.contains(&q.name))
+    }
+
+    /// Grade and record an answer. Incorrect answers leave the question open.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the question is unknown or the answer cannot be recorded.
+    pub fn answer_question(&mut self, name: &str, answer: &str) -> Result<AnswerOutcome, VmError> {
+        let question = self
+            .scenario
+            .questions
+            .iter()
+            .find(|q| q.name == name)
+            .ok_or_else(|| VmError::NotPermitted(format!("unknown question '{name}'")))?;
+
+        let (outcome, correct) = grade_answer(question, answer, self.scenario.is_exam());
+        self.answers.record(name, answer, correct)?;
+        self.audit
+            .record(AuditOperation::QuestionAnswered, name.to_string())?;
+        if outcome.closes_question() {
+            self.answered_questions.insert(name.to_string());
+        }
+        Ok(outcome)
+    }
+
+    /// Whether probe `name` passes on every VM that lists it.
+    fn probe_passing(&self, name: &str) -> bool {
+        self.scenario
+            .vms
+            .iter()
+            .filter(|vm| vm.probes.iter().any(|p| p == name))
+            .all(|vm| {
+                self.probe_results
+                    .get(&vm.name)
+                    .and_then(|results| results.get(name))
+                    .is_some_and(|r| r.passed)
+            })
+    }
+
     /// Start streaming SSH action events from all VMs.
     ///
     /// # Errors
@@ -207,6 +1337,8 @@ impl ScenarioRunner {
                 vm.actions_socket.clone(),
                 vm.logs_dir.clone(),
                 tx.clone(),
+                self.boot_stage_tx.clone(),
+                self.session_mirror.clone(),
             );
             self.action_tasks.push(handle);
         }
@@ -214,17 +1346,271 @@ impl ScenarioRunner {
         Ok(())
     }
 
+    /// Forward boot milestones reported by cloud-init to `tx`. Call before
+    /// [`ScenarioRunner::start_action_recording`].
+    pub fn report_boot_stages(&mut self, tx: mpsc::Sender<BootStageEvent>) {
+        self.boot_stage_tx = Some(tx);
+    }
+
+    /// Send each VM's name to `tx` as it starts, so queued VMs can be told from booting ones.
+    /// Call before [`ScenarioRunner::start_vms`].
+    pub fn report_vm_starts(&mut self, tx: mpsc::Sender<String>) {
+        self.vm_start_tx = Some(tx);
+    }
+
+    fn note_vm_start(&self, name: &str) {
+        if let Some(tx) = &self.vm_start_tx {
+            let _ = tx.try_send(name.to_string());
+        }
+    }
+
+    /// Live screens of the VMs' SSH sessions, filled once action recording has started.
+    #[must_use]
+    pub fn session_mirror(&self) -> SessionMirror {
+        self.session_mirror.clone()
+    }
+
     #[must_use]
     pub fn drain_action_lines(&mut self) -> Vec<ActionLineEvent> {
-        let Some(rx) = self.action_rx.as_mut() else {
+        let mut out = self
+            .backend
+            .as_mut()
+            .map(|backend| backend.take_action_lines())
+            .unwrap_or_default();
+        if let Some(rx) = self.action_rx.as_mut() {
+            while let Ok(ev) = rx.try_recv() {
+                if let Some(auto) = self.auto_balloon.as_mut() {
+                    auto.note_activity(&ev.vm, ev.received_at);
+                }
+                out.push(ev);
+            }
+        }
+        if !out.is_empty() {
+            self.note_activity();
+            if let Err(e) = self.action_log.append(&out) {
+                warn!("Failed to write the action log: {}", e);
+            }
+        }
+        out
+    }
+
+    /// Every action line of the run so far, including those already drained.
+    #[must_use]
+    pub fn action_log(&self) -> &ActionLog {
+        &self.action_log
+    }
+
+    /// Follow the QMP events of all VMs so [`ScenarioRunner::apply_qmp_events`] can track what
+    /// QEMU reports: pauses, shutdowns, resets, disk I/O errors, and guest panics. VMs whose
+    /// control session cannot be opened keep their runner-inferred state.
+    pub async fn start_event_tracking(&mut self) {
+        // Cloud instances have no QMP.
+        if self.qmp_event_rx.is_some() || self.cloud.is_some() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel::<QmpEvent>(256);
+        self.qmp_event_rx = Some(rx);
+
+        // VMs still waiting are followed once they start.
+        for vm in self.vms.values().filter(|vm| vm.state != VmState::Waiting) {
+            if let Some(task) = follow_qmp_events(vm, tx.clone()).await {
+                self.qmp_event_tasks.push(task);
+            }
+        }
+        self.qmp_event_tx = Some(tx);
+    }
+
+    /// Update VM states from the QMP events received since the last call and return them.
+    #[must_use]
+    pub fn apply_qmp_events(&mut self) -> Vec<QmpEvent> {
+        let Some(rx) = self.qmp_event_rx.as_mut() else {
             return Vec::new();
         };
 
-        let mut out = Vec::new();
-        while let Ok(ev) = rx.try_recv() {
-            out.push(ev);
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
         }
-        out
+
+        for event in &events {
+            let Some(vm) = self.vms.get_mut(&event.vm) else {
+                continue;
+            };
+            let resumed = self
+                .resume_states
+                .get(&event.vm)
+                .copied()
+                .unwrap_or(VmState::Ready);
+            let Some(state) = vm.state.after_qmp_event(&event.name, resumed) else {
+                continue;
+            };
+
+            match state {
+                VmState::Paused => {
+                    self.resume_states.insert(event.vm.clone(), vm.state);
+                }
+                VmState::Panicked => error!(
+                    "Guest of VM {} panicked; see {}",
+                    event.vm,
+                    vm.console_log_path().display()
+                ),
+                VmState::Error if event.name == QEMU_EXITED_EVENT => {
+                    let reason = vm.exit_status().map_or_else(
+                        || "QEMU exited".to_string(),
+                        |status| format!("QEMU exited ({status})"),
+                    );
+                    error!("VM {} crashed: {}", event.vm, reason);
+                    self.crashes.insert(event.vm.clone(), reason);
+                }
+                VmState::Error => error!("VM {} hit a disk I/O error: {}", event.vm, event.data),
+                VmState::Stopped => warn!("VM {} was shut down: {}", event.vm, event.data),
+                _ => {}
+            }
+            info!("VM {} is now {}", event.vm, state.label());
+            vm.state = state;
+        }
+        events
+    }
+
+    /// Why the QEMU process of `vm` exited, if it crashed and was not restarted yet.
+    #[must_use]
+    pub fn crash(&self, vm: &str) -> Option<&str> {
+        self.crashes.get(vm).map(String::as_str)
+    }
+
+    /// Restart `name` after its QEMU process died. The overlay disk is kept and the latest
+    /// checkpoint loaded; without one the guest boots again from the overlay.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the VM is unknown, QEMU fails to start, or the checkpoint cannot be
+    /// loaded, and `VmError::NotPermitted` for a remote run.
+    pub async fn restart_vm(&mut self, name: &str) -> Result<(), VmError> {
+        if let Some(remote) = &self.remote {
+            return Err(VmError::NotPermitted(format!(
+                "VM {name} runs on {} and cannot be restarted from this host",
+                remote.host
+            )));
+        }
+        if self.cloud.is_some() {
+            return Err(VmError::NotPermitted(format!(
+                "VM {name} is a cloud instance and cannot be restarted"
+            )));
+        }
+        let vm = self
+            .vms
+            .get_mut(name)
+            .ok_or_else(|| VmError::VmNotFound(name.to_string()))?;
+
+        info!("Restarting VM: {}", name);
+        // Reaps the dead process and removes its stale sockets and PID file.
+        vm.stop().await?;
+        vm.start(&detect_arch())?;
+        if let Some(checkpoint) = &self.latest_checkpoint {
+            info!("Loading checkpoint '{}' for VM: {}", checkpoint, name);
+            vm.pause().await?;
+            vm.load_checkpoint(checkpoint).await?;
+            vm.resume().await?;
+            vm.state = VmState::Ready;
+        }
+
+        self.crashes.remove(name);
+        self.resume_states.remove(name);
+        self.audit
+            .record(AuditOperation::VmRestarted, name.to_string())
+    }
+
+    /// Counters of the shared LAN switch, if the run has one.
+    #[must_use]
+    pub fn lan_stats(&self) -> Option<LanStats> {
+        self.lan_switch.as_ref().map(LanSwitch::stats)
+    }
+
+    /// File the shared LAN is being captured to, if a capture is running.
+    #[must_use]
+    pub fn lan_capture(&self) -> Option<PathBuf> {
+        self.lan_stats().and_then(|stats| stats.capture)
+    }
+
+    /// Capture the shared LAN to pcap files; without `config`, to [`DEFAULT_CAPTURE_FILE`] in
+    /// the run directory with the default size limits.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if the run has no shared LAN, or another `VmError` if the
+    /// capture file cannot be created.
+    pub fn start_lan_capture(&mut self, config: Option<CaptureConfig>) -> Result<(), VmError> {
+        let switch = self.lan_switch.as_ref().ok_or_else(|| {
+            VmError::NotPermitted(if self.lan_backend.uses_switch() {
+                "the run has no shared LAN to capture".into()
+            } else {
+                "the shared LAN bypasses intar's switch (network.lan_backend), so it cannot \
+                     be captured"
+                    .into()
+            })
+        })?;
+        let config =
+            config.unwrap_or_else(|| CaptureConfig::new(self.work_dir.join(DEFAULT_CAPTURE_FILE)));
+        switch.start_capture(config)
+    }
+
+    /// Stop capturing the shared LAN. Does nothing without a capture running.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the switch has stopped.
+    pub fn stop_lan_capture(&mut self) -> Result<(), VmError> {
+        match self.lan_switch.as_ref() {
+            Some(switch) if self.lan_capture().is_some() => switch.stop_capture(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Start or stop captures requested via `intar net capture`.
+    fn apply_capture_requests(&mut self) {
+        for request in take_run_requests(&self.work_dir, CAPTURES_DIR) {
+            let result = if request == "stop" {
+                self.stop_lan_capture()
+            } else {
+                serde_json::from_str(&request)
+                    .map_err(VmError::from)
+                    .and_then(|config| self.start_lan_capture(Some(config)))
+            };
+            if let Err(e) = result {
+                warn!("Failed to handle LAN capture request: {}", e);
+            }
+        }
+    }
+
+    /// Reconnect to the VMs after the host slept, since virtio-serial and QMP connections often
+    /// do not survive it: action streams and QMP sessions are reopened, and probes connect
+    /// afresh on their next check. Coming back counts as activity.
+    pub async fn reconnect(&mut self) {
+        if self.action_rx.is_some() {
+            for handle in self.action_tasks.drain(..) {
+                handle.abort();
+            }
+            self.action_rx = None;
+            if let Err(e) = self.start_action_recording() {
+                warn!("Failed to restart action recording: {}", e);
+            }
+        }
+
+        for (name, vm) in &self.vms {
+            if let Err(e) = vm.reconnect_qmp().await {
+                warn!("Failed to reconnect QMP of VM {}: {}", name, e);
+            }
+        }
+        self.note_activity();
+    }
+
+    /// Count the run as in use from now on, e.g. when it is resumed after an idle suspend.
+    pub fn note_activity(&mut self) {
+        self.last_activity = std::time::Instant::now();
+    }
+
+    /// Time since the last SSH action line, probe status change, or [`Self::note_activity`].
+    #[must_use]
+    pub fn idle_for(&self) -> std::time::Duration {
+        self.last_activity.elapsed()
     }
 
     /// Prepare a VM: pick ports, create overlay disk, and generate cloud-init assets.
@@ -237,15 +1623,31 @@ impl ScenarioRunner {
         image_cache: &ImageCache,
         arch: &str,
     ) -> Result<(), VmError> {
+        if self.backend.is_some() {
+            self.probe_results
+                .insert(vm_def.name.clone(), HashMap::new());
+            self.vm_order.push(vm_def.name.clone());
+            return Ok(());
+        }
+
         let ssh_port = self.next_port()?;
-        let shared_ep = if let Some(hub_port) = self.shared_lan_hub_port {
-            let local_port = find_free_udp_port()?;
-            Some(SharedNetworkEndpoint::Dgram {
-                hub_port,
-                local_port,
-            })
-        } else {
-            None
+        let api_port = self.next_port()?;
+        // Cloud instances share the provider's network instead.
+        let in_cloud = self.cloud.is_some();
+        let arch = self
+            .cloud
+            .as_ref()
+            .map_or_else(|| arch.to_string(), |cloud| cloud.arch.clone());
+        let shared_ep = match self.shared_lan_hub_port.filter(|_| !in_cloud) {
+            Some(hub_port) => {
+                let switch_port = if self.lan_backend.uses_switch() {
+                    Some(find_free_udp_port()?)
+                } else {
+                    None
+                };
+                self.lan_backend.endpoint(hub_port, switch_port)
+            }
+            None => None,
         };
         let has_shared_lan = shared_ep.is_some();
 
@@ -253,7 +1655,7 @@ impl ScenarioRunner {
         let (primary_mac, lan_mac) = Self::generate_macs(vm_index)?;
         let primary_mac_for_cfg = primary_mac.clone();
         let lan_mac_for_cfg = lan_mac.clone();
-        let mgmt_ip = Self::mgmt_ip(vm_index)?;
+        let mgmt_ip = Self::mgmt_ip(self.scenario.network.management(), vm_index)?;
         #[cfg(unix)]
         let qmp_socket = self.host_socket_for_vm(&vm_def.name, "qmp");
         #[cfg(windows)]
@@ -269,10 +1671,27 @@ impl ScenarioRunner {
         #[cfg(windows)]
         let actions_socket = self.host_socket_for_vm(&vm_def.name, "actions")?;
 
+        #[cfg(unix)]
+        let control_socket = self.host_socket_for_vm(&vm_def.name, "control");
+        #[cfg(windows)]
+        let control_socket = self.host_socket_for_vm(&vm_def.name, "control")?;
+
+        // Cloud instances log their serial console with the provider.
+        #[cfg(unix)]
+        let console_socket = (!in_cloud).then(|| self.host_socket_for_vm(&vm_def.name, "console"));
+        #[cfg(windows)]
+        let console_socket = if in_cloud {
+            None
+        } else {
+            Some(self.host_socket_for_vm(&vm_def.name, "console")?)
+        };
+
         let mut vm = QemuInstance::new(
             QemuInstanceConfig {
                 definition: vm_def.clone(),
                 ssh_port,
+                api_port: Some(api_port),
+                mgmt_subnet: self.scenario.network.management(),
                 mgmt_ip: mgmt_ip.clone(),
                 shared_lan: shared_ep,
                 primary_mac: Some(primary_mac),
@@ -281,24 +1700,60 @@ impl ScenarioRunner {
                     qmp: qmp_socket,
                     serial: serial_socket,
                     actions: actions_socket,
+                    control: Some(control_socket),
+                    console: console_socket,
                 },
+                priority: self.priority.clone(),
+                disk_dir: self.disk_dir().to_path_buf(),
+                libvirt: self.libvirt_domain(&vm_def.name),
+                cloud: self.cloud_vm(vm_def)?,
+                paranoid: self.paranoid,
             },
             &self.work_dir,
         );
-        let base_image = self.base_image_for_vm(vm_def, image_cache, arch)?;
-        vm.create_overlay_disk(&base_image)?;
-        let agent_binary = self.agent_binary_for_arch(arch)?;
-        let cloud_init_gen =
-            CloudInitGenerator::new(self.ssh_public_key.clone(), agent_binary.clone());
+        if !in_cloud {
+            let base_image = self.base_image_for_vm(vm_def, image_cache, &arch)?;
+            vm.create_overlay_disk(&base_image)
+                .map_err(|e| self.check_base_image(vm_def, &base_image, e))?;
+        }
+        let steps = self.resolve_vm_steps(vm_def);
+        self.register_triggered_steps(vm_def, &mgmt_ip, &steps)?;
+        let agent_binary = self.agent_binary_for_arch(&arch)?;
+        let mut cloud_init_gen =
+            CloudInitGenerator::new(self.ssh_public_key.clone(), agent_binary.clone())
+                .with_users(vm_def.users.clone());
+        if vm_def.ssh_password_auth {
+            cloud_init_gen = cloud_init_gen.with_ssh_password_auth();
+        }
+        if let Some(user) = &vm_def.console_autologin {
+            cloud_init_gen = cloud_init_gen.with_console_autologin(user);
+        }
+        if in_cloud {
+            cloud_init_gen = cloud_init_gen.with_agent_over_ssh();
+        }
+        if self.paranoid {
+            cloud_init_gen = cloud_init_gen.with_actions_group();
+        }
+        // Cloud images may replace the host keys, so cloud instances keep their own.
+        if !in_cloud {
+            let host_key = SshHostKey::generate()?;
+            record_host_key(&self.work_dir, &vm_def.name, &host_key.public_key)?;
+            vm.host_key_fingerprint = Some(host_key.fingerprint()?);
+            cloud_init_gen = cloud_init_gen.with_host_key(host_key);
+        }
         let cloud_init_config = self.build_cloud_init_config(
             vm_def,
+            &steps,
             &primary_mac_for_cfg,
             &mgmt_ip,
             lan_mac_for_cfg.as_deref(),
             has_shared_lan,
         )?;
+        // A cloud instance boots the user-data saved to the logs.
         cloud_init_gen.save_to_logs(&cloud_init_config, &vm_def.name, &vm.logs_dir)?;
-        cloud_init_gen.create_iso(&cloud_init_config, &vm_def.name, &vm.cloud_init_iso)?;
+        if !in_cloud {
+            cloud_init_gen.create_iso(&cloud_init_config, &vm_def.name, &vm.cloud_init_iso)?;
+        }
 
         self.vms.insert(vm_def.name.clone(), vm);
         self.probe_results
@@ -307,22 +1762,66 @@ impl ScenarioRunner {
         Ok(())
     }
 
+    /// Remember the post-boot steps of a VM, with templates expanded, for triggering later.
+    fn register_triggered_steps(
+        &mut self,
+        vm_def: &VmDefinition,
+        mgmt_ip: &str,
+        steps: &[VmStep],
+    ) -> Result<(), VmError> {
+        let triggered = self
+            .template_vars(vm_def, mgmt_ip)
+            .expand_steps(steps)?
+            .into_iter()
+            .filter(|step| step.trigger != StepTrigger::Boot)
+            .map(|step| TriggeredStep {
+                vm: vm_def.name.clone(),
+                step,
+                fired: false,
+                running: None,
+            });
+        self.triggered_steps.extend(triggered);
+        Ok(())
+    }
+
+    /// Fixed steps followed by the seeded pick from each `random_one_of` group.
+    fn resolve_vm_steps(&mut self, vm_def: &VmDefinition) -> Vec<VmStep> {
+        let mut steps = vm_def.steps.clone();
+        for group in &vm_def.random_steps {
+            if let Some(step) = select_random_step(self.seed, &vm_def.name, group) {
+                info!(
+                    "Random fault '{}' on VM {} resolved to step '{}'",
+                    group.name, vm_def.name, step.name
+                );
+                self.fault_selections
+                    .insert(format!("{}/{}", vm_def.name, group.name), step.name.clone());
+                steps.push(step.clone());
+            }
+        }
+        steps
+    }
+
     fn build_cloud_init_config(
         &self,
         vm_def: &VmDefinition,
+        steps: &[VmStep],
         primary_mac: &str,
         mgmt_ip: &str,
         lan_mac: Option<&str>,
         has_shared_lan: bool,
     ) -> Result<CloudInitConfig, VmError> {
+        let vars = self.template_vars(vm_def, mgmt_ip);
         let mut cloud_init_config = vm_def.cloud_init.clone().unwrap_or_default();
-        apply_vm_steps_to_cloud_init(&vm_def.name, &vm_def.steps, &mut cloud_init_config)?;
+        vars.expand_cloud_init(&mut cloud_init_config)?;
+        let steps = vars.expand_steps(steps)?;
+        apply_vm_steps_to_cloud_init(&vm_def.name, &steps, &mut cloud_init_config)?;
 
         let hosts_content = self.render_hosts_file()?;
         cloud_init_config.write_files.push(WriteFile {
             path: "/etc/hosts.intar".into(),
             content: hosts_content,
             permissions: Some("0644".into()),
+            binary: false,
         });
 
         let cluster_ip = if has_shared_lan {
@@ -336,31 +1835,66 @@ impl ScenarioRunner {
             None
         };
 
-        cloud_init_config.network_config = Some(Self::netplan_config(
-            primary_mac,
-            mgmt_ip,
-            cluster_ip.as_deref().zip(lan_mac),
-        )?);
-
-        // Disable IPv6 system-wide.
-        cloud_init_config.write_files.push(WriteFile {
-            path: "/etc/sysctl.d/99-intar-no-ipv6.conf".into(),
-            content:
-                "net.ipv6.conf.all.disable_ipv6 = 1\nnet.ipv6.conf.default.disable_ipv6 = 1\nnet.ipv6.conf.lo.disable_ipv6 = 1\n".into(),
-            permissions: Some("0644".into()),
+        let ipv6 = self.scenario.ipv6_enabled(vm_def);
+        let lan = cluster_ip.as_deref().zip(lan_mac).map(|(ip, mac)| LanNic {
+            mac,
+            ip,
+            ipv6: self.vm_ipv6_addresses.get(&vm_def.name).map(String::as_str),
         });
+        // Cloud instances keep the network their provider set up.
+        let local_network = self.cloud.is_none();
+        if local_network {
+            cloud_init_config.network_config = Some(Self::netplan_config(
+                primary_mac,
+                mgmt_ip,
+                lan,
+                &self.scenario.network,
+            )?);
+        }
+
+        // Disable IPv6 system-wide unless the scenario asks for it.
+        if !ipv6 {
+            cloud_init_config.write_files.push(WriteFile {
+                path: "/etc/sysctl.d/99-intar-no-ipv6.conf".into(),
+                content:
+                    "net.ipv6.conf.all.disable_ipv6 = 1\nnet.ipv6.conf.default.disable_ipv6 = 1\nnet.ipv6.conf.lo.disable_ipv6 = 1\n".into(),
+                permissions: Some("0644".into()),
+                binary: false,
+            });
+        }
+
+        for plugin in &self.scenario.probe_plugins {
+            cloud_init_config.write_files.push(WriteFile {
+                path: format!("{PLUGIN_DIR}/{}", plugin.name),
+                content: plugin.content.clone(),
+                permissions: Some("0755".into()),
+                binary: true,
+            });
+        }
 
         let mut runcmd = String::new();
-        // Interface naming is handled by netplan `match` + `set-name` above.
-        // This script just applies addresses immediately for the first boot.
-        let net_setup =
-            Self::net_setup_script(primary_mac, mgmt_ip, cluster_ip.as_deref().zip(lan_mac))?;
-        cloud_init_config.write_files.push(WriteFile {
-            path: "/usr/local/bin/intar-net-setup.sh".into(),
-            content: net_setup,
-            permissions: Some("0755".into()),
-        });
-        runcmd.push_str("/usr/local/bin/intar-net-setup.sh\n");
+        if local_network {
+            // Interface naming is handled by netplan `match` + `set-name` above.
+            // This script just applies addresses immediately for the first boot.
+            let net_setup =
+                Self::net_setup_script(primary_mac, mgmt_ip, lan, &self.scenario.network, ipv6)?;
+            cloud_init_config.write_files.push(WriteFile {
+                path: "/usr/local/bin/intar-net-setup.sh".into(),
+                content: net_setup,
+                permissions: Some("0755".into()),
+                binary: false,
+            });
+            runcmd.push_str("/usr/local/bin/intar-net-setup.sh\n");
+        }
+        if let Some(script) = self.mock_services_script()?.filter(|_| local_network) {
+            cloud_init_config.write_files.push(WriteFile {
+                path: "/usr/local/bin/intar-mock-services.sh".into(),
+                content: script,
+                permissions: Some("0755".into()),
+                binary: false,
+            });
+            runcmd.push_str("/usr/local/bin/intar-mock-services.sh\n");
+        }
         runcmd.push_str("cat /etc/hosts.intar >> /etc/hosts\n");
         if let Some(existing) = &cloud_init_config.runcmd {
             runcmd.push_str(existing);
@@ -370,6 +1904,54 @@ impl ScenarioRunner {
         Ok(cloud_init_config)
     }
 
+    fn template_vars(&self, vm_def: &VmDefinition, mgmt_ip: &str) -> TemplateVars {
+        let mut vars = TemplateVars::default();
+        vars.insert("vm.name", vm_def.name.clone());
+        vars.insert("vm.mgmt_ip", mgmt_ip);
+        if let Some(ip) = self.vm_addresses.get(&vm_def.name) {
+            vars.insert("vm.ip", ip.clone());
+        }
+        for (name, ip) in &self.vm_addresses {
+            vars.insert(format!("vms.{name}.ip"), ip.clone());
+        }
+        if let Some(ipv6) = self.vm_ipv6_addresses.get(&vm_def.name) {
+            vars.insert("vm.ipv6", ipv6.clone());
+        }
+        for (name, ipv6) in &self.vm_ipv6_addresses {
+            vars.insert(format!("vms.{name}.ipv6"), ipv6.clone());
+        }
+        for (index, service) in self.scenario.mock_services.iter().enumerate() {
+            if let Ok(ip) = Self::mock_service_ip(self.scenario.network.management(), index) {
+                vars.insert(format!("services.{}.ip", service.name), ip);
+            }
+            vars.insert(
+                format!("services.{}.host", service.name),
+                service.name.clone(),
+            );
+            vars.insert(
+                format!("services.{}.port", service.name),
+                service.port.to_string(),
+            );
+        }
+        vars.insert("scenario.name", self.scenario.name.clone());
+        vars.insert("scenario.run_id", self.run_id());
+        for cluster in &self.scenario.clusters {
+            vars.insert(cluster.token_var(), self.cluster_token(cluster));
+        }
+        vars
+    }
+
+    /// Join token of a `k8s_cluster`, derived from the run's private key so it is secret, differs
+    /// per run, and is the same again when the run is resumed.
+    fn cluster_token(&self, cluster: &K8sCluster) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"intar-k3s-token\0");
+        hasher.update(cluster.name.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(self.ssh_private_key.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
     fn next_port(&mut self) -> Result<u16, VmError> {
         let port = self
             .ports
@@ -419,6 +2001,47 @@ impl ScenarioRunner {
         })
     }
 
+    /// When `error` reads like a damaged qcow2, hash-check the VM's base image against the
+    /// scenario checksum and report `VmError::CorruptImage` if it no longer matches, so the
+    /// user can be offered a fresh download instead of an opaque `qemu-img` error.
+    fn check_base_image(
+        &self,
+        vm_def: &VmDefinition,
+        base_image: &Path,
+        error: VmError,
+    ) -> VmError {
+        let VmError::Qemu(message) = &error else {
+            return error;
+        };
+        if !indicates_image_corruption(message) {
+            return error;
+        }
+        let Some(source) = self
+            .scenario
+            .images
+            .get(&vm_def.image)
+            .and_then(|image| image.source_for_arch(&detect_arch()))
+        else {
+            return error;
+        };
+        warn!(
+            "VM {} failed with signs of a corrupt base image; checking {}",
+            vm_def.name,
+            base_image.display()
+        );
+        match ImageCache::file_matches_checksum(base_image, &source.checksum) {
+            Ok(false) => VmError::CorruptImage(base_image.to_path_buf()),
+            Ok(true) => {
+                info!("Base image {} matches its checksum", base_image.display());
+                error
+            }
+            Err(e) => {
+                warn!("Could not check base image {}: {}", base_image.display(), e);
+                error
+            }
+        }
+    }
+
     fn agent_binary_for_arch(&self, arch: &str) -> Result<&Vec<u8>, VmError> {
         match arch {
             "x86_64" | "amd64" => Ok(&self.agent_binary_x86_64),
@@ -427,21 +2050,60 @@ impl ScenarioRunner {
         }
     }
 
-    fn mgmt_ip(vm_index: usize) -> Result<String, VmError> {
-        let idx = u32::try_from(vm_index)
-            .map_err(|_| VmError::Qemu("Too many VMs for management IP addressing".into()))?;
-        let last = 100u32
-            .checked_add(idx)
-            .filter(|octet| *octet <= 254)
+    pub(crate) fn mgmt_ip(subnet: Ipv4Subnet, vm_index: usize) -> Result<String, VmError> {
+        let offset = u8::try_from(vm_index)
+            .ok()
+            .and_then(|idx| 100u8.checked_add(idx))
+            .filter(|offset| *offset <= 254)
             .ok_or_else(|| VmError::Qemu("Too many VMs for management IP addressing".into()))?;
-        Ok(format!("10.0.2.{last}"))
+        Ok(subnet.host(offset).to_string())
+    }
+
+    /// Address guests use for the `index`th mock service, on the management network below the
+    /// VMs' addresses.
+    pub(crate) fn mock_service_ip(subnet: Ipv4Subnet, index: usize) -> Result<String, VmError> {
+        u8::try_from(index)
+            .ok()
+            .and_then(|idx| 50u8.checked_add(idx))
+            .filter(|offset| *offset < 100)
+            .map(|offset| subnet.host(offset).to_string())
+            .ok_or_else(|| VmError::Qemu("Too many mock services for addressing".into()))
+    }
+
+    /// Boot script redirecting each mock service's address and port to its server on the host,
+    /// which guests reach through the user-mode network's host loopback address.
+    fn mock_services_script(&self) -> Result<Option<String>, VmError> {
+        if self.scenario.mock_services.is_empty() {
+            return Ok(None);
+        }
+        let management = self.scenario.network.management();
+        let mut rules = String::new();
+        for (index, service) in self.scenario.mock_services.iter().enumerate() {
+            let Some(host_port) = self.mock_ports.get(&service.name) else {
+                continue;
+            };
+            let protocol = if service.kind.is_udp() { "udp" } else { "tcp" };
+            writeln!(
+                rules,
+                "  iptables -t nat -A OUTPUT -d {} -p {protocol} --dport {} -j DNAT --to-destination {}:{host_port}",
+                Self::mock_service_ip(management, index)?,
+                service.port,
+                user_net_host_ip(management)
+            )
+            .map_err(|_| VmError::Qemu("Failed to format mock services script".into()))?;
+        }
+        Ok(Some(format!(
+            "#!/bin/sh\nif command -v iptables >/dev/null 2>&1; then\n{rules}else\n  echo \"iptables not found; mock services are unreachable\" >&2\nfi\n"
+        )))
     }
 
     fn netplan_config(
         primary_mac: &str,
         mgmt_ip: &str,
-        lan: Option<(&str, &str)>,
+        lan: Option<LanNic<'_>>,
+        network: &ScenarioNetwork,
     ) -> Result<String, VmError> {
+        let management = network.management();
         let mut netplan = format!(
             r#"network:
   version: 2
@@ -453,30 +2115,54 @@ impl ScenarioRunner {
       dhcp4: false
       dhcp6: false
       addresses:
-        - {mgmt_ip}/24
-      gateway4: 10.0.2.2
+        - {mgmt_ip}/{}
+      gateway4: {}
       nameservers:
         addresses:
-          - 10.0.2.3
+          - {}
       optional: true
-"#
+"#,
+            management.prefix(),
+            user_net_host_ip(management),
+            user_net_dns_ip(management)
         );
 
-        if let Some((cluster_ip, lan_mac)) = lan {
+        if let Some(lan) = lan {
             write!(
                 netplan,
                 r#"    lan0:
       match:
-        macaddress: "{lan_mac}"
+        macaddress: "{}"
       set-name: enp0s2
-      dhcp4: false
+      dhcp4: {}
       dhcp6: false
-      addresses:
-        - {cluster_ip}/24
-      optional: true
 "#,
+                lan.mac, network.dhcp
             )
             .map_err(|_| VmError::Qemu("Failed to format network config".into()))?;
+            // With DHCP, the lease carries the IPv4 address, DNS server, and search domain.
+            let addresses: Vec<String> = (!network.dhcp)
+                .then(|| format!("{}/{}", lan.ip, network.lan().prefix()))
+                .into_iter()
+                .chain(lan.ipv6.map(|ipv6| format!("{ipv6}/64")))
+                .collect();
+            if !addresses.is_empty() {
+                netplan.push_str("      addresses:\n");
+                for address in addresses {
+                    writeln!(netplan, "        - \"{address}\"")
+                        .map_err(|_| VmError::Qemu("Failed to format network config".into()))?;
+                }
+            }
+            if network.dns && !network.dhcp {
+                writeln!(
+                    netplan,
+                    "      nameservers:\n        addresses:\n          - {}\n        search:\n          - {}",
+                    lan_services_ip(network.lan()),
+                    network.domain
+                )
+                .map_err(|_| VmError::Qemu("Failed to format network config".into()))?;
+            }
+            netplan.push_str("      optional: true\n");
         }
 
         Ok(netplan)
@@ -485,8 +2171,11 @@ impl ScenarioRunner {
     fn net_setup_script(
         primary_mac: &str,
         mgmt_ip: &str,
-        lan: Option<(&str, &str)>,
+        lan: Option<LanNic<'_>>,
+        network: &ScenarioNetwork,
+        ipv6: bool,
     ) -> Result<String, VmError> {
+        let management = network.management();
         let mut script = String::new();
 
         script.push_str(NET_SETUP_SCRIPT_PREFIX);
@@ -496,10 +2185,11 @@ impl ScenarioRunner {
         )
         .map_err(|_| VmError::Qemu("Failed to format network setup script".into()))?;
 
-        if let Some((_cluster_ip, lan_mac)) = lan {
+        if let Some(lan) = lan {
             write!(
                 script,
-                "LAN_MAC=\"{lan_mac}\"\nLAN_IF=\"$(iface_for_mac \"$LAN_MAC\" || true)\"\n",
+                "LAN_MAC=\"{}\"\nLAN_IF=\"$(iface_for_mac \"$LAN_MAC\" || true)\"\n",
+                lan.mac
             )
             .map_err(|_| VmError::Qemu("Failed to format network setup script".into()))?;
         }
@@ -514,75 +2204,135 @@ ip addr flush dev "$MGMT_IF" 2>/dev/null || true
         );
         writeln!(
             script,
-            "ip addr add {mgmt_ip}/24 dev \"$MGMT_IF\" 2>/dev/null || true",
+            "ip addr add {mgmt_ip}/{} dev \"$MGMT_IF\" 2>/dev/null || true\nip link set \"$MGMT_IF\" up || true\nip route replace default via {} dev \"$MGMT_IF\" 2>/dev/null || true",
+            management.prefix(),
+            user_net_host_ip(management)
         )
         .map_err(|_| VmError::Qemu("Failed to format network setup script".into()))?;
-        script.push_str(
-            r#"ip link set "$MGMT_IF" up || true
-ip route replace default via 10.0.2.2 dev "$MGMT_IF" 2>/dev/null || true
-"#,
-        );
 
-        if let Some((cluster_ip, _lan_mac)) = lan {
-            script.push_str(
-                r#"
+        if let Some(lan) = lan {
+            if network.dhcp {
+                script.push_str(
+                    r#"
+# The shared LAN NIC gets its address over DHCP; ask again in case it was renamed.
+ip link set "$LAN_IF" up || true
+networkctl reconfigure "$LAN_IF" 2>/dev/null || true
+"#,
+                );
+            } else {
+                script.push_str(
+                    r#"
 # Configure shared LAN NIC immediately with static IPv4.
 ip addr flush dev "$LAN_IF" 2>/dev/null || true
 "#,
-            );
-            writeln!(
-                script,
-                "ip addr add {cluster_ip}/24 dev \"$LAN_IF\" 2>/dev/null || true",
-            )
-            .map_err(|_| VmError::Qemu("Failed to format network setup script".into()))?;
-            script.push_str(
-                r#"ip link set "$LAN_IF" up || true
+                );
+                writeln!(
+                    script,
+                    "ip addr add {}/{} dev \"$LAN_IF\" 2>/dev/null || true",
+                    lan.ip,
+                    network.lan().prefix()
+                )
+                .map_err(|_| VmError::Qemu("Failed to format network setup script".into()))?;
+                script.push_str(
+                    r#"ip link set "$LAN_IF" up || true
 "#,
-            );
+                );
+            }
+            if let Some(lan_ipv6) = lan.ipv6 {
+                writeln!(
+                    script,
+                    "ip -6 addr add {lan_ipv6}/64 dev \"$LAN_IF\" 2>/dev/null || true",
+                )
+                .map_err(|_| VmError::Qemu("Failed to format network setup script".into()))?;
+            }
         }
 
-        script.push_str(
-            r"
+        if !ipv6 {
+            script.push_str(
+                r"
 # Apply IPv6 disablement without blocking boot.
 sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
 ",
-        );
+            );
+        }
 
         Ok(script)
     }
 
-    fn assign_vm_addresses(scenario: &Scenario) -> Result<HashMap<String, String>, VmError> {
+    pub(crate) fn assign_vm_addresses(
+        scenario: &Scenario,
+    ) -> Result<HashMap<String, String>, VmError> {
+        let subnet = scenario.network.lan();
         let mut ips = HashMap::new();
         for (idx, vm) in scenario.vms.iter().enumerate() {
-            let idx = u32::try_from(idx)
-                .map_err(|_| VmError::Qemu("Too many VMs for shared LAN addressing".into()))?;
-            let last = 10u32
-                .checked_add(idx)
-                .filter(|octet| *octet <= 254)
+            let offset = u8::try_from(idx)
+                .ok()
+                .and_then(|idx| 10u8.checked_add(idx))
+                .filter(|offset| *offset <= 254)
                 .ok_or_else(|| VmError::Qemu("Too many VMs for shared LAN addressing".into()))?;
-            ips.insert(vm.name.clone(), format!("10.11.0.{last}"));
+            ips.insert(vm.name.clone(), subnet.host(offset).to_string());
         }
         Ok(ips)
     }
 
+    /// Shared LAN ULA addresses of the VMs that keep IPv6; none without a shared LAN.
+    pub(crate) fn assign_vm_ipv6_addresses(
+        scenario: &Scenario,
+    ) -> Result<HashMap<String, String>, VmError> {
+        let ipv4 = Self::assign_vm_addresses(scenario)?;
+        Ok(scenario
+            .vms
+            .iter()
+            .filter(|vm| scenario.vms.len() > 1 && scenario.ipv6_enabled(vm))
+            .filter_map(|vm| {
+                let last = ipv4.get(&vm.name)?.rsplit('.').next()?;
+                Some((vm.name.clone(), format!("{LAN_IPV6_PREFIX}{last}")))
+            })
+            .collect())
+    }
+
     fn render_hosts_file(&self) -> Result<String, VmError> {
         let mut content = String::from("127.0.0.1 localhost\n");
 
-        for vm in &self.scenario.vms {
+        // With LAN DNS, VM names are left for the guests to resolve there.
+        let vms = if self.scenario.network.dns {
+            &[][..]
+        } else {
+            &self.scenario.vms[..]
+        };
+        for vm in vms {
             if let Some(ip) = self.vm_addresses.get(&vm.name) {
                 let mut names = vec![format!("{}.intar", vm.name), vm.name.clone()];
-                if vm.name == "k3s-1" {
-                    names.push("k3s-server.intar".into());
-                    names.push("k3s-server".into());
+                for cluster in &self.scenario.clusters {
+                    if vm.name == cluster.server_name(1) {
+                        names.push(format!("{}.intar", cluster.api_host()));
+                        names.push(cluster.api_host());
+                    }
                 }
                 writeln!(content, "{ip} {}", names.join(" "))
                     .map_err(|_| VmError::Qemu("Failed to format hosts file".into()))?;
+                if let Some(ipv6) = self.vm_ipv6_addresses.get(&vm.name) {
+                    writeln!(content, "{ipv6} {}", names.join(" "))
+                        .map_err(|_| VmError::Qemu("Failed to format hosts file".into()))?;
+                }
             }
         }
+        for (index, service) in self.scenario.mock_services.iter().enumerate() {
+            writeln!(
+                content,
+                "{} {}.intar {}",
+                Self::mock_service_ip(self.scenario.network.management(), index)?,
+                service.name,
+                service.name
+            )
+            .map_err(|_| VmError::Qemu("Failed to format hosts file".into()))?;
+        }
 
         Ok(content)
     }
 
+    /// MAC addresses of the VM at `idx`. They only depend on the index, so they are the same
+    /// for every run and seed.
     fn generate_macs(idx: usize) -> Result<(String, Option<String>), VmError> {
         let idx = u8::try_from(idx)
             .map_err(|_| VmError::Qemu("Too many VMs to generate MAC addresses".into()))?;
@@ -601,7 +2351,12 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
         let Some(hub_port) = self.shared_lan_hub_port else {
             return Ok(());
         };
-        if self.lan_switch.is_some() {
+        // A remote run's switch runs on its host, and cloud instances have none.
+        if self.lan_switch.is_some()
+            || !self.lan_backend.uses_switch()
+            || self.remote.is_some()
+            || self.cloud.is_some()
+        {
             return Ok(());
         }
 
@@ -609,35 +2364,138 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
             .vms
             .values()
             .filter_map(|vm| {
-                vm.shared_lan
-                    .as_ref()
-                    .map(|SharedNetworkEndpoint::Dgram { local_port, .. }| {
-                        std::net::SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, *local_port))
-                    })
+                let local_port = vm.shared_lan.as_ref()?.switch_port()?;
+                let addr = (std::net::Ipv4Addr::LOCALHOST, local_port);
+                Some((vm.name.clone(), std::net::SocketAddr::from(addr)))
             })
             .collect();
 
-        self.lan_switch = Some(LanSwitch::spawn(hub_port, peers)?);
+        let services = if self.scenario.network.has_services() {
+            Some(self.lan_services_config()?)
+        } else {
+            None
+        };
+        self.lan_switch = Some(LanSwitch::spawn(hub_port, peers, services, &self.work_dir)?);
+        Ok(())
+    }
+
+    /// DHCP leases and DNS names of the VMs' shared LAN NICs, matching their generated MACs.
+    fn lan_services_config(&self) -> Result<LanServicesConfig, VmError> {
+        let network = &self.scenario.network;
+        let hosts = self
+            .vm_order
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let mac = Self::generate_macs(index)?
+                    .1
+                    .as_deref()
+                    .and_then(parse_mac)
+                    .ok_or_else(|| VmError::Qemu("Invalid shared LAN MAC address".into()))?;
+                let ip = self
+                    .vm_addresses
+                    .get(name)
+                    .and_then(|ip| ip.parse().ok())
+                    .ok_or_else(|| VmError::Qemu("Missing address for VM".into()))?;
+                let aliases = self
+                    .scenario
+                    .clusters
+                    .iter()
+                    .filter(|cluster| *name == cluster.server_name(1))
+                    .map(K8sCluster::api_host)
+                    .collect();
+                Ok(LanHost {
+                    name: name.clone(),
+                    mac,
+                    ip,
+                    ipv6: self
+                        .vm_ipv6_addresses
+                        .get(name)
+                        .and_then(|ipv6| ipv6.parse().ok()),
+                    aliases,
+                })
+            })
+            .collect::<Result<_, VmError>>()?;
+        Ok(LanServicesConfig {
+            subnet: network.lan(),
+            dhcp: network.dhcp,
+            dns: network.dns,
+            domain: network.domain.clone(),
+            hosts,
+        })
+    }
+
+    fn start_mock_services_if_needed(&mut self) -> Result<(), VmError> {
+        if self.mock_ports.is_empty()
+            || self.mock_services.is_some()
+            || self.remote.is_some()
+            || self.cloud.is_some()
+        {
+            return Ok(());
+        }
+        self.mock_services = Some(MockServices::spawn(
+            &self.scenario.mock_services,
+            &self.mock_ports,
+            &self.work_dir,
+        )?);
         Ok(())
     }
 
-    /// Start all prepared VMs.
+    /// Start the first wave of prepared VMs: those that `wait_for` no others, up to the boot
+    /// concurrency limit. The rest stay [`VmState::Waiting`] until
+    /// [`ScenarioRunner::wait_for_agents_with_progress`] starts them.
     ///
     /// # Errors
     /// Returns `VmError` if any VM fails to start or the state cannot be saved.
     pub fn start_vms(&mut self) -> Result<(), VmError> {
+        if self.backend.is_some() {
+            return self.save_state();
+        }
         self.start_lan_switch_if_needed()?;
+        self.start_mock_services_if_needed()?;
         let arch = detect_arch();
-        for name in &self.vm_order {
-            if let Some(vm) = self.vms.get_mut(name) {
-                vm.start(&arch)?;
-                vm.state = VmState::CloudInit;
+        let wave_size = self.wave_size();
+        let mut started = 0;
+        for name in self.vm_order.clone() {
+            let Some(vm) = self.vms.get_mut(&name) else {
+                continue;
+            };
+            if !vm.definition.wait_for.is_empty() {
+                info!(
+                    "VM {} waits for {}",
+                    name,
+                    vm.definition.wait_for.join(", ")
+                );
+                vm.state = VmState::Waiting;
+            } else if started == wave_size {
+                info!("VM {} is queued to boot in a later wave", name);
+                vm.state = VmState::Waiting;
+            } else {
+                self.start_vm(&name, &arch)?;
+                started += 1;
             }
         }
         self.save_state()?;
         Ok(())
     }
 
+    /// Start the QEMU process of the prepared VM `name`.
+    fn start_vm(&mut self, name: &str, arch: &str) -> Result<(), VmError> {
+        let Some(vm) = self.vms.get_mut(name) else {
+            return Ok(());
+        };
+        if let Err(e) = vm.start(arch) {
+            let (definition, base_image) = (vm.definition.clone(), vm.base_image.clone());
+            return Err(match base_image {
+                Some(base_image) => self.check_base_image(&definition, &base_image, e),
+                None => e,
+            });
+        }
+        vm.state = VmState::CloudInit;
+        self.note_vm_start(name);
+        Ok(())
+    }
+
     /// Persist current VM metadata to disk.
     ///
     /// # Errors
@@ -646,49 +2504,263 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
         let state = RunState {
             scenario_name: self.scenario.name.clone(),
             vms: self
-                .vms
-                .values()
+                .vm_order
+                .iter()
+                .filter_map(|name| self.vms.get(name))
                 .map(|vm| VmInfo {
                     name: vm.name.clone(),
                     ssh_port: vm.ssh_port,
+                    api_port: vm.api_port,
                     image: vm.definition.image.clone(),
+                    lan_port: vm
+                        .shared_lan
+                        .as_ref()
+                        .and_then(SharedNetworkEndpoint::switch_port),
+                    socket_ports: [&vm.qmp_socket, &vm.serial_socket, &vm.actions_socket]
+                        .into_iter()
+                        .chain(vm.control_socket.as_ref())
+                        .chain(vm.console_socket.as_ref())
+                        .filter_map(HostSocket::tcp_port)
+                        .collect(),
+                    host_key_fingerprint: vm.host_key_fingerprint.clone(),
                 })
                 .collect(),
+            seed: Some(self.seed),
+            fault_selections: self.fault_selections.clone(),
+            triggers: self.triggered_steps.iter().map(TriggeredStep::id).collect(),
+            lan_hub_port: self.shared_lan_hub_port,
+            lan_backend: self.lan_backend,
+            ephemeral_dir: self.ephemeral_dir.clone(),
+            mock_ports: self.mock_ports.clone(),
+            expires_at_ms: self.expires_at_ms,
+            incoming_migration: false,
+            remote: self.remote.clone(),
+            libvirt_uri: self.libvirt_uri.clone(),
+            cloud: self.cloud.clone(),
+            paranoid: self.paranoid,
+            host_hooks: self.host_hooks,
+            audit: Some(self.audit.head()),
         };
         state.save(&self.work_dir)?;
         Ok(())
     }
 
+    /// Derive the boot timeouts from a quick host benchmark, the largest base image, and the
+    /// scenario's `timeouts` block. Call after [`ScenarioRunner::create_vm`].
+    pub fn calibrate_boot_timeouts(&mut self) -> BootTimeouts {
+        let largest_image = self
+            .vms
+            .values()
+            .filter_map(|vm| vm.base_image.as_ref())
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .max()
+            .unwrap_or(0);
+        // VMs booting in waves only contend with their own wave.
+        let mut cpus: Vec<u32> = self.scenario.vms.iter().map(|vm| vm.cpu).collect();
+        cpus.sort_unstable_by_key(|cpu| std::cmp::Reverse(*cpu));
+        let vcpus = cpus.iter().take(self.wave_size()).sum();
+        let host_cpus = std::thread::available_parallelism()
+            .ok()
+            .and_then(|n| u32::try_from(n.get()).ok())
+            .unwrap_or(1);
+
+        // Cloud instances boot on their own CPUs, as fast as the reference host.
+        let (speed, host_cpus) = match self.cloud {
+            Some(_) => (HostSpeed { slowdown: 1.0 }, vcpus),
+            None => (HostSpeed::measure(), host_cpus),
+        };
+        self.boot_timeouts = BootTimeouts::calibrate(
+            speed,
+            largest_image,
+            vcpus,
+            host_cpus,
+            &self.scenario.timeouts,
+        );
+        info!(
+            "Host slowdown {:.1}x; expecting boot in {}s (agent timeout {}s, boot probe timeout {}s)",
+            speed.slowdown,
+            self.boot_timeouts.expected.as_secs(),
+            self.boot_timeouts.agent.as_secs(),
+            self.boot_timeouts.boot_probes.as_secs()
+        );
+        self.boot_timeouts
+    }
+
     /// Wait for all guest agents to become responsive.
     ///
     /// # Errors
     /// Returns `VmError` if any agent fails or times out.
     pub async fn wait_for_agents(&mut self) -> Result<(), VmError> {
-        for (name, vm) in &self.vms {
-            info!("Waiting for agent on VM: {}", name);
-
-            let result = timeout(Duration::from_secs(600), wait_for_agent(&vm.serial_socket)).await;
+        self.wait_for_agents_with_progress(|_| {}).await
+    }
 
-            match result {
-                Ok(Ok(())) => {
-                    info!("Agent ready on VM: {}", name);
+    /// Wait for all guest agents, calling `on_ready` with each VM name as its agent answers.
+    ///
+    /// # Errors
+    /// Returns `VmError` if any agent fails or does not answer within the agent timeout.
+    pub async fn wait_for_agents_with_progress<F>(&mut self, mut on_ready: F) -> Result<(), VmError>
+    where
+        F: FnMut(&str),
+    {
+        if let Some(backend) = &self.backend {
+            // Boot in the same waves as real VMs: each wave starts once the previous one is up.
+            let wave_size = self.wave_size();
+            let mut pending = self.scenario.boot_order();
+            let mut booted: HashSet<&str> = HashSet::new();
+            // (time, agent ready rather than started, VM)
+            let mut timeline: Vec<(Duration, bool, &str)> = Vec::new();
+            let mut wave_start = Duration::ZERO;
+            while !pending.is_empty() {
+                let mut wave = Vec::new();
+                pending.retain(|vm| {
+                    let starts = wave.len() < wave_size
+                        && vm
+                            .wait_for
+                            .iter()
+                            .all(|name| booted.contains(name.as_str()));
+                    if starts {
+                        wave.push(*vm);
+                    }
+                    !starts
+                });
+                if wave.is_empty() {
+                    break;
                 }
-                Ok(Err(e)) => {
-                    error!("Agent failed on VM {}: {}", name, e);
-                    return Err(e);
+                let mut wave_end = wave_start;
+                for vm in wave {
+                    let ready = wave_start + backend.boot_delay(&vm.name);
+                    timeline.push((wave_start, false, vm.name.as_str()));
+                    timeline.push((ready, true, vm.name.as_str()));
+                    booted.insert(vm.name.as_str());
+                    wave_end = wave_end.max(ready);
+                }
+                wave_start = wave_end;
+            }
+            timeline.sort_unstable();
+            let started = std::time::Instant::now();
+            for (at, ready, name) in timeline {
+                tokio::time::sleep(at.saturating_sub(started.elapsed())).await;
+                if ready {
+                    info!("Simulated agent ready on VM: {}", name);
+                    on_ready(name);
+                } else {
+                    self.note_vm_start(name);
+                }
+            }
+            return Ok(());
+        }
+
+        let mut answered: HashSet<String> = HashSet::new();
+        loop {
+            // The VMs started together boot in parallel, so they share one deadline.
+            let deadline = std::time::Instant::now() + self.boot_timeouts.agent;
+            let mut booted = Vec::new();
+            for (name, vm) in &self.vms {
+                if vm.state == VmState::Waiting || answered.contains(name) {
+                    continue;
                 }
-                Err(_) => {
+                info!("Waiting for agent on VM: {}", name);
+
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                if timeout(remaining, wait_for_agent(&vm.serial_socket))
+                    .await
+                    .is_err()
+                {
                     error!("Timeout waiting for agent on VM: {}", name);
-                    return Err(VmError::Timeout(format!("Agent timeout on {name}")));
+                    return Err(VmError::Timeout(format!(
+                        "Agent on {name} did not answer within {}s",
+                        self.boot_timeouts.agent.as_secs()
+                    )));
+                }
+                info!("Agent ready on VM: {}", name);
+                on_ready(name);
+                booted.push(name.clone());
+            }
+
+            for name in booted {
+                if let Some(vm) = self.vms.get_mut(&name) {
+                    vm.state = VmState::Ready;
                 }
+                answered.insert(name);
+            }
+
+            if !self.vms.values().any(|vm| vm.state == VmState::Waiting) {
+                return Ok(());
             }
+            self.start_waiting_vms().await?;
         }
+    }
 
-        for vm in self.vms.values_mut() {
-            vm.state = VmState::Ready;
+    /// Start the next wave of [`VmState::Waiting`] VMs: those whose `wait_for` VMs all pass
+    /// their boot probes, up to the boot concurrency limit. Checks every 5s until at least one
+    /// can start.
+    ///
+    /// # Errors
+    /// Returns `VmError` if a VM fails to start, or `VmError::Timeout` if none can start within
+    /// the boot probe timeout.
+    async fn start_waiting_vms(&mut self) -> Result<(), VmError> {
+        let deadline = std::time::Instant::now() + self.boot_timeouts.boot_probes;
+        loop {
+            // VMs queued only by the concurrency limit need no probe results.
+            if self
+                .vms
+                .values()
+                .any(|vm| vm.state == VmState::Waiting && !vm.definition.wait_for.is_empty())
+            {
+                self.check_probes_phase(ProbePhase::Boot).await?;
+            }
+            let released: Vec<String> = self
+                .vm_order
+                .iter()
+                .filter_map(|name| self.vms.get(name))
+                .filter(|vm| vm.state == VmState::Waiting)
+                .filter(|vm| {
+                    vm.definition.wait_for.iter().all(|name| {
+                        self.vms
+                            .get(name)
+                            .is_some_and(|other| other.state != VmState::Waiting)
+                            && self.boot_probes_passing(name)
+                    })
+                })
+                .map(|vm| vm.name.clone())
+                .take(self.wave_size())
+                .collect();
+
+            if !released.is_empty() {
+                let arch = detect_arch();
+                for name in &released {
+                    info!("Starting VM {} in the next boot wave", name);
+                    self.start_vm(name, &arch)?;
+                    if let (Some(tx), Some(vm)) = (&self.qmp_event_tx, self.vms.get(name))
+                        && let Some(task) = follow_qmp_events(vm, tx.clone()).await
+                    {
+                        self.qmp_event_tasks.push(task);
+                    }
+                }
+                return self.save_state();
+            }
+            if std::time::Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
         }
 
-        Ok(())
+        let waiting: Vec<&str> = self
+            .vm_order
+            .iter()
+            .filter(|name| {
+                self.vms
+                    .get(*name)
+                    .is_some_and(|vm| vm.state == VmState::Waiting)
+            })
+            .map(String::as_str)
+            .collect();
+        Err(VmError::Timeout(format!(
+            "{} waited for VMs whose boot probes did not pass within {}s",
+            waiting.join(", "),
+            self.boot_timeouts.boot_probes.as_secs()
+        )))
     }
 
     /// Dispatch probe checks to all VMs.
@@ -696,60 +2768,226 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
     /// # Errors
     /// Returns `VmError` if communication with agents fails.
     pub async fn check_probes(&mut self) -> Result<(), VmError> {
-        self.check_probes_phase(ProbePhase::Scenario).await
+        let before = self.probe_statuses();
+        self.check_probes_phase(ProbePhase::Scenario).await?;
+        if self.probe_statuses() != before {
+            self.note_activity();
+        }
+        Ok(())
     }
 
-    /// Dispatch probe checks for a specific phase.
-    async fn check_probes_phase(&mut self, phase: ProbePhase) -> Result<(), VmError> {
-        for (vm_name, vm) in &self.vms {
-            let probe_names = &self
-                .scenario
-                .vms
+    /// Add the latest scenario probe results to [`ScenarioRunner::probe_history`], `elapsed`
+    /// into the run.
+    pub fn record_probe_history(&mut self, elapsed: Duration) {
+        for vm in &self.scenario.vms {
+            let Some(vm_results) = self.probe_results.get(&vm.name) else {
+                continue;
+            };
+            for probe in &vm.probes {
+                let objective = self
+                    .scenario
+                    .probes
+                    .get(probe)
+                    .is_some_and(|def| def.phase == ProbePhase::Scenario);
+                if let Some(result) = vm_results.get(probe).filter(|_| objective) {
+                    self.probe_history
+                        .record(&vm.name, probe, result.passed, elapsed);
+                }
+            }
+        }
+    }
+
+    fn probe_statuses(&self) -> HashMap<(String, String), bool> {
+        self.probe_results
+            .iter()
+            .flat_map(|(vm, results)| {
+                results
+                    .values()
+                    .map(move |result| ((vm.clone(), result.id.clone()), result.passed))
+            })
+            .collect()
+    }
+
+    /// Evaluate the scenario probes with `evaluator` on the host instead of through the guest
+    /// agents, then update completion as [`ScenarioRunner::check_probes`] does. Replaying a
+    /// [`ProbeFixture`] this way exercises scoring and completion without QEMU.
+    pub async fn check_probes_with(&mut self, evaluator: &mut dyn ProbeEvaluator) {
+        let before = self.probe_statuses();
+        self.evaluate_locally(ProbePhase::Scenario, &mut |_, id, spec| {
+            evaluator.evaluate(id, spec)
+        });
+        self.apply_probe_severities();
+        self.update_completion(ProbePhase::Scenario).await;
+        if self.probe_statuses() != before {
+            self.note_activity();
+        }
+    }
+
+    /// Evaluate scenario probe `probe` on `vm_name` right away instead of waiting for the next
+    /// check, e.g. to chase a flaky probe under author mode.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the VM does not exist or its agent cannot evaluate the probe.
+    pub async fn recheck_probe(&mut self, vm_name: &str, probe: &str) -> Result<(), VmError> {
+        let (probes, local_results) = self.phase_probes(vm_name, ProbePhase::Scenario);
+        let result = if let Some((id, spec)) = probes.into_iter().find(|(id, _)| id == probe) {
+            let started = std::time::Instant::now();
+            let (result, raw) = if let Some(backend) = self.backend.as_mut() {
+                let result = backend.evaluate(vm_name, ProbePhase::Scenario, &id, &spec);
+                let raw = serde_json::to_string(&result)?;
+                (result, raw)
+            } else {
+                let vm = self
+                    .vms
+                    .get(vm_name)
+                    .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+                let mut conn = try_connect(&vm.serial_socket, 3, 500).await?;
+                let result = conn.check_probe(&id, &spec).await?;
+                (result, conn.last_response().to_string())
+            };
+            if let Some(trace) = self.probe_trace.as_mut() {
+                trace.record(vm_name, &result, started.elapsed(), raw);
+            }
+            result
+        } else if let Some(result) = local_results.into_iter().find(|r| r.id == probe) {
+            result
+        } else {
+            return Ok(());
+        };
+
+        self.probe_results
+            .entry(vm_name.to_string())
+            .or_default()
+            .insert(result.id.clone(), result);
+        self.apply_probe_severities();
+        self.update_completion(ProbePhase::Scenario).await;
+        Ok(())
+    }
+
+    /// Evaluate the probes of `phase` on every VM with `evaluate(vm, id, spec)` instead of
+    /// asking the agents.
+    fn evaluate_locally(
+        &mut self,
+        phase: ProbePhase,
+        evaluate: &mut dyn FnMut(&str, &str, &ProbeSpec) -> ProbeResult,
+    ) {
+        let vm_names: Vec<String> = self.scenario.vms.iter().map(|vm| vm.name.clone()).collect();
+        for vm_name in vm_names {
+            let (probes, local_results) = self.phase_probes(&vm_name, phase);
+            let evaluated: Vec<ProbeResult> = probes
                 .iter()
-                .find(|v| v.name == *vm_name)
-                .map(|v| v.probes.clone())
-                .unwrap_or_default();
+                .map(|(id, spec)| {
+                    let started = std::time::Instant::now();
+                    let result = evaluate(&vm_name, id, spec);
+                    if let Some(trace) = self.probe_trace.as_mut() {
+                        let raw = serde_json::to_string(&result).unwrap_or_default();
+                        trace.record(&vm_name, &result, started.elapsed(), raw);
+                    }
+                    result
+                })
+                .collect();
+            let vm_results = self.probe_results.entry(vm_name).or_default();
+            for result in local_results.into_iter().chain(evaluated) {
+                vm_results.insert(result.id.clone(), result);
+            }
+        }
+    }
+
+    /// The probes of `phase` on `vm_name` to send to its agent, and the results known without
+    /// asking it: undefined or invalid probes and flags already submitted.
+    fn phase_probes(
+        &self,
+        vm_name: &str,
+        phase: ProbePhase,
+    ) -> (Vec<(String, ProbeSpec)>, Vec<ProbeResult>) {
+        let probe_names = self
+            .scenario
+            .vms
+            .iter()
+            .find(|v| v.name == vm_name)
+            .map(|v| v.probes.as_slice())
+            .unwrap_or_default();
+
+        let mut probes: Vec<(String, ProbeSpec)> = Vec::new();
+        let mut local_results: Vec<ProbeResult> = Vec::new();
+
+        for name in probe_names {
+            let Some(def) = self.scenario.probes.get(name) else {
+                local_results.push(ProbeResult::fail(
+                    name.clone(),
+                    format!("Probe '{name}' not defined in scenario"),
+                ));
+                continue;
+            };
+
+            if def.phase != phase {
+                continue;
+            }
 
-            let mut probes: Vec<(String, ProbeSpec)> = Vec::new();
-            let mut probe_ids: Vec<String> = Vec::new();
-            let mut local_failures: Vec<ProbeResult> = Vec::new();
+            if let Err(e) = probe_severity(def) {
+                local_results.push(ProbeResult::fail(
+                    name.clone(),
+                    format!("Invalid probe config: {e}"),
+                ));
+                continue;
+            }
 
-            for name in probe_names {
-                let Some(def) = self.scenario.probes.get(name) else {
-                    local_failures.push(ProbeResult::fail(
+            let config = def
+                .config
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+
+            match ProbeSpec::from_definition(&def.probe_type, &config) {
+                Ok(ProbeSpec::Flag { sha256, .. })
+                    if self.accepted_flags.contains(&sha256.to_ascii_lowercase()) =>
+                {
+                    local_results.push(ProbeResult::pass(name.clone(), "Flag submitted"));
+                }
+                Ok(spec) => {
+                    probes.push((name.clone(), spec));
+                }
+                Err(e) => {
+                    local_results.push(ProbeResult::fail(
                         name.clone(),
-                        format!("Probe '{name}' not defined in scenario"),
+                        format!("Invalid probe config: {e}"),
                     ));
-                    continue;
-                };
-
-                if def.phase != phase {
-                    continue;
                 }
+            }
+        }
 
-                let config = def
-                    .config
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
+        (probes, local_results)
+    }
+
+    /// Dispatch probe checks for a specific phase.
+    async fn check_probes_phase(&mut self, phase: ProbePhase) -> Result<(), VmError> {
+        for flag in take_run_requests(&self.work_dir, SUBMISSIONS_DIR) {
+            self.submit_flag(&flag);
+        }
+        self.apply_capture_requests();
+
+        if let Some(mut backend) = self.backend.take() {
+            self.evaluate_locally(phase, &mut |vm, id, spec| {
+                backend.evaluate(vm, phase, id, spec)
+            });
+            self.backend = Some(backend);
+            self.apply_probe_severities();
+            self.update_completion(phase).await;
+            return Ok(());
+        }
 
-                match ProbeSpec::from_definition(&def.probe_type, &config) {
-                    Ok(spec) => {
-                        probe_ids.push(name.clone());
-                        probes.push((name.clone(), spec));
-                    }
-                    Err(e) => {
-                        local_failures.push(ProbeResult::fail(
-                            name.clone(),
-                            format!("Invalid probe config: {e}"),
-                        ));
-                    }
-                }
+        // VMs whose agent answered; a guest that was reset is running again once it does.
+        let mut answered = Vec::new();
+        for (vm_name, vm) in &self.vms {
+            if vm.state == VmState::Waiting || self.step_running(vm_name) {
+                continue;
             }
+            let (probes, local_results) = self.phase_probes(vm_name, phase);
+            let probe_ids: Vec<String> = probes.iter().map(|(id, _)| id.clone()).collect();
 
             if let Some(vm_results) = self.probe_results.get_mut(vm_name) {
-                for failure in local_failures {
-                    vm_results.insert(failure.id.clone(), failure);
+                for result in local_results {
+                    vm_results.insert(result.id.clone(), result);
                 }
             }
 
@@ -758,28 +2996,33 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
             }
 
             match try_connect(&vm.serial_socket, 3, 500).await {
-                Ok(mut conn) => match conn.check_all(probes).await {
-                    Ok(results) => {
-                        if let Some(vm_results) = self.probe_results.get_mut(vm_name) {
-                            for result in results {
-                                vm_results.insert(result.id.clone(), result);
+                Ok(mut conn) => {
+                    match check_agent_probes(&mut conn, vm_name, probes, self.probe_trace.as_mut())
+                        .await
+                    {
+                        Ok(results) => {
+                            answered.push(vm_name.clone());
+                            if let Some(vm_results) = self.probe_results.get_mut(vm_name) {
+                                for result in results {
+                                    vm_results.insert(result.id.clone(), result);
+                                }
                             }
                         }
-                    }
-                    Err(e) => {
-                        warn!("Failed to check probes on {}: {}", vm_name, e);
-                        if let Some(vm_results) = self.probe_results.get_mut(vm_name) {
-                            for id in probe_ids {
-                                vm_results.entry(id.clone()).or_insert_with(|| {
-                                    ProbeResult::fail(
-                                        id,
-                                        format!("Failed to check probes via agent: {e}"),
-                                    )
-                                });
+                        Err(e) => {
+                            warn!("Failed to check probes on {}: {}", vm_name, e);
+                            if let Some(vm_results) = self.probe_results.get_mut(vm_name) {
+                                for id in probe_ids {
+                                    vm_results.entry(id.clone()).or_insert_with(|| {
+                                        ProbeResult::fail(
+                                            id,
+                                            format!("Failed to check probes via agent: {e}"),
+                                        )
+                                    });
+                                }
                             }
                         }
                     }
-                },
+                }
                 Err(e) => {
                     warn!("Failed to connect to agent on {}: {}", vm_name, e);
                     if let Some(vm_results) = self.probe_results.get_mut(vm_name) {
@@ -793,32 +3036,339 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
             }
         }
 
-        if phase == ProbePhase::Scenario && self.all_scenario_probes_passing() {
+        for name in answered {
+            if let Some(vm) = self.vms.get_mut(&name)
+                && vm.state == VmState::Booting
+            {
+                vm.state = VmState::Ready;
+            }
+        }
+
+        self.apply_probe_severities();
+        self.update_completion(phase).await;
+        Ok(())
+    }
+
+    /// Mark each result with its probe's `severity` from the scenario.
+    fn apply_probe_severities(&mut self) {
+        for result in self
+            .probe_results
+            .values_mut()
+            .flat_map(HashMap::values_mut)
+        {
+            if let Some(def) = self.scenario.probes.get(&result.id)
+                && let Ok(Some(severity)) = probe_severity(def)
+            {
+                result.details.severity = Some(severity);
+            }
+        }
+    }
+
+    /// Mark the scenario completed, queueing its completion hooks once, when every objective
+    /// passes and every question is answered.
+    async fn update_completion(&mut self, phase: ProbePhase) {
+        if phase == ProbePhase::Scenario
+            && self.all_scenario_probes_passing()
+            && self.all_questions_answered()
+        {
+            let newly_completed = self.state != ScenarioState::Completed;
             self.state = ScenarioState::Completed;
+            if newly_completed {
+                self.pending_hooks
+                    .extend(self.scenario.on_complete.iter().cloned());
+            }
+        }
+    }
+
+    /// Run `teardown` probes once and return their results in VM order.
+    ///
+    /// # Errors
+    /// Returns `VmError` if probes cannot be dispatched.
+    pub async fn check_teardown_probes(&mut self) -> Result<Vec<(String, ProbeResult)>, VmError> {
+        let has_teardown = self
+            .scenario
+            .vms
+            .iter()
+            .flat_map(|v| v.probes.iter())
+            .any(|p| self.probe_phase(p) == Some(ProbePhase::Teardown));
+        if !has_teardown {
+            return Ok(Vec::new());
+        }
+
+        self.check_probes_phase(ProbePhase::Teardown).await?;
+
+        let mut results = Vec::new();
+        for vm_name in &self.vm_order {
+            let Some(vm_results) = self.probe_results.get(vm_name) else {
+                continue;
+            };
+            let mut vm_teardown: Vec<&ProbeResult> = vm_results
+                .values()
+                .filter(|r| self.probe_phase(&r.id) == Some(ProbePhase::Teardown))
+                .collect();
+            vm_teardown.sort_by(|a, b| a.id.cmp(&b.id));
+            results.extend(
+                vm_teardown
+                    .into_iter()
+                    .map(|r| (vm_name.clone(), r.clone())),
+            );
+        }
+        Ok(results)
+    }
+
+    /// Every sudo invocation on each VM since it booted, as `(vm, invocation)` in VM order.
+    /// VMs whose agent does not answer are left out.
+    pub async fn sudo_usage(&self) -> Vec<(String, SudoInvocation)> {
+        if self.backend.is_some() {
+            return Vec::new();
+        }
+        let mut usage = Vec::new();
+        for vm_name in &self.vm_order {
+            let Some(vm) = self.vms.get(vm_name) else {
+                continue;
+            };
+            let invocations = match try_connect(&vm.serial_socket, 3, 500).await {
+                Ok(mut conn) => conn.sudo_log().await,
+                Err(e) => Err(e),
+            };
+            match invocations {
+                Ok(invocations) => usage.extend(
+                    invocations
+                        .into_iter()
+                        .map(|invocation| (vm_name.clone(), invocation)),
+                ),
+                Err(e) => warn!("Failed to read the sudo log on {}: {}", vm_name, e),
+            }
+        }
+        usage
+    }
+
+    /// Manifests of each VM's `track_changes` directories, skipping VMs whose agent does not
+    /// answer.
+    async fn file_manifests(&self) -> BTreeMap<String, FileManifest> {
+        let mut manifests = BTreeMap::new();
+        if self.backend.is_some() {
+            return manifests;
+        }
+        for vm_def in &self.scenario.vms {
+            let Some(vm) = self.vms.get(&vm_def.name) else {
+                continue;
+            };
+            if vm_def.track_changes.is_empty() {
+                continue;
+            }
+            let manifest = match try_connect(&vm.serial_socket, 3, 500).await {
+                Ok(mut conn) => conn.file_manifest(&vm_def.track_changes).await,
+                Err(e) => Err(e),
+            };
+            match manifest {
+                Ok(manifest) => {
+                    manifests.insert(vm_def.name.clone(), manifest);
+                }
+                Err(e) => warn!("Failed to hash tracked files on {}: {}", vm_def.name, e),
+            }
+        }
+        manifests
+    }
+
+    /// Record the tracked files as they are now, the state [`ScenarioRunner::file_changes`]
+    /// compares against. Resets go back to the same checkpoint, so the baseline stays valid.
+    async fn save_file_baseline(&self) -> Result<(), VmError> {
+        let manifests = self.file_manifests().await;
+        if manifests.is_empty() {
+            return Ok(());
+        }
+        std::fs::write(
+            self.work_dir.join(FILE_BASELINE_FILE),
+            serde_json::to_string(&manifests)?,
+        )?;
+        Ok(())
+    }
+
+    /// Files under each VM's `track_changes` directories that changed since the last
+    /// checkpoint, as `(vm, changes)` for VMs with any.
+    pub async fn file_changes(&self) -> Vec<(String, FileChanges)> {
+        let Ok(baseline) = std::fs::read_to_string(self.work_dir.join(FILE_BASELINE_FILE)) else {
+            return Vec::new();
+        };
+        let baseline: BTreeMap<String, FileManifest> = match serde_json::from_str(&baseline) {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                warn!("Ignoring unreadable {}: {}", FILE_BASELINE_FILE, e);
+                return Vec::new();
+            }
+        };
+        self.file_manifests()
+            .await
+            .into_iter()
+            .filter_map(|(vm, after)| {
+                let changes = baseline.get(&vm)?.diff(&after);
+                (!changes.is_empty()).then_some((vm, changes))
+            })
+            .collect()
+    }
+
+    /// Run the scenario's `action "external"` blocks in order once every VM has booted,
+    /// calling `on_progress` with each line they print. The run's Ansible inventory is written
+    /// to `inventory.ini` for them and each provider's output is kept in
+    /// `logs/external-<n>-<provider>.log`.
+    ///
+    /// # Errors
+    /// Returns `VmError::NotPermitted` if the run may not run them (see
+    /// [`ScenarioRunner::check_host_execution`]), and `VmError` if the inventory cannot be
+    /// written or a provider fails.
+    pub async fn run_external_actions<F>(&self, mut on_progress: F) -> Result<(), VmError>
+    where
+        F: FnMut(&str),
+    {
+        if self.scenario.external_actions.is_empty() {
+            return Ok(());
         }
+        self.check_host_execution()?;
+        let hosts = self
+            .scenario
+            .vms
+            .iter()
+            .filter_map(|vm_def| {
+                let vm = self.vms.get(&vm_def.name)?;
+                Some(InventoryHost {
+                    name: vm_def.name.clone(),
+                    ssh_port: vm.ssh_port,
+                    host_key_fingerprint: vm.host_key_fingerprint.clone(),
+                })
+            })
+            .collect();
+        let inventory = AnsibleInventory::new(&self.scenario, hosts, &self.work_dir);
+        let inventory_path = self.work_dir.join("inventory.ini");
+        std::fs::write(&inventory_path, inventory.to_ini())?;
+        let context = ProviderContext {
+            scenario: self.scenario.name.clone(),
+            run_id: self.run_id(),
+            run_dir: self.work_dir.clone(),
+            inventory_path,
+            inventory,
+        };
 
+        let logs = self.work_dir.join("logs");
+        std::fs::create_dir_all(&logs)?;
+        for (i, action) in self.scenario.external_actions.iter().enumerate() {
+            info!("Running external action {} ({})", i + 1, action.provider);
+            on_progress(&format!("{}: starting", action.provider));
+            let log_path = logs.join(format!("external-{}-{}.log", i + 1, action.provider));
+            run_external_action(action, &context, &log_path, |line| {
+                on_progress(&format!("{}: {line}", action.provider));
+            })
+            .await?;
+        }
         Ok(())
     }
 
+    fn probe_phase(&self, id: &str) -> Option<ProbePhase> {
+        self.scenario.probes.get(id).map(|def| def.phase)
+    }
+
     /// Wait for all boot probes to pass before entering Running phase.
     ///
     /// # Errors
     /// Returns `VmError` if probes cannot be evaluated or the wait times out.
     pub async fn wait_for_boot_probes(&mut self) -> Result<(), VmError> {
+        self.wait_for_boot_probes_with_progress(|_, _| {}).await
+    }
+
+    /// Wait for all boot probes, calling `on_progress(passing, total)` after every check.
+    ///
+    /// # Errors
+    /// Returns `VmError` if probes cannot be evaluated or do not pass within the boot probe
+    /// timeout.
+    pub async fn wait_for_boot_probes_with_progress<F>(
+        &mut self,
+        mut on_progress: F,
+    ) -> Result<(), VmError>
+    where
+        F: FnMut(usize, usize),
+    {
         // If there are no boot probes, return immediately.
-        if self.total_boot_probe_count() == 0 {
-            return Ok(());
+        let total = self.total_boot_probe_count();
+        if total == 0 {
+            return self.distribute_kubeconfigs().await;
         }
 
-        for _ in 0..60 {
+        let deadline = std::time::Instant::now() + self.boot_timeouts.boot_probes;
+        loop {
             self.check_probes_phase(ProbePhase::Boot).await?;
+            on_progress(self.passing_boot_probe_count(), total);
             if self.all_boot_probes_passing() {
-                return Ok(());
+                return self.distribute_kubeconfigs().await;
+            }
+            if std::time::Instant::now() >= deadline {
+                break;
             }
             tokio::time::sleep(Duration::from_secs(5)).await;
         }
 
-        Err(VmError::Timeout("Boot probes did not pass in time".into()))
+        Err(VmError::Timeout(format!(
+            "Boot probes did not pass within {}s",
+            self.boot_timeouts.boot_probes.as_secs()
+        )))
+    }
+
+    /// Copy each `k8s_cluster`'s admin kubeconfig from its first server to `~user/.kube/config`
+    /// on every node, and to the k3s path on agents so `kubectl` and k8s actions work anywhere.
+    /// Other nodes reach the API through the cluster's API host name.
+    async fn distribute_kubeconfigs(&self) -> Result<(), VmError> {
+        if self.backend.is_some() {
+            return Ok(());
+        }
+        for cluster in &self.scenario.clusters {
+            let first = cluster.server_name(1);
+            let output = self
+                .exec_in_vm(&first, &format!("cat {K3S_KUBECONFIG}"))
+                .await?;
+            let local = output.stdout;
+            let remote = local.replace(
+                &format!("https://127.0.0.1:{K3S_API_PORT}"),
+                &format!("https://{}:{K3S_API_PORT}", cluster.api_host()),
+            );
+
+            for node in cluster.nodes() {
+                let kubeconfig = if node == first { &local } else { &remote };
+                let encoded = base64::engine::general_purpose::STANDARD.encode(kubeconfig);
+                let script = format!(
+                    r#"set -e
+if [ ! -f {K3S_KUBECONFIG} ]; then
+  mkdir -p "$(dirname {K3S_KUBECONFIG})"
+  echo '{encoded}' | base64 -d > {K3S_KUBECONFIG}
+  chmod 0644 {K3S_KUBECONFIG}
+fi
+install -d -o user -g user -m 0700 /home/user/.kube
+echo '{encoded}' | base64 -d > /home/user/.kube/config
+chown user:user /home/user/.kube/config
+chmod 0600 /home/user/.kube/config
+"#
+                );
+                self.exec_in_vm(&node, &script).await?;
+            }
+            info!("Distributed kubeconfig of cluster {}", cluster.name);
+        }
+        Ok(())
+    }
+
+    /// Run a shell script in `vm_name` through the agent, failing on a non-zero exit.
+    async fn exec_in_vm(&self, vm_name: &str, script: &str) -> Result<ExecOutput, VmError> {
+        let vm = self
+            .vms
+            .get(vm_name)
+            .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+        let mut conn = try_connect(&vm.serial_socket, 3, 500).await?;
+        let output = conn.exec(script).await?;
+        if output.exit_code != 0 {
+            return Err(VmError::Serial(format!(
+                "command on VM {vm_name} exited with status {}: {}",
+                output.exit_code,
+                output.stderr.trim()
+            )));
+        }
+        Ok(output)
     }
 
     #[must_use]
@@ -860,6 +3410,22 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
         true
     }
 
+    /// Whether every boot probe of VM `vm_name` passes.
+    fn boot_probes_passing(&self, vm_name: &str) -> bool {
+        let results = self.probe_results.get(vm_name);
+        self.scenario
+            .vms
+            .iter()
+            .filter(|vm| vm.name == vm_name)
+            .flat_map(|vm| &vm.probes)
+            .filter(|probe| self.probe_phase(probe) == Some(ProbePhase::Boot))
+            .all(|probe| {
+                results
+                    .and_then(|results| results.get(probe))
+                    .is_some_and(|result| result.passed)
+            })
+    }
+
     fn all_boot_probes_passing(&self) -> bool {
         for (vm_name, vm_results) in &self.probe_results {
             let expected_count = self
@@ -962,7 +3528,28 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
     ///
     /// # Errors
     /// Returns `VmError` if any VM checkpoint command fails.
-    pub async fn save_checkpoint(&self, name: &str) -> Result<(), VmError> {
+    pub async fn save_checkpoint(&mut self, name: &str) -> Result<(), VmError> {
+        self.save_checkpoint_with_progress(name, |_| {}).await
+    }
+
+    /// Create a full checkpoint of every VM at once, reporting how far it got to
+    /// `on_progress`.
+    ///
+    /// The VMs stay paused while QEMU writes their RAM into the overlays. QEMU's
+    /// `background-snapshot` migration would write it with the VMs running, but to a stream
+    /// outside the disk that only a restarted QEMU can load, which would make every reset
+    /// slower than the save it spares.
+    ///
+    /// # Errors
+    /// Returns `VmError` if any VM checkpoint command fails.
+    pub async fn save_checkpoint_with_progress<F>(
+        &mut self,
+        name: &str,
+        on_progress: F,
+    ) -> Result<(), VmError>
+    where
+        F: FnMut(&CheckpointProgress) + Send,
+    {
         info!("Pausing all VMs for checkpoint '{}'", name);
         let pause_result = try_join_all(self.vms.values().map(QemuInstance::pause)).await;
         if let Err(e) = pause_result {
@@ -970,27 +3557,68 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
             return Err(e);
         }
 
-        let snapshot_result = async {
-            for (vm_name, vm) in &self.vms {
-                info!("Saving checkpoint '{}' for VM: {}", name, vm_name);
-                vm.save_checkpoint(name).await?;
+        let vms: Vec<&QemuInstance> = self.vms.values().collect();
+        let memory: Vec<u64> = vms
+            .iter()
+            .map(|vm| u64::from(vm.definition.memory) * 1024 * 1024)
+            .collect();
+        let tracker = CheckpointTracker::new(name, false, &memory, on_progress);
+        let snapshot_result = try_join_all(vms.iter().enumerate().map(|(index, vm)| {
+            let tracker = &tracker;
+            async move {
+                info!("Saving checkpoint '{}' for VM: {}", name, vm.name);
+                vm.save_checkpoint(name, |bytes| tracker.written(index, bytes))
+                    .await?;
+                tracker.vm_done();
+                Ok::<(), VmError>(())
             }
-            Ok::<(), VmError>(())
-        }
+        }))
         .await;
 
         info!("Resuming all VMs after checkpoint '{}'", name);
         let resume_result = try_join_all(self.vms.values().map(QemuInstance::resume)).await;
 
         snapshot_result?;
-        resume_result.map(|_| ())
+        resume_result.map(|_| ())?;
+        self.latest_checkpoint = Some(name.to_string());
+        self.save_file_baseline().await?;
+
+        self.audit
+            .record(AuditOperation::CheckpointSaved, name.to_string())
     }
 
     /// Reset all VMs back to the initial checkpoint.
     ///
     /// # Errors
-    /// Returns `VmError` if any VM fails to reset.
+    /// Returns `VmError` if any VM fails to reset, or `VmError::NotPermitted` in exam mode.
     pub async fn reset(&mut self) -> Result<(), VmError> {
+        self.reset_with_progress(|_| {}).await
+    }
+
+    /// Reset all VMs back to the initial checkpoint, loading it into every VM at once and
+    /// reporting how far that got to `on_progress`. Returns once the agents and boot probes
+    /// are back.
+    ///
+    /// # Errors
+    /// Returns `VmError` if any VM fails to reset, or `VmError::NotPermitted` in exam mode.
+    pub async fn reset_with_progress<F>(&mut self, on_progress: F) -> Result<(), VmError>
+    where
+        F: FnMut(&CheckpointProgress) + Send,
+    {
+        if self.scenario.is_exam() {
+            return Err(VmError::NotPermitted(
+                "reset is disabled in exam mode".into(),
+            ));
+        }
+        if self.cloud.is_some() {
+            return Err(VmError::NotPermitted(
+                "reset needs checkpoints, which cloud instances do not have".into(),
+            ));
+        }
+
+        self.audit
+            .record(AuditOperation::Reset, "user requested reset")?;
+
         info!("Pausing all VMs for reset");
         let pause_result = try_join_all(self.vms.values().map(QemuInstance::pause)).await;
         if let Err(e) = pause_result {
@@ -998,13 +3626,16 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
             return Err(e);
         }
 
-        let load_result = async {
-            for (name, vm) in &self.vms {
+        let tracker = CheckpointTracker::new("init", true, &vec![0; self.vms.len()], on_progress);
+        let load_result = try_join_all(self.vms.iter().map(|(name, vm)| {
+            let tracker = &tracker;
+            async move {
                 info!("Loading checkpoint 'init' for VM: {}", name);
                 vm.load_checkpoint("init").await?;
+                tracker.vm_done();
+                Ok::<(), VmError>(())
             }
-            Ok::<(), VmError>(())
-        }
+        }))
         .await;
 
         info!("Resuming all VMs after reset");
@@ -1012,8 +3643,22 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
 
         load_result?;
         resume_result.map(|_| ())?;
+        self.audit
+            .record(AuditOperation::CheckpointRestored, "init")?;
 
         self.clear_probe_results();
+        self.probe_history.clear();
+        if let Some(backend) = self.backend.as_mut() {
+            backend.reset();
+        }
+        for triggered in &mut self.triggered_steps {
+            triggered.fired = false;
+            if let Some(running) = triggered.running.take() {
+                running.abort();
+            }
+        }
+        self.accepted_flags.clear();
+        self.answered_questions.clear();
         self.wait_for_agents().await?;
         self.wait_for_boot_probes().await?;
         self.state = ScenarioState::Running;
@@ -1021,6 +3666,287 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
         Ok(())
     }
 
+    /// Manual steps that have not fired since start (or the last reset) and are not running,
+    /// as `<vm>/<step>`.
+    #[must_use]
+    pub fn pending_manual_triggers(&self) -> Vec<String> {
+        self.triggered_steps
+            .iter()
+            .filter(|t| !t.fired && t.running.is_none() && t.step.trigger == StepTrigger::Manual)
+            .map(TriggeredStep::id)
+            .collect()
+    }
+
+    /// Start a triggered step (`<vm>/<step>` or a unique `<step>`) through the agent. The step
+    /// runs in the background: [`ScenarioRunner::fire_pending_triggers`] marks it fired and
+    /// logs a failure once the guest is done, and its VM's probes keep their last results
+    /// until then, since the step holds the VM's agent connection.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the step is unknown, already fired or running, or its VM is gone.
+    pub fn trigger_step(&mut self, name: &str) -> Result<(), VmError> {
+        let mut candidates = self
+            .triggered_steps
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.matches(name));
+        let (idx, triggered) = match (candidates.next(), candidates.next()) {
+            (Some(found), None) => found,
+            (Some(_), Some(_)) => {
+                return Err(VmError::NotPermitted(format!(
+                    "step '{name}' is ambiguous; use <vm>/<step>"
+                )));
+            }
+            (None, _) => {
+                return Err(VmError::NotPermitted(format!(
+                    "'{name}' is not a triggerable step"
+                )));
+            }
+        };
+        if triggered.fired {
+            return Err(VmError::NotPermitted(format!(
+                "step '{}' has already been triggered",
+                triggered.id()
+            )));
+        }
+        if triggered.running.is_some() {
+            return Err(VmError::NotPermitted(format!(
+                "step '{}' is still running",
+                triggered.id()
+            )));
+        }
+
+        info!("Triggering step {}", triggered.id());
+        let script = render_triggered_step(&triggered.vm, &triggered.step)?;
+        let socket = self
+            .vms
+            .get(&triggered.vm)
+            .ok_or_else(|| VmError::VmNotFound(triggered.vm.clone()))?
+            .serial_socket
+            .clone();
+        self.triggered_steps[idx].running = Some(tokio::spawn(async move {
+            let mut conn = try_connect(&socket, 3, 500).await?;
+            conn.exec(&script).await
+        }));
+        Ok(())
+    }
+
+    /// Whether a triggered step is running on `vm_name`.
+    fn step_running(&self, vm_name: &str) -> bool {
+        self.triggered_steps
+            .iter()
+            .any(|t| t.vm == vm_name && t.running.is_some())
+    }
+
+    /// Mark the triggered steps whose script finished as fired, logging the ones that failed.
+    /// A step the agent could not run stays pending so it can be triggered again.
+    async fn collect_finished_steps(&mut self) {
+        let finished: Vec<_> = self
+            .triggered_steps
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(idx, t)| {
+                t.running
+                    .take_if(|running| running.is_finished())
+                    .map(|running| (idx, running))
+            })
+            .collect();
+
+        for (idx, running) in finished {
+            let id = self.triggered_steps[idx].id();
+            let output = match running.await {
+                Ok(Ok(output)) => output,
+                Ok(Err(e)) => {
+                    warn!("Failed to trigger step {}: {}", id, e);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Step {} did not finish: {}", id, e);
+                    continue;
+                }
+            };
+            self.triggered_steps[idx].fired = true;
+            if let Err(e) = self.audit.record(
+                AuditOperation::StepTriggered,
+                format!("{id} exit={}", output.exit_code),
+            ) {
+                warn!("Failed to audit step {}: {}", id, e);
+            }
+            if output.exit_code != 0 {
+                warn!(
+                    "Step {} exited with status {}: {}",
+                    id,
+                    output.exit_code,
+                    output.stderr.trim()
+                );
+            }
+        }
+    }
+
+    async fn exec_step(&self, vm_name: &str, step: &VmStep) -> Result<ExecOutput, VmError> {
+        let script = render_triggered_step(vm_name, step)?;
+        let vm = self
+            .vms
+            .get(vm_name)
+            .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+        let mut conn = try_connect(&vm.serial_socket, 3, 500).await?;
+        conn.exec(&script).await
+    }
+
+    /// The `on_complete` hook [`ScenarioRunner::run_next_completion_hook`] runs next, e.g.
+    /// `host command 'notify-send done'`; `None` once every hook has run.
+    #[must_use]
+    pub fn next_completion_hook(&self) -> Option<String> {
+        self.pending_hooks.front().map(describe_hook)
+    }
+
+    /// Run the next queued `on_complete` hook. Failures are logged and do not undo completion;
+    /// host commands and webhooks the run may not use are skipped.
+    pub async fn run_next_completion_hook(&mut self) {
+        let Some(hook) = self.pending_hooks.pop_front() else {
+            return;
+        };
+        let result = match &hook {
+            CompletionAction::Vm { vm, step } => self.run_vm_hook(vm, step).await,
+            _ if self.paranoid || !self.host_hooks => {
+                warn!(
+                    "Skipping on_complete {}: host hooks are not allowed",
+                    describe_hook(&hook)
+                );
+                return;
+            }
+            CompletionAction::HostCommand { cmd } => self.run_host_hook(cmd).await,
+            CompletionAction::Webhook { url } => self.post_completion_webhook(url).await,
+        };
+        if let Err(e) = result {
+            warn!("on_complete hook failed: {}", e);
+        }
+    }
+
+    /// Run every queued `on_complete` hook, in order.
+    pub async fn run_completion_hooks(&mut self) {
+        while !self.pending_hooks.is_empty() {
+            self.run_next_completion_hook().await;
+        }
+    }
+
+    async fn run_vm_hook(&self, vm_name: &str, step: &VmStep) -> Result<(), VmError> {
+        let vm_def = self
+            .scenario
+            .vms
+            .iter()
+            .find(|v| v.name == vm_name)
+            .ok_or_else(|| VmError::VmNotFound(vm_name.to_string()))?;
+        let mgmt_ip = self
+            .vms
+            .get(vm_name)
+            .map(|vm| vm.mgmt_ip.clone())
+            .unwrap_or_default();
+        let steps = self
+            .template_vars(vm_def, &mgmt_ip)
+            .expand_steps(std::slice::from_ref(step))?;
+
+        let output = self.exec_step(vm_name, &steps[0]).await?;
+        if output.exit_code != 0 {
+            return Err(VmError::Hook(format!(
+                "actions on VM {vm_name} exited with status {}: {}",
+                output.exit_code,
+                output.stderr.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn run_host_hook(&self, cmd: &str) -> Result<(), VmError> {
+        let mut command = if cfg!(windows) {
+            let mut c = tokio::process::Command::new("cmd");
+            c.args(["/C", cmd]);
+            c
+        } else {
+            let mut c = tokio::process::Command::new("sh");
+            c.args(["-c", cmd]);
+            c
+        };
+        let status = command
+            .env("INTAR_SCENARIO", &self.scenario.name)
+            .env("INTAR_RUN_ID", self.run_id())
+            .env("INTAR_RUN_DIR", &self.work_dir)
+            .stdin(std::process::Stdio::null())
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(VmError::Hook(format!(
+                "host command '{cmd}' exited with {status}"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn post_completion_webhook(&self, url: &str) -> Result<(), VmError> {
+        let mut body = serde_json::json!({
+            "event": "scenario_completed",
+            "scenario": self.scenario.name,
+            "run_id": self.run_id(),
+        });
+        if let Some(learner) = &self.learner {
+            body["learner"] = serde_json::to_value(learner)?;
+        }
+        reqwest::Client::new()
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| VmError::Hook(format!("webhook {url} failed: {e}")))?;
+        Ok(())
+    }
+
+    /// Collect triggered steps that finished, then start delayed steps whose delay has elapsed
+    /// and any steps requested via `intar trigger`.
+    ///
+    /// `elapsed` is the time since the scenario became ready. Failures are logged rather than
+    /// returned so one broken step does not stop the session.
+    pub async fn fire_pending_triggers(&mut self, elapsed: Duration) {
+        self.collect_finished_steps().await;
+        let mut due: Vec<String> = self
+            .triggered_steps
+            .iter()
+            .filter(|t| {
+                !t.fired
+                    && t.running.is_none()
+                    && matches!(t.step.trigger, StepTrigger::After { secs }
+                        if elapsed >= Duration::from_secs(secs))
+            })
+            .map(TriggeredStep::id)
+            .collect();
+        due.extend(take_run_requests(&self.work_dir, TRIGGERS_DIR));
+
+        for name in due {
+            if let Err(e) = self.trigger_step(&name) {
+                warn!("Failed to trigger step {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Swap in the probes of `scenario`, a newer version of the running scenario with no
+    /// [`Scenario::topology_change`]. Results of probes a VM no longer lists are dropped; the
+    /// rest are replaced by the next probe check.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the run's scenario cannot be saved.
+    pub fn reload_probes(&mut self, scenario: &Scenario) -> Result<(), VmError> {
+        self.scenario.reload_probes(scenario);
+        for vm in &self.scenario.vms {
+            if let Some(results) = self.probe_results.get_mut(&vm.name) {
+                results.retain(|probe, _| vm.probes.contains(probe));
+            }
+        }
+        info!("Reloaded probes of scenario {}", self.scenario.name);
+        save_run_scenario(&self.work_dir, &self.scenario)
+    }
+
     fn clear_probe_results(&mut self) {
         self.probe_results.clear();
         for vm_name in self.vms.keys() {
@@ -1035,10 +3961,23 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
     pub async fn stop(&mut self) -> Result<(), VmError> {
         info!("Stopping scenario: {}", self.scenario.name);
 
-        for handle in self.action_tasks.drain(..) {
+        if let Err(e) = self
+            .audit
+            .record(AuditOperation::RunStopped, self.scenario.name.clone())
+        {
+            warn!("Failed to record run stop in audit log: {}", e);
+        }
+
+        for handle in self
+            .action_tasks
+            .drain(..)
+            .chain(self.qmp_event_tasks.drain(..))
+        {
             handle.abort();
         }
         self.action_rx = None;
+        self.qmp_event_rx = None;
+        self.qmp_event_tx = None;
 
         for (name, vm) in &mut self.vms {
             info!("Stopping VM: {}", name);
@@ -1049,6 +3988,7 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
         if let Some(mut switch) = self.lan_switch.take() {
             switch.stop();
         }
+        self.mock_services = None;
 
         Ok(())
     }
@@ -1058,6 +3998,12 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
     /// # Errors
     /// Returns `VmError` if filesystem cleanup fails.
     pub fn cleanup(&self) -> Result<(), VmError> {
+        if let Some(dir) = &self.ephemeral_dir
+            && let Err(e) = std::fs::remove_dir_all(dir)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove ephemeral disks {}: {}", dir.display(), e);
+        }
         if !self.work_dir.exists() {
             return Ok(());
         }
@@ -1077,11 +4023,78 @@ sysctl -p /etc/sysctl.d/99-intar-no-ipv6.conf 2>/dev/null || true
         Ok(())
     }
 
+    /// The learner profile stamped into this run, if someone was logged in when it started.
+    #[must_use]
+    pub fn learner(&self) -> Option<&LearnerProfile> {
+        self.learner.as_ref()
+    }
+
+    fn cloud_vm(&self, vm_def: &VmDefinition) -> Result<Option<CloudVm>, VmError> {
+        let Some(settings) = &self.cloud else {
+            return Ok(None);
+        };
+        let agent = self.agent_binary_for_arch(&settings.arch)?.clone();
+        Ok(Some(CloudVm::new(
+            settings.clone(),
+            &self.run_id(),
+            &vm_def.name,
+            &vm_def.image,
+            agent,
+            self.work_dir.join("id_ed25519"),
+        )))
+    }
+
+    fn libvirt_domain(&self, vm: &str) -> Option<LibvirtDomain> {
+        self.libvirt_uri
+            .as_deref()
+            .map(|uri| LibvirtDomain::new(uri, &self.run_id(), vm))
+    }
+
+    #[must_use]
+    pub fn run_id(&self) -> String {
+        self.work_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Arguments for `ssh` to log in to `vm_name`, accepting only the VM's recorded host key.
+    /// `None` if there is no such VM or its recorded key does not match the run's state.
+    #[must_use]
+    pub fn ssh_args(&self, vm_name: &str) -> Option<Vec<String>> {
+        let vm = self.vms.get(vm_name)?;
+        let host_key =
+            host_key_options(&self.work_dir, vm_name, vm.host_key_fingerprint.as_deref())
+                .map_err(|e| warn!("Not connecting to VM {vm_name}: {e}"))
+                .ok()?;
+        let key = self.work_dir.join("id_ed25519");
+        let mut args = vec![
+            "-i".to_string(),
+            key.display().to_string(),
+            "-p".to_string(),
+            vm.ssh_port.to_string(),
+        ];
+        args.extend(host_key);
+        args.extend(["-o".to_string(), "LogLevel=ERROR".to_string()]);
+        args.push("user@localhost".to_string());
+        Some(args)
+    }
+
     #[must_use]
     pub fn get_ssh_command(&self, vm_name: &str) -> Option<String> {
         self.vms.get(vm_name).map(|vm| {
+            let host_key = if vm.host_key_fingerprint.is_some() {
+                format!(
+                    "-o StrictHostKeyChecking=yes -o 'UserKnownHostsFile=\"{}\"' \
+                     -o HostKeyAlias={vm_name}",
+                    self.work_dir.join(KNOWN_HOSTS_FILE).display()
+                )
+            } else {
+                "-o StrictHostKeyChecking=no".to_string()
+            };
             format!(
-                "ssh -i {} -p {} -o StrictHostKeyChecking=no user@localhost",
+                "ssh -i {} -p {} {host_key} user@localhost",
                 self.work_dir.join("id_ed25519").display(),
                 vm.ssh_port
             )
@@ -1097,17 +4110,77 @@ impl Drop for ScenarioRunner {
     }
 }
 
-async fn wait_for_agent(socket: &HostSocket) -> Result<(), VmError> {
-    for _ in 0..60 {
+/// Poll until the agent answers; the caller bounds the wait.
+/// Forward the QMP events of `vm` to `tx` from a background task, or `None` if its control
+/// session cannot be opened.
+async fn follow_qmp_events(
+    vm: &QemuInstance,
+    tx: mpsc::Sender<QmpEvent>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let mut events = match vm.subscribe_qmp_events().await {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Failed to follow QMP events of VM {}: {}", vm.name, e);
+            return None;
+        }
+    };
+    let name = vm.name.clone();
+    Some(tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Missed {} QMP events of VM {}", missed, name);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }))
+}
+
+async fn wait_for_agent(socket: &HostSocket) {
+    loop {
         if let Ok(mut conn) = try_connect(socket, 1, 0).await
             && conn.ping().await.is_ok()
         {
-            return Ok(());
+            return;
         }
         tokio::time::sleep(Duration::from_secs(5)).await;
     }
+}
 
-    Err(VmError::Timeout("Agent did not become ready".into()))
+/// Check `probes` on `vm_name` through its agent: all in one request, or under author mode one
+/// at a time so `trace` gets each probe's timing and the agent's reply to it.
+async fn check_agent_probes(
+    conn: &mut AgentConnection,
+    vm_name: &str,
+    probes: Vec<(String, ProbeSpec)>,
+    trace: Option<&mut ProbeTrace>,
+) -> Result<Vec<ProbeResult>, VmError> {
+    let Some(trace) = trace else {
+        return conn.check_all(probes).await;
+    };
+    let mut results = Vec::with_capacity(probes.len());
+    for (id, spec) in probes {
+        let started = std::time::Instant::now();
+        match conn.check_probe(&id, &spec).await {
+            Ok(result) => {
+                let raw = conn.last_response().to_string();
+                trace.record(vm_name, &result, started.elapsed(), raw);
+                results.push(result);
+            }
+            Err(e) => {
+                let result = ProbeResult::fail(id, format!("Agent error: {e}"));
+                trace.record(vm_name, &result, started.elapsed(), String::new());
+                return Err(e);
+            }
+        }
+    }
+    Ok(results)
 }
 
 fn generate_ssh_keypair(work_dir: &Path) -> Result<(String, String), VmError> {
@@ -1146,7 +4219,9 @@ fn generate_ssh_keypair(work_dir: &Path) -> Result<(String, String), VmError> {
     Ok((private_key, public_key))
 }
 
-fn detect_arch() -> String {
+/// Architecture of the host, as QEMU and image `source` lookups name it.
+#[must_use]
+pub fn detect_arch() -> String {
     #[cfg(target_arch = "x86_64")]
     return "x86_64".to_string();
 
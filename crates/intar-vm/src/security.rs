@@ -0,0 +1,364 @@
+use crate::{IntarDirs, RunState, VmError};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Group whose members may send recorded sessions to the guest agent in `--paranoid` runs;
+/// other runs let every guest user write to its socket.
+pub const ACTIONS_GROUP: &str = "intar-actions";
+
+/// Something QEMU listens on for a VM, as found on its command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostListener {
+    /// A TCP or UDP port, on every interface when `host` is `None`.
+    Inet {
+        protocol: &'static str,
+        host: Option<IpAddr>,
+        port: u16,
+    },
+    /// A unix socket.
+    Unix(PathBuf),
+}
+
+impl HostListener {
+    /// Whether only this host can reach the listener: a loopback address or a unix socket.
+    #[must_use]
+    pub fn is_local(&self) -> bool {
+        match self {
+            Self::Inet { host, .. } => host.is_some_and(|host| host.is_loopback()),
+            Self::Unix(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for HostListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inet {
+                protocol,
+                host: Some(host),
+                port,
+            } => write!(f, "{protocol} {host}:{port}"),
+            Self::Inet {
+                protocol,
+                host: None,
+                port,
+            } => write!(f, "{protocol} port {port} on all interfaces"),
+            Self::Unix(path) => write!(f, "unix socket {}", path.display()),
+        }
+    }
+}
+
+/// The host endpoints QEMU listens on when run with `args`: user-network port forwards,
+/// server-mode socket chardevs and QMP monitors, and the local ends of UDP LAN links.
+#[must_use]
+pub fn qemu_listeners(args: &[String]) -> Vec<HostListener> {
+    let mut listeners = Vec::new();
+    for arg in args {
+        let options: Vec<&str> = arg.split(',').collect();
+        let option = |key: &str| {
+            options
+                .iter()
+                .find_map(|option| option.strip_prefix(key)?.strip_prefix('='))
+        };
+        let server = options
+            .iter()
+            .any(|option| matches!(*option, "server" | "server=on"));
+        match options[0] {
+            "socket" if server => match (option("path"), option("port")) {
+                (Some(path), _) => listeners.push(HostListener::Unix(PathBuf::from(path))),
+                (None, Some(port)) => listeners.extend(inet("tcp", option("host"), port)),
+                (None, None) => {}
+            },
+            "dgram" if option("local.type") == Some("inet") => {
+                let port = option("local.port").or_else(|| option("remote.port"));
+                if let Some(port) = port {
+                    listeners.extend(inet("udp", option("local.host"), port));
+                }
+            }
+            first if server => {
+                if let Some(path) = first.strip_prefix("unix:") {
+                    listeners.push(HostListener::Unix(PathBuf::from(path)));
+                } else if let Some((host, port)) = first
+                    .strip_prefix("tcp:")
+                    .and_then(|addr| addr.rsplit_once(':'))
+                {
+                    listeners.extend(inet("tcp", Some(host), port));
+                }
+            }
+            _ => {}
+        }
+        // `hostfwd=[tcp|udp]:[hostaddr]:hostport-[guestaddr]:guestport`
+        for forward in options.iter().filter_map(|o| o.strip_prefix("hostfwd=")) {
+            let Some((protocol, rest)) = forward.split_once(':') else {
+                continue;
+            };
+            let protocol = if protocol == "udp" { "udp" } else { "tcp" };
+            let host_side = rest
+                .split_once('-')
+                .map_or(rest, |(host_side, _)| host_side);
+            if let Some((host, port)) = host_side.rsplit_once(':') {
+                listeners.extend(inet(protocol, Some(host), port));
+            }
+        }
+    }
+    listeners
+}
+
+fn inet(protocol: &'static str, host: Option<&str>, port: &str) -> Option<HostListener> {
+    Some(HostListener::Inet {
+        protocol,
+        host: host
+            .filter(|host| !host.is_empty())
+            .and_then(|host| host.parse().ok()),
+        port: port.parse().ok()?,
+    })
+}
+
+/// Check that `listeners` can only be reached from this host, with every unix socket in
+/// `socket_dir` and that directory private to the user (`intar start --paranoid`).
+///
+/// # Errors
+/// Returns `VmError::NotPermitted` naming the first listener others could reach.
+pub fn check_local_only(listeners: &[HostListener], socket_dir: &Path) -> Result<(), VmError> {
+    let refuse = |listener: &HostListener, why: &str| {
+        Err(VmError::NotPermitted(format!(
+            "--paranoid refuses to start QEMU with {listener}, {why}"
+        )))
+    };
+    for listener in listeners {
+        if !listener.is_local() {
+            return refuse(listener, "which other hosts can reach");
+        }
+        if let HostListener::Unix(path) = listener {
+            if path.parent() != Some(socket_dir) {
+                return refuse(listener, "which is outside the run directory");
+            }
+            if !is_private_dir(socket_dir) {
+                return refuse(listener, "whose directory other users can enter");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether only the owner can enter `dir`.
+#[must_use]
+pub fn is_private_dir(dir: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        dir.metadata()
+            .is_ok_and(|meta| meta.is_dir() && meta.permissions().mode() & 0o077 == 0)
+    }
+    #[cfg(not(unix))]
+    {
+        dir.is_dir()
+    }
+}
+
+/// Make `dir` private to the user, as `--paranoid` does with the run directory.
+///
+/// # Errors
+/// Returns `VmError::Io` if the permissions cannot be changed.
+pub fn make_private_dir(dir: &Path) -> Result<(), VmError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// One line of the security summary `intar doctor` prints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityCheck {
+    /// What was checked, e.g. `run calm-otter-1234`.
+    pub subject: String,
+    /// Whether it only exposes what `--paranoid` allows.
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// What intar exposes on this host: the runs directory and, for each run, its SSH forwards,
+/// its sockets, and who may write to the agent in its guests.
+#[must_use]
+pub fn security_summary(dirs: &IntarDirs) -> Vec<SecurityCheck> {
+    let runs_dir = dirs.runs_dir();
+    let private = is_private_dir(&runs_dir);
+    let mut checks = vec![SecurityCheck {
+        subject: "runs directory".into(),
+        ok: private,
+        detail: if private {
+            format!("{} is private", runs_dir.display())
+        } else {
+            format!(
+                "other users can list the runs in {}; each run's directory guards its own sockets",
+                runs_dir.display()
+            )
+        },
+    }];
+    let mut runs: Vec<PathBuf> = std::fs::read_dir(&runs_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|dir| dir.join("state.json").is_file())
+        .collect();
+    runs.sort();
+    for run_dir in runs {
+        let Ok(state) = RunState::load(&run_dir) else {
+            continue;
+        };
+        let name = run_dir
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        checks.extend(run_checks(&name, &run_dir, &state));
+    }
+    checks
+}
+
+fn run_checks(name: &str, run_dir: &Path, state: &RunState) -> Vec<SecurityCheck> {
+    let subject = format!("run {name}");
+    let ports = state
+        .vms
+        .iter()
+        .map(|vm| vm.ssh_port.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    // Tunnels of remote and cloud runs bind ssh's default, loopback.
+    let ssh_local = state.paranoid || state.remote.is_some() || state.cloud.is_some();
+    let mut checks = vec![SecurityCheck {
+        subject: subject.clone(),
+        ok: ssh_local,
+        detail: if ssh_local {
+            format!("SSH forwards ({ports}) listen on 127.0.0.1")
+        } else {
+            format!(
+                "SSH forwards ({ports}) listen on all interfaces; the guests accept only the \
+                 run's key"
+            )
+        },
+    }];
+    let private = is_private_dir(run_dir);
+    checks.push(SecurityCheck {
+        subject: subject.clone(),
+        ok: private,
+        detail: if private {
+            "QMP, serial, and actions sockets are in a private directory".into()
+        } else {
+            format!(
+                "other users can enter {}, which holds the QMP, serial, and actions sockets",
+                run_dir.display()
+            )
+        },
+    });
+    checks.push(SecurityCheck {
+        subject,
+        ok: state.paranoid,
+        detail: if state.paranoid {
+            format!("only members of {ACTIONS_GROUP} can send recorded sessions in the guests")
+        } else {
+            "every guest user can send recorded sessions to the agent".into()
+        },
+    });
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_qemu_listeners() {
+        let listeners = qemu_listeners(&args(&[
+            "-netdev",
+            "user,id=net0,net=10.0.2.0/24,hostfwd=tcp::2201-10.0.2.15:22,\
+             hostfwd=tcp:127.0.0.1:40001-10.0.2.15:6443",
+            "-netdev",
+            "dgram,id=net1,local.type=inet,local.host=127.0.0.1,local.port=5001,\
+             remote.type=inet,remote.host=127.0.0.1,remote.port=5000",
+            "-chardev",
+            "socket,id=agent,path=/runs/lab/web-serial.sock,server=on,wait=off",
+            "-chardev",
+            "socket,id=actions,host=127.0.0.1,port=40002,server=on,wait=off",
+            "-chardev",
+            "file,id=console,path=/runs/lab/logs/web/console.log",
+            "-qmp",
+            "unix:/runs/lab/web-qmp.sock,server,nowait",
+            "-qmp",
+            "tcp:0.0.0.0:4444,server,nowait",
+        ]));
+        let loopback = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            listeners,
+            vec![
+                HostListener::Inet {
+                    protocol: "tcp",
+                    host: None,
+                    port: 2201
+                },
+                HostListener::Inet {
+                    protocol: "tcp",
+                    host: loopback,
+                    port: 40001
+                },
+                HostListener::Inet {
+                    protocol: "udp",
+                    host: loopback,
+                    port: 5001
+                },
+                HostListener::Unix("/runs/lab/web-serial.sock".into()),
+                HostListener::Inet {
+                    protocol: "tcp",
+                    host: loopback,
+                    port: 40002
+                },
+                HostListener::Unix("/runs/lab/web-qmp.sock".into()),
+                HostListener::Inet {
+                    protocol: "tcp",
+                    host: Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+                    port: 4444
+                },
+            ]
+        );
+        assert_eq!(listeners[0].to_string(), "tcp port 2201 on all interfaces");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_local_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = HostListener::Unix(dir.path().join("web-qmp.sock"));
+        let forward = HostListener::Inet {
+            protocol: "tcp",
+            host: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            port: 2201,
+        };
+        make_private_dir(dir.path()).unwrap();
+        check_local_only(&[forward.clone(), socket.clone()], dir.path()).unwrap();
+
+        let open = HostListener::Inet {
+            protocol: "tcp",
+            host: None,
+            port: 2201,
+        };
+        let err = check_local_only(&[open], dir.path()).unwrap_err();
+        assert!(err.to_string().contains("other hosts"));
+
+        let elsewhere = HostListener::Unix("/tmp/web-qmp.sock".into());
+        assert!(check_local_only(&[elsewhere], dir.path()).is_err());
+
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(!is_private_dir(dir.path()));
+        assert!(check_local_only(&[socket], dir.path()).is_err());
+    }
+}
@@ -1,14 +1,28 @@
 use crate::{HostSocket, VmError, connect_host_socket};
-use intar_probes::{ProbeResult, ProbeSpec, Request, Response};
+use intar_probes::{FileManifest, ProbeResult, ProbeSpec, Request, Response, SudoInvocation};
 use std::time::Instant;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::time::{Duration, timeout};
 
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const EXEC_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Output of a script run through the agent `exec` request.
+#[derive(Debug, Clone)]
+pub struct ExecOutput {
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum ExpectedResponse {
     Pong,
     ProbeResult,
     AllResults,
+    ExecResult,
+    SudoLog,
+    FileManifest,
 }
 
 impl ExpectedResponse {
@@ -17,12 +31,16 @@ impl ExpectedResponse {
             ExpectedResponse::Pong => matches!(response, Response::Pong { .. }),
             ExpectedResponse::ProbeResult => matches!(response, Response::ProbeResult { .. }),
             ExpectedResponse::AllResults => matches!(response, Response::AllResults { .. }),
+            ExpectedResponse::ExecResult => matches!(response, Response::ExecResult { .. }),
+            ExpectedResponse::SudoLog => matches!(response, Response::SudoLog { .. }),
+            ExpectedResponse::FileManifest => matches!(response, Response::FileManifest { .. }),
         }
     }
 }
 
 pub struct AgentConnection {
     stream: BufReader<crate::HostStream>,
+    last_response: String,
 }
 
 impl AgentConnection {
@@ -35,16 +53,23 @@ impl AgentConnection {
 
         Ok(Self {
             stream: BufReader::new(stream),
+            last_response: String::new(),
         })
     }
 
+    /// The line the agent answered the last request with, verbatim.
+    #[must_use]
+    pub fn last_response(&self) -> &str {
+        &self.last_response
+    }
+
     /// Send a ping request to the agent.
     ///
     /// # Errors
     /// Returns `VmError` when the agent does not respond or replies with an error.
     pub async fn ping(&mut self) -> Result<u64, VmError> {
         let response = self
-            .send_request_expect(&Request::Ping, ExpectedResponse::Pong)
+            .send_request_expect(&Request::Ping, ExpectedResponse::Pong, REQUEST_TIMEOUT)
             .await?;
 
         let Response::Pong { uptime_secs } = response else {
@@ -69,13 +94,14 @@ impl AgentConnection {
         };
 
         let response = self
-            .send_request_expect(&request, ExpectedResponse::ProbeResult)
+            .send_request_expect(&request, ExpectedResponse::ProbeResult, REQUEST_TIMEOUT)
             .await?;
 
         let Response::ProbeResult {
             id,
             passed,
             message,
+            details,
         } = response
         else {
             return Err(VmError::Serial("Unexpected response to check_probe".into()));
@@ -85,6 +111,7 @@ impl AgentConnection {
             id,
             passed,
             message,
+            details,
         })
     }
 
@@ -98,7 +125,7 @@ impl AgentConnection {
     ) -> Result<Vec<ProbeResult>, VmError> {
         let request = Request::CheckAll { probes };
         let response = self
-            .send_request_expect(&request, ExpectedResponse::AllResults)
+            .send_request_expect(&request, ExpectedResponse::AllResults, REQUEST_TIMEOUT)
             .await?;
 
         let Response::AllResults { results } = response else {
@@ -108,6 +135,75 @@ impl AgentConnection {
         Ok(results)
     }
 
+    /// Run a shell script as root inside the guest and wait for it to finish.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the agent cannot run the script or the request times out.
+    pub async fn exec(&mut self, script: &str) -> Result<ExecOutput, VmError> {
+        let request = Request::Exec {
+            script: script.to_string(),
+        };
+        let response = self
+            .send_request_expect(&request, ExpectedResponse::ExecResult, EXEC_TIMEOUT)
+            .await?;
+
+        let Response::ExecResult {
+            exit_code,
+            stdout,
+            stderr,
+        } = response
+        else {
+            return Err(VmError::Serial("Unexpected response to exec".into()));
+        };
+
+        Ok(ExecOutput {
+            exit_code,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Every sudo invocation in the guest since it booted.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the agent cannot read the sudo log or the request fails.
+    pub async fn sudo_log(&mut self) -> Result<Vec<SudoInvocation>, VmError> {
+        let response = self
+            .send_request_expect(
+                &Request::SudoLog,
+                ExpectedResponse::SudoLog,
+                REQUEST_TIMEOUT,
+            )
+            .await?;
+
+        let Response::SudoLog { invocations } = response else {
+            return Err(VmError::Serial("Unexpected response to sudo_log".into()));
+        };
+
+        Ok(invocations)
+    }
+
+    /// Hash every file under `paths` in the guest.
+    ///
+    /// # Errors
+    /// Returns `VmError` if the request fails.
+    pub async fn file_manifest(&mut self, paths: &[String]) -> Result<FileManifest, VmError> {
+        let request = Request::FileManifest {
+            paths: paths.to_vec(),
+        };
+        let response = self
+            .send_request_expect(&request, ExpectedResponse::FileManifest, EXEC_TIMEOUT)
+            .await?;
+
+        let Response::FileManifest { manifest } = response else {
+            return Err(VmError::Serial(
+                "Unexpected response to file_manifest".into(),
+            ));
+        };
+
+        Ok(manifest)
+    }
+
     /// Send a request over the serial socket and wait for the expected response.
     ///
     /// # Errors
@@ -116,6 +212,7 @@ impl AgentConnection {
         &mut self,
         request: &Request,
         expected: ExpectedResponse,
+        limit: Duration,
     ) -> Result<Response, VmError> {
         let request_json = serde_json::to_string(request)?;
 
@@ -131,7 +228,7 @@ impl AgentConnection {
             .await
             .map_err(|e| VmError::Serial(format!("Failed to flush: {e}")))?;
 
-        let deadline = Instant::now() + Duration::from_secs(30);
+        let deadline = Instant::now() + limit;
 
         loop {
             let now = Instant::now();
@@ -164,7 +261,10 @@ impl AgentConnection {
 
             match response {
                 Response::Error { message } => return Err(VmError::Serial(message)),
-                other if expected.matches(&other) => return Ok(other),
+                other if expected.matches(&other) => {
+                    self.last_response = line.to_string();
+                    return Ok(other);
+                }
                 _ => {}
             }
         }
@@ -0,0 +1,134 @@
+use crate::{ProbeTarget, VmError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::process::Command;
+
+/// A host intar drives with the system `ssh`: where `intar migrate-run` moves a run to, and
+/// the server of `intar start --remote`. Written `ssh://[user@]host[:port]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SshHost {
+    /// `[user@]host`, as `ssh` takes it.
+    pub destination: String,
+    pub port: Option<u16>,
+}
+
+impl FromStr for SshHost {
+    type Err = VmError;
+
+    /// Parse `ssh://[user@]host[:port]`.
+    fn from_str(host: &str) -> Result<Self, VmError> {
+        match host.parse::<ProbeTarget>() {
+            Ok(ProbeTarget::Ssh { destination, port }) => Ok(Self { destination, port }),
+            _ => Err(VmError::Remote(format!(
+                "'{host}' is not a host like ssh://user@host or ssh://user@host:2222"
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for SshHost {
+    type Error = VmError;
+
+    fn try_from(host: String) -> Result<Self, VmError> {
+        host.parse()
+    }
+}
+
+impl From<SshHost> for String {
+    fn from(host: SshHost) -> Self {
+        host.to_string()
+    }
+}
+
+impl fmt::Display for SshHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ssh://{}", self.destination)?;
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
+impl SshHost {
+    /// `ssh` set up to never prompt, with the port but not yet the destination, so options
+    /// such as forwards can still be added before it.
+    #[must_use]
+    pub fn command(&self) -> Command {
+        let mut ssh = Command::new("ssh");
+        ssh.args(self.options());
+        ssh
+    }
+
+    /// The `ssh` invocation rsync should use to reach this host.
+    #[must_use]
+    pub fn rsync_shell(&self) -> String {
+        std::iter::once("ssh".to_string())
+            .chain(self.options())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Run `command` on this host and return its stdout.
+    ///
+    /// # Errors
+    /// Returns `VmError::Remote` if ssh cannot be run or the command fails.
+    pub async fn run(&self, command: &str) -> Result<String, VmError> {
+        let output = self
+            .command()
+            .arg(&self.destination)
+            .arg(command)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| VmError::Remote(format!("Failed to run ssh: {e}")))?;
+        if !output.status.success() {
+            return Err(VmError::Remote(format!(
+                "`{command}` on {} failed ({}): {}",
+                self.destination,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn options(&self) -> Vec<String> {
+        let mut options: Vec<String> = ["-o", "BatchMode=yes", "-o", "ConnectTimeout=10"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        if let Some(port) = self.port {
+            options.extend(["-p".to_string(), port.to_string()]);
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_host() {
+        let host: SshHost = "ssh://lab@server.example:2222".parse().unwrap();
+        assert_eq!(
+            host,
+            SshHost {
+                destination: "lab@server.example".into(),
+                port: Some(2222),
+            }
+        );
+        assert_eq!(host.to_string(), "ssh://lab@server.example:2222");
+        assert_eq!(
+            host.rsync_shell(),
+            "ssh -o BatchMode=yes -o ConnectTimeout=10 -p 2222"
+        );
+
+        let err = "server.example".parse::<SshHost>().unwrap_err();
+        assert!(matches!(err, VmError::Remote(_)));
+    }
+}
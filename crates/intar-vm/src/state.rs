@@ -1,11 +1,21 @@
+use crate::QEMU_EXITED_EVENT;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VmState {
+    /// Not started yet: queued for a later boot wave, or until the VMs it `wait_for`s pass
+    /// their boot probes.
+    Waiting,
     Starting,
     Booting,
     CloudInit,
     Ready,
+    /// Stopped by QEMU (`stop`, or an I/O error with `werror=stop`); resumes where it left off.
+    Paused,
+    /// Powered off by the guest.
+    Stopped,
+    /// The guest kernel panicked (reported through the pvpanic device).
+    Panicked,
     Error,
 }
 
@@ -16,21 +26,45 @@ impl VmState {
             VmState::Starting => (1, 4),
             VmState::Booting => (2, 4),
             VmState::CloudInit => (3, 4),
-            VmState::Ready => (4, 4),
-            VmState::Error => (0, 4),
+            VmState::Ready | VmState::Paused => (4, 4),
+            VmState::Waiting | VmState::Stopped | VmState::Panicked | VmState::Error => (0, 4),
         }
     }
 
     #[must_use]
     pub fn label(&self) -> &'static str {
         match self {
+            VmState::Waiting => "Queued",
             VmState::Starting => "Starting",
             VmState::Booting => "Booting",
             VmState::CloudInit => "Cloud-init",
             VmState::Ready => "Ready",
+            VmState::Paused => "Paused",
+            VmState::Stopped => "Stopped",
+            VmState::Panicked => "Guest panicked",
             VmState::Error => "Error",
         }
     }
+
+    /// State after the QMP event `event` (e.g. `STOP`), or `None` if it does not change it.
+    /// `resumed` is the state to return to on `RESUME`. Failures stick until the guest resets.
+    #[must_use]
+    pub fn after_qmp_event(self, event: &str, resumed: VmState) -> Option<VmState> {
+        match (self, event) {
+            (VmState::Stopped, QEMU_EXITED_EVENT) => None,
+            (_, QEMU_EXITED_EVENT) => Some(VmState::Error),
+            (_, "GUEST_PANICKED") => Some(VmState::Panicked),
+            (_, "BLOCK_IO_ERROR") => Some(VmState::Error),
+            (_, "SHUTDOWN") => Some(VmState::Stopped),
+            (_, "RESET") => Some(VmState::Booting),
+            (VmState::Stopped | VmState::Panicked | VmState::Error | VmState::Paused, "STOP") => {
+                None
+            }
+            (_, "STOP") => Some(VmState::Paused),
+            (VmState::Paused, "RESUME") => Some(resumed),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -0,0 +1,162 @@
+use crate::VmError;
+use intar_core::{CloudInitConfig, VmAction, VmStep};
+use std::collections::BTreeMap;
+
+const NAMESPACES: [&str; 4] = ["vm.", "vms.", "scenario.", "clusters."];
+
+/// Built-in `{{...}}` variables available to step and cloud-init content for one VM.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars {
+    values: BTreeMap<String, String>,
+}
+
+impl TemplateVars {
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(key.into(), value.into());
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Expand `{{vm.*}}`, `{{vms.<name>.*}}`, `{{scenario.*}}`, and `{{clusters.<name>.*}}`
+    /// references in `input`.
+    ///
+    /// Placeholders outside those namespaces (e.g. Go templates or kubectl jsonpath) are left
+    /// untouched.
+    ///
+    /// # Errors
+    /// Returns `VmError::CloudInit` if a placeholder in a known namespace has no value.
+    pub fn expand(&self, input: &str) -> Result<String, VmError> {
+        let mut out = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find("{{") {
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                break;
+            };
+
+            out.push_str(&rest[..start]);
+            let key = after[..end].trim();
+            if NAMESPACES.iter().any(|ns| key.starts_with(ns)) {
+                let value = self.get(key).ok_or_else(|| {
+                    VmError::CloudInit(format!("Unknown template variable '{key}'"))
+                })?;
+                out.push_str(value);
+            } else {
+                out.push_str(&rest[start..start + 2 + end + 2]);
+            }
+            rest = &after[end + 2..];
+        }
+
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Expand templates in `file_write` content and `command` actions of `steps`.
+    ///
+    /// # Errors
+    /// Returns `VmError::CloudInit` if any action references an unknown variable.
+    pub fn expand_steps(&self, steps: &[VmStep]) -> Result<Vec<VmStep>, VmError> {
+        steps
+            .iter()
+            .map(|step| {
+                let actions = step
+                    .actions
+                    .iter()
+                    .map(|action| self.expand_action(action))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(VmStep {
+                    name: step.name.clone(),
+                    actions,
+                    trigger: step.trigger,
+                })
+            })
+            .collect()
+    }
+
+    /// Expand templates in user-provided `runcmd` and text `write_file` content.
+    ///
+    /// # Errors
+    /// Returns `VmError::CloudInit` if any entry references an unknown variable.
+    pub fn expand_cloud_init(&self, config: &mut CloudInitConfig) -> Result<(), VmError> {
+        if let Some(runcmd) = config.runcmd.as_mut() {
+            *runcmd = self.expand(runcmd)?;
+        }
+        for file in config.write_files.iter_mut().filter(|file| !file.binary) {
+            file.content = self.expand(&file.content)?;
+        }
+        Ok(())
+    }
+
+    fn expand_action(&self, action: &VmAction) -> Result<VmAction, VmError> {
+        Ok(match action {
+            VmAction::FileWrite {
+                path,
+                content,
+                permissions,
+            } => VmAction::FileWrite {
+                path: path.clone(),
+                content: self.expand(content)?,
+                permissions: permissions.clone(),
+            },
+            VmAction::Command { cmd } => VmAction::Command {
+                cmd: self.expand(cmd)?,
+            },
+            VmAction::CronEntry {
+                name,
+                schedule,
+                command,
+                user,
+            } => VmAction::CronEntry {
+                name: name.clone(),
+                schedule: schedule.clone(),
+                command: self.expand(command)?,
+                user: user.clone(),
+            },
+            other => other.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> TemplateVars {
+        let mut vars = TemplateVars::default();
+        vars.insert("vm.name", "db-1");
+        vars.insert("vm.ip", "10.11.0.10");
+        vars.insert("vms.db-2.ip", "10.11.0.11");
+        vars.insert("scenario.run_id", "calm-otter-1234");
+        vars.insert("clusters.lab.token", "s3cr3t");
+        vars
+    }
+
+    #[test]
+    fn test_expand_known_variables() {
+        let out = vars()
+            .expand(
+                "node={{vm.name}} ip={{ vm.ip }} peer={{vms.db-2.ip}} run={{scenario.run_id}} \
+                 token={{clusters.lab.token}}",
+            )
+            .unwrap();
+        assert_eq!(
+            out,
+            "node=db-1 ip=10.11.0.10 peer=10.11.0.11 run=calm-otter-1234 token=s3cr3t"
+        );
+    }
+
+    #[test]
+    fn test_expand_leaves_foreign_placeholders() {
+        let input = "kubectl get pod -o go-template='{{.status.phase}}' {{";
+        assert_eq!(vars().expand(input).unwrap(), input);
+    }
+
+    #[test]
+    fn test_expand_rejects_unknown_variable() {
+        assert!(vars().expand("{{vms.db-9.ip}}").is_err());
+    }
+}
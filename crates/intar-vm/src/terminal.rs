@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+/// Where [`VtParser`] is within an escape sequence.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    #[default]
+    Ground,
+    /// After ESC.
+    Escape,
+    /// After ESC and intermediate bytes, e.g. `ESC ( B`.
+    EscapeIntermediate,
+    /// Control sequence (`ESC [`), up to its final byte.
+    Csi,
+    /// Operating system command (`ESC ]`), up to BEL or ST.
+    Osc,
+    /// DCS, SOS, PM, or APC string, up to ST.
+    String,
+    /// ESC inside an OSC or string, which is ST when followed by `\`.
+    StringEscape,
+}
+
+/// What [`VtParser`] found in a raw terminal stream, in stream order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VtAction<'a> {
+    /// A printable character.
+    Print(char),
+    /// A C0 control such as CR, LF, backspace, or tab, or DEL.
+    Execute(u8),
+    /// A control sequence with its parameter bytes, e.g. `?1049` and `h`.
+    Csi { params: &'a str, final_byte: u8 },
+}
+
+/// Splits a raw terminal stream into characters, controls, and control sequences. Other escape
+/// sequences (OSC, DCS and other strings, and two-byte escapes) are dropped whole, and UTF-8 is
+/// decoded across chunk boundaries.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct VtParser {
+    state: EscapeState,
+    /// Start of a UTF-8 character whose remaining bytes are in the next chunk.
+    utf8: Vec<u8>,
+    /// Parameter bytes of the current control sequence.
+    params: String,
+}
+
+impl VtParser {
+    /// Feed `chunk`, calling `on_action` with everything found in it.
+    pub(crate) fn feed(&mut self, chunk: &[u8], mut on_action: impl FnMut(VtAction<'_>)) {
+        for &b in chunk {
+            match self.state {
+                EscapeState::Ground => self.ground(b, &mut on_action),
+                EscapeState::Escape => self.escape(b),
+                EscapeState::EscapeIntermediate => {
+                    if !(0x20..=0x2f).contains(&b) {
+                        self.state = EscapeState::Ground;
+                    }
+                }
+                EscapeState::Csi => match b {
+                    0x1b => self.state = EscapeState::Escape,
+                    0x40..=0x7e => {
+                        self.state = EscapeState::Ground;
+                        on_action(VtAction::Csi {
+                            params: &self.params,
+                            final_byte: b,
+                        });
+                    }
+                    // CAN and SUB abort the sequence.
+                    0x18 | 0x1a => self.state = EscapeState::Ground,
+                    0x20..=0x3f => self.params.push(char::from(b)),
+                    _ => {}
+                },
+                EscapeState::Osc => match b {
+                    0x07 => self.state = EscapeState::Ground,
+                    0x1b => self.state = EscapeState::StringEscape,
+                    _ => {}
+                },
+                EscapeState::String => {
+                    if b == 0x1b {
+                        self.state = EscapeState::StringEscape;
+                    }
+                }
+                EscapeState::StringEscape => {
+                    if b == b'\\' {
+                        self.state = EscapeState::Ground;
+                    } else {
+                        self.escape(b);
+                    }
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self, b: u8) {
+        self.params.clear();
+        self.state = match b {
+            b'[' => EscapeState::Csi,
+            b']' => EscapeState::Osc,
+            b'P' | b'X' | b'^' | b'_' => EscapeState::String,
+            0x1b => EscapeState::Escape,
+            0x20..=0x2f => EscapeState::EscapeIntermediate,
+            _ => EscapeState::Ground,
+        };
+    }
+
+    fn ground(&mut self, b: u8, on_action: &mut impl FnMut(VtAction<'_>)) {
+        if b >= 0x80 {
+            self.utf8.push(b);
+            match std::str::from_utf8(&self.utf8) {
+                Ok(text) => {
+                    text.chars().for_each(|c| on_action(VtAction::Print(c)));
+                    self.utf8.clear();
+                }
+                // Wait for the rest of the character.
+                Err(e) if e.error_len().is_none() => {}
+                Err(_) => self.utf8.clear(),
+            }
+            return;
+        }
+        // A character cut short by anything else is dropped.
+        self.utf8.clear();
+
+        match b {
+            0x1b => self.state = EscapeState::Escape,
+            0x20..=0x7e => on_action(VtAction::Print(char::from(b))),
+            _ => on_action(VtAction::Execute(b)),
+        }
+    }
+}
+
+/// Whether the control sequence `params` + `final_byte` enters (`true`) or leaves the
+/// alternate screen (DEC private modes 47, 1047, and 1049).
+pub(crate) fn alt_screen_switch(params: &str, final_byte: u8) -> Option<bool> {
+    let enter = match final_byte {
+        b'h' => true,
+        b'l' => false,
+        _ => return None,
+    };
+    let modes = params.strip_prefix('?')?;
+    modes
+        .split(';')
+        .any(|mode| matches!(mode, "47" | "1047" | "1049"))
+        .then_some(enter)
+}
+
+/// The visible screen of an SSH session, rebuilt from its raw output so the TUI can show what
+/// the learner sees. Only text and cursor placement are kept: colors and attributes are
+/// dropped, which is enough to follow a shell, an editor, or a pager.
+#[derive(Debug, Clone)]
+pub struct SessionScreen {
+    width: usize,
+    height: usize,
+    grid: Vec<Vec<char>>,
+    /// The main screen while a full-screen program has the alternate one.
+    main_grid: Option<Vec<Vec<char>>>,
+    row: usize,
+    col: usize,
+    saved_cursor: (usize, usize),
+    parser: VtParser,
+    active: bool,
+    updated_at: Instant,
+}
+
+impl SessionScreen {
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        let width = usize::from(width.max(1));
+        let height = usize::from(height.max(1));
+        Self {
+            width,
+            height,
+            grid: vec![vec![' '; width]; height],
+            main_grid: None,
+            row: 0,
+            col: 0,
+            saved_cursor: (0, 0),
+            parser: VtParser::default(),
+            active: true,
+            updated_at: Instant::now(),
+        }
+    }
+
+    /// Apply a chunk of session output.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut parser = std::mem::take(&mut self.parser);
+        parser.feed(chunk, |action| self.apply(action));
+        self.parser = parser;
+        self.updated_at = Instant::now();
+    }
+
+    /// The screen's rows, without trailing blanks.
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        self.grid
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    /// Cursor position as `(row, column)`.
+    #[must_use]
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col.min(self.width - 1))
+    }
+
+    /// Size as `(width, height)`.
+    #[must_use]
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Whether the session is still open.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// When output last arrived.
+    #[must_use]
+    pub fn updated_at(&self) -> Instant {
+        self.updated_at
+    }
+
+    fn apply(&mut self, action: VtAction<'_>) {
+        match action {
+            VtAction::Print(c) => self.print(c),
+            VtAction::Execute(b'\r') => self.col = 0,
+            VtAction::Execute(b'\n' | 0x0b | 0x0c) => self.line_feed(),
+            VtAction::Execute(0x08) => self.col = self.col.min(self.width - 1).saturating_sub(1),
+            VtAction::Execute(b'\t') => self.col = ((self.col / 8 + 1) * 8).min(self.width - 1),
+            VtAction::Execute(_) => {}
+            VtAction::Csi { params, final_byte } => self.csi(params, final_byte),
+        }
+    }
+
+    fn print(&mut self, c: char) {
+        if self.col >= self.width {
+            self.col = 0;
+            self.line_feed();
+        }
+        self.grid[self.row][self.col] = c;
+        self.col += 1;
+    }
+
+    fn line_feed(&mut self) {
+        if self.row + 1 < self.height {
+            self.row += 1;
+        } else {
+            self.grid.remove(0);
+            self.grid.push(vec![' '; self.width]);
+        }
+    }
+
+    fn csi(&mut self, params: &str, final_byte: u8) {
+        if let Some(enter) = alt_screen_switch(params, final_byte) {
+            self.switch_screen(enter);
+            return;
+        }
+        if params.starts_with(['?', '>', '<', '=']) {
+            return;
+        }
+        let args: Vec<usize> = params
+            .split(';')
+            .map(|arg| arg.parse().unwrap_or(0))
+            .collect();
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0);
+        let count = arg(0).max(1);
+        let last_row = self.height - 1;
+        let last_col = self.width - 1;
+
+        match final_byte {
+            b'A' => self.row = self.row.saturating_sub(count),
+            b'B' | b'e' => self.row = (self.row + count).min(last_row),
+            b'C' | b'a' => self.col = (self.col + count).min(last_col),
+            b'D' => self.col = self.col.min(last_col).saturating_sub(count),
+            b'E' => (self.row, self.col) = ((self.row + count).min(last_row), 0),
+            b'F' => (self.row, self.col) = (self.row.saturating_sub(count), 0),
+            b'G' | b'`' => self.col = (count - 1).min(last_col),
+            b'd' => self.row = (count - 1).min(last_row),
+            b'H' | b'f' => {
+                self.row = (arg(0).max(1) - 1).min(last_row);
+                self.col = (arg(1).max(1) - 1).min(last_col);
+            }
+            b'J' => self.erase_display(arg(0)),
+            b'K' => self.erase_line(arg(0)),
+            b's' => self.saved_cursor = (self.row, self.col),
+            b'u' => (self.row, self.col) = self.saved_cursor,
+            _ => {}
+        }
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let blank = vec![' '; self.width];
+        match mode {
+            0 => {
+                self.erase_line(0);
+                self.grid[self.row + 1..].fill(blank);
+            }
+            1 => {
+                self.erase_line(1);
+                self.grid[..self.row].fill(blank);
+            }
+            _ => self.grid.fill(blank),
+        }
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let col = self.col.min(self.width);
+        let row = &mut self.grid[self.row];
+        match mode {
+            0 => row[col..].fill(' '),
+            1 => row[..(col + 1).min(self.width)].fill(' '),
+            _ => row.fill(' '),
+        }
+    }
+
+    fn switch_screen(&mut self, enter: bool) {
+        if enter && self.main_grid.is_none() {
+            let blank = vec![vec![' '; self.width]; self.height];
+            self.main_grid = Some(std::mem::replace(&mut self.grid, blank));
+            self.saved_cursor = (self.row, self.col);
+        } else if !enter && let Some(main) = self.main_grid.take() {
+            self.grid = main;
+            (self.row, self.col) = self.saved_cursor;
+        }
+    }
+}
+
+/// The live screens of each VM's SSH session, shared between the action tasks that feed them
+/// and the TUI that draws them.
+#[derive(Debug, Clone, Default)]
+pub struct SessionMirror {
+    screens: Arc<Mutex<BTreeMap<String, SessionScreen>>>,
+}
+
+impl SessionMirror {
+    /// A session on `vm` started with a `width` x `height` terminal.
+    pub fn start(&self, vm: &str, width: u16, height: u16) {
+        self.lock()
+            .insert(vm.to_string(), SessionScreen::new(width, height));
+    }
+
+    /// Output from the session on `vm`.
+    pub fn feed(&self, vm: &str, chunk: &[u8]) {
+        if let Some(screen) = self.lock().get_mut(vm) {
+            screen.feed(chunk);
+        }
+    }
+
+    /// The session on `vm` closed; its last screen stays visible.
+    pub fn end(&self, vm: &str) {
+        if let Some(screen) = self.lock().get_mut(vm) {
+            screen.active = false;
+        }
+    }
+
+    /// A copy of the screen of `vm`'s latest session.
+    #[must_use]
+    pub fn screen(&self, vm: &str) -> Option<SessionScreen> {
+        self.lock().get(vm).cloned()
+    }
+
+    /// VMs that have had a session, by name.
+    #[must_use]
+    pub fn vms(&self) -> Vec<String> {
+        self.lock().keys().cloned().collect()
+    }
+
+    /// The VM whose session printed most recently.
+    #[must_use]
+    pub fn latest_vm(&self) -> Option<String> {
+        self.lock()
+            .iter()
+            .max_by_key(|(_, screen)| (screen.active, screen.updated_at))
+            .map(|(vm, _)| vm.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, SessionScreen>> {
+        self.screens.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(chunks: &[&[u8]]) -> SessionScreen {
+        let mut screen = SessionScreen::new(20, 4);
+        for chunk in chunks {
+            screen.feed(chunk);
+        }
+        screen
+    }
+
+    #[test]
+    fn test_session_screen_shell_output() {
+        let screen = screen(&[
+            b"\x1b]0;user@web\x07\x1b[01;32m$\x1b[00m ls\r\n",
+            b"a.txt  b.txt\r\n$ echo hi\x08\x08\x1b[K",
+        ]);
+        assert_eq!(screen.lines(), ["$ ls", "a.txt  b.txt", "$ echo", ""]);
+        assert_eq!(screen.cursor(), (2, 7));
+    }
+
+    #[test]
+    fn test_session_screen_scrolls_and_wraps() {
+        let screen = screen(&[b"1\r\n2\r\n3\r\n4\r\n5\r\n", b"0123456789012345678901"]);
+        assert_eq!(screen.lines(), ["4", "5", "01234567890123456789", "01"]);
+        assert_eq!(screen.cursor(), (3, 2));
+    }
+
+    #[test]
+    fn test_session_screen_alternate_screen() {
+        let mut screen = screen(&[b"$ vim notes\r\n"]);
+        screen.feed(b"\x1b[?1049h\x1b[H\x1b[2Jhello\x1b[4;1H\"notes\" 1L");
+        assert_eq!(screen.lines(), ["hello", "", "", "\"notes\" 1L"]);
+        screen.feed(b"\x1b[?1049l");
+        assert_eq!(screen.lines(), ["$ vim notes", "", "", ""]);
+        assert_eq!(screen.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn test_session_mirror_tracks_latest_session() {
+        let mirror = SessionMirror::default();
+        assert_eq!(mirror.latest_vm(), None);
+        mirror.start("db", 80, 24);
+        mirror.start("web", 80, 24);
+        mirror.feed("db", b"psql\r\n");
+        assert_eq!(mirror.latest_vm().as_deref(), Some("db"));
+        mirror.end("db");
+        assert_eq!(mirror.latest_vm().as_deref(), Some("web"));
+        assert_eq!(mirror.vms(), ["db", "web"]);
+        assert_eq!(mirror.screen("db").unwrap().lines()[0], "psql");
+        assert!(!mirror.screen("db").unwrap().is_active());
+    }
+}
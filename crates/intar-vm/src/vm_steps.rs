@@ -1,10 +1,44 @@
 use crate::VmError;
-use intar_core::{CloudInitConfig, SystemctlAction, VmAction, VmStep, WriteFile};
+use intar_core::{
+    CloudInitConfig, RandomStepGroup, StepTrigger, SystemctlAction, VmAction, VmStep, WriteFile,
+};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fmt::Write as _;
 
+/// Pick the step a `random_one_of` group applies for a given run seed.
+///
+/// The choice depends only on the seed, VM name, and group name, so graders can reproduce a
+/// run's fault from the seed recorded in `state.json`.
+#[must_use]
+pub fn select_random_step<'a>(
+    seed: u64,
+    vm_name: &str,
+    group: &'a RandomStepGroup,
+) -> Option<&'a VmStep> {
+    if group.steps.is_empty() {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    hasher.update(vm_name.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(group.name.as_bytes());
+    let digest = hasher.finalize();
+
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    let len = u64::try_from(group.steps.len()).ok()?;
+    let idx = usize::try_from(u64::from_be_bytes(prefix) % len).ok()?;
+    group.steps.get(idx)
+}
+
 /// Compile VM `step` actions into cloud-init `write_files` + `runcmd` entries.
 ///
+/// Steps with a manual or delayed trigger are skipped; the runner sends them through the agent
+/// later (see [`render_triggered_step`]).
+///
 /// # Errors
 /// Returns `VmError::CloudInit` if the generated scripts cannot be rendered.
 pub fn apply_vm_steps_to_cloud_init(
@@ -19,9 +53,9 @@ pub fn apply_vm_steps_to_cloud_init(
     let mut runcmd = config.runcmd.clone().unwrap_or_default();
     let vm_slug = slugify(vm_name);
 
-    for step in steps {
+    for step in steps.iter().filter(|s| s.trigger == StepTrigger::Boot) {
         let step_slug = slugify(&step.name);
-        let hidden = is_hidden_step(step);
+        let hidden = step.is_hidden();
         let script_path = if hidden {
             format!("/run/intar-step-{vm_slug}-{step_slug}.sh")
         } else {
@@ -33,6 +67,7 @@ pub fn apply_vm_steps_to_cloud_init(
             path: script_path.clone(),
             content: script,
             permissions: Some("0755".into()),
+            binary: false,
         });
 
         if hidden {
@@ -49,6 +84,19 @@ pub fn apply_vm_steps_to_cloud_init(
     Ok(())
 }
 
+/// Render a standalone script for a step that runs after boot via the agent `exec` request.
+///
+/// # Errors
+/// Returns `VmError::CloudInit` if the script cannot be rendered.
+pub fn render_triggered_step(vm_name: &str, step: &VmStep) -> Result<String, VmError> {
+    render_step_script(
+        &slugify(vm_name),
+        &slugify(&step.name),
+        step,
+        step.is_hidden(),
+    )
+}
+
 fn render_step_script(
     vm_slug: &str,
     step_slug: &str,
@@ -119,11 +167,6 @@ fn render_step_footer(
     Ok(())
 }
 
-fn is_hidden_step(step: &VmStep) -> bool {
-    let name = step.name.to_lowercase();
-    name.starts_with("break") || name.contains("break-") || name.contains("break_")
-}
-
 fn render_action(
     script: &mut String,
     step_slug: &str,
@@ -155,6 +198,28 @@ fn render_action(
             render_command(script, cmd);
             Ok(())
         }
+        VmAction::UserCreate {
+            name,
+            shell,
+            groups,
+            system,
+        } => render_user_create(script, name, shell.as_deref(), groups, *system),
+        VmAction::UserDelete { name, remove_home } => {
+            render_user_delete(script, name, *remove_home)
+        }
+        VmAction::GroupAdd { name, users } => render_group_add(script, name, users),
+        VmAction::CronEntry {
+            name,
+            schedule,
+            command,
+            user,
+        } => render_cron_entry(script, name, schedule, command, user),
+        VmAction::Download {
+            url,
+            path,
+            sha256,
+            permissions,
+        } => render_download(script, idx, url, path, sha256, permissions.as_deref()),
         VmAction::K8sApply {
             manifest,
             kubeconfig,
@@ -311,6 +376,132 @@ fn render_command(script: &mut String, cmd: &str) {
     }
 }
 
+fn render_download(
+    script: &mut String,
+    idx: usize,
+    url: &str,
+    path: &str,
+    sha256: &str,
+    permissions: Option<&str>,
+) -> Result<(), VmError> {
+    let tmp = format!("intar_dl_{idx}");
+    let path_q = shell_quote(path);
+    writeln!(script, "install -d -m 0755 -- \"$(dirname -- {path_q})\"")
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    writeln!(script, "{tmp}=\"$(mktemp)\"")
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    writeln!(
+        script,
+        "curl -fsSL --retry 5 --retry-delay 2 --retry-connrefused -o \"${tmp}\" {}",
+        shell_quote(url)
+    )
+    .map_err(|_| VmError::CloudInit("format error".into()))?;
+    writeln!(
+        script,
+        "echo {}\"  ${tmp}\" | sha256sum -c --status -",
+        shell_quote(sha256)
+    )
+    .map_err(|_| VmError::CloudInit("format error".into()))?;
+    writeln!(script, "mv -f -- \"${tmp}\" {path_q}")
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    // mktemp creates the file 0600, which the move keeps.
+    let perm = permissions.unwrap_or("0644");
+    writeln!(script, "chmod {perm} -- {path_q}")
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    Ok(())
+}
+
+fn render_ensure_group(script: &mut String, group: &str) -> Result<(), VmError> {
+    let group = shell_quote(group);
+    writeln!(
+        script,
+        "getent group {group} >/dev/null 2>&1 || groupadd {group}"
+    )
+    .map_err(|_| VmError::CloudInit("format error".into()))?;
+    Ok(())
+}
+
+fn render_user_create(
+    script: &mut String,
+    name: &str,
+    shell: Option<&str>,
+    groups: &[String],
+    system: bool,
+) -> Result<(), VmError> {
+    let user = shell_quote(name);
+    let mut useradd = String::from("useradd -m");
+    if system {
+        useradd.push_str(" -r");
+    }
+    if let Some(shell) = shell {
+        write!(useradd, " -s {}", shell_quote(shell))
+            .map_err(|_| VmError::CloudInit("format error".into()))?;
+    }
+
+    writeln!(script, "id -u {user} >/dev/null 2>&1 || {useradd} {user}")
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+
+    if !groups.is_empty() {
+        for group in groups {
+            render_ensure_group(script, group)?;
+        }
+        writeln!(
+            script,
+            "usermod -aG {} {user}",
+            shell_quote(&groups.join(","))
+        )
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    }
+    Ok(())
+}
+
+fn render_user_delete(script: &mut String, name: &str, remove_home: bool) -> Result<(), VmError> {
+    let user = shell_quote(name);
+    let flags = if remove_home { " -r" } else { "" };
+    writeln!(
+        script,
+        "if id -u {user} >/dev/null 2>&1; then userdel{flags} {user}; fi"
+    )
+    .map_err(|_| VmError::CloudInit("format error".into()))?;
+    Ok(())
+}
+
+fn render_group_add(script: &mut String, name: &str, users: &[String]) -> Result<(), VmError> {
+    render_ensure_group(script, name)?;
+    for user in users {
+        writeln!(
+            script,
+            "usermod -aG {} {}",
+            shell_quote(name),
+            shell_quote(user)
+        )
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    }
+    Ok(())
+}
+
+fn render_cron_entry(
+    script: &mut String,
+    name: &str,
+    schedule: &str,
+    command: &str,
+    user: &str,
+) -> Result<(), VmError> {
+    // cron.d ignores files whose names contain dots, so slugify the entry name.
+    let path = format!("/etc/cron.d/intar-{}", slugify(name));
+    let line = format!("{} {user} {command}", schedule.trim());
+    writeln!(
+        script,
+        "printf '%s\\n' 'SHELL=/bin/sh' 'PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin' {} > {}",
+        shell_quote(&line),
+        shell_quote(&path)
+    )
+    .map_err(|_| VmError::CloudInit("format error".into()))?;
+    writeln!(script, "chmod 0644 -- {}", shell_quote(&path))
+        .map_err(|_| VmError::CloudInit("format error".into()))?;
+    Ok(())
+}
+
 fn k8s_ctx<'a>(step_slug: &'a str, idx: usize, kubeconfig: Option<&'a str>) -> K8sRenderCtx<'a> {
     K8sRenderCtx {
         step_slug,
@@ -505,10 +696,37 @@ fn shell_quote(s: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_select_random_step_is_reproducible() {
+        let group = RandomStepGroup {
+            name: "nginx-fault".into(),
+            steps: (0..4)
+                .map(|i| VmStep {
+                    trigger: StepTrigger::Boot,
+                    name: format!("break-{i}"),
+                    actions: vec![VmAction::Command {
+                        cmd: format!("echo {i}"),
+                    }],
+                })
+                .collect(),
+        };
+
+        let first = select_random_step(42, "web", &group).unwrap();
+        let again = select_random_step(42, "web", &group).unwrap();
+        assert_eq!(first.name, again.name);
+
+        let picks: std::collections::HashSet<&str> = (0..64)
+            .filter_map(|seed| select_random_step(seed, "web", &group))
+            .map(|step| step.name.as_str())
+            .collect();
+        assert!(picks.len() > 1);
+    }
+
     #[test]
     fn test_apply_vm_steps_to_cloud_init() {
         let steps = vec![VmStep {
             name: "break-nginx".into(),
+            trigger: StepTrigger::Boot,
             actions: vec![
                 VmAction::Systemctl {
                     unit: "nginx".into(),
@@ -550,4 +768,121 @@ mod tests {
         assert!(script.contains("export KUBECONFIG='/etc/rancher/k3s/k3s.yaml'"));
         assert!(script.contains("| kubectl apply -f -"));
     }
+
+    #[test]
+    fn test_triggered_steps_are_not_baked_into_cloud_init() {
+        let steps = vec![
+            VmStep {
+                name: "setup".into(),
+                trigger: StepTrigger::Boot,
+                actions: vec![VmAction::Command { cmd: "true".into() }],
+            },
+            VmStep {
+                name: "break-later".into(),
+                trigger: StepTrigger::After { secs: 60 },
+                actions: vec![VmAction::Command {
+                    cmd: "systemctl stop nginx".into(),
+                }],
+            },
+        ];
+
+        let mut config = CloudInitConfig::default();
+        apply_vm_steps_to_cloud_init("web", &steps, &mut config).unwrap();
+        assert_eq!(config.write_files.len(), 1);
+        assert!(!config.runcmd.unwrap().contains("break-later"));
+
+        let script = render_triggered_step("web", &steps[1]).unwrap();
+        assert!(script.contains("systemctl stop nginx"));
+        assert!(script.contains("exec >/dev/null 2>&1"));
+    }
+
+    #[test]
+    fn test_render_download_action() {
+        let steps = vec![VmStep {
+            name: "stage".into(),
+            trigger: StepTrigger::Boot,
+            actions: vec![VmAction::Download {
+                url: "https://example.com/tool".into(),
+                path: "/usr/local/bin/tool".into(),
+                sha256: "ab".repeat(32),
+                permissions: Some("0755".into()),
+            }],
+        }];
+
+        let mut config = CloudInitConfig::default();
+        apply_vm_steps_to_cloud_init("web", &steps, &mut config).unwrap();
+
+        let script = &config.write_files[0].content;
+        assert!(script.contains("curl -fsSL --retry 5"));
+        assert!(script.contains(&format!(
+            "echo '{}'\"  $intar_dl_0\" | sha256sum -c --status -",
+            "ab".repeat(32)
+        )));
+        assert!(script.contains("mv -f -- \"$intar_dl_0\" '/usr/local/bin/tool'"));
+        assert!(script.contains("chmod 0755 -- '/usr/local/bin/tool'"));
+
+        let steps = vec![VmStep {
+            name: "stage".into(),
+            trigger: StepTrigger::Boot,
+            actions: vec![VmAction::Download {
+                url: "https://example.com/data.csv".into(),
+                path: "/srv/data.csv".into(),
+                sha256: "ab".repeat(32),
+                permissions: None,
+            }],
+        }];
+        let mut config = CloudInitConfig::default();
+        apply_vm_steps_to_cloud_init("web", &steps, &mut config).unwrap();
+        assert!(
+            config.write_files[0]
+                .content
+                .contains("chmod 0644 -- '/srv/data.csv'")
+        );
+    }
+
+    #[test]
+    fn test_render_identity_and_cron_actions() {
+        let steps = vec![VmStep {
+            name: "setup-users".into(),
+            trigger: StepTrigger::Boot,
+            actions: vec![
+                VmAction::UserCreate {
+                    name: "deploy".into(),
+                    shell: Some("/bin/bash".into()),
+                    groups: vec!["www-data".into()],
+                    system: false,
+                },
+                VmAction::UserDelete {
+                    name: "olduser".into(),
+                    remove_home: true,
+                },
+                VmAction::GroupAdd {
+                    name: "ops".into(),
+                    users: vec!["deploy".into()],
+                },
+                VmAction::CronEntry {
+                    name: "cleanup".into(),
+                    schedule: "*/5 * * * *".into(),
+                    command: "rm -rf /tmp/cache".into(),
+                    user: "root".into(),
+                },
+            ],
+        }];
+
+        let mut config = CloudInitConfig::default();
+        apply_vm_steps_to_cloud_init("web", &steps, &mut config).unwrap();
+
+        let script = &config.write_files[0].content;
+        assert!(
+            script.contains("id -u 'deploy' >/dev/null 2>&1 || useradd -m -s '/bin/bash' 'deploy'")
+        );
+        assert!(script.contains("getent group 'www-data' >/dev/null 2>&1 || groupadd 'www-data'"));
+        assert!(script.contains("usermod -aG 'www-data' 'deploy'"));
+        assert!(script.contains("userdel -r 'olduser'"));
+        assert!(script.contains("usermod -aG 'ops' 'deploy'"));
+        assert!(
+            script.contains("'*/5 * * * * root rm -rf /tmp/cache' > '/etc/cron.d/intar-cleanup'")
+        );
+        assert!(script.contains("chmod 0644 -- '/etc/cron.d/intar-cleanup'"));
+    }
 }
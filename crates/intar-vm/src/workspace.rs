@@ -0,0 +1,228 @@
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+const DEFAULT_TMUX_TEMPLATE: &str = "\
+tmux new-session -d -s {{session}} -n {{run}} {{attach}}
+{{panes}}
+tmux select-layout -t {{session}} tiled
+tmux attach-session -t {{session}} || tmux switch-client -t {{session}}
+";
+
+const DEFAULT_ZELLIJ_TEMPLATE: &str = "\
+layout {
+    pane split_direction=\"vertical\" {
+        {{attach}}
+        pane split_direction=\"horizontal\" {
+{{panes}}
+        }
+    }
+}
+";
+
+/// Terminal multiplexer `intar workspace` lays a run out in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Multiplexer {
+    #[default]
+    Tmux,
+    Zellij,
+}
+
+impl FromStr for Multiplexer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tmux" => Ok(Self::Tmux),
+            "zellij" => Ok(Self::Zellij),
+            other => Err(format!(
+                "unknown multiplexer '{other}' (expected tmux or zellij)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Multiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Tmux => "tmux",
+            Self::Zellij => "zellij",
+        })
+    }
+}
+
+/// The `workspace` section of the user configuration.
+///
+/// `template` replaces the built-in layout: a shell script for tmux, a KDL layout for zellij.
+/// `{{run}}` and `{{session}}` expand to the run and multiplexer session names, `{{attach}}`
+/// to the pane running `intar attach`, and `{{panes}}` to one SSH pane per VM, each on its
+/// own line.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceSettings {
+    #[serde(default)]
+    pub multiplexer: Multiplexer,
+    pub template: Option<String>,
+}
+
+/// The panes of `intar workspace`: `intar attach` for the run, then `intar ssh` to each VM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceLayout {
+    pub run: String,
+    /// Path of the intar binary the panes run.
+    pub intar: String,
+    pub vms: Vec<String>,
+}
+
+impl WorkspaceLayout {
+    /// Name of the multiplexer session, e.g. `intar-calm-otter-1234`.
+    #[must_use]
+    pub fn session(&self) -> String {
+        format!("intar-{}", self.run)
+    }
+
+    /// The tmux script or zellij layout for this run, from `template` or the built-in one.
+    #[must_use]
+    pub fn render(&self, multiplexer: Multiplexer, template: Option<&str>) -> String {
+        let attach = [self.intar.as_str(), "attach", self.run.as_str()];
+        let (default, attach, panes) = match multiplexer {
+            Multiplexer::Tmux => {
+                let panes: Vec<String> = self
+                    .vms
+                    .iter()
+                    .map(|vm| {
+                        let ssh = shell_command(&[
+                            self.intar.as_str(),
+                            "ssh",
+                            vm.as_str(),
+                            "--run",
+                            self.run.as_str(),
+                        ]);
+                        format!(
+                            "tmux split-window -t {} {ssh}\ntmux select-pane -T {}",
+                            shell_quote(&self.session()),
+                            shell_quote(vm)
+                        )
+                    })
+                    .collect();
+                (
+                    DEFAULT_TMUX_TEMPLATE,
+                    shell_command(&attach),
+                    panes.join("\n"),
+                )
+            }
+            Multiplexer::Zellij => {
+                let panes: Vec<String> = self
+                    .vms
+                    .iter()
+                    .map(|vm| {
+                        format!(
+                            "            {}",
+                            kdl_pane(
+                                vm,
+                                &self.intar,
+                                &["ssh", vm.as_str(), "--run", self.run.as_str()]
+                            )
+                        )
+                    })
+                    .collect();
+                (
+                    DEFAULT_ZELLIJ_TEMPLATE,
+                    kdl_pane("intar", &self.intar, &["attach", self.run.as_str()]),
+                    panes.join("\n"),
+                )
+            }
+        };
+        let (run, session) = match multiplexer {
+            Multiplexer::Tmux => (shell_quote(&self.run), shell_quote(&self.session())),
+            Multiplexer::Zellij => (kdl_string(&self.run), kdl_string(&self.session())),
+        };
+        template
+            .unwrap_or(default)
+            .replace("{{run}}", &run)
+            .replace("{{session}}", &session)
+            .replace("{{attach}}", &attach)
+            .replace("{{panes}}", &panes)
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+fn shell_command(args: &[&str]) -> String {
+    args.iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn kdl_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', r"\\").replace('"', "\\\""))
+}
+
+fn kdl_pane(name: &str, command: &str, args: &[&str]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| kdl_string(arg)).collect();
+    format!(
+        "pane name={} command={} {{ args {}; }}",
+        kdl_string(name),
+        kdl_string(command),
+        args.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> WorkspaceLayout {
+        WorkspaceLayout {
+            run: "calm-otter-1234".into(),
+            intar: "/usr/bin/intar".into(),
+            vms: vec!["web".into(), "db".into()],
+        }
+    }
+
+    #[test]
+    fn test_tmux_workspace() {
+        let script = layout().render(Multiplexer::Tmux, None);
+        assert_eq!(
+            script.lines().next(),
+            Some(
+                "tmux new-session -d -s 'intar-calm-otter-1234' -n 'calm-otter-1234' \
+                 '/usr/bin/intar' 'attach' 'calm-otter-1234'"
+            )
+        );
+        assert!(script.contains(
+            "tmux split-window -t 'intar-calm-otter-1234' \
+             '/usr/bin/intar' 'ssh' 'db' '--run' 'calm-otter-1234'\ntmux select-pane -T 'db'"
+        ));
+        assert!(script.ends_with(
+            "tmux attach-session -t 'intar-calm-otter-1234' \
+             || tmux switch-client -t 'intar-calm-otter-1234'\n"
+        ));
+    }
+
+    #[test]
+    fn test_zellij_workspace() {
+        let kdl = layout().render(Multiplexer::Zellij, None);
+        assert!(kdl.contains(
+            r#"pane name="intar" command="/usr/bin/intar" { args "attach" "calm-otter-1234"; }"#
+        ));
+        assert!(kdl.contains(
+            r#"pane name="web" command="/usr/bin/intar" { args "ssh" "web" "--run" "calm-otter-1234"; }"#
+        ));
+    }
+
+    #[test]
+    fn test_workspace_template() {
+        let settings: WorkspaceSettings = serde_yaml::from_str(
+            "multiplexer: tmux\ntemplate: \"tmux new -s {{session}}\\n{{panes}}\\n\"\n",
+        )
+        .unwrap();
+        let script = layout().render(settings.multiplexer, settings.template.as_deref());
+        assert!(script.starts_with("tmux new -s 'intar-calm-otter-1234'\ntmux split-window"));
+        assert!("screen".parse::<Multiplexer>().is_err());
+    }
+}
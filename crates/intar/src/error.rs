@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+/// Why a scenario could not be loaded or run. Messages are for people; match on the variant
+/// only.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Invalid scenario: {0}")]
+    Scenario(String),
+
+    #[error("Run failed: {0}")]
+    Run(String),
+
+    #[error("The run has already ended")]
+    Ended,
+}
+
+impl Error {
+    pub(crate) fn run(e: impl std::fmt::Display) -> Self {
+        Self::Run(e.to_string())
+    }
+}
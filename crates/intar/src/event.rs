@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+/// Something that happened in a run, in the order it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A base image is being downloaded; `index` counts from zero.
+    Downloading {
+        image: String,
+        index: usize,
+        total: usize,
+    },
+    /// A VM's disk and configuration are being created.
+    CreatingVm { vm: String },
+    /// The VMs are booting.
+    Booting,
+    /// The agent in `vm` answered.
+    AgentReady { vm: String },
+    /// Boot probes checked so far.
+    BootProbes { passing: usize, total: usize },
+    /// The run is ready for the learner, with the ssh command for each VM.
+    Ready { ssh: BTreeMap<String, String> },
+    /// A probe passed or stopped passing.
+    ProbeChanged(ProbeStatus),
+    /// Every scenario probe passes.
+    Completed,
+    /// The VMs were reset to how they were when the run became ready.
+    Reset,
+    /// The run's TTL ran out; it is stopped next.
+    Expired,
+    /// The run could not be started or kept going; it is stopped next.
+    Failed(String),
+    /// The VMs are stopped and the run's files removed. Always the last event.
+    Stopped,
+}
+
+/// The latest result of one probe on one VM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProbeStatus {
+    pub vm: String,
+    pub probe: String,
+    pub passed: bool,
+    pub message: String,
+}
@@ -0,0 +1,28 @@
+//! Run intar scenarios from other Rust programs, such as desktop apps or exam platforms,
+//! without depending on the internal `intar-*` crates, whose types change between releases.
+//!
+//! ```no_run
+//! # async fn example(agent_x86_64: Vec<u8>, agent_aarch64: Vec<u8>) -> Result<(), intar::Error> {
+//! let scenario = intar::Scenario::from_file("labs/web/scenario.hcl".as_ref())?;
+//! let options = intar::Options::new(agent_x86_64, agent_aarch64).with_seed(42);
+//! let mut run = intar::run(scenario, options)?;
+//! while let Some(event) = run.next_event().await {
+//!     if let intar::Event::Completed = event {
+//!         break;
+//!     }
+//! }
+//! run.stop().await
+//! # }
+//! ```
+
+mod error;
+mod event;
+mod options;
+mod run;
+mod scenario;
+
+pub use error::*;
+pub use event::*;
+pub use options::*;
+pub use run::*;
+pub use scenario::*;
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+/// How [`crate::run`] runs a scenario.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub(crate) agent_x86_64: Vec<u8>,
+    pub(crate) agent_aarch64: Vec<u8>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) ttl: Option<Duration>,
+    pub(crate) probe_interval: Duration,
+    pub(crate) allow_host_hooks: bool,
+}
+
+impl Options {
+    /// Run with the `intar-agent` binaries installed into x86_64 and aarch64 guests, built
+    /// for the matching `*-unknown-linux-musl` targets.
+    #[must_use]
+    pub fn new(agent_x86_64: Vec<u8>, agent_aarch64: Vec<u8>) -> Self {
+        Self {
+            agent_x86_64,
+            agent_aarch64,
+            seed: None,
+            ttl: None,
+            probe_interval: Duration::from_secs(2),
+            allow_host_hooks: false,
+        }
+    }
+
+    /// Make the run's randomized choices reproducible; random by default.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Stop the run once it has been up for `ttl`, with [`crate::Event::Expired`].
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// How often probes are checked once the run is ready; every 2 seconds by default.
+    #[must_use]
+    pub fn with_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_interval = interval;
+        self
+    }
+
+    /// Let the scenario's `on_complete` host commands and webhooks and its external action
+    /// providers run on this host, as
+    /// `intar start --allow-host-hooks` does; a run of a scenario with such hooks fails to boot
+    /// otherwise, unless intar's config allows them.
+    #[must_use]
+    pub fn with_allow_host_hooks(mut self, allow: bool) -> Self {
+        self.allow_host_hooks = allow;
+        self
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{Error, Event, Options, ProbeStatus, Scenario};
+use intar_core::ImageSource;
+use intar_probes::ProbeResult;
+use intar_vm::{
+    ImageCache, IntarConfig, IntarDirs, ScenarioRunner, ScenarioState, VmError, check_disk_space,
+    detect_arch, required_run_space,
+};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+enum Command {
+    Reset(oneshot::Sender<Result<(), Error>>),
+    Stop,
+}
+
+/// A scenario started with [`run`]. Dropping it stops the run in the background.
+pub struct RunHandle {
+    run_id: String,
+    seed: u64,
+    events: mpsc::UnboundedReceiver<Event>,
+    commands: mpsc::UnboundedSender<Command>,
+    task: JoinHandle<()>,
+}
+
+/// Start `scenario` in a new run directory and boot it in the background, reporting how it
+/// goes as [`Event`]s on the returned handle. Must be called within a Tokio runtime.
+///
+/// # Errors
+/// Returns `Error::Run` if intar's directories or the run's files cannot be set up, or the
+/// scenario is over the configured quota.
+pub fn run(scenario: Scenario, options: Options) -> Result<RunHandle, Error> {
+    let dirs = IntarDirs::new().map_err(Error::run)?;
+    dirs.ensure_dirs().map_err(Error::run)?;
+    let runner = match options.seed {
+        Some(seed) => {
+            let run_dir = dirs.create_seeded_run_dir(None, seed).map_err(Error::run)?;
+            ScenarioRunner::new_in_run_dir(
+                scenario.inner,
+                options.agent_x86_64,
+                options.agent_aarch64,
+                &dirs,
+                run_dir.clone(),
+            )
+            .map(|mut runner| {
+                runner.set_seed(seed);
+                runner
+            })
+            .inspect_err(|_| {
+                // Only removed while still empty, i.e. when the run never got going.
+                let _ = std::fs::remove_dir(&run_dir);
+            })
+        }
+        None => ScenarioRunner::new_with_dirs(
+            scenario.inner,
+            options.agent_x86_64,
+            options.agent_aarch64,
+            &dirs,
+        ),
+    };
+    let mut runner = runner.map_err(Error::run)?;
+    if let Some(ttl) = options.ttl {
+        runner.set_ttl(ttl);
+    }
+    if options.allow_host_hooks {
+        runner.set_allow_host_hooks();
+    }
+
+    let (event_tx, events) = mpsc::unbounded_channel();
+    let (commands, command_rx) = mpsc::unbounded_channel();
+    let handle = RunHandle {
+        run_id: runner.run_id(),
+        seed: runner.seed(),
+        events,
+        commands,
+        task: tokio::spawn(drive(
+            runner,
+            dirs,
+            options.probe_interval,
+            event_tx,
+            command_rx,
+        )),
+    };
+    Ok(handle)
+}
+
+impl RunHandle {
+    /// Name of the run, as `intar` commands such as `intar ssh --run` take it.
+    #[must_use]
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Seed of the run's randomized choices; pass it to [`Options::with_seed`] to repeat them.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The next event, waiting for it; `None` once [`Event::Stopped`] was returned.
+    pub async fn next_event(&mut self) -> Option<Event> {
+        self.events.recv().await
+    }
+
+    /// Reset the VMs to how they were when the run became ready.
+    ///
+    /// # Errors
+    /// Returns `Error::Run` if the run is not ready yet or the reset fails, and `Error::Ended`
+    /// if the run has stopped.
+    pub async fn reset(&self) -> Result<(), Error> {
+        let (reply, result) = oneshot::channel();
+        self.commands
+            .send(Command::Reset(reply))
+            .map_err(|_| Error::Ended)?;
+        result.await.map_err(|_| Error::Ended)?
+    }
+
+    /// Stop the VMs and remove the run's files, returning once they are. Events not read yet
+    /// are dropped.
+    ///
+    /// # Errors
+    /// Returns `Error::Run` if the run's background task panicked.
+    pub async fn stop(self) -> Result<(), Error> {
+        let _ = self.commands.send(Command::Stop);
+        self.task.await.map_err(Error::run)
+    }
+}
+
+/// Boot the run, then check its probes until it is stopped, expires, or fails; always ends by
+/// stopping the VMs and removing the run.
+async fn drive(
+    mut runner: ScenarioRunner,
+    dirs: IntarDirs,
+    probe_interval: Duration,
+    events: mpsc::UnboundedSender<Event>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let booted = tokio::select! {
+        booted = boot(&mut runner, &dirs, &events) => Some(booted),
+        () = stop_requested(&mut commands) => None,
+    };
+    match booted {
+        Some(Ok(())) => watch(&mut runner, probe_interval, &events, &mut commands).await,
+        Some(Err(e)) => {
+            let _ = events.send(Event::Failed(e.to_string()));
+        }
+        None => {}
+    }
+
+    if let Err(e) = runner.stop().await {
+        warn!("Failed to stop run {}: {}", runner.run_id(), e);
+    }
+    if let Err(e) = runner.cleanup() {
+        warn!("Failed to remove run {}: {}", runner.run_id(), e);
+    }
+    let _ = events.send(Event::Stopped);
+}
+
+/// Wait for a stop, or for the handle to be dropped, refusing resets meanwhile.
+async fn stop_requested(commands: &mut mpsc::UnboundedReceiver<Command>) {
+    while let Some(command) = commands.recv().await {
+        match command {
+            Command::Reset(reply) => {
+                let _ = reply.send(Err(Error::Run("the run is not ready yet".into())));
+            }
+            Command::Stop => return,
+        }
+    }
+}
+
+async fn boot(
+    runner: &mut ScenarioRunner,
+    dirs: &IntarDirs,
+    events: &mpsc::UnboundedSender<Event>,
+) -> Result<(), VmError> {
+    let config = IntarConfig::load(dirs)?;
+    if config.host_execution.allow {
+        runner.set_allow_host_hooks();
+    }
+    runner.set_allowed_providers(config.host_execution.providers.clone());
+    runner.check_host_execution()?;
+    let image_cache = ImageCache::new(dirs.images_dir()).with_settings(&config.downloads);
+    let arch = detect_arch();
+    let scenario = runner.scenario.clone();
+    fetch_images(&scenario, dirs, &image_cache, &arch, events).await?;
+
+    for vm in &scenario.vms {
+        let _ = events.send(Event::CreatingVm {
+            vm: vm.name.clone(),
+        });
+        runner.create_vm(vm, &image_cache, &arch)?;
+    }
+    runner.set_boot_concurrency(config.boot_concurrency);
+    let _ = runner.calibrate_boot_timeouts();
+
+    let _ = events.send(Event::Booting);
+    runner.start_vms()?;
+    runner.start_action_recording()?;
+    runner.start_event_tracking().await;
+    runner
+        .wait_for_agents_with_progress(|vm| {
+            let _ = events.send(Event::AgentReady { vm: vm.to_string() });
+        })
+        .await?;
+    runner
+        .wait_for_boot_probes_with_progress(|passing, total| {
+            let _ = events.send(Event::BootProbes { passing, total });
+        })
+        .await?;
+    runner.run_external_actions(|_| {}).await?;
+    runner.save_checkpoint("init").await?;
+    runner.state = ScenarioState::Running;
+
+    let ssh = runner
+        .vm_order
+        .iter()
+        .filter_map(|vm| Some((vm.clone(), runner.get_ssh_command(vm)?)))
+        .collect();
+    let _ = events.send(Event::Ready { ssh });
+    Ok(())
+}
+
+/// Download the base images of `scenario` that are not cached yet, after checking there is
+/// room for them and the run.
+async fn fetch_images(
+    scenario: &intar_core::Scenario,
+    dirs: &IntarDirs,
+    image_cache: &ImageCache,
+    arch: &str,
+    events: &mpsc::UnboundedSender<Event>,
+) -> Result<(), VmError> {
+    let mut missing: Vec<(&str, &ImageSource)> = Vec::new();
+    for vm in &scenario.vms {
+        let source = scenario
+            .images
+            .get(&vm.image)
+            .and_then(|image| image.source_for_arch(arch))
+            .ok_or_else(|| {
+                VmError::Qemu(format!(
+                    "No image source for architecture '{arch}' in image '{}'",
+                    vm.image
+                ))
+            })?;
+        if !image_cache.is_cached(source) && !missing.iter().any(|(name, _)| *name == vm.image) {
+            missing.push((vm.image.as_str(), source));
+        }
+    }
+
+    let mut image_bytes = 0;
+    for (_, source) in &missing {
+        image_bytes += image_cache.download_size(source).await.unwrap_or(0);
+    }
+    check_disk_space(
+        &dirs.images_dir(),
+        image_bytes,
+        &dirs.runs_dir(),
+        required_run_space(scenario),
+    )?;
+
+    let total = missing.len();
+    for (index, (image, source)) in missing.into_iter().enumerate() {
+        let _ = events.send(Event::Downloading {
+            image: image.to_string(),
+            index,
+            total,
+        });
+        image_cache.ensure_image(source).await?;
+    }
+    Ok(())
+}
+
+/// Check probes every `probe_interval` and answer commands until the run should stop.
+async fn watch(
+    runner: &mut ScenarioRunner,
+    probe_interval: Duration,
+    events: &mpsc::UnboundedSender<Event>,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+) {
+    let ready = Instant::now();
+    let mut passing = HashMap::new();
+    let mut completed = false;
+    let mut checks = tokio::time::interval(probe_interval);
+    checks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(Command::Reset(reply)) => {
+                    let reset = runner.reset().await.map_err(Error::run);
+                    if reset.is_ok() {
+                        completed = false;
+                        let _ = events.send(Event::Reset);
+                    }
+                    let _ = reply.send(reset);
+                }
+                Some(Command::Stop) | None => return,
+            },
+            _ = checks.tick() => {
+                if runner.time_left() == Some(Duration::ZERO) {
+                    let _ = events.send(Event::Expired);
+                    return;
+                }
+                runner.fire_pending_triggers(ready.elapsed()).await;
+                if let Err(e) = runner.check_probes().await {
+                    let _ = events.send(Event::Failed(e.to_string()));
+                    return;
+                }
+                runner.run_completion_hooks().await;
+                for status in probe_changes(&runner.probe_results, &mut passing) {
+                    let _ = events.send(Event::ProbeChanged(status));
+                }
+                let done = runner.state == ScenarioState::Completed;
+                if done && !completed {
+                    let _ = events.send(Event::Completed);
+                }
+                completed = done;
+            }
+        }
+    }
+}
+
+/// The probes in `results` whose outcome differs from `passing`, sorted by VM and probe, and
+/// note their new outcome there. Probes seen for the first time count as changed.
+fn probe_changes(
+    results: &HashMap<String, HashMap<String, ProbeResult>>,
+    passing: &mut HashMap<(String, String), bool>,
+) -> Vec<ProbeStatus> {
+    let mut changes = Vec::new();
+    for (vm, vm_results) in results {
+        for (probe, result) in vm_results {
+            if passing.insert((vm.clone(), probe.clone()), result.passed) != Some(result.passed) {
+                changes.push(ProbeStatus {
+                    vm: vm.clone(),
+                    probe: probe.clone(),
+                    passed: result.passed,
+                    message: result.message.clone(),
+                });
+            }
+        }
+    }
+    changes.sort_by(|a, b| (&a.vm, &a.probe).cmp(&(&b.vm, &b.probe)));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_changes_report_transitions_only() {
+        let results = |passed: bool| {
+            HashMap::from([(
+                "web".to_string(),
+                HashMap::from([
+                    ("nginx".to_string(), ProbeResult::pass("nginx", "active")),
+                    (
+                        "port".to_string(),
+                        if passed {
+                            ProbeResult::pass("port", "open")
+                        } else {
+                            ProbeResult::fail("port", "closed")
+                        },
+                    ),
+                ]),
+            )])
+        };
+        let mut passing = HashMap::new();
+
+        let first = probe_changes(&results(false), &mut passing);
+        assert_eq!(
+            first
+                .iter()
+                .map(|status| (status.probe.as_str(), status.passed))
+                .collect::<Vec<_>>(),
+            [("nginx", true), ("port", false)]
+        );
+        assert!(probe_changes(&results(false), &mut passing).is_empty());
+
+        let fixed = probe_changes(&results(true), &mut passing);
+        assert_eq!(fixed.len(), 1);
+        assert_eq!(fixed[0].probe, "port");
+        assert_eq!(fixed[0].message, "open");
+    }
+}
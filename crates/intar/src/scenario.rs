@@ -0,0 +1,46 @@
+use crate::Error;
+use std::path::Path;
+
+/// A parsed and validated scenario.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub(crate) inner: intar_core::Scenario,
+}
+
+impl Scenario {
+    /// Load the scenario at `path`; files it references are resolved next to it.
+    ///
+    /// # Errors
+    /// Returns `Error::Scenario` if the file cannot be read or is not a valid scenario.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        intar_core::Scenario::from_file(path)
+            .map(|inner| Self { inner })
+            .map_err(|e| Error::Scenario(e.to_string()))
+    }
+
+    /// Parse a scenario from HCL source.
+    ///
+    /// # Errors
+    /// Returns `Error::Scenario` if `source` is not a valid scenario.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        intar_core::Scenario::parse(source)
+            .map(|inner| Self { inner })
+            .map_err(|e| Error::Scenario(e.to_string()))
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    #[must_use]
+    pub fn description(&self) -> &str {
+        &self.inner.description
+    }
+
+    /// Names of the scenario's VMs, in definition order.
+    #[must_use]
+    pub fn vm_names(&self) -> Vec<&str> {
+        self.inner.vms.iter().map(|vm| vm.name.as_str()).collect()
+    }
+}